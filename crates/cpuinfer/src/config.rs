@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use crate::detector::{ChannelOrder, Preprocessing, ResizeMode, parse_channel_values};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -22,6 +23,12 @@ pub struct InferConfig {
     pub maintain_aspect_ratio: u32,
     pub symmetric_padding: u32,
     pub gpu_id: u32,
+    /// Pixel scale factor; with `offsets`, input = factor * (pixel - offset)
+    pub net_scale_factor: Option<f32>,
+    /// Per-channel offsets subtracted from 0-255 pixels
+    pub offsets: Option<[f32; 3]>,
+    /// 0 = RGB, 1 = BGR
+    pub model_color_format: u32,
 
     // [class-attrs-all] section
     pub pre_cluster_threshold: f32,
@@ -42,9 +49,13 @@ impl Default for InferConfig {
             unique_id: 0,
             network_mode: 0, // FP32
             cluster_mode: 2, // NMS
-            maintain_aspect_ratio: 1,
+            // As in nvinfer: stretch unless the config asks to letterbox
+            maintain_aspect_ratio: 0,
             symmetric_padding: 1,
             gpu_id: 0,
+            net_scale_factor: None,
+            offsets: None,
+            model_color_format: 0,
             pre_cluster_threshold: 0.4,
             nms_iou_threshold: 0.5,
             topk: 300,
@@ -52,6 +63,34 @@ impl Default for InferConfig {
     }
 }
 
+impl InferConfig {
+    /// Input preprocessing the nvinfer keys describe, in terms of the
+    /// detector's mean and std on 0-1 pixels
+    pub fn preprocessing(&self) -> Preprocessing {
+        let mut preprocessing = Preprocessing {
+            resize: if self.maintain_aspect_ratio != 0 {
+                ResizeMode::Letterbox
+            } else {
+                ResizeMode::Stretch
+            },
+            symmetric_padding: self.symmetric_padding != 0,
+            channel_order: if self.model_color_format == 1 {
+                ChannelOrder::Bgr
+            } else {
+                ChannelOrder::Rgb
+            },
+            ..Default::default()
+        };
+        if let Some(offsets) = self.offsets {
+            preprocessing.mean = offsets.map(|offset| offset / 255.0);
+        }
+        if let Some(factor) = self.net_scale_factor.filter(|&factor| factor > 0.0) {
+            preprocessing.std = [1.0 / (factor * 255.0); 3];
+        }
+        preprocessing
+    }
+}
+
 /// Parse a nvinfer-style configuration file
 pub fn parse_config_file(path: &str) -> Result<InferConfig, String> {
     if !Path::new(path).exists() {
@@ -104,12 +143,17 @@ pub fn parse_config_string(contents: &str) -> Result<InferConfig, String> {
                         "network-mode" => config.network_mode = value.parse().unwrap_or(0),
                         "cluster-mode" => config.cluster_mode = value.parse().unwrap_or(2),
                         "maintain-aspect-ratio" => {
-                            config.maintain_aspect_ratio = value.parse().unwrap_or(1)
+                            config.maintain_aspect_ratio = value.parse().unwrap_or(0)
                         }
                         "symmetric-padding" => {
                             config.symmetric_padding = value.parse().unwrap_or(1)
                         }
                         "gpu-id" => config.gpu_id = value.parse().unwrap_or(0),
+                        "net-scale-factor" => config.net_scale_factor = value.parse().ok(),
+                        "offsets" => config.offsets = parse_channel_values(value).ok(),
+                        "model-color-format" => {
+                            config.model_color_format = value.parse().unwrap_or(0)
+                        }
                         _ => {} // Ignore unknown properties
                     }
                 }
@@ -159,6 +203,13 @@ pub fn validate_config(config: &InferConfig) -> Result<(), String> {
         ));
     }
 
+    if config.model_color_format > 1 {
+        return Err(format!(
+            "Invalid model-color-format: {} (must be 0 for RGB or 1 for BGR)",
+            config.model_color_format
+        ));
+    }
+
     // Check thresholds
     if config.pre_cluster_threshold < 0.0 || config.pre_cluster_threshold > 1.0 {
         return Err(format!(
@@ -206,6 +257,31 @@ topk=200
         assert_eq!(config.topk, 200);
     }
 
+    #[test]
+    fn test_preprocessing_from_config() {
+        let config = parse_config_string(
+            "[property]\nonnx-file=model.onnx\nnet-scale-factor=0.0039215697906911373\n\
+             offsets=123.675;116.28;103.53\nmodel-color-format=1\nmaintain-aspect-ratio=0\n",
+        )
+        .unwrap();
+        let preprocessing = config.preprocessing();
+        assert_eq!(preprocessing.resize, ResizeMode::Stretch);
+        assert_eq!(preprocessing.channel_order, ChannelOrder::Bgr);
+        assert!((preprocessing.mean[0] - 0.485).abs() < 1e-6);
+        assert!((preprocessing.std[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_config_without_aspect_ratio_stretches() {
+        let config = parse_config_string("[property]\nonnx-file=model.onnx\n").unwrap();
+        assert_eq!(config.preprocessing().resize, ResizeMode::Stretch);
+
+        let config =
+            parse_config_string("[property]\nonnx-file=model.onnx\nmaintain-aspect-ratio=1\n")
+                .unwrap();
+        assert_eq!(config.preprocessing().resize, ResizeMode::Letterbox);
+    }
+
     #[test]
     fn test_validate_config() {
         let mut config = InferConfig::default();
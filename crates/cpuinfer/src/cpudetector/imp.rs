@@ -1,4 +1,10 @@
-use crate::detector::{DetectorConfig, OnnxDetector};
+use super::sampling::{Chroma, PixelLayout, Roi, Sampling};
+use crate::detector::{
+    ChannelOrder, Detection, DetectorConfig, OnnxDetector, Preprocessing, ResizeMode,
+    parse_channel_values,
+};
+use crate::meta::{DetectionMeta, FrameDetections};
+use crate::pool::WorkerPool;
 use gstreamer::glib;
 use gstreamer::prelude::*;
 use gstreamer::subclass::prelude::*;
@@ -27,6 +33,10 @@ const DEFAULT_BATCH_SIZE: u32 = 2;
 const DEFAULT_UNIQUE_ID: u32 = 0;
 const DEFAULT_PROCESS_MODE: u32 = 1; // Primary mode
 const DEFAULT_OUTPUT_TENSOR_META: bool = false;
+const DEFAULT_EMIT_MESSAGES: bool = false;
+const DEFAULT_MAX_DETECTIONS: u32 = 100;
+const DEFAULT_INFERENCE_THREADS: u32 = 1;
+const DEFAULT_DOWNSCALE: bool = false;
 
 #[derive(Debug, Clone)]
 struct Settings {
@@ -41,6 +51,20 @@ struct Settings {
     unique_id: u32,           // nvinfer compatibility
     process_mode: u32,        // nvinfer compatibility (1=primary, 2=secondary)
     output_tensor_meta: bool, // nvinfer compatibility
+    emit_messages: bool,
+    preprocessing: Preprocessing,
+    max_detections: u32,
+    inference_threads: u32,
+    roi: Option<Roi>,
+    downscale: bool,
+}
+
+impl Settings {
+    /// Size frames may be downscaled to before inference
+    fn downscale_to(&self) -> Option<(u32, u32)> {
+        self.downscale
+            .then_some((self.input_width, self.input_height))
+    }
 }
 
 impl Default for Settings {
@@ -57,26 +81,60 @@ impl Default for Settings {
             unique_id: DEFAULT_UNIQUE_ID,
             process_mode: DEFAULT_PROCESS_MODE,
             output_tensor_meta: DEFAULT_OUTPUT_TENSOR_META,
+            emit_messages: DEFAULT_EMIT_MESSAGES,
+            preprocessing: Preprocessing::default(),
+            max_detections: DEFAULT_MAX_DETECTIONS,
+            inference_threads: DEFAULT_INFERENCE_THREADS,
+            roi: None,
+            downscale: DEFAULT_DOWNSCALE,
         }
     }
 }
 
+/// A frame on its way through the worker pool
+struct Job {
+    buffer: gstreamer::Buffer,
+    info: gst_video::VideoInfo,
+    frame_number: u64,
+    /// Skipped frames go through the pool too, to keep their place
+    detect: bool,
+    confidence_threshold: f32,
+    nms_threshold: f32,
+    max_detections: usize,
+    roi: Option<Roi>,
+    downscale_to: Option<(u32, u32)>,
+}
+
+struct Done {
+    buffer: gstreamer::Buffer,
+    frame_number: u64,
+    detections: Option<Vec<Detection>>,
+}
+
 #[derive(Default)]
 pub struct CpuDetector {
     settings: Mutex<Settings>,
     detector: Mutex<Option<OnnxDetector>>,
+    /// Workers when `inference-threads` is above 1
+    pool: Mutex<Option<WorkerPool<Job, Done>>>,
     frame_count: Mutex<u64>,
 }
 
 impl CpuDetector {
-    fn initialize_detector(&self, settings: &Settings) -> Result<OnnxDetector, String> {
+    fn initialize_detector(
+        &self,
+        settings: &Settings,
+        num_threads: usize,
+    ) -> Result<OnnxDetector, String> {
         let config = DetectorConfig {
             model_path: Some(settings.model_path.clone()),
             input_width: settings.input_width,
             input_height: settings.input_height,
             confidence_threshold: settings.confidence_threshold as f32,
             nms_threshold: settings.nms_threshold as f32,
-            num_threads: 4,
+            num_threads,
+            preprocessing: settings.preprocessing.clone(),
+            max_detections: settings.max_detections as usize,
             ..Default::default()
         };
 
@@ -84,92 +142,277 @@ impl CpuDetector {
             .map_err(|e| format!("Failed to create detector: {}", e))
     }
 
+    fn load_detector(&self, settings: &Settings, num_threads: usize) -> Option<OnnxDetector> {
+        match self.initialize_detector(settings, num_threads) {
+            Ok(detector) => {
+                gstreamer::info!(
+                    CAT,
+                    imp = self,
+                    "Loaded ONNX detector from: {}",
+                    settings.model_path
+                );
+                Some(detector)
+            }
+            #[cfg(test)]
+            Err(e) => {
+                gstreamer::warning!(
+                    CAT,
+                    imp = self,
+                    "Failed to load detector: {}, using mock",
+                    e
+                );
+                Some(OnnxDetector::new_mock())
+            }
+            #[cfg(not(test))]
+            Err(e) => {
+                gstreamer::warning!(CAT, imp = self, "Failed to load detector: {}", e);
+                None
+            }
+        }
+    }
+
     fn ensure_detector_loaded(&self) {
         let settings = self.settings.lock().unwrap().clone();
         let mut detector_guard = self.detector.lock().unwrap();
 
         if detector_guard.is_none() {
-            match self.initialize_detector(&settings) {
-                Ok(detector) => {
-                    gstreamer::info!(
-                        CAT,
-                        imp = self,
-                        "Loaded ONNX detector from: {}",
-                        settings.model_path
-                    );
-                    *detector_guard = Some(detector);
-                }
-                #[cfg(test)]
-                Err(e) => {
-                    gstreamer::warning!(
-                        CAT,
-                        imp = self,
-                        "Failed to load detector: {}, using mock",
-                        e
-                    );
-                    *detector_guard = Some(OnnxDetector::new_mock());
-                }
-                #[cfg(not(test))]
-                Err(e) => {
-                    gstreamer::warning!(CAT, imp = self, "Failed to load detector: {}", e);
-                }
-            }
+            *detector_guard = self.load_detector(&settings, 4);
         }
     }
 
-    fn frame_to_image(
+    /// Start one worker, with a detector of its own, per inference thread
+    fn start_pool(&self, settings: &Settings) {
+        let threads = settings.inference_threads as usize;
+        // Share the cores between the sessions rather than oversubscribe
+        let session_threads = std::thread::available_parallelism()
+            .map_or(4, |cores| cores.get())
+            .div_ceil(threads)
+            .max(1);
+        let pool = WorkerPool::new(threads, |_| {
+            let mut detector = self.load_detector(settings, session_threads);
+            move |job: Job| run_job(detector.as_mut(), job)
+        });
+        gstreamer::info!(
+            CAT,
+            imp = self,
+            "Running inference on {} threads with {} session threads each",
+            threads,
+            session_threads
+        );
+        *self.pool.lock().unwrap() = Some(pool);
+    }
+
+    fn video_info(&self) -> Result<gst_video::VideoInfo, gstreamer::FlowError> {
+        let sink_pad = self.obj().static_pad("sink").unwrap();
+        let caps = sink_pad
+            .current_caps()
+            .ok_or(gstreamer::FlowError::NotNegotiated)?;
+        gst_video::VideoInfo::from_caps(&caps).map_err(|_| gstreamer::FlowError::NotSupported)
+    }
+
+    /// Attach a frame's detections to its buffer and announce them
+    fn publish(
         &self,
-        frame: &gst_video::VideoFrameRef<&gstreamer::BufferRef>,
-    ) -> Option<DynamicImage> {
-        let width = frame.width();
-        let height = frame.height();
-        let format = frame.format();
-
-        match format {
-            gst_video::VideoFormat::Rgb => {
-                let data = frame.plane_data(0).ok()?;
-                let stride = frame.plane_stride()[0] as usize;
-
-                // Convert strided RGB to contiguous RGB
-                let mut rgb_data = Vec::with_capacity((width * height * 3) as usize);
-                for y in 0..height {
-                    let row_start = (y as usize) * stride;
-                    let row_end = row_start + (width as usize * 3);
-                    if row_end <= data.len() {
-                        rgb_data.extend_from_slice(&data[row_start..row_end]);
-                    }
-                }
+        buf: &mut gstreamer::BufferRef,
+        frame_number: u64,
+        settings: &Settings,
+        detections: Vec<Detection>,
+    ) {
+        let element = self.obj();
+        let detection_count = detections.len() as u32;
 
-                image::RgbImage::from_raw(width, height, rgb_data).map(DynamicImage::ImageRgb8)
-            }
-            gst_video::VideoFormat::Bgr => {
-                let data = frame.plane_data(0).ok()?;
-                let stride = frame.plane_stride()[0] as usize;
-
-                // Convert BGR to RGB
-                let mut rgb_data = Vec::with_capacity((width * height * 3) as usize);
-                for y in 0..height {
-                    let row_start = (y as usize) * stride;
-                    for x in 0..width {
-                        let pixel_start = row_start + (x as usize * 3);
-                        if pixel_start + 2 < data.len() {
-                            rgb_data.push(data[pixel_start + 2]); // R
-                            rgb_data.push(data[pixel_start + 1]); // G
-                            rgb_data.push(data[pixel_start]); // B
-                        }
-                    }
-                }
+        gstreamer::trace!(
+            CAT,
+            imp = self,
+            "Frame {}: Detected {} objects",
+            frame_number,
+            detection_count
+        );
+
+        // Log detections for debugging
+        for detection in &detections {
+            gstreamer::trace!(
+                CAT,
+                imp = self,
+                "Detection: {} at ({:.1}, {:.1}) {}x{} conf={:.2}",
+                detection.class_name,
+                detection.x,
+                detection.y,
+                detection.width,
+                detection.height,
+                detection.confidence
+            );
+        }
+
+        let result = FrameDetections {
+            frame_number,
+            unique_id: settings.unique_id,
+            pts: buf.pts(),
+            detections,
+        };
+        if settings.emit_messages {
+            let message = gstreamer::message::Element::builder(result.to_structure())
+                .src(&*element)
+                .build();
+            let _ = element.post_message(message);
+        }
+        DetectionMeta::add(buf, result);
+
+        // Emit signal with detection results
+        element.emit_by_name::<()>("inference-done", &[&frame_number, &detection_count]);
+    }
 
-                image::RgbImage::from_raw(width, height, rgb_data).map(DynamicImage::ImageRgb8)
+    /// Hand a finished frame back, with its detections attached
+    fn finish(
+        &self,
+        done: std::thread::Result<Done>,
+    ) -> Result<gstreamer::Buffer, gstreamer::FlowError> {
+        let Ok(done) = done else {
+            gstreamer::error!(CAT, imp = self, "Inference worker panicked");
+            return Err(gstreamer::FlowError::Error);
+        };
+        let mut buffer = done.buffer;
+        if let Some(detections) = done.detections {
+            let settings = self.settings.lock().unwrap().clone();
+            self.publish(buffer.make_mut(), done.frame_number, &settings, detections);
+        }
+        Ok(buffer)
+    }
+
+    /// Delay the pool adds: it holds a frame per worker before the oldest
+    /// one has to come out
+    fn pool_latency(&self) -> gstreamer::ClockTime {
+        let frames = match self.pool.lock().unwrap().as_ref() {
+            Some(pool) => pool.threads() as u64,
+            None => return gstreamer::ClockTime::ZERO,
+        };
+        let fps = match self.video_info() {
+            Ok(info) => info.fps(),
+            Err(_) => return gstreamer::ClockTime::ZERO,
+        };
+        if fps.numer() <= 0 || fps.denom() <= 0 {
+            return gstreamer::ClockTime::ZERO;
+        }
+        gstreamer::ClockTime::SECOND
+            .mul_div_ceil(frames * fps.denom() as u64, fps.numer() as u64)
+            .unwrap_or(gstreamer::ClockTime::ZERO)
+    }
+
+    /// Push every frame still in the pool, as at end of stream
+    fn drain_pool(&self) {
+        let src_pad = self.obj().static_pad("src").unwrap();
+        loop {
+            let done = match self.pool.lock().unwrap().as_mut() {
+                Some(pool) => pool.next(),
+                None => None,
+            };
+            let Some(done) = done else {
+                break;
+            };
+            if let Ok(buffer) = self.finish(done) {
+                let _ = src_pad.push(buffer);
             }
-            _ => {
-                gstreamer::warning!(CAT, imp = self, "Unsupported video format: {:?}", format);
-                None
+        }
+    }
+}
+
+fn run_job(detector: Option<&mut OnnxDetector>, job: Job) -> Done {
+    let detections = match detector {
+        Some(detector) if job.detect => {
+            // Thresholds may change while playing
+            detector.set_confidence_threshold(job.confidence_threshold);
+            detector.set_nms_threshold(job.nms_threshold);
+            detector.set_max_detections(job.max_detections);
+            detect_frame(detector, &job.buffer, &job.info, job.roi, job.downscale_to)
+        }
+        _ => None,
+    };
+    Done {
+        buffer: job.buffer,
+        frame_number: job.frame_number,
+        detections,
+    }
+}
+
+fn detect_frame(
+    detector: &OnnxDetector,
+    buffer: &gstreamer::BufferRef,
+    info: &gst_video::VideoInfo,
+    roi: Option<Roi>,
+    downscale_to: Option<(u32, u32)>,
+) -> Option<Vec<Detection>> {
+    let Some(sampling) = Sampling::new(info.width(), info.height(), roi, downscale_to) else {
+        gstreamer::warning!(
+            CAT,
+            "ROI lies outside the {}x{} frame",
+            info.width(),
+            info.height()
+        );
+        return None;
+    };
+    // Map buffer for reading (we don't modify the video data); the
+    // mapping is released before the meta is added
+    let image = {
+        let Ok(frame) = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, info) else {
+            gstreamer::warning!(CAT, "Failed to map frame for reading");
+            return None;
+        };
+        frame_to_image(&frame, &sampling)?
+    };
+    match detector.detect(&image) {
+        Ok(mut detections) => {
+            for detection in &mut detections {
+                sampling.to_frame(detection);
             }
+            Some(detections)
+        }
+        Err(e) => {
+            gstreamer::warning!(CAT, "Detection failed: {}", e);
+            None
         }
     }
 }
 
+fn frame_to_image(
+    frame: &gst_video::VideoFrameRef<&gstreamer::BufferRef>,
+    sampling: &Sampling,
+) -> Option<DynamicImage> {
+    let data = frame.plane_data(0).ok()?;
+    let strides = frame.plane_stride();
+    let stride = strides[0] as usize;
+    let image = match frame.format() {
+        gst_video::VideoFormat::I420 => sampling.read_yuv(
+            data,
+            stride,
+            Chroma::Planar {
+                u: frame.plane_data(1).ok()?,
+                v: frame.plane_data(2).ok()?,
+                stride: strides[1] as usize,
+            },
+        ),
+        gst_video::VideoFormat::Nv12 => sampling.read_yuv(
+            data,
+            stride,
+            Chroma::Interleaved {
+                uv: frame.plane_data(1).ok()?,
+                stride: strides[1] as usize,
+            },
+        ),
+        format => {
+            let Some(layout) = PixelLayout::for_format(format) else {
+                gstreamer::warning!(CAT, "Unsupported video format: {:?}", format);
+                return None;
+            };
+            sampling.read(data, stride, layout)
+        }
+    };
+    image.map(DynamicImage::ImageRgb8)
+}
+
+fn channel_values(values: &[f32; 3]) -> String {
+    format!("{};{};{}", values[0], values[1], values[2])
+}
+
 #[glib::object_subclass]
 impl ObjectSubclass for CpuDetector {
     const NAME: &'static str = "GstCpuDetector";
@@ -278,6 +521,70 @@ impl ObjectImpl for CpuDetector {
                     .default_value(DEFAULT_OUTPUT_TENSOR_META)
                     .mutable_playing()
                     .build(),
+                // Pre/post-processing, to fit detectors other than COCO YOLO
+                glib::ParamSpecBoolean::builder("letterbox")
+                    .nick("Letterbox")
+                    .blurb("Keep the aspect ratio and pad to the input size instead of stretching")
+                    .default_value(false)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("symmetric-padding")
+                    .nick("Symmetric Padding")
+                    .blurb("Center letterboxed frames instead of padding right and bottom only")
+                    .default_value(true)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("channel-order")
+                    .nick("Channel Order")
+                    .blurb("Channel order the model expects: rgb or bgr")
+                    .default_value(Some("rgb"))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("mean")
+                    .nick("Mean")
+                    .blurb("Mean subtracted from 0-1 pixels, one value or three in the model's channel order")
+                    .default_value(Some("0;0;0"))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("std")
+                    .nick("Std")
+                    .blurb("Standard deviation divided out, one value or three in the model's channel order")
+                    .default_value(Some("1;1;1"))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-detections")
+                    .nick("Max Detections")
+                    .blurb("Most detections kept per frame after NMS")
+                    .minimum(1)
+                    .maximum(10000)
+                    .default_value(DEFAULT_MAX_DETECTIONS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("inference-threads")
+                    .nick("Inference Threads")
+                    .blurb("Frames inferred concurrently, each thread with its own model session")
+                    .minimum(1)
+                    .maximum(64)
+                    .default_value(DEFAULT_INFERENCE_THREADS)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("roi")
+                    .nick("Region of Interest")
+                    .blurb("Run inference only on x,y,width,height of the frame; whole frame when empty")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("downscale")
+                    .nick("Downscale")
+                    .blurb("Skip pixels while reading large frames, down to the model input size")
+                    .default_value(DEFAULT_DOWNSCALE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("emit-messages")
+                    .nick("Emit Messages")
+                    .blurb("Post an element message with the detections of every processed frame")
+                    .default_value(DEFAULT_EMIT_MESSAGES)
+                    .mutable_playing()
+                    .build(),
             ]
         });
 
@@ -345,6 +652,8 @@ impl ObjectImpl for CpuDetector {
                             settings.process_mode = config.process_mode;
                             settings.confidence_threshold = config.pre_cluster_threshold as f64;
                             settings.nms_threshold = config.nms_iou_threshold as f64;
+                            settings.preprocessing = config.preprocessing();
+                            settings.max_detections = config.topk.max(1);
 
                             // Reset detector to reload with new settings
                             *self.detector.lock().unwrap() = None;
@@ -391,6 +700,73 @@ impl ObjectImpl for CpuDetector {
             "output-tensor-meta" => {
                 settings.output_tensor_meta = value.get().expect("type checked upstream");
             }
+            "emit-messages" => {
+                settings.emit_messages = value.get().expect("type checked upstream");
+            }
+            "letterbox" => {
+                let letterbox: bool = value.get().expect("type checked upstream");
+                settings.preprocessing.resize = if letterbox {
+                    ResizeMode::Letterbox
+                } else {
+                    ResizeMode::Stretch
+                };
+                *self.detector.lock().unwrap() = None;
+            }
+            "symmetric-padding" => {
+                settings.preprocessing.symmetric_padding =
+                    value.get().expect("type checked upstream");
+                *self.detector.lock().unwrap() = None;
+            }
+            "channel-order" => {
+                let order: Option<String> = value.get().expect("type checked upstream");
+                match order.as_deref().unwrap_or("rgb").parse::<ChannelOrder>() {
+                    Ok(order) => {
+                        settings.preprocessing.channel_order = order;
+                        *self.detector.lock().unwrap() = None;
+                    }
+                    Err(e) => gstreamer::error!(CAT, imp = self, "{}", e),
+                }
+            }
+            "mean" | "std" => {
+                let values: Option<String> = value.get().expect("type checked upstream");
+                let default = if pspec.name() == "mean" { "0" } else { "1" };
+                match parse_channel_values(values.as_deref().unwrap_or(default)) {
+                    Ok(values) if pspec.name() == "mean" => {
+                        settings.preprocessing.mean = values;
+                        *self.detector.lock().unwrap() = None;
+                    }
+                    Ok(values) if values.contains(&0.0) => {
+                        gstreamer::error!(CAT, imp = self, "std values must not be zero");
+                    }
+                    Ok(values) => {
+                        settings.preprocessing.std = values;
+                        *self.detector.lock().unwrap() = None;
+                    }
+                    Err(e) => gstreamer::error!(CAT, imp = self, "{}", e),
+                }
+            }
+            "roi" => {
+                let roi: Option<String> = value.get().expect("type checked upstream");
+                match roi.as_deref().map(str::trim).filter(|roi| !roi.is_empty()) {
+                    None => settings.roi = None,
+                    Some(roi) => match roi.parse::<Roi>() {
+                        Ok(roi) => settings.roi = Some(roi),
+                        Err(e) => gstreamer::error!(CAT, imp = self, "{}", e),
+                    },
+                }
+            }
+            "downscale" => {
+                settings.downscale = value.get().expect("type checked upstream");
+            }
+            "inference-threads" => {
+                settings.inference_threads = value.get().expect("type checked upstream");
+            }
+            "max-detections" => {
+                settings.max_detections = value.get().expect("type checked upstream");
+                if let Some(ref mut detector) = *self.detector.lock().unwrap() {
+                    detector.set_max_detections(settings.max_detections as usize);
+                }
+            }
             _ => {
                 gstreamer::warning!(
                     CAT,
@@ -417,6 +793,20 @@ impl ObjectImpl for CpuDetector {
             "unique-id" => settings.unique_id.to_value(),
             "process-mode" => settings.process_mode.to_value(),
             "output-tensor-meta" => settings.output_tensor_meta.to_value(),
+            "emit-messages" => settings.emit_messages.to_value(),
+            "letterbox" => (settings.preprocessing.resize == ResizeMode::Letterbox).to_value(),
+            "symmetric-padding" => settings.preprocessing.symmetric_padding.to_value(),
+            "channel-order" => match settings.preprocessing.channel_order {
+                ChannelOrder::Rgb => "rgb",
+                ChannelOrder::Bgr => "bgr",
+            }
+            .to_value(),
+            "mean" => channel_values(&settings.preprocessing.mean).to_value(),
+            "std" => channel_values(&settings.preprocessing.std).to_value(),
+            "max-detections" => settings.max_detections.to_value(),
+            "inference-threads" => settings.inference_threads.to_value(),
+            "roi" => settings.roi.map(|roi| roi.to_string()).to_value(),
+            "downscale" => settings.downscale.to_value(),
             _ => {
                 gstreamer::warning!(
                     CAT,
@@ -439,7 +829,7 @@ impl ElementImpl for CpuDetector {
             gstreamer::subclass::ElementMetadata::new(
                 "CPU Object Detector",
                 "Filter/Analyzer/Video",
-                "Detects objects using ONNX models on CPU with passthrough behavior",
+                "Detects objects using ONNX models on CPU and attaches them to buffers as meta",
                 "DeepStream Rust Team <dev@example.com>",
             )
         });
@@ -455,6 +845,8 @@ impl ElementImpl for CpuDetector {
                     gst_video::VideoFormat::Bgr,
                     gst_video::VideoFormat::Rgba,
                     gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::I420,
+                    gst_video::VideoFormat::Nv12,
                 ])
                 .build();
 
@@ -484,14 +876,122 @@ impl ElementImpl for CpuDetector {
 impl BaseTransformImpl for CpuDetector {
     const MODE: gstreamer_base::subclass::BaseTransformMode =
         gstreamer_base::subclass::BaseTransformMode::AlwaysInPlace;
-    const PASSTHROUGH_ON_SAME_CAPS: bool = true;
-    const TRANSFORM_IP_ON_PASSTHROUGH: bool = true;
+    // Not passthrough, since detections are attached to the buffers
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
 
     fn start(&self) -> Result<(), gstreamer::ErrorMessage> {
-        self.ensure_detector_loaded();
+        let settings = self.settings.lock().unwrap().clone();
+        if settings.inference_threads > 1 {
+            self.start_pool(&settings);
+        } else {
+            self.ensure_detector_loaded();
+        }
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gstreamer::ErrorMessage> {
+        // Dropping the pool waits for the workers to finish
+        self.pool.lock().unwrap().take();
         Ok(())
     }
 
+    fn query(&self, direction: gstreamer::PadDirection, query: &mut gstreamer::QueryRef) -> bool {
+        let handled = self.parent_query(direction, query);
+        if handled
+            && direction == gstreamer::PadDirection::Src
+            && let gstreamer::QueryViewMut::Latency(latency) = query.view_mut()
+        {
+            let (live, min, max) = latency.result();
+            let pool = self.pool_latency();
+            gstreamer::debug!(CAT, imp = self, "Adding {} of pool latency", pool);
+            latency.set(live, min + pool, max.map(|max| max + pool));
+        }
+        handled
+    }
+
+    fn sink_event(&self, event: gstreamer::Event) -> bool {
+        let new_caps = matches!(event.view(), gstreamer::EventView::Caps(_));
+        match event.view() {
+            gstreamer::EventView::Eos(_) => self.drain_pool(),
+            gstreamer::EventView::FlushStop(_) => {
+                // Frames from before the flush are dropped
+                if let Some(pool) = self.pool.lock().unwrap().as_mut() {
+                    while pool.next().is_some() {}
+                }
+            }
+            _ => {}
+        }
+        let handled = self.parent_sink_event(event);
+        if new_caps && self.pool.lock().unwrap().is_some() {
+            // The pool's latency follows the framerate
+            let element = self.obj();
+            let _ = element.post_message(
+                gstreamer::message::Latency::builder()
+                    .src(&*element)
+                    .build(),
+            );
+        }
+        handled
+    }
+
+    fn submit_input_buffer(
+        &self,
+        is_discont: bool,
+        inbuf: gstreamer::Buffer,
+    ) -> Result<gstreamer::FlowSuccess, gstreamer::FlowError> {
+        let mut guard = self.pool.lock().unwrap();
+        let Some(pool) = guard.as_mut() else {
+            drop(guard);
+            return self.parent_submit_input_buffer(is_discont, inbuf);
+        };
+
+        let frame_number = {
+            let mut frame_count = self.frame_count.lock().unwrap();
+            *frame_count += 1;
+            *frame_count
+        };
+        let settings = self.settings.lock().unwrap().clone();
+        pool.submit(Job {
+            buffer: inbuf,
+            info: self.video_info()?,
+            frame_number,
+            detect: frame_number % (settings.process_every_n_frames as u64) == 0,
+            confidence_threshold: settings.confidence_threshold as f32,
+            nms_threshold: settings.nms_threshold as f32,
+            max_detections: settings.max_detections as usize,
+            roi: settings.roi,
+            downscale_to: settings.downscale_to(),
+        });
+        Ok(gstreamer::FlowSuccess::Ok)
+    }
+
+    fn generate_output(
+        &self,
+    ) -> Result<gstreamer_base::subclass::base_transform::GenerateOutputSuccess, gstreamer::FlowError>
+    {
+        use gstreamer_base::subclass::base_transform::GenerateOutputSuccess;
+
+        let done = {
+            let mut guard = self.pool.lock().unwrap();
+            let Some(pool) = guard.as_mut() else {
+                drop(guard);
+                return self.parent_generate_output();
+            };
+            // Wait only once every worker has a frame; otherwise take
+            // whatever is done and let the next frame in
+            if pool.pending() > pool.threads() {
+                pool.next()
+            } else {
+                pool.try_next()
+            }
+        };
+        match done {
+            Some(done) => Ok(GenerateOutputSuccess::Buffer(self.finish(done)?)),
+            None => Ok(GenerateOutputSuccess::NoOutput),
+        }
+    }
+
     fn transform_caps(
         &self,
         direction: gstreamer::PadDirection,
@@ -518,77 +1018,36 @@ impl BaseTransformImpl for CpuDetector {
         }
     }
 
-    fn transform_ip_passthrough(
+    fn transform_ip(
         &self,
-        buf: &gstreamer::Buffer,
+        buf: &mut gstreamer::BufferRef,
     ) -> Result<gstreamer::FlowSuccess, gstreamer::FlowError> {
-        let mut frame_count = self.frame_count.lock().unwrap();
-        *frame_count += 1;
+        let frame_number = {
+            let mut frame_count = self.frame_count.lock().unwrap();
+            *frame_count += 1;
+            *frame_count
+        };
 
         let settings = self.settings.lock().unwrap().clone();
 
         // Skip processing if not on the right frame interval
-        if *frame_count % (settings.process_every_n_frames as u64) != 0 {
+        if frame_number % (settings.process_every_n_frames as u64) != 0 {
             return Ok(gstreamer::FlowSuccess::Ok);
         }
 
         // Get video info from sink pad caps
-        let element = self.obj();
-        let sink_pad = element.static_pad("sink").unwrap();
-        let caps = sink_pad
-            .current_caps()
-            .ok_or(gstreamer::FlowError::NotNegotiated)?;
-        let info = gst_video::VideoInfo::from_caps(&caps)
-            .map_err(|_| gstreamer::FlowError::NotSupported)?;
-
-        // Map buffer for reading (we don't modify the video data)
-        let frame = gst_video::VideoFrameRef::from_buffer_ref_readable(buf.as_ref(), &info)
-            .map_err(|_| gstreamer::FlowError::Error)?;
-
-        // Convert frame to image for detection
-        if let Some(image) = self.frame_to_image(&frame) {
-            if let Some(ref detector) = *self.detector.lock().unwrap() {
-                match detector.detect(&image) {
-                    Ok(detections) => {
-                        let detection_count = detections.len() as u32;
-
-                        gstreamer::trace!(
-                            CAT,
-                            imp = self,
-                            "Frame {}: Detected {} objects",
-                            *frame_count,
-                            detection_count
-                        );
-
-                        // Emit signal with detection results
-                        element.emit_by_name::<()>(
-                            "inference-done",
-                            &[&(*frame_count as u64), &detection_count],
-                        );
-
-                        // Log detections for debugging
-                        for detection in &detections {
-                            gstreamer::trace!(
-                                CAT,
-                                imp = self,
-                                "Detection: {} at ({:.1}, {:.1}) {}x{} conf={:.2}",
-                                detection.class_name,
-                                detection.x,
-                                detection.y,
-                                detection.width,
-                                detection.height,
-                                detection.confidence
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        gstreamer::warning!(CAT, imp = self, "Detection failed: {}", e);
-                    }
-                }
+        let info = self.video_info()?;
+
+        let detections = match *self.detector.lock().unwrap() {
+            Some(ref detector) => {
+                detect_frame(detector, buf, &info, settings.roi, settings.downscale_to())
             }
+            None => None,
+        };
+        if let Some(detections) = detections {
+            self.publish(buf, frame_number, &settings, detections);
         }
 
-        // Buffer passes through unchanged (identity behavior)
         Ok(gstreamer::FlowSuccess::Ok)
     }
 }
@@ -2,6 +2,7 @@ use gstreamer::glib;
 use gstreamer::prelude::*;
 
 mod imp;
+mod sampling;
 
 glib::wrapper! {
     pub struct CpuDetector(ObjectSubclass<imp::CpuDetector>) @extends gstreamer_base::BaseTransform, gstreamer::Element, gstreamer::Object;
@@ -0,0 +1,325 @@
+//! Picking the pixels of a frame the detector sees
+//!
+//! Converting a whole 4K frame to an image only for the detector to shrink
+//! it to 640x640 costs more than the inference. Frames are instead read
+//! once, restricted to the region of interest and, with downscaling on,
+//! taking every n-th pixel so the image is no larger than it must be.
+//! I420 and NV12 frames are read the same way and only the pixels read
+//! are converted to RGB, so decoders can feed the element without a
+//! `videoconvert` converting every pixel of the frame first. Detections
+//! are mapped back to frame coordinates afterwards.
+
+use crate::detector::Detection;
+use gstreamer_video as gst_video;
+use image::RgbImage;
+use std::fmt;
+use std::str::FromStr;
+
+/// Region of the frame inference runs on, in frame pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Roi {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Roi {
+    type Err = String;
+
+    /// `x,y,width,height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<u32> = s
+            .split([',', ';'])
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid ROI '{}': {}", s, e))?;
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!(
+                "Invalid ROI '{}': expected x,y,width,height with a non-empty size",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Roi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Where the red, green and blue bytes of a pixel are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelLayout {
+    pub bytes_per_pixel: usize,
+    pub red: usize,
+    pub green: usize,
+    pub blue: usize,
+}
+
+impl PixelLayout {
+    pub fn for_format(format: gst_video::VideoFormat) -> Option<Self> {
+        let (bytes_per_pixel, red, green, blue) = match format {
+            gst_video::VideoFormat::Rgb => (3, 0, 1, 2),
+            gst_video::VideoFormat::Bgr => (3, 2, 1, 0),
+            gst_video::VideoFormat::Rgba => (4, 0, 1, 2),
+            gst_video::VideoFormat::Bgra => (4, 2, 1, 0),
+            _ => return None,
+        };
+        Some(Self {
+            bytes_per_pixel,
+            red,
+            green,
+            blue,
+        })
+    }
+}
+
+/// Chroma planes of a 4:2:0 frame, at half the luma resolution
+#[derive(Debug, Clone, Copy)]
+pub enum Chroma<'a> {
+    /// Separate U and V planes, as in I420
+    Planar {
+        u: &'a [u8],
+        v: &'a [u8],
+        stride: usize,
+    },
+    /// One plane of interleaved U and V, as in NV12
+    Interleaved { uv: &'a [u8], stride: usize },
+}
+
+impl Chroma<'_> {
+    /// U and V of the luma pixel at `x`, `y`
+    fn at(&self, x: usize, y: usize) -> Option<(u8, u8)> {
+        let (x, y) = (x / 2, y / 2);
+        match *self {
+            Self::Planar { u, v, stride } => {
+                Some((*u.get(y * stride + x)?, *v.get(y * stride + x)?))
+            }
+            Self::Interleaved { uv, stride } => {
+                let at = y * stride + x * 2;
+                Some((*uv.get(at)?, *uv.get(at + 1)?))
+            }
+        }
+    }
+}
+
+/// BT.601 limited-range YUV to RGB
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = (y as i32 - 16) * 298;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 409 * e),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 516 * d),
+    ]
+}
+
+/// The part of a frame read into the detector's image, and how sparsely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Every `step`-th pixel in both directions is read
+    pub step: u32,
+}
+
+impl Sampling {
+    /// Sampling of a `frame_width`x`frame_height` frame, within `roi` if
+    /// given and, with `downscale_to`, skipping pixels while the image
+    /// stays at least that size. `None` if the ROI misses the frame.
+    pub fn new(
+        frame_width: u32,
+        frame_height: u32,
+        roi: Option<Roi>,
+        downscale_to: Option<(u32, u32)>,
+    ) -> Option<Self> {
+        let roi = roi.unwrap_or(Roi {
+            x: 0,
+            y: 0,
+            width: frame_width,
+            height: frame_height,
+        });
+        if roi.x >= frame_width || roi.y >= frame_height {
+            return None;
+        }
+        let width = roi.width.min(frame_width - roi.x);
+        let height = roi.height.min(frame_height - roi.y);
+        let step = match downscale_to {
+            Some((target_width, target_height)) => (width / target_width.max(1))
+                .min(height / target_height.max(1))
+                .max(1),
+            None => 1,
+        };
+        Some(Self {
+            left: roi.x,
+            top: roi.y,
+            width,
+            height,
+            step,
+        })
+    }
+
+    pub fn image_size(&self) -> (u32, u32) {
+        (
+            self.width.div_ceil(self.step),
+            self.height.div_ceil(self.step),
+        )
+    }
+
+    /// Read the sampled pixels of a plane with `stride` bytes per row
+    pub fn read(&self, data: &[u8], stride: usize, layout: PixelLayout) -> Option<RgbImage> {
+        let (width, height) = self.image_size();
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for row in 0..height {
+            let y = (self.top + row * self.step) as usize;
+            for column in 0..width {
+                let x = (self.left + column * self.step) as usize;
+                let pixel = y * stride + x * layout.bytes_per_pixel;
+                let bytes = data.get(pixel..pixel + layout.bytes_per_pixel)?;
+                rgb.extend_from_slice(&[
+                    bytes[layout.red],
+                    bytes[layout.green],
+                    bytes[layout.blue],
+                ]);
+            }
+        }
+        RgbImage::from_raw(width, height, rgb)
+    }
+
+    /// Read the sampled pixels of a 4:2:0 frame, converting only those
+    pub fn read_yuv(&self, luma: &[u8], luma_stride: usize, chroma: Chroma) -> Option<RgbImage> {
+        let (width, height) = self.image_size();
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for row in 0..height {
+            let y = (self.top + row * self.step) as usize;
+            for column in 0..width {
+                let x = (self.left + column * self.step) as usize;
+                let (u, v) = chroma.at(x, y)?;
+                rgb.extend_from_slice(&yuv_to_rgb(*luma.get(y * luma_stride + x)?, u, v));
+            }
+        }
+        RgbImage::from_raw(width, height, rgb)
+    }
+
+    /// Move a detection from image to frame coordinates
+    pub fn to_frame(&self, detection: &mut Detection) {
+        let step = self.step as f32;
+        detection.x = detection.x * step + self.left as f32;
+        detection.y = detection.y * step + self.top as f32;
+        detection.width *= step;
+        detection.height *= step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every other pixel of columns 1..4 of a 4x2 frame
+    fn sampling() -> Sampling {
+        Sampling {
+            left: 1,
+            top: 0,
+            width: 3,
+            height: 2,
+            step: 2,
+        }
+    }
+
+    #[test]
+    fn test_parse_roi() {
+        let roi: Roi = "100, 50, 400, 300".parse().unwrap();
+        assert_eq!(roi.to_string(), "100,50,400,300");
+        assert!("1,2,3".parse::<Roi>().is_err());
+        assert!("0,0,0,10".parse::<Roi>().is_err());
+    }
+
+    #[test]
+    fn test_downscale_step() {
+        // A 1080p frame for a 640x640 model is read in full, as every
+        // other pixel would leave too few rows; a 4K frame at every third
+        let full = Sampling::new(1920, 1080, None, Some((640, 640))).unwrap();
+        assert_eq!(full.step, 1);
+        let full = Sampling::new(3840, 2160, None, Some((640, 640))).unwrap();
+        assert_eq!(full.step, 3);
+        assert_eq!(full.image_size(), (1280, 720));
+    }
+
+    #[test]
+    fn test_roi_clipped_to_frame() {
+        let roi = Sampling::new(640, 480, Some("100,50,400,300".parse().unwrap()), None);
+        assert_eq!(roi.map(|roi| (roi.width, roi.height)), Some((400, 300)));
+        let clipped = Sampling::new(300, 200, Some("100,50,400,300".parse().unwrap()), None);
+        assert_eq!(clipped.unwrap().width, 200);
+        assert!(Sampling::new(64, 64, Some("100,0,10,10".parse().unwrap()), None).is_none());
+    }
+
+    #[test]
+    fn test_read_packed() {
+        // 4x2 BGRA frame, pixel value = x * 10 + y in the red byte
+        let layout = PixelLayout::for_format(gst_video::VideoFormat::Bgra).unwrap();
+        let stride = 4 * 4;
+        let mut data = vec![0u8; stride * 2];
+        for y in 0..2 {
+            for x in 0..4 {
+                data[y * stride + x * 4 + 2] = (x * 10 + y) as u8;
+            }
+        }
+        let image = sampling().read(&data, stride, layout).unwrap();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0)[0], 10);
+        assert_eq!(image.get_pixel(1, 0)[0], 30);
+    }
+
+    #[test]
+    fn test_read_yuv() {
+        // 4x2 NV12 frame: white on the left half, black on the right
+        let luma = [235, 235, 16, 16, 235, 235, 16, 16];
+        let uv = [128, 128, 128, 128];
+        let chroma = Chroma::Interleaved { uv: &uv, stride: 4 };
+        let image = sampling().read_yuv(&luma, 4, chroma).unwrap();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0]);
+
+        // The same frame in I420
+        let (u, v) = ([128, 128], [128, 128]);
+        let planar = Chroma::Planar {
+            u: &u,
+            v: &v,
+            stride: 2,
+        };
+        assert_eq!(sampling().read_yuv(&luma, 4, planar).unwrap(), image);
+        assert_eq!(yuv_to_rgb(81, 90, 240), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_detection_to_frame() {
+        let mut detection = Detection {
+            x: 10.0,
+            y: 5.0,
+            width: 20.0,
+            height: 10.0,
+            confidence: 0.9,
+            class_id: 0,
+            class_name: "person".to_string(),
+        };
+        sampling().to_frame(&mut detection);
+        assert_eq!(
+            (detection.x, detection.y, detection.width),
+            (21.0, 10.0, 40.0)
+        );
+    }
+}
@@ -77,6 +77,192 @@ pub enum YoloVersion {
     Auto, // Auto-detect based on output shape
 }
 
+/// ONNX Runtime execution provider used for inference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProvider {
+    #[default]
+    Cpu,
+    Cuda,
+    TensorRt,
+    DirectMl,
+    OpenVino,
+    CoreMl,
+}
+
+impl std::fmt::Display for ExecutionProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ExecutionProvider::Cpu => "cpu",
+            ExecutionProvider::Cuda => "cuda",
+            ExecutionProvider::TensorRt => "tensorrt",
+            ExecutionProvider::DirectMl => "directml",
+            ExecutionProvider::OpenVino => "openvino",
+            ExecutionProvider::CoreMl => "coreml",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for ExecutionProvider {
+    type Err = DetectorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Ok(ExecutionProvider::Cpu),
+            "cuda" => Ok(ExecutionProvider::Cuda),
+            "tensorrt" | "trt" => Ok(ExecutionProvider::TensorRt),
+            "directml" | "dml" => Ok(ExecutionProvider::DirectMl),
+            "openvino" => Ok(ExecutionProvider::OpenVino),
+            "coreml" => Ok(ExecutionProvider::CoreMl),
+            _ => Err(DetectorError::Configuration(format!(
+                "Unknown execution provider: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// How frames are fitted to the model input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Scale each axis to the input size, distorting the aspect ratio
+    #[default]
+    Stretch,
+    /// Scale to fit and pad the rest with gray, keeping the aspect ratio
+    Letterbox,
+}
+
+/// Channel order the model expects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelOrder {
+    #[default]
+    Rgb,
+    Bgr,
+}
+
+impl std::str::FromStr for ChannelOrder {
+    type Err = DetectorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rgb" => Ok(ChannelOrder::Rgb),
+            "bgr" => Ok(ChannelOrder::Bgr),
+            _ => Err(DetectorError::Configuration(format!(
+                "Unknown channel order: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// How frames become model input
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Preprocessing {
+    pub resize: ResizeMode,
+    /// Center a letterboxed frame instead of padding only right and bottom
+    pub symmetric_padding: bool,
+    pub channel_order: ChannelOrder,
+    /// Per-channel mean, in the model's channel order, subtracted from
+    /// pixels scaled to 0-1
+    pub mean: [f32; 3],
+    /// Per-channel standard deviation the result is divided by
+    pub std: [f32; 3],
+}
+
+impl Default for Preprocessing {
+    fn default() -> Self {
+        Self {
+            resize: ResizeMode::Stretch,
+            symmetric_padding: true,
+            channel_order: ChannelOrder::Rgb,
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+/// Parse three values separated by `;` or `,`, as nvinfer writes `offsets`
+pub fn parse_channel_values(value: &str) -> Result<[f32; 3]> {
+    let values: Vec<f32> = value
+        .split([';', ','])
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<f32>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| {
+            DetectorError::Configuration(format!("Invalid value in '{}': {}", value, e))
+        })?;
+    match values[..] {
+        [v] => Ok([v; 3]),
+        [r, g, b] => Ok([r, g, b]),
+        _ => Err(DetectorError::Configuration(format!(
+            "Expected 1 or 3 channel values, got '{}'",
+            value
+        ))),
+    }
+}
+
+/// Maps model input coordinates back onto the frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct InputTransform {
+    scale_x: f32,
+    scale_y: f32,
+    pad_x: f32,
+    pad_y: f32,
+}
+
+impl InputTransform {
+    fn new(
+        preprocessing: &Preprocessing,
+        img_width: u32,
+        img_height: u32,
+        input_width: u32,
+        input_height: u32,
+    ) -> Self {
+        let scale_x = input_width as f32 / img_width.max(1) as f32;
+        let scale_y = input_height as f32 / img_height.max(1) as f32;
+        match preprocessing.resize {
+            ResizeMode::Stretch => Self {
+                scale_x,
+                scale_y,
+                pad_x: 0.0,
+                pad_y: 0.0,
+            },
+            ResizeMode::Letterbox => {
+                let scale = scale_x.min(scale_y);
+                let (pad_x, pad_y) = if preprocessing.symmetric_padding {
+                    (
+                        ((input_width as f32 - img_width as f32 * scale) / 2.0).floor(),
+                        ((input_height as f32 - img_height as f32 * scale) / 2.0).floor(),
+                    )
+                } else {
+                    (0.0, 0.0)
+                };
+                Self {
+                    scale_x: scale,
+                    scale_y: scale,
+                    pad_x,
+                    pad_y,
+                }
+            }
+        }
+    }
+
+    /// Frame coordinates of a center-format box in model coordinates
+    fn to_frame(&self, cx: f32, cy: f32, w: f32, h: f32) -> (f32, f32, f32, f32) {
+        (
+            (cx - self.pad_x) / self.scale_x,
+            (cy - self.pad_y) / self.scale_y,
+            w / self.scale_x,
+            h / self.scale_y,
+        )
+    }
+}
+
 /// Configuration for the ONNX detector
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DetectorConfig {
@@ -96,6 +282,19 @@ pub struct DetectorConfig {
     pub yolo_version: YoloVersion,
     /// Custom class names (optional)
     pub class_names: Option<Vec<String>>,
+    /// Execution provider; unavailable providers fall back to CPU
+    #[serde(default)]
+    pub execution_provider: ExecutionProvider,
+    /// Resizing, channel order and normalization of the input
+    #[serde(default)]
+    pub preprocessing: Preprocessing,
+    /// Most detections kept per frame after NMS, highest confidence first
+    #[serde(default = "default_max_detections")]
+    pub max_detections: usize,
+}
+
+fn default_max_detections() -> usize {
+    100
 }
 
 impl Default for DetectorConfig {
@@ -109,6 +308,9 @@ impl Default for DetectorConfig {
             num_threads: 4,
             yolo_version: YoloVersion::Auto,
             class_names: None,
+            execution_provider: ExecutionProvider::Cpu,
+            preprocessing: Preprocessing::default(),
+            max_detections: default_max_detections(),
         }
     }
 }
@@ -125,6 +327,8 @@ pub struct OnnxDetector {
     nms_threshold: f32,
     class_names: Vec<String>,
     yolo_version: YoloVersion,
+    preprocessing: Preprocessing,
+    max_detections: usize,
 }
 
 impl OnnxDetector {
@@ -146,7 +350,11 @@ impl OnnxDetector {
                 if !Path::new(model_path).exists() {
                     (None, None)
                 } else {
-                    match Self::load_onnx_model(model_path, config.num_threads) {
+                    match Self::load_onnx_model(
+                        model_path,
+                        config.num_threads,
+                        config.execution_provider,
+                    ) {
                         Ok((env, sess)) => (Some(sess), Some(env)),
                         Err(_e) => (None, None),
                     }
@@ -166,6 +374,8 @@ impl OnnxDetector {
                 nms_threshold: config.nms_threshold,
                 class_names,
                 yolo_version: config.yolo_version,
+                preprocessing: config.preprocessing,
+                max_detections: config.max_detections,
             })
         }
 
@@ -182,6 +392,7 @@ impl OnnxDetector {
     fn load_onnx_model(
         model_path: &str,
         num_threads: usize,
+        execution_provider: ExecutionProvider,
     ) -> Result<(std::sync::Arc<ort::Environment>, ort::Session)> {
         use ort::{Environment, GraphOptimizationLevel, SessionBuilder};
         use std::sync::Arc;
@@ -212,6 +423,10 @@ impl OnnxDetector {
             .map_err(|e| {
                 DetectorError::Configuration(format!("Failed to set intra threads: {}", e))
             })?
+            .with_execution_providers(Self::ort_providers(execution_provider))
+            .map_err(|e| {
+                DetectorError::Configuration(format!("Failed to set execution providers: {}", e))
+            })?
             .with_model_from_file(model_path)
             .map_err(|e| {
                 DetectorError::Configuration(format!("Failed to load model from file: {}", e))
@@ -220,6 +435,39 @@ impl OnnxDetector {
         Ok((environment, session))
     }
 
+    /// Providers registered with ONNX Runtime, in priority order
+    #[cfg(feature = "ort")]
+    fn ort_providers(provider: ExecutionProvider) -> Vec<ort::ExecutionProvider> {
+        use ort::ExecutionProvider as Ep;
+
+        let mut providers = match provider {
+            ExecutionProvider::Cpu => vec![],
+            ExecutionProvider::Cuda => vec![Ep::CUDA(Default::default())],
+            ExecutionProvider::TensorRt => vec![
+                Ep::TensorRT(Default::default()),
+                Ep::CUDA(Default::default()),
+            ],
+            ExecutionProvider::DirectMl => vec![Ep::DirectML(Default::default())],
+            ExecutionProvider::OpenVino => vec![Ep::OpenVINO(Default::default())],
+            ExecutionProvider::CoreMl => vec![Ep::CoreML(Default::default())],
+        };
+        providers.push(Ep::CPU(Default::default()));
+        providers
+    }
+
+    /// Whether a real model was loaded; otherwise detection runs in mock mode
+    pub fn is_model_loaded(&self) -> bool {
+        #[cfg(feature = "ort")]
+        {
+            self.session.is_some()
+        }
+
+        #[cfg(not(feature = "ort"))]
+        {
+            false
+        }
+    }
+
     /// Perform detection on an image
     pub fn detect(&self, image: &DynamicImage) -> Result<Vec<Detection>> {
         #[cfg(feature = "ort")]
@@ -238,7 +486,7 @@ impl OnnxDetector {
             };
 
             // Preprocess image
-            let input_tensor = self.preprocess_image(image)?;
+            let (input_tensor, transform) = self.preprocess_image(image)?;
 
             // Create ndarray with correct shape for YOLO (batch, channels, height, width)
             let shape = vec![1, 3, self.input_height as usize, self.input_width as usize];
@@ -356,7 +604,7 @@ impl OnnxDetector {
             };
 
             // Postprocess outputs
-            return self.postprocess_outputs(&output, image.width(), image.height());
+            return self.postprocess_outputs(&output, image.width(), image.height(), transform);
         }
 
         #[cfg(not(feature = "ort"))]
@@ -368,30 +616,71 @@ impl OnnxDetector {
         }
     }
 
-    /// Preprocess image for model input
-    fn preprocess_image(&self, image: &DynamicImage) -> Result<Vec<f32>> {
+    /// Preprocess image for model input, returning the tensor and how
+    /// its coordinates map back onto the image
+    fn preprocess_image(&self, image: &DynamicImage) -> Result<(Vec<f32>, InputTransform)> {
+        let transform = InputTransform::new(
+            &self.preprocessing,
+            image.width(),
+            image.height(),
+            self.input_width,
+            self.input_height,
+        );
+
         // Resize image to model input size
-        let resized = image.resize_exact(self.input_width, self.input_height, FilterType::Triangle);
+        let rgb_image = match self.preprocessing.resize {
+            ResizeMode::Stretch => image
+                .resize_exact(self.input_width, self.input_height, FilterType::Triangle)
+                .to_rgb8(),
+            ResizeMode::Letterbox => {
+                let width = ((image.width() as f32 * transform.scale_x).round() as u32)
+                    .clamp(1, self.input_width);
+                let height = ((image.height() as f32 * transform.scale_y).round() as u32)
+                    .clamp(1, self.input_height);
+                let resized = image.resize_exact(width, height, FilterType::Triangle);
+                // Gray padding, as YOLO models are trained with
+                let mut canvas = image::RgbImage::from_pixel(
+                    self.input_width,
+                    self.input_height,
+                    image::Rgb([114, 114, 114]),
+                );
+                image::imageops::replace(
+                    &mut canvas,
+                    &resized.to_rgb8(),
+                    transform.pad_x as i64,
+                    transform.pad_y as i64,
+                );
+                canvas
+            }
+        };
 
-        // Convert to RGB if needed
-        let rgb_image = resized.to_rgb8();
+        let channels = match self.preprocessing.channel_order {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Bgr => [2, 1, 0],
+        };
+        let Preprocessing { mean, std, .. } = self.preprocessing;
 
         // Create tensor in CHW format (Channels, Height, Width) for YOLO
         let mut tensor = Vec::with_capacity((3 * self.input_width * self.input_height) as usize);
 
-        // Normalize and arrange in CHW format
-        // YOLO typically expects values normalized to [0, 1]
-        for channel in 0..3 {
+        // Normalize and arrange in CHW format; with the default mean and
+        // std values stay in the [0, 1] YOLO expects
+        for (channel, &source) in channels.iter().enumerate() {
+            let divisor = if std[channel] == 0.0 {
+                1.0
+            } else {
+                std[channel]
+            };
             for y in 0..self.input_height {
                 for x in 0..self.input_width {
                     let pixel = rgb_image.get_pixel(x, y);
-                    let value = pixel[channel as usize] as f32 / 255.0;
-                    tensor.push(value);
+                    let value = pixel[source] as f32 / 255.0;
+                    tensor.push((value - mean[channel]) / divisor);
                 }
             }
         }
 
-        Ok(tensor)
+        Ok((tensor, transform))
     }
 
     /// Process model outputs to detections
@@ -400,6 +689,7 @@ impl OnnxDetector {
         outputs: &[f32],
         img_width: u32,
         img_height: u32,
+        transform: InputTransform,
     ) -> Result<Vec<Detection>> {
         // Auto-detect YOLO version based on output shape
         let version = match self.yolo_version {
@@ -415,23 +705,23 @@ impl OnnxDetector {
             | YoloVersion::V4
             | YoloVersion::V5
             | YoloVersion::V6
-            | YoloVersion::V7 => self.postprocess_yolov5(outputs, img_width, img_height),
+            | YoloVersion::V7 => self.postprocess_yolov5(outputs, img_width, img_height, transform),
             // Modern format without objectness (v8-v12)
             YoloVersion::V8
             | YoloVersion::V9
             | YoloVersion::V11
             | YoloVersion::V12
-            | YoloVersion::RD => self.postprocess_yolov8(outputs, img_width, img_height),
+            | YoloVersion::RD => self.postprocess_yolov8(outputs, img_width, img_height, transform),
             // Special handling for v10 (NMS-free)
             YoloVersion::V10 => {
                 // V10 uses one-to-one predictions, may need special handling
                 // For now, treat similar to v8 but log the difference
                 // info!("Processing YOLOv10 with NMS-free design");
-                self.postprocess_yolov8(outputs, img_width, img_height)
+                self.postprocess_yolov8(outputs, img_width, img_height, transform)
             }
             YoloVersion::Auto => {
                 // Fallback to V5 if auto-detection somehow fails
-                self.postprocess_yolov5(outputs, img_width, img_height)
+                self.postprocess_yolov5(outputs, img_width, img_height, transform)
             }
         }
     }
@@ -495,6 +785,7 @@ impl OnnxDetector {
         outputs: &[f32],
         img_width: u32,
         img_height: u32,
+        transform: InputTransform,
     ) -> Result<Vec<Detection>> {
         let mut detections = Vec::new();
 
@@ -540,10 +831,6 @@ impl OnnxDetector {
             self.confidence_threshold
         );
 
-        // Scale factors to convert from model coordinates to image coordinates
-        let x_scale = img_width as f32 / self.input_width as f32;
-        let y_scale = img_height as f32 / self.input_height as f32;
-
         // Check if format is transposed [1, 85, 25200] instead of [1, 25200, 85]
         // In transposed format, all x coords are together, then all y coords, etc.
         let is_transposed = {
@@ -710,10 +997,7 @@ impl OnnxDetector {
 
             if confidence >= self.confidence_threshold {
                 // Scale coordinates to image size
-                let scaled_cx = cx * x_scale;
-                let scaled_cy = cy * y_scale;
-                let scaled_w = w * x_scale;
-                let scaled_h = h * y_scale;
+                let (scaled_cx, scaled_cy, scaled_w, scaled_h) = transform.to_frame(cx, cy, w, h);
 
                 // Convert from center format to top-left format
                 let x = (scaled_cx - scaled_w / 2.0).max(0.0);
@@ -758,15 +1042,7 @@ impl OnnxDetector {
         }
 
         // Apply Non-Maximum Suppression
-        let mut filtered_detections = self.apply_nms(detections);
-
-        // Limit maximum detections per frame to prevent excessive false positives
-        const MAX_DETECTIONS_PER_FRAME: usize = 100;
-        if filtered_detections.len() > MAX_DETECTIONS_PER_FRAME {
-            // Sort by confidence and keep top detections
-            filtered_detections.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
-            filtered_detections.truncate(MAX_DETECTIONS_PER_FRAME);
-        }
+        let filtered_detections = self.apply_nms(detections);
 
         debug!(
             "YOLOv5: Postprocessed {} anchors, {} detections after NMS",
@@ -784,6 +1060,7 @@ impl OnnxDetector {
         outputs: &[f32],
         img_width: u32,
         img_height: u32,
+        transform: InputTransform,
     ) -> Result<Vec<Detection>> {
         let mut detections = Vec::new();
 
@@ -794,10 +1071,6 @@ impl OnnxDetector {
         let num_values = 84; // 4 bbox + 80 classes
         let num_anchors = outputs.len() / num_values;
 
-        // Scale factors
-        let x_scale = img_width as f32 / self.input_width as f32;
-        let y_scale = img_height as f32 / self.input_height as f32;
-
         // Process transposed format
         for anchor_idx in 0..num_anchors {
             // In v8/v9, data is arranged as [84, 8400]
@@ -826,10 +1099,11 @@ impl OnnxDetector {
 
             if confidence >= self.confidence_threshold {
                 // Convert and scale
-                let x = ((cx - w / 2.0) * x_scale).max(0.0);
-                let y = ((cy - h / 2.0) * y_scale).max(0.0);
-                let width = (w * x_scale).min(img_width as f32 - x);
-                let height = (h * y_scale).min(img_height as f32 - y);
+                let (cx, cy, w, h) = transform.to_frame(cx, cy, w, h);
+                let x = (cx - w / 2.0).max(0.0);
+                let y = (cy - h / 2.0).max(0.0);
+                let width = w.min(img_width as f32 - x);
+                let height = h.min(img_height as f32 - y);
 
                 let class_name = self
                     .class_names
@@ -861,13 +1135,13 @@ impl OnnxDetector {
         Ok(filtered_detections)
     }
 
-    /// Apply Non-Maximum Suppression
+    /// Apply Non-Maximum Suppression, keeping at most `max_detections`
     fn apply_nms(&self, mut detections: Vec<Detection>) -> Vec<Detection> {
         detections.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
 
         let mut keep = Vec::new();
 
-        while !detections.is_empty() {
+        while !detections.is_empty() && keep.len() < self.max_detections {
             let current = detections.remove(0);
             keep.push(current.clone());
 
@@ -1027,6 +1301,10 @@ impl OnnxDetector {
         self.nms_threshold = threshold;
     }
 
+    pub fn set_max_detections(&mut self, max_detections: usize) {
+        self.max_detections = max_detections;
+    }
+
     /// Set the YOLO version for output processing
     pub fn set_yolo_version(&mut self, version: YoloVersion) {
         self.yolo_version = version;
@@ -1047,6 +1325,8 @@ impl OnnxDetector {
             nms_threshold: 0.4,
             class_names: Self::default_class_names(),
             yolo_version: YoloVersion::Auto,
+            preprocessing: Preprocessing::default(),
+            max_detections: default_max_detections(),
         }
     }
 }
@@ -1100,6 +1380,8 @@ mod tests {
             num_threads: 2,
             yolo_version: YoloVersion::V8,
             class_names: Some(vec!["test_class".to_string()]),
+            execution_provider: ExecutionProvider::Cpu,
+            ..Default::default()
         };
 
         let detector = OnnxDetector::new_with_config(config).unwrap();
@@ -1168,6 +1450,46 @@ mod tests {
         assert!(iou > 0.0 && iou < 1.0);
     }
 
+    #[test]
+    fn test_letterbox_preprocessing() {
+        let mut detector = OnnxDetector::new_mock();
+        detector.input_width = 64;
+        detector.input_height = 64;
+        detector.preprocessing = Preprocessing {
+            resize: ResizeMode::Letterbox,
+            channel_order: ChannelOrder::Bgr,
+            mean: [0.5; 3],
+            std: [0.5; 3],
+            ..Default::default()
+        };
+
+        // A wide red frame fills the middle rows, with gray above and below
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            128,
+            64,
+            image::Rgb([255, 0, 0]),
+        ));
+        let (tensor, transform) = detector.preprocess_image(&image).unwrap();
+        assert_eq!(tensor.len(), 3 * 64 * 64);
+        assert_eq!(transform.scale_x, 0.5);
+        assert_eq!(transform.pad_y, 16.0);
+        let plane = 64 * 64;
+        let gray = (114.0 / 255.0 - 0.5) / 0.5;
+        assert!((tensor[0] - gray).abs() < 1e-6);
+        // Blue first, then red, each normalized to [-1, 1]
+        let middle = 32 * 64 + 32;
+        assert!((tensor[middle] + 1.0).abs() < 1e-3);
+        assert!((tensor[2 * plane + middle] - 1.0).abs() < 1e-3);
+
+        // Boxes map back through the padding
+        let (cx, cy, w, h) = transform.to_frame(32.0, 32.0, 10.0, 10.0);
+        assert_eq!((cx, cy, w, h), (64.0, 32.0, 20.0, 20.0));
+
+        assert_eq!(parse_channel_values("0.485;0.456;0.406").unwrap()[2], 0.406);
+        assert_eq!(parse_channel_values("0.5").unwrap(), [0.5; 3]);
+        assert!(parse_channel_values("1;2").is_err());
+    }
+
     #[test]
     #[cfg(feature = "half")]
     fn test_f16_ndarray_creation() {
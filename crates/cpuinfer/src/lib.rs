@@ -4,6 +4,8 @@ use gstreamer::glib;
 pub mod config;
 mod cpudetector;
 pub mod detector;
+pub mod meta;
+mod pool;
 
 #[cfg(feature = "ort")]
 pub use ort;
@@ -38,10 +40,7 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
             }
             Err(_) => {
                 let workspace_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
-                std::env::set_var(
-                    "GST_PLUGIN_PATH",
-                    format!("{workspace_dir}/target/release"),
-                );
+                std::env::set_var("GST_PLUGIN_PATH", format!("{workspace_dir}/target/release"));
             }
         };
     };
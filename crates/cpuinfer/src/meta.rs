@@ -0,0 +1,353 @@
+//! Detection results for downstream elements
+//!
+//! The detector attaches a [`DetectionMeta`] to every buffer it runs
+//! inference on, carrying the frame's detections in pixel coordinates.
+//! The plugin and the applications reading the meta are built separately,
+//! so the meta only holds a `GstStructure`, allocated and freed by
+//! GStreamer; it is copied along with the buffer and dropped when the
+//! video is scaled or converted, as the boxes would no longer fit.
+//!
+//! With `emit-messages` set the detector also posts an element message
+//! named [`MESSAGE_NAME`] per frame, for applications that watch the bus
+//! rather than buffers. The message and the meta share one structure,
+//! holding `frame`, `unique-id`, `pts` and a `detections` list of
+//! `detection` structures with `class-id`, `class-name`, `confidence`,
+//! `x`, `y`, `width` and `height`.
+
+use crate::detector::Detection;
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::glib::translate::IntoGlibPtr;
+use gstreamer::prelude::*;
+use std::fmt;
+
+/// Name of the element messages carrying a frame's detections
+pub const MESSAGE_NAME: &str = "cpuinfer-detections";
+
+/// Detections of one frame, from a [`DetectionMeta`] or a message
+#[derive(Debug, Clone)]
+pub struct FrameDetections {
+    pub frame_number: u64,
+    /// `unique-id` of the detector that produced them
+    pub unique_id: u32,
+    pub pts: Option<gst::ClockTime>,
+    pub detections: Vec<Detection>,
+}
+
+impl FrameDetections {
+    /// Structure for an element message, see the module docs
+    pub fn to_structure(&self) -> gst::Structure {
+        let detections = self.detections.iter().map(|d| {
+            gst::Structure::builder("detection")
+                .field("class-id", d.class_id as u32)
+                .field("class-name", d.class_name.as_str())
+                .field("confidence", d.confidence)
+                .field("x", d.x)
+                .field("y", d.y)
+                .field("width", d.width)
+                .field("height", d.height)
+                .build()
+        });
+        gst::Structure::builder(MESSAGE_NAME)
+            .field("frame", self.frame_number)
+            .field("unique-id", self.unique_id)
+            .field("pts", self.pts.map_or(u64::MAX, |pts| pts.nseconds()))
+            .field("detections", gst::List::new(detections))
+            .build()
+    }
+
+    /// Read a structure made by [`to_structure`](Self::to_structure)
+    pub fn from_structure(structure: &gst::StructureRef) -> Option<Self> {
+        if structure.name() != MESSAGE_NAME {
+            return None;
+        }
+        let detections = structure
+            .get::<gst::List>("detections")
+            .ok()?
+            .iter()
+            .filter_map(|value| {
+                let d = value.get::<gst::Structure>().ok()?;
+                Some(Detection {
+                    x: d.get("x").ok()?,
+                    y: d.get("y").ok()?,
+                    width: d.get("width").ok()?,
+                    height: d.get("height").ok()?,
+                    confidence: d.get("confidence").ok()?,
+                    class_id: d.get::<u32>("class-id").ok()? as usize,
+                    class_name: d.get("class-name").ok()?,
+                })
+            })
+            .collect();
+        Some(Self {
+            frame_number: structure.get("frame").ok()?,
+            unique_id: structure.get("unique-id").ok()?,
+            pts: structure
+                .get::<u64>("pts")
+                .ok()
+                .filter(|&pts| pts != u64::MAX)
+                .map(gst::ClockTime::from_nseconds),
+            detections,
+        })
+    }
+
+    /// Detections posted by a detector, if `message` is one of its messages
+    pub fn from_message(message: &gst::Message) -> Option<Self> {
+        match message.view() {
+            gst::MessageView::Element(element) => Self::from_structure(element.structure()?),
+            _ => None,
+        }
+    }
+}
+
+/// A frame's detections, attached to its buffer
+#[repr(transparent)]
+pub struct DetectionMeta(imp::DetectionMeta);
+
+unsafe impl Send for DetectionMeta {}
+unsafe impl Sync for DetectionMeta {}
+
+impl DetectionMeta {
+    pub fn add(
+        buffer: &mut gst::BufferRef,
+        detections: FrameDetections,
+    ) -> gst::MetaRefMut<'_, Self, gst::meta::Standalone> {
+        // SAFETY: the buffer is borrowed mutably and the meta takes the
+        // freshly built structure
+        unsafe {
+            imp::add(
+                buffer.as_mut_ptr(),
+                detections.to_structure().into_glib_ptr(),
+            )
+        };
+        buffer
+            .meta_mut::<Self>()
+            .expect("Failed to add detection meta")
+    }
+
+    /// The detections as stored, in the element message layout
+    pub fn structure(&self) -> &gst::StructureRef {
+        unsafe { gst::StructureRef::from_glib_borrow(self.0.structure) }
+    }
+
+    pub fn frame(&self) -> Option<FrameDetections> {
+        FrameDetections::from_structure(self.structure())
+    }
+
+    pub fn detections(&self) -> Vec<Detection> {
+        self.frame()
+            .map(|frame| frame.detections)
+            .unwrap_or_default()
+    }
+}
+
+unsafe impl MetaAPI for DetectionMeta {
+    type GstType = imp::DetectionMeta;
+
+    fn meta_api() -> glib::Type {
+        imp::detection_meta_api_get_type()
+    }
+}
+
+impl fmt::Debug for DetectionMeta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DetectionMeta")
+            .field("structure", &self.structure())
+            .finish()
+    }
+}
+
+mod imp {
+    use gstreamer as gst;
+    use gstreamer::glib;
+    use gstreamer::glib::translate::*;
+    use gstreamer_video as gst_video;
+    use std::ptr;
+    use std::sync::OnceLock;
+
+    const API_NAME: &std::ffi::CStr = c"GstCpuDetectionMetaAPI";
+    const META_NAME: &std::ffi::CStr = c"GstCpuDetectionMeta";
+
+    /// Plain C layout, so any binary registering the meta reads it alike
+    #[repr(C)]
+    pub struct DetectionMeta {
+        parent: gst::ffi::GstMeta,
+        /// Owned by the meta, freed in [`detection_meta_free`]
+        pub(super) structure: *mut gst::ffi::GstStructure,
+    }
+
+    /// Attach a meta taking ownership of `structure`
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be writable and `structure` a structure the caller owns.
+    pub(super) unsafe fn add(
+        buffer: *mut gst::ffi::GstBuffer,
+        structure: *mut gst::ffi::GstStructure,
+    ) {
+        unsafe {
+            let meta = gst::ffi::gst_buffer_add_meta(
+                buffer,
+                detection_meta_get_info(),
+                structure as glib::ffi::gpointer,
+            );
+            if meta.is_null() {
+                gst::ffi::gst_structure_free(structure);
+            }
+        }
+    }
+
+    pub(super) fn detection_meta_api_get_type() -> glib::Type {
+        static TYPE: OnceLock<glib::Type> = OnceLock::new();
+        *TYPE.get_or_init(|| unsafe {
+            // The plugin and an application linking this crate both
+            // register the API; the second finds the first's
+            if let Some(registered) = glib::Type::from_name(API_NAME.to_str().unwrap()) {
+                return registered;
+            }
+            // Only valid as long as the video is not scaled or converted
+            let tags = [
+                gst_video::ffi::GST_META_TAG_VIDEO_STR.as_ptr() as *const std::ffi::c_char,
+                ptr::null(),
+            ];
+            let api: glib::Type = from_glib(gst::ffi::gst_meta_api_type_register(
+                API_NAME.as_ptr(),
+                tags.as_ptr() as *mut *const _,
+            ));
+            assert_ne!(api, glib::Type::INVALID);
+            api
+        })
+    }
+
+    unsafe extern "C" fn detection_meta_init(
+        meta: *mut gst::ffi::GstMeta,
+        params: glib::ffi::gpointer,
+        _buffer: *mut gst::ffi::GstBuffer,
+    ) -> glib::ffi::gboolean {
+        if params.is_null() {
+            return false.into_glib();
+        }
+        unsafe {
+            let meta = &mut *(meta as *mut DetectionMeta);
+            meta.structure = params as *mut gst::ffi::GstStructure;
+        }
+        true.into_glib()
+    }
+
+    unsafe extern "C" fn detection_meta_free(
+        meta: *mut gst::ffi::GstMeta,
+        _buffer: *mut gst::ffi::GstBuffer,
+    ) {
+        unsafe {
+            let meta = &mut *(meta as *mut DetectionMeta);
+            if !meta.structure.is_null() {
+                gst::ffi::gst_structure_free(meta.structure);
+                meta.structure = ptr::null_mut();
+            }
+        }
+    }
+
+    unsafe extern "C" fn detection_meta_transform(
+        dest: *mut gst::ffi::GstBuffer,
+        meta: *mut gst::ffi::GstMeta,
+        _buffer: *mut gst::ffi::GstBuffer,
+        type_: glib::ffi::GQuark,
+        _data: glib::ffi::gpointer,
+    ) -> glib::ffi::gboolean {
+        unsafe {
+            // Copies keep the boxes; scaling and conversion lose them
+            if glib::Quark::from_glib(type_) != glib::Quark::from_str("gst-copy") {
+                return false.into_glib();
+            }
+            let meta = &*(meta as *mut DetectionMeta);
+            add(dest, gst::ffi::gst_structure_copy(meta.structure));
+        }
+        true.into_glib()
+    }
+
+    pub(super) fn detection_meta_get_info() -> *const gst::ffi::GstMetaInfo {
+        struct MetaInfo(ptr::NonNull<gst::ffi::GstMetaInfo>);
+        unsafe impl Send for MetaInfo {}
+        unsafe impl Sync for MetaInfo {}
+
+        static META_INFO: OnceLock<MetaInfo> = OnceLock::new();
+        META_INFO
+            .get_or_init(|| unsafe {
+                let registered = gst::ffi::gst_meta_get_info(META_NAME.as_ptr());
+                let info = if registered.is_null() {
+                    gst::ffi::gst_meta_register(
+                        detection_meta_api_get_type().into_glib(),
+                        META_NAME.as_ptr(),
+                        std::mem::size_of::<DetectionMeta>(),
+                        Some(detection_meta_init),
+                        Some(detection_meta_free),
+                        Some(detection_meta_transform),
+                    )
+                } else {
+                    registered
+                };
+                MetaInfo(
+                    ptr::NonNull::new(info as *mut gst::ffi::GstMetaInfo)
+                        .expect("Failed to register detection meta"),
+                )
+            })
+            .0
+            .as_ptr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> FrameDetections {
+        FrameDetections {
+            frame_number: 7,
+            unique_id: 1,
+            pts: Some(gst::ClockTime::from_mseconds(40)),
+            detections: vec![Detection {
+                x: 10.0,
+                y: 20.0,
+                width: 30.0,
+                height: 40.0,
+                confidence: 0.9,
+                class_id: 2,
+                class_name: "car".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_detection_meta_on_buffer() {
+        gst::init().unwrap();
+
+        let mut buffer = gst::Buffer::with_size(16).unwrap();
+        DetectionMeta::add(buffer.get_mut().unwrap(), frame());
+        let meta = buffer.meta::<DetectionMeta>().unwrap();
+        assert_eq!(meta.structure().name(), MESSAGE_NAME);
+        assert_eq!(meta.frame().unwrap().frame_number, 7);
+        assert_eq!(meta.detections()[0].class_name, "car");
+    }
+
+    #[test]
+    fn test_detection_meta_survives_copy() {
+        gst::init().unwrap();
+
+        let mut buffer = gst::Buffer::with_size(16).unwrap();
+        DetectionMeta::add(buffer.get_mut().unwrap(), frame());
+        let copy = buffer.copy();
+        drop(buffer);
+        let meta = copy.meta::<DetectionMeta>().unwrap();
+        assert_eq!(meta.frame().unwrap().unique_id, 1);
+    }
+
+    #[test]
+    fn test_frame_detections_structure_round_trip() {
+        gst::init().unwrap();
+
+        let structure = frame().to_structure();
+        let parsed = FrameDetections::from_structure(&structure).unwrap();
+        assert_eq!(parsed.pts, Some(gst::ClockTime::from_mseconds(40)));
+        assert_eq!(parsed.detections[0].class_id, 2);
+        assert_eq!(parsed.detections[0].width, 30.0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Inference on several threads with results in submission order
+//!
+//! One ONNX Runtime session call leaves most cores of a large CPU idle.
+//! The pool runs one worker, with its own session, per thread and gives
+//! each job a sequence number; results are held back until every earlier
+//! job is done, so frames leave in the order they came in.
+
+use std::collections::BTreeMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+
+pub(crate) struct WorkerPool<I, O> {
+    jobs: Option<mpsc::Sender<(u64, I)>>,
+    results: mpsc::Receiver<(u64, thread::Result<O>)>,
+    workers: Vec<JoinHandle<()>>,
+    next_seq: u64,
+    next_out: u64,
+    ready: BTreeMap<u64, thread::Result<O>>,
+}
+
+impl<I: Send + 'static, O: Send + 'static> WorkerPool<I, O> {
+    /// Start `threads` workers, each running the job function
+    /// `make_worker` returns for its index
+    pub fn new<W, F>(threads: usize, mut make_worker: W) -> Self
+    where
+        W: FnMut(usize) -> F,
+        F: FnMut(I) -> O + Send + 'static,
+    {
+        let (job_sender, job_receiver) = mpsc::channel::<(u64, I)>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (result_sender, results) = mpsc::channel();
+
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let jobs = job_receiver.clone();
+                let results = result_sender.clone();
+                let mut work = make_worker(index);
+                thread::Builder::new()
+                    .name(format!("cpuinfer-{}", index))
+                    .spawn(move || {
+                        loop {
+                            let job = jobs.lock().unwrap().recv();
+                            let Ok((seq, input)) = job else {
+                                break;
+                            };
+                            // A panicking job fails its frame, not the pool
+                            let output = catch_unwind(AssertUnwindSafe(|| work(input)));
+                            if results.send((seq, output)).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn inference worker")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            workers,
+            next_seq: 0,
+            next_out: 0,
+            ready: BTreeMap::new(),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Jobs submitted whose results have not been taken
+    pub fn pending(&self) -> usize {
+        (self.next_seq - self.next_out) as usize
+    }
+
+    /// Queue `input`, returning its sequence number
+    pub fn submit(&mut self, input: I) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if let Some(jobs) = &self.jobs {
+            // Workers only stop once the sender is dropped
+            let _ = jobs.send((seq, input));
+        }
+        seq
+    }
+
+    /// The next result in order, if it is done
+    pub fn try_next(&mut self) -> Option<thread::Result<O>> {
+        while let Ok((seq, output)) = self.results.try_recv() {
+            self.ready.insert(seq, output);
+        }
+        self.take_ready()
+    }
+
+    /// Wait for the next result in order; `None` with nothing pending
+    pub fn next(&mut self) -> Option<thread::Result<O>> {
+        while self.pending() > 0 {
+            if let Some(output) = self.take_ready() {
+                return Some(output);
+            }
+            let (seq, output) = self.results.recv().ok()?;
+            self.ready.insert(seq, output);
+        }
+        None
+    }
+
+    fn take_ready(&mut self) -> Option<thread::Result<O>> {
+        let output = self.ready.remove(&self.next_out)?;
+        self.next_out += 1;
+        Some(output)
+    }
+}
+
+impl<I, O> Drop for WorkerPool<I, O> {
+    fn drop(&mut self) {
+        // Workers finish their current job and see the closed channel
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Four threads where early jobs take longest, so they finish last,
+    /// and job 5 panics
+    fn slow_pool() -> WorkerPool<u64, u64> {
+        WorkerPool::new(4, |_| {
+            |job: u64| {
+                thread::sleep(Duration::from_millis(40 - job * 5));
+                if job == 5 {
+                    panic!("bad frame");
+                }
+                job * 10
+            }
+        })
+    }
+
+    #[test]
+    fn test_results_keep_submission_order() {
+        let mut pool = slow_pool();
+        for job in 0..5 {
+            pool.submit(job);
+        }
+        let results: Vec<_> = std::iter::from_fn(|| pool.next())
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, [0, 10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn test_panicking_job_fails_alone() {
+        let mut pool = slow_pool();
+        for job in 4..7 {
+            pool.submit(job);
+        }
+        let results: Vec<_> = std::iter::from_fn(|| pool.next())
+            .map(|result| result.ok())
+            .collect();
+        assert_eq!(results, [Some(40), None, Some(60)]);
+    }
+
+    #[test]
+    fn test_pending_jobs() {
+        let mut pool = slow_pool();
+        assert_eq!(pool.threads(), 4);
+        for job in 0..8 {
+            pool.submit(job);
+        }
+        assert_eq!(pool.pending(), 8);
+        assert!(pool.try_next().is_none());
+
+        while pool.next().is_some() {}
+        assert_eq!(pool.pending(), 0);
+    }
+}
@@ -0,0 +1,127 @@
+use super::DetectorArgs;
+use crate::CliResult;
+use crate::output::OutputFormat;
+use ds_rs::bench::{
+    AvSyncBenchConfig, BenchVariant, DecodeBenchConfig, DecodeTarget, InferenceBenchConfig,
+    run_avsync_bench, run_decode_bench, run_inference_bench,
+};
+use gstcpuinfer::detector::ExecutionProvider;
+use std::time::Duration;
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub detector: DetectorArgs,
+
+    /// Image files, video files or URIs; a synthetic frame is used when none are given
+    pub inputs: Vec<String>,
+
+    /// Seconds to run each configuration
+    #[arg(short, long, default_value_t = 10)]
+    pub duration: u64,
+
+    /// Untimed steps run before measuring each configuration
+    #[arg(long, default_value_t = 3)]
+    pub warmup: usize,
+
+    /// Thread counts to compare (defaults to --threads)
+    #[arg(long, value_delimiter = ',')]
+    pub thread_counts: Vec<usize>,
+
+    /// Batch sizes to compare
+    #[arg(long, value_delimiter = ',', default_value = "1")]
+    pub batch_sizes: Vec<usize>,
+
+    /// Execution providers to compare (cpu, cuda, tensorrt, directml, openvino, coreml)
+    #[arg(long = "ep", value_delimiter = ',', default_value = "cpu")]
+    pub execution_providers: Vec<ExecutionProvider>,
+
+    /// Frames decoded from each video input
+    #[arg(long, default_value_t = 100)]
+    pub max_frames: usize,
+
+    /// Compare decode throughput of each backend on the inputs instead of running the detector
+    #[arg(long)]
+    pub decode: bool,
+
+    /// Decode paths to compare with --decode (mock, standard, deepstream, nvcodec)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "mock,standard,deepstream,nvcodec",
+        requires = "decode"
+    )]
+    pub targets: Vec<DecodeTarget>,
+
+    /// Measure the A/V offset and its drift on streams that flash and beep together,
+    /// such as a source-videos avsync pattern, instead of running the detector
+    #[arg(long, conflicts_with = "decode")]
+    pub avsync: bool,
+}
+
+pub fn run(args: BenchArgs, output: OutputFormat) -> CliResult {
+    ds_rs::init()?;
+
+    if args.decode {
+        return run_decode(args, output);
+    }
+    if args.avsync {
+        return run_avsync(args, output);
+    }
+
+    let thread_counts = if args.thread_counts.is_empty() {
+        vec![args.detector.threads]
+    } else {
+        args.thread_counts.clone()
+    };
+
+    let config = InferenceBenchConfig {
+        detector: args.detector.detector_config()?,
+        inputs: args.inputs.clone(),
+        max_frames_per_input: args.max_frames,
+        duration: Duration::from_secs(args.duration),
+        warmup_steps: args.warmup,
+        variants: BenchVariant::matrix(
+            &thread_counts,
+            &args.batch_sizes,
+            &args.execution_providers,
+        ),
+    };
+
+    let report = run_inference_bench(&config)?;
+    output.emit(&report);
+    Ok(())
+}
+
+fn run_decode(args: BenchArgs, output: OutputFormat) -> CliResult {
+    if args.inputs.is_empty() {
+        return Err("--decode needs at least one video file or URI".into());
+    }
+
+    for input in &args.inputs {
+        let config = DecodeBenchConfig {
+            targets: args.targets.clone(),
+            max_duration: Duration::from_secs(args.duration),
+            ..DecodeBenchConfig::new(input.clone())
+        };
+        let report = run_decode_bench(&config)?;
+        output.emit(&report);
+    }
+    Ok(())
+}
+
+fn run_avsync(args: BenchArgs, output: OutputFormat) -> CliResult {
+    if args.inputs.is_empty() {
+        return Err("--avsync needs at least one stream URI".into());
+    }
+
+    for input in &args.inputs {
+        let config = AvSyncBenchConfig {
+            duration: Duration::from_secs(args.duration),
+            ..AvSyncBenchConfig::new(input.clone())
+        };
+        let report = run_avsync_bench(&config)?;
+        output.emit(&report);
+    }
+    Ok(())
+}
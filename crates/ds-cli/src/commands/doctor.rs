@@ -0,0 +1,20 @@
+use crate::CliResult;
+use crate::output::OutputFormat;
+use ds_rs::BackendType;
+use ds_rs::doctor::run_doctor;
+
+#[derive(clap::Args, Debug)]
+pub struct DoctorArgs {
+    /// Backend to check for (deepstream, standard, mock); detected when omitted
+    #[arg(short, long)]
+    pub backend: Option<BackendType>,
+}
+
+pub fn run(args: DoctorArgs, output: OutputFormat) -> CliResult {
+    let report = run_doctor(args.backend);
+    output.emit(&report);
+    if report.has_missing() {
+        return Err("required dependencies are missing".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use super::DetectorArgs;
+use crate::CliResult;
+use crate::output::OutputFormat;
+use gstcpuinfer::detector::{Detection, OnnxDetector};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(clap::Args, Debug)]
+pub struct InferArgs {
+    #[command(flatten)]
+    pub detector: DetectorArgs,
+
+    /// Images to run detection on
+    #[arg(required = true)]
+    pub images: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct DetectionRecord {
+    class_id: usize,
+    class_name: String,
+    confidence: f32,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl From<&Detection> for DetectionRecord {
+    fn from(detection: &Detection) -> Self {
+        Self {
+            class_id: detection.class_id,
+            class_name: detection.class_name.clone(),
+            confidence: detection.confidence,
+            x: detection.x,
+            y: detection.y,
+            width: detection.width,
+            height: detection.height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InferReport {
+    image: String,
+    inference_ms: f64,
+    detections: Vec<DetectionRecord>,
+}
+
+impl fmt::Display for InferReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} detections in {:.1}ms",
+            self.image,
+            self.detections.len(),
+            self.inference_ms
+        )?;
+        for d in &self.detections {
+            write!(
+                f,
+                "\n  {} ({:.2}) at [{:.0}, {:.0}, {:.0}x{:.0}]",
+                d.class_name, d.confidence, d.x, d.y, d.width, d.height
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: InferArgs, output: OutputFormat) -> CliResult {
+    let detector = OnnxDetector::new_with_config(args.detector.detector_config()?)?;
+
+    for path in &args.images {
+        let image = image::open(path)?;
+
+        let started = Instant::now();
+        let detections = detector.detect(&image)?;
+        let inference_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        output.emit(&InferReport {
+            image: path.display().to_string(),
+            inference_ms,
+            detections: detections.iter().map(DetectionRecord::from).collect(),
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+pub mod bench;
+pub mod doctor;
+pub mod infer;
+pub mod run;
+pub mod serve;
+
+use clap::CommandFactory;
+use clap_complete::{Shell, generate};
+
+pub fn completions<C: CommandFactory>(shell: Shell) {
+    let mut command = C::command();
+    let name = command.get_name().to_string();
+    generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Detector flags shared by `infer` and `bench`
+#[derive(clap::Args, Debug, Clone)]
+pub struct DetectorArgs {
+    /// Path to the ONNX model; needed unless a bench measures something
+    /// else
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// Confidence threshold for detections
+    #[arg(long, default_value_t = 0.5)]
+    pub confidence: f32,
+
+    /// NMS IoU threshold
+    #[arg(long, default_value_t = 0.45)]
+    pub nms: f32,
+
+    /// Number of inference threads
+    #[arg(long, default_value_t = 4)]
+    pub threads: usize,
+
+    /// Model input size as WIDTHxHEIGHT
+    #[arg(long, default_value = "640x640", value_parser = parse_size)]
+    pub input_size: (u32, u32),
+}
+
+impl DetectorArgs {
+    /// Detector config for the flags; fails without `--model`
+    pub fn detector_config(&self) -> Result<gstcpuinfer::detector::DetectorConfig, String> {
+        let model = self.model.clone().ok_or("--model is required")?;
+        Ok(gstcpuinfer::detector::DetectorConfig {
+            model_path: Some(model),
+            input_width: self.input_size.0,
+            input_height: self.input_size.1,
+            confidence_threshold: self.confidence,
+            nms_threshold: self.nms,
+            num_threads: self.threads,
+            ..Default::default()
+        })
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", value))?;
+    let width = width.parse().map_err(|e| format!("invalid width: {}", e))?;
+    let height = height
+        .parse()
+        .map_err(|e| format!("invalid height: {}", e))?;
+    Ok((width, height))
+}
@@ -0,0 +1,59 @@
+use crate::CliResult;
+use crate::output::OutputFormat;
+use ds_rs::app::Application;
+use serde::Serialize;
+use std::fmt;
+use std::time::Instant;
+
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    /// Source URI (file:///path/to/video.mp4 or rtsp://...)
+    pub uri: String,
+
+    /// Force a specific backend (mock, standard, deepstream)
+    #[arg(short, long)]
+    pub backend: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunSummary {
+    uri: String,
+    backend: Option<String>,
+    elapsed_secs: f64,
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pipeline for {} exited after {:.1}s",
+            self.uri, self.elapsed_secs
+        )
+    }
+}
+
+pub fn run(args: RunArgs, output: OutputFormat) -> CliResult {
+    // Backend selection is read from the environment by BackendManager
+    if let Some(backend) = &args.backend {
+        // SAFETY: main has not started any threads yet and ds_rs::init,
+        // which starts GStreamer's, runs after this
+        unsafe {
+            std::env::set_var("FORCE_BACKEND", backend);
+        }
+    }
+
+    ds_rs::init()?;
+
+    let started = Instant::now();
+    let mut app = Application::new(args.uri.clone())?;
+    app.init()
+        .map_err(|e| format!("{} (run `ds doctor` to check dependencies)", e))?;
+    app.run_with_glib_signals()?;
+
+    output.emit(&RunSummary {
+        uri: args.uri,
+        backend: args.backend,
+        elapsed_secs: started.elapsed().as_secs_f64(),
+    });
+    Ok(())
+}
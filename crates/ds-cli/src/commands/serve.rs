@@ -0,0 +1,115 @@
+use crate::CliResult;
+use crate::output::OutputFormat;
+use serde::Serialize;
+use source_videos::network::NetworkProfile;
+use source_videos::shutdown::{ShutdownCoordinator, wait_for_signal};
+use source_videos::{EmbeddedServer, VideoSourceConfig};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// RTSP port (0 = pick a free port)
+    #[arg(short, long, default_value_t = 8554)]
+    pub port: u16,
+
+    /// Address to bind
+    #[arg(short, long, default_value = "0.0.0.0")]
+    pub address: String,
+
+    /// Try up to N following ports when the port is in use
+    #[arg(long, default_value_t = 0)]
+    pub port_retries: u16,
+
+    /// Test pattern to serve, mounted as /pattern-N (repeatable)
+    #[arg(long = "pattern", value_delimiter = ',')]
+    pub patterns: Vec<String>,
+
+    /// Video file to serve, mounted by file stem (repeatable)
+    #[arg(short, long = "file", value_delimiter = ',')]
+    pub files: Vec<PathBuf>,
+
+    /// Network profile applied to all streams (perfect, 3g, 4g, 5g, wifi, public, satellite, broadband, poor)
+    #[arg(long)]
+    pub network_profile: Option<String>,
+
+    /// Stop after this many seconds
+    #[arg(long)]
+    pub duration: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ServeReport {
+    address: String,
+    port: u16,
+    urls: Vec<String>,
+}
+
+impl fmt::Display for ServeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Serving RTSP on {}:{}", self.address, self.port)?;
+        for url in &self.urls {
+            writeln!(f, "  {}", url)?;
+        }
+        write!(f, "Press Ctrl+C to stop")
+    }
+}
+
+pub fn run(args: ServeArgs, output: OutputFormat) -> CliResult {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(serve(args, output))
+}
+
+async fn serve(args: ServeArgs, output: OutputFormat) -> CliResult {
+    let mut builder = EmbeddedServer::builder()
+        .address(args.address.clone())
+        .port(args.port)
+        .port_retries(args.port_retries);
+
+    if let Some(profile) = &args.network_profile {
+        builder = builder.network_profile(NetworkProfile::from_str(profile)?);
+    }
+
+    let patterns = if args.patterns.is_empty() && args.files.is_empty() {
+        vec!["smpte".to_string()]
+    } else {
+        args.patterns.clone()
+    };
+    for (i, pattern) in patterns.iter().enumerate() {
+        builder = builder.add_test_pattern(&format!("pattern-{}", i + 1), pattern);
+    }
+    for path in &args.files {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("video");
+        builder = builder.add_source(VideoSourceConfig::file(name, path.display().to_string()));
+    }
+
+    let mut server = builder.build()?;
+    server.start().await?;
+
+    output.emit(&ServeReport {
+        address: args.address.clone(),
+        port: server.port().await,
+        urls: server.urls().await,
+    });
+
+    let coordinator = ShutdownCoordinator::default();
+    let token = coordinator.token();
+    match args.duration {
+        Some(seconds) => {
+            tokio::select! {
+                _ = wait_for_signal(&token) => {}
+                _ = tokio::time::sleep(Duration::from_secs(seconds)) => {}
+            }
+        }
+        None => wait_for_signal(&token).await,
+    }
+
+    output.note("Stopping RTSP server");
+    server.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+mod commands;
+mod output;
+
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use output::OutputFormat;
+
+pub type CliResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "ds",
+    version,
+    about = "DeepStream Rust - unified command line",
+    long_about = "Single entry point for the ds-rs workspace.\n\
+                  Serve test streams, run the analytics pipeline, run inference on images\n\
+                  and benchmark detectors with consistent flags and output formats.",
+    after_help = "EXAMPLES:\n  \
+                  ds serve --pattern smpte --pattern ball --port 8554\n  \
+                  ds run rtsp://localhost:8554/pattern-1 --backend standard\n  \
+                  ds infer --model yolov5n.onnx image.jpg --output json\n  \
+                  ds bench --model yolov5n.onnx --thread-counts 1,2,4 --output json\n  \
+                  ds bench --decode video.mp4 --targets standard,nvcodec\n  \
+                  ds bench --avsync rtsp://localhost:8554/pattern-1   # while ds serve --pattern avsync runs\n  \
+                  ds doctor --backend standard\n  \
+                  ds completions bash > /etc/bash_completion.d/ds"
+)]
+struct Cli {
+    /// Output format for results
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Enable debug logging
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Serve test patterns and video files over RTSP (wraps source-videos)
+    Serve(commands::serve::ServeArgs),
+
+    /// Run the analytics pipeline on a source URI (wraps ds-rs)
+    Run(commands::run::RunArgs),
+
+    /// Run the CPU detector on images and print detections
+    Infer(commands::infer::InferArgs),
+
+    /// Measure detector throughput per configuration, or decode throughput per backend
+    Bench(commands::bench::BenchArgs),
+
+    /// Check plugins, libraries and codecs needed by a backend
+    Doctor(commands::doctor::DoctorArgs),
+
+    /// Generate shell completion scripts
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let level = if cli.verbose { "debug" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+
+    let result = match cli.command {
+        Commands::Serve(args) => commands::serve::run(args, cli.output),
+        Commands::Run(args) => commands::run::run(args, cli.output),
+        Commands::Infer(args) => commands::infer::run(args, cli.output),
+        Commands::Bench(args) => commands::bench::run(args, cli.output),
+        Commands::Doctor(args) => commands::doctor::run(args, cli.output),
+        Commands::Completions { shell } => {
+            commands::completions::<Cli>(shell);
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,37 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// How command results are written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// One JSON document per result, suitable for scripts and CI
+    Json,
+}
+
+impl OutputFormat {
+    /// Print `value` in this format.
+    ///
+    /// Text output uses the value's `Display` impl so each command decides
+    /// its own layout; JSON output is always a single line.
+    pub fn emit<T: Serialize + Display>(&self, value: &T) {
+        match self {
+            OutputFormat::Text => println!("{}", value),
+            OutputFormat::Json => match serde_json::to_string(value) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Failed to serialize output: {}", e),
+            },
+        }
+    }
+
+    /// Print an informational line; suppressed in JSON mode to keep stdout parseable
+    pub fn note(&self, message: impl Display) {
+        match self {
+            OutputFormat::Text => println!("{}", message),
+            OutputFormat::Json => log::info!("{}", message),
+        }
+    }
+}
@@ -246,9 +246,49 @@ impl BallTrackingApp {
                     warning
                 );
             }
-            SourceEvent::Error { id, error } => {
+            SourceEvent::Error { id, error, .. } => {
                 log::error!("[{:.3}] Source {:?} error: {:?}", timestamp(), id, error);
             }
+            SourceEvent::RecoveryAttempt { id, attempt, delay } => {
+                log::info!(
+                    "[{:.3}] Source {:?} recovery attempt {} in {:?}",
+                    timestamp(),
+                    id,
+                    attempt,
+                    delay
+                );
+            }
+            SourceEvent::Recovered { id, attempts } => {
+                log::info!(
+                    "[{:.3}] Source {:?} recovered after {} attempt(s)",
+                    timestamp(),
+                    id,
+                    attempts
+                );
+            }
+            SourceEvent::RecoveryFailed { id, reason } => {
+                log::error!(
+                    "[{:.3}] Source {:?} recovery failed: {}",
+                    timestamp(),
+                    id,
+                    reason
+                );
+            }
+            SourceEvent::Progress {
+                id,
+                position,
+                stalled,
+                ..
+            } => {
+                if *stalled {
+                    log::warn!(
+                        "[{:.3}] Source {:?} stalled at {:?}",
+                        timestamp(),
+                        id,
+                        position
+                    );
+                }
+            }
         });
 
         let gst_pipeline = pipeline_arc.gst_pipeline().clone();
@@ -73,6 +73,7 @@ fn test_real_detection(model_path: &str) -> Result<(), Box<dyn std::error::Error
         num_threads: 4,
         yolo_version: YoloVersion::Auto,
         class_names: None, // Use default COCO classes
+        ..Default::default()
     };
 
     println!("Creating detector with config:");
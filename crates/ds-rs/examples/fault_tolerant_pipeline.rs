@@ -57,6 +57,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         window_size_secs: 10,
         check_interval: Duration::from_secs(5),
         failure_threshold: 2,
+        ..Default::default()
     };
 
     println!("Health Monitoring Configuration:");
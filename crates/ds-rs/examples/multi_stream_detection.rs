@@ -40,6 +40,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             num_threads: 2,
             yolo_version: ds_rs::backend::cpu_vision::YoloVersion::V8,
             class_names: None,
+            ..Default::default()
         })
         .worker_threads(4)
         .debug_mode(true)
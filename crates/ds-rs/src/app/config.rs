@@ -1,5 +1,8 @@
 // Configuration constants matching the C reference implementation
 
+use crate::error::{DeepStreamError, Result};
+use serde::{Deserialize, Serialize};
+
 pub const MAX_NUM_SOURCES: usize = 4;
 pub const MUXER_OUTPUT_WIDTH: u32 = 1920;
 pub const MUXER_OUTPUT_HEIGHT: u32 = 1080;
@@ -16,5 +19,66 @@ pub const SGIE1_CONFIG_FILE: &str = "dstest_sgie1_config.txt";
 pub const SGIE2_CONFIG_FILE: &str = "dstest_sgie2_config.txt";
 pub const SGIE3_CONFIG_FILE: &str = "dstest_sgie3_config.txt";
 
+/// nvinfer configs of the models the pipeline runs
+pub const MODEL_CONFIGS: [&str; 4] = [
+    PGIE_CONFIG_FILE,
+    SGIE1_CONFIG_FILE,
+    SGIE2_CONFIG_FILE,
+    SGIE3_CONFIG_FILE,
+];
+
+/// Frames further behind the clock are dropped before rendering (Standard backend)
+pub const FRAME_DEADLINE_MS: u64 = 200;
+
 pub const SOURCE_ADD_INTERVAL_SECS: u64 = 10;
 pub const SOURCE_DELETE_INTERVAL_SECS: u64 = 10;
+
+/// What the demo does with sources once playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DemoMode {
+    /// Add sources periodically up to the maximum, then remove them again
+    #[default]
+    Cycle,
+    /// Play the given sources only, as a simple player/analyzer
+    Static,
+}
+
+/// Demo behavior, the `[demo]` section of an
+/// [`ApplicationConfig`](crate::config::ApplicationConfig)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DemoConfig {
+    pub mode: DemoMode,
+    pub add_interval_secs: u64,
+    pub delete_interval_secs: u64,
+    /// Sources active at most
+    pub max_sources: usize,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            mode: DemoMode::Cycle,
+            add_interval_secs: SOURCE_ADD_INTERVAL_SECS,
+            delete_interval_secs: SOURCE_DELETE_INTERVAL_SECS,
+            max_sources: MAX_NUM_SOURCES,
+        }
+    }
+}
+
+impl DemoConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_sources == 0 {
+            return Err(DeepStreamError::Configuration(
+                "demo max_sources must be at least 1".to_string(),
+            ));
+        }
+        if self.add_interval_secs == 0 || self.delete_interval_secs == 0 {
+            return Err(DeepStreamError::Configuration(
+                "demo timer intervals must be at least 1 second".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+//! Keyboard controls for the running demo
+//!
+//! Keys are read from stdin a line at a time, so a key takes effect once
+//! Enter is pressed; several keys on one line run in order.
+
+use crate::pipeline::Pipeline;
+use crate::shutdown::ShutdownToken;
+use crate::source::uri::redact;
+use crate::source::{SourceController, SourceId};
+use gstreamer::prelude::*;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const HELP: &str = "Keys (then Enter): a add source, d remove last source, s snapshot, \
+                        space pause/resume, 1-9 show one tile, 0 show all tiles, h help, q quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCommand {
+    AddSource,
+    RemoveLast,
+    Snapshot,
+    TogglePause,
+    Quit,
+    /// Show only the nth tile, counted from 1; 0 shows all
+    SelectTile(usize),
+    Help,
+}
+
+impl KeyCommand {
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'a' => Some(Self::AddSource),
+            'd' => Some(Self::RemoveLast),
+            's' => Some(Self::Snapshot),
+            ' ' => Some(Self::TogglePause),
+            'q' => Some(Self::Quit),
+            'h' | '?' => Some(Self::Help),
+            '0'..='9' => key.to_digit(10).map(|n| Self::SelectTile(n as usize)),
+            _ => None,
+        }
+    }
+}
+
+/// Commands for the keys on one line of input
+pub fn parse_line(line: &str) -> Vec<KeyCommand> {
+    line.trim_end_matches(['\r', '\n'])
+        .chars()
+        .filter_map(KeyCommand::from_key)
+        .collect()
+}
+
+/// Read commands from stdin on a background thread, until stdin closes
+pub fn spawn_stdin_reader() -> mpsc::Receiver<KeyCommand> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            for command in parse_line(&line) {
+                if tx.send(command).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// What the keys act on
+pub struct KeyboardControls {
+    source_controller: Arc<Mutex<SourceController>>,
+    pipeline: Arc<Pipeline>,
+    uri: String,
+    shutdown: ShutdownToken,
+    paused: bool,
+}
+
+impl KeyboardControls {
+    pub fn new(
+        source_controller: Arc<Mutex<SourceController>>,
+        pipeline: Arc<Pipeline>,
+        uri: String,
+        shutdown: ShutdownToken,
+    ) -> Self {
+        Self {
+            source_controller,
+            pipeline,
+            uri,
+            shutdown,
+            paused: false,
+        }
+    }
+
+    pub fn handle(&mut self, command: KeyCommand) {
+        let timestamp = crate::timestamp();
+        let result = match command {
+            KeyCommand::AddSource => self.add_source(),
+            KeyCommand::RemoveLast => self.remove_last(),
+            KeyCommand::Snapshot => {
+                println!("{}", self.pipeline.snapshot());
+                Ok(())
+            }
+            KeyCommand::TogglePause => self.toggle_pause(),
+            KeyCommand::Quit => {
+                println!("[{:.3}] Key: quit", timestamp);
+                self.shutdown.cancel();
+                Ok(())
+            }
+            KeyCommand::SelectTile(tile) => self.select_tile(tile),
+            KeyCommand::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("[{:.3}] Key {:?} failed: {:?}", timestamp, command, e);
+        }
+    }
+
+    fn add_source(&self) -> crate::error::Result<()> {
+        let id = self
+            .source_controller
+            .lock()
+            .unwrap()
+            .add_source(&self.uri)?;
+        println!(
+            "[{:.3}] Key: added source {} ({})",
+            crate::timestamp(),
+            id,
+            redact(&self.uri)
+        );
+        Ok(())
+    }
+
+    fn remove_last(&self) -> crate::error::Result<()> {
+        let controller = self.source_controller.lock().unwrap();
+        let Some(id) = active_sources(&controller)?.pop() else {
+            println!("[{:.3}] Key: no source to remove", crate::timestamp());
+            return Ok(());
+        };
+        controller.remove_source(id)?;
+        println!("[{:.3}] Key: removed source {}", crate::timestamp(), id);
+        Ok(())
+    }
+
+    fn toggle_pause(&mut self) -> crate::error::Result<()> {
+        if self.paused {
+            self.pipeline.play()?;
+        } else {
+            self.pipeline.pause()?;
+        }
+        self.paused = !self.paused;
+        println!(
+            "[{:.3}] Key: {}",
+            crate::timestamp(),
+            if self.paused { "paused" } else { "resumed" }
+        );
+        Ok(())
+    }
+
+    /// Show only the source of the nth tile: through the tiler's
+    /// `show-source` with DeepStream, by hiding the other compositor pads
+    /// otherwise
+    fn select_tile(&self, tile: usize) -> crate::error::Result<()> {
+        let source = match tile {
+            0 => None,
+            n => {
+                let controller = self.source_controller.lock().unwrap();
+                match active_sources(&controller)?.get(n - 1) {
+                    Some(&id) => Some(id),
+                    None => {
+                        println!("[{:.3}] Key: no tile {}", crate::timestamp(), n);
+                        return Ok(());
+                    }
+                }
+            }
+        };
+
+        if let Some(tiler) = self.pipeline.get_by_name("nvtiler")
+            && tiler.has_property("show-source")
+        {
+            tiler.set_property("show-source", source.map_or(-1, |id| id.0 as i32));
+        } else if let Some(mux) = self.pipeline.get_by_name("stream-muxer") {
+            let selected = source.map(|id| format!("sink_{}", id.0));
+            for pad in mux.sink_pads() {
+                if pad.has_property("alpha") {
+                    let visible = selected.as_ref().is_none_or(|name| pad.name() == *name);
+                    pad.set_property("alpha", if visible { 1.0f64 } else { 0.0 });
+                }
+            }
+        }
+
+        match source {
+            Some(id) => println!(
+                "[{:.3}] Key: showing tile {} (source {})",
+                crate::timestamp(),
+                tile,
+                id
+            ),
+            None => println!("[{:.3}] Key: showing all tiles", crate::timestamp()),
+        }
+        Ok(())
+    }
+}
+
+/// Active sources by ID, which is the order they take the tiles in
+fn active_sources(controller: &SourceController) -> crate::error::Result<Vec<SourceId>> {
+    let mut ids: Vec<_> = controller
+        .list_active_sources()?
+        .into_iter()
+        .map(|(id, _, _)| id)
+        .collect();
+    ids.sort_by_key(|id| id.0);
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_line("a d\r\n"),
+            [
+                KeyCommand::AddSource,
+                KeyCommand::TogglePause,
+                KeyCommand::RemoveLast
+            ]
+        );
+        assert_eq!(
+            parse_line("3x0"),
+            [KeyCommand::SelectTile(3), KeyCommand::SelectTile(0)]
+        );
+        assert_eq!(parse_line("q"), [KeyCommand::Quit]);
+        assert!(parse_line("").is_empty());
+    }
+}
@@ -1,23 +1,72 @@
 pub mod config;
+pub mod keyboard;
 pub mod runner;
+pub mod sources;
+pub mod stress;
 pub mod timers;
 
-use crate::backend::BackendManager;
+use crate::backend::gpu::set_gpu_id;
+use crate::backend::{BackendManager, ElementOverrides, EngineCache, GpuConfig, GpuPlacement};
+use crate::config::{ApplicationConfig, GieConfig};
+use crate::elements::DeepStreamElementType;
 use crate::elements::factory::ElementFactory;
-use crate::error::Result;
-use crate::pipeline::Pipeline;
-use crate::source::SourceController;
+use crate::error::{DeepStreamError, Result};
+use crate::inference::{InferenceFilter, InferenceFilters, InferenceTelemetry};
+use crate::messages::DSMessageHandler;
+use crate::operation::{Operation, OperationRegistry};
+use crate::output::{RecordingConfig, SegmentRecorder};
+use crate::pipeline::{
+    BranchManager, DeadlineConfig, DeadlineStats, ElementHooks, FrameDeadline, HookContext,
+    HookPoint, MessageRouter, Pipeline, PipelineSnapshot, ReplayConfig, ReplayMode, introspect,
+};
+use crate::rules::{RuleEngine, RulesConfig};
+use crate::shutdown::{ShutdownCoordinator, ShutdownToken};
+use crate::source::uri::redact;
+use crate::source::{
+    BatchPolicy, CredentialStore, RecoveryPolicies, SourceController, SourceEvent, SourceId,
+    TimestampAlignment, UriValidator,
+};
+use crate::status::{StatusResponse, StatusServer};
 use gstreamer as gst;
 use gstreamer::glib;
 use gstreamer::prelude::*;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+/// Name of the primary inference element, which frames dropped ahead of
+/// inference are counted against
+const PRIMARY_MODEL: &str = "primary-nvinference-engine";
+
 /// Main application demonstrating runtime source addition/deletion
 pub struct Application {
     pipeline: Arc<Pipeline>,
     source_controller: Arc<Mutex<SourceController>>,
     backend_manager: Arc<BackendManager>,
     initial_uri: String,
+    initial_sources: Vec<sources::InitialSource>,
+    shutdown: ShutdownCoordinator,
+    deadline: Option<DeadlineConfig>,
+    frame_deadline: Option<FrameDeadline>,
+    hooks: Arc<ElementHooks>,
+    messages: Arc<DSMessageHandler>,
+    operations: Arc<OperationRegistry>,
+    gpu_placement: Option<Arc<GpuPlacement>>,
+    engine_cache: Option<Arc<EngineCache>>,
+    telemetry: Arc<InferenceTelemetry>,
+    rules: Option<Arc<Mutex<RuleEngine>>>,
+    inference_filters: InferenceFilters,
+    recording: Option<RecordingConfig>,
+    recorder: Option<(Arc<SegmentRecorder>, BranchManager)>,
+    alignment: Option<TimestampAlignment>,
+    batching: Option<BatchPolicy>,
+    credentials: Option<Arc<CredentialStore>>,
+    recovery: Option<RecoveryPolicies>,
+    /// Config given to [`apply_config`](Self::apply_config), whose
+    /// DeepStream sections shape the pipeline
+    sections: Option<ApplicationConfig>,
+    stress: Option<stress::StressConfig>,
+    demo: config::DemoConfig,
+    keyboard: bool,
 }
 
 // Use the common timestamp function from lib.rs
@@ -28,6 +77,18 @@ pub(crate) fn now() -> f64 {
 
 impl Application {
     pub fn new(uri: String) -> Result<Self> {
+        Self::with_sources(vec![sources::InitialSource::new(uri)])
+    }
+
+    /// Start with several sources, added highest priority first; the
+    /// source timers add copies of the first one given
+    pub fn with_sources(initial_sources: Vec<sources::InitialSource>) -> Result<Self> {
+        let Some(first) = initial_sources.first() else {
+            return Err(crate::error::DeepStreamError::InvalidInput(
+                "at least one source is required".to_string(),
+            ));
+        };
+        let initial_uri = first.uri.clone();
         let backend_manager = Arc::new(BackendManager::new()?);
 
         Ok(Self {
@@ -37,7 +98,29 @@ impl Application {
                 gst::ElementFactory::make("fakesink").build()?,
             ))),
             backend_manager,
-            initial_uri: uri,
+            initial_uri,
+            initial_sources: sources::by_priority(initial_sources),
+            shutdown: ShutdownCoordinator::default(),
+            deadline: None,
+            frame_deadline: None,
+            hooks: ElementHooks::new(),
+            messages: Arc::new(DSMessageHandler::new()),
+            operations: Arc::new(OperationRegistry::new()),
+            gpu_placement: None,
+            engine_cache: None,
+            telemetry: Arc::new(InferenceTelemetry::new()),
+            rules: None,
+            inference_filters: InferenceFilters::default(),
+            recording: None,
+            recorder: None,
+            alignment: None,
+            batching: None,
+            credentials: None,
+            recovery: None,
+            sections: None,
+            stress: None,
+            demo: config::DemoConfig::default(),
+            keyboard: false,
         })
     }
 
@@ -90,31 +173,17 @@ impl Application {
     fn log_element_states(&self) {
         println!("[{:.3}] Logging states of all pipeline elements:", now());
 
-        let gst_pipeline = self.pipeline.gst_pipeline();
-        let bin = gst_pipeline.clone().upcast::<gst::Bin>();
-        let mut iter = bin.iterate_elements();
-
-        while let Ok(Some(element)) = iter.next() {
-            let name = element.name();
-            let (result, current, pending) = element.state(gst::ClockTime::from_mseconds(0));
-
-            match result {
-                Ok(_) => {
-                    if pending != gst::State::VoidPending {
-                        println!(
-                            "[{:.3}]   {} : {:?} -> {:?} (pending)",
-                            now(),
-                            name,
-                            current,
-                            pending
-                        );
-                    } else {
-                        println!("[{:.3}]   {} : {:?}", now(), name, current);
-                    }
-                }
-                Err(_) => {
-                    println!("[{:.3}]   {} : <unknown state>", now(), name);
-                }
+        let snapshot = self.pipeline.snapshot();
+        for element in &snapshot.elements {
+            match &element.pending {
+                Some(pending) => println!(
+                    "[{:.3}]   {} : {} -> {} (pending)",
+                    now(),
+                    element.path,
+                    element.state,
+                    pending
+                ),
+                None => println!("[{:.3}]   {} : {}", now(), element.path, element.state),
             }
         }
     }
@@ -132,11 +201,21 @@ impl Application {
 
         // Only set nvstreammux-specific properties if using DeepStream backend
         if self.backend_manager.backend_type() == crate::backend::BackendType::DeepStream {
+            let mux = self.sections.as_ref().map(|config| &config.pipeline);
             streammux.set_property("batch-size", 30i32);
-            streammux.set_property("batched-push-timeout", 25000i32);
-            streammux.set_property("width", config::MUXER_OUTPUT_WIDTH as i32);
-            streammux.set_property("height", config::MUXER_OUTPUT_HEIGHT as i32);
-            streammux.set_property("live-source", true);
+            streammux.set_property(
+                "batched-push-timeout",
+                mux.map_or(25000, |mux| mux.batched_push_timeout) as i32,
+            );
+            streammux.set_property(
+                "width",
+                mux.map_or(config::MUXER_OUTPUT_WIDTH, |mux| mux.width) as i32,
+            );
+            streammux.set_property(
+                "height",
+                mux.map_or(config::MUXER_OUTPUT_HEIGHT, |mux| mux.height) as i32,
+            );
+            streammux.set_property("live-source", mux.is_none_or(|mux| mux.live_source));
         } else if self.backend_manager.backend_type() == crate::backend::BackendType::Standard {
             // For standard backend (compositor), set different properties
             streammux.set_property_from_str("background", "black");
@@ -148,76 +227,148 @@ impl Application {
 
         let mut elements = vec![streammux.clone()];
 
+        // Software processing can fall behind; bound latency by leaking and
+        // dropping late frames rather than queueing them
+        if self.backend_manager.backend_type() == crate::backend::BackendType::Standard {
+            let deadline = FrameDeadline::new(self.deadline.clone().unwrap_or(DeadlineConfig {
+                max_lateness_ms: config::FRAME_DEADLINE_MS,
+                ..Default::default()
+            }));
+            let queue = deadline.leaky_queue(Some("deadline-queue"))?;
+            // Frames the deadline queue leaks never reach any model; they
+            // are counted against the primary one
+            self.telemetry.attach_queue(PRIMARY_MODEL, &queue);
+            elements.push(queue);
+            self.frame_deadline = Some(deadline);
+        }
+
         // Skip inference for Standard backend since it's causing issues
         if self.backend_manager.backend_type() != crate::backend::BackendType::Standard {
             // Only add inference if backend supports it
             if caps.supports_inference {
-                let pgie = factory.create_inference(
-                    Some("primary-nvinference-engine"),
-                    config::PGIE_CONFIG_FILE,
-                )?;
-                elements.push(pgie);
-
-                let sgie1 = factory.create_inference(
-                    Some("secondary-nvinference-engine1"),
-                    config::SGIE1_CONFIG_FILE,
-                )?;
-                elements.push(sgie1);
-
-                let sgie2 = factory.create_inference(
-                    Some("secondary-nvinference-engine2"),
-                    config::SGIE2_CONFIG_FILE,
-                )?;
-                elements.push(sgie2);
-
-                let sgie3 = factory.create_inference(
-                    Some("secondary-nvinference-engine3"),
-                    config::SGIE3_CONFIG_FILE,
-                )?;
-                elements.push(sgie3);
+                let (primary, secondaries) = self.model_configs();
+                if let Some(path) = primary {
+                    let pgie = factory
+                        .create_inference(Some(PRIMARY_MODEL), &self.inference_config(&path))?;
+                    // A cpudetector sees one stream and can crop to its ROIs
+                    if let Some(filter) = self.single_source_filter()
+                        && pgie.find_property("roi").is_some()
+                    {
+                        filter
+                            .apply_to_element(&pgie)
+                            .map_err(|e| DeepStreamError::Configuration(e.to_string()))?;
+                    }
+                    self.telemetry.attach(&pgie.name(), &pgie);
+                    elements.push(pgie);
+                }
+                for (n, path) in secondaries.iter().enumerate() {
+                    let sgie = factory.create_inference(
+                        Some(&format!("secondary-nvinference-engine{}", n + 1)),
+                        &self.inference_config(path),
+                    )?;
+                    self.telemetry.attach(&sgie.name(), &sgie);
+                    elements.push(sgie);
+                }
             }
 
             // Only add tracker if backend supports it
-            if caps.supports_tracking {
+            let tracker_config = self.sections.as_ref().and_then(|c| c.tracker.as_ref());
+            if caps.supports_tracking && tracker_config.is_none_or(|tracker| tracker.enable) {
                 let tracker = factory.create_tracker(Some("nvtracker"))?;
-                // Only set tracker-config-file for DeepStream backend
+                // Only configure nvtracker for DeepStream backend
                 if self.backend_manager.backend_type() == crate::backend::BackendType::DeepStream {
-                    tracker
-                        .set_property_from_str("tracker-config-file", config::TRACKER_CONFIG_FILE);
+                    match tracker_config {
+                        Some(config) => {
+                            tracker.set_property("ll-lib-file", &config.ll_lib_file);
+                            tracker.set_property("ll-config-file", &config.ll_config_file);
+                            tracker.set_property("tracker-width", config.tracker_width);
+                            tracker.set_property("tracker-height", config.tracker_height);
+                        }
+                        None => tracker.set_property_from_str(
+                            "tracker-config-file",
+                            config::TRACKER_CONFIG_FILE,
+                        ),
+                    }
                 }
                 elements.push(tracker);
             }
         }
 
+        elements.extend(
+            self.hooks
+                .build(&HookContext::new(HookPoint::PostInference))?,
+        );
+
         // Add tiler for multi-source display
-        let tiler = factory.create_tiler(Some("nvtiler"))?;
-        if self.backend_manager.backend_type() == crate::backend::BackendType::DeepStream {
-            tiler.set_property("rows", config::TILER_ROWS as u32);
-            tiler.set_property("columns", config::TILER_COLUMNS as u32);
-            tiler.set_property("width", config::TILED_OUTPUT_WIDTH as u32);
-            tiler.set_property("height", config::TILED_OUTPUT_HEIGHT as u32);
+        let tiler_config = self.sections.as_ref().and_then(|c| c.tiler.as_ref());
+        if tiler_config.is_none_or(|tiler| tiler.enable) {
+            let tiler = factory.create_tiler(Some("nvtiler"))?;
+            if self.backend_manager.backend_type() == crate::backend::BackendType::DeepStream {
+                let (rows, columns, width, height) = tiler_config.map_or(
+                    (
+                        config::TILER_ROWS,
+                        config::TILER_COLUMNS,
+                        config::TILED_OUTPUT_WIDTH,
+                        config::TILED_OUTPUT_HEIGHT,
+                    ),
+                    |tiler| (tiler.rows, tiler.columns, tiler.width, tiler.height),
+                );
+                tiler.set_property("rows", rows);
+                tiler.set_property("columns", columns);
+                tiler.set_property("width", width);
+                tiler.set_property("height", height);
+            }
+            elements.push(tiler);
         }
-        elements.push(tiler);
 
         // Add conversion and output
         let convert = factory.create_video_convert(Some("nvvideo-converter"))?;
         elements.push(convert);
 
+        let osd_config = self.sections.as_ref().and_then(|c| c.osd.as_ref());
         if caps.supports_osd
             && self.backend_manager.backend_type() != crate::backend::BackendType::Standard
+            && osd_config.is_none_or(|osd| osd.enable)
         {
             let osd = factory.create_osd(Some("nv-onscreendisplay"))?;
             elements.push(osd);
         }
 
         let sink = factory.create_video_sink(Some("video-sink"))?;
-        sink.set_property("sync", false);
+        let sync = self
+            .sections
+            .as_ref()
+            .is_some_and(|config| config.sink.sync);
+        sink.set_property("sync", sync);
+        if let (Some(deadline), Some(pad)) = (&self.frame_deadline, sink.static_pad("sink")) {
+            deadline.attach(&pad)?;
+        }
+        if let (Some(rules), Some(pad)) = (&self.rules, sink.static_pad("sink")) {
+            RuleEngine::attach(rules, &pad, &self.inference_filters);
+        }
         // autovideosink doesn't have qos property
         if self.backend_manager.backend_type() == crate::backend::BackendType::DeepStream {
             sink.set_property("qos", false);
         }
+        elements.extend(self.hooks.build(&HookContext::new(HookPoint::PreSink))?);
+        let record_tee = match &self.recording {
+            Some(_) => {
+                let tee = gst::ElementFactory::make("tee")
+                    .name("record-tee")
+                    .build()?;
+                elements.push(tee.clone());
+                Some(tee)
+            }
+            None => None,
+        };
         elements.push(sink);
 
+        if let Some(placement) = &self.gpu_placement {
+            for element in &elements {
+                set_gpu_id(element, placement.pipeline_gpu());
+            }
+        }
+
         // Add all elements to pipeline
         for element in &elements {
             self.pipeline.add_element(element)?;
@@ -228,27 +379,481 @@ impl Application {
             elements[i].link(&elements[i + 1])?;
         }
 
+        if let (Some(config), Some(tee)) = (&self.recording, record_tee) {
+            let branches = BranchManager::new(self.pipeline.gst_pipeline(), tee)?;
+            let recorder = SegmentRecorder::new("recording", config.clone())?;
+            recorder.start(&branches)?;
+            self.recorder = Some((Arc::new(recorder), branches));
+        }
+
         // Create source controller with the streammux
         let pipeline_clone = self.pipeline.clone();
-        self.source_controller = Arc::new(Mutex::new(SourceController::with_max_sources(
-            pipeline_clone,
-            streammux,
-            config::MAX_NUM_SOURCES,
-        )));
+        let max_sources = self
+            .stress
+            .as_ref()
+            .map_or(self.demo.max_sources, |stress| stress.max_sources);
+        let mut controller =
+            SourceController::with_max_sources(pipeline_clone, streammux, max_sources);
+        controller.set_element_hooks(self.hooks.clone());
+        controller.set_uri_validator(UriValidator::strict());
+        controller.set_gpu_placement(self.gpu_placement.clone());
+        controller.set_decoder(
+            self.backend_manager
+                .element_override(DeepStreamElementType::Decoder),
+        );
+        controller.set_inference_filters(self.inference_filters.clone());
+        if let Some(credentials) = &self.credentials {
+            controller.credentials().extend(credentials);
+        }
+        if let Some(recovery) = &self.recovery {
+            controller.set_recovery_policies(recovery.clone());
+        }
+        if let Some(alignment) = &self.alignment {
+            controller.set_timestamp_alignment(alignment.clone());
+        }
+        // Without a policy batches grow up to the sources the demo adds
+        let batching = self.batching.clone().unwrap_or_else(|| BatchPolicy {
+            max_batch_size: max_sources as u32,
+            ..Default::default()
+        });
+        controller.enable_dynamic_batching(batching);
+        self.source_controller = Arc::new(Mutex::new(controller));
 
         Ok(())
     }
 
-    pub fn add_initial_source(&self) -> Result<()> {
-        let controller = self.source_controller.lock().unwrap();
-        let source_id = controller.add_source(&self.initial_uri)?;
+    /// Apply every section of `config`, e.g. one read with
+    /// [`ApplicationConfig::from_file`]: the muxer, model, tracker, tiler,
+    /// OSD and sink sections shape the pipeline and the others go through
+    /// the matching setters. `[tracking]` configures an
+    /// [`ObjectTracker`](crate::tracking::ObjectTracker) of your own; the
+    /// pipeline tracks with the backend's tracker element. Call before
+    /// [`init`](Self::init).
+    pub fn apply_config(&mut self, config: ApplicationConfig) -> Result<()> {
+        if let Some(demo) = config.demo.clone() {
+            self.set_demo(demo)?;
+        }
+        if let Some(gpu) = config.gpu.clone() {
+            self.set_gpu_placement(gpu)?;
+        }
+        self.set_element_overrides(&config.elements)?;
+        if let Some(rules) = config.rules.clone() {
+            self.set_rules(rules)?;
+        }
+        if let Some(recording) = config.recording.clone() {
+            self.set_recording(recording);
+        }
+        if let Some(alignment) = config.alignment.clone() {
+            self.set_timestamp_alignment(alignment);
+        }
+        if let Some(batching) = config.batching.clone() {
+            self.set_batch_policy(batching);
+        }
+        if let Some(deadline) = config.deadline.clone() {
+            self.set_deadline(deadline);
+        }
+        if let Some(recovery) = config.recovery.clone() {
+            self.set_recovery_policies(recovery);
+        }
+        if !config.credentials.is_empty() {
+            let credentials = CredentialStore::from_config(&config.credentials)?;
+            self.set_credentials(Arc::new(credentials));
+        }
+        self.sections = Some(config);
+        Ok(())
+    }
+
+    /// Config files of the primary and secondary models, from
+    /// [`ApplicationConfig::inference`] when given
+    fn model_configs(&self) -> (Option<String>, Vec<String>) {
+        let inference = self.sections.as_ref().and_then(|c| c.inference.as_ref());
+        let enabled = |gie: &GieConfig| gie.enable.then(|| gie.config_file.clone()).flatten();
+        let primary = match inference.and_then(|i| i.primary_gie.as_ref()) {
+            Some(gie) => enabled(gie),
+            None => Some(config::PGIE_CONFIG_FILE.to_string()),
+        };
+        let secondaries = match inference.and_then(|i| i.secondary_gies.as_ref()) {
+            Some(gies) => gies.iter().filter_map(enabled).collect(),
+            None => [
+                config::SGIE1_CONFIG_FILE,
+                config::SGIE2_CONFIG_FILE,
+                config::SGIE3_CONFIG_FILE,
+            ]
+            .map(String::from)
+            .to_vec(),
+        };
+        (primary, secondaries)
+    }
+
+    /// Filter of the only configured source, if it has one
+    fn single_source_filter(&self) -> Option<InferenceFilter> {
+        let mut sources = self
+            .sections
+            .as_ref()?
+            .sources
+            .iter()
+            .filter(|source| source.enable);
+        match (sources.next(), sources.next()) {
+            (Some(source), None) if source.num_sources <= 1 && !source.inference.is_empty() => {
+                Some(source.inference.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Replace the backend's elements by role, e.g. from
+    /// [`ApplicationConfig::elements`](crate::config::ApplicationConfig::elements);
+    /// call before [`init`](Self::init)
+    pub fn set_element_overrides(&self, overrides: &ElementOverrides) -> Result<()> {
+        self.backend_manager.set_element_overrides(overrides)
+    }
+
+    /// Spread the pipeline over several GPUs, e.g. from
+    /// [`ApplicationConfig::gpu`](crate::config::ApplicationConfig::gpu);
+    /// fails if `config` names a GPU that does not exist. Call before
+    /// [`init`](Self::init).
+    pub fn set_gpu_placement(&mut self, config: GpuConfig) -> Result<()> {
+        let placement = GpuPlacement::detect(config)?;
         println!(
-            "Added initial source: {} (ID: {:?})",
-            self.initial_uri, source_id
+            "[{:.3}] Pipeline on GPU {}, sources placed {:?} over {:?}",
+            now(),
+            placement.pipeline_gpu(),
+            placement.config().placement,
+            placement.gpus()
         );
+        self.gpu_placement = Some(Arc::new(placement));
+        Ok(())
+    }
+
+    /// Evaluate `config`'s rules on every frame reaching the sink, e.g.
+    /// from [`ApplicationConfig::rules`](crate::config::ApplicationConfig::rules);
+    /// call before [`init`](Self::init)
+    pub fn set_rules(&mut self, config: RulesConfig) -> Result<()> {
+        let engine = RuleEngine::new(config)
+            .map_err(|e| crate::error::DeepStreamError::Configuration(e.to_string()))?;
+        self.rules = Some(Arc::new(Mutex::new(engine)));
+        Ok(())
+    }
+
+    /// Record the processed stream in segments, uploading closed ones when
+    /// configured, e.g. from
+    /// [`ApplicationConfig::recording`](crate::config::ApplicationConfig::recording);
+    /// call before [`init`](Self::init)
+    pub fn set_recording(&mut self, config: RecordingConfig) {
+        self.recording = Some(config);
+    }
+
+    /// Align the timestamps of file and live sources before batching, e.g.
+    /// from [`ApplicationConfig::alignment`](crate::config::ApplicationConfig::alignment);
+    /// call before [`init`](Self::init)
+    pub fn set_timestamp_alignment(&mut self, alignment: TimestampAlignment) {
+        self.alignment = Some(alignment);
+    }
+
+    /// Size batches and their push timeout by `policy`, e.g. from
+    /// [`ApplicationConfig::batching`](crate::config::ApplicationConfig::batching),
+    /// instead of batching up to the demo's maximum of sources; call before
+    /// [`init`](Self::init)
+    pub fn set_batch_policy(&mut self, policy: BatchPolicy) {
+        self.batching = Some(policy);
+    }
+
+    /// Named credentials for `{cred:name}` source URIs, e.g. loaded with
+    /// [`CredentialStore::from_config`] from
+    /// [`ApplicationConfig::credentials`](crate::config::ApplicationConfig::credentials);
+    /// call before [`init`](Self::init)
+    pub fn set_credentials(&mut self, credentials: Arc<CredentialStore>) {
+        self.credentials = Some(credentials);
+    }
+
+    /// Lateness and leaky queue sizes for the Standard backend's frame
+    /// deadline, e.g. from
+    /// [`ApplicationConfig::deadline`](crate::config::ApplicationConfig::deadline),
+    /// instead of the default 200 ms; call before [`init`](Self::init)
+    pub fn set_deadline(&mut self, config: DeadlineConfig) {
+        self.deadline = Some(config);
+    }
+
+    /// Retry policies for sources by kind and URI, e.g. from
+    /// [`ApplicationConfig::recovery`](crate::config::ApplicationConfig::recovery);
+    /// call before [`init`](Self::init)
+    pub fn set_recovery_policies(&mut self, policies: RecoveryPolicies) {
+        self.recovery = Some(policies);
+    }
+
+    /// Give every nvinfer a cached TensorRT engine of its own instead of
+    /// letting it rebuild one; call before [`init`](Self::init)
+    pub fn set_engine_cache(&mut self, cache: EngineCache) {
+        self.engine_cache = Some(Arc::new(cache));
+    }
+
+    /// Config path for an nvinfer, pinned to its cached engine when there
+    /// is an engine cache
+    fn inference_config(&self, path: &str) -> String {
+        let Some(cache) = &self.engine_cache else {
+            return path.to_string();
+        };
+        match cache.pinned_config(Path::new(path), self.inference_gpu()) {
+            Ok(pinned) => pinned.to_string_lossy().into_owned(),
+            Err(e) => {
+                eprintln!("[{:.3}] Not caching the engine for {}: {}", now(), path, e);
+                path.to_string()
+            }
+        }
+    }
+
+    /// GPU the placement puts nvinfer on, over the `gpu-id` of its config
+    fn inference_gpu(&self) -> Option<u32> {
+        self.gpu_placement
+            .as_ref()
+            .map(|placement| placement.pipeline_gpu())
+    }
+
+    /// Build the TensorRT engines for the nvinfer configs at `configs` in
+    /// the background, one after another, so the pipeline does not stall
+    /// building them on its first start. The operation's progress is that
+    /// of the engine being built.
+    pub fn prebuild_engines(&self, configs: Vec<PathBuf>) -> Result<Operation> {
+        let cache = self.engine_cache.clone().ok_or_else(|| {
+            crate::error::DeepStreamError::NotInitialized("No engine cache set".to_string())
+        })?;
+        let gpu_id = self.inference_gpu();
+        Ok(self.operations.spawn("build-engines", move |op| {
+            let mut engines = Vec::new();
+            for config in &configs {
+                op.check_cancelled()?;
+                let engine = cache.build(config, gpu_id, op)?;
+                engines.push(engine.to_string_lossy().into_owned());
+            }
+            Ok(serde_json::json!({ "engines": engines }))
+        }))
+    }
+
+    /// Hooks for custom elements; register them before [`init`](Self::init)
+    pub fn element_hooks(&self) -> Arc<ElementHooks> {
+        self.hooks.clone()
+    }
+
+    /// Serve status endpoints on `address`: `/api/v1/capabilities`,
+    /// `/api/v1/pipeline`, `/api/v1/operations`, `/api/v1/metrics`
+    /// (per-model inference latency and frame drops; `/metrics` has the
+    /// same in Prometheus format) and, with an engine cache,
+    /// `/api/v1/engines` (`?build=<nvinfer config>` starts a build of one of
+    /// the configs in [`MODEL_CONFIGS`](config::MODEL_CONFIGS)). The
+    /// endpoints stop when the server is dropped.
+    pub fn serve_status(&self, address: &str) -> Result<StatusServer> {
+        let server = StatusServer::bind(address)?;
+        let backend_manager = self.backend_manager.clone();
+        server.route("/api/v1/capabilities", move |_| {
+            StatusResponse::json(&backend_manager.report())
+        });
+        let pipeline = self.pipeline.gst_pipeline().clone();
+        server.route("/api/v1/pipeline", move |request| {
+            let snapshot = introspect::snapshot(&pipeline);
+            if request.query.get("format").is_some_and(|f| f == "text") {
+                StatusResponse::text(200, &snapshot.to_string())
+            } else {
+                StatusResponse::json(&snapshot)
+            }
+        });
+        let operations = self.operations.clone();
+        server.route("/api/v1/operations", move |request| {
+            match request.query.get("cancel") {
+                Some(id) if operations.cancel(id) => StatusResponse::text(202, "cancelling"),
+                Some(id) => match operations.get(id) {
+                    Some(_) => StatusResponse::text(409, "operation already finished"),
+                    None => StatusResponse::not_found(),
+                },
+                None => match request.query.get("id") {
+                    Some(id) => operations
+                        .get(id)
+                        .map_or_else(StatusResponse::not_found, |op| {
+                            StatusResponse::json(&op.status())
+                        }),
+                    None => StatusResponse::json(&operations.list()),
+                },
+            }
+        });
+        let telemetry = self.telemetry.clone();
+        let deadline = self.frame_deadline.clone();
+        server.route("/api/v1/metrics", move |_| {
+            StatusResponse::json(&serde_json::json!({
+                "models": telemetry.snapshot(),
+                "deadline": deadline.as_ref().map(FrameDeadline::stats),
+            }))
+        });
+        let telemetry = self.telemetry.clone();
+        server.route("/metrics", move |_| {
+            StatusResponse::bytes(
+                "text/plain; version=0.0.4; charset=utf-8",
+                telemetry.to_prometheus().into_bytes(),
+            )
+        });
+        if let Some(cache) = self.engine_cache.clone() {
+            let operations = self.operations.clone();
+            let gpu_id = self.inference_gpu();
+            server.route("/api/v1/engines", move |request| {
+                let Some(config) = request.query.get("build") else {
+                    return StatusResponse::json(&cache.entries());
+                };
+                // Only the models the pipeline runs; the path is never
+                // handed to trtexec as given
+                let Some(config) = config::MODEL_CONFIGS.iter().find(|&&known| known == config)
+                else {
+                    return StatusResponse::text(400, "not a configured model");
+                };
+                let cache = cache.clone();
+                let config = PathBuf::from(config);
+                let op = operations.spawn("build-engines", move |op| {
+                    let engine = cache.build(&config, gpu_id, op)?;
+                    Ok(serde_json::json!({ "engines": [engine] }))
+                });
+                let mut response = StatusResponse::json(&op.status());
+                response.status = 202;
+                response
+            });
+        }
+        Ok(server)
+    }
+
+    /// Deterministic test mode, see [`ReplayMode`]; call before
+    /// [`init`](Self::init) and keep the handle while the pipeline runs
+    pub fn enable_replay(&self, config: ReplayConfig) -> ReplayMode {
+        self.pipeline.enable_replay(config)
+    }
+
+    /// Elements, pads, caps, queue levels and latency of the running
+    /// pipeline
+    pub fn snapshot(&self) -> PipelineSnapshot {
+        self.pipeline.snapshot()
+    }
+
+    /// Late-frame and queue drop counters, when the backend drops frames
+    pub fn frame_deadline_stats(&self) -> Option<DeadlineStats> {
+        self.frame_deadline.as_ref().map(FrameDeadline::stats)
+    }
+
+    /// Latency percentiles, queue wait and skipped frames of each inference
+    /// element, filled once [`init`](Self::init) has built the pipeline
+    pub fn inference_telemetry(&self) -> Arc<InferenceTelemetry> {
+        self.telemetry.clone()
+    }
+
+    /// Report inference statistics to `telemetry`, e.g. one shared with an
+    /// [`InferenceProcessor`](crate::inference::InferenceProcessor) or a
+    /// [`MultiStreamManager`](crate::multistream::MultiStreamManager); call
+    /// before [`init`](Self::init)
+    pub fn set_inference_telemetry(&mut self, telemetry: Arc<InferenceTelemetry>) {
+        self.telemetry = telemetry;
+    }
+
+    /// Per-stream EOS and error tracking fed from the pipeline bus
+    pub fn message_handler(&self) -> Arc<DSMessageHandler> {
+        self.messages.clone()
+    }
+
+    /// Token that stops the application when cancelled from another thread
+    pub fn shutdown_token(&self) -> ShutdownToken {
+        self.shutdown.token()
+    }
+
+    /// Long-running work started by the application, such as
+    /// [`add_sources_in_background`](Self::add_sources_in_background)
+    pub fn operations(&self) -> Arc<OperationRegistry> {
+        self.operations.clone()
+    }
+
+    /// Set the source timers, or turn them off with
+    /// [`DemoMode::Static`](config::DemoMode::Static); call before
+    /// [`init`](Self::init)
+    pub fn set_demo(&mut self, demo: config::DemoConfig) -> Result<()> {
+        demo.validate()?;
+        self.demo = demo;
         Ok(())
     }
 
+    /// Read keyboard commands from stdin while running, see
+    /// [`keyboard::HELP`]
+    pub fn set_keyboard_controls(&mut self, enabled: bool) {
+        self.keyboard = enabled;
+    }
+
+    /// Replace the periodic source timers with a stress run that adds and
+    /// removes sources continuously; call before [`init`](Self::init)
+    pub fn set_stress(&mut self, config: stress::StressConfig) {
+        self.stress = Some(config);
+    }
+
+    /// Add `uris` on a background thread; the returned operation reports
+    /// progress per source and removes the added ones when cancelled
+    pub fn add_sources_in_background(&self, uris: Vec<String>) -> Operation {
+        let controller = self.source_controller.clone();
+        self.operations.spawn("add-sources", move |op| {
+            let ids = controller
+                .lock()
+                .unwrap()
+                .add_sources_operation(&uris, op)?;
+            Ok(serde_json::json!({
+                "sources": ids.iter().map(|id| id.0).collect::<Vec<_>>(),
+            }))
+        })
+    }
+
+    /// Add the sources given at startup, then the enabled `[[sources]]` of
+    /// the config given to [`apply_config`](Self::apply_config); the ones
+    /// that fail are skipped as long as any could be added
+    pub fn add_initial_sources(&self) -> Result<Vec<SourceId>> {
+        let controller = self.source_controller.lock().unwrap();
+        let mut ids = Vec::new();
+        let mut last_error = None;
+        for source in &self.initial_sources {
+            match source.add_to(&controller) {
+                Ok(id) => {
+                    println!(
+                        "Added initial source: {} (ID: {:?}, priority: {:?})",
+                        redact(&source.uri),
+                        id,
+                        source.priority
+                    );
+                    ids.push(id);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Failed to add initial source {}: {:?}",
+                        redact(&source.uri),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        let configured = self.sections.iter().flat_map(|config| &config.sources);
+        for source in configured.filter(|source| source.enable) {
+            match controller.add_configured_sources(source) {
+                Ok(added) => {
+                    println!(
+                        "Added configured source: {} (IDs: {:?})",
+                        redact(&source.uri),
+                        added
+                    );
+                    ids.extend(added);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Failed to add configured source {}: {:?}",
+                        redact(&source.uri),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if ids.is_empty() => Err(e),
+            _ => Ok(ids),
+        }
+    }
+
     pub fn run_with_glib_signals(&mut self) -> Result<()> {
         println!("Starting pipeline...");
 
@@ -259,10 +864,39 @@ impl Application {
         // Get the bus for message handling
         let bus = self.pipeline.bus().unwrap();
 
+        // Track every source's end of stream, including sources added later
+        let messages = self.messages.clone();
+        let source_events = self.source_controller.lock().unwrap().get_event_handler();
+        if let Ok(sources) = self.source_controller.lock().unwrap().list_active_sources() {
+            for (id, _, _) in sources {
+                messages.eos_tracker().register_stream(id.0 as u32);
+            }
+        }
+        let tracker = messages.eos_tracker().clone();
+        source_events.register_callback(move |event| match event {
+            SourceEvent::SourceAdded { id, .. } => tracker.register_stream(id.0 as u32),
+            SourceEvent::SourceRemoved { id } => tracker.unregister_stream(id.0 as u32),
+            _ => {}
+        });
+
+        // Source errors, warnings and state changes become source events
+        let router = MessageRouter::new();
+        router.on_all_sources(source_events);
+        router.on_pipeline(messages);
+        let recorder = self.recorder.as_ref().map(|(recorder, _)| recorder.clone());
+
         // Add bus watch for GStreamer messages
-        let _bus_watch = bus.add_watch(move |_, msg| {
+        let _bus_watch = bus.add_watch(move |bus, msg| {
             use gst::MessageView;
 
+            router.route(bus, msg);
+            if let Some(recorder) = &recorder
+                && recorder.handle_message(msg).is_some()
+            {
+                return glib::ControlFlow::Continue;
+            }
+            let stream = DSMessageHandler::stream_id(msg);
+
             let now = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default();
@@ -274,6 +908,13 @@ impl Application {
                     main_loop_quit.quit();
                     glib::ControlFlow::Break
                 }
+                // A failing source is handed to its recovery policy, the
+                // rest of the pipeline keeps running
+                MessageView::Error(err) if stream.is_some() => {
+                    let id = SourceId(stream.unwrap_or_default() as usize);
+                    eprintln!("[{}] Source {} error: {}", timestamp, id, err.error());
+                    glib::ControlFlow::Continue
+                }
                 MessageView::Error(err) => {
                     eprintln!("[{}] Error: {}", timestamp, err.error());
                     if let Some(debug) = err.debug() {
@@ -322,6 +963,12 @@ impl Application {
                     );
                     glib::ControlFlow::Continue
                 }
+                MessageView::Element(_) if DSMessageHandler::is_stream_eos(msg) => {
+                    if let Ok(id) = DSMessageHandler::parse_stream_eos(msg) {
+                        println!("[{}] Source {} reached end of stream", timestamp, id);
+                    }
+                    glib::ControlFlow::Continue
+                }
                 MessageView::Element(element) => {
                     println!(
                         "[{}] Element message from {}: {:?}",
@@ -341,31 +988,44 @@ impl Application {
         // Add SIGINT handler using GLib's signal handling
         #[cfg(unix)]
         {
-            let main_loop_signal = main_loop.clone();
-            // SIGINT is typically signal number 2 on Unix systems
-            let _signal_handler = glib::unix_signal_add(
-                2, // SIGINT signal number
-                move || {
-                    println!("\nReceived interrupt signal, shutting down...");
-                    main_loop_signal.quit();
+            // SIGINT (2) and SIGTERM (15) both request a coordinated shutdown
+            for signum in [2, 15] {
+                let token = self.shutdown.token();
+                let _signal_handler = glib::unix_signal_add(signum, move || {
+                    println!("\nReceived signal {}, shutting down...", signum);
+                    token.cancel();
                     glib::ControlFlow::Break
-                },
-            );
+                });
+            }
         }
 
         // On Windows, we'll still use ctrlc as glib unix signals don't work
         #[cfg(windows)]
         {
-            let main_loop_ctrlc = main_loop.clone();
+            let token = self.shutdown.token();
             ctrlc::set_handler(move || {
                 println!("\nReceived interrupt signal, shutting down...");
-                main_loop_ctrlc.quit();
+                token.cancel();
             })
             .expect("Error setting Ctrl+C handler");
         }
 
-        // Add initial source BEFORE changing pipeline state
-        self.add_initial_source()?;
+        // Quit the main loop once shutdown is requested from anywhere
+        {
+            let token = self.shutdown.token();
+            let main_loop_shutdown = main_loop.clone();
+            glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+                if token.is_cancelled() {
+                    main_loop_shutdown.quit();
+                    glib::ControlFlow::Break
+                } else {
+                    glib::ControlFlow::Continue
+                }
+            });
+        }
+
+        // Add initial sources BEFORE changing pipeline state
+        self.add_initial_sources()?;
 
         // Now set pipeline to PAUSED state
         println!("[{:.3}] Setting pipeline to PAUSED state...", now());
@@ -457,41 +1117,122 @@ impl Application {
             }
         }
 
-        println!("[{:.3}] Now playing: {}", now(), self.initial_uri);
+        for source in &self.initial_sources {
+            println!("[{:.3}] Now playing: {}", now(), redact(&source.uri));
+        }
         println!("[{:.3}] Pipeline running... Press Ctrl+C to exit", now());
 
-        // Start the source addition timer
+        if self.keyboard {
+            let commands = keyboard::spawn_stdin_reader();
+            let mut controls = keyboard::KeyboardControls::new(
+                self.source_controller.clone(),
+                self.pipeline.clone(),
+                self.initial_uri.clone(),
+                self.shutdown.token(),
+            );
+            println!("[{:.3}] {}", now(), keyboard::HELP);
+            glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+                while let Ok(command) = commands.try_recv() {
+                    controls.handle(command);
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        let stress_state = self.stress.clone().map(|config| {
+            std::rc::Rc::new(std::cell::RefCell::new(stress::StressState::new(
+                config,
+                self.source_controller.clone(),
+                self.pipeline.clone(),
+                self.initial_uri.clone(),
+                main_loop.clone(),
+            )))
+        });
+        if let Some(state) = &stress_state {
+            let config = state.borrow().config.clone();
+            println!(
+                "[{:.3}] Starting {} stress run (interval: {:?}, max sources: {})",
+                now(),
+                config.mode,
+                config.interval,
+                config.max_sources
+            );
+            let state = state.clone();
+            glib::timeout_add_local(config.interval, move || {
+                stress::stress_callback(state.clone())
+            });
+        } else if self.demo.mode == config::DemoMode::Cycle {
+            self.start_source_timers(&main_loop);
+        } else {
+            println!(
+                "[{:.3}] Static mode: sources are not added or removed",
+                now()
+            );
+        }
+
+        // Run the main loop - this will block until main_loop.quit() is called
+        main_loop.run();
+
+        println!("Shutting down pipeline...");
+        self.cleanup()?;
+
+        if let Some(state) = stress_state {
+            let report = state.borrow().report.clone();
+            println!("Stress run: {}", report);
+            if let Some(failure) = report.failure {
+                return Err(crate::error::DeepStreamError::Pipeline(format!(
+                    "Stress run failed after {} operations: {}",
+                    report.operations(),
+                    failure
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add sources periodically up to the maximum, then remove them again
+    fn start_source_timers(&self, main_loop: &glib::MainLoop) {
         let timer_state = std::rc::Rc::new(std::cell::RefCell::new(timers::TimerState::new(
             self.source_controller.clone(),
             self.initial_uri.clone(),
             main_loop.clone(),
+            self.demo.clone(),
         )));
 
         println!(
             "[{:.3}] Starting source addition timer (interval: {} seconds)",
             now(),
-            config::SOURCE_ADD_INTERVAL_SECS
+            self.demo.add_interval_secs
         );
 
-        glib::timeout_add_seconds_local(config::SOURCE_ADD_INTERVAL_SECS as u32, move || {
+        glib::timeout_add_seconds_local(self.demo.add_interval_secs as u32, move || {
             timers::add_sources_callback(timer_state.clone())
         });
-
-        // Run the main loop - this will block until main_loop.quit() is called
-        main_loop.run();
-
-        println!("Shutting down pipeline...");
-        self.cleanup()?;
-        Ok(())
     }
 
     fn cleanup(&self) -> Result<()> {
         println!("Returned, stopping playback");
-        self.pipeline.set_state(gst::State::Null)?;
 
-        println!("Deleting pipeline");
-        let controller = self.source_controller.lock().unwrap();
-        controller.remove_all_sources()?;
+        // Registered in reverse stop order: drain the pipeline, then remove sources
+        let controller = self.source_controller.clone();
+        self.shutdown.register("sources", move || {
+            println!("Deleting pipeline");
+            controller.lock().unwrap().remove_all_sources()
+        });
+        self.shutdown
+            .register_pipeline("pipeline", self.pipeline.clone());
+
+        let report = self.shutdown.shutdown();
+        for (component, reason) in &report.failed {
+            eprintln!("Component '{}' failed to stop: {}", component, reason);
+        }
+
+        if !report.is_clean() {
+            return Err(crate::error::DeepStreamError::Pipeline(format!(
+                "{} component(s) failed to stop",
+                report.failed.len()
+            )));
+        }
 
         println!("Cleanup complete");
         Ok(())
@@ -0,0 +1,127 @@
+//! Sources the demo starts with, from the command line or a sources file
+//!
+//! A sources file lists one `[[source]]` table per camera:
+//!
+//! ```toml
+//! [[source]]
+//! uri = "rtsp://gate.local/stream"
+//! priority = "High"
+//! eos = "loop"
+//!
+//! [[source]]
+//! uri = "file:///recordings/lobby.mp4"
+//! recovery = { max_retries = 3 }
+//! ```
+
+use crate::error::{DeepStreamError, Result};
+use crate::multistream::StreamPriority;
+use crate::source::{EosPolicy, RecoveryPolicy, SourceController, SourceId};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InitialSource {
+    pub uri: String,
+
+    /// Higher priority sources are added first, so they get in when there
+    /// are more sources than room
+    #[serde(default)]
+    pub priority: StreamPriority,
+
+    /// What happens at end of stream; the controller's default when unset
+    #[serde(default)]
+    pub eos: Option<EosPolicy>,
+
+    /// Retry settings; the policy for the URI's kind when unset
+    #[serde(default)]
+    pub recovery: Option<RecoveryPolicy>,
+}
+
+impl InitialSource {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            priority: StreamPriority::default(),
+            eos: None,
+            recovery: None,
+        }
+    }
+
+    /// Add the source to `controller` with its options, which apply from
+    /// its first buffer
+    pub fn add_to(&self, controller: &SourceController) -> Result<SourceId> {
+        let policy = self
+            .recovery
+            .clone()
+            .unwrap_or_else(|| controller.recovery_policies().for_uri(&self.uri).clone());
+        match self.eos {
+            Some(eos) => controller.add_source_with_policies(&self.uri, policy, eos),
+            None => controller.add_source_with_policy(&self.uri, policy),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SourcesFile {
+    #[serde(default)]
+    source: Vec<InitialSource>,
+}
+
+/// Read the `[[source]]` tables of a sources file
+pub fn load_sources_file(path: &Path) -> Result<Vec<InitialSource>> {
+    let contents = fs::read_to_string(path)?;
+    parse_sources(&contents)
+}
+
+fn parse_sources(contents: &str) -> Result<Vec<InitialSource>> {
+    let file: SourcesFile = toml::from_str(contents)?;
+    if file.source.is_empty() {
+        return Err(DeepStreamError::Configuration(
+            "sources file lists no [[source]]".to_string(),
+        ));
+    }
+    Ok(file.source)
+}
+
+/// Highest priority first, in the given order within a priority
+pub fn by_priority(mut sources: Vec<InitialSource>) -> Vec<InitialSource> {
+    sources.sort_by(|a, b| b.priority.cmp(&a.priority));
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &str = r#"
+        [[source]]
+        uri = "file:///lobby.mp4"
+
+        [[source]]
+        uri = "rtsp://gate.local/stream"
+        priority = "High"
+        eos = "loop"
+        recovery = { max_retries = 3 }
+        "#;
+
+    #[test]
+    fn test_parse_sources_file() {
+        let sources = parse_sources(SOURCES).unwrap();
+        assert_eq!(sources[0], InitialSource::new("file:///lobby.mp4"));
+        assert_eq!(sources[1].eos, Some(EosPolicy::Loop));
+        assert_eq!(sources[1].recovery.as_ref().unwrap().max_retries, Some(3));
+    }
+
+    #[test]
+    fn test_by_priority() {
+        let ordered = by_priority(parse_sources(SOURCES).unwrap());
+        assert_eq!(ordered[0].uri, "rtsp://gate.local/stream");
+        assert_eq!(ordered[0].priority, StreamPriority::High);
+    }
+
+    #[test]
+    fn test_empty_sources_file() {
+        assert!(parse_sources("").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Stress mode for the demo: sources are added and removed continuously
+//! and the pipeline is checked after every operation, to reproduce races
+//! in runtime source management
+
+use crate::pipeline::Pipeline;
+use crate::source::{SourceController, SourceId, SourceState};
+use gstreamer as gst;
+use gstreamer::glib;
+use rand::Rng;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a health check waits for a pending state change
+const STATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What the stress mode exercises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StressMode {
+    /// Add or remove one source per tick
+    AddRemove,
+}
+
+impl FromStr for StressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "add-remove" => Ok(StressMode::AddRemove),
+            other => Err(format!(
+                "Unknown stress mode '{}', expected add-remove",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StressMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StressMode::AddRemove => write!(f, "add-remove"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StressConfig {
+    pub mode: StressMode,
+    /// Time between operations
+    pub interval: Duration,
+    /// Sources active at most
+    pub max_sources: usize,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            mode: StressMode::AddRemove,
+            interval: Duration::from_millis(500),
+            max_sources: 30,
+        }
+    }
+}
+
+/// What a stress run did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StressReport {
+    pub added: u64,
+    pub removed: u64,
+    /// Operations the controller refused; the run goes on
+    pub errors: u64,
+    /// The health check that stopped the run, if one failed
+    pub failure: Option<String>,
+}
+
+impl StressReport {
+    pub fn operations(&self) -> u64 {
+        self.added + self.removed
+    }
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} operations ({} added, {} removed, {} refused)",
+            self.operations(),
+            self.added,
+            self.removed,
+            self.errors
+        )?;
+        if let Some(failure) = &self.failure {
+            write!(f, ", failed: {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Add,
+    Remove,
+}
+
+/// Add while empty, remove while full, otherwise either
+fn next_step(active: usize, max_sources: usize, rng: &mut impl Rng) -> Step {
+    if active == 0 {
+        Step::Add
+    } else if active >= max_sources || rng.gen_bool(0.5) {
+        Step::Remove
+    } else {
+        Step::Add
+    }
+}
+
+/// State shared by the stress timer
+pub struct StressState {
+    pub config: StressConfig,
+    pub report: StressReport,
+    source_controller: Arc<Mutex<SourceController>>,
+    pipeline: Arc<Pipeline>,
+    uri: String,
+    /// Sources the stress run expects to be active
+    sources: Vec<SourceId>,
+    main_loop: glib::MainLoop,
+}
+
+impl StressState {
+    pub fn new(
+        config: StressConfig,
+        source_controller: Arc<Mutex<SourceController>>,
+        pipeline: Arc<Pipeline>,
+        uri: String,
+        main_loop: glib::MainLoop,
+    ) -> Self {
+        let sources = source_controller
+            .lock()
+            .unwrap()
+            .list_active_sources()
+            .map(|sources| sources.into_iter().map(|(id, _, _)| id).collect())
+            .unwrap_or_default();
+
+        Self {
+            config,
+            report: StressReport::default(),
+            source_controller,
+            pipeline,
+            uri,
+            sources,
+            main_loop,
+        }
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        let controller = self.source_controller.lock().unwrap();
+
+        // Sources that finished are not what we are after
+        if let Ok(removed) = controller.handle_eos_sources() {
+            self.sources.retain(|id| !removed.contains(id));
+        }
+
+        let mut rng = rand::thread_rng();
+        let step = next_step(self.sources.len(), self.config.max_sources, &mut rng);
+        let timestamp = crate::timestamp();
+        let result = match step {
+            Step::Add => controller.add_source(&self.uri).map(|id| {
+                self.sources.push(id);
+                self.report.added += 1;
+                println!(
+                    "[{:.3}] Stress: added source {} (active: {})",
+                    timestamp,
+                    id,
+                    self.sources.len()
+                );
+            }),
+            Step::Remove => {
+                let id = self
+                    .sources
+                    .swap_remove(rng.gen_range(0..self.sources.len()));
+                controller.remove_source(id).map(|_| {
+                    self.report.removed += 1;
+                    println!(
+                        "[{:.3}] Stress: removed source {} (active: {})",
+                        timestamp,
+                        id,
+                        self.sources.len()
+                    );
+                })
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("[{:.3}] Stress: {:?} failed: {:?}", timestamp, step, e);
+            self.report.errors += 1;
+            // A refused removal leaves the source in place
+            if let Ok(active) = controller.list_active_sources() {
+                self.sources = active.into_iter().map(|(id, _, _)| id).collect();
+            }
+        }
+        drop(controller);
+
+        self.check_health()
+    }
+
+    /// The pipeline is playing and runs exactly the sources we expect
+    fn check_health(&self) -> Result<(), String> {
+        match self.pipeline.get_state(Some(STATE_TIMEOUT)) {
+            Ok((_, gst::State::Playing, _)) => {}
+            Ok((_, current, pending)) => {
+                return Err(format!(
+                    "pipeline is {:?} (pending {:?}), expected Playing",
+                    current, pending
+                ));
+            }
+            Err(e) => return Err(format!("pipeline state change failed: {:?}", e)),
+        }
+
+        let active = self
+            .source_controller
+            .lock()
+            .unwrap()
+            .list_active_sources()
+            .map_err(|e| format!("cannot list sources: {:?}", e))?;
+        if let Some((id, _, SourceState::Error(reason))) = active
+            .iter()
+            .find(|(_, _, state)| matches!(state, SourceState::Error(_)))
+        {
+            return Err(format!("source {} failed: {}", id, reason));
+        }
+        if let Some(id) = self
+            .sources
+            .iter()
+            .find(|id| !active.iter().any(|(active, _, _)| active == *id))
+        {
+            return Err(format!("source {} is missing", id));
+        }
+        if active.len() != self.sources.len() {
+            return Err(format!(
+                "{} sources active, expected {}",
+                active.len(),
+                self.sources.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Timer callback running one stress operation; stops the main loop on
+/// the first failed health check
+pub fn stress_callback(state: Rc<RefCell<StressState>>) -> glib::ControlFlow {
+    let mut state = state.borrow_mut();
+    match state.step() {
+        Ok(()) => glib::ControlFlow::Continue,
+        Err(failure) => {
+            eprintln!(
+                "[{:.3}] Stress: health check failed after {} operations: {}",
+                crate::timestamp(),
+                state.report.operations(),
+                failure
+            );
+            state.report.failure = Some(failure);
+            state.main_loop.quit();
+            glib::ControlFlow::Break
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("add-remove".parse(), Ok(StressMode::AddRemove));
+        assert!("remove-add".parse::<StressMode>().is_err());
+        assert_eq!(StressMode::AddRemove.to_string(), "add-remove");
+    }
+
+    #[test]
+    fn test_steps_respect_limits() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            assert_eq!(next_step(0, 30, &mut rng), Step::Add);
+            assert_eq!(next_step(30, 30, &mut rng), Step::Remove);
+        }
+    }
+
+    #[test]
+    fn test_report_operations() {
+        let report = StressReport {
+            added: 3,
+            removed: 2,
+            ..Default::default()
+        };
+        assert_eq!(report.operations(), 5);
+    }
+}
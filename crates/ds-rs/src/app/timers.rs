@@ -1,4 +1,4 @@
-use super::config;
+use super::config::DemoConfig;
 use crate::source::SourceController;
 use gstreamer::glib;
 use rand::Rng;
@@ -13,6 +13,7 @@ pub struct TimerState {
     pub num_sources: usize,
     pub enabled_sources: Vec<bool>,
     pub main_loop: glib::MainLoop,
+    pub demo: DemoConfig,
 }
 
 impl TimerState {
@@ -20,16 +21,24 @@ impl TimerState {
         source_controller: Arc<Mutex<SourceController>>,
         initial_uri: String,
         main_loop: glib::MainLoop,
+        demo: DemoConfig,
     ) -> Self {
-        let mut enabled_sources = vec![false; config::MAX_NUM_SOURCES];
-        enabled_sources[0] = true; // Initial source is already added
+        // The initial sources are already added
+        let num_sources = source_controller
+            .lock()
+            .unwrap()
+            .list_active_sources()
+            .map_or(1, |sources| sources.len());
+        let mut enabled_sources = vec![false; demo.max_sources.max(num_sources)];
+        enabled_sources[..num_sources].fill(true);
 
         Self {
             source_controller,
             initial_uri,
-            num_sources: 1, // Start with 1 because initial source is already added
+            num_sources,
             enabled_sources,
             main_loop,
+            demo,
         }
     }
 }
@@ -42,7 +51,7 @@ pub fn add_sources_callback(state: Rc<RefCell<TimerState>>) -> glib::ControlFlow
 
     // Find an available slot
     let mut source_id = None;
-    for i in 0..config::MAX_NUM_SOURCES {
+    for i in 0..state_borrow.demo.max_sources {
         if !state_borrow.enabled_sources[i] {
             source_id = Some(i);
             break;
@@ -66,25 +75,6 @@ pub fn add_sources_callback(state: Rc<RefCell<TimerState>>) -> glib::ControlFlow
                     "[{:.3}] Added source {} at slot {} (total: {})",
                     timestamp, id, slot, state_borrow.num_sources
                 );
-
-                // Check if we've reached the maximum
-                if state_borrow.num_sources >= config::MAX_NUM_SOURCES {
-                    println!(
-                        "[{:.3}] Reached MAX_NUM_SOURCES ({}), starting deletion timer",
-                        timestamp,
-                        config::MAX_NUM_SOURCES
-                    );
-
-                    // Start the deletion timer
-                    let state_clone = state.clone();
-                    glib::timeout_add_seconds_local(
-                        config::SOURCE_DELETE_INTERVAL_SECS as u32,
-                        move || delete_sources_callback(state_clone.clone()),
-                    );
-
-                    // Stop the addition timer
-                    return glib::ControlFlow::Break;
-                }
             }
             Err(e) => {
                 eprintln!("[{:.3}] Failed to add source: {:?}", timestamp, e);
@@ -92,6 +82,23 @@ pub fn add_sources_callback(state: Rc<RefCell<TimerState>>) -> glib::ControlFlow
         }
     }
 
+    // Check if we've reached the maximum, possibly with the initial sources
+    if state_borrow.num_sources >= state_borrow.demo.max_sources {
+        println!(
+            "[{:.3}] Reached max sources ({}), starting deletion timer",
+            timestamp, state_borrow.demo.max_sources
+        );
+
+        // Start the deletion timer
+        let state_clone = state.clone();
+        glib::timeout_add_seconds_local(state_borrow.demo.delete_interval_secs as u32, move || {
+            delete_sources_callback(state_clone.clone())
+        });
+
+        // Stop the addition timer
+        return glib::ControlFlow::Break;
+    }
+
     glib::ControlFlow::Continue
 }
 
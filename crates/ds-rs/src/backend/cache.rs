@@ -0,0 +1,204 @@
+//! Startup detection results kept between runs
+//!
+//! Detecting the platform shells out to `nvcc` and picking a backend probes
+//! the plugin registry, which adds up on every start. The outcome is saved
+//! as JSON in the user cache directory together with a fingerprint of the
+//! loaded GStreamer registry and of the environment the platform checks
+//! read. A later run whose fingerprint matches reuses the result; any
+//! plugin added, removed or upgraded changes the fingerprint.
+//!
+//! `DS_RS_CACHE_DIR` moves the cache, `DS_RS_NO_CACHE` turns it off and
+//! `DS_RS_STARTUP_BUDGET_MS` sets how long detection may take before a
+//! warning is logged.
+
+use super::BackendType;
+use crate::platform::PlatformInfo;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const CACHE_FILE: &str = "backend-detection.json";
+const DEFAULT_BUDGET: Duration = Duration::from_millis(500);
+
+/// Environment the platform detection depends on
+const PLATFORM_ENV: &[&str] = &["CUDA_VER", "GPU_ID", "FORCE_BACKEND"];
+const PLATFORM_PATHS: &[&str] = &[
+    "/usr/local/cuda",
+    "/opt/nvidia/deepstream",
+    "/etc/nv_tegra_release",
+    "/dev/nvidia0",
+];
+
+/// Element lookups made in this process, seeded from the saved cache
+static ELEMENTS: Lazy<Mutex<HashMap<String, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct StartupOptions {
+    /// Read and write the cache file
+    pub enabled: bool,
+    pub dir: Option<PathBuf>,
+    /// Log a warning when detection takes longer
+    pub budget: Duration,
+}
+
+impl StartupOptions {
+    pub fn from_env() -> Self {
+        let budget = std::env::var("DS_RS_STARTUP_BUDGET_MS")
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .map_or(DEFAULT_BUDGET, Duration::from_millis);
+        Self {
+            enabled: std::env::var_os("DS_RS_NO_CACHE").is_none(),
+            dir: cache_dir(),
+            budget,
+        }
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(CACHE_FILE))
+    }
+}
+
+/// `DS_RS_CACHE_DIR`, or `ds-rs` in the platform's cache directory
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("DS_RS_CACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(base.join("ds-rs"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedDetection {
+    pub fingerprint: String,
+    pub platform: PlatformInfo,
+    pub backend: BackendType,
+    /// Element factories probed while detecting, and whether they exist
+    pub elements: BTreeMap<String, bool>,
+}
+
+impl CachedDetection {
+    /// The saved detection, if there is one for `fingerprint`
+    pub fn load(path: &Path, fingerprint: &str) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let cached: Self = serde_json::from_str(&text)
+            .inspect_err(|e| log::debug!("Ignoring unreadable {}: {}", path.display(), e))
+            .ok()?;
+        (cached.fingerprint == fingerprint).then_some(cached)
+    }
+
+    pub fn store(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write then rename so a concurrent start never reads half a file
+        let partial = path.with_extension("json.tmp");
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(partial, path)
+    }
+}
+
+/// Hash of the loaded plugins and the platform inputs. Call after
+/// `gst::init`, which brings the registry up to date.
+pub fn fingerprint() -> String {
+    let mut plugins: Vec<(String, String, String)> = gst::Registry::get()
+        .plugins()
+        .into_iter()
+        .map(|plugin| {
+            (
+                plugin.plugin_name().to_string(),
+                plugin.version().to_string(),
+                plugin
+                    .filename()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    plugins.sort();
+
+    let environment: Vec<(&str, Option<String>)> = PLATFORM_ENV
+        .iter()
+        .map(|var| (*var, std::env::var(var).ok()))
+        .collect();
+    let paths: Vec<bool> = PLATFORM_PATHS
+        .iter()
+        .map(|path| Path::new(path).exists())
+        .collect();
+
+    let mut hasher = DefaultHasher::new();
+    gst::version_string().as_str().hash(&mut hasher);
+    plugins.hash(&mut hasher);
+    environment.hash(&mut hasher);
+    paths.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Whether an element factory exists, looked up once per process
+pub fn element_available(name: &str) -> bool {
+    if let Some(&known) = ELEMENTS.lock().unwrap().get(name) {
+        return known;
+    }
+    let found = gst::ElementFactory::find(name).is_some();
+    ELEMENTS.lock().unwrap().insert(name.to_string(), found);
+    found
+}
+
+pub(crate) fn seed_elements(elements: &BTreeMap<String, bool>) {
+    let mut known = ELEMENTS.lock().unwrap();
+    for (name, found) in elements {
+        known.insert(name.clone(), *found);
+    }
+}
+
+pub(crate) fn probed_elements() -> BTreeMap<String, bool> {
+    ELEMENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, found)| (name.clone(), *found))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(CACHE_FILE);
+        let cached = CachedDetection {
+            fingerprint: "abc".to_string(),
+            platform: PlatformInfo {
+                platform: Platform::X86,
+                cuda_version: None,
+                gpu_id: None,
+                compute_capability: Some("7.5".to_string()),
+            },
+            backend: BackendType::Standard,
+            elements: BTreeMap::from([("compositor".to_string(), true)]),
+        };
+        cached.store(&path).unwrap();
+
+        let loaded = CachedDetection::load(&path, "abc").unwrap();
+        assert_eq!(loaded.backend, BackendType::Standard);
+        assert!(loaded.elements["compositor"]);
+        // A changed registry or environment means detecting again
+        assert!(CachedDetection::load(&path, "def").is_none());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(CachedDetection::load(&path, "abc").is_none());
+    }
+}
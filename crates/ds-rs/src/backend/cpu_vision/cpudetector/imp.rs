@@ -1,5 +1,6 @@
 #![allow(unused)]
 use crate::error::Result;
+use crate::inference::{InferenceFilter, RegionOfInterest};
 use gstcpuinfer::detector::{DetectorConfig, OnnxDetector};
 use gstreamer as gst;
 use gstreamer::glib;
@@ -36,6 +37,7 @@ struct Settings {
     input_width: u32,
     input_height: u32,
     process_every_n_frames: u32,
+    filter: InferenceFilter,
 }
 
 impl Default for Settings {
@@ -47,6 +49,7 @@ impl Default for Settings {
             input_width: DEFAULT_INPUT_WIDTH,
             input_height: DEFAULT_INPUT_HEIGHT,
             process_every_n_frames: DEFAULT_PROCESS_EVERY_N_FRAMES,
+            filter: InferenceFilter::default(),
         }
     }
 }
@@ -148,6 +151,35 @@ impl CpuDetector {
         }
     }
 
+    /// Run the detector on each ROI crop, map boxes back to frame
+    /// coordinates and drop classes outside the allow-list
+    fn detect_filtered(
+        &self,
+        detector: &OnnxDetector,
+        image: &DynamicImage,
+        filter: &InferenceFilter,
+    ) -> std::result::Result<Vec<gstcpuinfer::detector::Detection>, String> {
+        let (width, height) = (image.width(), image.height());
+        let mut detections = Vec::new();
+
+        for roi in filter.regions(width, height) {
+            let mut found = if roi.is_full_frame(width, height) {
+                detector.detect(image).map_err(|e| e.to_string())?
+            } else {
+                let crop = image.crop_imm(roi.x, roi.y, roi.width, roi.height);
+                detector.detect(&crop).map_err(|e| e.to_string())?
+            };
+
+            for detection in &mut found {
+                (detection.x, detection.y) = roi.to_frame(detection.x, detection.y);
+            }
+            detections.extend(found);
+        }
+
+        detections.retain(|d| filter.allows(d.class_id, &d.class_name));
+        Ok(detections)
+    }
+
     fn emit_inference_results(
         &self,
         frame_num: u64,
@@ -289,6 +321,16 @@ impl ObjectImpl for CpuDetector {
                     .default_value(DEFAULT_INPUT_HEIGHT)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecString::builder("roi")
+                    .nick("Regions of Interest")
+                    .blurb("Regions to run inference on as x,y,w,h;x,y,w,h (empty = whole frame)")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("allowed-classes")
+                    .nick("Allowed Classes")
+                    .blurb("Comma-separated class names or IDs to keep (empty = all)")
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecUInt::builder("process-every-n-frames")
                     .nick("Process Every N Frames")
                     .blurb("Process every Nth frame (1 = every frame)")
@@ -340,6 +382,18 @@ impl ObjectImpl for CpuDetector {
             "process-every-n-frames" => {
                 settings.process_every_n_frames = value.get().expect("type checked upstream");
             }
+            "roi" => {
+                let rois: Option<String> = value.get().expect("type checked upstream");
+                match RegionOfInterest::parse_list(rois.as_deref().unwrap_or("")) {
+                    Ok(rois) => settings.filter.rois = rois,
+                    Err(e) => gst::warning!(CAT, imp = self, "Ignoring roi: {}", e),
+                }
+            }
+            "allowed-classes" => {
+                let classes: Option<String> = value.get().expect("type checked upstream");
+                settings.filter.allowed_classes =
+                    InferenceFilter::parse_classes(classes.as_deref().unwrap_or(""));
+            }
             _ => {
                 gstreamer::warning!(
                     CAT,
@@ -361,6 +415,8 @@ impl ObjectImpl for CpuDetector {
             "input-width" => settings.input_width.to_value(),
             "input-height" => settings.input_height.to_value(),
             "process-every-n-frames" => settings.process_every_n_frames.to_value(),
+            "roi" => RegionOfInterest::format_list(&settings.filter.rois).to_value(),
+            "allowed-classes" => settings.filter.allowed_classes.join(",").to_value(),
             _ => {
                 gstreamer::warning!(
                     CAT,
@@ -462,7 +518,7 @@ impl BaseTransformImpl for CpuDetector {
             // Convert frame to image for detection
             if let Some(image) = self.frame_to_image(&frame) {
                 if let Some(ref detector) = *self.detector.lock().unwrap() {
-                    match detector.detect(&image) {
+                    match self.detect_filtered(detector, &image, &settings.filter) {
                         Ok(detections) => {
                             gst::debug!(
                                 CAT,
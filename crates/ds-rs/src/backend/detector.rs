@@ -1,3 +1,4 @@
+use super::cache::{self, CachedDetection, StartupOptions};
 use super::{Backend, BackendType};
 use crate::error::{DeepStreamError, Result};
 use crate::platform::PlatformInfo;
@@ -6,10 +7,12 @@ use gstreamer::prelude::*;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
 
 static DETECTION_CACHE: Lazy<Mutex<Option<BackendType>>> = Lazy::new(|| Mutex::new(None));
 
-const DEEPSTREAM_ELEMENTS: &[&str] = &[
+pub(crate) const DEEPSTREAM_ELEMENTS: &[&str] = &[
     "nvstreammux",
     "nvinfer",
     "nvtracker",
@@ -18,7 +21,7 @@ const DEEPSTREAM_ELEMENTS: &[&str] = &[
     "nvvideoconvert",
 ];
 
-const STANDARD_ELEMENTS: &[&str] = &[
+pub(crate) const STANDARD_ELEMENTS: &[&str] = &[
     "compositor",
     "queue",
     "videoconvert",
@@ -61,27 +64,105 @@ pub fn detect_and_create_backend(platform: &PlatformInfo) -> Result<Box<dyn Back
     // Initialize GStreamer if not already done
     gst::init().map_err(|e| DeepStreamError::GStreamer(e.into()))?;
 
-    // Detect optimal backend
-    let backend_type = if platform.has_nvidia_hardware() && check_deepstream_availability() {
+    let backend_type = choose_backend(
+        platform.has_nvidia_hardware() && check_deepstream_availability(),
+        check_standard_availability(),
+    );
+
+    // Cache the detection result
+    if let Ok(mut cache) = DETECTION_CACHE.lock() {
+        *cache = Some(backend_type);
+    }
+
+    create_backend(backend_type, platform)
+}
+
+fn choose_backend(deepstream: bool, standard: bool) -> BackendType {
+    if deepstream {
         log::info!("DeepStream elements detected, using DeepStream backend");
         BackendType::DeepStream
-    } else if check_standard_availability() {
+    } else if standard {
         log::info!("Standard GStreamer elements detected, using standard backend");
         BackendType::Standard
     } else {
         log::warn!("No suitable GStreamer elements found, using mock backend");
         BackendType::Mock
+    }
+}
+
+/// Platform and backend for this process, from the cache saved by an
+/// earlier run when the plugin registry and environment are unchanged.
+/// Otherwise the platform and both backends' elements are probed in
+/// parallel and the result saved.
+pub fn detect_startup() -> Result<(PlatformInfo, BackendType)> {
+    let started = Instant::now();
+    let options = StartupOptions::from_env();
+
+    // Platform checks shell out, so run them alongside GStreamer init; on
+    // a cache hit the result is simply not waited for
+    let platform = thread::spawn(PlatformInfo::detect);
+    gst::init().map_err(|e| DeepStreamError::GStreamer(e.into()))?;
+    let fingerprint = cache::fingerprint();
+
+    let path = options.path().filter(|_| options.enabled);
+    let cached = path
+        .as_deref()
+        .and_then(|path| CachedDetection::load(path, &fingerprint));
+    let (platform_info, backend_type) = match cached {
+        Some(cached) => {
+            log::debug!("Using saved backend detection: {}", cached.backend);
+            cache::seed_elements(&cached.elements);
+            (cached.platform, cached.backend)
+        }
+        None => {
+            let (deepstream, standard) = thread::scope(|scope| {
+                let deepstream = scope.spawn(check_deepstream_availability);
+                let standard = check_standard_availability();
+                (deepstream.join().unwrap_or(false), standard)
+            });
+            let platform_info = join_platform(platform)?;
+            let backend_type =
+                choose_backend(platform_info.has_nvidia_hardware() && deepstream, standard);
+
+            if let Some(path) = &path {
+                let detection = CachedDetection {
+                    fingerprint,
+                    platform: platform_info.clone(),
+                    backend: backend_type,
+                    elements: cache::probed_elements(),
+                };
+                if let Err(e) = detection.store(path) {
+                    log::debug!("Could not save backend detection: {}", e);
+                }
+            }
+            (platform_info, backend_type)
+        }
     };
 
-    // Cache the detection result
     if let Ok(mut cache) = DETECTION_CACHE.lock() {
         *cache = Some(backend_type);
     }
+    let elapsed = started.elapsed();
+    if elapsed > options.budget {
+        log::warn!(
+            "Backend detection took {:?}, over the {:?} startup budget",
+            elapsed,
+            options.budget
+        );
+    }
+    Ok((platform_info, backend_type))
+}
 
-    create_backend(backend_type, platform)
+fn join_platform(platform: thread::JoinHandle<Result<PlatformInfo>>) -> Result<PlatformInfo> {
+    platform
+        .join()
+        .map_err(|_| DeepStreamError::Unknown("Platform detection panicked".to_string()))?
 }
 
-fn create_backend(backend_type: BackendType, platform: &PlatformInfo) -> Result<Box<dyn Backend>> {
+pub(crate) fn create_backend(
+    backend_type: BackendType,
+    platform: &PlatformInfo,
+) -> Result<Box<dyn Backend>> {
     match backend_type {
         BackendType::DeepStream => super::deepstream::DeepStreamBackend::new(platform),
         BackendType::Standard => super::standard::StandardBackend::new(platform),
@@ -112,7 +193,7 @@ fn check_standard_availability() -> bool {
 }
 
 pub fn check_element_availability(element_name: &str) -> bool {
-    gst::ElementFactory::find(element_name).is_some()
+    cache::element_available(element_name)
 }
 
 pub fn list_available_elements() -> Vec<String> {
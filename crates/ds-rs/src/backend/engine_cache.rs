@@ -0,0 +1,472 @@
+//! TensorRT engine files kept per model, precision, GPU and batch size
+//!
+//! nvinfer builds a TensorRT engine from the model the first time it
+//! starts, which can take minutes, and builds it again whenever it cannot
+//! find the engine its config names. The cache gives every combination of
+//! model, `network-mode`, `gpu-id` and `batch-size` one engine file of its
+//! own and points `model-engine-file` at it, so nvinfer finds the same
+//! engine on every start. When GPU placement puts nvinfer on another GPU
+//! than its config names, the key and the pinned config carry that GPU. Engines for ONNX models can be built ahead of
+//! time with `trtexec`, as an [`Operation`] that reports build progress.
+//!
+//! Engines live in `engines/` under the ds-rs cache directory;
+//! `DS_RS_ENGINE_DIR` moves them and `TRTEXEC` names the builder.
+
+use crate::config::nvinfer::{NetworkMode, NvInferConfig};
+use crate::error::{DeepStreamError, Result};
+use crate::operation::Operation;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Where `trtexec` lives in DeepStream images, when it is not on the path
+const TRTEXEC_FALLBACK: &str = "/usr/src/tensorrt/bin/trtexec";
+/// How often a build checks for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Progress a build crawls up to while TensorRT tries tactics
+const TACTIC_PROGRESS: f64 = 85.0;
+
+/// Builds started by this process, to name their partial engines apart
+static BUILDS: AtomicU64 = AtomicU64::new(0);
+
+/// What an engine was built for; engines differ in every field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EngineKey {
+    pub model: PathBuf,
+    /// Size and modification time of the model, so a replaced model gets
+    /// a new engine
+    pub model_fingerprint: u64,
+    pub precision: NetworkMode,
+    pub gpu_id: u32,
+    pub batch_size: u32,
+}
+
+impl EngineKey {
+    /// Key of the engine for `config`, whose model paths are absolute
+    pub fn for_config(config: &NvInferConfig) -> Result<Self> {
+        let p = &config.property;
+        let model = p
+            .onnx_file
+            .as_ref()
+            .or(p.model_file.as_ref())
+            .or(p.tlt_encoded_model.as_ref())
+            .ok_or_else(|| {
+                DeepStreamError::Configuration(
+                    "nvinfer config has no model to build an engine from".to_string(),
+                )
+            })?;
+        let model = PathBuf::from(model);
+        let metadata = fs::metadata(&model).map_err(|e| DeepStreamError::Model {
+            element: None,
+            reason: format!("{}: {}", model.display(), e),
+        })?;
+        let mut hasher = DefaultHasher::new();
+        metadata.len().hash(&mut hasher);
+        metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .hash(&mut hasher);
+
+        Ok(Self {
+            model,
+            model_fingerprint: hasher.finish(),
+            precision: p.network_mode,
+            gpu_id: p.gpu_id,
+            batch_size: p.batch_size,
+        })
+    }
+
+    /// File name in the style nvinfer uses, with the model fingerprint
+    pub fn file_name(&self) -> String {
+        let model = self
+            .model
+            .file_name()
+            .map_or_else(|| "model".into(), |name| name.to_string_lossy());
+        format!(
+            "{}-{:016x}_b{}_gpu{}_{}.engine",
+            model,
+            self.model_fingerprint,
+            self.batch_size,
+            self.gpu_id,
+            precision_name(self.precision)
+        )
+    }
+}
+
+fn precision_name(mode: NetworkMode) -> &'static str {
+    match mode {
+        NetworkMode::Fp32 => "fp32",
+        NetworkMode::Fp16 => "fp16",
+        NetworkMode::Int8 => "int8",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineEntry {
+    pub file: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub modified: Option<f64>,
+}
+
+pub struct EngineCache {
+    dir: PathBuf,
+}
+
+impl EngineCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache in `DS_RS_ENGINE_DIR`, or under the ds-rs cache directory
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("DS_RS_ENGINE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| super::cache::cache_dir().map(|dir| dir.join("engines")))
+            .map(Self::new)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn engine_path(&self, key: &EngineKey) -> PathBuf {
+        self.dir.join(key.file_name())
+    }
+
+    pub fn is_built(&self, key: &EngineKey) -> bool {
+        self.engine_path(key).is_file()
+    }
+
+    /// Point `config` at the cached engine for its key. Relative model
+    /// paths are resolved against `base`, the directory the config was
+    /// read from, so the config can be written anywhere. `gpu_id` is the
+    /// GPU nvinfer runs on when it overrides the config's `gpu-id`.
+    pub fn pin(
+        &self,
+        config: &mut NvInferConfig,
+        base: &Path,
+        gpu_id: Option<u32>,
+    ) -> Result<EngineKey> {
+        let p = &mut config.property;
+        if let Some(gpu_id) = gpu_id {
+            p.gpu_id = gpu_id;
+        }
+        for path in [
+            &mut p.onnx_file,
+            &mut p.model_file,
+            &mut p.proto_file,
+            &mut p.tlt_encoded_model,
+            &mut p.int8_calib_file,
+            &mut p.labelfile_path,
+            &mut p.custom_lib_path,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if Path::new(path).is_relative() {
+                *path = base.join(&*path).to_string_lossy().into_owned();
+            }
+        }
+
+        let key = EngineKey::for_config(config)?;
+        config.property.model_engine_file =
+            Some(self.engine_path(&key).to_string_lossy().into_owned());
+        Ok(key)
+    }
+
+    /// Write a pinned copy of the nvinfer config at `path` into the cache
+    /// and return its path, to give nvinfer as `config-file-path`
+    pub fn pinned_config(&self, path: &Path, gpu_id: Option<u32>) -> Result<PathBuf> {
+        let mut config = NvInferConfig::from_file(path)?;
+        let key = self.pin(&mut config, &config_dir(path)?, gpu_id)?;
+
+        let configs = self.dir.join("configs");
+        fs::create_dir_all(&configs)?;
+        let stem = path
+            .file_stem()
+            .map_or_else(|| "nvinfer".into(), |stem| stem.to_string_lossy());
+        let pinned = configs.join(format!("{}_{}.txt", stem, key.file_name()));
+        config.to_file(&pinned)?;
+        Ok(pinned)
+    }
+
+    /// Engines in the cache, by file name
+    pub fn entries(&self) -> Vec<EngineEntry> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<_> = dir
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "engine"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some(EngineEntry {
+                    file: entry.file_name().to_string_lossy().into_owned(),
+                    size: metadata.len(),
+                    modified: metadata
+                        .modified()
+                        .ok()
+                        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                        .map(|since| since.as_secs_f64()),
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.file.cmp(&b.file));
+        entries
+    }
+
+    /// Build the engine for the nvinfer config at `config_path` unless it
+    /// is cached, reporting `trtexec`'s output on `operation`. Only ONNX
+    /// models can be built this way; nvinfer builds others on first start.
+    pub fn build(
+        &self,
+        config_path: &Path,
+        gpu_id: Option<u32>,
+        operation: &Operation,
+    ) -> Result<PathBuf> {
+        let mut config = NvInferConfig::from_file(config_path)?;
+        let key = self.pin(&mut config, &config_dir(config_path)?, gpu_id)?;
+        let target = self.engine_path(&key);
+        if target.is_file() {
+            operation.set_message(format!("{} is already built", key.file_name()));
+            return Ok(target);
+        }
+        let onnx = config.property.onnx_file.clone().ok_or_else(|| {
+            DeepStreamError::Configuration(format!(
+                "{}: only onnx-file models can be built ahead of time",
+                config_path.display()
+            ))
+        })?;
+
+        fs::create_dir_all(&self.dir)?;
+        // Concurrent builds of one engine each write their own file; the
+        // last to finish renames over the others
+        let partial = target.with_extension(format!(
+            "engine.{}-{}.partial",
+            std::process::id(),
+            BUILDS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut command = Command::new(trtexec());
+        command
+            .arg(format!("--onnx={}", onnx))
+            .arg(format!("--saveEngine={}", partial.display()))
+            .arg(format!("--device={}", key.gpu_id))
+            .arg("--skipInference");
+        match key.precision {
+            NetworkMode::Fp32 => {}
+            NetworkMode::Fp16 => {
+                command.arg("--fp16");
+            }
+            NetworkMode::Int8 => {
+                command.arg("--int8");
+                if let Some(calib) = &config.property.int8_calib_file {
+                    command.arg(format!("--calib={}", calib));
+                }
+            }
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| DeepStreamError::Model {
+                element: None,
+                reason: format!("Cannot run trtexec: {}", e),
+            })?;
+
+        log::info!("Building TensorRT engine {}", key.file_name());
+        operation.set_progress(0.0);
+        let (lines, output) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            std::thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+                    if lines.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        let mut progress = 0.0;
+        let mut last_error = None;
+        loop {
+            if operation.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = fs::remove_file(&partial);
+                return Err(DeepStreamError::ProcessingFailed {
+                    reason: format!("Build of {} cancelled", key.file_name()),
+                });
+            }
+            match output.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => {
+                    progress = build_progress(&line, progress);
+                    operation.set_progress(progress);
+                    if line.contains("[E]") {
+                        last_error = Some(line.clone());
+                    }
+                    operation.set_message(line);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let status = child.wait()?;
+        if !status.success() || !partial.is_file() {
+            let _ = fs::remove_file(&partial);
+            return Err(DeepStreamError::Model {
+                element: None,
+                reason: format!(
+                    "trtexec failed for {}: {}",
+                    key.model.display(),
+                    last_error.unwrap_or_else(|| status.to_string())
+                ),
+            });
+        }
+        fs::rename(&partial, &target)?;
+        log::info!("Built TensorRT engine {}", target.display());
+        Ok(target)
+    }
+}
+
+fn trtexec() -> PathBuf {
+    if let Some(path) = std::env::var_os("TRTEXEC") {
+        return PathBuf::from(path);
+    }
+    if Path::new(TRTEXEC_FALLBACK).is_file() {
+        return PathBuf::from(TRTEXEC_FALLBACK);
+    }
+    PathBuf::from("trtexec")
+}
+
+/// Progress after `trtexec` printed `line`. Parsing and finishing are
+/// marked in the output; the tactic search in between only crawls.
+fn build_progress(line: &str, progress: f64) -> f64 {
+    const MILESTONES: &[(&str, f64)] = &[
+        ("Start parsing network model", 5.0),
+        ("Finished parsing network model", 15.0),
+        ("Engine built in", 95.0),
+    ];
+    match MILESTONES.iter().find(|(marker, _)| line.contains(marker)) {
+        Some(&(_, milestone)) => progress.max(milestone),
+        None if progress >= 15.0 && progress < TACTIC_PROGRESS => {
+            (progress + 0.1).min(TACTIC_PROGRESS)
+        }
+        None => progress,
+    }
+}
+
+/// Absolute directory of the config at `path`, which its relative model
+/// paths are relative to; a bare file name has an empty parent
+fn config_dir(path: &Path) -> Result<PathBuf> {
+    let path = path.canonicalize()?;
+    Ok(path
+        .parent()
+        .map_or_else(|| PathBuf::from("/"), Path::to_path_buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A detector model and its config `properties` in a temporary directory
+    fn detector(properties: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("detector.onnx"), b"onnx").unwrap();
+        let config_path = dir.path().join("pgie.txt");
+        fs::write(
+            &config_path,
+            format!("[property]\nonnx-file=detector.onnx\n{}", properties),
+        )
+        .unwrap();
+        (dir, config_path)
+    }
+
+    #[test]
+    fn test_pinned_engine_path() {
+        let (dir, config_path) = detector("batch-size=4\nnetwork-mode=2\ngpu-id=1\n");
+
+        let cache = EngineCache::new(dir.path().join("engines"));
+        let pinned = cache.pinned_config(&config_path, None).unwrap();
+        let config = NvInferConfig::from_file(&pinned).unwrap();
+        let engine = PathBuf::from(config.property.model_engine_file.unwrap());
+        assert_eq!(engine.parent().unwrap(), cache.dir());
+        let name = engine.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("detector.onnx-"));
+        assert!(name.ends_with("_b4_gpu1_fp16.engine"));
+        assert!(Path::new(&config.property.onnx_file.unwrap()).is_absolute());
+        assert!(cache.entries().is_empty());
+    }
+
+    #[test]
+    fn test_bare_config_name() {
+        // Config and model in the working directory, named without one
+        let model = tempfile::Builder::new()
+            .suffix(".onnx")
+            .tempfile_in(".")
+            .unwrap();
+        let model_name = model.path().file_name().unwrap().to_string_lossy();
+        let config = tempfile::Builder::new()
+            .suffix(".txt")
+            .tempfile_in(".")
+            .unwrap();
+        fs::write(
+            config.path(),
+            format!("[property]\nonnx-file={}\n", model_name),
+        )
+        .unwrap();
+        let bare = Path::new(config.path().file_name().unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EngineCache::new(dir.path().join("engines"));
+        let pinned = cache.pinned_config(bare, None).unwrap();
+        let onnx = NvInferConfig::from_file(&pinned)
+            .unwrap()
+            .property
+            .onnx_file
+            .unwrap();
+        assert!(Path::new(&onnx).is_absolute());
+        assert!(Path::new(&onnx).exists());
+    }
+
+    #[test]
+    fn test_batch_size_is_keyed() {
+        let (dir, config_path) = detector("batch-size=4\n");
+        let cache = EngineCache::new(dir.path().join("engines"));
+        let mut config = NvInferConfig::from_file(&config_path).unwrap();
+        let key = cache.pin(&mut config, dir.path(), None).unwrap();
+
+        let mut other = NvInferConfig::from_file(&config_path).unwrap();
+        other.property.batch_size = 8;
+        let other_key = cache.pin(&mut other, dir.path(), None).unwrap();
+        assert_ne!(cache.engine_path(&other_key), cache.engine_path(&key));
+    }
+
+    #[test]
+    fn test_gpu_override_is_keyed() {
+        let (dir, config_path) = detector("gpu-id=0\n");
+
+        let cache = EngineCache::new(dir.path().join("engines"));
+        let pinned = cache.pinned_config(&config_path, Some(1)).unwrap();
+        let config = NvInferConfig::from_file(&pinned).unwrap();
+        assert_eq!(config.property.gpu_id, 1);
+        let engine = config.property.model_engine_file.unwrap();
+        assert!(engine.contains("_gpu1_"));
+    }
+
+    #[test]
+    fn test_build_progress() {
+        assert_eq!(build_progress("[I] Start parsing network model.", 0.0), 5.0);
+        assert!((build_progress("[V] [TRT] Tactic: 0x01", 15.0) - 15.1).abs() < 1e-9);
+        assert_eq!(build_progress("[I] Engine built in 90.1 sec.", 50.0), 95.0);
+    }
+}
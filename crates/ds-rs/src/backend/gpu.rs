@@ -0,0 +1,270 @@
+//! Placement of a DeepStream pipeline across several GPUs
+//!
+//! The shared elements (streammux, inference, tracker, tiler, OSD) run on
+//! one pipeline GPU, while each source's decoder and converters go on the
+//! GPU the placement policy picks for it:
+//!
+//! ```toml
+//! [gpu]
+//! placement = "fill-first"   # or "round-robin", "explicit"
+//! gpus = [0, 1]              # every GPU found when left out
+//! sources_per_gpu = 8
+//! pipeline_gpu = 0
+//!
+//! [gpu.sources]
+//! "rtsp://10.0.0.5/stream1" = 1
+//! ```
+//!
+//! Sources listed under `[gpu.sources]` always go on their GPU. With
+//! `explicit` placement every other source shares the pipeline GPU. Every
+//! GPU named is checked against the GPUs present when the placement is
+//! created.
+
+use crate::error::{DeepStreamError, Result};
+use crate::platform::available_gpus;
+use crate::source::SourceId;
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlacementPolicy {
+    /// Each new source on the next GPU in turn
+    #[default]
+    RoundRobin,
+    /// Sources on the first GPU with fewer than `sources_per_gpu`
+    FillFirst,
+    /// Only the `[gpu.sources]` mapping; other sources on the pipeline GPU
+    Explicit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuConfig {
+    pub placement: PlacementPolicy,
+    /// GPUs sources are placed on; every GPU found when empty
+    pub gpus: Vec<u32>,
+    /// Sources a GPU takes before `fill-first` moves on to the next
+    pub sources_per_gpu: u32,
+    /// GPU of the shared elements; the first of `gpus` by default
+    pub pipeline_gpu: Option<u32>,
+    /// Sources pinned to a GPU, by URI
+    pub sources: HashMap<String, u32>,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            placement: PlacementPolicy::default(),
+            gpus: Vec::new(),
+            sources_per_gpu: 8,
+            pipeline_gpu: None,
+            sources: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Assignments {
+    gpus: HashMap<SourceId, u32>,
+    /// Index into the GPU list of the next round-robin placement
+    next: usize,
+}
+
+/// GPU of every source, picked as sources are added
+pub struct GpuPlacement {
+    config: GpuConfig,
+    gpus: Vec<u32>,
+    pipeline_gpu: u32,
+    assignments: Mutex<Assignments>,
+}
+
+impl GpuPlacement {
+    /// Placement over the GPUs of this machine
+    pub fn detect(config: GpuConfig) -> Result<Self> {
+        Self::new(config, &available_gpus())
+    }
+
+    /// Placement over the GPUs in `available`, failing if the config names
+    /// any other
+    pub fn new(config: GpuConfig, available: &[u32]) -> Result<Self> {
+        if available.is_empty() {
+            return Err(DeepStreamError::Configuration(
+                "No GPUs found for GPU placement".to_string(),
+            ));
+        }
+        let check = |gpu: u32, what: &str| {
+            if available.contains(&gpu) {
+                Ok(())
+            } else {
+                Err(DeepStreamError::Configuration(format!(
+                    "GPU {} for {} does not exist; GPUs found: {:?}",
+                    gpu, what, available
+                )))
+            }
+        };
+        for &gpu in &config.gpus {
+            check(gpu, "placement")?;
+        }
+        if let Some(gpu) = config.pipeline_gpu {
+            check(gpu, "the pipeline")?;
+        }
+        for (uri, &gpu) in &config.sources {
+            check(gpu, &crate::source::uri::redact(uri))?;
+        }
+        if config.placement == PlacementPolicy::FillFirst && config.sources_per_gpu == 0 {
+            return Err(DeepStreamError::Configuration(
+                "sources_per_gpu must be at least 1 for fill-first placement".to_string(),
+            ));
+        }
+
+        let gpus = if config.gpus.is_empty() {
+            available.to_vec()
+        } else {
+            config.gpus.clone()
+        };
+        let pipeline_gpu = config.pipeline_gpu.unwrap_or(gpus[0]);
+        Ok(Self {
+            config,
+            gpus,
+            pipeline_gpu,
+            assignments: Mutex::new(Assignments::default()),
+        })
+    }
+
+    pub fn config(&self) -> &GpuConfig {
+        &self.config
+    }
+
+    /// GPUs sources are placed on
+    pub fn gpus(&self) -> &[u32] {
+        &self.gpus
+    }
+
+    /// GPU of the streammux, inference, tracker, tiler and OSD
+    pub fn pipeline_gpu(&self) -> u32 {
+        self.pipeline_gpu
+    }
+
+    /// Pick the GPU of source `id`, reading `uri`, and remember it until
+    /// [`release`](Self::release)
+    pub fn assign(&self, id: SourceId, uri: &str) -> u32 {
+        let mut assignments = self.assignments.lock().unwrap();
+        if let Some(&gpu) = assignments.gpus.get(&id) {
+            return gpu;
+        }
+        let gpu = match (self.config.sources.get(uri), self.config.placement) {
+            (Some(&gpu), _) => gpu,
+            (None, PlacementPolicy::Explicit) => self.pipeline_gpu,
+            (None, PlacementPolicy::RoundRobin) => {
+                let gpu = self.gpus[assignments.next % self.gpus.len()];
+                assignments.next = (assignments.next + 1) % self.gpus.len();
+                gpu
+            }
+            (None, PlacementPolicy::FillFirst) => {
+                let load = |gpu: u32| assignments.gpus.values().filter(|&&g| g == gpu).count();
+                let limit = self.config.sources_per_gpu as usize;
+                self.gpus
+                    .iter()
+                    .copied()
+                    .find(|&gpu| load(gpu) < limit)
+                    // All full: the least loaded takes the overflow
+                    .or_else(|| self.gpus.iter().copied().min_by_key(|&gpu| load(gpu)))
+                    .unwrap_or(self.pipeline_gpu)
+            }
+        };
+        assignments.gpus.insert(id, gpu);
+        gpu
+    }
+
+    pub fn release(&self, id: SourceId) {
+        self.assignments.lock().unwrap().gpus.remove(&id);
+    }
+
+    pub fn gpu_of(&self, id: SourceId) -> Option<u32> {
+        self.assignments.lock().unwrap().gpus.get(&id).copied()
+    }
+
+    pub fn assignments(&self) -> HashMap<SourceId, u32> {
+        self.assignments.lock().unwrap().gpus.clone()
+    }
+}
+
+/// Put `element` on `gpu` if it has a `gpu-id`; returns whether it had one
+pub fn set_gpu_id(element: &gst::Element, gpu: u32) -> bool {
+    if element.find_property("gpu-id").is_none() {
+        return false;
+    }
+    // gpu-id is a uint on some DeepStream elements and an int on others
+    element.set_property_from_str("gpu-id", &gpu.to_string());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin() {
+        let round_robin = GpuPlacement::new(GpuConfig::default(), &[0, 1]).unwrap();
+        let placed: Vec<_> = (0..3)
+            .map(|i| round_robin.assign(SourceId(i), "file:///a.mp4"))
+            .collect();
+        assert_eq!(placed, [0, 1, 0]);
+        // A source keeps its GPU
+        assert_eq!(round_robin.assign(SourceId(1), "file:///a.mp4"), 1);
+    }
+
+    #[test]
+    fn test_fill_first() {
+        let fill = GpuPlacement::new(
+            GpuConfig {
+                placement: PlacementPolicy::FillFirst,
+                sources_per_gpu: 2,
+                ..Default::default()
+            },
+            &[0, 1],
+        )
+        .unwrap();
+        assert_eq!(fill.assign(SourceId(0), "rtsp://cam/1"), 0);
+        assert_eq!(fill.assign(SourceId(1), "rtsp://cam/2"), 0);
+        assert_eq!(fill.assign(SourceId(2), "rtsp://cam/3"), 1);
+
+        fill.release(SourceId(1));
+        assert_eq!(fill.assign(SourceId(3), "rtsp://cam/4"), 0);
+    }
+
+    #[test]
+    fn test_pinned_sources() {
+        let mut config = GpuConfig {
+            placement: PlacementPolicy::FillFirst,
+            sources_per_gpu: 2,
+            ..Default::default()
+        };
+        config.sources.insert("rtsp://cam/1".to_string(), 1);
+        let fill = GpuPlacement::new(config, &[0, 1]).unwrap();
+        assert_eq!(fill.assign(SourceId(0), "rtsp://cam/1"), 1);
+        assert_eq!(fill.assign(SourceId(1), "rtsp://cam/2"), 0);
+    }
+
+    #[test]
+    fn test_explicit_placement() {
+        let explicit: GpuConfig =
+            toml::from_str("placement = \"explicit\"\npipeline_gpu = 1").unwrap();
+        let explicit = GpuPlacement::new(explicit, &[0, 1]).unwrap();
+        assert_eq!(explicit.assign(SourceId(0), "rtsp://cam/1"), 1);
+    }
+
+    #[test]
+    fn test_unavailable_gpus_rejected() {
+        let missing = GpuConfig {
+            gpus: vec![0, 2],
+            ..Default::default()
+        };
+        assert!(GpuPlacement::new(missing, &[0, 1]).is_err());
+        assert!(GpuPlacement::new(GpuConfig::default(), &[]).is_err());
+    }
+}
@@ -1,17 +1,40 @@
 #![allow(unused)]
+use super::mock_script::MockScript;
 use super::{Backend, BackendCapabilities, BackendType};
 use crate::error::{DeepStreamError, Result};
 use crate::platform::PlatformInfo;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 pub struct MockBackend {
     capabilities: BackendCapabilities,
     platform: PlatformInfo,
+    script: Option<Arc<MockScript>>,
 }
 
 impl MockBackend {
+    /// Mock backend whose elements follow `script`
+    pub fn with_script(platform: &PlatformInfo, script: MockScript) -> Box<dyn Backend> {
+        Box::new(Self {
+            capabilities: Self::create_capabilities(),
+            platform: platform.clone(),
+            script: Some(Arc::new(script)),
+        })
+    }
+
+    pub fn script(&self) -> Option<&MockScript> {
+        self.script.as_deref()
+    }
+
+    fn scripted(&self, element: gst::Element, role: &str) -> gst::Element {
+        if let Some(script) = &self.script {
+            script.attach(&element, role);
+        }
+        element
+    }
+
     fn create_capabilities() -> BackendCapabilities {
         BackendCapabilities {
             supports_inference: true, // Mock inference
@@ -121,9 +144,17 @@ impl Backend for MockBackend {
     fn new(platform: &PlatformInfo) -> Result<Box<dyn Backend>> {
         log::info!("Creating mock backend for testing");
 
+        let script = MockScript::from_env()?;
+        if script.is_some() {
+            log::info!(
+                "Mock backend follows the script in {}",
+                super::mock_script::SCRIPT_ENV
+            );
+        }
         Ok(Box::new(Self {
             capabilities: Self::create_capabilities(),
             platform: platform.clone(),
+            script: script.map(Arc::new),
         }))
     }
 
@@ -139,7 +170,7 @@ impl Backend for MockBackend {
 
         log::debug!("Mock backend: Created mock stream mux");
 
-        Ok(tee)
+        Ok(self.scripted(tee, "streammux"))
     }
 
     fn create_inference(&self, name: Option<&str>, config_path: &str) -> Result<gst::Element> {
@@ -157,7 +188,7 @@ impl Backend for MockBackend {
         // Store config path as metadata
         bin.set_property_from_str("name", name.unwrap_or("mock-inference"));
 
-        Ok(bin)
+        Ok(self.scripted(bin, "inference"))
     }
 
     fn create_tracker(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -165,6 +196,7 @@ impl Backend for MockBackend {
 
         // Simple identity element for tracking simulation
         Self::create_mock_element(name.or(Some("mock-tracker")))
+            .map(|element| self.scripted(element, "tracker"))
     }
 
     fn create_tiler(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -175,6 +207,7 @@ impl Backend for MockBackend {
             name.unwrap_or("mock-tiler"),
             1, // Just one identity element
         )
+        .map(|bin| self.scripted(bin, "tiler"))
     }
 
     fn create_osd(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -182,6 +215,7 @@ impl Backend for MockBackend {
 
         // Create identity element for OSD simulation
         Self::create_mock_element(name.or(Some("mock-osd")))
+            .map(|element| self.scripted(element, "osd"))
     }
 
     fn create_video_convert(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -189,6 +223,7 @@ impl Backend for MockBackend {
 
         // Use identity as mock converter
         Self::create_mock_element(name.or(Some("mock-videoconvert")))
+            .map(|element| self.scripted(element, "converter"))
     }
 
     fn create_video_sink(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -203,7 +238,7 @@ impl Backend for MockBackend {
 
         log::debug!("Mock backend: Created mock video sink");
 
-        Ok(sink)
+        Ok(self.scripted(sink, "videosink"))
     }
 
     fn create_decoder(&self, name: Option<&str>) -> Result<gst::Element> {
@@ -211,6 +246,7 @@ impl Backend for MockBackend {
 
         // Use identity as mock decoder
         Self::create_mock_element(name.or(Some("mock-decoder")))
+            .map(|element| self.scripted(element, "decoder"))
     }
 
     fn configure_element(
@@ -0,0 +1,267 @@
+//! Scripted behaviour for the Mock backend
+//!
+//! A script says which detections the mock inference reports on each
+//! frame, which elements fail and when, and how long inference takes:
+//!
+//! ```json
+//! {
+//!   "processing_delay_ms": 5,
+//!   "repeat": true,
+//!   "frames": [
+//!     { "frame": 0, "detections": [
+//!       { "class_id": 0, "class_name": "person", "confidence": 0.9,
+//!         "x": 100, "y": 50, "width": 40, "height": 120 } ] }
+//!   ],
+//!   "failures": [
+//!     { "element": "tracker", "at_frame": 120, "message": "tracker crashed" }
+//!   ]
+//! }
+//! ```
+//!
+//! Detections are posted on the bus as `inference-results` element
+//! messages carrying `frame-num` and the same JSON the CPU detector emits,
+//! so code that consumes detections can be tested without a model. Set
+//! `DS_RS_MOCK_SCRIPT` to a script file to use it with the default Mock
+//! backend.
+
+use crate::error::{DeepStreamError, Result};
+use crate::metadata::{BoundingBox, ObjectMeta};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const SCRIPT_ENV: &str = "DS_RS_MOCK_SCRIPT";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedDetection {
+    pub class_id: usize,
+    pub class_name: String,
+    pub confidence: f32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedFrame {
+    pub frame: u64,
+    #[serde(default)]
+    pub detections: Vec<ScriptedDetection>,
+}
+
+/// An element failing with an error message on the bus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptedFailure {
+    /// Element role, e.g. `inference`, `tracker` or `decoder`
+    pub element: String,
+    /// Fail on this frame of the element
+    #[serde(default)]
+    pub at_frame: Option<u64>,
+    /// Fail on the first buffer at or after this timestamp
+    #[serde(default)]
+    pub at_ms: Option<u64>,
+    #[serde(default = "default_failure_message")]
+    pub message: String,
+}
+
+fn default_failure_message() -> String {
+    "Scripted failure".to_string()
+}
+
+impl ScriptedFailure {
+    fn is_due(&self, frame: u64, pts: Option<gst::ClockTime>) -> bool {
+        self.at_frame.is_some_and(|at| frame >= at)
+            || self
+                .at_ms
+                .zip(pts)
+                .is_some_and(|(at, pts)| pts.mseconds() >= at)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockScript {
+    pub frames: Vec<ScriptedFrame>,
+    /// Start over when frames run past the last scripted one
+    pub repeat: bool,
+    pub failures: Vec<ScriptedFailure>,
+    /// Time the mock inference spends on each frame
+    pub processing_delay_ms: u64,
+}
+
+impl std::str::FromStr for MockScript {
+    type Err = DeepStreamError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_str(s)
+            .map_err(|e| DeepStreamError::Configuration(format!("Invalid mock script: {}", e)))
+    }
+}
+
+impl MockScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// The script named by `DS_RS_MOCK_SCRIPT`, if set
+    pub fn from_env() -> Result<Option<Self>> {
+        std::env::var_os(SCRIPT_ENV)
+            .map(|path| Self::load(Path::new(&path)))
+            .transpose()
+    }
+
+    pub fn processing_delay(&self) -> Duration {
+        Duration::from_millis(self.processing_delay_ms)
+    }
+
+    /// Scripted detections for `frame`
+    pub fn detections(&self, frame: u64) -> &[ScriptedDetection] {
+        let frame = match self.frames.iter().map(|f| f.frame).max() {
+            Some(last) if self.repeat => frame % (last + 1),
+            _ => frame,
+        };
+        self.frames
+            .iter()
+            .find(|f| f.frame == frame)
+            .map_or(&[], |f| &f.detections)
+    }
+
+    /// [`detections`](Self::detections) as object metadata
+    pub fn objects(&self, frame: u64) -> Vec<ObjectMeta> {
+        self.detections(frame)
+            .iter()
+            .enumerate()
+            .map(|(index, detection)| {
+                let mut object = ObjectMeta::new(frame * 1000 + index as u64);
+                object.set_class(detection.class_id as i32, &detection.class_name);
+                object.set_detection_bbox(
+                    BoundingBox::new(detection.x, detection.y, detection.width, detection.height),
+                    detection.confidence,
+                );
+                object
+            })
+            .collect()
+    }
+
+    fn results_json(&self, frame: u64) -> String {
+        serde_json::json!({
+            "frame_num": frame,
+            "detections": self.detections(frame),
+        })
+        .to_string()
+    }
+
+    /// Make `element` in `role` follow the script: count its buffers, fail
+    /// when due and, for inference, delay and report detections
+    pub(crate) fn attach(self: &Arc<Self>, element: &gst::Element, role: &str) {
+        let failures: Vec<(ScriptedFailure, AtomicBool)> = self
+            .failures
+            .iter()
+            .filter(|failure| failure.element == role)
+            .map(|failure| (failure.clone(), AtomicBool::new(false)))
+            .collect();
+        let inference = role == "inference";
+        if failures.is_empty() && !inference {
+            return;
+        }
+        let Some(pad) = element
+            .static_pad("src")
+            .or_else(|| element.static_pad("sink"))
+        else {
+            log::warn!("Mock script: {} has no pad to watch", element.name());
+            return;
+        };
+
+        let script = self.clone();
+        let frames = AtomicU64::new(0);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let frame = frames.fetch_add(1, Ordering::SeqCst);
+            let pts = info.buffer().and_then(|buffer| buffer.pts());
+            let Some(element) = pad.parent_element() else {
+                return gst::PadProbeReturn::Ok;
+            };
+
+            for (failure, fired) in &failures {
+                if failure.is_due(frame, pts) && !fired.swap(true, Ordering::SeqCst) {
+                    log::info!("Mock script: failing {} on frame {}", element.name(), frame);
+                    let _ = element.post_message(
+                        gst::message::Error::builder(gst::CoreError::Failed, &failure.message)
+                            .src(&element)
+                            .build(),
+                    );
+                    return gst::PadProbeReturn::Drop;
+                }
+            }
+
+            if inference {
+                if script.processing_delay_ms > 0 {
+                    std::thread::sleep(script.processing_delay());
+                }
+                let results = gst::Structure::builder("inference-results")
+                    .field("frame-num", frame)
+                    .field("detections", script.results_json(frame))
+                    .build();
+                let _ = element.post_message(
+                    gst::message::Element::builder(results)
+                        .src(&element)
+                        .build(),
+                );
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script() -> MockScript {
+        r#"{
+            "repeat": true,
+            "frames": [
+                { "frame": 0, "detections": [{ "class_id": 2, "class_name": "car",
+                  "confidence": 0.8, "x": 1, "y": 2, "width": 3, "height": 4 }] },
+                { "frame": 1 }
+            ],
+            "failures": [{ "element": "tracker", "at_ms": 2000 }]
+        }"#
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scripted_detections() {
+        let script = script();
+        assert_eq!(script.detections(0).len(), 1);
+        assert!(script.detections(1).is_empty());
+        assert_eq!(script.objects(0).len(), 1);
+    }
+
+    #[test]
+    fn test_script_repeats() {
+        let script = script();
+        // Repeats with a period of two frames
+        assert_eq!(script.detections(4)[0].class_name, "car");
+        assert_eq!(script.objects(2).len(), 1);
+    }
+
+    #[test]
+    fn test_scripted_failure_timing() {
+        let script = script();
+        let failure = &script.failures[0];
+        assert_eq!(failure.message, "Scripted failure");
+        assert!(!failure.is_due(500, Some(gst::ClockTime::from_seconds(1))));
+        assert!(failure.is_due(0, Some(gst::ClockTime::from_seconds(2))));
+    }
+
+    #[test]
+    fn test_invalid_script_rejected() {
+        assert!("{ \"frames\": 3 }".parse::<MockScript>().is_err());
+    }
+}
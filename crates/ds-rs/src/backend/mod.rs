@@ -1,15 +1,31 @@
+pub mod cache;
 pub mod cpu_vision;
 pub mod deepstream;
 pub mod detector;
+pub mod engine_cache;
+pub mod gpu;
 pub mod mock;
+pub mod mock_script;
+pub mod overrides;
+pub mod report;
 pub mod standard;
 
+use crate::elements::DeepStreamElementType;
 use crate::error::Result;
 use crate::platform::PlatformInfo;
 use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::RwLock;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub use engine_cache::{EngineCache, EngineEntry, EngineKey};
+pub use gpu::{GpuConfig, GpuPlacement, PlacementPolicy};
+pub use mock_script::MockScript;
+pub use overrides::ElementOverrides;
+pub use report::{CapabilityReport, ResolvedElement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BackendType {
     DeepStream,
     Standard,
@@ -32,6 +48,22 @@ impl BackendType {
     }
 }
 
+impl std::str::FromStr for BackendType {
+    type Err = crate::error::DeepStreamError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "deepstream" => Ok(BackendType::DeepStream),
+            "standard" => Ok(BackendType::Standard),
+            "mock" => Ok(BackendType::Mock),
+            _ => Err(crate::error::DeepStreamError::InvalidInput(format!(
+                "Unknown backend: {}. Use deepstream, standard or mock.",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BackendCapabilities {
     pub supports_inference: bool,
@@ -98,12 +130,13 @@ pub trait Backend: Send + Sync {
 pub struct BackendManager {
     backend: Box<dyn Backend>,
     platform: PlatformInfo,
+    overrides: RwLock<HashMap<DeepStreamElementType, String>>,
 }
 
 impl BackendManager {
     pub fn new() -> Result<Self> {
-        let platform = PlatformInfo::detect()?;
-        let backend = detector::detect_and_create_backend(&platform)?;
+        let (platform, backend_type) = detector::detect_startup()?;
+        let backend = detector::create_backend(backend_type, &platform)?;
 
         log::info!(
             "Initialized {} backend on {:?} platform",
@@ -111,7 +144,11 @@ impl BackendManager {
             platform.platform
         );
 
-        Ok(Self { backend, platform })
+        Ok(Self {
+            backend,
+            platform,
+            overrides: RwLock::new(HashMap::new()),
+        })
     }
 
     pub fn with_backend(backend_type: BackendType) -> Result<Self> {
@@ -136,7 +173,21 @@ impl BackendManager {
             platform.platform
         );
 
-        Ok(Self { backend, platform })
+        Ok(Self {
+            backend,
+            platform,
+            overrides: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Mock backend following `script`, for tests
+    pub fn with_mock_script(script: MockScript) -> Result<Self> {
+        let platform = PlatformInfo::detect()?;
+        Ok(Self {
+            backend: mock::MockBackend::with_script(&platform, script),
+            platform,
+            overrides: RwLock::new(HashMap::new()),
+        })
     }
 
     pub fn backend(&self) -> &dyn Backend {
@@ -154,4 +205,19 @@ impl BackendManager {
     pub fn backend_type(&self) -> BackendType {
         self.backend.backend_type()
     }
+
+    /// Use the configured elements for this backend from now on. Every
+    /// factory must be installed, or nothing changes. Sources pick up a
+    /// decoder override through
+    /// [`SourceController::set_decoder`](crate::source::SourceController::set_decoder).
+    pub fn set_element_overrides(&self, overrides: &ElementOverrides) -> Result<()> {
+        let resolved = overrides.validate(self.backend_type())?;
+        *self.overrides.write().unwrap() = resolved;
+        Ok(())
+    }
+
+    /// Factory configured in place of the backend's own for `element_type`
+    pub fn element_override(&self, element_type: DeepStreamElementType) -> Option<String> {
+        self.overrides.read().unwrap().get(&element_type).cloned()
+    }
 }
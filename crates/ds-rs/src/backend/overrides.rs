@@ -0,0 +1,127 @@
+//! Configured replacements for the elements a backend picks
+//!
+//! Each backend hardcodes a factory for every role (sink, converter,
+//! decoder, ...). An `[elements]` table overrides those per backend:
+//!
+//! ```toml
+//! [elements.standard]
+//! videosink = "waylandsink"
+//! converter = "videoconvertscale"
+//! decoder = "avdec_h264"
+//! ```
+//!
+//! Entries under `[elements.all]` apply to every backend unless the
+//! backend's own table names the same role.
+//!
+//! The `decoder` is also what each source's `uridecodebin` plugs, chosen in
+//! that bin's `autoplug-select` so other pipelines keep their decoders.
+
+use super::BackendType;
+use crate::elements::DeepStreamElementType;
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElementOverrides {
+    pub all: HashMap<String, String>,
+    pub deepstream: HashMap<String, String>,
+    pub standard: HashMap<String, String>,
+    pub mock: HashMap<String, String>,
+}
+
+impl ElementOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.all.is_empty()
+            && self.deepstream.is_empty()
+            && self.standard.is_empty()
+            && self.mock.is_empty()
+    }
+
+    fn for_backend(&self, backend: BackendType) -> &HashMap<String, String> {
+        match backend {
+            BackendType::DeepStream => &self.deepstream,
+            BackendType::Standard => &self.standard,
+            BackendType::Mock => &self.mock,
+        }
+    }
+
+    /// Factory name per role for `backend`, without checking that the
+    /// factories exist
+    pub fn resolve(&self, backend: BackendType) -> Result<HashMap<DeepStreamElementType, String>> {
+        let mut resolved = HashMap::new();
+        // Backend entries go last so they win over `all`
+        for (role, factory) in self.all.iter().chain(self.for_backend(backend)) {
+            let element_type = DeepStreamElementType::from_role(role).ok_or_else(|| {
+                DeepStreamError::Configuration(format!(
+                    "Unknown element role '{}'; use one of {}",
+                    role,
+                    DeepStreamElementType::ROLES.join(", ")
+                ))
+            })?;
+            if factory.trim().is_empty() {
+                return Err(DeepStreamError::Configuration(format!(
+                    "Empty element name for role '{}'",
+                    role
+                )));
+            }
+            resolved.insert(element_type, factory.trim().to_string());
+        }
+        Ok(resolved)
+    }
+
+    /// [`resolve`](Self::resolve) and check every factory is installed
+    pub fn validate(&self, backend: BackendType) -> Result<HashMap<DeepStreamElementType, String>> {
+        let resolved = self.resolve(backend)?;
+        for (element_type, factory) in &resolved {
+            if gst::ElementFactory::find(factory).is_none() {
+                return Err(DeepStreamError::Configuration(format!(
+                    "Element '{}' configured for {} is not installed",
+                    factory,
+                    element_type.role()
+                )));
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_overrides() {
+        let overrides: ElementOverrides = toml::from_str(
+            r#"
+            [all]
+            videosink = "fakesink"
+            converter = "videoconvertscale"
+
+            [standard]
+            videosink = "waylandsink"
+            "#,
+        )
+        .unwrap();
+
+        let standard = overrides.resolve(BackendType::Standard).unwrap();
+        assert_eq!(standard[&DeepStreamElementType::VideoSink], "waylandsink");
+        assert_eq!(
+            standard[&DeepStreamElementType::VideoConvert],
+            "videoconvertscale"
+        );
+        let deepstream = overrides.resolve(BackendType::DeepStream).unwrap();
+        assert_eq!(deepstream[&DeepStreamElementType::VideoSink], "fakesink");
+    }
+
+    #[test]
+    fn test_unknown_role_rejected() {
+        let mut bad = ElementOverrides::default();
+        bad.mock
+            .insert("muxer2".to_string(), "identity".to_string());
+        assert!(bad.resolve(BackendType::Mock).is_err());
+        assert!(bad.resolve(BackendType::Standard).is_ok());
+    }
+}
@@ -0,0 +1,201 @@
+//! What the selected backend can do on this machine
+//!
+//! [`BackendManager::report`](super::BackendManager::report) collects the
+//! backend's capability flags, the factory it resolves for every element
+//! role and whether that factory is installed, and the hardware decoders in
+//! the registry. The report serializes to JSON for attaching to support
+//! tickets and prints as a table for people.
+
+use super::{BackendManager, BackendType, cache};
+use crate::elements::DeepStreamElementType;
+use crate::platform::PlatformInfo;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+use std::fmt;
+
+const ROLE_TYPES: [DeepStreamElementType; 8] = [
+    DeepStreamElementType::StreamMux,
+    DeepStreamElementType::Inference,
+    DeepStreamElementType::Tracker,
+    DeepStreamElementType::Tiler,
+    DeepStreamElementType::Osd,
+    DeepStreamElementType::VideoConvert,
+    DeepStreamElementType::VideoSink,
+    DeepStreamElementType::Decoder,
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedElement {
+    pub role: &'static str,
+    pub factory: String,
+    /// Set when the factory comes from the `[elements]` configuration
+    pub overridden: bool,
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityReport {
+    pub backend: BackendType,
+    pub platform: PlatformInfo,
+    pub gstreamer_version: String,
+    pub supports_inference: bool,
+    pub supports_tracking: bool,
+    pub supports_osd: bool,
+    pub supports_batching: bool,
+    pub supports_hardware_decode: bool,
+    pub max_batch_size: u32,
+    /// Factory used for each element role
+    pub elements: Vec<ResolvedElement>,
+    /// Hardware video decoders in the registry
+    pub hardware_decoders: Vec<String>,
+    pub available_backends: Vec<BackendType>,
+}
+
+impl CapabilityReport {
+    /// Roles whose factory is not installed
+    pub fn unresolved(&self) -> impl Iterator<Item = &ResolvedElement> {
+        self.elements.iter().filter(|element| !element.available)
+    }
+}
+
+impl BackendManager {
+    /// Capability matrix of the selected backend
+    pub fn report(&self) -> CapabilityReport {
+        let capabilities = self.capabilities();
+        let elements = ROLE_TYPES
+            .iter()
+            .map(|element_type| {
+                let configured = self.element_override(*element_type);
+                let factory = configured.clone().unwrap_or_else(|| {
+                    self.backend()
+                        .get_element_mapping(element_type.name())
+                        .unwrap_or(element_type.name())
+                        .to_string()
+                });
+                ResolvedElement {
+                    role: element_type.role(),
+                    available: cache::element_available(&factory),
+                    factory,
+                    overridden: configured.is_some(),
+                }
+            })
+            .collect();
+
+        CapabilityReport {
+            backend: self.backend_type(),
+            platform: self.platform().clone(),
+            gstreamer_version: gst::version_string().to_string(),
+            supports_inference: capabilities.supports_inference,
+            supports_tracking: capabilities.supports_tracking,
+            supports_osd: capabilities.supports_osd,
+            supports_batching: capabilities.supports_batching,
+            supports_hardware_decode: capabilities.supports_hardware_decode,
+            max_batch_size: capabilities.max_batch_size,
+            elements,
+            hardware_decoders: hardware_decoders(),
+            available_backends: super::detector::detect_available_backends(),
+        }
+    }
+}
+
+fn hardware_decoders() -> Vec<String> {
+    let mut decoders: Vec<String> = gst::ElementFactory::factories_with_type(
+        gst::ElementFactoryType::DECODER | gst::ElementFactoryType::MEDIA_VIDEO,
+        gst::Rank::NONE,
+    )
+    .into_iter()
+    .filter(|factory| factory.klass().contains("Hardware"))
+    .map(|factory| factory.name().to_string())
+    .collect();
+    decoders.sort();
+    decoders
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+impl fmt::Display for CapabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Backend:   {}", self.backend)?;
+        writeln!(f, "Platform:  {:?}", self.platform.platform)?;
+        if let Some(cuda) = &self.platform.cuda_version {
+            writeln!(f, "CUDA:      {}", cuda)?;
+        }
+        writeln!(f, "GStreamer: {}", self.gstreamer_version)?;
+        writeln!(f)?;
+        writeln!(f, "Inference:       {}", yes_no(self.supports_inference))?;
+        writeln!(f, "Tracking:        {}", yes_no(self.supports_tracking))?;
+        writeln!(f, "OSD:             {}", yes_no(self.supports_osd))?;
+        writeln!(
+            f,
+            "Batching:        {} (max {})",
+            yes_no(self.supports_batching),
+            self.max_batch_size
+        )?;
+        writeln!(
+            f,
+            "Hardware decode: {}",
+            yes_no(self.supports_hardware_decode)
+        )?;
+        writeln!(f)?;
+        writeln!(f, "Elements:")?;
+        for element in &self.elements {
+            write!(
+                f,
+                "  [{}] {:<10} {}",
+                if element.available { "ok  " } else { "FAIL" },
+                element.role,
+                element.factory
+            )?;
+            if element.overridden {
+                write!(f, " (configured)")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+        if self.hardware_decoders.is_empty() {
+            writeln!(f, "Hardware decoders: none")?;
+        } else {
+            writeln!(
+                f,
+                "Hardware decoders: {}",
+                self.hardware_decoders.join(", ")
+            )?;
+        }
+        let backends: Vec<&str> = self
+            .available_backends
+            .iter()
+            .map(|backend| backend.name())
+            .collect();
+        write!(f, "Available backends: {}", backends.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_report() {
+        let _ = gst::init();
+        let manager = BackendManager::with_backend(BackendType::Mock).unwrap();
+        let report = manager.report();
+
+        assert_eq!(report.backend, BackendType::Mock);
+        assert_eq!(report.elements.len(), DeepStreamElementType::ROLES.len());
+        let sink = report
+            .elements
+            .iter()
+            .find(|element| element.role == "videosink")
+            .unwrap();
+        assert_eq!(sink.factory, "fakesink");
+        assert!(!sink.overridden);
+        assert!(report.available_backends.contains(&BackendType::Mock));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["backend"], "Mock");
+        assert!(report.to_string().contains("videosink"));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Mean luma above which a frame is a flash
+const FLASH_LUMA: f64 = 128.0;
+
+/// Peak sample, as a share of full scale, above which audio is a beep
+const BEEP_LEVEL: f64 = 0.25;
+
+/// Every nth luma byte is averaged
+const LUMA_STEP: usize = 16;
+
+/// Configuration for [`run_avsync_bench`]
+#[derive(Debug, Clone)]
+pub struct AvSyncBenchConfig {
+    /// URI of a stream with flashes and beeps at the same time, such as a
+    /// source-videos `avsync` test pattern served over RTSP
+    pub input: String,
+    pub duration: Duration,
+    /// Time between flashes; a beep is matched to the flash nearest to it
+    /// within half of this
+    pub period: Duration,
+}
+
+impl AvSyncBenchConfig {
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            duration: Duration::from_secs(30),
+            period: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Offset of one beep from its flash
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AvSyncSample {
+    /// Seconds since the first flash
+    pub at_secs: f64,
+    /// Positive when the audio is late
+    pub offset_ms: f64,
+}
+
+/// A/V offset measured at the sinks over a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AvSyncReport {
+    pub input: String,
+    pub timestamp: f64,
+    pub samples: Vec<AvSyncSample>,
+    pub mean_offset_ms: f64,
+    pub min_offset_ms: f64,
+    pub max_offset_ms: f64,
+    /// Change of the offset per minute, from a least-squares fit
+    pub drift_ms_per_min: f64,
+    pub unmatched_flashes: usize,
+    pub unmatched_beeps: usize,
+}
+
+impl AvSyncReport {
+    /// Pair each beep with the nearest flash at most `max_offset` away
+    pub fn from_onsets(
+        input: impl Into<String>,
+        flashes: &[Duration],
+        beeps: &[Duration],
+        max_offset: Duration,
+    ) -> Self {
+        let mut used = vec![false; flashes.len()];
+        let mut samples = Vec::new();
+        let origin = flashes.first().copied().unwrap_or_default();
+        for &beep in beeps {
+            let nearest = flashes
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !used[*i])
+                .min_by_key(|(_, flash)| flash.abs_diff(beep));
+            let Some((i, &flash)) = nearest.filter(|(_, flash)| flash.abs_diff(beep) <= max_offset)
+            else {
+                continue;
+            };
+            used[i] = true;
+            samples.push(AvSyncSample {
+                at_secs: flash.saturating_sub(origin).as_secs_f64(),
+                offset_ms: (beep.as_secs_f64() - flash.as_secs_f64()) * 1000.0,
+            });
+        }
+
+        let offsets: Vec<f64> = samples.iter().map(|s| s.offset_ms).collect();
+        let mut report = Self {
+            input: input.into(),
+            timestamp: crate::timestamp(),
+            unmatched_flashes: flashes.len() - samples.len(),
+            unmatched_beeps: beeps.len() - samples.len(),
+            drift_ms_per_min: drift_per_min(&samples),
+            samples,
+            ..Default::default()
+        };
+        if !offsets.is_empty() {
+            report.mean_offset_ms = offsets.iter().sum::<f64>() / offsets.len() as f64;
+            report.min_offset_ms = offsets.iter().copied().fold(f64::INFINITY, f64::min);
+            report.max_offset_ms = offsets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        }
+        report
+    }
+}
+
+impl fmt::Display for AvSyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Input: {}", self.input)?;
+        if self.samples.is_empty() {
+            return write!(
+                f,
+                "No flash and beep pairs ({} flashes, {} beeps)",
+                self.unmatched_flashes, self.unmatched_beeps
+            );
+        }
+        writeln!(
+            f,
+            "A/V offset: {:+.1} ms mean, {:+.1} to {:+.1} ms over {} pairs (positive: audio late)",
+            self.mean_offset_ms,
+            self.min_offset_ms,
+            self.max_offset_ms,
+            self.samples.len()
+        )?;
+        writeln!(f, "Drift: {:+.2} ms/min", self.drift_ms_per_min)?;
+        write!(
+            f,
+            "Unmatched: {} flashes, {} beeps",
+            self.unmatched_flashes, self.unmatched_beeps
+        )
+    }
+}
+
+/// Least-squares slope of offset against time, per minute
+fn drift_per_min(samples: &[AvSyncSample]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let n = samples.len() as f64;
+    let mean_t = samples.iter().map(|s| s.at_secs).sum::<f64>() / n;
+    let mean_o = samples.iter().map(|s| s.offset_ms).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for s in samples {
+        covariance += (s.at_secs - mean_t) * (s.offset_ms - mean_o);
+        variance += (s.at_secs - mean_t).powi(2);
+    }
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance * 60.0
+    }
+}
+
+/// Rising edges of a signal, ignoring any within `holdoff` of the last
+/// one so a beep's oscillation counts once
+#[derive(Debug)]
+struct OnsetDetector {
+    holdoff: Duration,
+    last: Option<Duration>,
+    onsets: Vec<Duration>,
+}
+
+impl OnsetDetector {
+    fn new(holdoff: Duration) -> Self {
+        Self {
+            holdoff,
+            last: None,
+            onsets: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, active: bool, time: Duration) {
+        if active
+            && self
+                .last
+                .is_none_or(|last| time.saturating_sub(last) >= self.holdoff)
+        {
+            self.last = Some(time);
+            self.onsets.push(time);
+        }
+    }
+}
+
+/// Play the input to synchronized sinks and note the running time at
+/// which each flash and beep is rendered
+pub fn run_avsync_bench(config: &AvSyncBenchConfig) -> Result<AvSyncReport> {
+    gst::init()?;
+    let description = format!(
+        "uridecodebin uri=\"{}\" name=decode \
+         decode. ! queue ! videoconvert ! video/x-raw,format=GRAY8 ! \
+         fakesink name=video sync=true signal-handoffs=true \
+         decode. ! queue ! audioconvert ! audio/x-raw,format=S16LE,channels=1 ! \
+         fakesink name=audio sync=true signal-handoffs=true",
+        config.input
+    );
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| DeepStreamError::Pipeline("A/V sync pipeline is not a bin".to_string()))?;
+
+    let holdoff = config.period / 2;
+    let flashes = Arc::new(Mutex::new(OnsetDetector::new(holdoff)));
+    let beeps = Arc::new(Mutex::new(OnsetDetector::new(holdoff)));
+    let sink = |name: &str| {
+        pipeline
+            .by_name(name)
+            .ok_or_else(|| DeepStreamError::ElementNotFound {
+                element: name.to_string(),
+            })
+    };
+
+    let detector = flashes.clone();
+    sink("video")?.connect("handoff", false, move |values| {
+        if let (Ok(sink), Ok(buffer)) = (
+            values[0].get::<gst::Element>(),
+            values[1].get::<gst::Buffer>(),
+        ) && let Some(now) = render_time(&sink)
+            && let Ok(map) = buffer.map_readable()
+        {
+            let luma = map.iter().step_by(LUMA_STEP).map(|&y| y as f64);
+            let count = map.len().div_ceil(LUMA_STEP).max(1) as f64;
+            detector
+                .lock()
+                .unwrap()
+                .update(luma.sum::<f64>() / count > FLASH_LUMA, now);
+        }
+        None
+    });
+
+    let detector = beeps.clone();
+    sink("audio")?.connect("handoff", false, move |values| {
+        if let (Ok(sink), Ok(buffer), Ok(pad)) = (
+            values[0].get::<gst::Element>(),
+            values[1].get::<gst::Buffer>(),
+            values[2].get::<gst::Pad>(),
+        ) && let Some(now) = render_time(&sink)
+            && let Some(rate) = sample_rate(&pad)
+            && let Ok(map) = buffer.map_readable()
+        {
+            let threshold = BEEP_LEVEL * i16::MAX as f64;
+            // The buffer starts playing now; a beep starts at its first
+            // loud sample
+            let loud = map.chunks_exact(2).position(|bytes| {
+                i16::from_le_bytes([bytes[0], bytes[1]]).unsigned_abs() as f64 > threshold
+            });
+            if let Some(index) = loud {
+                let offset = Duration::from_nanos(index as u64 * 1_000_000_000 / rate as u64);
+                detector.lock().unwrap().update(true, now + offset);
+            }
+        }
+        None
+    });
+
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| DeepStreamError::Pipeline("Pipeline has no bus".to_string()))?;
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| DeepStreamError::StateChange(format!("{:?}", e)))?;
+
+    let started = Instant::now();
+    let mut failure = None;
+    while started.elapsed() < config.duration {
+        let message = bus.timed_pop_filtered(
+            gst::ClockTime::from_mseconds(200),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        match message.as_ref().map(|m| m.view()) {
+            Some(gst::MessageView::Eos(..)) => break,
+            Some(gst::MessageView::Error(err)) => {
+                failure = Some(err.error().to_string());
+                break;
+            }
+            _ => {}
+        }
+    }
+    let _ = pipeline.set_state(gst::State::Null);
+    if let Some(error) = failure {
+        return Err(DeepStreamError::Pipeline(error));
+    }
+
+    let flashes = flashes.lock().unwrap().onsets.clone();
+    let beeps = beeps.lock().unwrap().onsets.clone();
+    Ok(AvSyncReport::from_onsets(
+        config.input.clone(),
+        &flashes,
+        &beeps,
+        config.period / 2,
+    ))
+}
+
+/// Running time of the sink's clock, which is when a synchronized sink
+/// renders the buffer it hands off
+fn render_time(sink: &gst::Element) -> Option<Duration> {
+    let now = sink.clock()?.time();
+    let running = now.checked_sub(sink.base_time()?)?;
+    Some(Duration::from_nanos(running.nseconds()))
+}
+
+fn sample_rate(pad: &gst::Pad) -> Option<u32> {
+    let caps = pad.current_caps()?;
+    let rate = caps.structure(0)?.get::<i32>("rate").ok()?;
+    u32::try_from(rate).ok().filter(|&rate| rate > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Flashes every second with audio 40 ms late, 1 ms later each second,
+    /// and one beep without a flash
+    fn drifting_report() -> AvSyncReport {
+        let flashes = [ms(1000), ms(2000), ms(3000), ms(4000)];
+        let beeps = [ms(1040), ms(2041), ms(3042), ms(4043), ms(6000)];
+        AvSyncReport::from_onsets("rtsp://host/avsync", &flashes, &beeps, ms(500))
+    }
+
+    #[test]
+    fn test_onsets_debounced() {
+        let mut flashes = OnsetDetector::new(ms(500));
+        for t in [1000, 1033, 1066, 2000, 3000, 4000] {
+            flashes.update(true, ms(t));
+        }
+        flashes.update(false, ms(5000));
+        assert_eq!(flashes.onsets, [ms(1000), ms(2000), ms(3000), ms(4000)]);
+    }
+
+    #[test]
+    fn test_offsets() {
+        let report = drifting_report();
+        assert_eq!(report.samples.len(), 4);
+        assert_eq!(report.samples[1].at_secs, 1.0);
+        assert!((report.mean_offset_ms - 41.5).abs() < 1e-6);
+        assert!((report.min_offset_ms - 40.0).abs() < 1e-6);
+        assert!((report.max_offset_ms - 43.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_drift() {
+        let report = drifting_report();
+        assert!((report.drift_ms_per_min - 60.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unmatched_onsets_counted() {
+        let report = drifting_report();
+        assert_eq!((report.unmatched_flashes, report.unmatched_beeps), (0, 1));
+    }
+
+    #[test]
+    fn test_audio_ahead_is_negative() {
+        let early = AvSyncReport::from_onsets("x", &[ms(1000)], &[ms(980)], ms(500));
+        assert!((early.mean_offset_ms + 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_no_onsets_no_samples() {
+        assert!(
+            AvSyncReport::from_onsets("x", &[], &[], ms(500))
+                .samples
+                .is_empty()
+        );
+    }
+}
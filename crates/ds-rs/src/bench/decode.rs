@@ -0,0 +1,606 @@
+use super::CpuSampler;
+use crate::backend::{Backend, BackendType, deepstream::DeepStreamBackend};
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessesToUpdate, System};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Hardware decoders demoted when measuring the software path
+const HARDWARE_DECODERS: &[&str] = &[
+    "nvv4l2decoder",
+    "nvdec",
+    "nvh264dec",
+    "nvh265dec",
+    "nvh264sldec",
+    "nvh265sldec",
+    "nvav1dec",
+    "nvvp9dec",
+    "vah264dec",
+    "vah265dec",
+    "vaapih264dec",
+    "vaapih265dec",
+    "qsvh264dec",
+    "qsvh265dec",
+    "d3d11h264dec",
+    "d3d11h265dec",
+    "d3d12h264dec",
+    "d3d12h265dec",
+    "v4l2h264dec",
+    "v4l2h265dec",
+];
+
+const DEEPSTREAM_DECODERS: &[&str] = &["nvv4l2decoder", "nvdec"];
+const NVCODEC_DECODERS: &[&str] = &[
+    "nvh264dec",
+    "nvh265dec",
+    "nvav1dec",
+    "nvvp9dec",
+    "nvh264sldec",
+    "nvh265sldec",
+];
+
+/// A decode path to measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeTarget {
+    /// Demux and parse only, as the mock backend does not decode
+    Mock,
+    /// Software decoders with hardware decoders demoted
+    Standard,
+    /// DeepStream's nvv4l2decoder / nvdec
+    DeepStream,
+    /// GStreamer's nvcodec plugin (nvh264dec, ...)
+    NvCodec,
+}
+
+impl DecodeTarget {
+    pub fn all() -> [DecodeTarget; 4] {
+        [
+            DecodeTarget::Mock,
+            DecodeTarget::Standard,
+            DecodeTarget::DeepStream,
+            DecodeTarget::NvCodec,
+        ]
+    }
+
+    /// Backend this path corresponds to, if any
+    pub fn backend_type(&self) -> Option<BackendType> {
+        match self {
+            DecodeTarget::Mock => Some(BackendType::Mock),
+            DecodeTarget::Standard => Some(BackendType::Standard),
+            DecodeTarget::DeepStream => Some(BackendType::DeepStream),
+            DecodeTarget::NvCodec => None,
+        }
+    }
+
+    /// `FORCE_BACKEND` value that selects this path
+    pub fn force_backend(&self) -> &'static str {
+        match self {
+            DecodeTarget::Mock => "mock",
+            DecodeTarget::Standard | DecodeTarget::NvCodec => "standard",
+            DecodeTarget::DeepStream => "deepstream",
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        match self {
+            DecodeTarget::Mock => true,
+            DecodeTarget::Standard => gst::ElementFactory::find("decodebin").is_some(),
+            DecodeTarget::DeepStream => DeepStreamBackend::is_available(),
+            DecodeTarget::NvCodec => NVCODEC_DECODERS
+                .iter()
+                .any(|name| gst::ElementFactory::find(name).is_some()),
+        }
+    }
+}
+
+impl fmt::Display for DecodeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DecodeTarget::Mock => "mock",
+            DecodeTarget::Standard => "standard",
+            DecodeTarget::DeepStream => "deepstream",
+            DecodeTarget::NvCodec => "nvcodec",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for DecodeTarget {
+    type Err = DeepStreamError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "mock" => Ok(DecodeTarget::Mock),
+            "standard" => Ok(DecodeTarget::Standard),
+            "deepstream" => Ok(DecodeTarget::DeepStream),
+            "nvcodec" => Ok(DecodeTarget::NvCodec),
+            other => Err(DeepStreamError::InvalidInput(format!(
+                "Unknown decode target '{}' (expected mock, standard, deepstream or nvcodec)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Configuration for [`run_decode_bench`]
+#[derive(Debug, Clone)]
+pub struct DecodeBenchConfig {
+    /// File path or URI decoded by every target
+    pub input: String,
+    /// Paths to compare; unavailable ones are reported and skipped
+    pub targets: Vec<DecodeTarget>,
+    /// Upper bound per target; decoding also stops at end of stream
+    pub max_duration: Duration,
+}
+
+impl DecodeBenchConfig {
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            targets: DecodeTarget::all().to_vec(),
+            max_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Measurements for one decode path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodeBenchResult {
+    pub target: DecodeTarget,
+    pub force_backend: String,
+    pub available: bool,
+    pub error: Option<String>,
+    /// Decoder factories that ended up in the pipeline
+    pub decoders: Vec<String>,
+    pub frames: u64,
+    pub elapsed_secs: f64,
+    pub fps: f64,
+    pub cpu_usage_percent: f32,
+    /// Average GPU utilization from `nvidia-smi`, when available
+    pub gpu_usage_percent: Option<f32>,
+    pub gpu_memory_mb: Option<f64>,
+    pub peak_rss_mb: f64,
+}
+
+impl DecodeBenchResult {
+    fn unavailable(target: DecodeTarget, error: Option<String>) -> Self {
+        Self {
+            target,
+            force_backend: target.force_backend().to_string(),
+            available: false,
+            error,
+            decoders: Vec::new(),
+            frames: 0,
+            elapsed_secs: 0.0,
+            fps: 0.0,
+            cpu_usage_percent: 0.0,
+            gpu_usage_percent: None,
+            gpu_memory_mb: None,
+            peak_rss_mb: 0.0,
+        }
+    }
+}
+
+/// Side-by-side decode results for one input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodeBenchReport {
+    pub input: String,
+    pub timestamp: f64,
+    pub results: Vec<DecodeBenchResult>,
+}
+
+impl DecodeBenchReport {
+    /// Fastest path that actually decodes; the mock path is excluded
+    pub fn recommended(&self) -> Option<&DecodeBenchResult> {
+        self.results
+            .iter()
+            .filter(|r| r.available && r.error.is_none() && r.target != DecodeTarget::Mock)
+            .max_by(|a, b| a.fps.total_cmp(&b.fps))
+    }
+}
+
+impl fmt::Display for DecodeBenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Input: {}", self.input)?;
+        writeln!(
+            f,
+            "{:<11} {:>8} {:>9} {:>6} {:>6} {:>9} {:>9}  decoder",
+            "target", "frames", "fps", "cpu %", "gpu %", "gpu MB", "rss MB"
+        )?;
+        for r in &self.results {
+            if !r.available || r.error.is_some() {
+                writeln!(
+                    f,
+                    "{:<11} {}",
+                    r.target.to_string(),
+                    r.error.as_deref().unwrap_or("not available")
+                )?;
+                continue;
+            }
+
+            let optional =
+                |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.0}", v));
+            writeln!(
+                f,
+                "{:<11} {:>8} {:>9.1} {:>6.1} {:>6} {:>9} {:>9.1}  {}",
+                r.target.to_string(),
+                r.frames,
+                r.fps,
+                r.cpu_usage_percent,
+                optional(r.gpu_usage_percent.map(f64::from)),
+                optional(r.gpu_memory_mb),
+                r.peak_rss_mb,
+                r.decoders.join(",")
+            )?;
+        }
+
+        if let Some(best) = self.recommended() {
+            write!(
+                f,
+                "Recommended: FORCE_BACKEND={} ({})",
+                best.force_backend, best.target
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the same input through each target and compare.
+pub fn run_decode_bench(config: &DecodeBenchConfig) -> Result<DecodeBenchReport> {
+    gst::init()?;
+    let uri = to_uri(&config.input)?;
+
+    let mut results = Vec::with_capacity(config.targets.len());
+    for &target in &config.targets {
+        if !target.is_available() {
+            log::info!("Skipping {} decode: not available", target);
+            results.push(DecodeBenchResult::unavailable(target, None));
+            continue;
+        }
+
+        log::info!("Benchmarking {} decode of {}", target, uri);
+        let result = match bench_target(target, &uri, config.max_duration) {
+            Ok(result) => result,
+            Err(e) => {
+                let mut result = DecodeBenchResult::unavailable(target, Some(e.to_string()));
+                result.available = true;
+                result
+            }
+        };
+        results.push(result);
+    }
+
+    Ok(DecodeBenchReport {
+        input: config.input.clone(),
+        timestamp: crate::timestamp(),
+        results,
+    })
+}
+
+fn to_uri(input: &str) -> Result<String> {
+    if input.contains("://") {
+        return Ok(input.to_string());
+    }
+    let path = std::fs::canonicalize(input)?;
+    Ok(gst::glib::filename_to_uri(&path, None)?.to_string())
+}
+
+/// Temporarily changes decoder ranks so decodebin picks the wanted path
+struct RankGuard {
+    saved: Vec<(gst::PluginFeature, gst::Rank)>,
+}
+
+impl RankGuard {
+    fn for_target(target: DecodeTarget) -> Self {
+        let (demote, promote): (&[&str], &[&str]) = match target {
+            DecodeTarget::Mock => (&[], &[]),
+            DecodeTarget::Standard => (HARDWARE_DECODERS, &[]),
+            DecodeTarget::DeepStream => (&[], DEEPSTREAM_DECODERS),
+            DecodeTarget::NvCodec => (&[], NVCODEC_DECODERS),
+        };
+
+        let registry = gst::Registry::get();
+        let mut saved = Vec::new();
+        let mut set = |name: &str, rank: gst::Rank| {
+            if let Some(feature) = registry.lookup_feature(name) {
+                saved.push((feature.clone(), feature.rank()));
+                feature.set_rank(rank);
+            }
+        };
+
+        for name in demote {
+            set(name, gst::Rank::NONE);
+        }
+        for name in promote {
+            set(name, gst::Rank::PRIMARY + 100);
+        }
+
+        Self { saved }
+    }
+}
+
+impl Drop for RankGuard {
+    fn drop(&mut self) {
+        for (feature, rank) in self.saved.drain(..) {
+            feature.set_rank(rank);
+        }
+    }
+}
+
+fn bench_target(
+    target: DecodeTarget,
+    uri: &str,
+    max_duration: Duration,
+) -> Result<DecodeBenchResult> {
+    let _ranks = RankGuard::for_target(target);
+
+    let description = match target {
+        DecodeTarget::Mock => format!(
+            "urisourcebin uri=\"{}\" ! parsebin ! identity ! fakesink name=sink sync=false",
+            uri
+        ),
+        _ => format!(
+            "uridecodebin uri=\"{}\" caps=video/x-raw(ANY) ! fakesink name=sink sync=false",
+            uri
+        ),
+    };
+
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| DeepStreamError::Pipeline("Decode pipeline is not a bin".to_string()))?;
+    let sink = pipeline
+        .by_name("sink")
+        .ok_or_else(|| DeepStreamError::ElementNotFound {
+            element: "fakesink".to_string(),
+        })?;
+
+    let frames = Arc::new(AtomicU64::new(0));
+    let counter = frames.clone();
+    sink.static_pad("sink")
+        .ok_or_else(|| DeepStreamError::PadNotFound {
+            element: "fakesink".to_string(),
+            pad: "sink".to_string(),
+        })?
+        .add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            gst::PadProbeReturn::Ok
+        });
+
+    let mut cpu = CpuSampler::new();
+    let mut memory = MemorySampler::new();
+    let mut gpu = GpuSampler::default();
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| DeepStreamError::Pipeline("Pipeline has no bus".to_string()))?;
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| DeepStreamError::StateChange(format!("{:?}", e)))?;
+
+    let started = Instant::now();
+    let mut failure = None;
+    while started.elapsed() < max_duration {
+        let message = bus.timed_pop_filtered(
+            gst::ClockTime::from_mseconds(SAMPLE_INTERVAL.as_millis() as u64),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+
+        cpu.sample();
+        memory.sample();
+        gpu.sample();
+
+        match message.as_ref().map(|m| m.view()) {
+            Some(gst::MessageView::Eos(..)) => break,
+            Some(gst::MessageView::Error(err)) => {
+                failure = Some(err.error().to_string());
+                break;
+            }
+            _ => {}
+        }
+    }
+    let elapsed_secs = started.elapsed().as_secs_f64();
+    let decoders = decoder_factories(&pipeline);
+    let _ = pipeline.set_state(gst::State::Null);
+
+    if let Some(error) = failure {
+        return Err(DeepStreamError::Pipeline(error));
+    }
+
+    let frames = frames.load(Ordering::Relaxed);
+    Ok(DecodeBenchResult {
+        target,
+        force_backend: target.force_backend().to_string(),
+        available: true,
+        error: None,
+        decoders,
+        frames,
+        elapsed_secs,
+        fps: frames as f64 / elapsed_secs.max(f64::EPSILON),
+        cpu_usage_percent: cpu.average(),
+        gpu_usage_percent: gpu.average_utilization(),
+        gpu_memory_mb: gpu.peak_memory_mb(),
+        peak_rss_mb: memory.peak_mb(),
+    })
+}
+
+fn decoder_factories(pipeline: &gst::Pipeline) -> Vec<String> {
+    let mut names = Vec::new();
+    for element in pipeline.iterate_recurse().into_iter().flatten() {
+        let Some(factory) = element.factory() else {
+            continue;
+        };
+        if factory.klass().contains("Decoder") {
+            let name = factory.name().to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Tracks the peak resident memory of this process
+struct MemorySampler {
+    system: System,
+    pid: Option<Pid>,
+    peak_bytes: u64,
+}
+
+impl MemorySampler {
+    fn new() -> Self {
+        Self {
+            system: System::new(),
+            pid: sysinfo::get_current_pid().ok(),
+            peak_bytes: 0,
+        }
+    }
+
+    fn sample(&mut self) {
+        let Some(pid) = self.pid else {
+            return;
+        };
+        self.system
+            .refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        if let Some(process) = self.system.process(pid) {
+            self.peak_bytes = self.peak_bytes.max(process.memory());
+        }
+    }
+
+    fn peak_mb(&self) -> f64 {
+        self.peak_bytes as f64 / (1024.0 * 1024.0)
+    }
+}
+
+/// Samples NVIDIA GPU utilization through `nvidia-smi`, if installed
+#[derive(Default)]
+struct GpuSampler {
+    utilization: Vec<f32>,
+    peak_memory_mb: Option<f64>,
+    unavailable: bool,
+}
+
+impl GpuSampler {
+    fn sample(&mut self) {
+        if self.unavailable {
+            return;
+        }
+
+        let output = std::process::Command::new("nvidia-smi")
+            .args([
+                "--query-gpu=utilization.gpu,memory.used",
+                "--format=csv,noheader,nounits",
+            ])
+            .output();
+
+        let Ok(output) = output
+            .as_ref()
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        else {
+            self.unavailable = true;
+            return;
+        };
+
+        let Some((utilization, memory)) = output.lines().next().and_then(|l| l.split_once(','))
+        else {
+            self.unavailable = true;
+            return;
+        };
+
+        if let Ok(utilization) = utilization.trim().parse() {
+            self.utilization.push(utilization);
+        }
+        if let Ok(memory) = memory.trim().parse::<f64>() {
+            self.peak_memory_mb = Some(self.peak_memory_mb.unwrap_or(0.0).max(memory));
+        }
+    }
+
+    fn average_utilization(&self) -> Option<f32> {
+        if self.utilization.is_empty() {
+            None
+        } else {
+            Some(self.utilization.iter().sum::<f32>() / self.utilization.len() as f32)
+        }
+    }
+
+    fn peak_memory_mb(&self) -> Option<f64> {
+        self.peak_memory_mb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(target: DecodeTarget, fps: f64, error: Option<&str>) -> DecodeBenchResult {
+        DecodeBenchResult {
+            fps,
+            available: true,
+            error: error.map(str::to_string),
+            ..DecodeBenchResult::unavailable(target, None)
+        }
+    }
+
+    fn report(results: Vec<DecodeBenchResult>) -> DecodeBenchReport {
+        DecodeBenchReport {
+            input: "video.mp4".to_string(),
+            timestamp: 0.0,
+            results,
+        }
+    }
+
+    #[test]
+    fn test_recommendation_picks_fastest() {
+        let report = report(vec![
+            result(DecodeTarget::Standard, 240.0, None),
+            result(DecodeTarget::NvCodec, 600.0, None),
+        ]);
+
+        let best = report.recommended().unwrap();
+        assert_eq!(best.target, DecodeTarget::NvCodec);
+        assert_eq!(best.force_backend, "standard");
+    }
+
+    #[test]
+    fn test_recommendation_skips_mock() {
+        let report = report(vec![
+            result(DecodeTarget::Mock, 5000.0, None),
+            result(DecodeTarget::Standard, 240.0, None),
+        ]);
+
+        assert_eq!(report.recommended().unwrap().target, DecodeTarget::Standard);
+    }
+
+    #[test]
+    fn test_recommendation_skips_failures() {
+        let report = report(vec![
+            result(DecodeTarget::Standard, 240.0, None),
+            result(DecodeTarget::DeepStream, 900.0, Some("no GPU")),
+        ]);
+
+        assert_eq!(report.recommended().unwrap().target, DecodeTarget::Standard);
+    }
+
+    #[test]
+    fn test_mock_target_always_available() {
+        let _ = gst::init();
+        assert!(DecodeTarget::Mock.is_available());
+        assert_eq!(
+            DecodeTarget::DeepStream.backend_type(),
+            Some(BackendType::DeepStream)
+        );
+        assert_eq!(DecodeTarget::NvCodec.backend_type(), None);
+        assert_eq!(
+            "NvCodec".parse::<DecodeTarget>().unwrap(),
+            DecodeTarget::NvCodec
+        );
+        assert!("vulkan".parse::<DecodeTarget>().is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use super::{CpuSampler, LatencyStats, load_frames};
+use crate::error::{DeepStreamError, Result};
+use gstcpuinfer::detector::{DetectorConfig, ExecutionProvider, OnnxDetector};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// One detector configuration to measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchVariant {
+    pub threads: usize,
+    /// Frames submitted per step. The detector has no batched entry point,
+    /// so a batch is run back to back and timed as one step.
+    pub batch_size: usize,
+    pub execution_provider: ExecutionProvider,
+}
+
+impl BenchVariant {
+    /// Every combination of the given settings
+    pub fn matrix(
+        threads: &[usize],
+        batch_sizes: &[usize],
+        providers: &[ExecutionProvider],
+    ) -> Vec<Self> {
+        let mut variants = Vec::new();
+        for &execution_provider in providers {
+            for &threads in threads {
+                for &batch_size in batch_sizes {
+                    variants.push(Self {
+                        threads,
+                        batch_size: batch_size.max(1),
+                        execution_provider,
+                    });
+                }
+            }
+        }
+        variants
+    }
+}
+
+impl Default for BenchVariant {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            batch_size: 1,
+            execution_provider: ExecutionProvider::Cpu,
+        }
+    }
+}
+
+/// Configuration for [`run_inference_bench`]
+#[derive(Debug, Clone)]
+pub struct InferenceBenchConfig {
+    /// Detector settings shared by all variants; threads and provider are overridden
+    pub detector: DetectorConfig,
+    /// Image files, video files or URIs; empty uses one synthetic frame
+    pub inputs: Vec<String>,
+    /// Frames decoded from each video input
+    pub max_frames_per_input: usize,
+    /// Timed duration per variant
+    pub duration: Duration,
+    /// Untimed steps run before measuring
+    pub warmup_steps: usize,
+    pub variants: Vec<BenchVariant>,
+}
+
+impl Default for InferenceBenchConfig {
+    fn default() -> Self {
+        Self {
+            detector: DetectorConfig::default(),
+            inputs: Vec::new(),
+            max_frames_per_input: 100,
+            duration: Duration::from_secs(10),
+            warmup_steps: 3,
+            variants: vec![BenchVariant::default()],
+        }
+    }
+}
+
+/// Measurements for one variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceBenchResult {
+    pub variant: BenchVariant,
+    /// False when the model could not be loaded and the detector ran in mock mode
+    pub model_loaded: bool,
+    pub model_load_ms: f64,
+    pub frames: usize,
+    pub elapsed_secs: f64,
+    pub fps: f64,
+    /// Per-frame latency (step latency divided by batch size)
+    pub frame_latency: LatencyStats,
+    /// Average system-wide CPU usage while running, in percent
+    pub cpu_usage_percent: f32,
+}
+
+/// Results of a benchmark run across all variants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceBenchReport {
+    pub model: Option<String>,
+    pub input_frames: usize,
+    pub duration_secs: f64,
+    pub timestamp: f64,
+    pub results: Vec<InferenceBenchResult>,
+}
+
+impl fmt::Display for InferenceBenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Model: {} ({} input frames, {:.0}s per variant)",
+            self.model.as_deref().unwrap_or("<none>"),
+            self.input_frames,
+            self.duration_secs
+        )?;
+        writeln!(
+            f,
+            "{:<9} {:>7} {:>5} {:>9} {:>8} {:>8} {:>8} {:>8} {:>6}",
+            "ep", "threads", "batch", "load ms", "fps", "p50 ms", "p95 ms", "p99 ms", "cpu %"
+        )?;
+        for r in &self.results {
+            writeln!(
+                f,
+                "{:<9} {:>7} {:>5} {:>9.1} {:>8.1} {:>8.2} {:>8.2} {:>8.2} {:>6.1}{}",
+                r.variant.execution_provider.to_string(),
+                r.variant.threads,
+                r.variant.batch_size,
+                r.model_load_ms,
+                r.fps,
+                r.frame_latency.p50_ms,
+                r.frame_latency.p95_ms,
+                r.frame_latency.p99_ms,
+                r.cpu_usage_percent,
+                if r.model_loaded { "" } else { " (mock)" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the detector over the configured inputs once per variant.
+pub fn run_inference_bench(config: &InferenceBenchConfig) -> Result<InferenceBenchReport> {
+    if config.variants.is_empty() {
+        return Err(DeepStreamError::InvalidInput(
+            "No benchmark variants configured".to_string(),
+        ));
+    }
+
+    let mut frames = Vec::new();
+    for input in &config.inputs {
+        frames.extend(load_frames(input, config.max_frames_per_input)?);
+    }
+    if frames.is_empty() {
+        frames.push(DynamicImage::new_rgb8(
+            config.detector.input_width,
+            config.detector.input_height,
+        ));
+    }
+
+    let mut results = Vec::with_capacity(config.variants.len());
+    for variant in &config.variants {
+        log::info!(
+            "Benchmarking {} threads, batch {}, {}",
+            variant.threads,
+            variant.batch_size,
+            variant.execution_provider
+        );
+        results.push(bench_variant(config, *variant, &frames)?);
+    }
+
+    Ok(InferenceBenchReport {
+        model: config.detector.model_path.clone(),
+        input_frames: frames.len(),
+        duration_secs: config.duration.as_secs_f64(),
+        timestamp: crate::timestamp(),
+        results,
+    })
+}
+
+fn bench_variant(
+    config: &InferenceBenchConfig,
+    variant: BenchVariant,
+    frames: &[DynamicImage],
+) -> Result<InferenceBenchResult> {
+    let detector_config = DetectorConfig {
+        num_threads: variant.threads,
+        execution_provider: variant.execution_provider,
+        ..config.detector.clone()
+    };
+
+    let load_started = Instant::now();
+    let detector = OnnxDetector::new_with_config(detector_config)?;
+    let model_load_ms = load_started.elapsed().as_secs_f64() * 1000.0;
+
+    let batch_size = variant.batch_size.max(1);
+    let mut inputs = frames.iter().cycle();
+
+    for _ in 0..config.warmup_steps {
+        for frame in inputs.by_ref().take(batch_size) {
+            detector.detect(frame)?;
+        }
+    }
+
+    let mut cpu = CpuSampler::new();
+    let mut last_sample = Instant::now();
+    let mut latencies = Vec::new();
+    let mut processed = 0;
+
+    let started = Instant::now();
+    while started.elapsed() < config.duration {
+        let step_started = Instant::now();
+        for frame in inputs.by_ref().take(batch_size) {
+            detector.detect(frame)?;
+        }
+        latencies.push(step_started.elapsed() / batch_size as u32);
+        processed += batch_size;
+
+        if last_sample.elapsed() >= CPU_SAMPLE_INTERVAL {
+            cpu.sample();
+            last_sample = Instant::now();
+        }
+    }
+    let elapsed_secs = started.elapsed().as_secs_f64();
+    cpu.sample();
+
+    Ok(InferenceBenchResult {
+        variant,
+        model_loaded: detector.is_model_loaded(),
+        model_load_ms,
+        frames: processed,
+        elapsed_secs,
+        fps: processed as f64 / elapsed_secs.max(f64::EPSILON),
+        frame_latency: LatencyStats::from_samples(&latencies),
+        cpu_usage_percent: cpu.average(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_matrix() {
+        let variants = BenchVariant::matrix(
+            &[1, 4],
+            &[1, 0],
+            &[ExecutionProvider::Cpu, ExecutionProvider::Cuda],
+        );
+
+        assert_eq!(variants.len(), 8);
+        assert!(variants.iter().all(|v| v.batch_size >= 1));
+        assert_eq!(variants[0].execution_provider, ExecutionProvider::Cpu);
+        assert_eq!(variants[7].execution_provider, ExecutionProvider::Cuda);
+    }
+
+    #[test]
+    fn test_empty_variants_rejected() {
+        let config = InferenceBenchConfig {
+            variants: vec![],
+            ..Default::default()
+        };
+        assert!(run_inference_bench(&config).is_err());
+    }
+}
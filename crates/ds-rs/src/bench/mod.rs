@@ -0,0 +1,204 @@
+//! Benchmarks for comparing configurations on the current machine.
+//!
+//! Results are plain serde structs so CI can store the JSON output of one
+//! run and diff it against the next.
+
+pub mod avsync;
+pub mod decode;
+#[cfg(feature = "cpu_vision")]
+pub mod inference;
+
+pub use avsync::{AvSyncBenchConfig, AvSyncReport, AvSyncSample, run_avsync_bench};
+pub use decode::{
+    DecodeBenchConfig, DecodeBenchReport, DecodeBenchResult, DecodeTarget, run_decode_bench,
+};
+
+#[cfg(feature = "cpu_vision")]
+pub use inference::{
+    BenchVariant, InferenceBenchConfig, InferenceBenchReport, InferenceBenchResult,
+    run_inference_bench,
+};
+
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use image::{DynamicImage, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use sysinfo::System;
+
+/// Latency distribution in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(|a, b| a.total_cmp(b));
+
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * ms.len() as f64).ceil() as usize;
+            ms[rank.clamp(1, ms.len()) - 1]
+        };
+
+        Self {
+            samples: ms.len(),
+            mean_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            min_ms: ms[0],
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: ms[ms.len() - 1],
+        }
+    }
+}
+
+/// Averages system-wide CPU usage over a benchmark run
+pub(crate) struct CpuSampler {
+    system: System,
+    total: f64,
+    count: usize,
+}
+
+impl CpuSampler {
+    pub fn new() -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+        Self {
+            system,
+            total: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn sample(&mut self) {
+        self.system.refresh_cpu_usage();
+        let cpus = self.system.cpus();
+        if !cpus.is_empty() {
+            self.total +=
+                cpus.iter().map(|cpu| cpu.cpu_usage() as f64).sum::<f64>() / cpus.len() as f64;
+            self.count += 1;
+        }
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.total / self.count as f64) as f32
+        }
+    }
+}
+
+/// Load benchmark frames from an image file, or decode up to `max_frames`
+/// from a video file or URI.
+pub fn load_frames(input: &str, max_frames: usize) -> Result<Vec<DynamicImage>> {
+    let path = Path::new(input);
+    if path.exists() && image::ImageFormat::from_path(path).is_ok() {
+        let image = image::open(path).map_err(|e| {
+            DeepStreamError::InvalidInput(format!("Failed to open image {}: {}", input, e))
+        })?;
+        return Ok(vec![image]);
+    }
+
+    let uri = if input.contains("://") {
+        input.to_string()
+    } else {
+        let absolute = std::fs::canonicalize(path)?;
+        format!("file://{}", absolute.display())
+    };
+    decode_frames(&uri, max_frames)
+}
+
+fn decode_frames(uri: &str, max_frames: usize) -> Result<Vec<DynamicImage>> {
+    let description = format!(
+        "uridecodebin uri=\"{}\" ! videoconvert ! video/x-raw,format=RGB ! \
+         appsink name=sink sync=false",
+        uri
+    );
+    let pipeline = gst::parse::launch(&description)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| DeepStreamError::Pipeline("Decode pipeline is not a bin".to_string()))?;
+    let sink = pipeline
+        .by_name("sink")
+        .and_then(|sink| sink.downcast::<gst_app::AppSink>().ok())
+        .ok_or_else(|| DeepStreamError::ElementNotFound {
+            element: "appsink".to_string(),
+        })?;
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| DeepStreamError::StateChange(format!("{:?}", e)))?;
+
+    let mut frames = Vec::new();
+    while frames.len() < max_frames {
+        let Some(sample) = sink.try_pull_sample(gst::ClockTime::from_seconds(5)) else {
+            break;
+        };
+        if let Some(frame) = sample_to_image(&sample) {
+            frames.push(frame);
+        }
+    }
+
+    let _ = pipeline.set_state(gst::State::Null);
+
+    if frames.is_empty() {
+        return Err(DeepStreamError::ProcessingFailed {
+            reason: format!("No frames decoded from {}", uri),
+        });
+    }
+    Ok(frames)
+}
+
+fn sample_to_image(sample: &gst::Sample) -> Option<DynamicImage> {
+    let info = gst_video::VideoInfo::from_caps(sample.caps()?).ok()?;
+    let buffer = sample.buffer()?;
+    let map = buffer.map_readable().ok()?;
+
+    let (width, height) = (info.width() as usize, info.height() as usize);
+    let stride = info.stride()[0] as usize;
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for row in map.as_slice().chunks(stride).take(height) {
+        pixels.extend_from_slice(row.get(..width * 3)?);
+    }
+
+    RgbImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageRgb8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(&samples);
+
+        assert_eq!(stats.samples, 100);
+        assert_eq!(stats.min_ms, 1.0);
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p95_ms, 95.0);
+        assert_eq!(stats.p99_ms, 99.0);
+        assert_eq!(stats.max_ms, 100.0);
+        assert!((stats.mean_ms - 50.5).abs() < 1e-9);
+
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+    }
+}
@@ -1,9 +1,23 @@
+pub mod nvinfer;
+
+use crate::app::config::DemoConfig;
+use crate::backend::{ElementOverrides, GpuConfig};
 use crate::error::{DeepStreamError, Result};
+use crate::inference::InferenceFilter;
+use crate::output::RecordingConfig;
+use crate::pipeline::DeadlineConfig;
+use crate::rules::RulesConfig;
+use crate::source::{
+    BatchPolicy, CredentialSpec, RecoveryPolicies, TimestampAlignment, VideoCorrection,
+};
+use crate::tracking::TrackerAlgorithmConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+pub use nvinfer::{ClassAttributes, ConfigVersion, NvInferConfig, NvInferProperties};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceConfig {
     #[serde(rename = "property")]
@@ -16,7 +30,7 @@ pub struct InferenceConfig {
     pub secondary_gies: Option<Vec<GieConfig>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PropertyValue {
     String(String),
@@ -59,7 +73,7 @@ pub struct GieConfig {
     pub nvbuf_memory_type: Option<i32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TrackerConfig {
     pub enable: bool,
     pub tracker_width: u32,
@@ -85,6 +99,53 @@ pub struct ApplicationConfig {
     pub tiler: Option<TilerConfig>,
     pub inference: Option<InferenceConfig>,
     pub tracker: Option<TrackerConfig>,
+
+    /// Software tracker used when nvtracker is unavailable
+    #[serde(default)]
+    pub tracking: Option<TrackerAlgorithmConfig>,
+
+    /// Event rules and zones, see [`crate::rules`]
+    #[serde(default)]
+    pub rules: Option<RulesConfig>,
+
+    /// Reconnect behavior per source kind and per source URI
+    #[serde(default)]
+    pub recovery: Option<RecoveryPolicies>,
+
+    /// Streammux batch size and push timeout following the active source
+    /// count
+    #[serde(default)]
+    pub batching: Option<BatchPolicy>,
+
+    /// Timestamp alignment of live and file sources sharing the streammux
+    #[serde(default)]
+    pub alignment: Option<TimestampAlignment>,
+
+    /// Named credentials for `{cred:name}` source URIs, see
+    /// [`CredentialStore::from_config`](crate::source::CredentialStore::from_config);
+    /// never written back out
+    #[serde(default, skip_serializing)]
+    pub credentials: HashMap<String, CredentialSpec>,
+
+    /// Leaky queues and late-frame dropping for software backends
+    #[serde(default)]
+    pub deadline: Option<DeadlineConfig>,
+
+    /// Segmented recording and upload to S3-compatible storage
+    #[serde(default)]
+    pub recording: Option<RecordingConfig>,
+
+    /// GPU of the shared elements and placement of sources across GPUs
+    #[serde(default)]
+    pub gpu: Option<GpuConfig>,
+
+    /// Elements to use instead of each backend's defaults, by role
+    #[serde(default, skip_serializing_if = "ElementOverrides::is_empty")]
+    pub elements: ElementOverrides,
+
+    /// Source timers of the runtime demo application
+    #[serde(default)]
+    pub demo: Option<DemoConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +166,14 @@ pub struct SourceConfig {
     pub num_sources: u32,
     pub gpu_id: u32,
     pub cudadec_mem_type: i32,
+
+    /// Inference ROIs (`rois`) and class allow-list (`allowed_classes`)
+    #[serde(default, flatten)]
+    pub inference: InferenceFilter,
+
+    /// Deinterlacing, rotation, crop and aspect applied before the streammux
+    #[serde(default, skip_serializing_if = "VideoCorrection::is_identity")]
+    pub correction: VideoCorrection,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -190,6 +259,8 @@ impl ApplicationConfig {
                 num_sources: 1,
                 gpu_id: 0,
                 cudadec_mem_type: 0,
+                inference: InferenceFilter::default(),
+                correction: VideoCorrection::default(),
             }],
             sink: SinkConfig {
                 enable: true,
@@ -217,10 +288,22 @@ impl ApplicationConfig {
             tiler: None,
             inference: None,
             tracker: None,
+            tracking: None,
+            rules: None,
+            recovery: None,
+            batching: None,
+            alignment: None,
+            credentials: HashMap::new(),
+            deadline: None,
+            recording: None,
+            gpu: None,
+            elements: ElementOverrides::default(),
+            demo: None,
         }
     }
 }
 
+/// Flat `group:key` view of a key file. See [`NvInferConfig`] for typed parsing.
 pub fn parse_deepstream_config_file(path: &Path) -> Result<HashMap<String, String>> {
     let contents = fs::read_to_string(path)?;
     let mut config = HashMap::new();
@@ -283,6 +366,78 @@ mod tests {
         assert!(parsed.is_ok());
     }
 
+    #[test]
+    fn test_source_inference_filter() {
+        let source: SourceConfig = toml::from_str(
+            r#"
+            enable = true
+            uri = "rtsp://camera/stream"
+            num_sources = 1
+            gpu_id = 0
+            cudadec_mem_type = 0
+            allowed_classes = ["person", "car"]
+            rois = [{ x = 0, y = 200, width = 1920, height = 880 }]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(source.inference.allowed_classes, vec!["person", "car"]);
+        assert_eq!(source.inference.rois[0].y, 200);
+        assert!(ApplicationConfig::default().sources[0].inference.is_empty());
+    }
+
+    #[test]
+    fn test_tracking_algorithm_selection() {
+        use crate::tracking::TrackerAlgorithmKind;
+
+        let mut toml_str = toml::to_string(&ApplicationConfig::default()).unwrap();
+        toml_str.push_str("\n[tracking]\nalgorithm = \"bytetrack\"\nmin_hits = 2\n");
+
+        let config: ApplicationConfig = toml::from_str(&toml_str).unwrap();
+        let tracking = config.tracking.unwrap();
+        assert_eq!(tracking.algorithm, TrackerAlgorithmKind::ByteTrack);
+        assert_eq!(tracking.min_hits, 2);
+        assert_eq!(tracking.build().kind(), TrackerAlgorithmKind::ByteTrack);
+    }
+
+    #[test]
+    fn test_recovery_policies() {
+        let mut toml_str = toml::to_string(&ApplicationConfig::default()).unwrap();
+        toml_str.push_str(
+            "\n[recovery.file]\nmax_retries = 1\n\n[recovery.sources.\"rtsp://cam/1\"]\nmax_retries = 10\n",
+        );
+
+        let config: ApplicationConfig = toml::from_str(&toml_str).unwrap();
+        let recovery = config.recovery.unwrap();
+        assert_eq!(recovery.for_uri("file:///a.mp4").max_retries, Some(1));
+        assert_eq!(recovery.for_uri("rtsp://cam/1").max_retries, Some(10));
+        assert_eq!(recovery.for_uri("rtsp://cam/2").max_retries, None);
+    }
+
+    #[test]
+    fn test_static_demo() {
+        use crate::app::config::DemoMode;
+
+        let mut toml_str = toml::to_string(&ApplicationConfig::default()).unwrap();
+        toml_str.push_str("\n[demo]\nmode = \"static\"\nmax_sources = 8\n");
+
+        let config: ApplicationConfig = toml::from_str(&toml_str).unwrap();
+        let demo = config.demo.unwrap();
+        assert_eq!(demo.mode, DemoMode::Static);
+        assert_eq!(demo.max_sources, 8);
+        assert_eq!(demo.add_interval_secs, 10);
+        assert!(demo.validate().is_ok());
+    }
+
+    #[test]
+    fn test_demo_rejects_zero_interval() {
+        let demo = crate::app::config::DemoConfig {
+            add_interval_secs: 0,
+            ..Default::default()
+        };
+        assert!(demo.validate().is_err());
+    }
+
     #[test]
     fn test_parse_deepstream_config() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -0,0 +1,883 @@
+//! Typed reader and writer for nvinfer key-file configs.
+//!
+//! Handles the `[property]`, `[class-attrs-all]`, `[class-attrs-<id>]` and
+//! `[tracker]` groups. Keys this module does not model are kept per section
+//! and written back, so a parse/write round trip does not lose settings.
+
+use super::{ApplicationConfig, TrackerConfig};
+use crate::error::{DeepStreamError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Key layout of the class attribute groups
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigVersion {
+    /// DeepStream 4.x and earlier: `threshold` in `[class-attrs-*]`
+    Legacy,
+    /// DeepStream 5.0+: `pre-cluster-threshold` / `post-cluster-threshold`
+    #[default]
+    Current,
+}
+
+/// `network-mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkMode {
+    #[default]
+    Fp32,
+    Int8,
+    Fp16,
+}
+
+/// `network-type`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkType {
+    #[default]
+    Detector,
+    Classifier,
+    Segmentation,
+    InstanceSegmentation,
+}
+
+/// `cluster-mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClusterMode {
+    GroupRectangles,
+    Dbscan,
+    #[default]
+    Nms,
+    DbscanNmsHybrid,
+    NoClustering,
+}
+
+/// `model-color-format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModelColorFormat {
+    #[default]
+    Rgb,
+    Bgr,
+    Gray,
+}
+
+/// `process-mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessMode {
+    #[default]
+    Primary,
+    Secondary,
+}
+
+macro_rules! numeric_enum {
+    ($ty:ident { $($value:literal => $variant:ident),+ $(,)? }) => {
+        impl $ty {
+            fn from_code(key: &str, code: u32) -> Result<Self> {
+                match code {
+                    $($value => Ok($ty::$variant),)+
+                    _ => Err(invalid("property", key, &code.to_string())),
+                }
+            }
+
+            fn code(&self) -> u32 {
+                match self {
+                    $($ty::$variant => $value,)+
+                }
+            }
+        }
+    };
+}
+
+numeric_enum!(NetworkMode { 0 => Fp32, 1 => Int8, 2 => Fp16 });
+numeric_enum!(NetworkType {
+    0 => Detector,
+    1 => Classifier,
+    2 => Segmentation,
+    3 => InstanceSegmentation,
+});
+numeric_enum!(ClusterMode {
+    0 => GroupRectangles,
+    1 => Dbscan,
+    2 => Nms,
+    3 => DbscanNmsHybrid,
+    4 => NoClustering,
+});
+numeric_enum!(ModelColorFormat { 0 => Rgb, 1 => Bgr, 2 => Gray });
+numeric_enum!(ProcessMode { 1 => Primary, 2 => Secondary });
+
+/// The `[property]` group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvInferProperties {
+    pub gpu_id: u32,
+    pub net_scale_factor: f64,
+    /// Per-channel mean offsets
+    pub offsets: Vec<f64>,
+    pub model_color_format: ModelColorFormat,
+    pub onnx_file: Option<String>,
+    pub model_engine_file: Option<String>,
+    pub model_file: Option<String>,
+    pub proto_file: Option<String>,
+    pub tlt_encoded_model: Option<String>,
+    pub tlt_model_key: Option<String>,
+    pub int8_calib_file: Option<String>,
+    pub labelfile_path: Option<String>,
+    pub batch_size: u32,
+    pub network_mode: NetworkMode,
+    pub network_type: NetworkType,
+    pub num_detected_classes: Option<u32>,
+    pub interval: u32,
+    pub gie_unique_id: u32,
+    pub process_mode: ProcessMode,
+    pub cluster_mode: ClusterMode,
+    pub maintain_aspect_ratio: bool,
+    pub symmetric_padding: bool,
+    pub output_blob_names: Vec<String>,
+    pub parse_bbox_func_name: Option<String>,
+    pub custom_lib_path: Option<String>,
+    pub operate_on_gie_id: Option<i32>,
+    pub operate_on_class_ids: Vec<u32>,
+    /// Keys not modelled above
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Default for NvInferProperties {
+    fn default() -> Self {
+        Self {
+            gpu_id: 0,
+            net_scale_factor: 1.0,
+            offsets: Vec::new(),
+            model_color_format: ModelColorFormat::default(),
+            onnx_file: None,
+            model_engine_file: None,
+            model_file: None,
+            proto_file: None,
+            tlt_encoded_model: None,
+            tlt_model_key: None,
+            int8_calib_file: None,
+            labelfile_path: None,
+            batch_size: 1,
+            network_mode: NetworkMode::default(),
+            network_type: NetworkType::default(),
+            num_detected_classes: None,
+            interval: 0,
+            gie_unique_id: 1,
+            process_mode: ProcessMode::default(),
+            cluster_mode: ClusterMode::default(),
+            maintain_aspect_ratio: false,
+            symmetric_padding: false,
+            output_blob_names: Vec::new(),
+            parse_bbox_func_name: None,
+            custom_lib_path: None,
+            operate_on_gie_id: None,
+            operate_on_class_ids: Vec::new(),
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+impl NvInferProperties {
+    fn has_model(&self) -> bool {
+        self.onnx_file.is_some()
+            || self.model_engine_file.is_some()
+            || self.model_file.is_some()
+            || self.tlt_encoded_model.is_some()
+            || self.extra.contains_key("custom-network-config")
+    }
+}
+
+/// A `[class-attrs-all]` or `[class-attrs-<id>]` group
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassAttributes {
+    pub pre_cluster_threshold: Option<f32>,
+    pub post_cluster_threshold: Option<f32>,
+    pub nms_iou_threshold: Option<f32>,
+    pub topk: Option<i32>,
+    pub eps: Option<f32>,
+    pub group_threshold: Option<u32>,
+    pub min_boxes: Option<u32>,
+    pub detected_min_w: Option<u32>,
+    pub detected_min_h: Option<u32>,
+    pub detected_max_w: Option<u32>,
+    pub detected_max_h: Option<u32>,
+    pub roi_top_offset: Option<u32>,
+    pub roi_bottom_offset: Option<u32>,
+    pub extra: BTreeMap<String, String>,
+}
+
+/// A parsed nvinfer config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NvInferConfig {
+    pub version: ConfigVersion,
+    pub property: NvInferProperties,
+    pub class_attrs_all: Option<ClassAttributes>,
+    /// `[class-attrs-<id>]` groups keyed by class id
+    pub class_attrs: BTreeMap<u32, ClassAttributes>,
+    /// `[tracker]` group, as found in deepstream-app style files
+    pub tracker: Option<TrackerConfig>,
+    /// Groups this module does not model, kept for round trips
+    pub other_sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+type Section = BTreeMap<String, String>;
+
+fn invalid(section: &str, key: &str, value: &str) -> DeepStreamError {
+    DeepStreamError::Configuration(format!("[{}] {}: invalid value '{}'", section, key, value))
+}
+
+fn take<T: FromStr>(section: &mut Section, name: &str, key: &str) -> Result<Option<T>> {
+    match section.remove(key) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid(name, key, &value)),
+        None => Ok(None),
+    }
+}
+
+fn take_bool(section: &mut Section, name: &str, key: &str) -> Result<Option<bool>> {
+    match section.remove(key) {
+        Some(value) => match value.to_ascii_lowercase().as_str() {
+            "1" | "true" => Ok(Some(true)),
+            "0" | "false" => Ok(Some(false)),
+            _ => Err(invalid(name, key, &value)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn take_list<T: FromStr>(section: &mut Section, name: &str, key: &str) -> Result<Vec<T>> {
+    match section.remove(key) {
+        Some(value) => value
+            .split(';')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().map_err(|_| invalid(name, key, &value)))
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Split a key file into groups, keeping the last value of repeated keys.
+fn parse_sections(contents: &str) -> Result<Vec<(String, Section)>> {
+    let mut sections: Vec<(String, Section)> = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..line.len() - 1].trim().to_string(), Section::new()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(DeepStreamError::Configuration(format!(
+                "line {}: expected key=value, got '{}'",
+                number + 1,
+                line
+            )));
+        };
+        let Some((_, section)) = sections.last_mut() else {
+            return Err(DeepStreamError::Configuration(format!(
+                "line {}: '{}' appears before any [group]",
+                number + 1,
+                key.trim()
+            )));
+        };
+        section.insert(key.trim().to_string(), value.trim().to_string());
+    }
+
+    Ok(sections)
+}
+
+impl NvInferProperties {
+    fn parse(mut s: Section) -> Result<Self> {
+        let n = "property";
+        let d = Self::default();
+
+        Ok(Self {
+            gpu_id: take(&mut s, n, "gpu-id")?.unwrap_or(d.gpu_id),
+            net_scale_factor: take(&mut s, n, "net-scale-factor")?.unwrap_or(d.net_scale_factor),
+            offsets: take_list(&mut s, n, "offsets")?,
+            model_color_format: match take(&mut s, n, "model-color-format")? {
+                Some(code) => ModelColorFormat::from_code("model-color-format", code)?,
+                None => d.model_color_format,
+            },
+            onnx_file: s.remove("onnx-file"),
+            model_engine_file: s.remove("model-engine-file"),
+            model_file: s.remove("model-file"),
+            proto_file: s.remove("proto-file"),
+            tlt_encoded_model: s.remove("tlt-encoded-model"),
+            tlt_model_key: s.remove("tlt-model-key"),
+            int8_calib_file: s.remove("int8-calib-file"),
+            labelfile_path: s.remove("labelfile-path"),
+            batch_size: take(&mut s, n, "batch-size")?.unwrap_or(d.batch_size),
+            network_mode: match take(&mut s, n, "network-mode")? {
+                Some(code) => NetworkMode::from_code("network-mode", code)?,
+                None => d.network_mode,
+            },
+            network_type: match take(&mut s, n, "network-type")? {
+                Some(code) => NetworkType::from_code("network-type", code)?,
+                None => d.network_type,
+            },
+            num_detected_classes: take(&mut s, n, "num-detected-classes")?,
+            interval: take(&mut s, n, "interval")?.unwrap_or(d.interval),
+            gie_unique_id: take(&mut s, n, "gie-unique-id")?.unwrap_or(d.gie_unique_id),
+            process_mode: match take(&mut s, n, "process-mode")? {
+                Some(code) => ProcessMode::from_code("process-mode", code)?,
+                None => d.process_mode,
+            },
+            cluster_mode: match take(&mut s, n, "cluster-mode")? {
+                Some(code) => ClusterMode::from_code("cluster-mode", code)?,
+                None => d.cluster_mode,
+            },
+            maintain_aspect_ratio: take_bool(&mut s, n, "maintain-aspect-ratio")?
+                .unwrap_or(d.maintain_aspect_ratio),
+            symmetric_padding: take_bool(&mut s, n, "symmetric-padding")?
+                .unwrap_or(d.symmetric_padding),
+            output_blob_names: take_list(&mut s, n, "output-blob-names")?,
+            parse_bbox_func_name: s.remove("parse-bbox-func-name"),
+            custom_lib_path: s.remove("custom-lib-path"),
+            operate_on_gie_id: take(&mut s, n, "operate-on-gie-id")?,
+            operate_on_class_ids: take_list(&mut s, n, "operate-on-class-ids")?,
+            extra: s,
+        })
+    }
+
+    fn write(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "[property]")?;
+        writeln!(out, "gpu-id={}", self.gpu_id)?;
+        writeln!(out, "net-scale-factor={}", self.net_scale_factor)?;
+        if !self.offsets.is_empty() {
+            writeln!(out, "offsets={}", join(&self.offsets))?;
+        }
+        writeln!(out, "model-color-format={}", self.model_color_format.code())?;
+
+        let files = [
+            ("onnx-file", &self.onnx_file),
+            ("model-engine-file", &self.model_engine_file),
+            ("model-file", &self.model_file),
+            ("proto-file", &self.proto_file),
+            ("tlt-encoded-model", &self.tlt_encoded_model),
+            ("tlt-model-key", &self.tlt_model_key),
+            ("int8-calib-file", &self.int8_calib_file),
+            ("labelfile-path", &self.labelfile_path),
+        ];
+        for (key, value) in files {
+            if let Some(value) = value {
+                writeln!(out, "{}={}", key, value)?;
+            }
+        }
+
+        writeln!(out, "batch-size={}", self.batch_size)?;
+        writeln!(out, "network-mode={}", self.network_mode.code())?;
+        writeln!(out, "network-type={}", self.network_type.code())?;
+        if let Some(classes) = self.num_detected_classes {
+            writeln!(out, "num-detected-classes={}", classes)?;
+        }
+        writeln!(out, "interval={}", self.interval)?;
+        writeln!(out, "gie-unique-id={}", self.gie_unique_id)?;
+        writeln!(out, "process-mode={}", self.process_mode.code())?;
+        writeln!(out, "cluster-mode={}", self.cluster_mode.code())?;
+        writeln!(
+            out,
+            "maintain-aspect-ratio={}",
+            self.maintain_aspect_ratio as u8
+        )?;
+        writeln!(out, "symmetric-padding={}", self.symmetric_padding as u8)?;
+        if !self.output_blob_names.is_empty() {
+            writeln!(out, "output-blob-names={}", join(&self.output_blob_names))?;
+        }
+        if let Some(func) = &self.parse_bbox_func_name {
+            writeln!(out, "parse-bbox-func-name={}", func)?;
+        }
+        if let Some(lib) = &self.custom_lib_path {
+            writeln!(out, "custom-lib-path={}", lib)?;
+        }
+        if let Some(id) = self.operate_on_gie_id {
+            writeln!(out, "operate-on-gie-id={}", id)?;
+        }
+        if !self.operate_on_class_ids.is_empty() {
+            writeln!(
+                out,
+                "operate-on-class-ids={}",
+                join(&self.operate_on_class_ids)
+            )?;
+        }
+        write_extra(out, &self.extra)
+    }
+}
+
+impl ClassAttributes {
+    /// Parse a class group; `version` is updated if the legacy key is seen.
+    fn parse(name: &str, mut s: Section, version: &mut ConfigVersion) -> Result<Self> {
+        let legacy: Option<f32> = take(&mut s, name, "threshold")?;
+        if legacy.is_some() {
+            *version = ConfigVersion::Legacy;
+        }
+
+        Ok(Self {
+            pre_cluster_threshold: take(&mut s, name, "pre-cluster-threshold")?.or(legacy),
+            post_cluster_threshold: take(&mut s, name, "post-cluster-threshold")?,
+            nms_iou_threshold: take(&mut s, name, "nms-iou-threshold")?,
+            topk: take(&mut s, name, "topk")?,
+            eps: take(&mut s, name, "eps")?,
+            group_threshold: take(&mut s, name, "group-threshold")?,
+            min_boxes: take(&mut s, name, "minBoxes")?,
+            detected_min_w: take(&mut s, name, "detected-min-w")?,
+            detected_min_h: take(&mut s, name, "detected-min-h")?,
+            detected_max_w: take(&mut s, name, "detected-max-w")?,
+            detected_max_h: take(&mut s, name, "detected-max-h")?,
+            roi_top_offset: take(&mut s, name, "roi-top-offset")?,
+            roi_bottom_offset: take(&mut s, name, "roi-bottom-offset")?,
+            extra: s,
+        })
+    }
+
+    fn validate(&self, name: &str) -> Result<()> {
+        let thresholds = [
+            ("pre-cluster-threshold", self.pre_cluster_threshold),
+            ("post-cluster-threshold", self.post_cluster_threshold),
+            ("nms-iou-threshold", self.nms_iou_threshold),
+        ];
+        for (key, value) in thresholds {
+            if let Some(value) = value
+                && !(0.0..=1.0).contains(&value)
+            {
+                return Err(DeepStreamError::Configuration(format!(
+                    "[{}] {} must be between 0 and 1, got {}",
+                    name, key, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut String, name: &str, version: ConfigVersion) -> fmt::Result {
+        writeln!(out, "[{}]", name)?;
+        if let Some(threshold) = self.pre_cluster_threshold {
+            match version {
+                ConfigVersion::Legacy => writeln!(out, "threshold={}", threshold)?,
+                ConfigVersion::Current => writeln!(out, "pre-cluster-threshold={}", threshold)?,
+            }
+        }
+
+        let floats = [
+            ("post-cluster-threshold", self.post_cluster_threshold),
+            ("nms-iou-threshold", self.nms_iou_threshold),
+            ("eps", self.eps),
+        ];
+        for (key, value) in floats {
+            if let Some(value) = value {
+                writeln!(out, "{}={}", key, value)?;
+            }
+        }
+        if let Some(topk) = self.topk {
+            writeln!(out, "topk={}", topk)?;
+        }
+
+        let counts = [
+            ("group-threshold", self.group_threshold),
+            ("minBoxes", self.min_boxes),
+            ("detected-min-w", self.detected_min_w),
+            ("detected-min-h", self.detected_min_h),
+            ("detected-max-w", self.detected_max_w),
+            ("detected-max-h", self.detected_max_h),
+            ("roi-top-offset", self.roi_top_offset),
+            ("roi-bottom-offset", self.roi_bottom_offset),
+        ];
+        for (key, value) in counts {
+            if let Some(value) = value {
+                writeln!(out, "{}={}", key, value)?;
+            }
+        }
+        write_extra(out, &self.extra)
+    }
+}
+
+fn parse_tracker(mut s: Section) -> Result<(TrackerConfig, Section)> {
+    let n = "tracker";
+    let tracker = TrackerConfig {
+        enable: take_bool(&mut s, n, "enable")?.unwrap_or(true),
+        tracker_width: take(&mut s, n, "tracker-width")?.unwrap_or(640),
+        tracker_height: take(&mut s, n, "tracker-height")?.unwrap_or(384),
+        gpu_id: take(&mut s, n, "gpu-id")?.unwrap_or(0),
+        ll_lib_file: s.remove("ll-lib-file").unwrap_or_default(),
+        ll_config_file: s.remove("ll-config-file").unwrap_or_default(),
+        enable_batch_process: take_bool(&mut s, n, "enable-batch-process")?.unwrap_or(true),
+    };
+    Ok((tracker, s))
+}
+
+fn write_tracker(
+    out: &mut String,
+    tracker: &TrackerConfig,
+    extra: Option<&Section>,
+) -> fmt::Result {
+    writeln!(out, "[tracker]")?;
+    writeln!(out, "enable={}", tracker.enable as u8)?;
+    writeln!(out, "tracker-width={}", tracker.tracker_width)?;
+    writeln!(out, "tracker-height={}", tracker.tracker_height)?;
+    writeln!(out, "gpu-id={}", tracker.gpu_id)?;
+    if !tracker.ll_lib_file.is_empty() {
+        writeln!(out, "ll-lib-file={}", tracker.ll_lib_file)?;
+    }
+    if !tracker.ll_config_file.is_empty() {
+        writeln!(out, "ll-config-file={}", tracker.ll_config_file)?;
+    }
+    writeln!(
+        out,
+        "enable-batch-process={}",
+        tracker.enable_batch_process as u8
+    )?;
+    match extra {
+        Some(extra) => write_extra(out, extra),
+        None => Ok(()),
+    }
+}
+
+fn write_extra(out: &mut String, extra: &Section) -> fmt::Result {
+    for (key, value) in extra {
+        writeln!(out, "{}={}", key, value)?;
+    }
+    Ok(())
+}
+
+const TRACKER_EXTRA: &str = "tracker";
+
+impl NvInferConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        contents.parse().map_err(|e| match e {
+            DeepStreamError::Configuration(msg) => {
+                DeepStreamError::Configuration(format!("{}: {}", path.display(), msg))
+            }
+            other => other,
+        })
+    }
+
+    pub fn to_file(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Build a primary detector config from the application config.
+    ///
+    /// `[inference]` properties are applied last, using nvinfer key names,
+    /// so they override values derived from `primary_gie` and `pipeline`.
+    pub fn from_application(app: &ApplicationConfig) -> Result<Self> {
+        let mut property = NvInferProperties {
+            gpu_id: app.pipeline.gpu_id,
+            batch_size: app.pipeline.batch_size,
+            ..Default::default()
+        };
+
+        if let Some(gie) = app.inference.as_ref().and_then(|i| i.primary_gie.as_ref()) {
+            property.gpu_id = gie.gpu_id;
+            property.batch_size = gie.batch_size;
+            property.gie_unique_id = gie.unique_id;
+            property.interval = gie.interval;
+            property.model_engine_file = gie.model_engine_file.clone();
+        }
+
+        let mut config = Self {
+            property,
+            tracker: app.tracker.clone(),
+            ..Default::default()
+        };
+
+        if let Some(inference) = &app.inference
+            && !inference.properties.is_empty()
+        {
+            let mut text = String::new();
+            config
+                .property
+                .write(&mut text)
+                .map_err(|e| DeepStreamError::Configuration(e.to_string()))?;
+            for (key, value) in &inference.properties {
+                text.push_str(&format!("{}={}\n", key, value.as_string()));
+            }
+            let mut sections = parse_sections(&text)?;
+            let (_, section) = sections.remove(0);
+            config.property = NvInferProperties::parse(section)?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check value ranges and that the file names a model.
+    pub fn validate(&self) -> Result<()> {
+        let p = &self.property;
+        let fail = |msg: String| {
+            Err(DeepStreamError::Configuration(format!(
+                "[property] {}",
+                msg
+            )))
+        };
+
+        if p.batch_size == 0 {
+            return fail("batch-size must be at least 1".to_string());
+        }
+        if p.net_scale_factor <= 0.0 {
+            return fail(format!(
+                "net-scale-factor must be positive, got {}",
+                p.net_scale_factor
+            ));
+        }
+        let channels = match p.model_color_format {
+            ModelColorFormat::Gray => 1,
+            _ => 3,
+        };
+        if !p.offsets.is_empty() && p.offsets.len() != channels {
+            return fail(format!(
+                "offsets has {} values, model-color-format expects {}",
+                p.offsets.len(),
+                channels
+            ));
+        }
+        if p.process_mode == ProcessMode::Secondary && p.operate_on_gie_id.is_none() {
+            return fail("secondary inference requires operate-on-gie-id".to_string());
+        }
+        if p.network_mode == NetworkMode::Int8
+            && p.int8_calib_file.is_none()
+            && p.model_engine_file.is_none()
+        {
+            return fail("network-mode=1 (INT8) requires int8-calib-file".to_string());
+        }
+        if !p.has_model() {
+            return fail(
+                "no model given (onnx-file, model-engine-file, model-file or tlt-encoded-model)"
+                    .to_string(),
+            );
+        }
+
+        if let Some(all) = &self.class_attrs_all {
+            all.validate("class-attrs-all")?;
+        }
+        for (id, attrs) in &self.class_attrs {
+            attrs.validate(&format!("class-attrs-{}", id))?;
+        }
+        Ok(())
+    }
+
+    /// Class attributes for `class_id`, falling back to `[class-attrs-all]`.
+    pub fn class_attributes(&self, class_id: u32) -> Option<&ClassAttributes> {
+        self.class_attrs
+            .get(&class_id)
+            .or(self.class_attrs_all.as_ref())
+    }
+}
+
+impl FromStr for NvInferConfig {
+    type Err = DeepStreamError;
+
+    fn from_str(contents: &str) -> Result<Self> {
+        let mut config = NvInferConfig::default();
+        let mut has_property = false;
+
+        for (name, section) in parse_sections(contents)? {
+            if name == "property" {
+                config.property = NvInferProperties::parse(section)?;
+                has_property = true;
+            } else if name == "class-attrs-all" {
+                config.class_attrs_all =
+                    Some(ClassAttributes::parse(&name, section, &mut config.version)?);
+            } else if let Some(id) = name.strip_prefix("class-attrs-") {
+                let id = id.parse().map_err(|_| {
+                    DeepStreamError::Configuration(format!("invalid class id in [{}]", name))
+                })?;
+                let attrs = ClassAttributes::parse(&name, section, &mut config.version)?;
+                config.class_attrs.insert(id, attrs);
+            } else if name == "tracker" {
+                let (tracker, extra) = parse_tracker(section)?;
+                config.tracker = Some(tracker);
+                if !extra.is_empty() {
+                    config
+                        .other_sections
+                        .insert(TRACKER_EXTRA.to_string(), extra);
+                }
+            } else {
+                config.other_sections.insert(name, section);
+            }
+        }
+
+        if !has_property {
+            return Err(DeepStreamError::Configuration(
+                "missing [property] group".to_string(),
+            ));
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl fmt::Display for NvInferConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.property.write(&mut out)?;
+
+        if let Some(all) = &self.class_attrs_all {
+            out.push('\n');
+            all.write(&mut out, "class-attrs-all", self.version)?;
+        }
+        for (id, attrs) in &self.class_attrs {
+            out.push('\n');
+            attrs.write(&mut out, &format!("class-attrs-{}", id), self.version)?;
+        }
+        if let Some(tracker) = &self.tracker {
+            out.push('\n');
+            write_tracker(&mut out, tracker, self.other_sections.get(TRACKER_EXTRA))?;
+        }
+        for (name, section) in &self.other_sections {
+            if name == TRACKER_EXTRA && self.tracker.is_some() {
+                continue;
+            }
+            writeln!(out, "\n[{}]", name)?;
+            write_extra(&mut out, section)?;
+        }
+
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PGIE: &str = "\
+[property]
+gpu-id=0
+net-scale-factor=0.0039215697906911373
+model-engine-file=resnet10.caffemodel_b1_gpu0_int8.engine
+labelfile-path=labels.txt
+int8-calib-file=cal_trt.bin
+batch-size=1
+network-mode=1
+num-detected-classes=4
+interval=0
+gie-unique-id=1
+output-blob-names=conv2d_bbox;conv2d_cov/Sigmoid
+force-implicit-batch-dim=1
+
+[class-attrs-all]
+pre-cluster-threshold=0.2
+eps=0.2
+group-threshold=1
+
+[class-attrs-2]
+pre-cluster-threshold=0.6
+";
+
+    #[test]
+    fn test_parse_typed_config() {
+        let config: NvInferConfig = PGIE.parse().unwrap();
+
+        assert_eq!(config.version, ConfigVersion::Current);
+        assert_eq!(config.property.network_mode, NetworkMode::Int8);
+        assert_eq!(config.property.num_detected_classes, Some(4));
+        assert_eq!(
+            config.property.output_blob_names,
+            vec!["conv2d_bbox", "conv2d_cov/Sigmoid"]
+        );
+        assert_eq!(
+            config.property.extra.get("force-implicit-batch-dim"),
+            Some(&"1".to_string())
+        );
+        assert_eq!(config.property.cluster_mode, ClusterMode::Nms);
+
+        assert_eq!(
+            config.class_attributes(2).unwrap().pre_cluster_threshold,
+            Some(0.6)
+        );
+        assert_eq!(config.class_attributes(0).unwrap().group_threshold, Some(1));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config: NvInferConfig = PGIE.parse().unwrap();
+        let reparsed: NvInferConfig = config.to_string().parse().unwrap();
+        assert_eq!(config, reparsed);
+    }
+
+    #[test]
+    fn test_legacy_threshold_key() {
+        let text = "[property]\nonnx-file=model.onnx\n[class-attrs-all]\nthreshold=0.3\n";
+        let config: NvInferConfig = text.parse().unwrap();
+
+        assert_eq!(config.version, ConfigVersion::Legacy);
+        assert_eq!(
+            config
+                .class_attrs_all
+                .as_ref()
+                .unwrap()
+                .pre_cluster_threshold,
+            Some(0.3)
+        );
+        assert!(config.to_string().contains("\nthreshold=0.3\n"));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let bad_value = "[property]\nonnx-file=m.onnx\nbatch-size=two\n";
+        let err = bad_value.parse::<NvInferConfig>().unwrap_err();
+        assert!(err.to_string().contains("batch-size"));
+
+        let no_model = "[property]\nbatch-size=1\n";
+        assert!(no_model.parse::<NvInferConfig>().is_err());
+
+        let bad_threshold =
+            "[property]\nonnx-file=m.onnx\n[class-attrs-0]\npre-cluster-threshold=1.5\n";
+        assert!(bad_threshold.parse::<NvInferConfig>().is_err());
+
+        let bad_offsets = "[property]\nonnx-file=m.onnx\noffsets=1;2\n";
+        assert!(bad_offsets.parse::<NvInferConfig>().is_err());
+    }
+
+    #[test]
+    fn test_tracker_section() {
+        let text = "[property]\nonnx-file=m.onnx\n\n[tracker]\ntracker-width=960\n\
+                    ll-lib-file=/opt/libnvds_nvmultiobjecttracker.so\ndisplay-tracking-id=1\n";
+        let config: NvInferConfig = text.parse().unwrap();
+        let tracker = config.tracker.as_ref().unwrap();
+        assert_eq!(tracker.tracker_width, 960);
+        assert_eq!(tracker.tracker_height, 384);
+
+        let written = config.to_string();
+        assert!(written.contains("display-tracking-id=1"));
+        assert_eq!(written.matches("[tracker]").count(), 1);
+    }
+
+    #[test]
+    fn test_from_application_config() {
+        let mut app = ApplicationConfig::default();
+        app.pipeline.batch_size = 4;
+        app.inference = Some(super::super::InferenceConfig {
+            properties: [(
+                "onnx-file".to_string(),
+                super::super::PropertyValue::String("yolo.onnx".to_string()),
+            )]
+            .into_iter()
+            .collect(),
+            primary_gie: None,
+            secondary_gies: None,
+        });
+
+        let config = NvInferConfig::from_application(&app).unwrap();
+        assert_eq!(config.property.batch_size, 4);
+        assert_eq!(config.property.onnx_file.as_deref(), Some("yolo.onnx"));
+    }
+}
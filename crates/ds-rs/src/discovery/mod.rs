@@ -0,0 +1,444 @@
+//! Network discovery of RTSP endpoints and ONVIF cameras.
+//!
+//! [`SourceDiscovery`] probes every host of a subnet on the configured RTSP
+//! ports and, optionally, multicasts an ONVIF WS-Discovery probe. Results
+//! can be fed straight into a [`SourceController`].
+
+pub mod onvif;
+pub mod rtsp;
+
+use crate::error::{DeepStreamError, Result};
+use crate::source::{SourceController, SourceId};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// An IPv4 network in CIDR notation, e.g. `192.168.1.0/24`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subnet {
+    network: Ipv4Addr,
+    prefix: u8,
+}
+
+impl Subnet {
+    pub fn new(address: Ipv4Addr, prefix: u8) -> Result<Self> {
+        if prefix > 32 {
+            return Err(DeepStreamError::InvalidInput(format!(
+                "Invalid subnet prefix: /{}",
+                prefix
+            )));
+        }
+
+        let mask = Self::mask(prefix);
+        Ok(Self {
+            network: Ipv4Addr::from(u32::from(address) & mask),
+            prefix,
+        })
+    }
+
+    fn mask(prefix: u8) -> u32 {
+        if prefix == 0 {
+            0
+        } else {
+            u32::MAX << (32 - prefix)
+        }
+    }
+
+    /// Usable host addresses, excluding network and broadcast for prefixes below /31
+    pub fn hosts(&self) -> impl Iterator<Item = Ipv4Addr> {
+        let start = u32::from(self.network);
+        let end = start | !Self::mask(self.prefix);
+        let (first, last) = if self.prefix >= 31 {
+            (start, end)
+        } else {
+            (start + 1, end - 1)
+        };
+        (first..=last).map(Ipv4Addr::from)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hosts().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & Self::mask(self.prefix) == u32::from(self.network)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = DeepStreamError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, prefix) = s.split_once('/').unwrap_or((s, "32"));
+        let address = address
+            .parse()
+            .map_err(|e| DeepStreamError::InvalidInput(format!("Invalid subnet '{}': {}", s, e)))?;
+        let prefix = prefix
+            .parse()
+            .map_err(|e| DeepStreamError::InvalidInput(format!("Invalid subnet '{}': {}", s, e)))?;
+        Self::new(address, prefix)
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// How a candidate source was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiscoveryKind {
+    Rtsp,
+    Onvif,
+}
+
+impl fmt::Display for DiscoveryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscoveryKind::Rtsp => write!(f, "rtsp"),
+            DiscoveryKind::Onvif => write!(f, "onvif"),
+        }
+    }
+}
+
+/// A candidate source found on the network
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredSource {
+    pub uri: String,
+    pub host: IpAddr,
+    pub port: u16,
+    pub kind: DiscoveryKind,
+    /// Device name reported by ONVIF, if any
+    pub name: Option<String>,
+    /// Whether the endpoint answered 401 Unauthorized
+    pub requires_auth: bool,
+    /// Extra details such as the RTSP `Server` header or ONVIF hardware
+    pub metadata: HashMap<String, String>,
+}
+
+/// Configuration for a discovery run
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// Subnet to probe for RTSP servers; `None` skips the RTSP scan
+    pub subnet: Option<Subnet>,
+    /// Ports probed on every host
+    pub rtsp_ports: Vec<u16>,
+    /// Stream paths tried with DESCRIBE on responding servers
+    pub rtsp_paths: Vec<String>,
+    /// Timeout for each connection attempt and request
+    pub connect_timeout: Duration,
+    /// Number of hosts probed concurrently
+    pub max_concurrency: usize,
+    /// Send an ONVIF WS-Discovery probe
+    pub onvif: bool,
+    /// How long to collect ONVIF responses
+    pub onvif_timeout: Duration,
+    /// Name template for auto-added sources, see [`SourceDiscovery::source_name`]
+    pub naming_template: String,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            subnet: None,
+            rtsp_ports: vec![554, 8554],
+            rtsp_paths: vec![
+                "stream".to_string(),
+                "live".to_string(),
+                "h264".to_string(),
+                "Streaming/Channels/101".to_string(),
+                "cam/realmonitor?channel=1&subtype=0".to_string(),
+            ],
+            connect_timeout: Duration::from_millis(300),
+            max_concurrency: 64,
+            onvif: true,
+            onvif_timeout: Duration::from_secs(2),
+            naming_template: "{kind}-{host}-{index}".to_string(),
+        }
+    }
+}
+
+/// A discovered source that was added to a controller
+#[derive(Debug, Clone)]
+pub struct AddedSource {
+    pub id: SourceId,
+    pub name: String,
+    pub uri: String,
+}
+
+/// Probes the network for candidate video sources
+pub struct SourceDiscovery {
+    config: DiscoveryConfig,
+}
+
+impl SourceDiscovery {
+    pub fn new(config: DiscoveryConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &DiscoveryConfig {
+        &self.config
+    }
+
+    /// Run the configured probes and return de-duplicated candidates sorted by URI
+    pub fn discover(&self) -> Result<Vec<DiscoveredSource>> {
+        let mut found = Vec::new();
+
+        if let Some(subnet) = self.config.subnet {
+            log::info!(
+                "Probing {} hosts in {} on ports {:?}",
+                subnet.len(),
+                subnet,
+                self.config.rtsp_ports
+            );
+            found.extend(self.scan_subnet(subnet));
+        }
+
+        if self.config.onvif {
+            let port = self.config.rtsp_ports.first().copied().unwrap_or(554);
+            match onvif::discover(self.config.onvif_timeout, port) {
+                Ok(devices) => {
+                    for device in devices {
+                        let in_subnet = match (self.config.subnet, device.host) {
+                            (Some(subnet), IpAddr::V4(host)) => subnet.contains(host),
+                            (Some(_), IpAddr::V6(_)) => false,
+                            (None, _) => true,
+                        };
+                        if in_subnet {
+                            merge_onvif(&mut found, device);
+                        }
+                    }
+                }
+                Err(e) => log::warn!("ONVIF discovery failed: {}", e),
+            }
+        }
+
+        found.sort_by(|a, b| a.uri.cmp(&b.uri));
+        found.dedup_by(|a, b| a.uri == b.uri);
+        log::info!("Discovered {} candidate sources", found.len());
+        Ok(found)
+    }
+
+    fn scan_subnet(&self, subnet: Subnet) -> Vec<DiscoveredSource> {
+        let targets: Vec<(IpAddr, u16)> = subnet
+            .hosts()
+            .flat_map(|host| {
+                self.config
+                    .rtsp_ports
+                    .iter()
+                    .map(move |&port| (IpAddr::V4(host), port))
+            })
+            .collect();
+
+        let next = Mutex::new(targets.into_iter());
+        let results = Mutex::new(Vec::new());
+        let workers = self.config.max_concurrency.max(1);
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        let Some((host, port)) = next.lock().ok().and_then(|mut it| it.next())
+                        else {
+                            break;
+                        };
+
+                        let found = rtsp::probe(
+                            host,
+                            port,
+                            &self.config.rtsp_paths,
+                            self.config.connect_timeout,
+                        );
+                        if !found.is_empty() {
+                            if let Ok(mut results) = results.lock() {
+                                results.extend(found);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        results.into_inner().unwrap_or_default()
+    }
+
+    /// Render the naming template for the `index`-th candidate.
+    ///
+    /// Supported placeholders: `{kind}`, `{host}` (dots replaced by dashes),
+    /// `{port}`, `{name}` (ONVIF name, falling back to the host) and `{index}`.
+    pub fn source_name(&self, source: &DiscoveredSource, index: usize) -> String {
+        let host = source.host.to_string().replace(['.', ':'], "-");
+        let name = source
+            .name
+            .as_deref()
+            .map(|name| name.replace(' ', "-"))
+            .unwrap_or_else(|| host.clone());
+
+        self.config
+            .naming_template
+            .replace("{kind}", &source.kind.to_string())
+            .replace("{host}", &host)
+            .replace("{port}", &source.port.to_string())
+            .replace("{name}", &name)
+            .replace("{index}", &index.to_string())
+    }
+
+    /// Add candidates to the controller, skipping any that fail to add.
+    ///
+    /// Each added source is named with [`source_name`](Self::source_name)
+    /// through [`SourceController::set_source_name`].
+    ///
+    /// Stops early once the controller reaches capacity. Endpoints that
+    /// require credentials are skipped since their URIs would not play.
+    pub fn add_to_controller(
+        &self,
+        controller: &SourceController,
+        sources: &[DiscoveredSource],
+    ) -> Result<Vec<AddedSource>> {
+        let mut added = Vec::new();
+
+        for (index, source) in sources.iter().enumerate() {
+            if source.requires_auth {
+                log::info!("Skipping {}: authentication required", source.uri);
+                continue;
+            }
+            if !controller.has_capacity()? {
+                log::warn!("Source controller is full, not adding remaining candidates");
+                break;
+            }
+
+            let name = self.source_name(source, index);
+            match controller.add_source(&source.uri) {
+                Ok(id) => {
+                    controller.set_source_name(id, &name);
+                    log::info!(
+                        "Added discovered source {} as {} ({})",
+                        name,
+                        id,
+                        source.uri
+                    );
+                    added.push(AddedSource {
+                        id,
+                        name,
+                        uri: source.uri.clone(),
+                    });
+                }
+                Err(e) => log::warn!("Failed to add discovered source {}: {}", source.uri, e),
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Discover and immediately add everything found to the controller
+    pub fn discover_and_add(&self, controller: &SourceController) -> Result<Vec<AddedSource>> {
+        let sources = self.discover()?;
+        self.add_to_controller(controller, &sources)
+    }
+}
+
+/// Attach an ONVIF device to an RTSP result for the same host, or add it
+fn merge_onvif(found: &mut Vec<DiscoveredSource>, device: DiscoveredSource) {
+    let mut merged = false;
+    for source in found.iter_mut().filter(|s| s.host == device.host) {
+        source.name = source.name.take().or_else(|| device.name.clone());
+        for (key, value) in &device.metadata {
+            source
+                .metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        merged = true;
+    }
+
+    if !merged {
+        found.push(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subnet_parsing() {
+        let subnet: Subnet = "192.168.1.77/24".parse().unwrap();
+        assert_eq!(subnet.to_string(), "192.168.1.0/24");
+        assert_eq!(subnet.len(), 254);
+        assert!(subnet.contains("192.168.1.200".parse().unwrap()));
+        assert!(!subnet.contains("192.168.2.1".parse().unwrap()));
+
+        let single: Subnet = "10.0.0.5".parse().unwrap();
+        assert_eq!(
+            single.hosts().collect::<Vec<_>>(),
+            vec!["10.0.0.5".parse::<Ipv4Addr>().unwrap()]
+        );
+
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+        assert!("not-an-ip/24".parse::<Subnet>().is_err());
+    }
+
+    #[test]
+    fn test_naming_template() {
+        let discovery = SourceDiscovery::new(DiscoveryConfig {
+            naming_template: "{kind}-{name}-{port}-{index}".to_string(),
+            ..Default::default()
+        });
+        let mut source = DiscoveredSource {
+            uri: "rtsp://192.168.1.64:554/stream".to_string(),
+            host: "192.168.1.64".parse().unwrap(),
+            port: 554,
+            kind: DiscoveryKind::Rtsp,
+            name: None,
+            requires_auth: false,
+            metadata: HashMap::new(),
+        };
+
+        assert_eq!(discovery.source_name(&source, 0), "rtsp-192-168-1-64-554-0");
+
+        source.kind = DiscoveryKind::Onvif;
+        source.name = Some("Front Door".to_string());
+        assert_eq!(discovery.source_name(&source, 3), "onvif-Front-Door-554-3");
+    }
+
+    #[test]
+    fn test_scan_finds_local_rtsp_server() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer);
+                let _ =
+                    stream.write_all(b"RTSP/1.0 200 OK\r\nCSeq: 1\r\nServer: fake-camera\r\n\r\n");
+            }
+        });
+
+        let discovery = SourceDiscovery::new(DiscoveryConfig {
+            subnet: Some("127.0.0.1/32".parse().unwrap()),
+            rtsp_ports: vec![port],
+            rtsp_paths: vec!["stream".to_string()],
+            onvif: false,
+            ..Default::default()
+        });
+
+        let found = discovery.discover().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].uri, format!("rtsp://127.0.0.1:{}/stream", port));
+        assert_eq!(
+            found[0].metadata.get("server").map(String::as_str),
+            Some("fake-camera")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use super::{DiscoveredSource, DiscoveryKind};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const WS_DISCOVERY_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const WS_DISCOVERY_PORT: u16 = 3702;
+
+fn probe_message(message_id: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<e:Envelope xmlns:e="http://www.w3.org/2003/05/soap-envelope"
+            xmlns:w="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+            xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery"
+            xmlns:dn="http://www.onvif.org/ver10/network/wsdl">
+  <e:Header>
+    <w:MessageID>uuid:{}</w:MessageID>
+    <w:To e:mustUnderstand="true">urn:schemas-xmlsoap-org:ws:2005:04:discovery</w:To>
+    <w:Action e:mustUnderstand="true">http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</w:Action>
+  </e:Header>
+  <e:Body>
+    <d:Probe>
+      <d:Types>dn:NetworkVideoTransmitter</d:Types>
+    </d:Probe>
+  </e:Body>
+</e:Envelope>"#,
+        message_id
+    )
+}
+
+/// A device that answered a WS-Discovery probe
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeMatch {
+    /// Device service endpoints (`XAddrs`)
+    pub xaddrs: Vec<String>,
+    /// ONVIF scopes such as `onvif://www.onvif.org/name/Camera1`
+    pub scopes: Vec<String>,
+}
+
+impl ProbeMatch {
+    /// Extract the probe match from a WS-Discovery response, ignoring namespaces
+    pub fn parse(xml: &str) -> Option<Self> {
+        let xaddrs: Vec<String> = element_text(xml, "XAddrs")?
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let scopes = element_text(xml, "Scopes")
+            .map(|s| s.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        (!xaddrs.is_empty()).then_some(Self { xaddrs, scopes })
+    }
+
+    /// Value of an `onvif://www.onvif.org/<key>/<value>` scope
+    pub fn scope(&self, key: &str) -> Option<String> {
+        let prefix = format!("onvif://www.onvif.org/{}/", key);
+        self.scopes
+            .iter()
+            .find_map(|scope| scope.strip_prefix(&prefix))
+            .map(|value| value.replace("%20", " "))
+    }
+
+    /// Host of the first device service address
+    pub fn host(&self) -> Option<IpAddr> {
+        self.xaddrs.iter().find_map(|xaddr| {
+            let rest = xaddr.split("://").nth(1)?;
+            let authority = rest.split('/').next()?;
+            let host = authority
+                .rsplit_once(':')
+                .map_or(authority, |(host, _)| host);
+            host.trim_matches(|c| c == '[' || c == ']').parse().ok()
+        })
+    }
+}
+
+/// Text content of the first element with the given local name
+fn element_text<'a>(xml: &'a str, local_name: &str) -> Option<&'a str> {
+    let mut search = xml;
+    while let Some(start) = search.find('<') {
+        let tag_end = search[start..].find('>')? + start;
+        let tag = &search[start + 1..tag_end];
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let local = name.rsplit(':').next().unwrap_or(name);
+
+        if local == local_name && !tag.starts_with('/') && !tag.ends_with('/') {
+            let content = &search[tag_end + 1..];
+            let close = content.find("</")?;
+            return Some(content[..close].trim());
+        }
+        search = &search[tag_end + 1..];
+    }
+    None
+}
+
+/// Multicast a WS-Discovery probe and collect answers until `timeout` elapses.
+///
+/// The RTSP URI of an ONVIF device is only available through its media
+/// service, so the returned candidates point at the default RTSP port and
+/// carry the device service address in their metadata for follow-up.
+pub fn discover(timeout: Duration, rtsp_port: u16) -> std::io::Result<Vec<DiscoveredSource>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_multicast_ttl_v4(2)?;
+
+    let message_id = format!("{:032x}", rand::random::<u128>());
+    socket.send_to(
+        probe_message(&message_id).as_bytes(),
+        SocketAddr::from((WS_DISCOVERY_ADDR, WS_DISCOVERY_PORT)),
+    )?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut found: Vec<DiscoveredSource> = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let Some(probe_match) = ProbeMatch::parse(&String::from_utf8_lossy(&buffer[..len])) else {
+            continue;
+        };
+
+        let host = probe_match.host().unwrap_or(from.ip());
+        if found.iter().any(|source| source.host == host) {
+            continue;
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("xaddrs".to_string(), probe_match.xaddrs.join(" "));
+        for key in ["hardware", "location"] {
+            if let Some(value) = probe_match.scope(key) {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+
+        found.push(DiscoveredSource {
+            uri: format!("rtsp://{}/", SocketAddr::new(host, rtsp_port)),
+            host,
+            port: rtsp_port,
+            kind: DiscoveryKind::Onvif,
+            name: probe_match.scope("name"),
+            requires_auth: false,
+            metadata,
+        });
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_match() {
+        let xml = r#"<SOAP-ENV:Envelope><SOAP-ENV:Body><d:ProbeMatches><d:ProbeMatch>
+            <d:Scopes>onvif://www.onvif.org/name/Front%20Door onvif://www.onvif.org/hardware/IPC-1</d:Scopes>
+            <d:XAddrs>http://192.168.1.64:80/onvif/device_service</d:XAddrs>
+            </d:ProbeMatch></d:ProbeMatches></SOAP-ENV:Body></SOAP-ENV:Envelope>"#;
+
+        let probe_match = ProbeMatch::parse(xml).unwrap();
+        assert_eq!(probe_match.host(), Some("192.168.1.64".parse().unwrap()));
+        assert_eq!(probe_match.scope("name").as_deref(), Some("Front Door"));
+        assert_eq!(probe_match.scope("hardware").as_deref(), Some("IPC-1"));
+        assert!(ProbeMatch::parse("<d:Probe/>").is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use super::{DiscoveredSource, DiscoveryKind};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Parsed status line and headers of an RTSP response
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtspResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl RtspResponse {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut lines = raw.lines();
+        let status_line = lines.next()?;
+        let mut parts = status_line.splitn(3, ' ');
+
+        if !parts.next()?.starts_with("RTSP/") {
+            return None;
+        }
+        let status = parts.next()?.parse().ok()?;
+        let reason = parts.next().unwrap_or_default().trim().to_string();
+
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Some(Self {
+            status,
+            reason,
+            headers,
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn requires_auth(&self) -> bool {
+        self.status == 401
+    }
+}
+
+/// Send a single RTSP request and read back the response headers
+fn request(
+    address: SocketAddr,
+    method: &str,
+    uri: &str,
+    timeout: Duration,
+) -> std::io::Result<RtspResponse> {
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut message = format!(
+        "{} {} RTSP/1.0\r\nCSeq: 1\r\nUser-Agent: ds-rs-discovery\r\n",
+        method, uri
+    );
+    if method == "DESCRIBE" {
+        message.push_str("Accept: application/sdp\r\n");
+    }
+    message.push_str("\r\n");
+    stream.write_all(message.as_bytes())?;
+
+    let mut buffer = [0u8; 4096];
+    let mut received = Vec::new();
+    loop {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[..read]);
+        if received.windows(4).any(|w| w == b"\r\n\r\n") || received.len() > 16 * 1024 {
+            break;
+        }
+    }
+
+    RtspResponse::parse(&String::from_utf8_lossy(&received))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Not an RTSP response"))
+}
+
+/// Probe one host:port for an RTSP server and any of the candidate paths.
+///
+/// Returns an empty list when nothing answers RTSP on the port. When the
+/// server responds but none of the paths do, the root URI is returned so
+/// the caller still learns about the endpoint.
+pub fn probe(
+    host: IpAddr,
+    port: u16,
+    paths: &[String],
+    timeout: Duration,
+) -> Vec<DiscoveredSource> {
+    let address = SocketAddr::new(host, port);
+    let base = format!("rtsp://{}", address);
+
+    let options = match request(address, "OPTIONS", &format!("{}/", base), timeout) {
+        Ok(response) => response,
+        Err(e) => {
+            log::trace!("No RTSP server at {}: {}", address, e);
+            return Vec::new();
+        }
+    };
+
+    let mut metadata = HashMap::new();
+    if let Some(server) = options.header("server") {
+        metadata.insert("server".to_string(), server.to_string());
+    }
+    if let Some(public) = options.header("public") {
+        metadata.insert("methods".to_string(), public.to_string());
+    }
+
+    let mut found = Vec::new();
+    for path in paths {
+        let uri = format!("{}/{}", base, path.trim_start_matches('/'));
+        let Ok(response) = request(address, "DESCRIBE", &uri, timeout) else {
+            continue;
+        };
+
+        if response.status == 200 || response.requires_auth() {
+            found.push(DiscoveredSource {
+                uri,
+                host,
+                port,
+                kind: DiscoveryKind::Rtsp,
+                name: None,
+                requires_auth: response.requires_auth(),
+                metadata: metadata.clone(),
+            });
+        }
+    }
+
+    if found.is_empty() {
+        found.push(DiscoveredSource {
+            uri: format!("{}/", base),
+            host,
+            port,
+            kind: DiscoveryKind::Rtsp,
+            name: None,
+            requires_auth: options.requires_auth(),
+            metadata,
+        });
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let raw = "RTSP/1.0 401 Unauthorized\r\nCSeq: 1\r\nServer: GStreamer RTSP server\r\n\
+                   WWW-Authenticate: Basic realm=\"cam\"\r\n\r\n";
+        let response = RtspResponse::parse(raw).unwrap();
+
+        assert_eq!(response.status, 401);
+        assert_eq!(response.reason, "Unauthorized");
+        assert!(response.requires_auth());
+        assert_eq!(response.header("Server"), Some("GStreamer RTSP server"));
+        assert!(RtspResponse::parse("HTTP/1.1 200 OK\r\n\r\n").is_none());
+    }
+
+    #[test]
+    fn test_probe_closed_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let found = probe(
+            "127.0.0.1".parse().unwrap(),
+            port,
+            &["stream".to_string()],
+            Duration::from_millis(200),
+        );
+        assert!(found.is_empty());
+    }
+}
@@ -0,0 +1,354 @@
+//! Preflight check of everything a pipeline needs at runtime
+//!
+//! Missing plugins and libraries otherwise surface as an element that
+//! cannot be created halfway through building the pipeline. [`run_doctor`]
+//! checks up front: GStreamer itself, the core plugins, the elements of the
+//! selected backend, the DeepStream libraries, ONNX Runtime and the video
+//! decoders, and says how to fix whatever is missing.
+
+use crate::backend::BackendType;
+use crate::backend::detector::{DEEPSTREAM_ELEMENTS, STANDARD_ELEMENTS};
+use crate::platform::PlatformInfo;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Elements every backend uses, with the package that ships them
+const CORE_ELEMENTS: &[(&str, &str)] = &[
+    ("uridecodebin", "gst-plugins-base"),
+    ("decodebin", "gst-plugins-base"),
+    ("queue", "gstreamer core"),
+    ("capsfilter", "gstreamer core"),
+    ("videoconvert", "gst-plugins-base"),
+    ("videoscale", "gst-plugins-base"),
+    ("videotestsrc", "gst-plugins-base"),
+    ("appsink", "gst-plugins-base"),
+];
+
+/// Encoded formats and the package with their usual software decoder
+const CODECS: &[(&str, &str, &str)] = &[
+    ("H.264", "video/x-h264", "gst-libav"),
+    ("H.265", "video/x-h265", "gst-libav"),
+    ("VP8", "video/x-vp8", "gst-plugins-good"),
+    ("VP9", "video/x-vp9", "gst-plugins-good"),
+    ("AV1", "video/x-av1", "gst-plugins-bad"),
+    ("MJPEG", "image/jpeg", "gst-plugins-good"),
+];
+
+const DEEPSTREAM_ROOT: &str = "/opt/nvidia/deepstream/deepstream";
+const DEEPSTREAM_LIBS: &[&str] = &[
+    "libnvdsgst_meta.so",
+    "libnvds_meta.so",
+    "libnvds_nvmultiobjecttracker.so",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but with less than it could
+    Warning,
+    /// The pipeline will fail without it
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub category: &'static str,
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: Option<String>,
+    /// What to install or set to fix it
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(category: &'static str, name: impl Into<String>) -> Self {
+        Self {
+            category,
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: None,
+            fix: None,
+        }
+    }
+
+    fn problem(
+        category: &'static str,
+        name: impl Into<String>,
+        status: CheckStatus,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            category,
+            name: name.into(),
+            status,
+            detail: None,
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub platform: String,
+    /// Backend the checks were run for
+    pub backend: String,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Anything the pipeline cannot run without
+    pub fn has_missing(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == CheckStatus::Missing)
+    }
+
+    pub fn problems(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status != CheckStatus::Ok)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Platform: {}", self.platform)?;
+        writeln!(f, "Backend:  {}", self.backend)?;
+        writeln!(f)?;
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Ok => "ok  ",
+                CheckStatus::Warning => "warn",
+                CheckStatus::Missing => "FAIL",
+            };
+            write!(f, "[{}] {:<10} {}", mark, check.category, check.name)?;
+            if let Some(detail) = &check.detail {
+                write!(f, " ({})", detail)?;
+            }
+            writeln!(f)?;
+            if let Some(fix) = &check.fix {
+                writeln!(f, "       -> {}", fix)?;
+            }
+        }
+        let problems = self.problems().count();
+        if problems == 0 {
+            write!(f, "\nEverything needed is installed.")
+        } else {
+            write!(f, "\n{} problem(s) found.", problems)
+        }
+    }
+}
+
+/// Check what `backend` needs, or what the detected backend needs when
+/// `None`
+pub fn run_doctor(backend: Option<BackendType>) -> DoctorReport {
+    let platform = PlatformInfo::detect().ok();
+    let mut report = DoctorReport {
+        platform: platform
+            .as_ref()
+            .map(|p| format!("{:?}", p.platform))
+            .unwrap_or_else(|| "unknown".to_string()),
+        backend: backend.map_or("auto".to_string(), |b| b.name().to_string()),
+        checks: Vec::new(),
+    };
+
+    if let Err(e) = gst::init() {
+        report.checks.push(
+            Check::problem(
+                "gstreamer",
+                "GStreamer runtime",
+                CheckStatus::Missing,
+                "Install the GStreamer 1.x runtime and make sure its libraries are on the library path",
+            )
+            .detail(e.to_string()),
+        );
+        return report;
+    }
+    let (major, minor, micro, _) = gst::version();
+    report.checks.push(
+        Check::ok("gstreamer", "GStreamer runtime")
+            .detail(format!("{}.{}.{}", major, minor, micro)),
+    );
+
+    for (element, package) in CORE_ELEMENTS {
+        report.checks.push(element_check("core", element, package));
+    }
+
+    let nvidia = platform.as_ref().is_some_and(|p| p.has_nvidia_hardware());
+    let backend = backend.unwrap_or(if nvidia {
+        BackendType::DeepStream
+    } else {
+        BackendType::Standard
+    });
+    report.backend = backend.name().to_string();
+    match backend {
+        BackendType::DeepStream => {
+            for element in DEEPSTREAM_ELEMENTS {
+                report
+                    .checks
+                    .push(element_check("deepstream", element, "DeepStream SDK"));
+            }
+            report.checks.extend(deepstream_lib_checks());
+        }
+        BackendType::Standard => {
+            for element in STANDARD_ELEMENTS {
+                report
+                    .checks
+                    .push(element_check("standard", element, "gst-plugins-base"));
+            }
+            report.checks.push(onnx_runtime_check());
+        }
+        BackendType::Mock => {}
+    }
+
+    for (name, caps, package) in CODECS {
+        report.checks.push(codec_check(name, caps, package));
+    }
+    report
+}
+
+fn element_check(category: &'static str, element: &str, package: &str) -> Check {
+    if gst::ElementFactory::find(element).is_some() {
+        Check::ok(category, element)
+    } else {
+        Check::problem(
+            category,
+            element,
+            CheckStatus::Missing,
+            format!(
+                "Install {}, or point GST_PLUGIN_PATH at the directory holding it",
+                package
+            ),
+        )
+    }
+}
+
+fn codec_check(name: &str, caps: &str, package: &str) -> Check {
+    let caps = gst::Caps::builder(caps).build();
+    let decoders: Vec<String> = gst::ElementFactory::factories_with_type(
+        gst::ElementFactoryType::DECODER,
+        gst::Rank::MARGINAL,
+    )
+    .into_iter()
+    .filter(|factory| factory.can_sink_any_caps(&caps))
+    .map(|factory| factory.name().to_string())
+    .collect();
+
+    if decoders.is_empty() {
+        Check::problem(
+            "codec",
+            name,
+            CheckStatus::Warning,
+            format!("Install {} to play {} sources", package, name),
+        )
+    } else {
+        Check::ok("codec", name).detail(decoders.join(", "))
+    }
+}
+
+fn deepstream_lib_checks() -> Vec<Check> {
+    let lib_dir = Path::new(DEEPSTREAM_ROOT).join("lib");
+    DEEPSTREAM_LIBS
+        .iter()
+        .map(|lib| {
+            let path = lib_dir.join(lib);
+            if path.exists() {
+                Check::ok("deepstream", *lib)
+            } else {
+                Check::problem(
+                    "deepstream",
+                    *lib,
+                    CheckStatus::Missing,
+                    format!(
+                        "Install the DeepStream SDK under {} and run ldconfig",
+                        DEEPSTREAM_ROOT
+                    ),
+                )
+                .detail(format!("not at {}", path.display()))
+            }
+        })
+        .collect()
+}
+
+fn onnx_runtime_check() -> Check {
+    let name = "ONNX Runtime";
+    if let Ok(path) = std::env::var("ORT_DYLIB_PATH") {
+        return if Path::new(&path).exists() {
+            Check::ok("onnx", name).detail(path)
+        } else {
+            Check::problem(
+                "onnx",
+                name,
+                CheckStatus::Missing,
+                "Point ORT_DYLIB_PATH at the ONNX Runtime library, or unset it",
+            )
+            .detail(format!("ORT_DYLIB_PATH={} does not exist", path))
+        };
+    }
+
+    dll_check(name)
+}
+
+#[cfg(target_os = "windows")]
+fn dll_check(name: &str) -> Check {
+    let broken: Vec<_> = crate::dll_validator::validate_onnx_runtime_dlls()
+        .into_iter()
+        .filter(|info| !info.is_valid)
+        .collect();
+    match broken.first() {
+        None => Check::ok("onnx", name),
+        Some(info) => Check::problem(
+            "onnx",
+            name,
+            // The detector falls back to a mock without the runtime
+            CheckStatus::Warning,
+            info.suggestions
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "Reinstall ONNX Runtime".to_string()),
+        )
+        .detail(
+            info.error
+                .as_ref()
+                .map_or(info.dll_name.clone(), |e| e.to_string()),
+        ),
+    }
+}
+
+/// Outside Windows the runtime is linked when ds-rs is built
+#[cfg(not(target_os = "windows"))]
+fn dll_check(name: &str) -> Check {
+    Check::ok("onnx", name).detail("linked at build time")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_summary() {
+        let report = DoctorReport {
+            platform: "X86".to_string(),
+            backend: "Mock".to_string(),
+            checks: vec![
+                Check::ok("core", "queue"),
+                Check::problem("codec", "AV1", CheckStatus::Warning, "Install it"),
+            ],
+        };
+        assert!(!report.has_missing());
+        assert_eq!(report.problems().count(), 1);
+        assert!(report.to_string().contains("-> Install it"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][1]["status"], "warning");
+    }
+}
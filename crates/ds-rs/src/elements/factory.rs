@@ -24,8 +24,13 @@ impl ElementFactory {
         self.backend_manager.backend()
     }
 
+    /// Builder for `element_type`, using the configured override if any
     pub fn create(&self, element_type: DeepStreamElementType) -> ElementBuilder {
-        ElementBuilder::new(element_type)
+        let builder = ElementBuilder::new(element_type);
+        match self.backend_manager.element_override(element_type) {
+            Some(factory) => builder.factory(factory),
+            None => builder,
+        }
     }
 
     pub fn create_element(
@@ -1,12 +1,15 @@
 pub mod abstracted;
 pub mod factory;
+pub mod properties;
+
+pub use properties::{PropertyInfo, PropertyValue};
 
 use crate::error::Result;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DeepStreamElementType {
     StreamMux,
     Inference,
@@ -19,6 +22,46 @@ pub enum DeepStreamElementType {
 }
 
 impl DeepStreamElementType {
+    /// Role names accepted by [`from_role`](Self::from_role)
+    pub const ROLES: [&'static str; 8] = [
+        "streammux",
+        "inference",
+        "tracker",
+        "tiler",
+        "osd",
+        "converter",
+        "videosink",
+        "decoder",
+    ];
+
+    /// Backend-neutral name, as used in configuration
+    pub fn role(&self) -> &'static str {
+        match self {
+            Self::StreamMux => "streammux",
+            Self::Inference => "inference",
+            Self::Tracker => "tracker",
+            Self::Tiler => "tiler",
+            Self::Osd => "osd",
+            Self::VideoConvert => "converter",
+            Self::VideoSink => "videosink",
+            Self::Decoder => "decoder",
+        }
+    }
+
+    pub fn from_role(role: &str) -> Option<Self> {
+        match role.to_ascii_lowercase().as_str() {
+            "streammux" | "mux" => Some(Self::StreamMux),
+            "inference" => Some(Self::Inference),
+            "tracker" => Some(Self::Tracker),
+            "tiler" => Some(Self::Tiler),
+            "osd" => Some(Self::Osd),
+            "converter" | "videoconvert" => Some(Self::VideoConvert),
+            "videosink" | "sink" => Some(Self::VideoSink),
+            "decoder" => Some(Self::Decoder),
+            _ => None,
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Self::StreamMux => "nvstreammux",
@@ -55,8 +98,11 @@ pub trait DeepStreamElement {
     fn inner_mut(&mut self) -> &mut gst::Element;
 
     fn set_property_from_str(&self, name: &str, value: &str) -> Result<()> {
-        self.inner().set_property_from_str(name, value);
-        Ok(())
+        properties::set_property_str_checked(self.inner(), name, value)
+    }
+
+    fn set_typed_property(&self, name: &str, value: impl Into<PropertyValue>) -> Result<()> {
+        properties::set_property_checked(self.inner(), name, value)
     }
 
     fn link(&self, dest: &impl DeepStreamElement) -> Result<()> {
@@ -69,6 +115,11 @@ pub trait DeepStreamElement {
         })
     }
 
+    /// Like [`link`](Self::link), but reports incompatible caps explicitly.
+    fn link_checked(&self, dest: &impl DeepStreamElement) -> Result<()> {
+        properties::link_checked(self.inner(), dest.inner())
+    }
+
     fn set_state(&self, state: gst::State) -> Result<gst::StateChangeSuccess> {
         self.inner().set_state(state).map_err(|_| {
             crate::error::DeepStreamError::StateChange(format!(
@@ -84,6 +135,9 @@ pub struct ElementBuilder {
     element_type: DeepStreamElementType,
     name: Option<String>,
     properties: HashMap<String, String>,
+    typed_properties: Vec<(String, PropertyValue)>,
+    validate: bool,
+    factory: Option<String>,
 }
 
 impl ElementBuilder {
@@ -92,6 +146,9 @@ impl ElementBuilder {
             element_type,
             name: None,
             properties: HashMap::new(),
+            typed_properties: Vec::new(),
+            validate: false,
+            factory: None,
         }
     }
 
@@ -105,31 +162,99 @@ impl ElementBuilder {
         self
     }
 
+    /// Set a property with a typed value, checked against the element's spec on build.
+    pub fn typed_property(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<PropertyValue>,
+    ) -> Self {
+        self.typed_properties.push((key.into(), value.into()));
+        self
+    }
+
+    /// Check string properties against the created element before the backend applies them.
+    pub fn validate_properties(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Create this GStreamer factory instead of the backend's choice
+    pub fn factory(mut self, factory: impl Into<String>) -> Self {
+        self.factory = Some(factory.into());
+        self
+    }
+
     pub fn build_with_backend(self, backend: &dyn crate::backend::Backend) -> Result<gst::Element> {
-        let element = match self.element_type {
-            DeepStreamElementType::StreamMux => backend.create_stream_mux(self.name.as_deref())?,
-            DeepStreamElementType::Inference => backend.create_inference(
-                self.name.as_deref(),
-                self.properties
-                    .get("config-file-path")
-                    .map(|s| s.as_str())
-                    .unwrap_or(""),
-            )?,
-            DeepStreamElementType::Tracker => backend.create_tracker(self.name.as_deref())?,
-            DeepStreamElementType::Tiler => backend.create_tiler(self.name.as_deref())?,
-            DeepStreamElementType::Osd => backend.create_osd(self.name.as_deref())?,
-            DeepStreamElementType::VideoConvert => {
-                backend.create_video_convert(self.name.as_deref())?
-            }
-            DeepStreamElementType::VideoSink => backend.create_video_sink(self.name.as_deref())?,
-            DeepStreamElementType::Decoder => backend.create_decoder(self.name.as_deref())?,
+        let element = match &self.factory {
+            Some(factory) => self.build_override(factory)?,
+            None => match self.element_type {
+                DeepStreamElementType::StreamMux => {
+                    backend.create_stream_mux(self.name.as_deref())?
+                }
+                DeepStreamElementType::Inference => backend.create_inference(
+                    self.name.as_deref(),
+                    self.properties
+                        .get("config-file-path")
+                        .map(|s| s.as_str())
+                        .unwrap_or(""),
+                )?,
+                DeepStreamElementType::Tracker => backend.create_tracker(self.name.as_deref())?,
+                DeepStreamElementType::Tiler => backend.create_tiler(self.name.as_deref())?,
+                DeepStreamElementType::Osd => backend.create_osd(self.name.as_deref())?,
+                DeepStreamElementType::VideoConvert => {
+                    backend.create_video_convert(self.name.as_deref())?
+                }
+                DeepStreamElementType::VideoSink => {
+                    backend.create_video_sink(self.name.as_deref())?
+                }
+                DeepStreamElementType::Decoder => backend.create_decoder(self.name.as_deref())?,
+            },
         };
 
+        // Mock elements are stand-ins that don't expose the real properties
+        let checked =
+            self.factory.is_some() || backend.backend_type() != crate::backend::BackendType::Mock;
+
+        if self.validate && checked {
+            for (key, value) in &self.properties {
+                // Consumed by the backend when it creates the inference element
+                if key == "config-file-path"
+                    && self.element_type == DeepStreamElementType::Inference
+                {
+                    continue;
+                }
+                properties::validate_property_str(&element, key, value)?;
+            }
+        }
+
         // Apply additional properties
         if !self.properties.is_empty() {
             backend.configure_element(&element, &self.properties)?;
         }
 
+        for (key, value) in &self.typed_properties {
+            if checked {
+                properties::set_property_checked(&element, key, value.clone())?;
+            } else {
+                log::trace!("Mock backend: {} = {}", key, value);
+            }
+        }
+
+        Ok(element)
+    }
+
+    fn build_override(&self, factory: &str) -> Result<gst::Element> {
+        let mut builder = gst::ElementFactory::make(factory);
+        if let Some(name) = &self.name {
+            builder = builder.name(name);
+        }
+        let element =
+            builder
+                .build()
+                .map_err(|_| crate::error::DeepStreamError::ElementCreation {
+                    element: factory.to_string(),
+                })?;
+        log::info!("Using {} as {}", factory, self.element_type.role());
         Ok(element)
     }
 }
@@ -164,4 +289,21 @@ mod tests {
         );
         assert_eq!(builder.properties.get("width"), Some(&"1920".to_string()));
     }
+
+    #[test]
+    fn test_element_builder_typed_properties() {
+        let builder = ElementBuilder::new(DeepStreamElementType::Tiler)
+            .typed_property("rows", 2u32)
+            .typed_property("show-source", -1)
+            .validate_properties(true);
+
+        assert!(builder.validate);
+        assert_eq!(
+            builder.typed_properties,
+            vec![
+                ("rows".to_string(), PropertyValue::UInt(2)),
+                ("show-source".to_string(), PropertyValue::Int(-1)),
+            ]
+        );
+    }
 }
@@ -0,0 +1,367 @@
+//! Property and caps checks for elements.
+//!
+//! `set_property_from_str` only logs a warning when a property name is
+//! misspelled or a value does not parse, so a typo in a config leaves the
+//! element with its defaults. These helpers look the property up in the
+//! element's GObject spec first and return an error instead.
+
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use std::fmt;
+
+/// A typed property value, converted to the property's GType on set
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Double(f64),
+    /// Parsed with the property's spec, so enums, flags and caps work too
+    String(String),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Bool(v) => write!(f, "{}", v),
+            PropertyValue::Int(v) => write!(f, "{}", v),
+            PropertyValue::UInt(v) => write!(f, "{}", v),
+            PropertyValue::Double(v) => write!(f, "{}", v),
+            PropertyValue::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<i32> for PropertyValue {
+    fn from(value: i32) -> Self {
+        PropertyValue::Int(value.into())
+    }
+}
+
+impl From<i64> for PropertyValue {
+    fn from(value: i64) -> Self {
+        PropertyValue::Int(value)
+    }
+}
+
+impl From<u32> for PropertyValue {
+    fn from(value: u32) -> Self {
+        PropertyValue::UInt(value.into())
+    }
+}
+
+impl From<u64> for PropertyValue {
+    fn from(value: u64) -> Self {
+        PropertyValue::UInt(value)
+    }
+}
+
+impl From<f32> for PropertyValue {
+    fn from(value: f32) -> Self {
+        PropertyValue::Double(value.into())
+    }
+}
+
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        PropertyValue::Double(value)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.to_string())
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+/// Description of one element property
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyInfo {
+    pub name: String,
+    pub type_name: String,
+    pub writable: bool,
+    pub construct_only: bool,
+    pub blurb: Option<String>,
+}
+
+/// List the properties an element exposes.
+pub fn describe_properties(element: &gst::Element) -> Vec<PropertyInfo> {
+    element
+        .list_properties()
+        .iter()
+        .map(|pspec| {
+            let flags = pspec.flags();
+            PropertyInfo {
+                name: pspec.name().to_string(),
+                type_name: pspec.value_type().name().to_string(),
+                writable: flags.contains(glib::ParamFlags::WRITABLE),
+                construct_only: flags.contains(glib::ParamFlags::CONSTRUCT_ONLY),
+                blurb: pspec.blurb().map(|b| b.to_string()),
+            }
+        })
+        .collect()
+}
+
+fn element_name(element: &gst::Element) -> String {
+    element
+        .factory()
+        .map(|f| format!("{} ({})", element.name(), f.name()))
+        .unwrap_or_else(|| element.name().to_string())
+}
+
+fn property_error(
+    element: &gst::Element,
+    property: &str,
+    reason: impl fmt::Display,
+) -> DeepStreamError {
+    DeepStreamError::PropertySetting {
+        element: element_name(element),
+        property: format!("{}: {}", property, reason),
+    }
+}
+
+fn writable_pspec(element: &gst::Element, name: &str) -> Result<glib::ParamSpec> {
+    let pspec = element
+        .find_property(name)
+        .ok_or_else(|| property_error(element, name, "no such property"))?;
+
+    let flags = pspec.flags();
+    if !flags.contains(glib::ParamFlags::WRITABLE) {
+        return Err(property_error(element, name, "property is read-only"));
+    }
+    if flags.contains(glib::ParamFlags::CONSTRUCT_ONLY) {
+        return Err(property_error(
+            element,
+            name,
+            "property can only be set at construction",
+        ));
+    }
+    Ok(pspec)
+}
+
+/// Convert `value` to the type declared by the property's spec.
+pub fn to_property_value(
+    element: &gst::Element,
+    name: &str,
+    value: &PropertyValue,
+) -> Result<glib::Value> {
+    let pspec = writable_pspec(element, name)?;
+    let target = pspec.value_type();
+    let mismatch = || {
+        property_error(
+            element,
+            name,
+            format!("cannot use {:?} for a {} property", value, target.name()),
+        )
+    };
+    let out_of_range = || {
+        property_error(
+            element,
+            name,
+            format!("{} is out of range for {}", value, target.name()),
+        )
+    };
+
+    let converted = match (value, target) {
+        (PropertyValue::Bool(v), glib::Type::BOOL) => v.to_value(),
+        (PropertyValue::Int(v), glib::Type::I32) => {
+            i32::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::Int(v), glib::Type::U32) => {
+            u32::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::Int(v), glib::Type::I64) => v.to_value(),
+        (PropertyValue::Int(v), glib::Type::U64) => {
+            u64::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::UInt(v), glib::Type::I32) => {
+            i32::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::UInt(v), glib::Type::U32) => {
+            u32::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::UInt(v), glib::Type::I64) => {
+            i64::try_from(*v).map_err(|_| out_of_range())?.to_value()
+        }
+        (PropertyValue::UInt(v), glib::Type::U64) => v.to_value(),
+        (PropertyValue::Int(v), glib::Type::F64) => (*v as f64).to_value(),
+        (PropertyValue::UInt(v), glib::Type::F64) => (*v as f64).to_value(),
+        (PropertyValue::Double(v), glib::Type::F64) => v.to_value(),
+        (PropertyValue::Int(v), glib::Type::F32) => (*v as f32).to_value(),
+        (PropertyValue::UInt(v), glib::Type::F32) => (*v as f32).to_value(),
+        (PropertyValue::Double(v), glib::Type::F32) => (*v as f32).to_value(),
+        (PropertyValue::String(v), glib::Type::STRING) => v.to_value(),
+        (PropertyValue::String(v), _) => parse_with_pspec(element, name, v, &pspec)?,
+        _ => return Err(mismatch()),
+    };
+
+    Ok(converted)
+}
+
+fn parse_with_pspec(
+    element: &gst::Element,
+    name: &str,
+    value: &str,
+    pspec: &glib::ParamSpec,
+) -> Result<glib::Value> {
+    glib::Value::deserialize_with_pspec(value, pspec).map_err(|_| {
+        property_error(
+            element,
+            name,
+            format!(
+                "'{}' is not a valid {} value",
+                value,
+                pspec.value_type().name()
+            ),
+        )
+    })
+}
+
+/// Check that `value` parses for the named property without setting it.
+pub fn validate_property_str(element: &gst::Element, name: &str, value: &str) -> Result<()> {
+    to_property_value(element, name, &PropertyValue::String(value.to_string())).map(|_| ())
+}
+
+/// Set a property after checking its name, writability and type.
+pub fn set_property_checked(
+    element: &gst::Element,
+    name: &str,
+    value: impl Into<PropertyValue>,
+) -> Result<()> {
+    let value = to_property_value(element, name, &value.into())?;
+    element.set_property_from_value(name, &value);
+    Ok(())
+}
+
+/// Set a property from its string form, failing on unknown names or bad values.
+pub fn set_property_str_checked(element: &gst::Element, name: &str, value: &str) -> Result<()> {
+    set_property_checked(element, name, value)
+}
+
+fn pad_caps(element: &gst::Element, direction: gst::PadDirection) -> gst::Caps {
+    let pads = match direction {
+        gst::PadDirection::Src => element.src_pads(),
+        _ => element.sink_pads(),
+    };
+
+    // Existing pads reflect the element's current configuration; request and
+    // sometimes pads only exist as templates until linking.
+    if let Some(pad) = pads.iter().find(|pad| !pad.is_linked()) {
+        return pad.query_caps(None);
+    }
+
+    let mut caps = gst::Caps::new_empty();
+    for template in element.pad_template_list() {
+        if template.direction() == direction {
+            caps.merge(template.caps().clone());
+        }
+    }
+    caps
+}
+
+/// Verify that some src caps of `src` can be accepted by `dest`.
+pub fn check_caps_compatible(src: &gst::Element, dest: &gst::Element) -> Result<()> {
+    let src_caps = pad_caps(src, gst::PadDirection::Src);
+    let sink_caps = pad_caps(dest, gst::PadDirection::Sink);
+
+    if src_caps.can_intersect(&sink_caps) {
+        Ok(())
+    } else {
+        Err(DeepStreamError::PadLinking(format!(
+            "Incompatible caps between {} and {}: {} does not intersect {}",
+            element_name(src),
+            element_name(dest),
+            src_caps,
+            sink_caps
+        )))
+    }
+}
+
+/// Link two elements, reporting a caps mismatch instead of a bare link failure.
+pub fn link_checked(src: &gst::Element, dest: &gst::Element) -> Result<()> {
+    check_caps_compatible(src, dest)?;
+    src.link(dest).map_err(|_| {
+        DeepStreamError::PadLinking(format!(
+            "Failed to link {} to {}",
+            element_name(src),
+            element_name(dest)
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make(factory: &str) -> gst::Element {
+        let _ = gst::init();
+        gst::ElementFactory::make(factory).build().unwrap()
+    }
+
+    #[test]
+    fn test_unknown_property_rejected() {
+        let queue = make("queue");
+        assert!(set_property_checked(&queue, "max-size-buffers", 4u32).is_ok());
+        assert_eq!(queue.property::<u32>("max-size-buffers"), 4);
+
+        let err = set_property_checked(&queue, "max-size-bufers", 4u32).unwrap_err();
+        assert!(err.to_string().contains("no such property"));
+    }
+
+    #[test]
+    fn test_out_of_range_value_rejected() {
+        let queue = make("queue");
+        assert!(set_property_checked(&queue, "max-size-buffers", -1).is_err());
+    }
+
+    #[test]
+    fn test_wrong_type_rejected() {
+        let queue = make("queue");
+        assert!(set_property_checked(&queue, "max-size-buffers", "many").is_err());
+        assert!(set_property_checked(&queue, "max-size-buffers", true).is_err());
+    }
+
+    #[test]
+    fn test_enum_from_string() {
+        let queue = make("queue");
+        assert!(set_property_str_checked(&queue, "leaky", "downstream").is_ok());
+        assert!(validate_property_str(&queue, "leaky", "sideways").is_err());
+    }
+
+    #[test]
+    fn test_describe_properties() {
+        let queue = make("queue");
+        let info = describe_properties(&queue);
+        let leaky = info.iter().find(|p| p.name == "leaky").unwrap();
+        assert!(leaky.writable);
+        assert!(
+            info.iter()
+                .any(|p| p.name == "current-level-buffers" && !p.writable)
+        );
+    }
+
+    #[test]
+    fn test_caps_compatibility() {
+        let video = make("videotestsrc");
+        let audio_sink = make("audioconvert");
+        let convert = make("videoconvert");
+
+        assert!(check_caps_compatible(&video, &convert).is_ok());
+        let err = check_caps_compatible(&video, &audio_sink).unwrap_err();
+        assert!(err.to_string().contains("Incompatible caps"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::DeepStreamError;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Severity level of an error
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -29,6 +29,8 @@ pub enum ErrorCategory {
     Resource,
     /// Hardware or driver errors
     Hardware,
+    /// Inference model loading or execution errors
+    Model,
     /// Unknown or unclassified errors
     Unknown,
 }
@@ -71,14 +73,15 @@ pub struct ErrorClassification {
     pub description: String,
 }
 
-/// Error classifier that maps errors to classifications
+/// Error classifier that maps errors to classifications. Patterns are
+/// tried in lexical order, so the same error always gets the same match.
 pub struct ErrorClassifier {
-    patterns: HashMap<String, ErrorClassification>,
+    patterns: BTreeMap<String, ErrorClassification>,
 }
 
 impl ErrorClassifier {
     pub fn new() -> Self {
-        let mut patterns = HashMap::new();
+        let mut patterns = BTreeMap::new();
 
         // Network errors
         patterns.insert(
@@ -167,6 +170,19 @@ impl ErrorClassifier {
             },
         );
 
+        patterns.insert(
+            "busy".to_string(),
+            ErrorClassification {
+                severity: ErrorSeverity::Recoverable,
+                category: ErrorCategory::Resource,
+                persistence: ErrorPersistence::Transient,
+                action: RecoveryAction::RetryWithBackoff {
+                    initial_delay_ms: 1000,
+                },
+                description: "Resource busy".to_string(),
+            },
+        );
+
         patterns.insert(
             "out of memory".to_string(),
             ErrorClassification {
@@ -206,8 +222,12 @@ impl ErrorClassifier {
         Self { patterns }
     }
 
-    /// Classify an error based on its message
+    /// Classify an error based on its type and message
     pub fn classify_error(&self, error: &DeepStreamError) -> ErrorClassification {
+        if let Some(classification) = self.classify_typed(error) {
+            return classification;
+        }
+
         let error_str = error.to_string().to_lowercase();
 
         // Check for pattern matches
@@ -278,12 +298,94 @@ impl ErrorClassifier {
         }
     }
 
+    /// Typed errors keep their category; patterns only refine it, matched
+    /// against the reason so the element name cannot trigger one
+    fn classify_typed(&self, error: &DeepStreamError) -> Option<ErrorClassification> {
+        let (category, reason, default) = match error {
+            DeepStreamError::Decode { reason, .. } => (
+                ErrorCategory::Codec,
+                reason,
+                ErrorClassification {
+                    severity: ErrorSeverity::Recoverable,
+                    category: ErrorCategory::Codec,
+                    persistence: ErrorPersistence::Transient,
+                    action: RecoveryAction::ResetElement {
+                        element_name: "decoder".to_string(),
+                    },
+                    description: "Decode error".to_string(),
+                },
+            ),
+            DeepStreamError::Network { reason, .. } => (
+                ErrorCategory::Network,
+                reason,
+                ErrorClassification {
+                    severity: ErrorSeverity::Recoverable,
+                    category: ErrorCategory::Network,
+                    persistence: ErrorPersistence::Transient,
+                    action: RecoveryAction::Reconnect,
+                    description: "Network error".to_string(),
+                },
+            ),
+            DeepStreamError::Negotiation { reason, .. } => (
+                ErrorCategory::Codec,
+                reason,
+                ErrorClassification {
+                    severity: ErrorSeverity::Critical,
+                    category: ErrorCategory::Codec,
+                    persistence: ErrorPersistence::Permanent,
+                    action: RecoveryAction::RestartPipeline,
+                    description: "Caps negotiation failed".to_string(),
+                },
+            ),
+            DeepStreamError::Resource { reason, .. } => (
+                ErrorCategory::Resource,
+                reason,
+                ErrorClassification {
+                    severity: ErrorSeverity::Recoverable,
+                    category: ErrorCategory::Resource,
+                    persistence: ErrorPersistence::Transient,
+                    action: RecoveryAction::RetryWithBackoff {
+                        initial_delay_ms: 500,
+                    },
+                    description: "Resource error".to_string(),
+                },
+            ),
+            DeepStreamError::Model { reason, .. } => (
+                ErrorCategory::Model,
+                reason,
+                ErrorClassification {
+                    severity: ErrorSeverity::Critical,
+                    category: ErrorCategory::Model,
+                    persistence: ErrorPersistence::Permanent,
+                    action: RecoveryAction::NoRecovery,
+                    description: "Model error".to_string(),
+                },
+            ),
+            _ => return None,
+        };
+
+        let reason = reason.to_lowercase();
+        let mut classification = self
+            .patterns
+            .iter()
+            .find(|(pattern, c)| c.category == category && reason.contains(pattern.as_str()))
+            .map(|(_, c)| c.clone())
+            .unwrap_or(default);
+        if let (RecoveryAction::ResetElement { element_name }, Some(element)) =
+            (&mut classification.action, error.element())
+        {
+            *element_name = element.to_string();
+        }
+        Some(classification)
+    }
+
     /// Add a custom error pattern
     pub fn add_pattern(&mut self, pattern: String, classification: ErrorClassification) {
         self.patterns.insert(pattern, classification);
     }
 
-    /// Check if an error is retryable
+    /// Check if an error is retryable; resetting the failed element
+    /// counts, since the operation is retried once it is back
     pub fn is_retryable(&self, error: &DeepStreamError) -> bool {
         let classification = self.classify_error(error);
         matches!(
@@ -291,6 +393,7 @@ impl ErrorClassifier {
             RecoveryAction::RetryNow
                 | RecoveryAction::RetryWithBackoff { .. }
                 | RecoveryAction::Reconnect
+                | RecoveryAction::ResetElement { .. }
         )
     }
 
@@ -298,7 +401,9 @@ impl ErrorClassifier {
     pub fn get_retry_delay(&self, error: &DeepStreamError) -> Option<std::time::Duration> {
         let classification = self.classify_error(error);
         match classification.action {
-            RecoveryAction::RetryNow => Some(std::time::Duration::from_millis(0)),
+            RecoveryAction::RetryNow | RecoveryAction::ResetElement { .. } => {
+                Some(std::time::Duration::from_millis(0))
+            }
             RecoveryAction::RetryWithBackoff { initial_delay_ms } => {
                 Some(std::time::Duration::from_millis(initial_delay_ms))
             }
@@ -389,6 +494,74 @@ mod tests {
         assert_eq!(delay.unwrap(), std::time::Duration::from_millis(1000));
     }
 
+    #[test]
+    fn test_typed_error_classification() {
+        let classifier = ErrorClassifier::new();
+
+        let decode = DeepStreamError::Decode {
+            element: Some("h264dec0".to_string()),
+            reason: "Corrupt slice".to_string(),
+        };
+        let classification = classifier.classify_error(&decode);
+        assert_eq!(classification.category, ErrorCategory::Codec);
+        assert_eq!(
+            classification.action,
+            RecoveryAction::ResetElement {
+                element_name: "h264dec0".to_string()
+            }
+        );
+        assert!(classifier.is_retryable(&decode));
+
+        // The element name must not match the "rtsp" pattern
+        let network = DeepStreamError::Network {
+            element: Some("rtspsrc0".to_string()),
+            reason: "Connection refused".to_string(),
+        };
+        let classification = classifier.classify_error(&network);
+        assert_eq!(classification.category, ErrorCategory::Network);
+        assert_eq!(
+            classification.action,
+            RecoveryAction::RetryWithBackoff {
+                initial_delay_ms: 1000
+            }
+        );
+
+        let negotiation = DeepStreamError::Negotiation {
+            element: None,
+            reason: "No common caps".to_string(),
+        };
+        assert!(!classifier.is_retryable(&negotiation));
+
+        let missing = DeepStreamError::Resource {
+            element: Some("filesrc0".to_string()),
+            reason: "File not found".to_string(),
+        };
+        assert_eq!(
+            classifier.classify_error(&missing).action,
+            RecoveryAction::FailSource
+        );
+        let busy = DeepStreamError::Resource {
+            element: Some("v4l2src0".to_string()),
+            reason: "Device is busy".to_string(),
+        };
+        assert!(classifier.is_retryable(&busy));
+
+        let model = DeepStreamError::Model {
+            element: None,
+            reason: "Invalid ONNX graph".to_string(),
+        };
+        assert_eq!(
+            classifier.classify_error(&model).category,
+            ErrorCategory::Model
+        );
+        assert!(!classifier.is_retryable(&model));
+        assert_eq!(model.to_string(), "Model error: Invalid ONNX graph");
+        assert_eq!(
+            missing.to_string(),
+            "Resource error in filesrc0: File not found"
+        );
+    }
+
     #[test]
     fn test_custom_pattern() {
         let mut classifier = ErrorClassifier::new();
@@ -2,6 +2,9 @@ pub mod classification;
 
 #[cfg(feature = "cpu_vision")]
 use gstcpuinfer::detector::DetectorError;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use source_videos::SourceVideoError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -66,24 +69,224 @@ pub enum DeepStreamError {
     #[error("Resource limit: {0}")]
     ResourceLimit(String),
 
+    #[error("Decode error{}: {reason}", in_element(.element))]
+    Decode {
+        element: Option<String>,
+        reason: String,
+    },
+
+    #[error("Network error{}: {reason}", in_element(.element))]
+    Network {
+        element: Option<String>,
+        reason: String,
+    },
+
+    #[error("Caps negotiation failed{}: {reason}", in_element(.element))]
+    Negotiation {
+        element: Option<String>,
+        reason: String,
+    },
+
+    #[error("Resource error{}: {reason}", in_element(.element))]
+    Resource {
+        element: Option<String>,
+        reason: String,
+    },
+
+    #[error("Model error{}: {reason}", in_element(.element))]
+    Model {
+        element: Option<String>,
+        reason: String,
+    },
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+fn in_element(element: &Option<String>) -> String {
+    element
+        .as_ref()
+        .map(|element| format!(" in {}", element))
+        .unwrap_or_default()
+}
+
+impl DeepStreamError {
+    /// Element the error came from, for the typed variants that know it
+    pub fn element(&self) -> Option<&str> {
+        match self {
+            Self::Decode { element, .. }
+            | Self::Network { element, .. }
+            | Self::Negotiation { element, .. }
+            | Self::Resource { element, .. }
+            | Self::Model { element, .. } => element.as_deref(),
+            Self::ElementCreation { element }
+            | Self::ElementNotFound { element }
+            | Self::PropertySetting { element, .. }
+            | Self::PadNotFound { element, .. } => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Sort a GStreamer error into the taxonomy by its domain and code and
+    /// the element that posted it; errors that fit nowhere stay `GStreamer`
+    pub fn from_gst_error(error: &gst::glib::Error, src: Option<&gst::Object>) -> Self {
+        let element = src.map(|src| src.name().to_string());
+        let reason = error.message().to_string();
+
+        if let Some(code) = error.kind::<gst::StreamError>() {
+            match code {
+                gst::StreamError::Decode
+                | gst::StreamError::CodecNotFound
+                | gst::StreamError::Demux
+                | gst::StreamError::WrongType
+                | gst::StreamError::TypeNotFound => Self::Decode { element, reason },
+                gst::StreamError::Format => Self::Negotiation { element, reason },
+                _ => Self::GStreamer(error.clone()),
+            }
+        } else if error.kind::<gst::CoreError>() == Some(gst::CoreError::Negotiation) {
+            Self::Negotiation { element, reason }
+        } else if let Some(code) = error.kind::<gst::ResourceError>() {
+            if code != gst::ResourceError::NoSpaceLeft && src.is_some_and(is_network) {
+                Self::Network { element, reason }
+            } else {
+                Self::Resource { element, reason }
+            }
+        } else if error.kind::<gst::LibraryError>().is_some() && src.is_some_and(is_inference) {
+            Self::Model { element, reason }
+        } else {
+            Self::GStreamer(error.clone())
+        }
+    }
+
+    /// [`from_gst_error`](Self::from_gst_error) for an error message, which
+    /// also catches the generic stream errors caused by `not-negotiated`
+    pub fn from_gst_message(err: &gst::message::Error) -> Self {
+        let error = err.error();
+        let src = err.src();
+        let not_negotiated = err
+            .debug()
+            .is_some_and(|debug| debug.contains("not-negotiated"));
+        if not_negotiated && error.kind::<gst::StreamError>() == Some(gst::StreamError::Failed) {
+            return Self::Negotiation {
+                element: src.map(|src| src.name().to_string()),
+                reason: error.message().to_string(),
+            };
+        }
+        Self::from_gst_error(&error, src)
+    }
+}
+
+fn factory_matches(object: &gst::Object, matches: impl Fn(&gst::ElementFactory) -> bool) -> bool {
+    object
+        .downcast_ref::<gst::Element>()
+        .and_then(|element| element.factory())
+        .is_some_and(|factory| matches(&factory))
+}
+
+/// The object or a bin around it reads from the network, e.g. `udpsrc`
+/// inside `rtspsrc`
+fn is_network(src: &gst::Object) -> bool {
+    let mut object = Some(src.clone());
+    while let Some(current) = object {
+        if factory_matches(&current, |factory| factory.klass().contains("Network")) {
+            return true;
+        }
+        object = current.parent();
+    }
+    false
+}
+
+fn is_inference(src: &gst::Object) -> bool {
+    factory_matches(src, |factory| {
+        factory.name().contains("infer") || factory.klass().contains("Analyzer")
+    })
+}
+
 // Conversion from cpuinfer DetectorError
 #[cfg(feature = "cpu_vision")]
 impl From<DetectorError> for DeepStreamError {
     fn from(err: DetectorError) -> Self {
         match err {
             DetectorError::Configuration(msg) => DeepStreamError::Configuration(msg),
-            DetectorError::ModelLoading(msg) => {
-                DeepStreamError::Configuration(format!("Model loading: {}", msg))
-            }
+            DetectorError::ModelLoading(msg) => DeepStreamError::Model {
+                element: None,
+                reason: msg,
+            },
             DetectorError::Inference(msg) => DeepStreamError::ProcessingFailed { reason: msg },
         }
     }
 }
 
+// Applications that serve test streams with source-videos and analyze them
+// with ds-rs can use `?` across both crates
+impl From<SourceVideoError> for DeepStreamError {
+    fn from(err: SourceVideoError) -> Self {
+        match err {
+            SourceVideoError::GStreamer(e) => DeepStreamError::GStreamer(e),
+            SourceVideoError::GStreamerBool(e) => DeepStreamError::GStreamerBool(e),
+            SourceVideoError::Configuration(msg) => DeepStreamError::Configuration(msg),
+            SourceVideoError::Server(msg) => DeepStreamError::Network {
+                element: None,
+                reason: msg,
+            },
+            SourceVideoError::Resource(msg) => DeepStreamError::Resource {
+                element: None,
+                reason: msg,
+            },
+            SourceVideoError::Pipeline(msg) => DeepStreamError::Pipeline(msg),
+            SourceVideoError::SourceNotFound(name) => {
+                DeepStreamError::InvalidInput(format!("Source not found: {}", name))
+            }
+            SourceVideoError::InvalidPattern(pattern) => {
+                DeepStreamError::InvalidInput(format!("Invalid pattern: {}", pattern))
+            }
+            SourceVideoError::File(e) => DeepStreamError::Io(e),
+            SourceVideoError::FileNotFound(path) => DeepStreamError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("File not found: {}", path),
+            )),
+            SourceVideoError::TomlParse(e) => DeepStreamError::TomlParse(e),
+            SourceVideoError::StateChange(msg) => DeepStreamError::StateChange(msg),
+            SourceVideoError::ElementCreation(element) => {
+                DeepStreamError::ElementCreation { element }
+            }
+            SourceVideoError::LinkingFailed(src, sink) => {
+                DeepStreamError::PadLinking(format!("{} -> {}", src, sink))
+            }
+            SourceVideoError::RtspMountPoint(msg) => DeepStreamError::Network {
+                element: None,
+                reason: format!("RTSP mount point: {}", msg),
+            },
+            SourceVideoError::Timeout(secs) => {
+                DeepStreamError::Timeout(format!("operation timed out after {} seconds", secs))
+            }
+        }
+    }
+}
+
+/// The other way, for source-videos code calling into ds-rs; errors
+/// without a counterpart keep their message as a pipeline error
+impl From<DeepStreamError> for SourceVideoError {
+    fn from(err: DeepStreamError) -> Self {
+        match err {
+            DeepStreamError::GStreamer(e) => SourceVideoError::GStreamer(e),
+            DeepStreamError::GStreamerBool(e) => SourceVideoError::GStreamerBool(e),
+            DeepStreamError::Configuration(msg) => SourceVideoError::Configuration(msg),
+            DeepStreamError::Io(e) => SourceVideoError::File(e),
+            DeepStreamError::TomlParse(e) => SourceVideoError::TomlParse(e),
+            DeepStreamError::StateChange(msg) => SourceVideoError::StateChange(msg),
+            DeepStreamError::ElementCreation { element } => {
+                SourceVideoError::ElementCreation(element)
+            }
+            DeepStreamError::Resource { .. } | DeepStreamError::ResourceLimit(_) => {
+                SourceVideoError::Resource(err.to_string())
+            }
+            DeepStreamError::Pipeline(msg) => SourceVideoError::Pipeline(msg),
+            other => SourceVideoError::Pipeline(other.to_string()),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DeepStreamError>;
 
 pub trait ResultExt<T> {
@@ -106,3 +309,36 @@ pub use classification::{
     ErrorCategory, ErrorClassification, ErrorClassifier, ErrorPersistence, ErrorSeverity,
     RecoveryAction, classify, is_retryable,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_videos_conversion() {
+        fn serve() -> std::result::Result<(), SourceVideoError> {
+            Err(SourceVideoError::Server("port 8554 in use".to_string()))
+        }
+        fn analyze() -> Result<()> {
+            serve()?;
+            Ok(())
+        }
+
+        let err = analyze().unwrap_err();
+        assert!(matches!(err, DeepStreamError::Network { .. }));
+        assert_eq!(classify(&err).category, ErrorCategory::Network);
+
+        let err = DeepStreamError::from(SourceVideoError::FileNotFound("a.mp4".to_string()));
+        assert!(matches!(&err, DeepStreamError::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_conversion_to_source_videos() {
+        let back = SourceVideoError::from(DeepStreamError::ElementCreation {
+            element: "x264enc".to_string(),
+        });
+        assert!(matches!(back, SourceVideoError::ElementCreation(element) if element == "x264enc"));
+        let back = SourceVideoError::from(DeepStreamError::Timeout("no frames".to_string()));
+        assert_eq!(back.to_string(), "Pipeline error: Timeout: no frames");
+    }
+}
@@ -0,0 +1,388 @@
+//! Golden-output regression checks for detection pipelines
+//!
+//! A run over a fixture video produces a [`GoldenOutput`]: the detections
+//! (and track ids, when tracking) of every frame. [`check`] compares it with
+//! the JSON stored from an earlier, reviewed run and lists every frame
+//! where boxes moved or confidences changed by more than the
+//! [`Tolerance`], or where detections appeared or vanished.
+//!
+//! Set `DS_RS_UPDATE_GOLDEN=1` to write the current output as the new
+//! golden file instead of comparing; a missing golden file is written the
+//! same way.
+
+#[cfg(feature = "cpu_vision")]
+pub mod runner;
+
+#[cfg(feature = "cpu_vision")]
+pub use runner::{GoldenRunConfig, run_fixture};
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+pub const UPDATE_ENV: &str = "DS_RS_UPDATE_GOLDEN";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoldenDetection {
+    pub class_id: usize,
+    pub class_name: String,
+    pub confidence: f32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_id: Option<u64>,
+}
+
+impl GoldenDetection {
+    /// Largest distance between matching edges of the two boxes
+    fn offset(&self, other: &Self) -> f32 {
+        [
+            (self.x - other.x).abs(),
+            (self.y - other.y).abs(),
+            ((self.x + self.width) - (other.x + other.width)).abs(),
+            ((self.y + self.height) - (other.y + other.height)).abs(),
+        ]
+        .into_iter()
+        .fold(0.0, f32::max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoldenFrame {
+    pub frame: u64,
+    pub detections: Vec<GoldenDetection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoldenOutput {
+    pub fixture: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub frames: Vec<GoldenFrame>,
+}
+
+impl GoldenOutput {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| {
+            crate::error::DeepStreamError::Configuration(format!(
+                "Invalid golden file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    pub fn store(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::from)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+/// How far a detection may drift before it counts as changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Pixels any box edge may move
+    pub box_px: f32,
+    pub confidence: f32,
+    /// Compare track ids when both sides have them
+    pub track_ids: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            box_px: 2.0,
+            confidence: 0.02,
+            track_ids: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    FrameCount {
+        expected: usize,
+        actual: usize,
+    },
+    Missing {
+        frame: u64,
+        expected: GoldenDetection,
+    },
+    Unexpected {
+        frame: u64,
+        actual: GoldenDetection,
+    },
+    BoxMoved {
+        frame: u64,
+        class_name: String,
+        offset: f32,
+    },
+    Confidence {
+        frame: u64,
+        class_name: String,
+        expected: f32,
+        actual: f32,
+    },
+    TrackId {
+        frame: u64,
+        class_name: String,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FrameCount { expected, actual } => {
+                write!(f, "expected {} frames, got {}", expected, actual)
+            }
+            Self::Missing { frame, expected } => write!(
+                f,
+                "frame {}: missing {} at ({:.1}, {:.1}) {:.1}x{:.1}",
+                frame, expected.class_name, expected.x, expected.y, expected.width, expected.height
+            ),
+            Self::Unexpected { frame, actual } => write!(
+                f,
+                "frame {}: unexpected {} at ({:.1}, {:.1}) {:.1}x{:.1}",
+                frame, actual.class_name, actual.x, actual.y, actual.width, actual.height
+            ),
+            Self::BoxMoved {
+                frame,
+                class_name,
+                offset,
+            } => write!(
+                f,
+                "frame {}: {} box moved by {:.1}px",
+                frame, class_name, offset
+            ),
+            Self::Confidence {
+                frame,
+                class_name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "frame {}: {} confidence {:.3} -> {:.3}",
+                frame, class_name, expected, actual
+            ),
+            Self::TrackId {
+                frame,
+                class_name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "frame {}: {} track id {} -> {}",
+                frame, class_name, expected, actual
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenDiff {
+    pub fixture: String,
+    pub differences: Vec<Difference>,
+}
+
+impl GoldenDiff {
+    pub fn is_match(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for GoldenDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_match() {
+            return write!(f, "{}: matches golden output", self.fixture);
+        }
+        write!(
+            f,
+            "{}: {} difference(s) from golden output",
+            self.fixture,
+            self.differences.len()
+        )?;
+        for difference in &self.differences {
+            write!(f, "\n  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every difference between `expected` and `actual` beyond `tolerance`
+pub fn compare(
+    expected: &GoldenOutput,
+    actual: &GoldenOutput,
+    tolerance: &Tolerance,
+) -> GoldenDiff {
+    let mut differences = Vec::new();
+    if expected.frames.len() != actual.frames.len() {
+        differences.push(Difference::FrameCount {
+            expected: expected.frames.len(),
+            actual: actual.frames.len(),
+        });
+    }
+
+    for (expected_frame, actual_frame) in expected.frames.iter().zip(&actual.frames) {
+        compare_frame(
+            expected_frame.frame,
+            &expected_frame.detections,
+            &actual_frame.detections,
+            tolerance,
+            &mut differences,
+        );
+    }
+
+    GoldenDiff {
+        fixture: expected.fixture.clone(),
+        differences,
+    }
+}
+
+fn compare_frame(
+    frame: u64,
+    expected: &[GoldenDetection],
+    actual: &[GoldenDetection],
+    tolerance: &Tolerance,
+    differences: &mut Vec<Difference>,
+) {
+    let mut unmatched: Vec<&GoldenDetection> = actual.iter().collect();
+    for want in expected {
+        // Pair with the closest remaining detection of the same class
+        let closest = unmatched
+            .iter()
+            .enumerate()
+            .filter(|(_, got)| got.class_id == want.class_id)
+            .min_by(|(_, a), (_, b)| want.offset(a).total_cmp(&want.offset(b)))
+            .map(|(i, _)| i);
+        let Some(index) = closest else {
+            differences.push(Difference::Missing {
+                frame,
+                expected: want.clone(),
+            });
+            continue;
+        };
+        let got = unmatched.swap_remove(index);
+
+        let offset = want.offset(got);
+        if offset > tolerance.box_px {
+            differences.push(Difference::BoxMoved {
+                frame,
+                class_name: want.class_name.clone(),
+                offset,
+            });
+        }
+        if (want.confidence - got.confidence).abs() > tolerance.confidence {
+            differences.push(Difference::Confidence {
+                frame,
+                class_name: want.class_name.clone(),
+                expected: want.confidence,
+                actual: got.confidence,
+            });
+        }
+        if tolerance.track_ids
+            && let (Some(expected_id), Some(actual_id)) = (want.track_id, got.track_id)
+            && expected_id != actual_id
+        {
+            differences.push(Difference::TrackId {
+                frame,
+                class_name: want.class_name.clone(),
+                expected: expected_id,
+                actual: actual_id,
+            });
+        }
+    }
+
+    differences.extend(unmatched.into_iter().map(|got| Difference::Unexpected {
+        frame,
+        actual: got.clone(),
+    }));
+}
+
+/// Compare `actual` with the golden file at `path`, or write it there when
+/// the file is missing or `DS_RS_UPDATE_GOLDEN` is set
+pub fn check(path: &Path, actual: &GoldenOutput, tolerance: &Tolerance) -> Result<GoldenDiff> {
+    let update = std::env::var(UPDATE_ENV).is_ok_and(|value| value != "0");
+    if update || !path.exists() {
+        log::info!("Writing golden output {}", path.display());
+        actual.store(path)?;
+        return Ok(GoldenDiff {
+            fixture: actual.fixture.clone(),
+            differences: Vec::new(),
+        });
+    }
+
+    let expected = GoldenOutput::load(path)?;
+    Ok(compare(&expected, actual, tolerance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(x: f32, confidence: f32, track_id: u64) -> GoldenDetection {
+        GoldenDetection {
+            class_id: 0,
+            class_name: "person".to_string(),
+            confidence,
+            x,
+            y: 10.0,
+            width: 50.0,
+            height: 100.0,
+            track_id: Some(track_id),
+        }
+    }
+
+    fn output(frames: Vec<Vec<GoldenDetection>>) -> GoldenOutput {
+        GoldenOutput {
+            fixture: "walk.mp4".to_string(),
+            model: None,
+            frames: frames
+                .into_iter()
+                .enumerate()
+                .map(|(frame, detections)| GoldenFrame {
+                    frame: frame as u64,
+                    detections,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare_with_tolerance() {
+        let expected = output(vec![
+            vec![person(100.0, 0.90, 1), person(300.0, 0.80, 2)],
+            vec![person(104.0, 0.90, 1)],
+        ]);
+        let actual = output(vec![
+            // Reordered and within tolerance
+            vec![person(301.0, 0.81, 2), person(100.5, 0.89, 1)],
+            vec![person(110.0, 0.70, 3), person(500.0, 0.50, 4)],
+        ]);
+
+        let diff = compare(&expected, &actual, &Tolerance::default());
+        assert_eq!(diff.differences.len(), 4, "{}", diff);
+        assert!(matches!(
+            diff.differences[0],
+            Difference::BoxMoved { frame: 1, .. }
+        ));
+        assert!(matches!(diff.differences[1], Difference::Confidence { .. }));
+        assert!(matches!(diff.differences[2], Difference::TrackId { .. }));
+        assert!(matches!(diff.differences[3], Difference::Unexpected { .. }));
+    }
+
+    #[test]
+    fn test_identical_output_matches() {
+        let expected = output(vec![vec![person(100.0, 0.90, 1)]]);
+        assert!(compare(&expected, &expected, &Tolerance::default()).is_match());
+    }
+}
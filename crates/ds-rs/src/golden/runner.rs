@@ -0,0 +1,91 @@
+use super::{GoldenDetection, GoldenFrame, GoldenOutput};
+use crate::backend::cpu_vision::tracker::CentroidTracker;
+use crate::bench::load_frames;
+use crate::error::Result;
+use gstcpuinfer::detector::{DetectorConfig, OnnxDetector};
+
+/// What [`run_fixture`] runs and over which input
+#[derive(Debug, Clone)]
+pub struct GoldenRunConfig {
+    pub detector: DetectorConfig,
+    /// Video or image file, or URI
+    pub fixture: String,
+    pub max_frames: usize,
+    /// Run the centroid tracker and record track ids
+    pub track: bool,
+    /// Tracker association distance in pixels
+    pub max_track_distance: f32,
+}
+
+impl Default for GoldenRunConfig {
+    fn default() -> Self {
+        Self {
+            detector: DetectorConfig::default(),
+            fixture: String::new(),
+            max_frames: 100,
+            track: true,
+            max_track_distance: 50.0,
+        }
+    }
+}
+
+/// Decode the fixture and run the CPU detector, and optionally the
+/// tracker, over every frame in order
+pub fn run_fixture(config: &GoldenRunConfig) -> Result<GoldenOutput> {
+    let frames = load_frames(&config.fixture, config.max_frames)?;
+    let detector = OnnxDetector::new_with_config(config.detector.clone())?;
+    let mut tracker = CentroidTracker::new(config.max_track_distance, 30);
+
+    let mut output = Vec::with_capacity(frames.len());
+    for (frame, image) in frames.iter().enumerate() {
+        let found = detector.detect(image)?;
+        let mut detections: Vec<GoldenDetection> = if config.track {
+            tracker
+                .update(found)
+                .into_iter()
+                .filter(|object| object.disappeared_count == 0)
+                .map(|object| GoldenDetection {
+                    class_id: object.class_id,
+                    class_name: object.class_name,
+                    confidence: object.confidence,
+                    x: object.bbox.x,
+                    y: object.bbox.y,
+                    width: object.bbox.width,
+                    height: object.bbox.height,
+                    track_id: Some(object.id),
+                })
+                .collect()
+        } else {
+            found
+                .into_iter()
+                .map(|detection| GoldenDetection {
+                    class_id: detection.class_id,
+                    class_name: detection.class_name,
+                    confidence: detection.confidence,
+                    x: detection.x,
+                    y: detection.y,
+                    width: detection.width,
+                    height: detection.height,
+                    track_id: None,
+                })
+                .collect()
+        };
+        // Detector and tracker order is not part of the output
+        detections.sort_by(|a, b| {
+            a.class_id
+                .cmp(&b.class_id)
+                .then(a.x.total_cmp(&b.x))
+                .then(a.y.total_cmp(&b.y))
+        });
+        output.push(GoldenFrame {
+            frame: frame as u64,
+            detections,
+        });
+    }
+
+    Ok(GoldenOutput {
+        fixture: config.fixture.clone(),
+        model: config.detector.model_path.clone(),
+        frames: output,
+    })
+}
@@ -163,6 +163,15 @@ pub struct DetectionConfig {
 
     /// Maximum detections per frame
     pub max_detections: u32,
+
+    /// Per-class confidence thresholds, keyed by class ID or label.
+    /// Classes not listed use `threshold`.
+    #[serde(default)]
+    pub class_thresholds: HashMap<String, f32>,
+
+    /// Score calibration applied before thresholding and NMS
+    #[serde(default)]
+    pub calibration: Option<ScoreCalibration>,
 }
 
 impl Default for DetectionConfig {
@@ -172,10 +181,40 @@ impl Default for DetectionConfig {
             nms_iou_threshold: 0.5,
             min_box_size: 10.0,
             max_detections: 100,
+            class_thresholds: HashMap::new(),
+            calibration: None,
         }
     }
 }
 
+/// Maps raw detector confidences to calibrated probabilities
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum ScoreCalibration {
+    /// `sigmoid(logit(score) / temperature)`; values above 1 soften
+    /// over-confident scores
+    Temperature { temperature: f32 },
+
+    /// `sigmoid(a * logit(score) + b)`, with `a` and `b` fitted on a
+    /// validation set
+    Platt { a: f32, b: f32 },
+}
+
+impl ScoreCalibration {
+    /// Calibrate a confidence in `[0, 1]`
+    pub fn apply(&self, score: f32) -> f32 {
+        let score = score.clamp(1e-6, 1.0 - 1e-6);
+        let logit = (score / (1.0 - score)).ln();
+
+        let calibrated = match *self {
+            ScoreCalibration::Temperature { temperature } => logit / temperature.max(1e-6),
+            ScoreCalibration::Platt { a, b } => a * logit + b,
+        };
+
+        1.0 / (1.0 + (-calibrated).exp())
+    }
+}
+
 /// Processing configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProcessingConfig {
@@ -316,6 +355,39 @@ mod tests {
         assert_eq!(model.input.width, 640);
     }
 
+    #[test]
+    fn test_score_calibration() {
+        let identity = ScoreCalibration::Temperature { temperature: 1.0 };
+        assert!((identity.apply(0.8) - 0.8).abs() < 1e-4);
+
+        let soften = ScoreCalibration::Temperature { temperature: 2.0 };
+        assert!(soften.apply(0.9) < 0.9);
+        assert!(soften.apply(0.1) > 0.1);
+
+        let platt = ScoreCalibration::Platt { a: 1.0, b: 0.0 };
+        assert!((platt.apply(0.3) - 0.3).abs() < 1e-4);
+
+        let detection: DetectionConfig = toml::from_str(
+            r#"
+            threshold = 0.5
+            nms_iou_threshold = 0.45
+            min_box_size = 8.0
+            max_detections = 50
+            calibration = { method = "platt", a = 1.2, b = -0.3 }
+
+            [class_thresholds]
+            person = 0.6
+            "2" = 0.35
+            "#,
+        )
+        .unwrap();
+        assert_eq!(detection.class_thresholds.get("person"), Some(&0.6));
+        assert_eq!(
+            detection.calibration,
+            Some(ScoreCalibration::Platt { a: 1.2, b: -0.3 })
+        );
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = InferenceConfig::default();
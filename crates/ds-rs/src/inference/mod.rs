@@ -3,11 +3,18 @@
 use crate::metadata::{BoundingBox, ClassificationMeta, ObjectMeta};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
 
 pub mod config;
+pub mod roi;
+pub mod telemetry;
 
-pub use config::{InferenceConfig, ModelConfig};
+pub use roi::{InferenceFilter, InferenceFilters, RegionOfInterest};
+pub use telemetry::{InferenceTelemetry, LatencySummary, ModelStats};
+
+pub use config::{DetectionConfig, InferenceConfig, ModelConfig, ScoreCalibration};
 
 /// Errors that can occur during inference operations
 #[derive(Debug, Error)]
@@ -171,11 +178,131 @@ impl LabelMap {
         self.reverse_map.get(label).copied()
     }
 
+    /// Number of labels
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Whether the map has no labels
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
     /// Load label map from file
-    pub fn load_from_file<P: AsRef<Path>>(_path: P) -> Result<Self> {
-        // In a real implementation, this would parse a label file
-        // For now, return a default map
-        Ok(Self::default_coco())
+    ///
+    /// `.json` files are parsed with [`LabelMap::from_json`]; anything else
+    /// (DeepStream `labels.txt`, YOLO `.names`) with [`LabelMap::from_lines`].
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            InferenceError::ConfigError(format!(
+                "Failed to read label file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let map = if is_json {
+            Self::from_json(&content)?
+        } else {
+            Self::from_lines(&content)
+        };
+
+        if map.is_empty() {
+            return Err(InferenceError::ConfigError(format!(
+                "Label file {} contains no labels",
+                path.display()
+            )));
+        }
+        Ok(map)
+    }
+
+    /// Parse one label per line, class ID being the line index.
+    ///
+    /// Blank lines keep their index so IDs stay aligned with the model
+    /// output. A single line of `;`-separated labels, as DeepStream uses for
+    /// classifiers, is split into one label per class.
+    pub fn from_lines(content: &str) -> Self {
+        let mut lines: Vec<&str> = content.lines().map(str::trim).collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        if let [single] = lines.as_slice()
+            && single.contains(';')
+        {
+            lines = single.split(';').map(str::trim).collect();
+        }
+
+        let mut map = Self::new();
+        for (class_id, label) in lines.into_iter().enumerate() {
+            if !label.is_empty() {
+                map.add_label(class_id as i32, label);
+            }
+        }
+        map
+    }
+
+    /// Parse a JSON label map.
+    ///
+    /// Accepts an array of names (`["person", "car"]`), an object keyed by
+    /// class ID (`{"0": "person"}`) or an object keyed by name
+    /// (`{"person": 0}`).
+    pub fn from_json(content: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| InferenceError::ConfigError(format!("Invalid label JSON: {}", e)))?;
+
+        let mut map = Self::new();
+        match value {
+            serde_json::Value::Array(items) => {
+                for (class_id, item) in items.iter().enumerate() {
+                    let label = item.as_str().ok_or_else(|| {
+                        InferenceError::ConfigError(format!(
+                            "Label {} is not a string: {}",
+                            class_id, item
+                        ))
+                    })?;
+                    map.add_label(class_id as i32, label);
+                }
+            }
+            serde_json::Value::Object(entries) => {
+                for (key, item) in &entries {
+                    match (key.parse::<i32>(), item) {
+                        (Ok(class_id), serde_json::Value::String(label)) => {
+                            map.add_label(class_id, label)
+                        }
+                        (_, serde_json::Value::Number(id)) => {
+                            let class_id = id
+                                .as_i64()
+                                .and_then(|id| i32::try_from(id).ok())
+                                .ok_or_else(|| {
+                                    InferenceError::ConfigError(format!(
+                                        "Invalid class ID for \"{}\": {}",
+                                        key, id
+                                    ))
+                                })?;
+                            map.add_label(class_id, key);
+                        }
+                        _ => {
+                            return Err(InferenceError::ConfigError(format!(
+                                "Unsupported label entry \"{}\": {}",
+                                key, item
+                            )));
+                        }
+                    }
+                }
+            }
+            other => {
+                return Err(InferenceError::ConfigError(format!(
+                    "Label JSON must be an array or object, got {}",
+                    other
+                )));
+            }
+        }
+        Ok(map)
     }
 }
 
@@ -186,6 +313,15 @@ pub struct InferenceProcessor {
 
     /// Confidence thresholds per model
     thresholds: HashMap<String, f32>,
+
+    /// Per-class threshold overrides per model
+    class_thresholds: HashMap<String, HashMap<i32, f32>>,
+
+    /// Score calibration per model
+    calibrations: HashMap<String, ScoreCalibration>,
+
+    /// Latency per model
+    telemetry: Arc<InferenceTelemetry>,
 }
 
 impl InferenceProcessor {
@@ -194,15 +330,118 @@ impl InferenceProcessor {
         Self {
             label_maps: HashMap::new(),
             thresholds: HashMap::new(),
+            class_thresholds: HashMap::new(),
+            calibrations: HashMap::new(),
+            telemetry: Arc::new(InferenceTelemetry::new()),
         }
     }
 
+    /// Create a processor with label maps and thresholds for every model in
+    /// `config`. Models without a label file fall back to COCO labels.
+    pub fn from_config(config: &InferenceConfig) -> Result<Self> {
+        let mut processor = Self::new();
+
+        for model in config.primary_gie.iter().chain(&config.secondary_gies) {
+            let label_map = match &model.model_paths.label_file {
+                Some(path) => LabelMap::load_from_file(path)?,
+                None => {
+                    log::warn!("No label file for model {}, using COCO labels", model.name);
+                    LabelMap::default_coco()
+                }
+            };
+
+            let detection = &model.output.detection;
+            for (key, threshold) in &detection.class_thresholds {
+                let class_id = key
+                    .parse::<i32>()
+                    .ok()
+                    .or_else(|| label_map.get_class_id(key))
+                    .ok_or_else(|| {
+                        InferenceError::ConfigError(format!(
+                            "Model {}: threshold for unknown class \"{}\"",
+                            model.name, key
+                        ))
+                    })?;
+                processor.set_class_threshold(&model.name, class_id, *threshold);
+            }
+            if let Some(calibration) = detection.calibration {
+                processor.set_calibration(&model.name, calibration);
+            }
+
+            processor.register_model(&model.name, label_map, detection.threshold);
+        }
+
+        Ok(processor)
+    }
+
+    /// Latency of every model processed
+    pub fn telemetry(&self) -> Arc<InferenceTelemetry> {
+        self.telemetry.clone()
+    }
+
+    /// Report latency to `telemetry`, e.g. the one an application exposes
+    pub fn set_telemetry(&mut self, telemetry: Arc<InferenceTelemetry>) {
+        self.telemetry = telemetry;
+    }
+
+    /// Label map registered for a model
+    pub fn label_map(&self, model_name: &str) -> Option<&LabelMap> {
+        self.label_maps.get(model_name)
+    }
+
     /// Register a model with its label map
     pub fn register_model(&mut self, model_name: &str, label_map: LabelMap, threshold: f32) {
         self.label_maps.insert(model_name.to_string(), label_map);
         self.thresholds.insert(model_name.to_string(), threshold);
     }
 
+    /// Override the confidence threshold of one class
+    pub fn set_class_threshold(&mut self, model_name: &str, class_id: i32, threshold: f32) {
+        self.class_thresholds
+            .entry(model_name.to_string())
+            .or_default()
+            .insert(class_id, threshold);
+    }
+
+    /// Calibrate scores of a model before thresholding
+    pub fn set_calibration(&mut self, model_name: &str, calibration: ScoreCalibration) {
+        self.calibrations
+            .insert(model_name.to_string(), calibration);
+    }
+
+    /// Threshold applied to a class, falling back to the model threshold
+    pub fn threshold_for(&self, model_name: &str, class_id: i32) -> f32 {
+        self.class_thresholds
+            .get(model_name)
+            .and_then(|classes| classes.get(&class_id))
+            .or_else(|| self.thresholds.get(model_name))
+            .copied()
+            .unwrap_or(0.5)
+    }
+
+    /// Calibrate a raw score with the model's calibration, if any
+    pub fn calibrate(&self, model_name: &str, score: f32) -> f32 {
+        match self.calibrations.get(model_name) {
+            Some(calibration) => calibration.apply(score),
+            None => score,
+        }
+    }
+
+    /// Calibrate confidences, drop detections below their class threshold,
+    /// then apply NMS
+    pub fn postprocess(
+        &self,
+        model_name: &str,
+        detections: &mut Vec<ObjectMeta>,
+        iou_threshold: f32,
+    ) {
+        for obj in detections.iter_mut() {
+            obj.confidence = self.calibrate(model_name, obj.confidence);
+        }
+        detections.retain(|obj| obj.confidence >= self.threshold_for(model_name, obj.class_id));
+        Self::apply_nms(detections, iou_threshold);
+    }
+
     /// Process detection output
     pub fn process_detection(
         &self,
@@ -211,20 +450,20 @@ impl InferenceProcessor {
         frame_id: u64,
         source_id: u32,
     ) -> Result<DetectionResult> {
+        let started = Instant::now();
         let mut result = DetectionResult::new(frame_id, source_id, model_name.to_string());
 
         // This is a simplified processing - real implementation would parse
         // the raw tensor output based on model architecture
 
         // Mock processing for demonstration
-        let threshold = self.thresholds.get(model_name).copied().unwrap_or(0.5);
         let label_map = self.label_maps.get(model_name);
 
         // Create mock detections
         if !raw_output.is_empty() {
             let mut obj = ObjectMeta::new(1);
             obj.class_id = 0;
-            obj.confidence = 0.95;
+            obj.confidence = self.calibrate(model_name, 0.95);
             obj.detector_bbox_info = BoundingBox::new(100.0, 100.0, 50.0, 60.0);
             obj.rect_params = obj.detector_bbox_info.clone();
 
@@ -234,11 +473,12 @@ impl InferenceProcessor {
                 }
             }
 
-            if obj.confidence >= threshold {
+            if obj.confidence >= self.threshold_for(model_name, obj.class_id) {
                 result.add_object(obj);
             }
         }
 
+        self.telemetry.record(model_name, started.elapsed());
         Ok(result)
     }
 
@@ -309,6 +549,148 @@ mod tests {
         assert_eq!(map.get_class_id("person"), Some(1));
     }
 
+    #[test]
+    fn test_label_file_formats() {
+        let labels = LabelMap::from_lines("person\nbicycle\n\ncar\n\n");
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels.get_label(3), Some("car"));
+        assert_eq!(labels.get_label(2), None);
+
+        let classifier = LabelMap::from_lines("black;blue;red\n");
+        assert_eq!(classifier.get_label(2), Some("red"));
+
+        let array = LabelMap::from_json(r#"["person", "car"]"#).unwrap();
+        assert_eq!(array.get_class_id("car"), Some(1));
+
+        let by_id = LabelMap::from_json(r#"{"0": "person", "7": "truck"}"#).unwrap();
+        assert_eq!(by_id.get_label(7), Some("truck"));
+
+        let by_name = LabelMap::from_json(r#"{"person": 0, "truck": 7}"#).unwrap();
+        assert_eq!(by_name.get_label(7), Some("truck"));
+
+        assert!(LabelMap::from_json("42").is_err());
+    }
+
+    #[test]
+    fn test_processor_from_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = dir.path().join("coco.names");
+        std::fs::write(&names, "person\nbicycle\n").unwrap();
+
+        let mut model = ModelConfig::default_primary();
+        model.model_paths.label_file = Some(names);
+        let config = InferenceConfig {
+            primary_gie: Some(model),
+            ..InferenceConfig::default()
+        };
+
+        let processor = InferenceProcessor::from_config(&config).unwrap();
+        let map = processor.label_map("primary-detector").unwrap();
+        assert_eq!(map.get_label(1), Some("bicycle"));
+
+        let mut missing = ModelConfig::default_primary();
+        missing.model_paths.label_file = Some(dir.path().join("missing.txt"));
+        let config = InferenceConfig {
+            primary_gie: Some(missing),
+            ..InferenceConfig::default()
+        };
+        assert!(InferenceProcessor::from_config(&config).is_err());
+    }
+
+    fn detection(id: u64, class_id: i32, confidence: f32, x: f32) -> ObjectMeta {
+        let mut obj = ObjectMeta::new(id);
+        obj.class_id = class_id;
+        obj.confidence = confidence;
+        obj.rect_params = BoundingBox::new(x, 0.0, 10.0, 10.0);
+        obj
+    }
+
+    fn detector() -> InferenceProcessor {
+        let mut processor = InferenceProcessor::new();
+        processor.register_model("detector", LabelMap::default_coco(), 0.5);
+        processor
+    }
+
+    #[test]
+    fn test_per_class_thresholds() {
+        let mut processor = detector();
+        processor.set_class_threshold("detector", 2, 0.3);
+
+        assert_eq!(processor.threshold_for("detector", 0), 0.5);
+        assert_eq!(processor.threshold_for("detector", 2), 0.3);
+
+        // Both at 0.4: the person is below the global threshold, the car
+        // passes its class threshold
+        let mut detections = vec![detection(1, 0, 0.4, 0.0), detection(2, 2, 0.4, 50.0)];
+        processor.postprocess("detector", &mut detections, 0.5);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].object_id, 2);
+    }
+
+    #[test]
+    fn test_postprocess_suppresses_overlaps() {
+        let processor = detector();
+
+        let mut detections = vec![detection(1, 2, 0.9, 50.0), detection(2, 2, 0.8, 51.0)];
+        processor.postprocess("detector", &mut detections, 0.5);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].object_id, 1);
+    }
+
+    #[test]
+    fn test_calibration_applies_before_threshold() {
+        let mut processor = detector();
+        processor.set_class_threshold("detector", 2, 0.8);
+
+        let mut detections = vec![detection(1, 2, 0.9, 0.0)];
+        processor.postprocess("detector", &mut detections, 0.5);
+        assert_eq!(detections.len(), 1);
+
+        // A temperature of 2 softens 0.9 to 0.75, under the threshold
+        processor.set_calibration(
+            "detector",
+            ScoreCalibration::Temperature { temperature: 2.0 },
+        );
+        let mut detections = vec![detection(1, 2, 0.9, 0.0)];
+        processor.postprocess("detector", &mut detections, 0.5);
+        assert!(detections.is_empty());
+    }
+
+    #[test]
+    fn test_class_thresholds_from_config() {
+        let mut model = ModelConfig::default_primary();
+        model.model_paths.label_file = None;
+        model
+            .output
+            .detection
+            .class_thresholds
+            .insert("car".to_string(), 0.25);
+        model
+            .output
+            .detection
+            .class_thresholds
+            .insert("7".to_string(), 0.8);
+        let config = InferenceConfig {
+            primary_gie: Some(model.clone()),
+            ..InferenceConfig::default()
+        };
+
+        let processor = InferenceProcessor::from_config(&config).unwrap();
+        assert_eq!(processor.threshold_for("primary-detector", 2), 0.25);
+        assert_eq!(processor.threshold_for("primary-detector", 7), 0.8);
+
+        model
+            .output
+            .detection
+            .class_thresholds
+            .insert("unicorn".to_string(), 0.1);
+        let config = InferenceConfig {
+            primary_gie: Some(model),
+            ..InferenceConfig::default()
+        };
+        assert!(InferenceProcessor::from_config(&config).is_err());
+    }
+
     #[test]
     fn test_detection_result() {
         let mut result = DetectionResult::new(1, 0, "test-model".to_string());
@@ -330,6 +712,16 @@ mod tests {
         let counts = result.count_by_class();
         assert_eq!(counts.get(&0), Some(&1));
         assert_eq!(counts.get(&1), Some(&1));
+
+        let processor = InferenceProcessor::default();
+        processor
+            .process_detection("primary-detector", vec![1.0], 1, 0)
+            .unwrap();
+        let stats = processor
+            .telemetry()
+            .model_stats("primary-detector")
+            .unwrap();
+        assert_eq!(stats.frames, 1);
     }
 
     #[test]
@@ -0,0 +1,338 @@
+//! Regions of interest and class allow-lists applied around the detector
+
+use super::{InferenceError, Result};
+use crate::metadata::ObjectMeta;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// Rectangle in source frame pixels that inference is limited to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionOfInterest {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RegionOfInterest {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole frame
+    pub fn full_frame(width: u32, height: u32) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// Intersect with a frame of the given size, `None` if nothing remains
+    pub fn clamp(&self, frame_width: u32, frame_height: u32) -> Option<Self> {
+        if self.x >= frame_width || self.y >= frame_height {
+            return None;
+        }
+        let width = self.width.min(frame_width - self.x);
+        let height = self.height.min(frame_height - self.y);
+        (width > 0 && height > 0).then(|| Self::new(self.x, self.y, width, height))
+    }
+
+    /// Whether this region covers the whole frame
+    pub fn is_full_frame(&self, frame_width: u32, frame_height: u32) -> bool {
+        self.x == 0 && self.y == 0 && self.width >= frame_width && self.height >= frame_height
+    }
+
+    /// Map a point from region coordinates back to frame coordinates
+    pub fn to_frame(&self, x: f32, y: f32) -> (f32, f32) {
+        (x + self.x as f32, y + self.y as f32)
+    }
+
+    /// Parse `x,y,w,h;x,y,w,h`
+    pub fn parse_list(s: &str) -> Result<Vec<Self>> {
+        s.split(';')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Inverse of [`parse_list`](Self::parse_list)
+    pub fn format_list(regions: &[Self]) -> String {
+        regions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+impl fmt::Display for RegionOfInterest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl FromStr for RegionOfInterest {
+    type Err = InferenceError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let values: Vec<u32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| InferenceError::ConfigError(format!("Invalid ROI '{}'", s)))?;
+
+        match values.as_slice() {
+            &[x, y, width, height] if width > 0 && height > 0 => Ok(Self::new(x, y, width, height)),
+            _ => Err(InferenceError::ConfigError(format!(
+                "ROI must be x,y,width,height with non-zero size, got '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Per-source restrictions on where inference runs and which classes are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InferenceFilter {
+    /// Regions cropped and run through the detector separately; empty
+    /// means the whole frame
+    #[serde(default)]
+    pub rois: Vec<RegionOfInterest>,
+
+    /// Class names or IDs to keep; empty keeps every class
+    #[serde(default)]
+    pub allowed_classes: Vec<String>,
+}
+
+impl InferenceFilter {
+    /// Whether the filter changes anything
+    pub fn is_empty(&self) -> bool {
+        self.rois.is_empty() && self.allowed_classes.is_empty()
+    }
+
+    /// Regions to run inference on for a frame of the given size
+    pub fn regions(&self, frame_width: u32, frame_height: u32) -> Vec<RegionOfInterest> {
+        if self.rois.is_empty() {
+            return vec![RegionOfInterest::full_frame(frame_width, frame_height)];
+        }
+        self.rois
+            .iter()
+            .filter_map(|roi| roi.clamp(frame_width, frame_height))
+            .collect()
+    }
+
+    /// Whether a detection of this class is kept
+    pub fn allows(&self, class_id: usize, class_name: &str) -> bool {
+        self.allowed_classes.is_empty()
+            || self.allowed_classes.iter().any(|allowed| {
+                allowed.eq_ignore_ascii_case(class_name)
+                    || allowed.parse::<usize>().is_ok_and(|id| id == class_id)
+            })
+    }
+
+    /// Drop objects whose class is not allowed or whose center lies outside
+    /// every ROI. Used when the detector itself can't crop, e.g. nvinfer.
+    pub fn retain_objects(&self, objects: &mut Vec<ObjectMeta>) {
+        objects.retain(|obj| {
+            let class_id = usize::try_from(obj.class_id).unwrap_or(usize::MAX);
+            if !self.allows(class_id, &obj.obj_label) {
+                return false;
+            }
+
+            let bbox = &obj.rect_params;
+            let (cx, cy) = (bbox.left + bbox.width / 2.0, bbox.top + bbox.height / 2.0);
+            self.rois.is_empty()
+                || self.rois.iter().any(|roi| {
+                    cx >= roi.x as f32
+                        && cy >= roi.y as f32
+                        && cx < (roi.x + roi.width) as f32
+                        && cy < (roi.y + roi.height) as f32
+                })
+        });
+    }
+
+    /// Configure a `cpudetector` element to crop and filter with this filter
+    pub fn apply_to_element(&self, element: &gst::Element) -> Result<()> {
+        for property in ["roi", "allowed-classes"] {
+            if element.find_property(property).is_none() {
+                return Err(InferenceError::ConfigError(format!(
+                    "Element {} has no {} property",
+                    element.name(),
+                    property
+                )));
+            }
+        }
+
+        element.set_property("roi", RegionOfInterest::format_list(&self.rois));
+        element.set_property("allowed-classes", self.allowed_classes.join(","));
+        Ok(())
+    }
+
+    /// Parse the comma-separated class list used by element properties
+    pub fn parse_classes(s: &str) -> Vec<String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|class| !class.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Each source's filter by source ID, shared between the controller that
+/// adds sources and the probes that read their detections
+#[derive(Debug, Clone, Default)]
+pub struct InferenceFilters(Arc<RwLock<HashMap<u32, InferenceFilter>>>);
+
+impl InferenceFilters {
+    /// Filter the detections of `source_id` with `filter`; an empty one
+    /// clears it
+    pub fn set(&self, source_id: u32, filter: InferenceFilter) {
+        let mut filters = self.0.write().unwrap();
+        if filter.is_empty() {
+            filters.remove(&source_id);
+        } else {
+            filters.insert(source_id, filter);
+        }
+    }
+
+    pub fn get(&self, source_id: u32) -> Option<InferenceFilter> {
+        self.0.read().unwrap().get(&source_id).cloned()
+    }
+
+    pub fn remove(&self, source_id: u32) -> Option<InferenceFilter> {
+        self.0.write().unwrap().remove(&source_id)
+    }
+
+    /// [`InferenceFilter::retain_objects`] with the filter of `source_id`,
+    /// keeping everything for sources without one
+    pub fn retain_objects(&self, source_id: u32, objects: &mut Vec<ObjectMeta>) {
+        if let Some(filter) = self.0.read().unwrap().get(&source_id) {
+            filter.retain_objects(objects);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roi_list_round_trip() {
+        let rois = RegionOfInterest::parse_list("0,0,320,240; 600,400,100,100").unwrap();
+        assert_eq!(rois.len(), 2);
+        assert_eq!(
+            RegionOfInterest::format_list(&rois),
+            "0,0,320,240;600,400,100,100"
+        );
+    }
+
+    #[test]
+    fn test_invalid_roi_rejected() {
+        assert!("1,2,3".parse::<RegionOfInterest>().is_err());
+        assert!("0,0,0,10".parse::<RegionOfInterest>().is_err());
+    }
+
+    #[test]
+    fn test_roi_clamped_to_frame() {
+        assert_eq!(
+            RegionOfInterest::new(600, 400, 100, 100).clamp(640, 480),
+            Some(RegionOfInterest::new(600, 400, 40, 80))
+        );
+        assert_eq!(RegionOfInterest::new(700, 0, 10, 10).clamp(640, 480), None);
+    }
+
+    #[test]
+    fn test_no_rois_means_full_frame() {
+        let filter = InferenceFilter::default();
+        assert_eq!(
+            filter.regions(640, 480),
+            vec![RegionOfInterest::full_frame(640, 480)]
+        );
+        assert!(!RegionOfInterest::new(100, 50, 200, 200).is_full_frame(640, 480));
+    }
+
+    #[test]
+    fn test_remap_to_frame() {
+        let roi = RegionOfInterest::new(100, 50, 200, 200);
+        assert_eq!(roi.to_frame(10.0, 20.0), (110.0, 70.0));
+    }
+
+    #[test]
+    fn test_class_allow_list() {
+        let filter = InferenceFilter {
+            rois: vec![],
+            allowed_classes: InferenceFilter::parse_classes("Person, 2"),
+        };
+
+        assert!(filter.allows(0, "person"));
+        assert!(filter.allows(2, "car"));
+        assert!(!filter.allows(7, "truck"));
+        assert!(InferenceFilter::default().allows(7, "truck"));
+    }
+
+    #[test]
+    fn test_retain_objects() {
+        use crate::metadata::BoundingBox;
+
+        let object = |id, class_id, label: &str, x| {
+            let mut obj = ObjectMeta::new(id);
+            obj.class_id = class_id;
+            obj.obj_label = label.to_string();
+            obj.rect_params = BoundingBox::new(x, 10.0, 20.0, 20.0);
+            obj
+        };
+        let mut objects = vec![
+            object(1, 0, "person", 10.0),
+            object(2, 0, "person", 500.0),
+            object(3, 7, "truck", 10.0),
+        ];
+
+        let filter = InferenceFilter {
+            rois: vec![RegionOfInterest::new(0, 0, 100, 100)],
+            allowed_classes: vec!["person".to_string()],
+        };
+        filter.retain_objects(&mut objects);
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].object_id, 1);
+    }
+
+    #[test]
+    fn test_filters_apply_per_source() {
+        use crate::metadata::BoundingBox;
+
+        let object = |id, label: &str| {
+            let mut obj = ObjectMeta::new(id);
+            obj.obj_label = label.to_string();
+            obj.rect_params = BoundingBox::new(10.0, 10.0, 20.0, 20.0);
+            obj
+        };
+        let filters = InferenceFilters::default();
+        filters.set(
+            1,
+            InferenceFilter {
+                rois: vec![],
+                allowed_classes: vec!["person".to_string()],
+            },
+        );
+        filters.set(2, InferenceFilter::default());
+
+        let mut objects = vec![object(1, "person"), object(2, "car")];
+        filters.retain_objects(0, &mut objects);
+        assert_eq!(objects.len(), 2);
+        filters.retain_objects(1, &mut objects);
+        assert_eq!(objects.len(), 1);
+        assert!(filters.get(2).is_none());
+
+        assert!(filters.remove(1).is_some());
+        assert!(filters.get(1).is_none());
+    }
+}
@@ -0,0 +1,459 @@
+//! Per-model inference latency and skipped frames
+//!
+//! Averages hide the frames that make a pipeline stutter, so each model
+//! keeps a window of recent latencies and reports percentiles. Latency is
+//! measured from a buffer entering the inference element to it leaving,
+//! queue wait from entering the queue in front of it. Frames that queue
+//! leaks under backpressure count as skipped for the model behind it, as
+//! do frames a model passes through without inference because it only
+//! runs on every n-th.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+use source_videos::exposition::Exposition;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latencies kept per model for percentiles
+const DEFAULT_WINDOW: usize = 1000;
+
+/// Buffers in flight through one element before the oldest are forgotten
+const MAX_IN_FLIGHT: usize = 64;
+
+/// Percentiles of a latency window, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl LatencySummary {
+    fn from_samples(samples: &VecDeque<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted: Vec<f64> = samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        // Nearest rank
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Self {
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Statistics of one model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelStats {
+    pub model: String,
+    /// Frames inference ran on
+    pub frames: u64,
+    /// Frames dropped before reaching the model
+    pub skipped: u64,
+    pub latency_ms: LatencySummary,
+    pub queue_wait_ms: LatencySummary,
+}
+
+impl ModelStats {
+    /// Share of frames that never reached the model
+    pub fn skip_rate(&self) -> f64 {
+        let total = self.frames + self.skipped;
+        if total == 0 {
+            0.0
+        } else {
+            self.skipped as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ModelWindow {
+    frames: u64,
+    skipped: u64,
+    latency: VecDeque<f64>,
+    queue_wait: VecDeque<f64>,
+}
+
+impl ModelWindow {
+    fn new(window: usize) -> Self {
+        Self {
+            frames: 0,
+            skipped: 0,
+            latency: VecDeque::with_capacity(window),
+            queue_wait: VecDeque::with_capacity(window),
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<f64>, window: usize, value: Duration) {
+    if samples.len() >= window {
+        samples.pop_front();
+    }
+    samples.push_back(value.as_secs_f64() * 1000.0);
+}
+
+/// Telemetry of every model in a pipeline, shared by the probes feeding it
+/// and whoever reports it
+#[derive(Debug)]
+pub struct InferenceTelemetry {
+    window: usize,
+    models: Mutex<BTreeMap<String, ModelWindow>>,
+}
+
+impl InferenceTelemetry {
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Keep the last `window` latencies per model
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            models: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn update(&self, model: &str, f: impl FnOnce(&mut ModelWindow)) {
+        let mut models = self.models.lock().unwrap();
+        let entry = models
+            .entry(model.to_string())
+            .or_insert_with(|| ModelWindow::new(self.window));
+        f(entry);
+    }
+
+    /// Count a frame inference ran on and how long it took
+    pub fn record(&self, model: &str, latency: Duration) {
+        let window = self.window;
+        self.update(model, |entry| {
+            entry.frames += 1;
+            push_sample(&mut entry.latency, window, latency);
+        });
+    }
+
+    /// How long a frame waited in the queue in front of the model
+    pub fn record_queue_wait(&self, model: &str, wait: Duration) {
+        let window = self.window;
+        self.update(model, |entry| {
+            push_sample(&mut entry.queue_wait, window, wait)
+        });
+    }
+
+    /// Count a frame dropped before reaching the model
+    pub fn record_skipped(&self, model: &str) {
+        self.update(model, |entry| entry.skipped += 1);
+    }
+
+    pub fn model_stats(&self, model: &str) -> Option<ModelStats> {
+        let models = self.models.lock().unwrap();
+        models.get(model).map(|entry| stats(model, entry))
+    }
+
+    /// Statistics of every model seen, by name
+    pub fn snapshot(&self) -> Vec<ModelStats> {
+        let models = self.models.lock().unwrap();
+        models
+            .iter()
+            .map(|(model, entry)| stats(model, entry))
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.models.lock().unwrap().clear();
+    }
+
+    /// Statistics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = Exposition::new();
+
+        let counters: [(&str, &str, fn(&ModelStats) -> u64); 2] = [
+            (
+                "ds_inference_frames_total",
+                "Frames inference ran on",
+                |s| s.frames,
+            ),
+            (
+                "ds_inference_skipped_frames_total",
+                "Frames dropped before reaching the model or passed through without inference",
+                |s| s.skipped,
+            ),
+        ];
+        for (name, help, value) in counters {
+            out.family(name, "counter", help);
+            for stats in &snapshot {
+                out.sample(
+                    name,
+                    &[("model", stats.model.as_str())],
+                    value(stats) as f64,
+                );
+            }
+        }
+
+        let summaries: [(&str, &str, fn(&ModelStats) -> &LatencySummary); 2] = [
+            (
+                "ds_inference_latency_ms",
+                "Time a frame spends in the inference element",
+                |s| &s.latency_ms,
+            ),
+            (
+                "ds_inference_queue_wait_ms",
+                "Time a frame waits in the queue before inference",
+                |s| &s.queue_wait_ms,
+            ),
+        ];
+        for (name, help, summary) in summaries {
+            out.family(name, "summary", help);
+            for stats in &snapshot {
+                let summary = summary(stats);
+                for (quantile, value) in [
+                    ("0.5", summary.p50),
+                    ("0.95", summary.p95),
+                    ("0.99", summary.p99),
+                ] {
+                    out.sample_with(
+                        name,
+                        &[("model", stats.model.as_str())],
+                        ("quantile", quantile),
+                        value,
+                    );
+                }
+            }
+        }
+        out.finish()
+    }
+
+    /// Time buffers through `element`, the inference element of `model`.
+    /// Buffers an `nvinfer` interval or a `cpudetector`'s
+    /// `process-every-n-frames` passes through without inference count as
+    /// skipped.
+    pub fn attach(self: &Arc<Self>, model: &str, element: &gst::Element) -> bool {
+        let (Some(sink), Some(src)) = (element.static_pad("sink"), element.static_pad("src"))
+        else {
+            return false;
+        };
+        let every = inference_period(element);
+        let passed = AtomicU64::new(0);
+        let telemetry = self.clone();
+        let model = model.to_string();
+        time_between(&sink, &src, move |latency| {
+            // Both run inference on the first buffer of each period
+            if passed.fetch_add(1, Ordering::Relaxed) % every == 0 {
+                telemetry.record(&model, latency)
+            } else {
+                telemetry.record_skipped(&model)
+            }
+        });
+        true
+    }
+
+    /// Time buffers through `queue` in front of the inference element of
+    /// `model`, counting what it leaks as skipped frames
+    pub fn attach_queue(self: &Arc<Self>, model: &str, queue: &gst::Element) -> bool {
+        let (Some(sink), Some(src)) = (queue.static_pad("sink"), queue.static_pad("src")) else {
+            return false;
+        };
+        let telemetry = self.clone();
+        let name = model.to_string();
+        time_between(&sink, &src, move |wait| {
+            telemetry.record_queue_wait(&name, wait)
+        });
+
+        if queue.find_property("leaky").is_some() {
+            let telemetry = self.clone();
+            let name = model.to_string();
+            queue.connect("overrun", false, move |_| {
+                telemetry.record_skipped(&name);
+                None
+            });
+        }
+        true
+    }
+}
+
+impl Default for InferenceTelemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Buffers per buffer `element` runs inference on
+fn inference_period(element: &gst::Element) -> u64 {
+    if element.find_property("interval").is_some() {
+        // nvinfer skips `interval` batches after each one it infers
+        element.property::<u32>("interval") as u64 + 1
+    } else if element.find_property("process-every-n-frames").is_some() {
+        (element.property::<u32>("process-every-n-frames") as u64).max(1)
+    } else {
+        1
+    }
+}
+
+fn stats(model: &str, entry: &ModelWindow) -> ModelStats {
+    ModelStats {
+        model: model.to_string(),
+        frames: entry.frames,
+        skipped: entry.skipped,
+        latency_ms: LatencySummary::from_samples(&entry.latency),
+        queue_wait_ms: LatencySummary::from_samples(&entry.queue_wait),
+    }
+}
+
+/// Report how long each buffer took from `input` to `output`, matching
+/// them by timestamp. Buffers that never come out are forgotten once
+/// enough newer ones are in flight.
+fn time_between(
+    input: &gst::Pad,
+    output: &gst::Pad,
+    report: impl Fn(Duration) + Send + Sync + 'static,
+) {
+    let in_flight = Arc::new(Mutex::new(VecDeque::<(gst::ClockTime, Instant)>::new()));
+
+    let arrivals = in_flight.clone();
+    input.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if let Some(pts) = info.buffer().and_then(|buffer| buffer.pts()) {
+            let mut arrivals = arrivals.lock().unwrap();
+            if arrivals.len() >= MAX_IN_FLIGHT {
+                arrivals.pop_front();
+            }
+            arrivals.push_back((pts, Instant::now()));
+        }
+        gst::PadProbeReturn::Ok
+    });
+
+    output.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if let Some(pts) = info.buffer().and_then(|buffer| buffer.pts()) {
+            let mut arrivals = in_flight.lock().unwrap();
+            if let Some(index) = arrivals.iter().position(|&(arrived, _)| arrived == pts) {
+                // Anything queued before it was dropped inside the element
+                let (_, arrived) = arrivals.drain(..=index).last().unwrap();
+                drop(arrivals);
+                report(arrived.elapsed());
+            }
+        }
+        gst::PadProbeReturn::Ok
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_over_window() {
+        let telemetry = InferenceTelemetry::with_window(100);
+        for ms in 1..=200 {
+            telemetry.record("primary", Duration::from_millis(ms));
+        }
+
+        // Only the last 100 latencies, 101..=200 ms, are kept
+        let primary = telemetry.model_stats("primary").unwrap();
+        assert_eq!(primary.frames, 200);
+        assert_eq!(primary.latency_ms.p50, 150.0);
+        assert_eq!(primary.latency_ms.p95, 195.0);
+        assert_eq!(primary.latency_ms.p99, 199.0);
+        assert_eq!(primary.latency_ms.max, 200.0);
+    }
+
+    #[test]
+    fn test_queue_wait() {
+        let telemetry = InferenceTelemetry::new();
+        telemetry.record_queue_wait("primary", Duration::from_millis(4));
+        assert_eq!(
+            telemetry.model_stats("primary").unwrap().queue_wait_ms.p50,
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_skip_rate() {
+        let telemetry = InferenceTelemetry::new();
+        for _ in 0..3 {
+            telemetry.record("primary", Duration::from_millis(10));
+        }
+        telemetry.record_skipped("primary");
+
+        let primary = telemetry.model_stats("primary").unwrap();
+        assert_eq!(primary.skipped, 1);
+        assert!((primary.skip_rate() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_snapshot_per_model() {
+        let telemetry = InferenceTelemetry::new();
+        telemetry.record("secondary", Duration::from_millis(10));
+        telemetry.record("primary", Duration::from_millis(10));
+
+        let models: Vec<_> = telemetry.snapshot().into_iter().map(|s| s.model).collect();
+        assert_eq!(models, ["primary", "secondary"]);
+
+        telemetry.reset();
+        assert!(telemetry.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_prometheus_output() {
+        let telemetry = InferenceTelemetry::new();
+        telemetry.record("primary", Duration::from_millis(5));
+        telemetry.record("secondary", Duration::from_millis(10));
+
+        let text = telemetry.to_prometheus();
+        assert!(text.contains("ds_inference_frames_total{model=\"primary\"} 1"));
+        assert!(
+            text.contains("ds_inference_latency_ms{model=\"secondary\",quantile=\"0.99\"} 10\n")
+        );
+    }
+
+    #[test]
+    fn test_attach_times_identity() {
+        gst::init().unwrap();
+
+        let telemetry = Arc::new(InferenceTelemetry::new());
+        let identity = gst::ElementFactory::make("identity").build().unwrap();
+        assert!(telemetry.attach("identity", &identity));
+
+        let pipeline = gst::Pipeline::new();
+        let src = gst::ElementFactory::make("videotestsrc")
+            .property("num-buffers", 5)
+            .build()
+            .unwrap();
+        let sink = gst::ElementFactory::make("fakesink").build().unwrap();
+        pipeline.add_many([&src, &identity, &sink]).unwrap();
+        gst::Element::link_many([&src, &identity, &sink]).unwrap();
+
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let bus = pipeline.bus().unwrap();
+        bus.timed_pop_filtered(
+            gst::ClockTime::from_seconds(5),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+
+        assert_eq!(telemetry.model_stats("identity").unwrap().frames, 5);
+    }
+
+    #[test]
+    fn test_frames_between_inferences_are_skipped() {
+        gst::init().unwrap();
+
+        let detector = crate::backend::cpu_vision::cpudetector::CpuDetector::new(Some("every-3"))
+            .upcast::<gst::Element>();
+        detector.set_property("process-every-n-frames", 3u32);
+        assert_eq!(inference_period(&detector), 3);
+
+        let identity = gst::ElementFactory::make("identity").build().unwrap();
+        assert_eq!(inference_period(&identity), 1);
+    }
+}
@@ -1,23 +1,36 @@
 pub mod app;
 pub mod backend;
+pub mod bench;
 pub mod config;
+pub mod discovery;
+pub mod doctor;
 pub mod elements;
 pub mod error;
+pub mod golden;
 pub mod inference;
 pub mod messages;
 pub mod metadata;
 pub mod multistream;
+pub mod operation;
+pub mod output;
 pub mod pipeline;
 pub mod platform;
 pub mod rendering;
+pub mod rules;
+pub mod shutdown;
 pub mod source;
+pub mod status;
 pub mod tracking;
 
 #[cfg(target_os = "windows")]
 pub mod dll_validator;
 
-pub use backend::{Backend, BackendCapabilities, BackendManager, BackendType};
+pub use backend::{
+    Backend, BackendCapabilities, BackendManager, BackendType, CapabilityReport, ElementOverrides,
+    GpuConfig, GpuPlacement, MockScript, PlacementPolicy,
+};
 pub use config::ApplicationConfig;
+pub use discovery::{DiscoveredSource, DiscoveryConfig, SourceDiscovery, Subnet};
 pub use elements::factory::ElementFactory;
 pub use elements::{DeepStreamElement, DeepStreamElementType, ElementBuilder};
 pub use error::{DeepStreamError, ErrorClassification, ErrorClassifier, Result, is_retryable};
@@ -35,18 +48,25 @@ pub use multistream::{
     MultiStreamEvent, MultiStreamManager, MultiStreamStats, PipelinePool, ResourceLimits,
     ResourceManager, StreamCoordinator, StreamMetrics, StreamPriority,
 };
+pub use operation::{Operation, OperationRegistry, OperationStatus};
 pub use pipeline::{
-    BusWatcher, MessageHandler, Pipeline, PipelineBuilder, PipelineState, StateManager,
+    BusWatcher, ElementHooks, HookContext, HookPoint, MessageHandler, MessageRouter, Pipeline,
+    PipelineBuilder, PipelineSnapshot, PipelineState, ReplayConfig, ReplayMode, StateManager,
 };
 pub use platform::{Platform, PlatformInfo};
 pub use rendering::{
     BoundingBoxRenderer, MetadataBridge, PerformanceMetrics, RendererFactory, RenderingConfig,
 };
+pub use rules::{RuleAlert, RuleEngine, RulesConfig};
+pub use shutdown::{ShutdownCoordinator, ShutdownReport, ShutdownToken};
 pub use source::{
+    AlignMode,
     CircuitBreaker,
     CircuitBreakerConfig,
     CircuitBreakerManager,
     CircuitState,
+    CredentialStore,
+    EosPolicy,
     ErrorBoundary,
     FaultTolerantSourceController,
     HealthConfig,
@@ -55,6 +75,7 @@ pub use source::{
     IsolatedSource,
     IsolationManager,
     IsolationPolicy,
+    PanicGuard,
     // Recovery and fault tolerance exports
     RecoveryConfig,
     RecoveryManager,
@@ -70,10 +91,18 @@ pub use source::{
     SourceManager,
     SourceRemoval,
     SourceState,
+    SourceStats,
     SourceSynchronizer,
+    SourceUri,
+    TimestampAlignment,
+    VideoCorrection,
     VideoSource,
 };
-pub use tracking::{ObjectTracker, TrackStatus, TrackerState, TrackingStats, Trajectory};
+pub use status::{StatusRequest, StatusResponse, StatusServer};
+pub use tracking::{
+    ObjectTracker, TrackStatus, TrackerAlgorithm, TrackerAlgorithmConfig, TrackerState,
+    TrackingStats, Trajectory,
+};
 
 /// Get current timestamp in seconds since Unix epoch
 /// Used for consistent timestamp formatting in log messages
@@ -1,7 +1,14 @@
 #![allow(unused)]
 use clap::Parser;
-use ds_rs::{app::Application, init};
+use ds_rs::app::config::{DemoConfig, DemoMode};
+use ds_rs::app::sources::{InitialSource, load_sources_file};
+use ds_rs::app::stress::{StressConfig, StressMode};
+use ds_rs::backend::EngineCache;
+use ds_rs::{ApplicationConfig, BackendManager, app::Application, init};
 use gstreamer::glib;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -9,12 +16,21 @@ use gstreamer::glib;
     about = "DeepStream Rust - Runtime Source Addition/Deletion Demo",
     long_about = "Demonstrates dynamic video source management in AI-powered video analytics pipelines.\n\
                   This application showcases the runtime source control APIs by automatically adding\n\
-                  sources every 10 seconds up to MAX_NUM_SOURCES, then removing them periodically."
+                  sources every 10 seconds up to MAX_NUM_SOURCES, then removing them periodically.\n\
+                  Use --static to only play the given sources."
 )]
 struct Args {
-    /// URI of the video source (file:///path/to/video.mp4 or rtsp://...)
-    #[arg(help = "Video source URI")]
-    uri: String,
+    /// URIs of the video sources (file:///path/to/video.mp4 or rtsp://...)
+    #[arg(
+        help = "Video source URIs",
+        required_unless_present_any = ["print_capabilities", "sources_file"]
+    )]
+    uris: Vec<String>,
+
+    /// TOML file of [[source]] tables with a URI and per-source priority,
+    /// EOS policy and recovery settings, added alongside the URIs given
+    #[arg(long, value_name = "FILE")]
+    sources_file: Option<PathBuf>,
 
     /// Enable debug logging
     #[arg(short, long, help = "Enable debug output")]
@@ -23,6 +39,84 @@ struct Args {
     /// Force a specific backend (mock, standard, deepstream)
     #[arg(short, long, help = "Force backend selection")]
     backend: Option<String>,
+
+    /// Print what the backend can do on this machine and exit
+    #[arg(long)]
+    print_capabilities: bool,
+
+    /// Print the capabilities as JSON
+    #[arg(long, requires = "print_capabilities")]
+    json: bool,
+
+    /// Serve status endpoints such as /api/v1/capabilities on this address
+    #[arg(long, value_name = "ADDR")]
+    status_addr: Option<String>,
+
+    /// Ignore keyboard commands on stdin (read by default on a terminal)
+    #[arg(long)]
+    no_keyboard: bool,
+
+    /// Application config file, applied whole: the DeepStream sections
+    /// shape the pipeline, [demo] sets the source timers (overridden by the
+    /// flags below), [rules] are evaluated on every frame and so on
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Keep TensorRT engines in a cache (DS_RS_ENGINE_DIR, or engines/
+    /// under the ds-rs cache directory) instead of rebuilding them
+    #[arg(long)]
+    engine_cache: bool,
+
+    /// Only play the given sources, without adding or removing any
+    #[arg(long = "static", conflicts_with = "stress")]
+    static_mode: bool,
+
+    /// Seconds between adding sources
+    #[arg(long, value_name = "SECS")]
+    add_interval: Option<u64>,
+
+    /// Seconds between removing sources once the maximum is reached
+    #[arg(long, value_name = "SECS")]
+    delete_interval: Option<u64>,
+
+    /// Sources the demo adds up to
+    #[arg(long, value_name = "N")]
+    max_sources: Option<usize>,
+
+    /// Add and remove sources continuously instead of every 10 seconds,
+    /// checking the pipeline after each operation
+    #[arg(long, value_name = "MODE")]
+    stress: Option<StressMode>,
+
+    /// Time between stress operations, e.g. 500ms or 2s
+    #[arg(long, requires = "stress", default_value = "500ms", value_parser = parse_interval)]
+    interval: Duration,
+
+    /// Sources active at most during a stress run
+    #[arg(long, requires = "stress", default_value_t = 30)]
+    max: usize,
+}
+
+/// Parse an interval like `500ms`, `2s` or `1m`
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let (number, unit) = s
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((s, "ms"), |at| s.split_at(at));
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid interval: {}", s))?;
+    if number == 0 {
+        return Err(format!("Interval must be greater than zero: {}", s));
+    }
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number.saturating_mul(60))),
+        _ => Err(format!(
+            "Invalid interval unit in {}, expected ms, s or m",
+            s
+        )),
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -45,13 +139,69 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize GStreamer and the library
     init()?;
 
+    if args.print_capabilities {
+        let report = BackendManager::new()?.report();
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            println!("{}", report);
+        }
+        return Ok(());
+    }
+
     println!("DeepStream Rust - Runtime Source Addition/Deletion Demo");
     println!("========================================================\n");
 
     // Create and initialize the application
-    let mut app = Application::new(args.uri)?;
+    let mut sources: Vec<_> = args.uris.iter().map(InitialSource::new).collect();
+    if let Some(path) = &args.sources_file {
+        sources.extend(load_sources_file(path)?);
+    }
+    let config = match &args.config {
+        Some(path) => Some(ApplicationConfig::from_file(path)?),
+        None => None,
+    };
+    let mut demo = config
+        .as_ref()
+        .and_then(|config| config.demo.clone())
+        .unwrap_or_default();
+    if args.static_mode {
+        demo.mode = DemoMode::Static;
+    }
+    if let Some(secs) = args.add_interval {
+        demo.add_interval_secs = secs;
+    }
+    if let Some(secs) = args.delete_interval {
+        demo.delete_interval_secs = secs;
+    }
+    if let Some(max) = args.max_sources {
+        demo.max_sources = max;
+    }
+
+    let mut app = Application::with_sources(sources)?;
+    if let Some(config) = config {
+        app.apply_config(config)?;
+    }
+    app.set_demo(demo)?;
+    if args.engine_cache {
+        let cache = EngineCache::from_env().ok_or("No cache directory for TensorRT engines")?;
+        app.set_engine_cache(cache);
+    }
+    app.set_keyboard_controls(!args.no_keyboard && std::io::stdin().is_terminal());
+    if let Some(mode) = args.stress {
+        app.set_stress(StressConfig {
+            mode,
+            interval: args.interval,
+            max_sources: args.max,
+        });
+    }
     app.init()?;
 
+    let _status = match &args.status_addr {
+        Some(address) => Some(app.serve_status(address)?),
+        None => None,
+    };
+
     // Run the application with GLib's native signal handling
     app.run_with_glib_signals()?;
 
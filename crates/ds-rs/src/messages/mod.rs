@@ -1,6 +1,13 @@
 #![allow(unused)]
 //! DeepStream-specific message handling
-
+//!
+//! nvstreammux posts a `stream-eos` element message with a `stream-id`
+//! field when one of its inputs ends. The per-source EOS probes post the
+//! same message on every backend, so [`DSMessageHandler`] tracks the end
+//! of each stream the same way whatever the backend. Errors raised inside
+//! a `source-bin-NN` are attributed to that stream.
+
+use crate::source::isolation::catch_panic;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::collections::HashMap;
@@ -22,12 +29,31 @@ pub enum MessageError {
 
 pub type Result<T> = std::result::Result<T, MessageError>;
 
+/// Structure name of per-stream EOS messages
+pub const STREAM_EOS: &str = "stream-eos";
+
+const SOURCE_BIN_PREFIX: &str = "source-bin-";
+
+/// The message nvstreammux posts when stream `stream_id` ends
+pub fn stream_eos_message(src: &gst::Element, stream_id: u32) -> gst::Message {
+    gst::message::Element::builder(
+        gst::Structure::builder(STREAM_EOS)
+            .field("stream-id", stream_id)
+            .build(),
+    )
+    .src(src)
+    .build()
+}
+
 /// DeepStream message types
 #[derive(Debug, Clone, PartialEq)]
 pub enum DSMessageType {
     /// Stream-specific EOS
     StreamEos(u32),
 
+    /// Error raised inside one stream's source bin
+    StreamError(u32, String),
+
     /// Stream added
     StreamAdded(u32),
 
@@ -89,20 +115,54 @@ impl StreamEosTracker {
         }
     }
 
-    /// Mark stream as EOS
+    /// Mark stream as EOS. Callbacks run once per EOS, however many
+    /// elements report it.
     pub fn mark_eos(&self, stream_id: u32) {
-        if let Ok(mut status) = self.eos_status.lock() {
-            status.insert(stream_id, true);
+        let already = match self.eos_status.lock() {
+            Ok(mut status) => status.insert(stream_id, true).unwrap_or(false),
+            Err(_) => false,
+        };
+        if already {
+            return;
         }
 
         // Call callbacks
         if let Ok(callbacks) = self.eos_callbacks.lock() {
             for callback in callbacks.iter() {
-                callback(stream_id);
+                if let Err(panic) = catch_panic(|| callback(stream_id)) {
+                    log::error!("EOS callback for stream {} panicked: {}", stream_id, panic);
+                }
             }
         }
     }
 
+    /// Mark every registered stream still running as EOS and return them
+    pub fn mark_all_eos(&self) -> Vec<u32> {
+        let running: Vec<u32> = self
+            .eos_status
+            .lock()
+            .map(|status| {
+                status
+                    .iter()
+                    .filter(|&(_, &eos)| !eos)
+                    .map(|(&id, _)| id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for &stream_id in &running {
+            self.mark_eos(stream_id);
+        }
+        running
+    }
+
+    /// Whether every registered stream has ended
+    pub fn all_eos(&self) -> bool {
+        self.eos_status
+            .lock()
+            .map(|status| !status.is_empty() && status.values().all(|&eos| eos))
+            .unwrap_or(false)
+    }
+
     /// Check if stream has received EOS
     pub fn is_eos(&self, stream_id: u32) -> bool {
         self.eos_status
@@ -178,43 +238,70 @@ impl DSMessageHandler {
         }
     }
 
-    /// Check if message is stream EOS
+    /// Check if message is stream EOS, like `gst_nvmessage_is_stream_eos`
     pub fn is_stream_eos(msg: &gst::Message) -> bool {
-        // In real DeepStream, this would call gst_nvmessage_is_stream_eos
-        // For now, check if it's an element message with specific structure
-        matches!(msg.view(), gst::MessageView::Element(_))
+        match msg.view() {
+            gst::MessageView::Element(element) => element
+                .structure()
+                .is_some_and(|s| s.has_name(STREAM_EOS) && s.has_field("stream-id")),
+            _ => false,
+        }
     }
 
-    /// Parse stream EOS from message
+    /// Parse stream EOS from message, like `gst_nvmessage_parse_stream_eos`
     pub fn parse_stream_eos(msg: &gst::Message) -> Result<u32> {
-        // In real DeepStream, this would call gst_nvmessage_parse_stream_eos
-        // For now, return mock stream ID
-        if Self::is_stream_eos(msg) {
-            Ok(0) // Mock stream ID
-        } else {
-            Err(MessageError::InvalidFormat)
+        if !Self::is_stream_eos(msg) {
+            return Err(MessageError::InvalidFormat);
         }
+        msg.structure()
+            .ok_or(MessageError::InvalidFormat)?
+            .get::<u32>("stream-id")
+            .map_err(|e| MessageError::ParseFailed(e.to_string()))
+    }
+
+    /// Stream whose source bin `msg` came from, if any
+    pub fn stream_id(msg: &gst::Message) -> Option<u32> {
+        msg.src().and_then(Self::stream_id_of_object)
+    }
+
+    /// Stream of the source bin `object` is, or is inside of
+    pub fn stream_id_of_object(object: &gst::Object) -> Option<u32> {
+        let mut object = Some(object.clone());
+        while let Some(current) = object {
+            if let Some(id) = current
+                .name()
+                .strip_prefix(SOURCE_BIN_PREFIX)
+                .and_then(|id| id.parse().ok())
+            {
+                return Some(id);
+            }
+            object = current.parent();
+        }
+        None
     }
 
     /// Handle GStreamer message
     pub fn handle_message(&self, msg: &gst::Message) -> Result<()> {
         match msg.view() {
-            gst::MessageView::Element(_element_msg) => {
-                // Check for stream EOS
-                if Self::is_stream_eos(msg) {
-                    if let Ok(stream_id) = Self::parse_stream_eos(msg) {
-                        self.eos_tracker.mark_eos(stream_id);
-                        self.emit_message(DSMessageType::StreamEos(stream_id));
-                    }
+            gst::MessageView::Element(_) if Self::is_stream_eos(msg) => {
+                let stream_id = Self::parse_stream_eos(msg)?;
+                if !self.eos_tracker.is_eos(stream_id) {
+                    self.eos_tracker.mark_eos(stream_id);
+                    self.emit_message(DSMessageType::StreamEos(stream_id));
+                }
+            }
+            gst::MessageView::Error(err) => {
+                if let Some(stream_id) = Self::stream_id(msg) {
+                    self.emit_message(DSMessageType::StreamError(
+                        stream_id,
+                        err.error().to_string(),
+                    ));
                 }
             }
             gst::MessageView::Eos(_) => {
-                // Global EOS - mark all streams as EOS
-                for stream_id in 0..10 {
-                    if self.eos_tracker.is_eos(stream_id) {
-                        continue;
-                    }
-                    self.eos_tracker.mark_eos(stream_id);
+                // Global EOS ends whatever streams did not report their own
+                for stream_id in self.eos_tracker.mark_all_eos() {
+                    self.emit_message(DSMessageType::StreamEos(stream_id));
                 }
             }
             _ => {}
@@ -240,6 +327,7 @@ impl DSMessageHandler {
     fn emit_message(&self, msg: DSMessageType) {
         let msg_type = match &msg {
             DSMessageType::StreamEos(_) => "stream_eos",
+            DSMessageType::StreamError(..) => "stream_error",
             DSMessageType::StreamAdded(_) => "stream_added",
             DSMessageType::StreamRemoved(_) => "stream_removed",
             DSMessageType::InferenceDone(_) => "inference_done",
@@ -249,7 +337,9 @@ impl DSMessageHandler {
         if let Ok(callbacks) = self.callbacks.lock() {
             if let Some(cbs) = callbacks.get(msg_type) {
                 for callback in cbs {
-                    callback(msg.clone());
+                    if let Err(panic) = catch_panic(|| callback(msg.clone())) {
+                        log::error!("{} callback panicked: {}", msg_type, panic);
+                    }
                 }
             }
         }
@@ -267,6 +357,14 @@ impl Default for DSMessageHandler {
     }
 }
 
+/// Lets the handler sit on a [`MessageRouter`](crate::pipeline::MessageRouter)
+impl crate::pipeline::MessageHandler for DSMessageHandler {
+    fn handle_message(&self, _bus: &gst::Bus, msg: &gst::Message) -> gst::BusSyncReply {
+        let _ = DSMessageHandler::handle_message(self, msg);
+        gst::BusSyncReply::Pass
+    }
+}
+
 use gst::glib::ControlFlow;
 
 /// Helper trait for bus message handling
@@ -365,4 +463,45 @@ mod tests {
 
         assert!(*received.lock().unwrap());
     }
+
+    #[test]
+    fn test_stream_eos_messages() {
+        let _ = gst::init();
+        let handler = DSMessageHandler::new();
+        let ended = Arc::new(Mutex::new(Vec::new()));
+        let ended_clone = ended.clone();
+        handler.register_callback("stream_eos", move |msg| {
+            if let DSMessageType::StreamEos(id) = msg {
+                ended_clone.lock().unwrap().push(id);
+            }
+        });
+        for id in [3, 12, 40] {
+            handler.eos_tracker().register_stream(id);
+        }
+
+        let pipeline = gst::Pipeline::new();
+        let bin = gst::Bin::with_name("source-bin-12");
+        let decoder = gst::ElementFactory::make("identity").build().unwrap();
+        bin.add(&decoder).unwrap();
+        pipeline.add(&bin).unwrap();
+
+        let msg = stream_eos_message(bin.upcast_ref(), 12);
+        assert_eq!(DSMessageHandler::parse_stream_eos(&msg).unwrap(), 12);
+        handler.handle_message(&msg).unwrap();
+        // The muxer reporting the same EOS does not count twice
+        handler.handle_message(&msg).unwrap();
+        assert_eq!(*ended.lock().unwrap(), [12]);
+
+        let error = gst::message::Error::builder(gst::CoreError::Failed, "boom")
+            .src(&decoder)
+            .build();
+        assert_eq!(DSMessageHandler::stream_id(&error), Some(12));
+        assert!(!DSMessageHandler::is_stream_eos(&error));
+
+        handler.handle_message(&gst::message::Eos::new()).unwrap();
+        let mut ended = ended.lock().unwrap().clone();
+        ended.sort();
+        assert_eq!(ended, [3, 12, 40]);
+        assert!(handler.eos_tracker().all_eos());
+    }
 }
@@ -0,0 +1,111 @@
+//! Detections from the cpuinfer `cpudetector` element
+//!
+//! `cpudetector` attaches its detections to each buffer it processes and,
+//! with `emit-messages` set, posts them on the bus as well. These helpers
+//! turn either form into [`ObjectMeta`], as the DeepStream metadata reads.
+
+use super::object::{BoundingBox, ObjectMeta};
+use gstcpuinfer::detector::Detection;
+use gstcpuinfer::meta::{DetectionMeta, FrameDetections};
+use gstreamer as gst;
+
+pub use gstcpuinfer::meta::MESSAGE_NAME as CPU_DETECTIONS_MESSAGE;
+
+/// One frame's objects from a `cpudetector`
+#[derive(Debug, Clone)]
+pub struct CpuFrameDetections {
+    pub frame_number: u64,
+    /// `unique-id` of the detector
+    pub unique_id: u32,
+    pub pts: Option<gst::ClockTime>,
+    pub objects: Vec<ObjectMeta>,
+}
+
+impl From<&FrameDetections> for CpuFrameDetections {
+    fn from(frame: &FrameDetections) -> Self {
+        Self {
+            frame_number: frame.frame_number,
+            unique_id: frame.unique_id,
+            pts: frame.pts,
+            objects: frame
+                .detections
+                .iter()
+                .map(|d| detection_to_object(d, frame.unique_id))
+                .collect(),
+        }
+    }
+}
+
+/// Detections a `cpudetector` attached to `buffer`, if it processed it
+pub fn cpu_detections(buffer: &gst::BufferRef) -> Option<CpuFrameDetections> {
+    buffer
+        .meta::<DetectionMeta>()
+        .and_then(|meta| meta.frame())
+        .map(|frame| CpuFrameDetections::from(&frame))
+}
+
+/// Detections in `message`, if a `cpudetector` posted it
+pub fn cpu_detections_from_message(message: &gst::Message) -> Option<CpuFrameDetections> {
+    FrameDetections::from_message(message).map(|frame| CpuFrameDetections::from(&frame))
+}
+
+/// An untracked object for `detection`, credited to detector `unique_id`
+pub fn detection_to_object(detection: &Detection, unique_id: u32) -> ObjectMeta {
+    let mut object = ObjectMeta::new_untracked();
+    object.set_class(detection.class_id as i32, &detection.class_name);
+    object.set_detection_bbox(
+        BoundingBox::new(detection.x, detection.y, detection.width, detection.height),
+        detection.confidence,
+    );
+    object.unique_component_id = unique_id as i32;
+    object
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> FrameDetections {
+        FrameDetections {
+            frame_number: 12,
+            unique_id: 3,
+            pts: Some(gst::ClockTime::from_mseconds(400)),
+            detections: vec![Detection {
+                x: 5.0,
+                y: 6.0,
+                width: 50.0,
+                height: 60.0,
+                confidence: 0.8,
+                class_id: 0,
+                class_name: "person".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_cpu_detections_from_buffer() {
+        let _ = gst::init();
+
+        let mut buffer = gst::Buffer::new();
+        assert!(cpu_detections(&buffer).is_none());
+        DetectionMeta::add(buffer.get_mut().unwrap(), frame());
+
+        let detections = cpu_detections(&buffer).unwrap();
+        assert_eq!(detections.frame_number, 12);
+        let object = &detections.objects[0];
+        assert_eq!(object.obj_label, "person");
+        assert_eq!(object.unique_component_id, 3);
+        assert_eq!(object.bbox().width, 50.0);
+        assert!(!object.is_tracked());
+    }
+
+    #[test]
+    fn test_cpu_detections_from_message() {
+        let _ = gst::init();
+
+        let message = gst::message::Element::new(frame().to_structure());
+        let detections = cpu_detections_from_message(&message).unwrap();
+        assert_eq!(detections.pts, Some(gst::ClockTime::from_mseconds(400)));
+        assert_eq!(detections.objects[0].confidence, 0.8);
+    }
+}
@@ -3,6 +3,7 @@
 //! This module provides safe wrappers around DeepStream metadata structures,
 //! enabling access to AI inference results, object tracking data, and frame metadata.
 
+use crate::source::isolation::PanicGuard;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::collections::HashMap;
@@ -10,10 +11,12 @@ use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
 pub mod batch;
+pub mod cpu;
 pub mod frame;
 pub mod object;
 
 pub use batch::BatchMeta;
+pub use cpu::{CpuFrameDetections, cpu_detections, cpu_detections_from_message};
 pub use frame::FrameMeta;
 pub use object::{BoundingBox, ClassificationMeta, ObjectMeta};
 
@@ -133,11 +136,12 @@ impl MetadataProbe for gst::Pad {
         F: Fn(&gst::PadProbeInfo) -> Option<BatchMeta> + Send + Sync + 'static,
     {
         let extractor = MetadataExtractor::new();
+        let guard = PanicGuard::new("metadata probe");
 
         self.add_probe(gst::PadProbeType::BUFFER, move |_pad, info| {
             if let Some(buffer) = info.buffer() {
                 if let Ok(_batch_meta) = extractor.extract_batch_meta(buffer) {
-                    callback(info);
+                    guard.run(|| callback(info));
                 }
             }
             gst::PadProbeReturn::Ok
@@ -2,6 +2,9 @@
 
 use super::ResourceLimits;
 use super::StreamPriority;
+use super::events::{EventOverflow, EventQueueConfig};
+use super::migration::MigrationConfig;
+use super::pipeline_pool::RecyclePolicy;
 use gstcpuinfer::detector::DetectorConfig;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -30,6 +33,18 @@ pub struct MultiStreamConfig {
     /// Metrics collection settings
     pub metrics_config: MetricsConfig,
 
+    /// Capacity and overflow policy of the event queue
+    #[serde(default)]
+    pub events: EventQueueConfig,
+
+    /// Handover of streams moved between pipelines
+    #[serde(default)]
+    pub migration: MigrationConfig,
+
+    /// When pipelines are replaced to bound memory growth
+    #[serde(default)]
+    pub recycle: RecyclePolicy,
+
     /// Number of worker threads for async processing
     pub worker_threads: usize,
 
@@ -47,6 +62,9 @@ impl Default for MultiStreamConfig {
             quality_control: QualityControlConfig::default(),
             recovery_config: StreamRecoveryConfig::default(),
             metrics_config: MetricsConfig::default(),
+            events: EventQueueConfig::default(),
+            migration: MigrationConfig::default(),
+            recycle: RecyclePolicy::default(),
             worker_threads: 4,
             debug_mode: false,
         }
@@ -284,6 +302,16 @@ impl MultiStreamConfigBuilder {
         self
     }
 
+    pub fn event_queue(mut self, capacity: usize, overflow: EventOverflow) -> Self {
+        self.config.events = EventQueueConfig { capacity, overflow };
+        self
+    }
+
+    pub fn recycle_policy(mut self, policy: RecyclePolicy) -> Self {
+        self.config.recycle = policy;
+        self
+    }
+
     pub fn worker_threads(mut self, threads: usize) -> Self {
         self.config.worker_threads = threads;
         self
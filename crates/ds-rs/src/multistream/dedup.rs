@@ -0,0 +1,204 @@
+//! Exactly-once detection results per frame
+//!
+//! While a source is migrated or rebalanced, the old and the new pipeline
+//! run it side by side and both report detections for the same frames.
+//! The [`DetectionFence`] lets the first result for each (source, PTS)
+//! through and drops the rest. Pipelines the source has been moved off
+//! are fenced out entirely once retired, so a late result from them cannot
+//! slip past the window of remembered timestamps.
+
+use crate::source::SourceId;
+use gstreamer as gst;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Timestamps remembered per source
+const DEFAULT_WINDOW: usize = 256;
+
+/// What the fence did with a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// First result for the frame; pass it on
+    Delivered,
+    /// The frame already had a result
+    Duplicate,
+    /// From a pipeline the source no longer runs on
+    Fenced,
+}
+
+impl Admission {
+    pub fn is_delivered(self) -> bool {
+        self == Admission::Delivered
+    }
+}
+
+/// Counters from a [`DetectionFence`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    pub delivered: u64,
+    pub duplicates: u64,
+    pub fenced: u64,
+}
+
+#[derive(Debug, Default)]
+struct SourceFence {
+    /// Pipelines allowed to report for the source
+    pipelines: HashSet<usize>,
+    /// Pipelines the source was moved off
+    retired: HashSet<usize>,
+    /// Recently delivered PTS in nanoseconds
+    delivered: BTreeSet<u64>,
+    /// Frames at or before this PTS count as delivered once forgotten
+    watermark: Option<u64>,
+}
+
+/// Drops repeated detection results for a frame
+#[derive(Debug)]
+pub struct DetectionFence {
+    window: usize,
+    sources: Mutex<HashMap<SourceId, SourceFence>>,
+    stats: Mutex<DedupStats>,
+}
+
+impl DetectionFence {
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Remember the last `window` delivered timestamps per source
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            sources: Mutex::new(HashMap::new()),
+            stats: Mutex::new(DedupStats::default()),
+        }
+    }
+
+    /// Accept results for `source_id` from `pipeline_id`, alongside any
+    /// pipeline already running it
+    pub fn add_pipeline(&self, source_id: SourceId, pipeline_id: usize) {
+        let mut sources = self.sources.lock().unwrap();
+        let fence = sources.entry(source_id).or_default();
+        fence.retired.remove(&pipeline_id);
+        fence.pipelines.insert(pipeline_id);
+    }
+
+    /// Reject any further results for `source_id` from `pipeline_id`
+    pub fn retire_pipeline(&self, source_id: SourceId, pipeline_id: usize) {
+        let mut sources = self.sources.lock().unwrap();
+        if let Some(fence) = sources.get_mut(&source_id) {
+            fence.pipelines.remove(&pipeline_id);
+            fence.retired.insert(pipeline_id);
+        }
+    }
+
+    /// Forget a removed source
+    pub fn remove_source(&self, source_id: SourceId) {
+        self.sources.lock().unwrap().remove(&source_id);
+    }
+
+    /// Decide whether a result from `pipeline_id` for the frame of
+    /// `source_id` at `pts` is passed on
+    pub fn admit(&self, source_id: SourceId, pipeline_id: usize, pts: gst::ClockTime) -> Admission {
+        let admission = {
+            let mut sources = self.sources.lock().unwrap();
+            let fence = sources.entry(source_id).or_default();
+            let pts = pts.nseconds();
+
+            if fence.retired.contains(&pipeline_id) {
+                Admission::Fenced
+            } else if fence.watermark.is_some_and(|mark| pts <= mark)
+                || !fence.delivered.insert(pts)
+            {
+                Admission::Duplicate
+            } else {
+                fence.pipelines.insert(pipeline_id);
+                if fence.delivered.len() > self.window
+                    && let Some(oldest) = fence.delivered.pop_first()
+                {
+                    fence.watermark = Some(fence.watermark.map_or(oldest, |mark| mark.max(oldest)));
+                }
+                Admission::Delivered
+            }
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        match admission {
+            Admission::Delivered => stats.delivered += 1,
+            Admission::Duplicate => stats.duplicates += 1,
+            Admission::Fenced => stats.fenced += 1,
+        }
+        admission
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl Default for DetectionFence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> gst::ClockTime {
+        gst::ClockTime::from_mseconds(ms)
+    }
+
+    #[test]
+    fn test_overlapping_pipelines_deliver_once() {
+        let fence = DetectionFence::with_window(4);
+        let source = SourceId(0);
+        fence.add_pipeline(source, 1);
+        assert!(fence.admit(source, 1, ms(0)).is_delivered());
+        assert!(fence.admit(source, 1, ms(33)).is_delivered());
+
+        // Migrating to pipeline 2: both report the next frames
+        fence.add_pipeline(source, 2);
+        assert!(fence.admit(source, 2, ms(66)).is_delivered());
+        assert_eq!(fence.admit(source, 1, ms(66)), Admission::Duplicate);
+        assert!(fence.admit(source, 1, ms(100)).is_delivered());
+        assert_eq!(fence.admit(source, 2, ms(100)), Admission::Duplicate);
+
+        let stats = fence.stats();
+        assert_eq!(stats.delivered, 4);
+        assert_eq!(stats.duplicates, 2);
+    }
+
+    #[test]
+    fn test_retired_pipeline_is_fenced() {
+        let fence = DetectionFence::with_window(4);
+        let source = SourceId(0);
+        fence.add_pipeline(source, 1);
+        fence.add_pipeline(source, 2);
+
+        // Once retired, the old pipeline is fenced even for new frames
+        fence.retire_pipeline(source, 1);
+        assert_eq!(fence.admit(source, 1, ms(133)), Admission::Fenced);
+        assert!(fence.admit(source, 2, ms(133)).is_delivered());
+        assert_eq!(fence.stats().fenced, 1);
+    }
+
+    #[test]
+    fn test_frames_older_than_window_stay_delivered() {
+        let fence = DetectionFence::with_window(4);
+        let source = SourceId(0);
+        fence.add_pipeline(source, 1);
+        for frame in 0..6 {
+            assert!(fence.admit(source, 1, ms(frame * 33)).is_delivered());
+        }
+        assert_eq!(fence.admit(source, 1, ms(0)), Admission::Duplicate);
+    }
+
+    #[test]
+    fn test_sources_are_independent() {
+        let fence = DetectionFence::with_window(4);
+        assert!(fence.admit(SourceId(0), 1, ms(66)).is_delivered());
+        assert!(fence.admit(SourceId(1), 1, ms(66)).is_delivered());
+    }
+}
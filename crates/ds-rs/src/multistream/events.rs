@@ -0,0 +1,512 @@
+//! Ordered, bounded delivery of [`MultiStreamEvent`]s
+//!
+//! Frames of one source may be processed by several workers at once, and
+//! whichever finishes first would report first. Work reserves an
+//! [`EventTicket`] when it starts; the queue holds each source's events
+//! back until every earlier ticket of that source is completed or dropped,
+//! so consumers see a source's events in the order the work was started,
+//! `StreamAdded` before its detections and `StreamRemoved` after them.
+//!
+//! Ready events wait in a queue of bounded capacity. When it is full the
+//! [`EventOverflow`] policy decides what gives; lifecycle events (added,
+//! removed, errors) are never dropped, only detection and resource
+//! updates, so the queue may exceed its capacity by lifecycle events.
+//! Under [`EventOverflow::Block`] each droppable event takes a slot of a
+//! bounded channel before it is queued, so producers wait for room with
+//! [`EventTicket::send`] without holding up a thread.
+
+use super::MultiStreamEvent;
+use crate::source::SourceId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// What happens to a droppable event when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventOverflow {
+    /// Wait for the consumer to make room
+    Block,
+    /// Drop the oldest droppable event in the queue
+    #[default]
+    DropOldest,
+    /// Drop the event being delivered
+    DropNewest,
+}
+
+/// Event queue settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventQueueConfig {
+    /// Ready events kept for the consumer
+    pub capacity: usize,
+    pub overflow: EventOverflow,
+}
+
+impl Default for EventQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: EventOverflow::DropOldest,
+        }
+    }
+}
+
+/// Counters from an [`EventQueue`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventQueueStats {
+    pub delivered: u64,
+    pub dropped: u64,
+    /// Events ready for the consumer
+    pub queued: usize,
+    /// Events completed but waiting for earlier work of their source
+    pub held: usize,
+}
+
+/// Order key: events without a source form a sequence of their own
+type Key = Option<SourceId>;
+
+#[derive(Debug, Default)]
+struct Sequence {
+    next_reserved: u64,
+    next_released: u64,
+    /// A thread is moving events from `held` to the ready queue
+    releasing: bool,
+    /// Completed out of order; `None` for tickets dropped unused
+    held: BTreeMap<u64, Option<MultiStreamEvent>>,
+}
+
+#[derive(Debug)]
+struct State {
+    sequences: HashMap<Key, Sequence>,
+    ready: VecDeque<MultiStreamEvent>,
+    /// Slots of queued droppable events under [`EventOverflow::Block`],
+    /// taken back as they are received
+    taken: mpsc::Receiver<()>,
+    closed: bool,
+    delivered: u64,
+    dropped: u64,
+}
+
+/// Per-source FIFO queue of multi-stream events
+#[derive(Debug)]
+pub struct EventQueue {
+    config: EventQueueConfig,
+    state: Mutex<State>,
+    /// Room for droppable events under [`EventOverflow::Block`]
+    slots: mpsc::Sender<()>,
+    /// Signalled when events become ready
+    changed: Condvar,
+}
+
+impl EventQueue {
+    pub fn new(config: EventQueueConfig) -> Arc<Self> {
+        let (slots, taken) = mpsc::channel(config.capacity.max(1));
+        Arc::new(Self {
+            config,
+            state: Mutex::new(State {
+                sequences: HashMap::new(),
+                ready: VecDeque::new(),
+                taken,
+                closed: false,
+                delivered: 0,
+                dropped: 0,
+            }),
+            slots,
+            changed: Condvar::new(),
+        })
+    }
+
+    pub fn config(&self) -> &EventQueueConfig {
+        &self.config
+    }
+
+    /// Reserve the next place in `source_id`'s order, before starting the
+    /// work whose event fills it
+    pub fn reserve(self: &Arc<Self>, source_id: Option<SourceId>) -> EventTicket {
+        let mut state = self.state.lock().unwrap();
+        let sequence = state.sequences.entry(source_id).or_default();
+        let seq = sequence.next_reserved;
+        sequence.next_reserved += 1;
+        EventTicket {
+            queue: self.clone(),
+            key: source_id,
+            seq,
+            done: false,
+        }
+    }
+
+    /// Deliver `event` after anything already reserved for its source
+    pub fn push(self: &Arc<Self>, event: MultiStreamEvent) {
+        self.reserve(event.source_id()).complete(event);
+    }
+
+    /// Like [`push`](Self::push), waiting for room under
+    /// [`EventOverflow::Block`]
+    pub async fn push_async(self: &Arc<Self>, event: MultiStreamEvent) {
+        self.reserve(event.source_id()).send(event).await;
+    }
+
+    fn blocks(&self, event: &MultiStreamEvent) -> bool {
+        self.config.overflow == EventOverflow::Block && event.is_droppable()
+    }
+
+    /// Put `event` in its place; true when the caller is to release the
+    /// source's events, false when someone else already is
+    fn fill(&self, key: Key, seq: u64, event: Option<MultiStreamEvent>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(sequence) = state.sequences.get_mut(&key) else {
+            return false;
+        };
+        sequence.held.insert(seq, event);
+        // Whoever is releasing this source's events picks it up; two
+        // releasers could overtake each other while waiting for room
+        if sequence.releasing {
+            return false;
+        }
+        sequence.releasing = true;
+        true
+    }
+
+    /// The next of the source's events that is due, until none is
+    fn next_due(&self, key: Key) -> Option<MultiStreamEvent> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let sequence = state.sequences.get_mut(&key)?;
+            let next = sequence.next_released;
+            let Some(event) = sequence.held.remove(&next) else {
+                sequence.releasing = false;
+                if sequence.next_released == sequence.next_reserved {
+                    state.sequences.remove(&key);
+                }
+                return None;
+            };
+            sequence.next_released += 1;
+            if event.is_some() {
+                return event;
+            }
+        }
+    }
+
+    /// Release the source's due events, waiting for room under
+    /// [`EventOverflow::Block`]
+    async fn release(&self, key: Key) {
+        while let Some(event) = self.next_due(key) {
+            let room = !self.blocks(&event) || self.slots.send(()).await.is_ok();
+            self.enqueue(event, room);
+        }
+    }
+
+    /// Release the source's due events from synchronous code: on the
+    /// runtime it is called from if there is one, otherwise blocking
+    fn release_from_sync(self: &Arc<Self>, key: Key) {
+        if self.config.overflow != EventOverflow::Block {
+            while let Some(event) = self.next_due(key) {
+                self.enqueue(event, true);
+            }
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let queue = self.clone();
+                runtime.spawn(async move { queue.release(key).await });
+            }
+            Err(_) => {
+                while let Some(event) = self.next_due(key) {
+                    let room = !self.blocks(&event) || self.slots.blocking_send(()).is_ok();
+                    self.enqueue(event, room);
+                }
+            }
+        }
+    }
+
+    /// Queue a released event; `room` is false when waiting for room
+    /// failed because the queue closed
+    fn enqueue(&self, event: MultiStreamEvent, room: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.ready.len() >= self.config.capacity && !state.closed && event.is_droppable() {
+            match self.config.overflow {
+                // Waited for a slot already
+                EventOverflow::Block => {}
+                EventOverflow::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                }
+                EventOverflow::DropOldest => {
+                    if let Some(index) = state.ready.iter().position(|e| e.is_droppable()) {
+                        state.ready.remove(index);
+                        state.dropped += 1;
+                    }
+                }
+            }
+        }
+        if state.closed || !room {
+            state.dropped += 1;
+            return;
+        }
+        state.ready.push_back(event);
+        self.changed.notify_all();
+    }
+
+    /// Take the oldest ready event, giving back its slot
+    fn pop(&self, state: &mut State) -> Option<MultiStreamEvent> {
+        let event = state.ready.pop_front()?;
+        if self.blocks(&event) {
+            let _ = state.taken.try_recv();
+        }
+        state.delivered += 1;
+        Some(event)
+    }
+
+    /// Next ready event, if any
+    pub fn try_recv(&self) -> Option<MultiStreamEvent> {
+        let mut state = self.state.lock().unwrap();
+        self.pop(&mut state)
+    }
+
+    /// Wait up to `timeout` for the next event; `None` on timeout or once
+    /// the queue is closed and drained
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<MultiStreamEvent> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.pop(&mut state) {
+                return Some(event);
+            }
+            let now = Instant::now();
+            if state.closed || now >= deadline {
+                return None;
+            }
+            state = self.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+
+    /// Wait for the next event; `None` once the queue is closed and drained
+    pub fn recv(&self) -> Option<MultiStreamEvent> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.pop(&mut state) {
+                return Some(event);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Stop accepting events and wake everyone waiting; queued events can
+    /// still be received
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        // Producers waiting for room give up
+        state.taken.close();
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    pub fn stats(&self) -> EventQueueStats {
+        let state = self.state.lock().unwrap();
+        EventQueueStats {
+            delivered: state.delivered,
+            dropped: state.dropped,
+            queued: state.ready.len(),
+            held: state
+                .sequences
+                .values()
+                .flat_map(|sequence| sequence.held.values())
+                .filter(|event| event.is_some())
+                .count(),
+        }
+    }
+}
+
+/// A reserved place in a source's event order
+///
+/// Dropping a ticket without completing it gives up the place, so events
+/// after it are not held back forever by work that failed.
+#[derive(Debug)]
+pub struct EventTicket {
+    queue: Arc<EventQueue>,
+    key: Key,
+    seq: u64,
+    done: bool,
+}
+
+impl EventTicket {
+    pub fn source_id(&self) -> Option<SourceId> {
+        self.key
+    }
+
+    /// Fill the place with `event`. Under [`EventOverflow::Block`] the
+    /// wait for room happens on the current runtime, or blocks outside
+    /// of one; async code should [`send`](Self::send) instead.
+    pub fn complete(mut self, event: MultiStreamEvent) {
+        self.done = true;
+        if self.queue.fill(self.key, self.seq, Some(event)) {
+            self.queue.release_from_sync(self.key);
+        }
+    }
+
+    /// Fill the place with `event`, waiting until the queue has room for
+    /// it and the events before it
+    pub async fn send(mut self, event: MultiStreamEvent) {
+        self.done = true;
+        if self.queue.fill(self.key, self.seq, Some(event)) {
+            self.queue.release(self.key).await;
+        }
+    }
+
+    /// Give up the place without an event
+    pub fn cancel(self) {}
+}
+
+impl Drop for EventTicket {
+    fn drop(&mut self) {
+        if !self.done && self.queue.fill(self.key, self.seq, None) {
+            self.queue.release_from_sync(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detections(source: usize, count: usize) -> MultiStreamEvent {
+        MultiStreamEvent::DetectionProcessed {
+            source_id: SourceId(source),
+            count,
+        }
+    }
+
+    fn counts(queue: &EventQueue) -> Vec<usize> {
+        std::iter::from_fn(|| queue.try_recv())
+            .map(|event| match event {
+                MultiStreamEvent::DetectionProcessed { count, .. } => count,
+                _ => usize::MAX,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_per_source_order() {
+        let queue = EventQueue::new(EventQueueConfig::default());
+
+        let added = queue.reserve(Some(SourceId(0)));
+        let first = queue.reserve(Some(SourceId(0)));
+        let second = queue.reserve(Some(SourceId(0)));
+        let failed = queue.reserve(Some(SourceId(0)));
+        let third = queue.reserve(Some(SourceId(0)));
+
+        // Finishing out of order holds the later events back
+        third.complete(detections(0, 3));
+        second.complete(detections(0, 2));
+        queue.push(detections(1, 10));
+        assert_eq!(queue.stats().held, 2);
+        assert_eq!(counts(&queue), [10]);
+
+        added.complete(MultiStreamEvent::StreamAdded {
+            source_id: SourceId(0),
+            uri: "file:///a.mp4".to_string(),
+        });
+        first.complete(detections(0, 1));
+        // Only the failed work blocks the third event now
+        assert!(matches!(
+            queue.try_recv(),
+            Some(MultiStreamEvent::StreamAdded { .. })
+        ));
+        assert_eq!(counts(&queue), [1, 2]);
+        drop(failed);
+        assert_eq!(counts(&queue), [3]);
+        assert_eq!(queue.stats().delivered, 5);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = EventQueue::new(EventQueueConfig {
+            capacity: 2,
+            overflow: EventOverflow::DropOldest,
+        });
+        queue.push(detections(0, 1));
+        queue.push(detections(0, 2));
+        queue.push(detections(0, 3));
+        queue.push(MultiStreamEvent::StreamRemoved {
+            source_id: SourceId(0),
+        });
+        assert_eq!(counts(&queue), [2, 3, usize::MAX]);
+        assert_eq!(queue.stats().dropped, 1);
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let queue = EventQueue::new(EventQueueConfig {
+            capacity: 1,
+            overflow: EventOverflow::DropNewest,
+        });
+        queue.push(detections(0, 1));
+        queue.push(detections(0, 2));
+        assert_eq!(counts(&queue), [1]);
+    }
+
+    fn blocking_queue() -> Arc<EventQueue> {
+        EventQueue::new(EventQueueConfig {
+            capacity: 1,
+            overflow: EventOverflow::Block,
+        })
+    }
+
+    #[test]
+    fn test_block_waits_for_room() {
+        let queue = blocking_queue();
+        queue.push(detections(0, 1));
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.push(detections(0, 2)))
+        };
+        assert_eq!(
+            queue.recv_timeout(Duration::from_secs(1)).map(|_| ()),
+            Some(())
+        );
+        producer.join().unwrap();
+        assert_eq!(counts(&queue), [2]);
+
+        queue.close();
+        assert!(queue.recv().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_block_awaits_room() {
+        let queue = blocking_queue();
+        queue.push_async(detections(0, 1)).await;
+        let producer = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push_async(detections(0, 2)).await }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(queue.stats().queued, 1);
+
+        assert_eq!(counts(&queue), [1]);
+        producer.await.unwrap();
+        assert_eq!(counts(&queue), [2]);
+    }
+
+    #[tokio::test]
+    async fn test_close_releases_waiting_producers() {
+        let queue = blocking_queue();
+        queue.push_async(detections(0, 1)).await;
+        let producer = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push_async(detections(0, 2)).await }
+        });
+        tokio::task::yield_now().await;
+
+        queue.close();
+        producer.await.unwrap();
+        assert_eq!(counts(&queue), [1]);
+        assert_eq!(queue.stats().dropped, 1);
+    }
+}
@@ -3,12 +3,16 @@
 //! Multi-stream manager for coordinating multiple detection pipelines
 
 use super::{
-    MetricsCollector, MultiStreamConfig, MultiStreamStateManager, PipelinePool, ResourceManager,
-    StreamCoordinator, StreamState,
+    DetectionFence, EventQueue, FrameRoute, KeyframeWatch, MetricsCollector, MigrationPhase,
+    MigrationReport, MultiStreamConfig, MultiStreamEvent, MultiStreamStateManager, PipelinePool,
+    ResourceManager, StreamCoordinator, StreamMigration, StreamState,
 };
 use crate::error::Result;
+use crate::inference::{InferenceTelemetry, ModelStats};
 use crate::pipeline::Pipeline;
+use crate::source::video_source::source_bin_name;
 use crate::source::{FaultTolerantSourceController, SourceId};
+use gstcpuinfer::detector::Detection;
 use gstreamer as gst;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -18,6 +22,8 @@ use tokio::runtime::Runtime;
 
 /// Manages multiple concurrent detection pipelines with fault tolerance
 pub struct MultiStreamManager {
+    /// Pipeline the sources are added to
+    pipeline: Arc<Pipeline>,
     /// Fault-tolerant source controller for stream management
     source_controller: Arc<FaultTolerantSourceController>,
     /// Pool of detection pipelines
@@ -36,6 +42,10 @@ pub struct MultiStreamManager {
     runtime: Arc<Runtime>,
     /// Mapping of source IDs to pipeline IDs
     source_to_pipeline: Arc<Mutex<HashMap<SourceId, usize>>>,
+    /// Drops results repeated by pipelines sharing a source
+    fence: Arc<DetectionFence>,
+    /// Streams being moved between pipelines
+    migrations: Arc<Mutex<HashMap<SourceId, Arc<StreamMigration>>>>,
 }
 
 impl MultiStreamManager {
@@ -53,7 +63,7 @@ impl MultiStreamManager {
 
         // Initialize components
         let pipeline_pool = Arc::new(PipelinePool::new(config.max_concurrent_streams));
-        let coordinator = Arc::new(StreamCoordinator::new());
+        let coordinator = Arc::new(StreamCoordinator::with_event_queue(config.events.clone()));
         let resource_manager = Arc::new(ResourceManager::new(config.resource_limits.clone()));
         let state_manager = Arc::new(MultiStreamStateManager::new());
         let metrics_collector = Arc::new(MetricsCollector::new());
@@ -68,6 +78,7 @@ impl MultiStreamManager {
         );
 
         Ok(Self {
+            pipeline,
             source_controller,
             pipeline_pool,
             coordinator,
@@ -77,6 +88,8 @@ impl MultiStreamManager {
             config,
             runtime,
             source_to_pipeline: Arc::new(Mutex::new(HashMap::new())),
+            fence: Arc::new(DetectionFence::new()),
+            migrations: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -101,11 +114,18 @@ impl MultiStreamManager {
             .lock()
             .unwrap()
             .insert(source_id, pipeline_id);
+        self.fence.add_pipeline(source_id, pipeline_id);
 
         // Register with state manager
         self.state_manager
             .add_stream(source_id, uri.to_string(), pipeline_id)?;
 
+        // Ahead of any detections the processing task reports
+        self.coordinator.publish(MultiStreamEvent::StreamAdded {
+            source_id,
+            uri: uri.to_string(),
+        });
+
         // Set up detection processing for this stream
         self.setup_detection_processing(source_id, pipeline_id)?;
 
@@ -134,12 +154,15 @@ impl MultiStreamManager {
         // Clean up state
         self.state_manager.remove_stream(source_id)?;
         self.source_to_pipeline.lock().unwrap().remove(&source_id);
+        self.fence.remove_source(source_id);
 
         // Stop metrics collection
         self.metrics_collector.stop_stream_metrics(source_id);
 
         // Notify coordinator
         self.coordinator.unregister_stream(source_id)?;
+        self.coordinator
+            .publish(MultiStreamEvent::StreamRemoved { source_id });
 
         // Update resource tracking
         self.resource_manager.stream_removed(source_id)?;
@@ -164,6 +187,46 @@ impl MultiStreamManager {
         Ok(source_ids)
     }
 
+    /// Pass on the detections `pipeline_id` produced for the frame of
+    /// `source_id` at `pts`, unless another pipeline already reported that
+    /// frame or the source has been moved off `pipeline_id`
+    pub fn deliver_detections(
+        &self,
+        source_id: SourceId,
+        pipeline_id: usize,
+        pts: gst::ClockTime,
+        detections: Vec<Detection>,
+        latency_ms: f32,
+    ) -> Option<Vec<Detection>> {
+        if !self.fence.admit(source_id, pipeline_id, pts).is_delivered() {
+            return None;
+        }
+        self.metrics_collector
+            .record_detection(source_id, detections.len(), latency_ms);
+        Some(detections)
+    }
+
+    /// Let `pipeline_id` report for `source_id` alongside its current
+    /// pipeline, e.g. while moving the source over
+    pub fn share_stream(&self, source_id: SourceId, pipeline_id: usize) {
+        self.fence.add_pipeline(source_id, pipeline_id);
+    }
+
+    /// Stop accepting results for `source_id` from `pipeline_id`
+    pub fn fence_pipeline(&self, source_id: SourceId, pipeline_id: usize) {
+        self.fence.retire_pipeline(source_id, pipeline_id);
+    }
+
+    /// Results delivered, dropped as duplicates and fenced out
+    pub fn dedup_stats(&self) -> super::DedupStats {
+        self.fence.stats()
+    }
+
+    /// Stream events, in order per stream
+    pub fn events(&self) -> Arc<EventQueue> {
+        self.coordinator.events()
+    }
+
     /// Get the current state of all streams
     pub fn get_all_stream_states(&self) -> Vec<StreamState> {
         self.state_manager.get_all_streams()
@@ -174,6 +237,17 @@ impl MultiStreamManager {
         self.metrics_collector.get_stream_metrics(source_id)
     }
 
+    /// Report per-model statistics from `telemetry`, e.g. the one an
+    /// [`Application`](crate::app::Application) fills
+    pub fn set_inference_telemetry(&self, telemetry: Arc<InferenceTelemetry>) {
+        self.metrics_collector.set_inference_telemetry(telemetry);
+    }
+
+    /// Latency percentiles, queue wait and skipped frames per model
+    pub fn model_stats(&self) -> Vec<ModelStats> {
+        self.metrics_collector.model_stats()
+    }
+
     /// Get global multi-stream statistics
     pub fn get_stats(&self) -> super::MultiStreamStats {
         let mut stats = self.state_manager.get_stats();
@@ -225,10 +299,35 @@ impl MultiStreamManager {
     fn setup_detection_processing(&self, source_id: SourceId, _pipeline_id: usize) -> Result<()> {
         let state_manager = self.state_manager.clone();
         let runtime = self.runtime.clone();
+        let fence = self.fence.clone();
+        let coordinator = self.coordinator.clone();
+        let migrations = self.migrations.clone();
+        let pipeline_pool = self.pipeline_pool.clone();
+
+        // Migrations hand over at the keyframes of the source's encoded
+        // stream
+        let keyframes = KeyframeWatch::new();
+        match self.pipeline.get_by_name(&source_bin_name(source_id)) {
+            Some(source) => keyframes.attach(&source),
+            None => log::warn!("No source bin to watch keyframes of stream {}", source_id),
+        }
 
         // Spawn async task for detection processing
         runtime.spawn(async move {
+            let mut frame = 0u64;
             loop {
+                // Check if stream is still active
+                let Some(state) = state_manager.get_stream_state(source_id) else {
+                    break;
+                };
+                if !state.is_active {
+                    break;
+                }
+
+                // Reserved before processing, so frames finishing out of
+                // order are still reported in order
+                let ticket = coordinator.begin_event(source_id);
+
                 // Get frames from the source
                 // Process through detection pipeline
                 // Update metrics
@@ -239,24 +338,185 @@ impl MultiStreamManager {
                 // Update metrics (simulated)
                 let fps = 30.0;
                 let detections = 2; // Simulated detection count
+                let pts = gst::ClockTime::from_mseconds(frame * 33);
+                let keyframe = keyframes.take();
+                frame += 1;
+
+                // A stream being migrated is processed by both pipelines
+                // for a few frames; only the first result of each counts
+                let route = migrations
+                    .lock()
+                    .unwrap()
+                    .get(&source_id)
+                    .map_or(FrameRoute::One(state.pipeline_id), |migration| {
+                        migration.on_frame(keyframe)
+                    });
+                let mut delivered = false;
+                for pipeline in route.pipelines() {
+                    pipeline_pool.record_frame(pipeline, detections);
+                    delivered |= fence.admit(source_id, pipeline, pts).is_delivered();
+                }
+                if !delivered {
+                    ticket.cancel();
+                    continue;
+                }
 
                 if let Err(e) = state_manager.update_stream_metrics(source_id, fps, detections) {
                     eprintln!("Failed to update metrics for stream {}: {:?}", source_id, e);
                     break;
                 }
+                ticket
+                    .send(MultiStreamEvent::DetectionProcessed {
+                        source_id,
+                        count: detections,
+                    })
+                    .await;
+            }
+        });
 
-                // Check if stream is still active
-                if let Some(state) = state_manager.get_stream_state(source_id) {
-                    if !state.is_active {
-                        break;
-                    }
-                } else {
+        Ok(())
+    }
+
+    /// Move a stream to a fresh pipeline from the pool, e.g. to rebalance
+    /// load, without dropping frames; see [`migration`](super::migration).
+    /// Resolves once the handover is done or has failed.
+    pub async fn migrate_stream(&self, source_id: SourceId) -> Result<MigrationReport> {
+        let from = self
+            .source_to_pipeline
+            .lock()
+            .unwrap()
+            .get(&source_id)
+            .copied()
+            .ok_or_else(|| {
+                crate::DeepStreamError::InvalidInput(format!("Unknown stream {}", source_id))
+            })?;
+        if self.migrations.lock().unwrap().contains_key(&source_id) {
+            return Err(crate::DeepStreamError::InvalidInput(format!(
+                "Stream {} is already being migrated",
+                source_id
+            )));
+        }
+
+        let to = self.pipeline_pool.allocate_standby(source_id)?;
+        let migration = Arc::new(StreamMigration::new(
+            source_id,
+            from,
+            to,
+            self.config.migration.clone(),
+        ));
+        self.migrations
+            .lock()
+            .unwrap()
+            .insert(source_id, migration.clone());
+        self.fence.add_pipeline(source_id, to);
+
+        let prerolled = match self.pipeline_pool.get_pipeline(to) {
+            Some(pipeline) => pipeline.lock().unwrap().preroll(
+                self.config.detector_config.input_width,
+                self.config.detector_config.input_height,
+            ),
+            None => Err(crate::DeepStreamError::Pipeline(format!(
+                "Pipeline {} vanished from the pool",
+                to
+            ))),
+        };
+        if let Err(e) = prerolled {
+            self.abort_migration(&migration);
+            return Err(e);
+        }
+        migration.prerolled();
+
+        if migration.wait().await != MigrationPhase::Complete {
+            self.abort_migration(&migration);
+            return Err(crate::DeepStreamError::Timeout(format!(
+                "Stream {} did not hand over from pipeline {} to {}",
+                source_id, from, to
+            )));
+        }
+
+        // Every view of the stream switches while the mapping is locked
+        {
+            let mut mapping = self.source_to_pipeline.lock().unwrap();
+            self.state_manager.reassign_stream(source_id, to)?;
+            self.coordinator.reassign_stream(source_id, to)?;
+            self.pipeline_pool.move_source(source_id, from, to)?;
+            mapping.insert(source_id, to);
+            self.fence.retire_pipeline(source_id, from);
+            self.migrations.lock().unwrap().remove(&source_id);
+        }
+        self.coordinator.publish(MultiStreamEvent::StreamMigrated {
+            source_id,
+            from,
+            to,
+        });
+
+        Ok(migration.report())
+    }
+
+    /// Replace the pipelines the recycle policy says are due, moving their
+    /// streams to fresh pipelines first; the pipelines replaced
+    pub async fn recycle_pipelines(&self) -> Result<Vec<usize>> {
+        let policy = &self.config.recycle;
+        let rss_mb = policy
+            .max_rss_mb
+            .and_then(|_| self.resource_manager.process_memory_mb());
+
+        let mut recycled = Vec::new();
+        for candidate in self.pipeline_pool.recycle_candidates(policy, rss_mb) {
+            if let Some(source_id) = candidate.source_id
+                && let Err(e) = self.migrate_stream(source_id).await
+            {
+                eprintln!(
+                    "Cannot recycle pipeline {} ({}): {:?}",
+                    candidate.pipeline_id, candidate.reason, e
+                );
+                continue;
+            }
+            if !self.pipeline_pool.replace_pipeline(candidate.pipeline_id)? {
+                continue;
+            }
+            self.coordinator
+                .publish(MultiStreamEvent::PipelineRecycled {
+                    pipeline_id: candidate.pipeline_id,
+                    reason: candidate.reason.to_string(),
+                });
+            recycled.push(candidate.pipeline_id);
+        }
+        Ok(recycled)
+    }
+
+    /// Check the recycle policy every `check_interval` in the background
+    /// for as long as the manager lives
+    pub fn start_recycling(self: &Arc<Self>) {
+        let manager = Arc::downgrade(self);
+        let interval = self.config.recycle.check_interval;
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                let Some(manager) = manager.upgrade() else {
                     break;
+                };
+                let runtime = manager.runtime.clone();
+                if let Err(e) = runtime.block_on(manager.recycle_pipelines()) {
+                    eprintln!("Failed to recycle pipelines: {:?}", e);
                 }
             }
         });
+    }
 
-        Ok(())
+    /// Migration of `source_id` in progress, if any
+    pub fn migration(&self, source_id: SourceId) -> Option<Arc<StreamMigration>> {
+        self.migrations.lock().unwrap().get(&source_id).cloned()
+    }
+
+    fn abort_migration(&self, migration: &StreamMigration) {
+        migration.abort();
+        let source_id = migration.source_id();
+        self.migrations.lock().unwrap().remove(&source_id);
+        self.fence.retire_pipeline(source_id, migration.to());
+        if let Err(e) = self.pipeline_pool.release_pipeline(migration.to()) {
+            eprintln!("Failed to release pipeline {}: {:?}", migration.to(), e);
+        }
     }
 
     /// Apply adaptive quality control based on resources
@@ -270,6 +530,11 @@ impl MultiStreamManager {
                 usage.cpu_percentage
             );
             self.coordinator.apply_quality_reduction(0.8)?;
+            self.coordinator
+                .publish(MultiStreamEvent::ResourceThresholdReached {
+                    cpu_usage: usage.cpu_percentage,
+                    memory_usage: usage.memory_mb,
+                });
         } else if usage.cpu_percentage < 50.0 {
             // Can increase quality
             println!(
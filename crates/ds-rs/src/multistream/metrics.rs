@@ -2,6 +2,7 @@
 
 //! Metrics collection and monitoring for multi-stream processing
 
+use crate::inference::{InferenceTelemetry, ModelStats};
 use crate::source::SourceId;
 use std::collections::{HashMap, VecDeque};
 use std::fs::File;
@@ -136,6 +137,7 @@ pub struct MetricsCollector {
     time_series: Arc<Mutex<HashMap<String, TimeSeries>>>,
     export_file: Option<Arc<Mutex<File>>>,
     collection_interval: Duration,
+    inference: RwLock<Arc<InferenceTelemetry>>,
 }
 
 impl MetricsCollector {
@@ -145,9 +147,25 @@ impl MetricsCollector {
             time_series: Arc::new(Mutex::new(HashMap::new())),
             export_file: None,
             collection_interval: Duration::from_secs(1),
+            inference: RwLock::new(Arc::new(InferenceTelemetry::new())),
         }
     }
 
+    /// Report per-model statistics from `telemetry`, e.g. that of an
+    /// [`InferenceProcessor`](crate::inference::InferenceProcessor)
+    pub fn set_inference_telemetry(&self, telemetry: Arc<InferenceTelemetry>) {
+        *self.inference.write().unwrap() = telemetry;
+    }
+
+    pub fn inference_telemetry(&self) -> Arc<InferenceTelemetry> {
+        self.inference.read().unwrap().clone()
+    }
+
+    /// Latency percentiles, queue wait and skipped frames per model
+    pub fn model_stats(&self) -> Vec<ModelStats> {
+        self.inference_telemetry().snapshot()
+    }
+
     /// Enable metrics export to file
     pub fn enable_export(&mut self, path: &str) -> std::io::Result<()> {
         let file = File::create(path)?;
@@ -284,6 +302,18 @@ impl MetricsCollector {
             writeln!(file, "Average FPS: {:.2}", stats.average_fps)?;
             writeln!(file, "Average Latency: {:.2}ms", stats.average_latency_ms)?;
             writeln!(file, "Drop Rate: {:.2}%", stats.drop_rate * 100.0)?;
+            for model in self.model_stats() {
+                writeln!(
+                    file,
+                    "Model {}: p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms, queue wait p95 {:.2}ms, skipped {}",
+                    model.model,
+                    model.latency_ms.p50,
+                    model.latency_ms.p95,
+                    model.latency_ms.p99,
+                    model.queue_wait_ms.p95,
+                    model.skipped
+                )?;
+            }
             writeln!(file, "---")?;
 
             file.flush()?;
@@ -302,6 +332,9 @@ impl MetricsCollector {
 
         // Clone stats for recommendations
         let stats_clone = stats.clone();
+        let model_stats = self.model_stats();
+        let mut recommendations = self.generate_recommendations(&stats_clone);
+        recommendations.extend(model_recommendations(&model_stats));
 
         PerformanceReport {
             timestamp: Instant::now(),
@@ -309,7 +342,8 @@ impl MetricsCollector {
             aggregate_stats: stats,
             fps_trend: fps_series.and_then(|s| s.get_average(window)),
             latency_trend: latency_series.and_then(|s| s.get_average(window)),
-            recommendations: self.generate_recommendations(&stats_clone),
+            model_stats,
+            recommendations,
         }
     }
 
@@ -344,6 +378,28 @@ impl MetricsCollector {
     }
 }
 
+fn model_recommendations(models: &[ModelStats]) -> Vec<String> {
+    let mut recommendations = Vec::new();
+    for model in models {
+        if model.latency_ms.p99 > 2.0 * model.latency_ms.p50.max(1.0)
+            && model.latency_ms.p99 > 100.0
+        {
+            recommendations.push(format!(
+                "Model {} has latency spikes (p99 {:.1}ms vs p50 {:.1}ms).",
+                model.model, model.latency_ms.p99, model.latency_ms.p50
+            ));
+        }
+        if model.skip_rate() > 0.1 {
+            recommendations.push(format!(
+                "Model {} skips {:.0}% of frames under backpressure. Consider a lighter model or fewer streams.",
+                model.model,
+                model.skip_rate() * 100.0
+            ));
+        }
+    }
+    recommendations
+}
+
 /// Aggregated statistics across all streams
 #[derive(Debug, Clone)]
 pub struct AggregateStats {
@@ -365,5 +421,7 @@ pub struct PerformanceReport {
     pub aggregate_stats: AggregateStats,
     pub fps_trend: Option<f32>,
     pub latency_trend: Option<f32>,
+    /// Per-model inference statistics
+    pub model_stats: Vec<ModelStats>,
     pub recommendations: Vec<String>,
 }
@@ -0,0 +1,407 @@
+//! Moving a stream to another detection pipeline without losing frames
+//!
+//! A migration goes through three phases. While the target pipeline is
+//! pre-rolled the source pipeline keeps processing alone. The target then
+//! joins at the next keyframe, so its decoder starts from a complete
+//! picture, and both pipelines process the stream for a few frames; the
+//! [`DetectionFence`](super::DetectionFence) passes on one result per
+//! frame. After that overlap the source pipeline is retired.
+//!
+//! Keyframes are those of the stream's encoded buffers, watched by a
+//! [`KeyframeWatch`] on the parsers in its source. Streams with rare
+//! keyframes, or raw ones that have no parser, would stall the handover, so
+//! once `keyframe_timeout` passes the target joins at the next frame.
+
+use crate::source::SourceId;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MigrationConfig {
+    /// Wait this long for a keyframe before joining at any frame
+    pub keyframe_timeout: Duration,
+
+    /// Frames both pipelines process before the source pipeline retires
+    pub overlap_frames: u32,
+
+    /// Give up when the handover has not finished by then
+    pub timeout: Duration,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            keyframe_timeout: Duration::from_secs(2),
+            overlap_frames: 5,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Where a migration is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationPhase {
+    /// Target warming up; frames go to the source pipeline
+    Prerolling,
+    /// Target ready; frames go to the source pipeline until a keyframe
+    AwaitingKeyframe,
+    /// Both pipelines process frames, this many left
+    Overlapping {
+        remaining: u32,
+    },
+    /// Frames go to the target only
+    Complete,
+    Aborted,
+}
+
+/// Pipelines a frame is sent to during a migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameRoute {
+    One(usize),
+    Both { from: usize, to: usize },
+}
+
+impl FrameRoute {
+    pub fn pipelines(self) -> impl Iterator<Item = usize> {
+        let (first, second) = match self {
+            FrameRoute::One(pipeline) => (pipeline, None),
+            FrameRoute::Both { from, to } => (from, Some(to)),
+        };
+        std::iter::once(first).chain(second)
+    }
+}
+
+/// Outcome of a finished migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    pub source_id: SourceId,
+    pub from: usize,
+    pub to: usize,
+    /// Whether the target joined at a keyframe rather than after the
+    /// keyframe timeout
+    pub joined_at_keyframe: bool,
+    /// Frames both pipelines processed
+    pub overlapped_frames: u32,
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+struct Progress {
+    ready_at: Option<Instant>,
+    joined_at_keyframe: bool,
+    overlapped_frames: u32,
+}
+
+/// Handover of one stream between two pipelines, driven by its frames
+#[derive(Debug)]
+pub struct StreamMigration {
+    source_id: SourceId,
+    from: usize,
+    to: usize,
+    config: MigrationConfig,
+    started: Instant,
+    progress: Mutex<Progress>,
+    phase: watch::Sender<MigrationPhase>,
+}
+
+impl StreamMigration {
+    pub fn new(source_id: SourceId, from: usize, to: usize, config: MigrationConfig) -> Self {
+        Self {
+            source_id,
+            from,
+            to,
+            config,
+            started: Instant::now(),
+            progress: Mutex::new(Progress {
+                ready_at: None,
+                joined_at_keyframe: false,
+                overlapped_frames: 0,
+            }),
+            phase: watch::Sender::new(MigrationPhase::Prerolling),
+        }
+    }
+
+    pub fn source_id(&self) -> SourceId {
+        self.source_id
+    }
+
+    pub fn from(&self) -> usize {
+        self.from
+    }
+
+    pub fn to(&self) -> usize {
+        self.to
+    }
+
+    pub fn phase(&self) -> MigrationPhase {
+        *self.phase.borrow()
+    }
+
+    /// The target has been pre-rolled and can join at the next keyframe
+    pub fn prerolled(&self) {
+        let mut progress = self.progress.lock().unwrap();
+        self.phase.send_if_modified(|phase| {
+            if *phase != MigrationPhase::Prerolling {
+                return false;
+            }
+            *phase = MigrationPhase::AwaitingKeyframe;
+            progress.ready_at = Some(Instant::now());
+            true
+        });
+    }
+
+    pub fn abort(&self) {
+        self.phase.send_if_modified(|phase| {
+            if *phase == MigrationPhase::Complete {
+                return false;
+            }
+            *phase = MigrationPhase::Aborted;
+            true
+        });
+    }
+
+    /// Route the stream's next frame and advance the handover
+    pub fn on_frame(&self, keyframe: bool) -> FrameRoute {
+        let mut progress = self.progress.lock().unwrap();
+        let mut phase = self.phase();
+        let route = match phase {
+            MigrationPhase::Prerolling | MigrationPhase::Aborted => FrameRoute::One(self.from),
+            MigrationPhase::AwaitingKeyframe => {
+                let waited = progress.ready_at.map_or(Duration::ZERO, |at| at.elapsed());
+                if keyframe || waited >= self.config.keyframe_timeout {
+                    progress.joined_at_keyframe = keyframe;
+                    progress.overlapped_frames = 1;
+                    phase = match self.config.overlap_frames {
+                        0 | 1 => MigrationPhase::Complete,
+                        overlap => MigrationPhase::Overlapping {
+                            remaining: overlap - 1,
+                        },
+                    };
+                    FrameRoute::Both {
+                        from: self.from,
+                        to: self.to,
+                    }
+                } else {
+                    FrameRoute::One(self.from)
+                }
+            }
+            MigrationPhase::Overlapping { remaining } => {
+                progress.overlapped_frames += 1;
+                phase = if remaining <= 1 {
+                    MigrationPhase::Complete
+                } else {
+                    MigrationPhase::Overlapping {
+                        remaining: remaining - 1,
+                    }
+                };
+                FrameRoute::Both {
+                    from: self.from,
+                    to: self.to,
+                }
+            }
+            MigrationPhase::Complete => FrameRoute::One(self.to),
+        };
+        // An abort in the meantime stands
+        self.phase.send_if_modified(|current| {
+            if *current == MigrationPhase::Aborted || *current == phase {
+                return false;
+            }
+            *current = phase;
+            true
+        });
+        route
+    }
+
+    /// Wait for the handover to finish or fail, up to the configured
+    /// timeout; the final phase
+    pub async fn wait(&self) -> MigrationPhase {
+        let remaining = self.config.timeout.saturating_sub(self.started.elapsed());
+        let mut phase = self.phase.subscribe();
+        let finished = phase
+            .wait_for(|phase| matches!(phase, MigrationPhase::Complete | MigrationPhase::Aborted));
+        match tokio::time::timeout(remaining, finished).await {
+            Ok(Ok(phase)) => *phase,
+            _ => self.phase(),
+        }
+    }
+
+    pub fn report(&self) -> MigrationReport {
+        let progress = self.progress.lock().unwrap();
+        MigrationReport {
+            source_id: self.source_id,
+            from: self.from,
+            to: self.to,
+            joined_at_keyframe: progress.joined_at_keyframe,
+            overlapped_frames: progress.overlapped_frames,
+            duration: self.started.elapsed(),
+        }
+    }
+}
+
+/// Whether a decoder can start at `buffer`
+pub fn is_keyframe(buffer: &gst::BufferRef) -> bool {
+    !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+}
+
+/// Keyframes of one stream, taken from the encoded buffers leaving the
+/// parsers its source plugs
+#[derive(Debug, Default)]
+pub struct KeyframeWatch {
+    pending: AtomicBool,
+}
+
+impl KeyframeWatch {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Watch the parsers `source` has or adds, e.g. inside a uridecodebin
+    pub fn attach(self: &Arc<Self>, source: &gst::Element) {
+        let Some(bin) = source.downcast_ref::<gst::Bin>() else {
+            self.watch_parser(source);
+            return;
+        };
+        for element in bin.iterate_recurse().into_iter().flatten() {
+            self.watch_parser(&element);
+        }
+        let watch = self.clone();
+        bin.connect_deep_element_added(move |_, _, element| watch.watch_parser(element));
+    }
+
+    fn watch_parser(self: &Arc<Self>, element: &gst::Element) {
+        let is_parser = element
+            .factory()
+            .is_some_and(|factory| factory.klass().contains("Parser"));
+        let Some(src) = element.static_pad("src").filter(|_| is_parser) else {
+            return;
+        };
+        let watch = Arc::downgrade(self);
+        src.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(watch) = watch.upgrade() else {
+                return gst::PadProbeReturn::Remove;
+            };
+            if let Some(buffer) = info.buffer() {
+                watch.observe(buffer);
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    pub fn observe(&self, buffer: &gst::BufferRef) {
+        if is_keyframe(buffer) {
+            self.pending.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether a keyframe went by since the last call
+    pub fn take(&self) -> bool {
+        self.pending.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handover_at_keyframe() {
+        let migration = StreamMigration::new(
+            SourceId(0),
+            1,
+            2,
+            MigrationConfig {
+                overlap_frames: 2,
+                ..Default::default()
+            },
+        );
+
+        // Nothing reaches the target before it is pre-rolled, keyframe or not
+        assert_eq!(migration.on_frame(true), FrameRoute::One(1));
+        migration.prerolled();
+        assert_eq!(migration.on_frame(false), FrameRoute::One(1));
+
+        let both = FrameRoute::Both { from: 1, to: 2 };
+        assert_eq!(migration.on_frame(true), both);
+        assert_eq!(both.pipelines().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(migration.on_frame(false), both);
+        assert_eq!(migration.phase(), MigrationPhase::Complete);
+        assert_eq!(migration.on_frame(false), FrameRoute::One(2));
+
+        assert_eq!(migration.wait().await, MigrationPhase::Complete);
+        let report = migration.report();
+        assert!(report.joined_at_keyframe);
+        assert_eq!(report.overlapped_frames, 2);
+    }
+
+    fn impatient() -> MigrationConfig {
+        MigrationConfig {
+            keyframe_timeout: Duration::ZERO,
+            overlap_frames: 1,
+            timeout: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_keyframe_timeout_joins_on_delta() {
+        let migration = StreamMigration::new(SourceId(0), 1, 2, impatient());
+        migration.prerolled();
+
+        assert_eq!(
+            migration.on_frame(false),
+            FrameRoute::Both { from: 1, to: 2 }
+        );
+        assert_eq!(migration.phase(), MigrationPhase::Complete);
+        assert!(!migration.report().joined_at_keyframe);
+    }
+
+    #[tokio::test]
+    async fn test_wait_times_out_before_preroll() {
+        let stalled = StreamMigration::new(SourceId(0), 1, 2, impatient());
+        assert_eq!(stalled.wait().await, MigrationPhase::Prerolling);
+    }
+
+    #[test]
+    fn test_abort_keeps_source_pipeline() {
+        let aborted = StreamMigration::new(SourceId(0), 1, 2, impatient());
+        aborted.prerolled();
+        aborted.abort();
+        assert_eq!(aborted.on_frame(true), FrameRoute::One(1));
+    }
+
+    #[test]
+    fn test_is_keyframe() {
+        gst::init().unwrap();
+        let mut buffer = gst::Buffer::new();
+        assert!(is_keyframe(&buffer));
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_flags(gst::BufferFlags::DELTA_UNIT);
+        assert!(!is_keyframe(&buffer));
+    }
+
+    #[test]
+    fn test_keyframe_watch() {
+        gst::init().unwrap();
+
+        let watch = KeyframeWatch::new();
+        let mut delta = gst::Buffer::new();
+        delta
+            .get_mut()
+            .unwrap()
+            .set_flags(gst::BufferFlags::DELTA_UNIT);
+        watch.observe(&delta);
+        assert!(!watch.take());
+
+        watch.observe(&gst::Buffer::new());
+        assert!(watch.take());
+        // Taken once
+        assert!(!watch.take());
+    }
+}
@@ -4,16 +4,26 @@
 //! fault tolerance, and resource management.
 
 pub mod config;
+pub mod dedup;
+pub mod events;
 pub mod manager;
 pub mod metrics;
+pub mod migration;
 pub mod pipeline_pool;
 pub mod resource_manager;
 pub mod stream_coordinator;
 
 pub use config::{MultiStreamConfig, MultiStreamConfigBuilder};
+pub use dedup::{Admission, DedupStats, DetectionFence};
+pub use events::{EventOverflow, EventQueue, EventQueueConfig, EventQueueStats, EventTicket};
 pub use manager::MultiStreamManager;
 pub use metrics::{MetricsCollector, StreamMetrics};
-pub use pipeline_pool::{DetectionPipeline, PipelinePool};
+pub use migration::{
+    FrameRoute, KeyframeWatch, MigrationConfig, MigrationPhase, MigrationReport, StreamMigration,
+};
+pub use pipeline_pool::{
+    DetectionPipeline, PipelinePool, RecycleCandidate, RecyclePolicy, RecycleReason,
+};
 pub use resource_manager::{ResourceLimits, ResourceManager};
 pub use stream_coordinator::{StreamCoordinator, StreamPriority};
 
@@ -38,11 +48,55 @@ pub struct StreamState {
 /// Multi-stream event types
 #[derive(Debug, Clone)]
 pub enum MultiStreamEvent {
-    StreamAdded { source_id: SourceId, uri: String },
-    StreamRemoved { source_id: SourceId },
-    DetectionProcessed { source_id: SourceId, count: usize },
-    StreamError { source_id: SourceId, error: String },
-    ResourceThresholdReached { cpu_usage: f32, memory_usage: f32 },
+    StreamAdded {
+        source_id: SourceId,
+        uri: String,
+    },
+    StreamRemoved {
+        source_id: SourceId,
+    },
+    DetectionProcessed {
+        source_id: SourceId,
+        count: usize,
+    },
+    StreamError {
+        source_id: SourceId,
+        error: String,
+    },
+    StreamMigrated {
+        source_id: SourceId,
+        from: usize,
+        to: usize,
+    },
+    ResourceThresholdReached {
+        cpu_usage: f32,
+        memory_usage: f32,
+    },
+}
+
+impl MultiStreamEvent {
+    /// Source the event is about, `None` for system-wide events
+    pub fn source_id(&self) -> Option<SourceId> {
+        match self {
+            MultiStreamEvent::StreamAdded { source_id, .. }
+            | MultiStreamEvent::StreamRemoved { source_id }
+            | MultiStreamEvent::DetectionProcessed { source_id, .. }
+            | MultiStreamEvent::StreamError { source_id, .. }
+            | MultiStreamEvent::StreamMigrated { source_id, .. } => Some(*source_id),
+            MultiStreamEvent::ResourceThresholdReached { .. }
+            | MultiStreamEvent::PipelineRecycled { .. } => None,
+        }
+    }
+
+    /// Whether a full event queue may drop the event; a later one of the
+    /// same kind supersedes it, unlike stream lifecycle events
+    pub fn is_droppable(&self) -> bool {
+        matches!(
+            self,
+            MultiStreamEvent::DetectionProcessed { .. }
+                | MultiStreamEvent::ResourceThresholdReached { .. }
+        )
+    }
 }
 
 /// Multi-stream statistics
@@ -132,6 +186,17 @@ impl MultiStreamStateManager {
         Ok(())
     }
 
+    /// Move a stream to another pipeline; readers see either the old or
+    /// the new assignment, never a stream without one
+    pub fn reassign_stream(&self, source_id: SourceId, pipeline_id: usize) -> Result<()> {
+        let mut streams = self.streams.write().unwrap();
+        let stream = streams.get_mut(&source_id).ok_or_else(|| {
+            crate::DeepStreamError::InvalidInput(format!("Unknown stream {}", source_id))
+        })?;
+        stream.pipeline_id = pipeline_id;
+        Ok(())
+    }
+
     pub fn get_stream_state(&self, source_id: SourceId) -> Option<StreamState> {
         self.streams.read().unwrap().get(&source_id).cloned()
     }
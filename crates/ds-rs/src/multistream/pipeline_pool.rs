@@ -3,7 +3,10 @@
 use crate::error::Result;
 use crate::source::SourceId;
 use gstcpuinfer::detector::{Detection, DetectorConfig, OnnxDetector};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
@@ -15,6 +18,63 @@ pub enum PipelineState {
     Error(String),
 }
 
+/// When pipelines are replaced by fresh ones, as long-running detector
+/// sessions slowly grow memory; unset limits are not checked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecyclePolicy {
+    /// Replace pipelines older than this
+    pub max_uptime: Option<Duration>,
+
+    /// Replace pipelines after this many frames
+    pub max_frames: Option<u64>,
+
+    /// Replace the oldest busy pipeline while the process's resident
+    /// memory exceeds this many MB
+    pub max_rss_mb: Option<f32>,
+
+    /// How often [`MultiStreamManager::start_recycling`](super::MultiStreamManager::start_recycling)
+    /// checks the policy
+    pub check_interval: Duration,
+}
+
+impl Default for RecyclePolicy {
+    fn default() -> Self {
+        Self {
+            max_uptime: None,
+            max_frames: None,
+            max_rss_mb: None,
+            check_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Why a pipeline is due for recycling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecycleReason {
+    Uptime(Duration),
+    Frames(u64),
+    Memory { rss_mb: f32 },
+}
+
+impl fmt::Display for RecycleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecycleReason::Uptime(uptime) => write!(f, "up for {}s", uptime.as_secs()),
+            RecycleReason::Frames(frames) => write!(f, "processed {} frames", frames),
+            RecycleReason::Memory { rss_mb } => write!(f, "process RSS at {:.0} MB", rss_mb),
+        }
+    }
+}
+
+/// A pipeline due for recycling and the stream to move off it first
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecycleCandidate {
+    pub pipeline_id: usize,
+    pub source_id: Option<SourceId>,
+    pub reason: RecycleReason,
+}
+
 /// A single detection pipeline for processing video frames
 pub struct DetectionPipeline {
     pub id: usize,
@@ -24,6 +84,10 @@ pub struct DetectionPipeline {
     pub last_used: Instant,
     pub frames_processed: u64,
     pub detections_total: u64,
+    /// When the detector session was created
+    pub created: Instant,
+    /// Frames processed since creation, across assignments
+    pub lifetime_frames: u64,
 }
 
 impl DetectionPipeline {
@@ -45,6 +109,8 @@ impl DetectionPipeline {
             last_used: Instant::now(),
             frames_processed: 0,
             detections_total: 0,
+            created: Instant::now(),
+            lifetime_frames: 0,
         })
     }
 
@@ -60,16 +126,47 @@ impl DetectionPipeline {
         // Stub implementation - in real implementation would perform detection
         let detections = vec![];
 
-        // Update statistics
-        self.frames_processed += 1;
-        self.detections_total += detections.len() as u64;
-        self.last_used = Instant::now();
+        self.record_frame(detections.len());
 
         *self.state.write().unwrap() = PipelineState::Idle;
 
         Ok(detections)
     }
 
+    /// Count a frame processed with `detections` results
+    pub fn record_frame(&mut self, detections: usize) {
+        self.frames_processed += 1;
+        self.lifetime_frames += 1;
+        self.detections_total += detections as u64;
+        self.last_used = Instant::now();
+    }
+
+    /// Why `policy` says the pipeline is due for replacement, if it is
+    pub fn recycle_reason(&self, policy: &RecyclePolicy) -> Option<RecycleReason> {
+        let uptime = self.created.elapsed();
+        if policy.max_uptime.is_some_and(|max| uptime >= max) {
+            return Some(RecycleReason::Uptime(uptime));
+        }
+        if policy
+            .max_frames
+            .is_some_and(|max| self.lifetime_frames >= max)
+        {
+            return Some(RecycleReason::Frames(self.lifetime_frames));
+        }
+        None
+    }
+
+    /// Run the detector once on a blank frame, so the first real frame
+    /// does not pay for session warm-up
+    pub fn preroll(&mut self, width: u32, height: u32) -> Result<()> {
+        let detector = self.detector.lock().unwrap();
+        if detector.is_model_loaded() {
+            detector.detect(&DynamicImage::new_rgb8(width, height))?;
+        }
+        self.last_used = Instant::now();
+        Ok(())
+    }
+
     /// Reset the pipeline for reuse
     pub fn reset(&mut self) {
         self.assigned_source = None;
@@ -129,6 +226,30 @@ impl PipelinePool {
             return Ok(pipeline_id);
         }
 
+        let pipeline_id = self.take_pipeline(source_id)?;
+        self.source_to_pipeline
+            .write()
+            .unwrap()
+            .insert(source_id, pipeline_id);
+        Ok(pipeline_id)
+    }
+
+    /// Allocate a second pipeline for a source that already has one, to
+    /// migrate it to with [`move_source`](Self::move_source)
+    pub fn allocate_standby(&self, source_id: SourceId) -> Result<usize> {
+        self.take_pipeline(source_id)
+    }
+
+    /// Point `source_id` at pipeline `to` and release `from`
+    pub fn move_source(&self, source_id: SourceId, from: usize, to: usize) -> Result<()> {
+        self.source_to_pipeline
+            .write()
+            .unwrap()
+            .insert(source_id, to);
+        self.release_pipeline(from)
+    }
+
+    fn take_pipeline(&self, source_id: SourceId) -> Result<usize> {
         // Try to get an available pipeline
         let mut available = self.available_pipelines.lock().unwrap();
 
@@ -137,14 +258,9 @@ impl PipelinePool {
             let pipelines = self.pipelines.read().unwrap();
             if let Some(pipeline) = pipelines.get(pipeline_id) {
                 let mut p = pipeline.lock().unwrap();
-                p.assigned_source = Some(source_id);
                 p.reset();
+                p.assigned_source = Some(source_id);
             }
-
-            self.source_to_pipeline
-                .write()
-                .unwrap()
-                .insert(source_id, pipeline_id);
             return Ok(pipeline_id);
         }
 
@@ -156,11 +272,6 @@ impl PipelinePool {
             pipeline.assigned_source = Some(source_id);
 
             pipelines.push(Arc::new(Mutex::new(pipeline)));
-            self.source_to_pipeline
-                .write()
-                .unwrap()
-                .insert(source_id, pipeline_id);
-
             Ok(pipeline_id)
         } else {
             Err(crate::DeepStreamError::ResourceLimit(format!(
@@ -178,9 +289,12 @@ impl PipelinePool {
         if let Some(pipeline) = pipelines.get(pipeline_id) {
             let mut p = pipeline.lock().unwrap();
 
-            // Remove source mapping
+            // Remove source mapping, unless the source moved elsewhere
             if let Some(source_id) = p.assigned_source {
-                self.source_to_pipeline.write().unwrap().remove(&source_id);
+                let mut mapping = self.source_to_pipeline.write().unwrap();
+                if mapping.get(&source_id) == Some(&pipeline_id) {
+                    mapping.remove(&source_id);
+                }
             }
 
             // Reset and mark as available
@@ -194,6 +308,71 @@ impl PipelinePool {
         Ok(())
     }
 
+    /// Count a frame processed by `pipeline_id`
+    pub fn record_frame(&self, pipeline_id: usize, detections: usize) {
+        if let Some(pipeline) = self.get_pipeline(pipeline_id) {
+            pipeline.lock().unwrap().record_frame(detections);
+        }
+    }
+
+    /// Pipelines `policy` says are due for replacement, idle ones first.
+    /// With the process at `rss_mb`, above the policy's limit, the oldest
+    /// busy pipeline is due as well.
+    pub fn recycle_candidates(
+        &self,
+        policy: &RecyclePolicy,
+        rss_mb: Option<f32>,
+    ) -> Vec<RecycleCandidate> {
+        let pipelines = self.pipelines.read().unwrap();
+        let mut candidates = Vec::new();
+        let mut oldest_busy: Option<(Instant, usize, SourceId)> = None;
+
+        for (pipeline_id, pipeline) in pipelines.iter().enumerate() {
+            let p = pipeline.lock().unwrap();
+            if let Some(reason) = p.recycle_reason(policy) {
+                candidates.push(RecycleCandidate {
+                    pipeline_id,
+                    source_id: p.assigned_source,
+                    reason,
+                });
+            } else if let Some(source_id) = p.assigned_source
+                && oldest_busy.is_none_or(|(created, _, _)| p.created < created)
+            {
+                oldest_busy = Some((p.created, pipeline_id, source_id));
+            }
+        }
+
+        if let (Some(max), Some(rss_mb)) = (policy.max_rss_mb, rss_mb)
+            && rss_mb > max
+            && !candidates.iter().any(|c| c.source_id.is_some())
+            && let Some((_, pipeline_id, source_id)) = oldest_busy
+        {
+            candidates.push(RecycleCandidate {
+                pipeline_id,
+                source_id: Some(source_id),
+                reason: RecycleReason::Memory { rss_mb },
+            });
+        }
+
+        candidates.sort_by_key(|c| c.source_id.is_some());
+        candidates
+    }
+
+    /// Swap an idle pipeline for a freshly created one; `false` if it has
+    /// been allocated meanwhile
+    pub fn replace_pipeline(&self, pipeline_id: usize) -> Result<bool> {
+        let mut pipelines = self.pipelines.write().unwrap();
+        let Some(slot) = pipelines.get_mut(pipeline_id) else {
+            return Ok(false);
+        };
+        if !slot.lock().unwrap().is_available() {
+            return Ok(false);
+        }
+        let fresh = DetectionPipeline::new(pipeline_id, self.detector_config.clone())?;
+        *slot = Arc::new(Mutex::new(fresh));
+        Ok(true)
+    }
+
     /// Get a pipeline by ID
     pub fn get_pipeline(&self, pipeline_id: usize) -> Option<Arc<Mutex<DetectionPipeline>>> {
         self.pipelines.read().unwrap().get(pipeline_id).cloned()
@@ -7,7 +7,7 @@ use crate::source::SourceId;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
-use sysinfo::System;
+use sysinfo::{ProcessesToUpdate, System};
 
 /// Resource limits configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -245,6 +245,16 @@ impl ResourceManager {
         Ok(())
     }
 
+    /// Resident memory of this process in MB
+    pub fn process_memory_mb(&self) -> Option<f32> {
+        let pid = sysinfo::get_current_pid().ok()?;
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        system
+            .process(pid)
+            .map(|process| process.memory() as f32 / 1024.0 / 1024.0)
+    }
+
     /// Update throttle state based on resource usage
     fn update_throttle_state(&self, cpu: f32, memory: f32) -> Result<()> {
         let mut state = self.throttle_state.write().unwrap();
@@ -2,6 +2,8 @@
 
 //! Stream coordination for timing, synchronization and load balancing
 
+use super::MultiStreamEvent;
+use super::events::{EventQueue, EventQueueConfig, EventTicket};
 use crate::error::Result;
 use crate::source::SourceId;
 use std::cmp::Ordering;
@@ -11,10 +13,20 @@ use std::time::{Duration, Instant};
 
 /// Priority level for stream processing
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 pub enum StreamPriority {
     Low = 0,
+    #[default]
     Normal = 1,
     High = 2,
     Critical = 3,
@@ -61,18 +73,40 @@ pub struct StreamCoordinator {
     processing_queue: Arc<Mutex<BinaryHeap<StreamSchedule>>>,
     load_balancer: Arc<LoadBalancer>,
     sync_manager: Arc<SyncManager>,
+    events: Arc<EventQueue>,
 }
 
 impl StreamCoordinator {
     pub fn new() -> Self {
+        Self::with_event_queue(EventQueueConfig::default())
+    }
+
+    pub fn with_event_queue(config: EventQueueConfig) -> Self {
         Self {
             schedules: Arc::new(RwLock::new(HashMap::new())),
             processing_queue: Arc::new(Mutex::new(BinaryHeap::new())),
             load_balancer: Arc::new(LoadBalancer::new()),
             sync_manager: Arc::new(SyncManager::new()),
+            events: EventQueue::new(config),
         }
     }
 
+    /// Events of all streams, in order per stream
+    pub fn events(&self) -> Arc<EventQueue> {
+        self.events.clone()
+    }
+
+    /// Hold a place in `source_id`'s event order for work about to start;
+    /// its event is delivered after those of work started earlier
+    pub fn begin_event(&self, source_id: SourceId) -> EventTicket {
+        self.events.reserve(Some(source_id))
+    }
+
+    /// Deliver `event` after everything already started for its stream
+    pub fn publish(&self, event: MultiStreamEvent) {
+        self.events.push(event);
+    }
+
     /// Register a new stream for coordination
     pub fn register_stream(&self, source_id: SourceId, pipeline_id: usize) -> Result<()> {
         let schedule = StreamSchedule {
@@ -94,6 +128,30 @@ impl StreamCoordinator {
         Ok(())
     }
 
+    /// Account a stream to another pipeline after migrating it
+    pub fn reassign_stream(&self, source_id: SourceId, pipeline_id: usize) -> Result<()> {
+        if let Some(schedule) = self.schedules.write().unwrap().get_mut(&source_id) {
+            schedule.pipeline_id = pipeline_id;
+        }
+        let mut queue = self.processing_queue.lock().unwrap();
+        let mut schedules: Vec<_> = queue.drain().collect();
+        for schedule in &mut schedules {
+            if schedule.source_id == source_id {
+                schedule.pipeline_id = pipeline_id;
+            }
+        }
+        queue.extend(schedules);
+
+        self.load_balancer.remove_stream(source_id);
+        self.load_balancer.add_stream(source_id, pipeline_id);
+        Ok(())
+    }
+
+    /// Pipeline carrying the least load, as a migration target
+    pub fn least_loaded_pipeline(&self) -> Option<usize> {
+        self.load_balancer.get_least_loaded_pipeline()
+    }
+
     /// Unregister a stream
     pub fn unregister_stream(&self, source_id: SourceId) -> Result<()> {
         self.schedules.write().unwrap().remove(&source_id);
@@ -0,0 +1,10 @@
+//! Long-running work with progress, cancellation and a result
+//!
+//! Adding a batch of sources can take a while when each one has to reach
+//! the pipeline's state. The work runs through source-videos' registry:
+//! started as an [`Operation`] it reports progress, checks the operation
+//! for cancellation, and its result or error stays in the
+//! [`OperationRegistry`] for the status server (`/api/v1/operations`) to
+//! show.
+
+pub use source_videos::operation::{Operation, OperationRegistry, OperationStatus, Status};
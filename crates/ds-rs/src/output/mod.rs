@@ -0,0 +1,12 @@
+//! Outputs fed from the processed stream
+//!
+//! Outputs attach to a `tee` through [`crate::pipeline::BranchManager`], so
+//! they can be started and stopped while the pipeline runs.
+
+pub mod recording;
+pub mod rtsp;
+pub mod upload;
+
+pub use recording::{RecordingConfig, SegmentRecorder, closed_segment};
+pub use rtsp::{RtspOutput, RtspOutputConfig};
+pub use upload::{RetentionPolicy, S3Client, S3Config, SegmentInfo, SegmentUploader, UploadStats};
@@ -0,0 +1,219 @@
+//! Segmented recording with optional upload
+//!
+//! A [`SegmentRecorder`] attaches a `splitmuxsink` branch to a tee and
+//! watches the bus for closed segments. Each one is tagged with the current
+//! source, track and event IDs and handed to a [`SegmentUploader`] when
+//! upload is configured, or left on disk subject to the retention policy.
+
+use super::upload::{RetentionPolicy, S3Client, S3Config, SegmentInfo, SegmentUploader};
+use crate::error::Result;
+use crate::pipeline::{BranchKind, BranchManager};
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub directory: PathBuf,
+
+    /// Target segment length; segments split on the next keyframe
+    pub segment_secs: u64,
+
+    pub bitrate_kbps: u32,
+
+    /// Upload closed segments when set
+    pub upload: Option<S3Config>,
+
+    pub retention: RetentionPolicy,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("recordings"),
+            segment_secs: 60,
+            bitrate_kbps: 4000,
+            upload: None,
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+impl RecordingConfig {
+    pub fn branch_kind(&self) -> BranchKind {
+        BranchKind::Segments {
+            directory: self.directory.clone(),
+            segment_secs: self.segment_secs,
+            bitrate_kbps: self.bitrate_kbps,
+        }
+    }
+}
+
+/// Location of a segment `splitmuxsink` has finished writing
+pub fn closed_segment(message: &gst::Message) -> Option<PathBuf> {
+    let gst::MessageView::Element(element) = message.view() else {
+        return None;
+    };
+    let structure = element.structure()?;
+    if structure.name() != "splitmuxsink-fragment-closed" {
+        return None;
+    }
+    structure.get::<String>("location").ok().map(PathBuf::from)
+}
+
+#[derive(Debug, Default)]
+struct Context {
+    source_id: Option<u32>,
+    track_id: Option<u64>,
+    event_id: Option<String>,
+}
+
+/// Records a tee's stream in segments and uploads the closed ones
+pub struct SegmentRecorder {
+    name: String,
+    config: RecordingConfig,
+    uploader: Option<SegmentUploader>,
+    context: Mutex<Context>,
+}
+
+impl SegmentRecorder {
+    pub fn new(name: impl Into<String>, config: RecordingConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.directory)?;
+        let uploader = match &config.upload {
+            Some(upload) => Some(S3Client::new(upload.clone())?.spawn(config.retention.clone())),
+            None => None,
+        };
+
+        Ok(Self {
+            name: name.into(),
+            config,
+            uploader,
+            context: Mutex::new(Context::default()),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn config(&self) -> &RecordingConfig {
+        &self.config
+    }
+
+    pub fn start(&self, branches: &BranchManager) -> Result<()> {
+        branches.attach(&self.name, self.config.branch_kind())?;
+        Ok(())
+    }
+
+    /// Detach the branch; the last segment is closed by EOS and still
+    /// reported on the bus
+    pub fn stop(&self, branches: &BranchManager) -> Result<()> {
+        branches.detach(&self.name)
+    }
+
+    /// Tag segments closed from now on with `source_id`
+    pub fn set_source(&self, source_id: Option<u32>) {
+        self.context.lock().unwrap().source_id = source_id;
+    }
+
+    pub fn set_track(&self, track_id: Option<u64>) {
+        self.context.lock().unwrap().track_id = track_id;
+    }
+
+    /// Tag segments with the event that triggered recording
+    pub fn set_event(&self, event_id: Option<String>) {
+        self.context.lock().unwrap().event_id = event_id;
+    }
+
+    /// Handle a bus message; returns the segment when it was one of ours
+    pub fn handle_message(&self, message: &gst::Message) -> Option<SegmentInfo> {
+        let path = closed_segment(message)?;
+        if !path.starts_with(&self.config.directory) {
+            return None;
+        }
+
+        let context = self.context.lock().unwrap();
+        let segment = SegmentInfo {
+            source_id: context.source_id,
+            track_id: context.track_id,
+            event_id: context.event_id.clone(),
+            ..SegmentInfo::new(path)
+        };
+        drop(context);
+
+        match &self.uploader {
+            Some(uploader) => {
+                if let Err(e) = uploader.enqueue(segment.clone()) {
+                    log::error!("Cannot queue {}: {}", segment.path.display(), e);
+                }
+            }
+            None => {
+                if let Err(e) = self
+                    .config
+                    .retention
+                    .prune(&self.config.directory, &HashSet::new())
+                {
+                    log::warn!("Segment retention failed: {}", e);
+                }
+            }
+        }
+        Some(segment)
+    }
+
+    pub fn uploader(&self) -> Option<&SegmentUploader> {
+        self.uploader.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_config() {
+        let config: RecordingConfig = toml::from_str(
+            r#"
+            directory = "/var/recordings"
+            segment_secs = 30
+
+            [upload]
+            endpoint = "http://minio:9000"
+            bucket = "recordings"
+            access_key = "key"
+            secret_key = "secret"
+            key_template = "{source}/{event}/{file}"
+
+            [retention]
+            max_files = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.segment_secs, 30);
+        assert_eq!(config.bitrate_kbps, 4000);
+        let upload = config.upload.unwrap();
+        assert_eq!(upload.region, "us-east-1");
+        assert_eq!(upload.max_retries, 5);
+        assert!(config.retention.delete_after_upload);
+        assert_eq!(config.retention.max_files, Some(10));
+    }
+
+    #[test]
+    fn test_closed_segment_message() {
+        let _ = gst::init();
+        let structure = gst::Structure::builder("splitmuxsink-fragment-closed")
+            .field("location", "/rec/cam-00001.mp4")
+            .build();
+        let message = gst::message::Element::new(structure);
+        assert_eq!(
+            closed_segment(&message),
+            Some(PathBuf::from("/rec/cam-00001.mp4"))
+        );
+
+        let other = gst::message::Element::new(gst::Structure::new_empty("other"));
+        assert_eq!(closed_segment(&other), None);
+    }
+}
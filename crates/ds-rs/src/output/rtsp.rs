@@ -0,0 +1,202 @@
+//! RTSP restreaming of the processed (OSD-overlaid) video
+//!
+//! A tee branch encodes the stream to H.264 and sends RTP to a local UDP
+//! port; an RTSP server mount depayloads and repayloads it for any number
+//! of clients. Encoding happens once no matter how many clients connect.
+
+use crate::error::{DeepStreamError, Result};
+use crate::pipeline::{BranchKind, BranchManager};
+use gstreamer as gst;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const BRANCH_NAME: &str = "rtsp-out";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RtspOutputConfig {
+    pub address: String,
+
+    /// RTSP port; 0 lets the OS pick one, see [`RtspOutput::port`]
+    pub port: u16,
+
+    pub mount_point: String,
+
+    /// Local UDP port between the tee branch and the RTSP server
+    pub udp_port: u16,
+
+    pub bitrate_kbps: u32,
+
+    /// RTSP server latency in milliseconds
+    pub latency_ms: u32,
+}
+
+impl Default for RtspOutputConfig {
+    fn default() -> Self {
+        Self {
+            address: "0.0.0.0".to_string(),
+            port: 8555,
+            mount_point: "/ds-out".to_string(),
+            udp_port: 5400,
+            bitrate_kbps: 4000,
+            latency_ms: 200,
+        }
+    }
+}
+
+impl RtspOutputConfig {
+    fn mount_path(&self) -> String {
+        if self.mount_point.starts_with('/') {
+            self.mount_point.clone()
+        } else {
+            format!("/{}", self.mount_point)
+        }
+    }
+
+    /// Server side of the restream: RTP from the tee branch to `pay0`
+    fn launch_string(&self) -> String {
+        format!(
+            "( udpsrc port={} caps=\"application/x-rtp,media=video,clock-rate=90000,\
+             encoding-name=H264,payload=96\" ! \
+             rtph264depay ! h264parse ! rtph264pay name=pay0 pt=96 config-interval=1 )",
+            self.udp_port
+        )
+    }
+}
+
+/// Republishes a tee's stream over RTSP
+pub struct RtspOutput {
+    config: RtspOutputConfig,
+    server: rtsp_server::RTSPServer,
+    source_id: Option<gst::glib::SourceId>,
+    port: u16,
+}
+
+impl RtspOutput {
+    pub fn new(config: RtspOutputConfig) -> Self {
+        let server = rtsp_server::RTSPServer::new();
+        server.set_address(&config.address);
+        server.set_service(&config.port.to_string());
+
+        let port = config.port;
+        Self {
+            config,
+            server,
+            source_id: None,
+            port,
+        }
+    }
+
+    pub fn config(&self) -> &RtspOutputConfig {
+        &self.config
+    }
+
+    /// Attach the encoding branch to `branches` and start serving.
+    ///
+    /// The server is attached to the default main context, which the
+    /// application's main loop must iterate.
+    pub fn start(&mut self, branches: &BranchManager) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        branches.attach(
+            BRANCH_NAME,
+            BranchKind::Rtp {
+                host: "127.0.0.1".to_string(),
+                port: self.config.udp_port,
+                bitrate_kbps: self.config.bitrate_kbps,
+            },
+        )?;
+
+        let factory = rtsp_server::RTSPMediaFactory::new();
+        factory.set_launch(&self.config.launch_string());
+        factory.set_shared(true);
+        factory.set_latency(self.config.latency_ms);
+
+        let mounts = self.server.mount_points().ok_or_else(|| {
+            DeepStreamError::Pipeline("RTSP server has no mount points".to_string())
+        })?;
+        mounts.add_factory(&self.config.mount_path(), factory);
+
+        match self.server.attach(None) {
+            Ok(source_id) => {
+                self.source_id = Some(source_id);
+                self.port = match self.server.bound_port() {
+                    bound if bound > 0 => bound as u16,
+                    _ => self.config.port,
+                };
+                log::info!("Restreaming processed output at {}", self.url());
+                Ok(())
+            }
+            Err(e) => {
+                mounts.remove_factory(&self.config.mount_path());
+                let _ = branches.detach(BRANCH_NAME);
+                Err(DeepStreamError::Pipeline(format!(
+                    "Failed to start RTSP server on {}:{}: {}",
+                    self.config.address, self.config.port, e
+                )))
+            }
+        }
+    }
+
+    /// Stop serving and detach the encoding branch
+    pub fn stop(&mut self, branches: &BranchManager) -> Result<()> {
+        let Some(source_id) = self.source_id.take() else {
+            return Ok(());
+        };
+
+        if let Some(mounts) = self.server.mount_points() {
+            mounts.remove_factory(&self.config.mount_path());
+        }
+        source_id.remove();
+        branches.detach(BRANCH_NAME)?;
+
+        log::info!("Stopped RTSP restream at {}", self.url());
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.source_id.is_some()
+    }
+
+    /// Port actually bound once started
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn url(&self) -> String {
+        let address = match self.config.address.as_str() {
+            "0.0.0.0" => "localhost",
+            address => address,
+        };
+        format!(
+            "rtsp://{}:{}{}",
+            address,
+            self.port,
+            self.config.mount_path()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        let config: RtspOutputConfig = toml::from_str("mount_point = \"overlay\"").unwrap();
+        assert_eq!(config.port, 8555);
+        assert_eq!(config.mount_path(), "/overlay");
+        assert!(config.launch_string().contains("udpsrc port=5400"));
+    }
+
+    #[test]
+    fn test_url() {
+        let _ = gst::init();
+        let output = RtspOutput::new(RtspOutputConfig::default());
+        assert!(!output.is_running());
+        assert_eq!(output.url(), "rtsp://localhost:8555/ds-out");
+    }
+}
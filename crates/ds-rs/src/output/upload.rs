@@ -0,0 +1,682 @@
+//! Upload of completed recording segments to S3-compatible storage
+//!
+//! Objects are sent with a single `PUT` to a URL presigned with AWS
+//! Signature Version 4 by `rusty-s3`, using path-style addressing
+//! (`{endpoint}/{bucket}/{key}`), which MinIO, Ceph and AWS itself accept.
+//! Endpoints may be `http://` or `https://`. Uploads run on a background
+//! thread with retries, and local files are pruned afterwards according to
+//! a [`RetentionPolicy`].
+
+use crate::error::{DeepStreamError, Result};
+use crate::rules::transport;
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+use serde::{Deserialize, Serialize};
+use source_videos::storage::available_space;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where completed segments are uploaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct S3Config {
+    /// `http[s]://host[:port]` of the S3-compatible service
+    pub endpoint: String,
+
+    pub bucket: String,
+
+    #[serde(default = "default_region")]
+    pub region: String,
+
+    pub access_key: String,
+
+    pub secret_key: String,
+
+    /// Object key with `{source}`, `{track}`, `{event}`, `{date}`
+    /// (YYYY-MM-DD), `{time}` (HHMMSS) and `{file}` placeholders
+    #[serde(default = "default_key_template")]
+    pub key_template: String,
+
+    /// Retries after the first attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_key_template() -> String {
+    "source-{source}/{date}/{file}".to_string()
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    60_000
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+impl S3Config {
+    pub fn new(
+        endpoint: impl Into<String>,
+        bucket: impl Into<String>,
+        access_key: impl Into<String>,
+        secret_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            bucket: bucket.into(),
+            region: default_region(),
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            key_template: default_key_template(),
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u64.checked_shl(retry).unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+/// What happens to local segment files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Delete a segment once it is uploaded
+    pub delete_after_upload: bool,
+
+    /// Delete segments older than this, uploaded or not
+    pub max_age_secs: Option<u64>,
+
+    /// Keep at most this many segments, deleting the oldest
+    pub max_files: Option<usize>,
+
+    /// Delete the oldest segments while all of them together are larger
+    pub max_total_bytes: Option<u64>,
+
+    /// Delete the oldest segments while the disk has less free space
+    pub min_free_bytes: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            delete_after_upload: true,
+            max_age_secs: None,
+            max_files: None,
+            max_total_bytes: None,
+            min_free_bytes: None,
+        }
+    }
+}
+
+struct SegmentFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl RetentionPolicy {
+    fn is_unbounded(&self) -> bool {
+        self.max_age_secs.is_none()
+            && self.max_files.is_none()
+            && self.max_total_bytes.is_none()
+            && self.min_free_bytes.is_none()
+    }
+
+    /// Delete expired `.mp4` segments in `directory`, never touching files
+    /// in `keep`. Returns the deleted paths.
+    pub fn prune(&self, directory: &Path, keep: &HashSet<PathBuf>) -> Result<Vec<PathBuf>> {
+        let available = match self.min_free_bytes {
+            Some(_) => available_space(directory),
+            None => None,
+        };
+        self.prune_with(directory, keep, available)
+    }
+
+    fn prune_with(
+        &self,
+        directory: &Path,
+        keep: &HashSet<PathBuf>,
+        mut available: Option<u64>,
+    ) -> Result<Vec<PathBuf>> {
+        if self.is_unbounded() {
+            return Ok(Vec::new());
+        }
+
+        let segments: Vec<SegmentFile> = std::fs::read_dir(directory)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mp4"))
+            .filter_map(|path| {
+                let metadata = path.metadata().ok()?;
+                Some(SegmentFile {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok()?,
+                    path,
+                })
+            })
+            .collect();
+        // Pending uploads still take up space but are never deleted
+        let mut total: u64 = segments.iter().map(|segment| segment.size).sum();
+        let mut candidates: Vec<SegmentFile> = segments
+            .into_iter()
+            .filter(|segment| !keep.contains(&segment.path))
+            .collect();
+        // Newest first
+        candidates.sort_by_key(|segment| std::cmp::Reverse(segment.modified));
+
+        let now = SystemTime::now();
+        let mut deleted = Vec::new();
+        let mut delete = |segment: &SegmentFile| match std::fs::remove_file(&segment.path) {
+            Ok(()) => {
+                deleted.push(segment.path.clone());
+                true
+            }
+            Err(e) => {
+                log::warn!("Cannot delete segment {}: {}", segment.path.display(), e);
+                false
+            }
+        };
+
+        let mut remaining = Vec::new();
+        for (index, segment) in candidates.into_iter().enumerate() {
+            let too_old = self.max_age_secs.is_some_and(|max_age| {
+                now.duration_since(segment.modified).unwrap_or_default()
+                    > Duration::from_secs(max_age)
+            });
+            let too_many = self.max_files.is_some_and(|max_files| index >= max_files);
+            if (too_old || too_many) && delete(&segment) {
+                total = total.saturating_sub(segment.size);
+                available = available.map(|available| available + segment.size);
+            } else {
+                remaining.push(segment);
+            }
+        }
+
+        // Size and free-space limits, oldest first
+        for segment in remaining.iter().rev() {
+            let too_big = self.max_total_bytes.is_some_and(|max| total > max);
+            let too_full = self
+                .min_free_bytes
+                .zip(available)
+                .is_some_and(|(min_free, available)| available < min_free);
+            if !(too_big || too_full) {
+                break;
+            }
+            if delete(segment) {
+                total = total.saturating_sub(segment.size);
+                available = available.map(|available| available + segment.size);
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// A closed segment and the context it was recorded in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentInfo {
+    pub path: PathBuf,
+    pub source_id: Option<u32>,
+    pub track_id: Option<u64>,
+    pub event_id: Option<String>,
+    pub closed_at: SystemTime,
+}
+
+impl SegmentInfo {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            source_id: None,
+            track_id: None,
+            event_id: None,
+            closed_at: SystemTime::now(),
+        }
+    }
+
+    pub fn with_source(mut self, source_id: u32) -> Self {
+        self.source_id = Some(source_id);
+        self
+    }
+
+    pub fn with_track(mut self, track_id: u64) -> Self {
+        self.track_id = Some(track_id);
+        self
+    }
+
+    pub fn with_event(mut self, event_id: impl Into<String>) -> Self {
+        self.event_id = Some(event_id.into());
+        self
+    }
+
+    /// Object key for this segment; missing IDs become `none`
+    pub fn object_key(&self, template: &str) -> String {
+        let secs = self
+            .closed_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let (year, month, day, hour, minute, second) = utc_datetime(secs);
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+
+        template
+            .replace(
+                "{source}",
+                &or_none(self.source_id.map(|id| id.to_string())),
+            )
+            .replace("{track}", &or_none(self.track_id.map(|id| id.to_string())))
+            .replace("{event}", &or_none(self.event_id.clone()))
+            .replace("{date}", &format!("{:04}-{:02}-{:02}", year, month, day))
+            .replace("{time}", &format!("{:02}{:02}{:02}", hour, minute, second))
+            .replace(
+                "{file}",
+                &self
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            )
+    }
+}
+
+/// Civil UTC date and time for Unix seconds
+fn utc_datetime(secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (rem / 3600) as u32,
+        (rem % 3600 / 60) as u32,
+        (rem % 60) as u32,
+    )
+}
+
+/// How long a presigned request stays valid
+const PRESIGN_VALIDITY: Duration = Duration::from_secs(15 * 60);
+
+/// Presigns and sends `PUT Object` requests
+#[derive(Debug, Clone)]
+pub struct S3Client {
+    config: S3Config,
+    bucket: Bucket,
+    credentials: Credentials,
+}
+
+impl S3Client {
+    pub fn new(config: S3Config) -> Result<Self> {
+        let endpoint = reqwest::Url::parse(&config.endpoint).map_err(|e| {
+            DeepStreamError::Configuration(format!(
+                "Invalid S3 endpoint '{}': {}",
+                config.endpoint, e
+            ))
+        })?;
+        if !matches!(endpoint.scheme(), "http" | "https") {
+            return Err(DeepStreamError::Configuration(format!(
+                "Unsupported S3 endpoint '{}': expected http:// or https://",
+                config.endpoint
+            )));
+        }
+        if config.bucket.is_empty() {
+            return Err(DeepStreamError::Configuration(
+                "S3 bucket must not be empty".to_string(),
+            ));
+        }
+        let bucket = Bucket::new(
+            endpoint,
+            UrlStyle::Path,
+            config.bucket.clone(),
+            config.region.clone(),
+        )
+        .map_err(|e| DeepStreamError::Configuration(format!("Invalid S3 bucket: {}", e)))?;
+        let credentials = Credentials::new(config.access_key.clone(), config.secret_key.clone());
+
+        Ok(Self {
+            config,
+            bucket,
+            credentials,
+        })
+    }
+
+    pub fn config(&self) -> &S3Config {
+        &self.config
+    }
+
+    /// Presigned URL for a `PUT` of `key`
+    fn put_url(&self, key: &str) -> reqwest::Url {
+        self.bucket
+            .put_object(Some(&self.credentials), key.trim_start_matches('/'))
+            .sign(PRESIGN_VALIDITY)
+    }
+
+    /// PUT once; `Ok(true)` on success, `Ok(false)` when worth retrying
+    fn attempt(&self, key: &str, body: &[u8]) -> Result<bool> {
+        match transport::http_request(
+            "s3",
+            "PUT",
+            self.put_url(key).as_str(),
+            &HashMap::new(),
+            "video/mp4",
+            body,
+            Duration::from_millis(self.config.timeout_ms),
+        ) {
+            Ok(status) if (200..300).contains(&status) => Ok(true),
+            Ok(status) if status == 429 || status >= 500 => {
+                log::debug!("Upload of {} returned HTTP {}", key, status);
+                Ok(false)
+            }
+            Ok(status) => Err(DeepStreamError::ProcessingFailed {
+                reason: format!("Upload of {} returned HTTP {}", key, status),
+            }),
+            Err(e) => {
+                log::debug!("Upload of {} failed: {}", key, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Upload `body` to `key`, retrying with backoff
+    pub fn put_object(&self, key: &str, body: &[u8]) -> Result<()> {
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                thread::sleep(self.config.backoff(attempt - 1));
+            }
+            if self.attempt(key, body)? {
+                return Ok(());
+            }
+        }
+        Err(DeepStreamError::ProcessingFailed {
+            reason: format!(
+                "Upload of {} gave up after {} attempts",
+                key,
+                self.config.max_retries + 1
+            ),
+        })
+    }
+
+    /// Upload a segment file under its templated key and return the key
+    pub fn upload_segment(&self, segment: &SegmentInfo) -> Result<String> {
+        let key = segment.object_key(&self.config.key_template);
+        let body = std::fs::read(&segment.path)?;
+        self.put_object(&key, &body)?;
+        Ok(key)
+    }
+
+    /// Move uploads to a background thread that applies `retention` after
+    /// each segment
+    pub fn spawn(self, retention: RetentionPolicy) -> SegmentUploader {
+        let (sender, receiver): (Sender<Job>, Receiver<Job>) = mpsc::channel();
+        let pending = Arc::new(Mutex::new(HashSet::new()));
+        let stats = Arc::new(UploadCounters::default());
+
+        let worker = {
+            let pending = pending.clone();
+            let stats = stats.clone();
+            thread::Builder::new()
+                .name("segment-upload".to_string())
+                .spawn(move || {
+                    for job in receiver {
+                        match job {
+                            Job::Upload(segment) => {
+                                self.process(&segment, &retention, &stats);
+                                pending.lock().unwrap().remove(&segment.path);
+                                if let Some(directory) = segment.path.parent() {
+                                    let keep = pending.lock().unwrap().clone();
+                                    if let Err(e) = retention.prune(directory, &keep) {
+                                        log::warn!("Segment retention failed: {}", e);
+                                    }
+                                }
+                            }
+                            Job::Stop => break,
+                        }
+                    }
+                })
+                .expect("failed to spawn upload thread")
+        };
+
+        SegmentUploader {
+            sender,
+            pending,
+            stats,
+            worker: Some(worker),
+        }
+    }
+
+    fn process(&self, segment: &SegmentInfo, retention: &RetentionPolicy, stats: &UploadCounters) {
+        let size = segment.path.metadata().map(|m| m.len()).unwrap_or(0);
+        match self.upload_segment(segment) {
+            Ok(key) => {
+                log::info!("Uploaded {} to {}", segment.path.display(), key);
+                stats.uploaded.fetch_add(1, Ordering::Relaxed);
+                stats.bytes.fetch_add(size, Ordering::Relaxed);
+                if retention.delete_after_upload
+                    && let Err(e) = std::fs::remove_file(&segment.path)
+                {
+                    log::warn!("Cannot delete {}: {}", segment.path.display(), e);
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to upload {}: {}", segment.path.display(), e);
+                stats.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+enum Job {
+    Upload(SegmentInfo),
+    Stop,
+}
+
+#[derive(Debug, Default)]
+struct UploadCounters {
+    uploaded: AtomicU64,
+    failed: AtomicU64,
+    bytes: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadStats {
+    pub uploaded: u64,
+    pub failed: u64,
+    pub bytes: u64,
+    pub pending: usize,
+}
+
+/// Background [`S3Client`]. Dropping it finishes the queued uploads, then
+/// stops the thread.
+pub struct SegmentUploader {
+    sender: Sender<Job>,
+    pending: Arc<Mutex<HashSet<PathBuf>>>,
+    stats: Arc<UploadCounters>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl SegmentUploader {
+    /// Queue a closed segment; it is protected from retention until its
+    /// upload has been attempted
+    pub fn enqueue(&self, segment: SegmentInfo) -> Result<()> {
+        self.pending.lock().unwrap().insert(segment.path.clone());
+        self.sender
+            .send(Job::Upload(segment))
+            .map_err(|_| DeepStreamError::ProcessingFailed {
+                reason: "Upload thread stopped".to_string(),
+            })
+    }
+
+    pub fn stats(&self) -> UploadStats {
+        UploadStats {
+            uploaded: self.stats.uploaded.load(Ordering::Relaxed),
+            failed: self.stats.failed.load(Ordering::Relaxed),
+            bytes: self.stats.bytes.load(Ordering::Relaxed),
+            pending: self.pending.lock().unwrap().len(),
+        }
+    }
+}
+
+impl Drop for SegmentUploader {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Job::Stop);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_datetime() {
+        assert_eq!(utc_datetime(0), (1970, 1, 1, 0, 0, 0));
+        // 2024-02-29T12:34:56Z
+        assert_eq!(utc_datetime(1_709_210_096), (2024, 2, 29, 12, 34, 56));
+    }
+
+    #[test]
+    fn test_object_key_template() {
+        let segment = SegmentInfo {
+            closed_at: UNIX_EPOCH + Duration::from_secs(1_709_210_096),
+            ..SegmentInfo::new("/rec/cam-00003.mp4")
+        }
+        .with_source(2)
+        .with_event("intrusion-7");
+
+        assert_eq!(
+            segment.object_key("source-{source}/{date}/{event}/{time}-{track}-{file}"),
+            "source-2/2024-02-29/intrusion-7/123456-none-cam-00003.mp4"
+        );
+    }
+
+    #[test]
+    fn test_retention_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let path = dir.path().join(format!("seg-{}.mp4", i));
+                std::fs::write(&path, b"x").unwrap();
+                let modified = SystemTime::now() - Duration::from_secs(100 - i * 10);
+                std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+                path
+            })
+            .collect();
+        std::fs::write(dir.path().join("notes.txt"), b"x").unwrap();
+
+        let policy = RetentionPolicy {
+            max_files: Some(2),
+            ..Default::default()
+        };
+        let keep: HashSet<PathBuf> = [paths[0].clone()].into_iter().collect();
+        let mut deleted = policy.prune(dir.path(), &keep).unwrap();
+        deleted.sort();
+
+        // seg-0 is pending upload; seg-3 and seg-2 are the newest two
+        assert_eq!(deleted, vec![paths[1].clone()]);
+        assert!(dir.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_retention_size_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("seg-{}.mp4", i));
+                std::fs::write(&path, vec![0u8; 100]).unwrap();
+                std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(SystemTime::now() - Duration::from_secs(100 - i * 10))
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let by_size = RetentionPolicy {
+            max_total_bytes: Some(250),
+            ..Default::default()
+        };
+        let deleted = by_size.prune(dir.path(), &HashSet::new()).unwrap();
+        assert_eq!(deleted, vec![paths[0].clone()]);
+
+        let by_free_space = RetentionPolicy {
+            min_free_bytes: Some(1000),
+            ..Default::default()
+        };
+        let deleted = by_free_space
+            .prune_with(dir.path(), &HashSet::new(), Some(850))
+            .unwrap();
+        assert_eq!(deleted, vec![paths[1].clone(), paths[2].clone()]);
+    }
+
+    #[test]
+    fn test_client_endpoints() {
+        assert!(S3Client::new(S3Config::new("https://s3.amazonaws.com", "b", "a", "s")).is_ok());
+        assert!(S3Client::new(S3Config::new("ftp://minio", "b", "a", "s")).is_err());
+        assert!(S3Client::new(S3Config::new("http://minio:9000", "", "a", "s")).is_err());
+    }
+
+    #[test]
+    fn test_presigned_put_url() {
+        let client =
+            S3Client::new(S3Config::new("http://minio:9000", "recordings", "a", "s")).unwrap();
+        let url = client.put_url("/cam/1 2.mp4");
+        assert_eq!(url.host_str(), Some("minio"));
+        assert_eq!(url.path(), "/recordings/cam/1%202.mp4");
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert!(query["X-Amz-Credential"].starts_with("a/"));
+        assert!(query.contains_key("X-Amz-Signature"));
+    }
+}
@@ -0,0 +1,529 @@
+//! Consumer branches attached to a `tee` at runtime
+//!
+//! One decoded (and possibly inferred) stream can feed a display, file or
+//! segment recorders, an RTP stream and an appsink at the same time. Each
+//! branch is a bin starting with its own queue, so a slow consumer never
+//! stalls the others. Detaching blocks the tee pad with an idle probe,
+//! unlinks it and, for branches that need it, pushes EOS through so files
+//! are finalized before the bin is removed.
+
+use super::Pipeline;
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// What a branch does with the frames it receives
+#[derive(Debug, Clone)]
+pub enum BranchKind {
+    /// Local video window
+    Display { sync: bool },
+
+    /// H.264 in MP4, finalized with EOS when the branch is detached
+    Recorder {
+        location: PathBuf,
+        bitrate_kbps: u32,
+    },
+
+    /// H.264 in MP4 files of about `segment_secs` each, named
+    /// `{branch}-00000.mp4` and up in `directory`
+    Segments {
+        directory: PathBuf,
+        segment_secs: u64,
+        bitrate_kbps: u32,
+    },
+
+    /// H.264 over RTP/UDP, e.g. for an RTSP server to restream
+    Rtp {
+        host: String,
+        port: u16,
+        bitrate_kbps: u32,
+    },
+
+    /// Frames for application code, see [`BranchManager::appsink`]
+    AppSink {
+        caps: Option<String>,
+        max_buffers: u32,
+    },
+
+    /// Caller-provided elements, linked in order after the branch queue
+    Custom {
+        elements: Vec<gst::Element>,
+        finalize_on_detach: bool,
+    },
+}
+
+impl BranchKind {
+    /// Whether detaching must push EOS through the branch before removal
+    pub fn finalize_on_detach(&self) -> bool {
+        match self {
+            BranchKind::Recorder { .. } | BranchKind::Segments { .. } => true,
+            BranchKind::Custom {
+                finalize_on_detach, ..
+            } => *finalize_on_detach,
+            _ => false,
+        }
+    }
+}
+
+struct Branch {
+    bin: gst::Bin,
+    tee_pad: gst::Pad,
+    finalize: bool,
+}
+
+/// Attaches and detaches consumer branches on one `tee`
+pub struct BranchManager {
+    pipeline: gst::Pipeline,
+    tee: gst::Element,
+    branches: Mutex<HashMap<String, Branch>>,
+}
+
+fn make(factory: &str, name: String) -> Result<gst::Element> {
+    gst::ElementFactory::make(factory)
+        .name(name)
+        .build()
+        .map_err(|_| DeepStreamError::ElementCreation {
+            element: factory.to_string(),
+        })
+}
+
+fn h264_encoder(name: &str, bitrate_kbps: u32) -> Result<gst::Element> {
+    let encoder = make("x264enc", format!("{}-encoder", name))?;
+    encoder.set_property("bitrate", bitrate_kbps);
+    encoder.set_property_from_str("tune", "zerolatency");
+    encoder.set_property_from_str("speed-preset", "ultrafast");
+    Ok(encoder)
+}
+
+fn build_elements(name: &str, kind: BranchKind) -> Result<Vec<gst::Element>> {
+    let elements = match kind {
+        BranchKind::Display { sync } => {
+            let sink = make("autovideosink", format!("{}-sink", name))?;
+            sink.set_property("sync", sync);
+            vec![make("videoconvert", format!("{}-convert", name))?, sink]
+        }
+        BranchKind::Recorder {
+            location,
+            bitrate_kbps,
+        } => {
+            let sink = make("filesink", format!("{}-sink", name))?;
+            sink.set_property("location", location.to_string_lossy().as_ref());
+            vec![
+                make("videoconvert", format!("{}-convert", name))?,
+                h264_encoder(name, bitrate_kbps)?,
+                make("h264parse", format!("{}-parse", name))?,
+                make("mp4mux", format!("{}-mux", name))?,
+                sink,
+            ]
+        }
+        BranchKind::Segments {
+            directory,
+            segment_secs,
+            bitrate_kbps,
+        } => {
+            let sink = make("splitmuxsink", format!("{}-sink", name))?;
+            let location = directory.join(format!("{}-%05d.mp4", name));
+            sink.set_property("location", location.to_string_lossy().as_ref());
+            sink.set_property("max-size-time", segment_secs * 1_000_000_000);
+            // Ask the encoder for a keyframe at each split point
+            sink.set_property("send-keyframe-requests", true);
+            vec![
+                make("videoconvert", format!("{}-convert", name))?,
+                h264_encoder(name, bitrate_kbps)?,
+                make("h264parse", format!("{}-parse", name))?,
+                sink,
+            ]
+        }
+        BranchKind::Rtp {
+            host,
+            port,
+            bitrate_kbps,
+        } => {
+            let pay = make("rtph264pay", format!("{}-pay", name))?;
+            pay.set_property("config-interval", -1i32);
+            pay.set_property("pt", 96u32);
+            let sink = make("udpsink", format!("{}-sink", name))?;
+            sink.set_property("host", host.as_str());
+            sink.set_property("port", i32::from(port));
+            sink.set_property("sync", false);
+            sink.set_property("async", false);
+            vec![
+                make("videoconvert", format!("{}-convert", name))?,
+                h264_encoder(name, bitrate_kbps)?,
+                pay,
+                sink,
+            ]
+        }
+        BranchKind::AppSink { caps, max_buffers } => {
+            let mut builder = gst_app::AppSink::builder()
+                .name(format!("{}-appsink", name))
+                .max_buffers(max_buffers)
+                .drop(true)
+                .sync(false);
+            if let Some(caps) = caps {
+                let caps = caps.parse::<gst::Caps>().map_err(|_| {
+                    DeepStreamError::Configuration(format!("Invalid caps '{}'", caps))
+                })?;
+                builder = builder.caps(&caps);
+            }
+            vec![
+                make("videoconvert", format!("{}-convert", name))?,
+                builder.build().upcast(),
+            ]
+        }
+        BranchKind::Custom { elements, .. } => {
+            if elements.is_empty() {
+                return Err(DeepStreamError::Configuration(format!(
+                    "Branch {} has no elements",
+                    name
+                )));
+            }
+            elements
+        }
+    };
+    Ok(elements)
+}
+
+/// Wrap the branch in a bin with a leading queue and a ghost sink pad
+fn build_branch(name: &str, kind: BranchKind) -> Result<gst::Bin> {
+    let bin = gst::Bin::builder().name(format!("branch-{}", name)).build();
+
+    let queue = make("queue", format!("{}-queue", name))?;
+    let mut chain = vec![queue];
+    chain.extend(build_elements(name, kind)?);
+
+    bin.add_many(&chain)?;
+    gst::Element::link_many(&chain)?;
+
+    let sink_pad = chain[0]
+        .static_pad("sink")
+        .ok_or_else(|| DeepStreamError::PadNotFound {
+            element: chain[0].name().to_string(),
+            pad: "sink".to_string(),
+        })?;
+    let ghost = gst::GhostPad::builder_with_target(&sink_pad)?
+        .name("sink")
+        .build();
+    bin.add_pad(&ghost)?;
+
+    Ok(bin)
+}
+
+/// Sink pad of the innermost sink element, where EOS arrives only once
+/// everything upstream (including muxers inside sink bins such as
+/// `splitmuxsink`) has been flushed
+fn final_sink_pad(bin: &gst::Bin) -> Option<gst::Pad> {
+    bin.iterate_recurse()
+        .into_iter()
+        .flatten()
+        .find(|element| {
+            element.element_flags().contains(gst::ElementFlags::SINK) && !element.is::<gst::Bin>()
+        })
+        .and_then(|sink| sink.static_pad("sink"))
+}
+
+/// Stop the bin and take it out of the pipeline, off the streaming thread
+fn remove_bin(pipeline: &gst::Pipeline, bin: &gst::Bin) {
+    let pipeline = pipeline.clone();
+    bin.call_async(move |bin| {
+        let _ = bin.set_state(gst::State::Null);
+        if pipeline.remove(bin).is_err() {
+            log::warn!("Failed to remove {} from pipeline", bin.name());
+        }
+    });
+}
+
+impl BranchManager {
+    /// Manage branches of `tee`, which must already be in `pipeline`
+    pub fn new(pipeline: &gst::Pipeline, tee: gst::Element) -> Result<Self> {
+        let is_tee = tee
+            .factory()
+            .map(|factory| factory.name() == "tee")
+            .unwrap_or(false);
+        if !is_tee {
+            return Err(DeepStreamError::InvalidInput(format!(
+                "{} is not a tee",
+                tee.name()
+            )));
+        }
+
+        // Keep the stream flowing while no branch is attached
+        tee.set_property("allow-not-linked", true);
+
+        Ok(Self {
+            pipeline: pipeline.clone(),
+            tee,
+            branches: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Manage branches of the tee named `tee_name` in `pipeline`
+    pub fn for_tee(pipeline: &Pipeline, tee_name: &str) -> Result<Self> {
+        let tee =
+            pipeline
+                .get_by_name(tee_name)
+                .ok_or_else(|| DeepStreamError::ElementNotFound {
+                    element: tee_name.to_string(),
+                })?;
+        Self::new(pipeline.gst_pipeline(), tee)
+    }
+
+    pub fn tee(&self) -> &gst::Element {
+        &self.tee
+    }
+
+    /// Build a branch, add it to the pipeline and link it to a new tee pad
+    pub fn attach(&self, name: &str, kind: BranchKind) -> Result<gst::Bin> {
+        let mut branches = self.branches.lock().unwrap();
+        if branches.contains_key(name) {
+            return Err(DeepStreamError::Configuration(format!(
+                "Branch {} is already attached",
+                name
+            )));
+        }
+
+        let finalize = kind.finalize_on_detach();
+        let bin = build_branch(name, kind)?;
+        self.pipeline.add(&bin)?;
+
+        let tee_pad = match self.link(&bin) {
+            Ok(pad) => pad,
+            Err(e) => {
+                let _ = bin.set_state(gst::State::Null);
+                let _ = self.pipeline.remove(&bin);
+                return Err(e);
+            }
+        };
+
+        log::info!("Attached branch {} to {}", name, self.tee.name());
+        branches.insert(
+            name.to_string(),
+            Branch {
+                bin: bin.clone(),
+                tee_pad,
+                finalize,
+            },
+        );
+        Ok(bin)
+    }
+
+    fn link(&self, bin: &gst::Bin) -> Result<gst::Pad> {
+        // Bring the branch up first so the tee never pushes into a flushing pad
+        bin.sync_state_with_parent()?;
+
+        let tee_pad =
+            self.tee
+                .request_pad_simple("src_%u")
+                .ok_or_else(|| DeepStreamError::PadNotFound {
+                    element: self.tee.name().to_string(),
+                    pad: "src_%u".to_string(),
+                })?;
+        let sink_pad = bin
+            .static_pad("sink")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: bin.name().to_string(),
+                pad: "sink".to_string(),
+            })?;
+
+        if let Err(e) = tee_pad.link(&sink_pad) {
+            self.tee.release_request_pad(&tee_pad);
+            return Err(DeepStreamError::PadLinking(format!(
+                "Failed to link {} to {}: {:?}",
+                self.tee.name(),
+                bin.name(),
+                e
+            )));
+        }
+        Ok(tee_pad)
+    }
+
+    /// Unlink a branch once its tee pad is idle, then remove it.
+    ///
+    /// Removal completes asynchronously; recorder branches are removed only
+    /// after EOS has reached their sink, so a paused pipeline keeps them
+    /// around until it plays again.
+    pub fn detach(&self, name: &str) -> Result<()> {
+        let branch =
+            self.branches.lock().unwrap().remove(name).ok_or_else(|| {
+                DeepStreamError::InvalidInput(format!("Branch {} not found", name))
+            })?;
+
+        let Branch {
+            bin,
+            tee_pad,
+            finalize,
+        } = branch;
+        let pipeline = self.pipeline.clone();
+        let tee = self.tee.clone();
+        let branch_name = name.to_string();
+
+        tee_pad.add_probe(gst::PadProbeType::IDLE, move |pad, _info| {
+            let Some(sink_pad) = bin.static_pad("sink") else {
+                return gst::PadProbeReturn::Remove;
+            };
+            let _ = pad.unlink(&sink_pad);
+
+            let eos_pad = finalize.then(|| final_sink_pad(&bin)).flatten();
+            match eos_pad {
+                Some(eos_pad) => {
+                    let (pipeline, bin_clone) = (pipeline.clone(), bin.clone());
+                    let name = branch_name.clone();
+                    eos_pad.add_probe(
+                        gst::PadProbeType::EVENT_DOWNSTREAM,
+                        move |_, info| match info.event() {
+                            Some(event) if event.type_() == gst::EventType::Eos => {
+                                log::info!("Branch {} finalized", name);
+                                remove_bin(&pipeline, &bin_clone);
+                                gst::PadProbeReturn::Remove
+                            }
+                            _ => gst::PadProbeReturn::Ok,
+                        },
+                    );
+                    sink_pad.send_event(gst::event::Eos::new());
+                }
+                None => remove_bin(&pipeline, &bin),
+            }
+
+            let tee_pad = pad.clone();
+            tee.call_async(move |tee| tee.release_request_pad(&tee_pad));
+            log::info!("Detached branch {}", branch_name);
+            gst::PadProbeReturn::Remove
+        });
+
+        Ok(())
+    }
+
+    /// Detach every branch
+    pub fn detach_all(&self) -> Result<()> {
+        for name in self.names() {
+            self.detach(&name)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_attached(&self, name: &str) -> bool {
+        self.branches.lock().unwrap().contains_key(name)
+    }
+
+    /// Names of attached branches, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.branches.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.branches.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.branches.lock().unwrap().is_empty()
+    }
+
+    /// The bin of an attached branch
+    pub fn branch(&self, name: &str) -> Option<gst::Bin> {
+        self.branches
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|branch| branch.bin.clone())
+    }
+
+    /// The appsink of an [`BranchKind::AppSink`] branch
+    pub fn appsink(&self, name: &str) -> Option<gst_app::AppSink> {
+        self.branch(name)?
+            .by_name(&format!("{}-appsink", name))?
+            .downcast::<gst_app::AppSink>()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn tee_pipeline() -> (gst::Pipeline, gst::Element) {
+        let pipeline = gst::Pipeline::with_name("branch-test");
+        let source = gst::ElementFactory::make("videotestsrc").build().unwrap();
+        let tee = gst::ElementFactory::make("tee")
+            .name("split")
+            .build()
+            .unwrap();
+        pipeline.add_many([&source, &tee]).unwrap();
+        source.link(&tee).unwrap();
+        (pipeline, tee)
+    }
+
+    fn fakesink() -> BranchKind {
+        BranchKind::Custom {
+            elements: vec![gst::ElementFactory::make("fakesink").build().unwrap()],
+            finalize_on_detach: false,
+        }
+    }
+
+    #[test]
+    fn test_attach() {
+        let _ = gst::init();
+        let (pipeline, tee) = tee_pipeline();
+        let manager = BranchManager::new(&pipeline, tee).unwrap();
+
+        manager.attach("first", fakesink()).unwrap();
+        manager
+            .attach(
+                "frames",
+                BranchKind::AppSink {
+                    caps: Some("video/x-raw,format=RGB".to_string()),
+                    max_buffers: 1,
+                },
+            )
+            .unwrap();
+        assert_eq!(manager.names(), vec!["first", "frames"]);
+        assert!(manager.appsink("frames").is_some());
+        assert!(pipeline.by_name("branch-first").is_some());
+    }
+
+    #[test]
+    fn test_duplicate_name_rejected() {
+        let _ = gst::init();
+        let (pipeline, tee) = tee_pipeline();
+        let manager = BranchManager::new(&pipeline, tee).unwrap();
+
+        manager.attach("first", fakesink()).unwrap();
+        assert!(manager.attach("first", fakesink()).is_err());
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn test_detach() {
+        let _ = gst::init();
+        let (pipeline, tee) = tee_pipeline();
+        let manager = BranchManager::new(&pipeline, tee).unwrap();
+        manager.attach("first", fakesink()).unwrap();
+
+        manager.detach("first").unwrap();
+        assert!(!manager.is_attached("first"));
+        assert!(manager.detach("first").is_err());
+
+        // Removal happens off the calling thread
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while pipeline.by_name("branch-first").is_some() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(pipeline.by_name("branch-first").is_none());
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn test_requires_tee() {
+        let _ = gst::init();
+        let pipeline = gst::Pipeline::new();
+        let queue = gst::ElementFactory::make("queue").build().unwrap();
+        pipeline.add(&queue).unwrap();
+        assert!(BranchManager::new(&pipeline, queue).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 use super::{Pipeline, StateManager};
 use crate::backend::{BackendManager, BackendType};
 use crate::elements::factory::ElementFactory;
+use crate::elements::properties;
 use crate::error::{DeepStreamError, Result};
 use crate::rendering::{MetadataBridge, RendererFactory, RenderingConfig};
 use gstreamer as gst;
@@ -26,6 +27,8 @@ pub struct PipelineBuilder {
     rendering_config: Option<RenderingConfig>,
     enable_dynamic_rendering: bool,
     metadata_bridge: Option<Arc<Mutex<MetadataBridge>>>,
+    validate_properties: bool,
+    check_caps: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +61,8 @@ impl PipelineBuilder {
             rendering_config: None,
             enable_dynamic_rendering: false,
             metadata_bridge: None,
+            validate_properties: false,
+            check_caps: false,
         }
     }
 
@@ -67,6 +72,19 @@ impl PipelineBuilder {
         self
     }
 
+    /// Fail the build on unknown property names or values of the wrong type
+    /// instead of leaving the element at its defaults
+    pub fn validate_properties(mut self, validate: bool) -> Self {
+        self.validate_properties = validate;
+        self
+    }
+
+    /// Check caps compatibility before each link so mismatches name both sides
+    pub fn check_caps(mut self, check: bool) -> Self {
+        self.check_caps = check;
+        self
+    }
+
     /// Add an element to the pipeline
     pub fn add_element(mut self, name: impl Into<String>, factory_name: impl Into<String>) -> Self {
         let element_config = ElementConfig {
@@ -276,6 +294,31 @@ impl PipelineBuilder {
         self
     }
 
+    /// Check that `value` has the type declared by the property's spec
+    fn check_value_type(element: &gst::Element, name: &str, value: &glib::Value) -> Result<()> {
+        let pspec =
+            element
+                .find_property(name)
+                .ok_or_else(|| DeepStreamError::PropertySetting {
+                    element: element.name().to_string(),
+                    property: format!("{}: no such property", name),
+                })?;
+
+        if value.type_().is_a(pspec.value_type()) {
+            Ok(())
+        } else {
+            Err(DeepStreamError::PropertySetting {
+                element: element.name().to_string(),
+                property: format!(
+                    "{}: expected {}, got {}",
+                    name,
+                    pspec.value_type().name(),
+                    value.type_().name()
+                ),
+            })
+        }
+    }
+
     /// Build the pipeline
     pub fn build(self) -> Result<Pipeline> {
         // Initialize GStreamer if not already done
@@ -331,21 +374,26 @@ impl PipelineBuilder {
             };
 
             // Set element properties
-            for (prop_name, prop_value) in &element_config.properties {
-                element.set_property_from_value(prop_name, prop_value);
-            }
-
-            // Apply properties from the separate properties map
-            if let Some(props) = self.properties.get(&element_config.name) {
-                for (prop_name, prop_value) in props {
-                    element.set_property_from_value(prop_name, prop_value);
+            let extra = self.properties.get(&element_config.name);
+            for (prop_name, prop_value) in element_config
+                .properties
+                .iter()
+                .chain(extra.into_iter().flatten())
+            {
+                if self.validate_properties {
+                    Self::check_value_type(&element, prop_name, prop_value)?;
                 }
+                element.set_property_from_value(prop_name, prop_value);
             }
 
             // Apply string properties using set_property_from_str
             if let Some(str_props) = self.string_properties.get(&element_config.name) {
                 for (prop_name, prop_value) in str_props {
-                    element.set_property_from_str(prop_name, prop_value);
+                    if self.validate_properties {
+                        properties::set_property_str_checked(&element, prop_name, prop_value)?;
+                    } else {
+                        element.set_property_from_str(prop_name, prop_value);
+                    }
                 }
             }
 
@@ -373,6 +421,10 @@ impl PipelineBuilder {
                 }
             })?;
 
+            if self.check_caps && link_config.caps.is_none() {
+                properties::check_caps_compatible(source, destination)?;
+            }
+
             if let Some(caps) = &link_config.caps {
                 source.link_filtered(destination, caps).map_err(|_| {
                     DeepStreamError::PadLinking(format!(
@@ -704,4 +756,50 @@ mod tests {
 
         assert!(pipeline.is_ok());
     }
+
+    #[test]
+    fn test_validate_properties() {
+        let _ = gst::init();
+
+        let result = PipelineBuilder::new("test-pipeline")
+            .backend(BackendType::Mock)
+            .add_element("source", "videotestsrc")
+            .set_property_from_str("source", "patern", "ball")
+            .add_element("sink", "fakesink")
+            .link("source", "sink")
+            .validate_properties(true)
+            .build();
+        assert!(matches!(
+            result,
+            Err(DeepStreamError::PropertySetting { .. })
+        ));
+
+        let result = PipelineBuilder::new("test-pipeline")
+            .backend(BackendType::Mock)
+            .add_element("source", "videotestsrc")
+            .set_property("source", "num-buffers", "ten")
+            .add_element("sink", "fakesink")
+            .link("source", "sink")
+            .validate_properties(true)
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_caps() {
+        let _ = gst::init();
+
+        let result = PipelineBuilder::new("test-pipeline")
+            .backend(BackendType::Mock)
+            .add_element("source", "videotestsrc")
+            .add_element("convert", "audioconvert")
+            .link("source", "convert")
+            .check_caps(true)
+            .build();
+
+        match result {
+            Err(DeepStreamError::PadLinking(msg)) => assert!(msg.contains("Incompatible caps")),
+            _ => panic!("expected caps mismatch"),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use crate::error::{DeepStreamError, Result};
+use crate::source::isolation::catch_panic;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::sync::{Arc, Mutex};
@@ -136,7 +137,10 @@ impl BusWatcher {
 
                 // Poll for messages with timeout
                 if let Some(msg) = bus_clone.timed_pop(gst::ClockTime::from_mseconds(100)) {
-                    handler(&bus_clone, &msg);
+                    // A panicking handler must not end the watch
+                    if let Err(panic) = catch_panic(|| handler(&bus_clone, &msg)) {
+                        log::error!("Bus handler panicked on {:?}: {}", msg.type_(), panic);
+                    }
                 }
             }
         });
@@ -205,7 +209,9 @@ impl StreamMessageHandler {
 
         if let Ok(handlers) = self.stream_handlers.lock() {
             for handler in handlers.iter() {
-                handler(stream_id, msg);
+                if let Err(panic) = catch_panic(|| handler(stream_id, msg)) {
+                    log::error!("Stream {} EOS handler panicked: {}", stream_id, panic);
+                }
             }
         }
     }
@@ -258,8 +264,10 @@ impl MessageCallbackManager {
     pub fn process_message(&self, msg: &gst::Message) -> bool {
         if let Ok(callbacks) = self.callbacks.lock() {
             for callback in callbacks.iter() {
-                if callback(msg) {
-                    return true; // Stop propagation
+                match catch_panic(|| callback(msg)) {
+                    Ok(true) => return true, // Stop propagation
+                    Ok(false) => {}
+                    Err(panic) => log::error!("Message callback panicked: {}", panic),
                 }
             }
         }
@@ -0,0 +1,248 @@
+//! Deadline-based frame dropping for pipelines that fall behind
+//!
+//! Under CPU overload the Standard backend processes frames slower than they
+//! arrive and end-to-end latency grows without bound. Two measures keep it
+//! bounded: leaky queues in front of slow elements, and a pad probe that
+//! drops buffers whose running time is further behind the pipeline clock
+//! than a deadline.
+
+use crate::error::{DeepStreamError, Result};
+use crate::rendering::PerformanceMetrics;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Which end of a full queue loses buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueLeak {
+    /// Block upstream instead of dropping
+    No,
+    /// Drop incoming buffers
+    Upstream,
+    /// Drop the oldest queued buffers
+    #[default]
+    Downstream,
+}
+
+impl QueueLeak {
+    fn as_str(self) -> &'static str {
+        match self {
+            QueueLeak::No => "no",
+            QueueLeak::Upstream => "upstream",
+            QueueLeak::Downstream => "downstream",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadlineConfig {
+    /// Drop frames running more than this far behind the clock; 0 disables
+    pub max_lateness_ms: u64,
+
+    /// Capacity of leaky queues in buffers
+    pub queue_max_buffers: u32,
+
+    pub queue_leak: QueueLeak,
+}
+
+impl Default for DeadlineConfig {
+    fn default() -> Self {
+        Self {
+            max_lateness_ms: 200,
+            queue_max_buffers: 2,
+            queue_leak: QueueLeak::Downstream,
+        }
+    }
+}
+
+/// Counters from a [`FrameDeadline`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DeadlineStats {
+    pub frames_checked: u64,
+    pub frames_dropped_late: u64,
+    /// Buffers leaked by full queues
+    pub queue_drops: u64,
+    pub max_lateness_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    checked: AtomicU64,
+    late: AtomicU64,
+    queue_drops: AtomicU64,
+    max_lateness_us: AtomicU64,
+}
+
+/// Deadline policy shared by the probes and queues it installs
+#[derive(Debug, Clone)]
+pub struct FrameDeadline {
+    config: DeadlineConfig,
+    counters: Arc<Counters>,
+}
+
+impl FrameDeadline {
+    pub fn new(config: DeadlineConfig) -> Self {
+        Self {
+            config,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    pub fn config(&self) -> &DeadlineConfig {
+        &self.config
+    }
+
+    /// Count a frame `lateness` behind the clock and decide whether to drop it
+    pub fn check(&self, lateness: Option<Duration>) -> bool {
+        self.counters.checked.fetch_add(1, Ordering::Relaxed);
+        let Some(lateness) = lateness else {
+            return false;
+        };
+
+        self.counters
+            .max_lateness_us
+            .fetch_max(lateness.as_micros() as u64, Ordering::Relaxed);
+
+        let late = self.config.max_lateness_ms > 0
+            && lateness > Duration::from_millis(self.config.max_lateness_ms);
+        if late {
+            self.counters.late.fetch_add(1, Ordering::Relaxed);
+        }
+        late
+    }
+
+    /// Queue that leaks instead of growing, counting what it drops
+    pub fn leaky_queue(&self, name: Option<&str>) -> Result<gst::Element> {
+        let mut builder = gst::ElementFactory::make("queue");
+        if let Some(name) = name {
+            builder = builder.name(name);
+        }
+        let queue = builder
+            .property("max-size-buffers", self.config.queue_max_buffers)
+            .property("max-size-bytes", 0u32)
+            .property("max-size-time", 0u64)
+            .property_from_str("leaky", self.config.queue_leak.as_str())
+            .build()
+            .map_err(|_| DeepStreamError::ElementCreation {
+                element: "queue".to_string(),
+            })?;
+
+        // A leaky queue drops one buffer per overrun
+        if self.config.queue_leak != QueueLeak::No {
+            let counters = self.counters.clone();
+            queue.connect("overrun", false, move |_| {
+                counters.queue_drops.fetch_add(1, Ordering::Relaxed);
+                None
+            });
+        }
+
+        Ok(queue)
+    }
+
+    /// Drop late buffers arriving on `pad`, e.g. the sink pad of an inference
+    /// element or video sink
+    pub fn attach(&self, pad: &gst::Pad) -> Result<()> {
+        let deadline = self.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let lateness = info.buffer().and_then(|buffer| lateness(pad, buffer));
+            if deadline.check(lateness) {
+                gst::PadProbeReturn::Drop
+            } else {
+                gst::PadProbeReturn::Ok
+            }
+        })
+        .ok_or_else(|| {
+            DeepStreamError::Pipeline(format!("Failed to add deadline probe to {}", pad.name()))
+        })?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> DeadlineStats {
+        DeadlineStats {
+            frames_checked: self.counters.checked.load(Ordering::Relaxed),
+            frames_dropped_late: self.counters.late.load(Ordering::Relaxed),
+            queue_drops: self.counters.queue_drops.load(Ordering::Relaxed),
+            max_lateness_ms: self.counters.max_lateness_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+
+    /// Copy the drop counters into renderer metrics, adding the frames this
+    /// deadline dropped to those the renderer counted
+    pub fn apply_to(&self, metrics: &mut PerformanceMetrics) {
+        let stats = self.stats();
+        metrics.frames_dropped_late = stats.frames_dropped_late;
+        metrics.queue_drops = stats.queue_drops;
+        metrics.max_lateness_ms = stats.max_lateness_ms;
+        metrics.frames_dropped += stats.frames_dropped_late + stats.queue_drops;
+    }
+}
+
+/// How far the buffer's running time trails the element's clock
+fn lateness(pad: &gst::Pad, buffer: &gst::BufferRef) -> Option<Duration> {
+    let pts = buffer.pts()?;
+    let element = pad.parent_element()?;
+    let now = element.current_running_time()?;
+
+    let event = pad.sticky_event::<gst::event::Segment>(0)?;
+    let segment = event.segment().downcast_ref::<gst::ClockTime>()?;
+    let running = segment.to_running_time(pts)?;
+
+    Some(Duration::from_nanos(
+        now.nseconds().saturating_sub(running.nseconds()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_check() {
+        let deadline = FrameDeadline::new(DeadlineConfig {
+            max_lateness_ms: 100,
+            ..Default::default()
+        });
+
+        assert!(!deadline.check(None));
+        assert!(!deadline.check(Some(Duration::from_millis(50))));
+        assert!(deadline.check(Some(Duration::from_millis(250))));
+
+        let stats = deadline.stats();
+        assert_eq!(stats.frames_checked, 3);
+        assert_eq!(stats.frames_dropped_late, 1);
+        assert_eq!(stats.max_lateness_ms, 250.0);
+
+        let mut metrics = PerformanceMetrics {
+            frames_dropped: 2,
+            ..Default::default()
+        };
+        deadline.apply_to(&mut metrics);
+        assert_eq!(metrics.frames_dropped_late, 1);
+        assert_eq!(metrics.frames_dropped, 3);
+    }
+
+    #[test]
+    fn test_disabled_deadline_never_drops() {
+        let deadline = FrameDeadline::new(DeadlineConfig {
+            max_lateness_ms: 0,
+            ..Default::default()
+        });
+        assert!(!deadline.check(Some(Duration::from_secs(10))));
+        assert_eq!(deadline.stats().frames_dropped_late, 0);
+    }
+
+    #[test]
+    fn test_leaky_queue() {
+        gst::init().unwrap();
+
+        let deadline = FrameDeadline::new(DeadlineConfig::default());
+        let queue = deadline.leaky_queue(Some("deadline-queue")).unwrap();
+        assert_eq!(queue.property::<u32>("max-size-buffers"), 2);
+        assert_eq!(queue.property::<u64>("max-size-time"), 0);
+    }
+}
@@ -0,0 +1,364 @@
+//! Declarative pipeline descriptions
+//!
+//! A [`PipelineDescription`] lists elements in the order they are linked,
+//! plus branches hanging off elements such as `tee`, so a processing chain
+//! can be changed in a TOML (or JSON) file instead of in code:
+//!
+//! ```toml
+//! name = "custom"
+//! backend = "standard"
+//!
+//! [[elements]]
+//! name = "source"
+//! factory = "videotestsrc"
+//! properties = { pattern = "ball", num-buffers = 300 }
+//!
+//! [[elements]]
+//! name = "split"
+//! factory = "tee"
+//!
+//! [[elements]]
+//! name = "display"
+//! factory = "autovideosink"
+//!
+//! [[branches]]
+//! from = "split"
+//! elements = [
+//!     { name = "record-queue", factory = "queue" },
+//!     { name = "record", factory = "fakesink" },
+//! ]
+//! ```
+
+use super::PipelineBuilder;
+use crate::backend::BackendType;
+use crate::config::PropertyValue;
+use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementDescription {
+    pub name: String,
+
+    /// Factory name, e.g. `queue`; `nv*` names go through the backend
+    pub factory: String,
+
+    /// Values are applied with `set_property_from_str`, so enums can be
+    /// given by nick and numbers are converted to the property's type
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyValue>,
+
+    /// Link to the next element of the chain. Turn off after elements with
+    /// dynamic pads such as `uridecodebin`.
+    #[serde(default = "default_true")]
+    pub link_next: bool,
+
+    /// Caps filter on the link to the next element
+    #[serde(default)]
+    pub caps: Option<String>,
+}
+
+/// A chain of elements fed from a branch point such as a `tee`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchDescription {
+    /// Element the branch is linked from
+    pub from: String,
+
+    pub elements: Vec<ElementDescription>,
+}
+
+/// A link not implied by element order, e.g. into a muxer or compositor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkDescription {
+    pub from: String,
+    pub to: String,
+
+    #[serde(default)]
+    pub caps: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineDescription {
+    pub name: String,
+
+    /// `deepstream`, `standard` or `mock`; auto-detected when unset
+    #[serde(default)]
+    pub backend: Option<String>,
+
+    /// Main chain, linked in order
+    #[serde(default)]
+    pub elements: Vec<ElementDescription>,
+
+    #[serde(default)]
+    pub branches: Vec<BranchDescription>,
+
+    #[serde(default)]
+    pub links: Vec<LinkDescription>,
+
+    #[serde(default)]
+    pub start_paused: bool,
+
+    #[serde(default)]
+    pub validate_properties: bool,
+
+    #[serde(default)]
+    pub check_caps: bool,
+}
+
+fn parse_backend(name: &str) -> Result<BackendType> {
+    match name.to_ascii_lowercase().as_str() {
+        "deepstream" => Ok(BackendType::DeepStream),
+        "standard" => Ok(BackendType::Standard),
+        "mock" => Ok(BackendType::Mock),
+        other => Err(DeepStreamError::Configuration(format!(
+            "Unknown backend '{}', expected deepstream, standard or mock",
+            other
+        ))),
+    }
+}
+
+fn parse_caps(caps: &str) -> Result<gst::Caps> {
+    gst::Caps::from_str(caps)
+        .map_err(|_| DeepStreamError::Configuration(format!("Invalid caps '{}'", caps)))
+}
+
+impl PipelineDescription {
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        let description: Self = toml::from_str(contents)?;
+        description.validate()?;
+        Ok(description)
+    }
+
+    /// Load from a `.toml` or `.json` file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let description: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(|e| {
+                DeepStreamError::Configuration(format!("{}: {}", path.display(), e))
+            })?,
+            _ => toml::from_str(&contents)?,
+        };
+        description.validate()?;
+        Ok(description)
+    }
+
+    fn all_elements(&self) -> impl Iterator<Item = &ElementDescription> {
+        self.elements
+            .iter()
+            .chain(self.branches.iter().flat_map(|b| b.elements.iter()))
+    }
+
+    /// Check names are unique and every link refers to a described element
+    pub fn validate(&self) -> Result<()> {
+        if let Some(backend) = &self.backend {
+            parse_backend(backend)?;
+        }
+
+        let mut names = HashSet::new();
+        for element in self.all_elements() {
+            if element.name.is_empty() || element.factory.is_empty() {
+                return Err(DeepStreamError::Configuration(
+                    "Every element needs a name and a factory".to_string(),
+                ));
+            }
+            if !names.insert(element.name.as_str()) {
+                return Err(DeepStreamError::Configuration(format!(
+                    "Duplicate element name '{}'",
+                    element.name
+                )));
+            }
+        }
+
+        let known = |name: &str| -> Result<()> {
+            if names.contains(name) {
+                Ok(())
+            } else {
+                Err(DeepStreamError::ElementNotFound {
+                    element: name.to_string(),
+                })
+            }
+        };
+        for branch in &self.branches {
+            known(&branch.from)?;
+            if branch.elements.is_empty() {
+                return Err(DeepStreamError::Configuration(format!(
+                    "Branch from '{}' has no elements",
+                    branch.from
+                )));
+            }
+        }
+        for link in &self.links {
+            known(&link.from)?;
+            known(&link.to)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the elements in the main chain
+    pub fn element_names(&self) -> Vec<&str> {
+        self.elements.iter().map(|e| e.name.as_str()).collect()
+    }
+}
+
+impl PipelineBuilder {
+    /// Builder preloaded with a description's elements, links and options.
+    /// Further elements or links can be added before building.
+    pub fn from_description(description: &PipelineDescription) -> Result<Self> {
+        description.validate()?;
+
+        let mut builder = PipelineBuilder::new(&description.name)
+            .start_paused(description.start_paused)
+            .validate_properties(description.validate_properties)
+            .check_caps(description.check_caps);
+        if let Some(backend) = &description.backend {
+            builder = builder.backend(parse_backend(backend)?);
+        }
+
+        builder = builder.add_chain(&description.elements, None)?;
+        for branch in &description.branches {
+            builder = builder.add_chain(&branch.elements, Some(&branch.from))?;
+        }
+
+        for link in &description.links {
+            builder = match &link.caps {
+                Some(caps) => builder.link_filtered(&link.from, &link.to, parse_caps(caps)?),
+                None => builder.link(&link.from, &link.to),
+            };
+        }
+
+        Ok(builder)
+    }
+
+    /// Builder for a `.toml` or `.json` description file
+    pub fn from_description_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_description(&PipelineDescription::from_file(path.as_ref())?)
+    }
+
+    fn add_chain(mut self, chain: &[ElementDescription], from: Option<&str>) -> Result<Self> {
+        let mut previous: Option<(&str, Option<&str>)> = from.map(|name| (name, None));
+
+        for element in chain {
+            self = self.add_element(&element.name, &element.factory);
+            for (property, value) in &element.properties {
+                self = self.set_property_from_str(&element.name, property, value.as_string());
+            }
+
+            if let Some((source, caps)) = previous {
+                self = match caps {
+                    Some(caps) => self.link_filtered(source, &element.name, parse_caps(caps)?),
+                    None => self.link(source, &element.name),
+                };
+            }
+            previous = element
+                .link_next
+                .then_some((element.name.as_str(), element.caps.as_deref()));
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gstreamer::prelude::*;
+
+    const DESCRIPTION: &str = r#"
+        name = "described"
+        backend = "mock"
+
+        [[elements]]
+        name = "source"
+        factory = "videotestsrc"
+        properties = { pattern = "ball", num-buffers = 10, is-live = false }
+        caps = "video/x-raw,width=320,height=240"
+
+        [[elements]]
+        name = "split"
+        factory = "tee"
+
+        [[elements]]
+        name = "display-queue"
+        factory = "queue"
+
+        [[elements]]
+        name = "display"
+        factory = "fakesink"
+
+        [[branches]]
+        from = "split"
+        elements = [
+            { name = "record-queue", factory = "queue" },
+            { name = "record", factory = "fakesink", properties = { sync = false } },
+        ]
+    "#;
+
+    #[test]
+    fn test_parse_description() {
+        let description = PipelineDescription::from_toml_str(DESCRIPTION).unwrap();
+        assert_eq!(
+            description.element_names(),
+            vec!["source", "split", "display-queue", "display"]
+        );
+        assert_eq!(description.branches[0].elements.len(), 2);
+        assert_eq!(
+            description.elements[0].properties["num-buffers"].as_string(),
+            "10"
+        );
+        assert!(description.elements[0].link_next);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let duplicate = r#"
+            name = "bad"
+            [[elements]]
+            name = "a"
+            factory = "queue"
+            [[elements]]
+            name = "a"
+            factory = "queue"
+        "#;
+        assert!(PipelineDescription::from_toml_str(duplicate).is_err());
+
+        let dangling = r#"
+            name = "bad"
+            [[elements]]
+            name = "a"
+            factory = "queue"
+            [[links]]
+            from = "a"
+            to = "missing"
+        "#;
+        assert!(matches!(
+            PipelineDescription::from_toml_str(dangling),
+            Err(DeepStreamError::ElementNotFound { .. })
+        ));
+
+        let backend = "name = \"bad\"\nbackend = \"cuda\"\n";
+        assert!(PipelineDescription::from_toml_str(backend).is_err());
+    }
+
+    #[test]
+    fn test_build_from_description() {
+        let _ = gst::init();
+
+        let description = PipelineDescription::from_toml_str(DESCRIPTION).unwrap();
+        let pipeline = PipelineBuilder::from_description(&description)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let gst_pipeline = pipeline.gst_pipeline();
+        for name in ["source", "split", "display", "record-queue", "record"] {
+            assert!(gst_pipeline.by_name(name).is_some(), "missing {}", name);
+        }
+    }
+}
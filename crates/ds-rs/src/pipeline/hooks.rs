@@ -0,0 +1,245 @@
+//! User elements spliced into the pipeline at fixed points
+//!
+//! Register a hook for a [`HookPoint`] and it is called whenever the
+//! pipeline (or, for [`HookPoint::PreMux`], each new source) is built. A
+//! hook returns a fresh element or bin with one sink and one src pad, or
+//! `None` to leave that spot alone. Hooks for the same point are chained
+//! in registration order.
+
+use crate::error::{DeepStreamError, Result};
+use crate::source::SourceId;
+use crate::source::isolation::catch_panic;
+use gst::prelude::*;
+use gstreamer as gst;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookPoint {
+    /// In each source's branch, before the streammux
+    PreMux,
+    /// After inference and tracking, before the tiler
+    PostInference,
+    /// Right in front of the video sink
+    PreSink,
+}
+
+impl HookPoint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PreMux => "pre-mux",
+            Self::PostInference => "post-inference",
+            Self::PreSink => "pre-sink",
+        }
+    }
+}
+
+impl fmt::Display for HookPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a hook is being asked to build for
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub point: HookPoint,
+    /// Set for [`HookPoint::PreMux`]
+    pub source: Option<SourceId>,
+    pub uri: Option<String>,
+}
+
+impl HookContext {
+    pub fn new(point: HookPoint) -> Self {
+        Self {
+            point,
+            source: None,
+            uri: None,
+        }
+    }
+
+    pub fn for_source(id: SourceId, uri: &str) -> Self {
+        Self {
+            point: HookPoint::PreMux,
+            source: Some(id),
+            uri: Some(uri.to_string()),
+        }
+    }
+}
+
+type Hook = Arc<dyn Fn(&HookContext) -> Result<Option<gst::Element>> + Send + Sync>;
+
+struct Registered {
+    point: HookPoint,
+    name: String,
+    hook: Hook,
+}
+
+#[derive(Default)]
+pub struct ElementHooks {
+    hooks: Mutex<Vec<Registered>>,
+}
+
+impl ElementHooks {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Add a hook under `name`, replacing any earlier one of that name
+    pub fn register<F>(&self, point: HookPoint, name: &str, hook: F)
+    where
+        F: Fn(&HookContext) -> Result<Option<gst::Element>> + Send + Sync + 'static,
+    {
+        let mut hooks = self.hooks.lock().unwrap();
+        hooks.retain(|registered| registered.name != name);
+        hooks.push(Registered {
+            point,
+            name: name.to_string(),
+            hook: Arc::new(hook),
+        });
+    }
+
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut hooks = self.hooks.lock().unwrap();
+        let before = hooks.len();
+        hooks.retain(|registered| registered.name != name);
+        hooks.len() != before
+    }
+
+    /// Names of the hooks at `point`, in the order they run
+    pub fn names(&self, point: HookPoint) -> Vec<String> {
+        self.hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|registered| registered.point == point)
+            .map(|registered| registered.name.clone())
+            .collect()
+    }
+
+    pub fn has_hooks(&self, point: HookPoint) -> bool {
+        self.hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|registered| registered.point == point)
+    }
+
+    /// Run the hooks for `context.point` and collect what they built
+    pub fn build(&self, context: &HookContext) -> Result<Vec<gst::Element>> {
+        // Hooks run unlocked so they may register or look up others
+        let hooks: Vec<(String, Hook)> = self
+            .hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|registered| registered.point == context.point)
+            .map(|registered| (registered.name.clone(), registered.hook.clone()))
+            .collect();
+
+        let mut elements = Vec::new();
+        for (name, hook) in hooks {
+            let built = catch_panic(|| hook(context)).unwrap_or_else(|panic| {
+                Err(DeepStreamError::ProcessingFailed {
+                    reason: format!("panicked: {}", panic),
+                })
+            });
+            match built {
+                Ok(Some(element)) => elements.push(element),
+                Ok(None) => {}
+                Err(e) => {
+                    return Err(DeepStreamError::Pipeline(format!(
+                        "{} hook '{}' failed: {}",
+                        context.point, name, e
+                    )));
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    /// Build the hooks for `context` into one bin with `sink` and `src`
+    /// ghost pads, or `None` if no hook contributed anything
+    pub fn build_bin(&self, context: &HookContext, name: &str) -> Result<Option<gst::Bin>> {
+        let elements = self.build(context)?;
+        let (Some(first), Some(last)) = (elements.first(), elements.last()) else {
+            return Ok(None);
+        };
+
+        let bin = gst::Bin::builder().name(name).build();
+        bin.add_many(&elements)?;
+        gst::Element::link_many(&elements)?;
+
+        let sink = first
+            .static_pad("sink")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: first.name().to_string(),
+                pad: "sink".to_string(),
+            })?;
+        let src = last
+            .static_pad("src")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: last.name().to_string(),
+                pad: "src".to_string(),
+            })?;
+        bin.add_pad(
+            &gst::GhostPad::builder_with_target(&sink)?
+                .name("sink")
+                .build(),
+        )?;
+        bin.add_pad(
+            &gst::GhostPad::builder_with_target(&src)?
+                .name("src")
+                .build(),
+        )?;
+        Ok(Some(bin))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn context() -> HookContext {
+        HookContext::for_source(SourceId(3), "file:///a.mp4")
+    }
+
+    #[test]
+    fn test_hook_registration() {
+        let hooks = ElementHooks::new();
+        hooks.register(HookPoint::PreMux, "count", |_| Ok(None));
+        hooks.register(HookPoint::PreSink, "sink", |_| Ok(None));
+        hooks.register(HookPoint::PreMux, "skip", |_| Ok(None));
+        assert_eq!(hooks.names(HookPoint::PreMux), ["count", "skip"]);
+        assert!(!hooks.has_hooks(HookPoint::PostInference));
+    }
+
+    #[test]
+    fn test_build_calls_hooks() {
+        let hooks = ElementHooks::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        hooks.register(HookPoint::PreMux, "count", move |context| {
+            assert_eq!(context.source, Some(SourceId(3)));
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        });
+        hooks.register(HookPoint::PreMux, "skip", |_| Ok(None));
+
+        assert!(hooks.build(&context()).unwrap().is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_failing_hook() {
+        let hooks = ElementHooks::new();
+        hooks.register(HookPoint::PreMux, "skip", |_| {
+            Err(DeepStreamError::Configuration("no plugin".to_string()))
+        });
+        assert!(hooks.build(&context()).is_err());
+        assert!(hooks.unregister("skip"));
+        assert!(!hooks.unregister("skip"));
+        assert!(hooks.build(&context()).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+//! Structured view of a running pipeline
+//!
+//! [`snapshot`] walks every element, including those inside bins, and
+//! records its state, its pads with their peers and negotiated caps, and
+//! the fill level of queues, together with the latency the pipeline
+//! reports. The result serializes to JSON for the status endpoint and
+//! prints as an indented listing.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineSnapshot {
+    pub name: String,
+    pub state: String,
+    pub pending: Option<String>,
+    /// `None` when the latency query fails, e.g. before prerolling
+    pub latency: Option<LatencyInfo>,
+    pub elements: Vec<ElementSnapshot>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LatencyInfo {
+    pub live: bool,
+    pub min_ns: u64,
+    /// `None` for unlimited
+    pub max_ns: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElementSnapshot {
+    pub name: String,
+    /// Position in the bin hierarchy, e.g. `/pipeline0/source-bin-00/decoder`
+    pub path: String,
+    pub factory: Option<String>,
+    pub state: String,
+    pub pending: Option<String>,
+    pub pads: Vec<PadSnapshot>,
+    pub queue: Option<QueueLevel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PadSnapshot {
+    pub name: String,
+    pub direction: &'static str,
+    /// `element:pad` this pad is linked to
+    pub peer: Option<String>,
+    /// Negotiated caps, once data has flowed
+    pub caps: Option<String>,
+}
+
+/// Current and maximum fill of a `queue` or `queue2`; a maximum of 0
+/// means no limit
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct QueueLevel {
+    pub buffers: u64,
+    pub max_buffers: u64,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub time_ns: u64,
+    pub max_time_ns: u64,
+}
+
+impl QueueLevel {
+    /// Fill of whichever limit is closest to being reached, 0 to 100
+    pub fn percent(&self) -> f64 {
+        [
+            (self.buffers, self.max_buffers),
+            (self.bytes, self.max_bytes),
+            (self.time_ns, self.max_time_ns),
+        ]
+        .iter()
+        .filter(|(_, max)| *max > 0)
+        .map(|(current, max)| *current as f64 * 100.0 / *max as f64)
+        .fold(0.0, f64::max)
+    }
+}
+
+impl PipelineSnapshot {
+    pub fn element(&self, name: &str) -> Option<&ElementSnapshot> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+    /// Elements not yet in the pipeline's state, or still changing state
+    pub fn lagging(&self) -> impl Iterator<Item = &ElementSnapshot> {
+        self.elements
+            .iter()
+            .filter(|element| element.state != self.state || element.pending.is_some())
+    }
+}
+
+fn state_name(state: gst::State) -> String {
+    format!("{:?}", state)
+}
+
+fn pending_name(pending: gst::State) -> Option<String> {
+    (pending != gst::State::VoidPending).then(|| state_name(pending))
+}
+
+/// Walk `pipeline` without waiting for state changes to finish
+pub fn snapshot(pipeline: &gst::Pipeline) -> PipelineSnapshot {
+    let (_, state, pending) = pipeline.state(gst::ClockTime::ZERO);
+
+    let mut query = gst::query::Latency::new();
+    let latency = pipeline.query(&mut query).then(|| {
+        let (live, min, max) = query.result();
+        LatencyInfo {
+            live,
+            min_ns: min.nseconds(),
+            max_ns: max.map(|max| max.nseconds()),
+        }
+    });
+
+    let mut elements = Vec::new();
+    let mut iter = pipeline.iterate_recurse();
+    loop {
+        match iter.next() {
+            Ok(Some(element)) => elements.push(element_snapshot(&element)),
+            Ok(None) => break,
+            // Elements were added or removed while walking
+            Err(gst::IteratorError::Resync) => {
+                elements.clear();
+                iter.resync();
+            }
+            Err(gst::IteratorError::Error) => break,
+        }
+    }
+    elements.sort_by(|a, b| a.path.cmp(&b.path));
+
+    PipelineSnapshot {
+        name: pipeline.name().to_string(),
+        state: state_name(state),
+        pending: pending_name(pending),
+        latency,
+        elements,
+    }
+}
+
+fn element_snapshot(element: &gst::Element) -> ElementSnapshot {
+    let (_, state, pending) = element.state(gst::ClockTime::ZERO);
+    let factory = element.factory().map(|factory| factory.name().to_string());
+    let queue = match factory.as_deref() {
+        Some("queue") | Some("queue2") => Some(queue_level(element)),
+        _ => None,
+    };
+
+    ElementSnapshot {
+        name: element.name().to_string(),
+        path: element.path_string().to_string(),
+        factory,
+        state: state_name(state),
+        pending: pending_name(pending),
+        pads: element.pads().iter().map(pad_snapshot).collect(),
+        queue,
+    }
+}
+
+fn pad_snapshot(pad: &gst::Pad) -> PadSnapshot {
+    let peer = pad.peer().map(|peer| {
+        let parent = peer
+            .parent()
+            .map(|parent| parent.name().to_string())
+            .unwrap_or_default();
+        format!("{}:{}", parent, peer.name())
+    });
+
+    PadSnapshot {
+        name: pad.name().to_string(),
+        direction: match pad.direction() {
+            gst::PadDirection::Src => "src",
+            gst::PadDirection::Sink => "sink",
+            _ => "unknown",
+        },
+        peer,
+        caps: pad.current_caps().map(|caps| caps.to_string()),
+    }
+}
+
+/// `queue` and `queue2` use different integer types for the same levels
+fn uint_property(element: &gst::Element, name: &str) -> u64 {
+    if element.find_property(name).is_none() {
+        return 0;
+    }
+    let value = element.property_value(name);
+    value
+        .get::<u32>()
+        .map(u64::from)
+        .or_else(|_| value.get::<u64>())
+        .unwrap_or(0)
+}
+
+fn queue_level(queue: &gst::Element) -> QueueLevel {
+    QueueLevel {
+        buffers: uint_property(queue, "current-level-buffers"),
+        max_buffers: uint_property(queue, "max-size-buffers"),
+        bytes: uint_property(queue, "current-level-bytes"),
+        max_bytes: uint_property(queue, "max-size-bytes"),
+        time_ns: uint_property(queue, "current-level-time"),
+        max_time_ns: uint_property(queue, "max-size-time"),
+    }
+}
+
+impl fmt::Display for PipelineSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.state)?;
+        if let Some(pending) = &self.pending {
+            write!(f, " -> {} (pending)", pending)?;
+        }
+        if let Some(latency) = &self.latency {
+            write!(
+                f,
+                ", latency {:.1} ms{}",
+                latency.min_ns as f64 / 1e6,
+                if latency.live { " (live)" } else { "" }
+            )?;
+        }
+        for element in &self.elements {
+            write!(f, "\n  {}", element.path)?;
+            if let Some(factory) = &element.factory {
+                write!(f, " ({})", factory)?;
+            }
+            write!(f, " : {}", element.state)?;
+            if let Some(pending) = &element.pending {
+                write!(f, " -> {} (pending)", pending)?;
+            }
+            if let Some(queue) = &element.queue {
+                write!(
+                    f,
+                    "\n    level {} buffers, {} bytes, {:.1}% full",
+                    queue.buffers,
+                    queue.bytes,
+                    queue.percent()
+                )?;
+            }
+            for pad in &element.pads {
+                write!(f, "\n    {} {}", pad.direction, pad.name)?;
+                if let Some(peer) = &pad.peer {
+                    write!(f, " <-> {}", peer)?;
+                }
+                if let Some(caps) = &pad.caps {
+                    write!(f, " [{}]", caps)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_walks_bins() {
+        let _ = gst::init();
+        let pipeline = gst::Pipeline::with_name("introspect");
+        let src = gst::ElementFactory::make("fakesrc")
+            .name("src")
+            .build()
+            .unwrap();
+        let bin = gst::Bin::with_name("inner");
+        let queue = gst::ElementFactory::make("queue")
+            .name("buffer")
+            .build()
+            .unwrap();
+        bin.add(&queue).unwrap();
+        pipeline.add_many([&src, bin.upcast_ref()]).unwrap();
+
+        let snapshot = snapshot(&pipeline);
+        assert_eq!(snapshot.state, "Null");
+        let queue = snapshot.element("buffer").unwrap();
+        assert_eq!(queue.path, "/introspect/inner/buffer");
+        assert_eq!(queue.queue.unwrap().max_buffers, 200);
+        assert_eq!(snapshot.element("src").unwrap().pads[0].direction, "src");
+        assert!(snapshot.to_string().contains("(queue)"));
+    }
+}
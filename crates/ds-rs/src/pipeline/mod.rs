@@ -1,5 +1,12 @@
+pub mod branch;
 pub mod builder;
 pub mod bus;
+pub mod deadline;
+pub mod description;
+pub mod hooks;
+pub mod introspect;
+pub mod replay;
+pub mod router;
 pub mod state;
 
 use crate::backend::BackendManager;
@@ -9,8 +16,17 @@ use gstreamer::prelude::*;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+pub use branch::{BranchKind, BranchManager};
 pub use builder::PipelineBuilder;
 pub use bus::{BusWatcher, MessageHandler};
+pub use deadline::{DeadlineConfig, DeadlineStats, FrameDeadline, QueueLeak};
+pub use description::{
+    BranchDescription, ElementDescription, LinkDescription, PipelineDescription,
+};
+pub use hooks::{ElementHooks, HookContext, HookPoint};
+pub use introspect::{ElementSnapshot, LatencyInfo, PadSnapshot, PipelineSnapshot, QueueLevel};
+pub use replay::{ReplayConfig, ReplayMode};
+pub use router::{ElementMessageHandler, MessageRouter, SourceMessageHandler};
 pub use state::{PipelineState, StateManager};
 
 /// Main pipeline struct that wraps GStreamer pipeline with additional management
@@ -212,6 +228,12 @@ impl Pipeline {
         }
     }
 
+    /// Watch the bus through `router`, which dispatches each message to
+    /// the handlers of its source, element or the whole pipeline
+    pub fn start_routed_bus_watch(&mut self, router: Arc<MessageRouter>) -> Result<()> {
+        self.start_bus_watch(move |bus, msg| router.route(bus, msg))
+    }
+
     /// Stop watching the bus
     pub fn stop_bus_watch(&mut self) {
         self.bus_watcher = None;
@@ -273,6 +295,17 @@ impl Pipeline {
             })
     }
 
+    /// Run on a test clock with unsynchronized sinks and bounded test
+    /// sources; call before starting the pipeline
+    pub fn enable_replay(&self, config: ReplayConfig) -> ReplayMode {
+        ReplayMode::enable(&self.gst_pipeline, config)
+    }
+
+    /// Elements, pads, caps, queue levels and latency as they are now
+    pub fn snapshot(&self) -> introspect::PipelineSnapshot {
+        introspect::snapshot(&self.gst_pipeline)
+    }
+
     /// Get the duration of the pipeline
     pub fn duration(&self) -> Result<Duration> {
         self.gst_pipeline
@@ -0,0 +1,200 @@
+//! Deterministic replay for tests
+//!
+//! In replay mode a pipeline runs on a clock that only moves when told to
+//! (a system clock calibrated to a rate of zero), every sink renders without waiting for the clock (`sync=false`)
+//! and video test sources stop after a fixed number of buffers. Buffers
+//! are then processed as fast as the pipeline can go, in the same order
+//! and with the same timestamps on every run, so detection and tracking
+//! output can be compared between runs.
+//!
+//! Elements added later, such as runtime sources and their decoders, are
+//! configured as they join the pipeline.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayConfig {
+    /// Buffers each video source produces before EOS; `None` leaves them
+    /// unbounded
+    pub num_buffers: Option<i32>,
+    /// Turn live test sources into non-live ones
+    pub disable_live: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            num_buffers: Some(300),
+            disable_live: true,
+        }
+    }
+}
+
+/// Handle on a pipeline in replay mode
+pub struct ReplayMode {
+    clock: gst::Clock,
+    config: ReplayConfig,
+    handler: Option<(gst::Pipeline, gst::glib::SignalHandlerId)>,
+}
+
+impl ReplayMode {
+    /// Put `pipeline` in replay mode; call before it leaves `Null`
+    pub fn enable(pipeline: &gst::Pipeline, config: ReplayConfig) -> Self {
+        let clock = gst::glib::Object::builder::<gst::SystemClock>()
+            .property("name", "replay-clock")
+            .build()
+            .upcast::<gst::Clock>();
+        freeze_at(&clock, gst::ClockTime::ZERO);
+        pipeline.use_clock(Some(&clock));
+        // Running time starts at zero on every run
+        pipeline.set_start_time(gst::ClockTime::NONE);
+        pipeline.set_base_time(gst::ClockTime::ZERO);
+
+        let mut iter = pipeline.iterate_recurse();
+        while let Ok(Some(element)) = iter.next() {
+            configure_element(&element, &config);
+        }
+        let handler = pipeline.connect_deep_element_added(move |_, _, element| {
+            configure_element(element, &config);
+        });
+
+        log::debug!("Pipeline {} in replay mode: {:?}", pipeline.name(), config);
+        Self {
+            clock,
+            config,
+            handler: Some((pipeline.clone(), handler)),
+        }
+    }
+
+    pub fn config(&self) -> &ReplayConfig {
+        &self.config
+    }
+
+    /// The pipeline's clock, for waiting on pending clock ids in tests
+    pub fn clock(&self) -> &gst::Clock {
+        &self.clock
+    }
+
+    /// Move the clock forward by `delta`
+    pub fn advance(&self, delta: Duration) {
+        let delta = gst::ClockTime::from_nseconds(delta.as_nanos() as u64);
+        freeze_at(&self.clock, self.clock.time() + delta);
+    }
+
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.clock.time().nseconds())
+    }
+}
+
+impl Drop for ReplayMode {
+    fn drop(&mut self) {
+        // Later elements keep their defaults; the clock stays until the
+        // pipeline picks a new one
+        if let Some((pipeline, handler)) = self.handler.take() {
+            pipeline.disconnect(handler);
+        }
+    }
+}
+
+/// Stop `clock` at `time`: with a rate of zero its time no longer follows
+/// the internal time it is calibrated against
+fn freeze_at(clock: &gst::Clock, time: gst::ClockTime) {
+    clock.set_calibration(
+        clock.internal_time(),
+        time,
+        gst::ClockTime::ZERO,
+        gst::ClockTime::from_nseconds(1),
+    );
+}
+
+fn has_property(element: &gst::Element, name: &str) -> bool {
+    element.find_property(name).is_some()
+}
+
+fn configure_element(element: &gst::Element, config: &ReplayConfig) {
+    let klass = element
+        .factory()
+        .map(|factory| factory.klass().to_string())
+        .unwrap_or_default();
+
+    if klass.contains("Sink") {
+        if has_property(element, "sync") {
+            element.set_property("sync", false);
+        }
+        if has_property(element, "qos") {
+            element.set_property("qos", false);
+        }
+    }
+
+    // File and network sources count blocks and packets, not frames, so
+    // only generated video is bounded
+    if klass.contains("Source") && klass.contains("Video") {
+        if let Some(num_buffers) = config.num_buffers
+            && has_property(element, "num-buffers")
+        {
+            element.set_property("num-buffers", num_buffers);
+        }
+        if config.disable_live && has_property(element, "is-live") {
+            element.set_property("is-live", false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_runs_to_eos_without_clock() {
+        let _ = gst::init();
+        let pipeline = gst::Pipeline::with_name("replay");
+        let src = gst::ElementFactory::make("videotestsrc")
+            .property("is-live", true)
+            .build()
+            .unwrap();
+        pipeline.add(&src).unwrap();
+
+        let replay = ReplayMode::enable(
+            &pipeline,
+            ReplayConfig {
+                num_buffers: Some(5),
+                disable_live: true,
+            },
+        );
+        // Added after enabling, like a source added at runtime
+        let sink = gst::ElementFactory::make("fakesink").build().unwrap();
+        pipeline.add(&sink).unwrap();
+        src.link(&sink).unwrap();
+        assert!(!sink.property::<bool>("sync"));
+        assert!(!src.property::<bool>("is-live"));
+
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let bus = pipeline.bus().unwrap();
+        let msg = bus
+            .timed_pop_filtered(
+                gst::ClockTime::from_seconds(5),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            )
+            .unwrap();
+        assert_eq!(msg.type_(), gst::MessageType::Eos);
+        // Nothing waited on the clock
+        assert_eq!(replay.now(), Duration::ZERO);
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[test]
+    fn test_clock_only_moves_when_advanced() {
+        let _ = gst::init();
+        let pipeline = gst::Pipeline::with_name("replay-clock-test");
+        let replay = ReplayMode::enable(&pipeline, ReplayConfig::default());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(replay.now(), Duration::ZERO);
+
+        replay.advance(Duration::from_millis(40));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(replay.now(), Duration::from_millis(40));
+    }
+}
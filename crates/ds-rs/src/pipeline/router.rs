@@ -0,0 +1,344 @@
+//! Bus messages dispatched to whoever owns their source
+//!
+//! One bus watch calls [`MessageRouter::route`] and the router works out
+//! where each message came from: a source (anything inside a
+//! `source-bin-NN`, or a `stream-eos` message naming the stream), named
+//! elements and the bins around them, and the pipeline as a whole. Source
+//! handlers get typed callbacks, so the source manager, recovery and the
+//! application no longer each parse the same bus.
+//!
+//! A handler that panics is logged and skipped; the source's other
+//! handlers are told through `on_error`.
+
+use super::bus::MessageHandler;
+use crate::error::DeepStreamError;
+use crate::messages::DSMessageHandler;
+use crate::source::SourceId;
+use crate::source::isolation::catch_panic;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Messages from one source; every method defaults to doing nothing
+pub trait SourceMessageHandler: Send + Sync {
+    fn on_eos(&self, _id: SourceId) {}
+
+    /// Errors arrive sorted into the typed variants where they fit, with
+    /// the posting element
+    fn on_error(&self, _id: SourceId, _error: &DeepStreamError, _debug: Option<&str>) {}
+
+    fn on_warning(&self, _id: SourceId, _warning: &gst::glib::Error, _debug: Option<&str>) {}
+
+    /// State changes of the source bin itself, not its children
+    fn on_state_changed(&self, _id: SourceId, _old: gst::State, _new: gst::State) {}
+
+    /// Every message from the source, before the typed callbacks
+    fn on_message(&self, _id: SourceId, _msg: &gst::Message) {}
+}
+
+/// Messages from a named element or anything inside it
+pub trait ElementMessageHandler: Send + Sync {
+    fn on_message(&self, element: &str, msg: &gst::Message);
+}
+
+#[derive(Default)]
+pub struct MessageRouter {
+    sources: RwLock<HashMap<SourceId, Vec<Arc<dyn SourceMessageHandler>>>>,
+    all_sources: RwLock<Vec<Arc<dyn SourceMessageHandler>>>,
+    elements: RwLock<HashMap<String, Vec<Arc<dyn ElementMessageHandler>>>>,
+    pipeline: RwLock<Vec<Arc<dyn MessageHandler>>>,
+}
+
+impl MessageRouter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Handle messages from source `id` only
+    pub fn on_source(&self, id: SourceId, handler: Arc<dyn SourceMessageHandler>) {
+        self.sources
+            .write()
+            .unwrap()
+            .entry(id)
+            .or_default()
+            .push(handler);
+    }
+
+    /// Handle messages from every source
+    pub fn on_all_sources(&self, handler: Arc<dyn SourceMessageHandler>) {
+        self.all_sources.write().unwrap().push(handler);
+    }
+
+    /// Handle messages from the element called `name` and its children
+    pub fn on_element(&self, name: &str, handler: Arc<dyn ElementMessageHandler>) {
+        self.elements
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .push(handler);
+    }
+
+    /// Handle every message, whatever its source
+    pub fn on_pipeline(&self, handler: Arc<dyn MessageHandler>) {
+        self.pipeline.write().unwrap().push(handler);
+    }
+
+    /// Drop the handlers registered for source `id`, e.g. once it is removed
+    pub fn remove_source(&self, id: SourceId) {
+        self.sources.write().unwrap().remove(&id);
+    }
+
+    pub fn remove_element(&self, name: &str) {
+        self.elements.write().unwrap().remove(name);
+    }
+
+    /// Source a message belongs to, if any
+    pub fn source_of(msg: &gst::Message) -> Option<SourceId> {
+        DSMessageHandler::parse_stream_eos(msg)
+            .ok()
+            .or_else(|| DSMessageHandler::stream_id(msg))
+            .map(|id| SourceId(id as usize))
+    }
+
+    /// Dispatch `msg` to every handler whose scope it falls in
+    pub fn route(&self, bus: &gst::Bus, msg: &gst::Message) -> gst::BusSyncReply {
+        if let Some(id) = Self::source_of(msg) {
+            self.route_source(id, msg);
+        }
+        self.route_elements(msg);
+
+        // Handlers run unlocked so they may register others
+        let pipeline: Vec<_> = self.pipeline.read().unwrap().clone();
+        let mut reply = gst::BusSyncReply::Pass;
+        for handler in pipeline {
+            match catch_panic(|| handler.handle_message(bus, msg)) {
+                Ok(gst::BusSyncReply::Drop) => reply = gst::BusSyncReply::Drop,
+                Ok(_) => {}
+                Err(panic) => log::error!("Pipeline message handler panicked: {}", panic),
+            }
+        }
+        reply
+    }
+
+    fn route_source(&self, id: SourceId, msg: &gst::Message) {
+        let mut handlers: Vec<_> = self.all_sources.read().unwrap().clone();
+        if let Some(own) = self.sources.read().unwrap().get(&id) {
+            handlers.extend(own.iter().cloned());
+        }
+        if handlers.is_empty() {
+            return;
+        }
+
+        let error = match msg.view() {
+            gst::MessageView::Error(err) => {
+                Some((DeepStreamError::from_gst_message(err), err.debug()))
+            }
+            _ => None,
+        };
+        let deliver = |handler: &Arc<dyn SourceMessageHandler>| {
+            handler.on_message(id, msg);
+            match msg.view() {
+                gst::MessageView::Element(_) if DSMessageHandler::is_stream_eos(msg) => {
+                    handler.on_eos(id);
+                }
+                gst::MessageView::Warning(warning) => {
+                    handler.on_warning(id, &warning.error(), warning.debug().as_deref());
+                }
+                gst::MessageView::StateChanged(state) if is_source_bin(msg) => {
+                    handler.on_state_changed(id, state.old(), state.current());
+                }
+                gst::MessageView::Error(_) => {
+                    if let Some((error, debug)) = &error {
+                        handler.on_error(id, error, debug.as_deref());
+                    }
+                }
+                _ => {}
+            }
+        };
+
+        let mut panicked = Vec::new();
+        for (index, handler) in handlers.iter().enumerate() {
+            if let Err(panic) = catch_panic(|| deliver(handler)) {
+                log::error!("Message handler for source {} panicked: {}", id, panic);
+                panicked.push((index, panic));
+            }
+        }
+
+        // The other handlers of the source hear about it as an error
+        for (culprit, panic) in panicked {
+            let error = DeepStreamError::ProcessingFailed {
+                reason: format!("Message handler panicked: {}", panic),
+            };
+            for (index, handler) in handlers.iter().enumerate() {
+                if index != culprit {
+                    let _ = catch_panic(|| handler.on_error(id, &error, None));
+                }
+            }
+        }
+    }
+
+    fn route_elements(&self, msg: &gst::Message) {
+        let elements = self.elements.read().unwrap();
+        if elements.is_empty() {
+            return;
+        }
+        let mut matched = Vec::new();
+        let mut object = msg.src().cloned();
+        while let Some(current) = object {
+            if let Some(handlers) = elements.get(current.name().as_str()) {
+                matched.extend(
+                    handlers
+                        .iter()
+                        .map(|handler| (current.name().to_string(), handler.clone())),
+                );
+            }
+            object = current.parent();
+        }
+        drop(elements);
+
+        for (name, handler) in matched {
+            if let Err(panic) = catch_panic(|| handler.on_message(&name, msg)) {
+                log::error!("Message handler for {} panicked: {}", name, panic);
+            }
+        }
+    }
+}
+
+/// The message comes from the source bin, not an element inside it
+fn is_source_bin(msg: &gst::Message) -> bool {
+    msg.src().is_some_and(|src| {
+        src.parent()
+            .is_none_or(|parent| DSMessageHandler::stream_id_of_object(&parent).is_none())
+    })
+}
+
+impl MessageHandler for MessageRouter {
+    fn handle_message(&self, bus: &gst::Bus, msg: &gst::Message) -> gst::BusSyncReply {
+        self.route(bus, msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl SourceMessageHandler for Recorder {
+        fn on_eos(&self, id: SourceId) {
+            self.seen.lock().unwrap().push(format!("eos {}", id.0));
+        }
+
+        fn on_error(&self, id: SourceId, error: &DeepStreamError, _debug: Option<&str>) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("error {} {}", id.0, error));
+        }
+    }
+
+    impl ElementMessageHandler for Recorder {
+        fn on_message(&self, element: &str, msg: &gst::Message) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("{} {:?}", element, msg.type_()));
+        }
+    }
+
+    struct Fixture {
+        bus: gst::Bus,
+        source_bin: gst::Bin,
+        decoder: gst::Element,
+        sink: gst::Element,
+        _pipeline: gst::Pipeline,
+    }
+
+    /// A pipeline with `source-bin-07` holding a decoder, next to a sink
+    fn fixture() -> Fixture {
+        let _ = gst::init();
+        let pipeline = gst::Pipeline::new();
+        let source_bin = gst::Bin::with_name("source-bin-07");
+        let decoder = gst::ElementFactory::make("identity")
+            .name("decoder")
+            .build()
+            .unwrap();
+        source_bin.add(&decoder).unwrap();
+        let sink = gst::ElementFactory::make("fakesink")
+            .name("sink")
+            .build()
+            .unwrap();
+        pipeline.add_many([source_bin.upcast_ref(), &sink]).unwrap();
+
+        Fixture {
+            bus: pipeline.bus().unwrap(),
+            source_bin,
+            decoder,
+            sink,
+            _pipeline: pipeline,
+        }
+    }
+
+    fn decode_error(fixture: &Fixture) -> gst::Message {
+        gst::message::Error::builder(gst::StreamError::Decode, "no data")
+            .src(&fixture.decoder)
+            .build()
+    }
+
+    #[test]
+    fn test_route_by_source() {
+        let fixture = fixture();
+        let router = MessageRouter::new();
+        let seventh = Arc::new(Recorder::default());
+        let other = Arc::new(Recorder::default());
+        router.on_source(SourceId(7), seventh.clone());
+        router.on_source(SourceId(8), other.clone());
+
+        router.route(&fixture.bus, &decode_error(&fixture));
+        let eos = crate::messages::stream_eos_message(fixture.source_bin.upcast_ref(), 7);
+        router.route(&fixture.bus, &eos);
+
+        assert_eq!(
+            *seventh.seen.lock().unwrap(),
+            ["error 7 Decode error in decoder: no data", "eos 7"]
+        );
+        assert!(other.seen.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_route_by_element() {
+        let fixture = fixture();
+        let router = MessageRouter::new();
+        let elements = Arc::new(Recorder::default());
+        router.on_element("source-bin-07", elements.clone());
+
+        // Messages from inside the bin count, the sink's do not
+        router.route(&fixture.bus, &decode_error(&fixture));
+        let eos = crate::messages::stream_eos_message(fixture.source_bin.upcast_ref(), 7);
+        router.route(&fixture.bus, &eos);
+        router.route(
+            &fixture.bus,
+            &gst::message::Eos::builder().src(&fixture.sink).build(),
+        );
+
+        assert_eq!(elements.seen.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_removed_source_not_routed() {
+        let fixture = fixture();
+        let router = MessageRouter::new();
+        let seventh = Arc::new(Recorder::default());
+        router.on_source(SourceId(7), seventh.clone());
+
+        router.remove_source(SourceId(7));
+        router.route(&fixture.bus, &decode_error(&fixture));
+        assert!(seventh.seen.lock().unwrap().is_empty());
+    }
+}
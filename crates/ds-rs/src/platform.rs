@@ -1,15 +1,16 @@
 use crate::error::Result;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::path::Path;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Platform {
     Jetson,
     X86,
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlatformInfo {
     pub platform: Platform,
     pub cuda_version: Option<String>,
@@ -65,6 +66,29 @@ impl PlatformInfo {
     }
 }
 
+/// CUDA device indices of the GPUs on this machine, empty without any
+pub fn available_gpus() -> Vec<u32> {
+    // Jetson has a single integrated GPU and no /dev/nvidiaN nodes
+    if detect_platform() == Platform::Jetson {
+        return vec![0];
+    }
+
+    if let Ok(output) = std::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=index", "--format=csv,noheader"])
+        .output()
+        && output.status.success()
+    {
+        return String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect();
+    }
+
+    (0..)
+        .take_while(|n| Path::new(&format!("/dev/nvidia{}", n)).exists())
+        .collect()
+}
+
 fn detect_platform() -> Platform {
     // Check for Jetson-specific files
     if Path::new("/etc/nv_tegra_release").exists()
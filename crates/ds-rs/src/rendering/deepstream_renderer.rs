@@ -5,6 +5,7 @@ use super::{BoundingBoxRenderer, PerformanceMetrics, RenderingConfig};
 use crate::error::{DeepStreamError, Result};
 use crate::metadata::object::ObjectMeta;
 use crate::rendering::metadata_bridge::MetadataBridge;
+use crate::source::isolation::PanicGuard;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::sync::{Arc, Mutex};
@@ -47,29 +48,32 @@ impl DeepStreamRenderer {
         let metrics_clone = metrics.clone();
         let config_clone = config.clone();
 
-        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |_pad, info| {
-            if let Some(buffer) = info.buffer() {
-                let start = Instant::now();
+        sink_pad.add_probe(
+            gst::PadProbeType::BUFFER,
+            PanicGuard::new("OSD metadata probe").probe(move |_pad, info| {
+                if let Some(buffer) = info.buffer() {
+                    let start = Instant::now();
 
-                // Process metadata on the buffer
-                if let Err(e) = process_buffer_metadata(buffer, &config_clone, &metrics_clone) {
-                    log::error!("Failed to process buffer metadata: {}", e);
-                }
+                    // Process metadata on the buffer
+                    if let Err(e) = process_buffer_metadata(buffer, &config_clone, &metrics_clone) {
+                        log::error!("Failed to process buffer metadata: {}", e);
+                    }
 
-                // Update metrics
-                let elapsed = start.elapsed().as_millis() as f64;
-                if let Ok(mut m) = metrics_clone.lock() {
-                    m.frames_rendered += 1;
-                    m.avg_render_time_ms = (m.avg_render_time_ms * (m.frames_rendered - 1) as f64
-                        + elapsed)
-                        / m.frames_rendered as f64;
-                    if elapsed > m.peak_render_time_ms {
-                        m.peak_render_time_ms = elapsed;
+                    // Update metrics
+                    let elapsed = start.elapsed().as_millis() as f64;
+                    if let Ok(mut m) = metrics_clone.lock() {
+                        m.frames_rendered += 1;
+                        m.avg_render_time_ms =
+                            (m.avg_render_time_ms * (m.frames_rendered - 1) as f64 + elapsed)
+                                / m.frames_rendered as f64;
+                        if elapsed > m.peak_render_time_ms {
+                            m.peak_render_time_ms = elapsed;
+                        }
                     }
                 }
-            }
-            gst::PadProbeReturn::Ok
-        });
+                gst::PadProbeReturn::Ok
+            }),
+        );
 
         log::info!("DeepStream renderer created with nvdsosd");
 
@@ -151,21 +155,25 @@ impl BoundingBoxRenderer for DeepStreamRenderer {
 
         let config_clone = self.config.clone();
 
-        src_pad.add_probe(gst::PadProbeType::BUFFER, move |_pad, info| {
-            if let Some(buffer) = info.buffer_mut() {
-                // Get current objects from bridge
-                if let Ok(bridge_guard) = bridge.lock() {
-                    if let Some((objects, _timestamp)) = bridge_guard.get_current_objects() {
-                        // Inject DeepStream metadata
-                        if let Err(e) = inject_deepstream_metadata(buffer, &objects, &config_clone)
-                        {
-                            log::error!("Failed to inject metadata: {}", e);
+        src_pad.add_probe(
+            gst::PadProbeType::BUFFER,
+            PanicGuard::new("OSD metadata injection").probe(move |_pad, info| {
+                if let Some(buffer) = info.buffer_mut() {
+                    // Get current objects from bridge
+                    if let Ok(bridge_guard) = bridge.lock() {
+                        if let Some((objects, _timestamp)) = bridge_guard.get_current_objects() {
+                            // Inject DeepStream metadata
+                            if let Err(e) =
+                                inject_deepstream_metadata(buffer, &objects, &config_clone)
+                            {
+                                log::error!("Failed to inject metadata: {}", e);
+                            }
                         }
                     }
                 }
-            }
-            gst::PadProbeReturn::Ok
-        });
+                gst::PadProbeReturn::Ok
+            }),
+        );
 
         log::info!("DeepStream renderer connected to metadata source");
         Ok(())
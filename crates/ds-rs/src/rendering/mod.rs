@@ -56,6 +56,12 @@ pub struct PerformanceMetrics {
     pub objects_rendered: u64,
     /// Frames dropped due to performance
     pub frames_dropped: u64,
+    /// Frames dropped for missing their deadline
+    pub frames_dropped_late: u64,
+    /// Buffers leaked by full queues
+    pub queue_drops: u64,
+    /// Worst lateness seen at a deadline probe
+    pub max_lateness_ms: f64,
 }
 
 /// Factory for creating backend-specific renderers
@@ -4,7 +4,9 @@
 use super::{BoundingBoxRenderer, PerformanceMetrics, RenderingConfig};
 use crate::error::{DeepStreamError, Result};
 use crate::metadata::object::ObjectMeta;
+use crate::pipeline::FrameDeadline;
 use crate::rendering::metadata_bridge::MetadataBridge;
+use crate::source::isolation::PanicGuard;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::sync::{Arc, Mutex, RwLock};
@@ -28,6 +30,7 @@ pub struct StandardRenderer {
     metadata_bridge: Option<Arc<Mutex<MetadataBridge>>>,
     frame_data: Arc<RwLock<FrameData>>,
     use_cairo: bool,
+    deadline: Option<FrameDeadline>,
 }
 
 impl StandardRenderer {
@@ -110,23 +113,26 @@ impl StandardRenderer {
         // Set up probe to extract video dimensions
         let frame_data_clone = frame_data.clone();
 
-        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
-            if let Some(_buffer) = info.buffer() {
-                // Extract caps to get video dimensions
-                if let Some(caps) = pad.current_caps() {
-                    if let Some(structure) = caps.structure(0) {
-                        let width = structure.get::<i32>("width").unwrap_or(1920) as u32;
-                        let height = structure.get::<i32>("height").unwrap_or(1080) as u32;
-
-                        if let Ok(mut data) = frame_data_clone.write() {
-                            data.width = width;
-                            data.height = height;
+        sink_pad.add_probe(
+            gst::PadProbeType::BUFFER,
+            PanicGuard::new("renderer caps probe").probe(move |pad, info| {
+                if let Some(_buffer) = info.buffer() {
+                    // Extract caps to get video dimensions
+                    if let Some(caps) = pad.current_caps() {
+                        if let Some(structure) = caps.structure(0) {
+                            let width = structure.get::<i32>("width").unwrap_or(1920) as u32;
+                            let height = structure.get::<i32>("height").unwrap_or(1080) as u32;
+
+                            if let Ok(mut data) = frame_data_clone.write() {
+                                data.width = width;
+                                data.height = height;
+                            }
                         }
                     }
                 }
-            }
-            gst::PadProbeReturn::Ok
-        });
+                gst::PadProbeReturn::Ok
+            }),
+        );
 
         log::info!(
             "Standard renderer created with {} overlay",
@@ -141,8 +147,23 @@ impl StandardRenderer {
             metadata_bridge: None,
             frame_data,
             use_cairo,
+            deadline: None,
         })
     }
+
+    /// Drop frames that reach the renderer later than the deadline allows
+    pub fn set_frame_deadline(&mut self, deadline: FrameDeadline) -> Result<()> {
+        let sink_pad = self
+            .bin
+            .static_pad("sink")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: self.bin.name().to_string(),
+                pad: "sink".to_string(),
+            })?;
+        deadline.attach(&sink_pad)?;
+        self.deadline = Some(deadline);
+        Ok(())
+    }
 }
 
 impl BoundingBoxRenderer for StandardRenderer {
@@ -210,28 +231,35 @@ impl BoundingBoxRenderer for StandardRenderer {
 
         let frame_data_clone = self.frame_data.clone();
 
-        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |_pad, info| {
-            if let Some(_buffer) = info.buffer() {
-                // Get current objects from bridge
-                if let Ok(bridge_guard) = bridge.lock() {
-                    if let Some((objects, timestamp)) = bridge_guard.get_current_objects() {
-                        // Update frame data
-                        if let Ok(mut data) = frame_data_clone.write() {
-                            data.objects = objects;
-                            data.timestamp = Some(timestamp);
+        sink_pad.add_probe(
+            gst::PadProbeType::BUFFER,
+            PanicGuard::new("renderer metadata probe").probe(move |_pad, info| {
+                if let Some(_buffer) = info.buffer() {
+                    // Get current objects from bridge
+                    if let Ok(bridge_guard) = bridge.lock() {
+                        if let Some((objects, timestamp)) = bridge_guard.get_current_objects() {
+                            // Update frame data
+                            if let Ok(mut data) = frame_data_clone.write() {
+                                data.objects = objects;
+                                data.timestamp = Some(timestamp);
+                            }
                         }
                     }
                 }
-            }
-            gst::PadProbeReturn::Ok
-        });
+                gst::PadProbeReturn::Ok
+            }),
+        );
 
         log::info!("Standard renderer connected to metadata source");
         Ok(())
     }
 
     fn get_performance_metrics(&self) -> PerformanceMetrics {
-        self.metrics.lock().unwrap().clone()
+        let mut metrics = self.metrics.lock().unwrap().clone();
+        if let Some(deadline) = &self.deadline {
+            deadline.apply_to(&mut metrics);
+        }
+        metrics
     }
 
     fn clear(&mut self) -> Result<()> {
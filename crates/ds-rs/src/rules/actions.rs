@@ -0,0 +1,387 @@
+//! Actions run when a rule fires
+
+use super::transport::MqttPublisher;
+use super::webhook::{WebhookConfig, WebhookQueue, WebhookSender};
+use super::{Result, RuleAlert, RuleError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One action attached to a rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionConfig {
+    /// Write the alert to the log
+    Log {
+        #[serde(default)]
+        message: Option<String>,
+    },
+
+    /// POST the alert as JSON, signed and retried as configured. Delivery
+    /// runs in the background.
+    Webhook(WebhookConfig),
+
+    /// Publish the alert as JSON to an MQTT broker at `host[:port]` (QoS 0)
+    Mqtt {
+        broker: String,
+        topic: String,
+        #[serde(default = "default_client_id")]
+        client_id: String,
+        #[serde(default = "default_timeout_ms")]
+        timeout_ms: u64,
+    },
+
+    /// Record the source that triggered the rule
+    StartRecording {
+        #[serde(default)]
+        duration_secs: Option<f64>,
+    },
+
+    /// Save a still of the frame that triggered the rule
+    Snapshot {
+        #[serde(default)]
+        directory: Option<String>,
+    },
+
+    /// Handled by whatever was registered under `name`
+    Custom {
+        name: String,
+        #[serde(default)]
+        params: HashMap<String, String>,
+    },
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_client_id() -> String {
+    "ds-rs".to_string()
+}
+
+impl ActionConfig {
+    /// Name the action is registered under in an [`ActionRegistry`]
+    pub fn kind(&self) -> &str {
+        match self {
+            ActionConfig::Log { .. } => "log",
+            ActionConfig::Webhook(_) => "webhook",
+            ActionConfig::Mqtt { .. } => "mqtt",
+            ActionConfig::StartRecording { .. } => "start_recording",
+            ActionConfig::Snapshot { .. } => "snapshot",
+            ActionConfig::Custom { name, .. } => name,
+        }
+    }
+}
+
+/// Executes one kind of action
+pub trait ActionHandler: Send + Sync {
+    fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()>;
+}
+
+impl<F> ActionHandler for F
+where
+    F: Fn(&ActionConfig, &RuleAlert) -> Result<()> + Send + Sync,
+{
+    fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()> {
+        self(action, alert)
+    }
+}
+
+fn alert_json(alert: &RuleAlert) -> Result<Vec<u8>> {
+    serde_json::to_vec(alert).map_err(|e| RuleError::ActionFailed {
+        action: "serialize".to_string(),
+        reason: e.to_string(),
+    })
+}
+
+struct LogHandler;
+
+impl ActionHandler for LogHandler {
+    fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()> {
+        let message = match action {
+            ActionConfig::Log {
+                message: Some(message),
+            } => message.as_str(),
+            _ => "rule fired",
+        };
+        log::info!(
+            "[{}] {} (source {}, {} objects, tracks {:?})",
+            alert.rule,
+            message,
+            alert.source_id,
+            alert.count,
+            alert.track_ids
+        );
+        Ok(())
+    }
+}
+
+/// Queues alerts on one background sender per webhook config, so retries
+/// and backoff never hold up rule evaluation
+#[derive(Default)]
+struct WebhookHandler {
+    queues: Mutex<Vec<(WebhookConfig, WebhookQueue)>>,
+}
+
+impl ActionHandler for WebhookHandler {
+    fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()> {
+        let ActionConfig::Webhook(config) = action else {
+            return Err(RuleError::NoHandler(action.kind().to_string()));
+        };
+        let body = alert_json(alert)?;
+
+        let mut queues = self.queues.lock().unwrap();
+        if let Some((_, queue)) = queues.iter().find(|(c, _)| c == config) {
+            return queue.enqueue(body);
+        }
+        let queue = WebhookSender::new(config.clone()).spawn();
+        queue.enqueue(body)?;
+        queues.push((config.clone(), queue));
+        Ok(())
+    }
+}
+
+/// Keeps one connection per broker and client id, so publishing only
+/// queues the message
+#[derive(Default)]
+struct MqttHandler {
+    publishers: Mutex<HashMap<(String, String), MqttPublisher>>,
+}
+
+impl ActionHandler for MqttHandler {
+    fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()> {
+        let ActionConfig::Mqtt {
+            broker,
+            topic,
+            client_id,
+            timeout_ms,
+        } = action
+        else {
+            return Err(RuleError::NoHandler(action.kind().to_string()));
+        };
+
+        let payload = alert_json(alert)?;
+        let mut publishers = self.publishers.lock().unwrap();
+        let key = (broker.clone(), client_id.clone());
+        if !publishers.contains_key(&key) {
+            let publisher =
+                MqttPublisher::connect(broker, client_id, Duration::from_millis(*timeout_ms))?;
+            publishers.insert(key.clone(), publisher);
+        }
+        publishers[&key].publish(topic, &payload)
+    }
+}
+
+/// Action handlers keyed by [`ActionConfig::kind`]. Log, webhook and MQTT
+/// are built in; recording, snapshots and custom actions need the
+/// application to register a handler since they act on the pipeline.
+#[derive(Clone)]
+pub struct ActionRegistry {
+    handlers: HashMap<String, Arc<dyn ActionHandler>>,
+}
+
+impl ActionRegistry {
+    /// A registry without any handlers
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, kind: impl Into<String>, handler: impl ActionHandler + 'static) {
+        self.handlers.insert(kind.into(), Arc::new(handler));
+    }
+
+    pub fn has_handler(&self, kind: &str) -> bool {
+        self.handlers.contains_key(kind)
+    }
+
+    pub fn execute(&self, action: &ActionConfig, alert: &RuleAlert) -> Result<()> {
+        self.handlers
+            .get(action.kind())
+            .ok_or_else(|| RuleError::NoHandler(action.kind().to_string()))?
+            .execute(action, alert)
+    }
+}
+
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("log", LogHandler);
+        registry.register("webhook", WebhookHandler::default());
+        registry.register("mqtt", MqttHandler::default());
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn alert() -> RuleAlert {
+        RuleAlert {
+            rule: "crowd".to_string(),
+            source_id: 2,
+            timestamp: 42,
+            count: 3,
+            track_ids: vec![1, 2, 3],
+            zone: Some("A".to_string()),
+            suppressed: 0,
+        }
+    }
+
+    #[test]
+    fn test_action_config_parsing() {
+        #[derive(Deserialize)]
+        struct Actions {
+            actions: Vec<ActionConfig>,
+        }
+
+        let parsed: Actions = toml::from_str(
+            r#"
+            actions = [
+                { type = "log", message = "crowd" },
+                { type = "mqtt", broker = "localhost:1883", topic = "alerts" },
+                { type = "start_recording", duration_secs = 30.0 },
+                { type = "custom", name = "siren", params = { volume = "11" } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let kinds: Vec<&str> = parsed.actions.iter().map(ActionConfig::kind).collect();
+        assert_eq!(kinds, vec!["log", "mqtt", "start_recording", "siren"]);
+    }
+
+    #[test]
+    fn test_registry_dispatch() {
+        let mut registry = ActionRegistry::default();
+        let recording = ActionConfig::StartRecording {
+            duration_secs: None,
+        };
+        assert!(matches!(
+            registry.execute(&recording, &alert()),
+            Err(RuleError::NoHandler(_))
+        ));
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        registry.register(
+            "start_recording",
+            move |_: &ActionConfig, alert: &RuleAlert| -> Result<()> {
+                seen.lock().unwrap().push(alert.source_id);
+                Ok(())
+            },
+        );
+
+        assert!(registry.execute(&recording, &alert()).is_ok());
+        assert!(
+            registry
+                .execute(&ActionConfig::Log { message: None }, &alert())
+                .is_ok()
+        );
+        assert_eq!(*calls.lock().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_webhook_posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                // Header names arrive lowercased
+                head.push(if head.is_empty() {
+                    line
+                } else {
+                    line.to_ascii_lowercase()
+                });
+            }
+            let length: usize = head
+                .iter()
+                .find_map(|l| l.strip_prefix("content-length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            (head, body)
+        });
+
+        let mut config = WebhookConfig::new(url);
+        config
+            .headers
+            .insert("X-Token".to_string(), "secret".to_string());
+        let action = ActionConfig::Webhook(config);
+        ActionRegistry::default()
+            .execute(&action, &alert())
+            .unwrap();
+
+        let (head, body) = server.join().unwrap();
+        assert!(head[0].starts_with("POST /hook HTTP/1.1"));
+        assert!(head.iter().any(|l| l == "x-token: secret\r\n"));
+        let received: RuleAlert = serde_json::from_slice(&body).unwrap();
+        assert_eq!(received, alert());
+    }
+
+    #[test]
+    fn test_mqtt_publish() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let broker = listener.local_addr().unwrap().to_string();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut connect = [0u8; 2];
+            stream.read_exact(&mut connect).unwrap();
+            assert_eq!(connect[0], 0x10);
+            let mut rest = vec![0; connect[1] as usize];
+            stream.read_exact(&mut rest).unwrap();
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        let action = ActionConfig::Mqtt {
+            broker,
+            topic: "alerts/crowd".to_string(),
+            client_id: "test".to_string(),
+            timeout_ms: 2000,
+        };
+        let registry = ActionRegistry::default();
+        registry.execute(&action, &alert()).unwrap();
+        // Disconnects and lets the event loop finish sending
+        drop(registry);
+
+        let received = server.join().unwrap();
+        assert_eq!(received[0], 0x30);
+        let topic = b"alerts/crowd";
+        let start = received
+            .windows(topic.len())
+            .position(|w| w == topic)
+            .unwrap();
+        let payload = &received[start + topic.len()..received.len() - 2];
+        let published: RuleAlert = serde_json::from_slice(payload).unwrap();
+        assert_eq!(published, alert());
+        // Ends with DISCONNECT
+        assert_eq!(&received[received.len() - 2..], &[0xe0, 0x00]);
+    }
+}
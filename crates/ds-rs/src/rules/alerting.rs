@@ -0,0 +1,296 @@
+//! Cooldown and deduplication between rule alerts and their actions
+//!
+//! A rule whose condition flickers can fire many times a minute. Within a
+//! rule's cooldown window, repeats of an alert with the same key are held
+//! back and counted; when the window ends a single summary alert (with
+//! [`RuleAlert::suppressed`] set) reports what was folded into it.
+
+use super::RuleAlert;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// What makes two alerts "the same" for deduplication
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupScope {
+    /// One window per rule and source
+    #[default]
+    Rule,
+
+    /// One window per rule, source and zone
+    Zone,
+
+    /// One window per tracked object; an alert is held back only if every
+    /// track in it is already cooling down
+    Track,
+}
+
+/// Per-rule (or rules-wide default) cooldown settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CooldownConfig {
+    /// Length of the window after a delivered alert
+    pub cooldown_secs: f64,
+
+    pub scope: DedupScope,
+
+    /// Send a summary of held-back alerts when the window closes
+    pub summarize: bool,
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            cooldown_secs: 60.0,
+            scope: DedupScope::default(),
+            summarize: true,
+        }
+    }
+}
+
+impl CooldownConfig {
+    fn cooldown_ns(&self) -> u64 {
+        (self.cooldown_secs.max(0.0) * 1e9) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DedupKey {
+    rule: String,
+    source_id: u32,
+    zone: Option<String>,
+    track_id: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Window {
+    ends_at: u64,
+    summarize: bool,
+    suppressed: usize,
+    last: Option<RuleAlert>,
+    track_ids: BTreeSet<u64>,
+}
+
+impl Window {
+    fn summary(self) -> Option<RuleAlert> {
+        if !self.summarize || self.suppressed == 0 {
+            return None;
+        }
+        self.last.map(|mut alert| {
+            alert.suppressed = self.suppressed;
+            alert.track_ids = self.track_ids.into_iter().collect();
+            alert
+        })
+    }
+}
+
+/// Track-scoped windows of one rule hold back copies of the same alerts, so
+/// their summaries coincide
+fn dedup_summaries(summaries: &mut Vec<RuleAlert>) {
+    summaries.sort_by(|a, b| (&a.rule, a.timestamp).cmp(&(&b.rule, b.timestamp)));
+    summaries.dedup_by(|a, b| {
+        a.rule == b.rule && a.timestamp == b.timestamp && a.source_id == b.source_id
+    });
+}
+
+/// Tracks cooldown windows across rules and sources
+#[derive(Debug, Default)]
+pub struct AlertDeduplicator {
+    windows: HashMap<DedupKey, Window>,
+}
+
+impl AlertDeduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn keys(alert: &RuleAlert, scope: DedupScope) -> Vec<DedupKey> {
+        let key = |zone: Option<String>, track_id| DedupKey {
+            rule: alert.rule.clone(),
+            source_id: alert.source_id,
+            zone,
+            track_id,
+        };
+        match scope {
+            DedupScope::Rule => vec![key(None, None)],
+            DedupScope::Zone => vec![key(alert.zone.clone(), None)],
+            DedupScope::Track if alert.track_ids.is_empty() => {
+                vec![key(alert.zone.clone(), None)]
+            }
+            DedupScope::Track => alert
+                .track_ids
+                .iter()
+                .map(|&id| key(alert.zone.clone(), Some(id)))
+                .collect(),
+        }
+    }
+
+    /// Pass an alert through the cooldown. Returns what should be sent now:
+    /// nothing if it was held back, otherwise the alert, preceded by the
+    /// summary of any window it closes.
+    pub fn offer(&mut self, alert: RuleAlert, config: &CooldownConfig) -> Vec<RuleAlert> {
+        let now = alert.timestamp;
+        let keys = Self::keys(&alert, config.scope);
+
+        let cooling = |window: Option<&Window>| window.is_some_and(|w| now < w.ends_at);
+        if keys.iter().all(|key| cooling(self.windows.get(key))) {
+            for key in &keys {
+                let window = self.windows.get_mut(key).expect("window exists");
+                window.suppressed += 1;
+                window.track_ids.extend(&alert.track_ids);
+                window.last = Some(alert.clone());
+            }
+            return Vec::new();
+        }
+
+        let mut out = Vec::new();
+        for key in keys {
+            if let Some(window) = self.windows.remove(&key)
+                && let Some(summary) = window.summary()
+            {
+                out.push(summary);
+            }
+            self.windows.insert(
+                key,
+                Window {
+                    ends_at: now.saturating_add(config.cooldown_ns()),
+                    summarize: config.summarize,
+                    suppressed: 0,
+                    last: None,
+                    track_ids: BTreeSet::new(),
+                },
+            );
+        }
+        dedup_summaries(&mut out);
+        out.push(alert);
+        out
+    }
+
+    /// Close windows of `source_id` that ended by `now`, returning summaries
+    /// for those that held alerts back
+    pub fn flush(&mut self, source_id: u32, now: u64) -> Vec<RuleAlert> {
+        let expired: Vec<DedupKey> = self
+            .windows
+            .iter()
+            .filter(|(key, window)| key.source_id == source_id && window.ends_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut summaries: Vec<RuleAlert> = expired
+            .into_iter()
+            .filter_map(|key| self.windows.remove(&key))
+            .filter_map(Window::summary)
+            .collect();
+        dedup_summaries(&mut summaries);
+        summaries
+    }
+
+    /// Number of open windows
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn alert(timestamp: u64, track_ids: Vec<u64>) -> RuleAlert {
+        RuleAlert {
+            rule: "loitering".to_string(),
+            source_id: 0,
+            timestamp,
+            count: track_ids.len(),
+            track_ids,
+            zone: Some("A".to_string()),
+            suppressed: 0,
+        }
+    }
+
+    fn config(scope: DedupScope) -> CooldownConfig {
+        CooldownConfig {
+            cooldown_secs: 10.0,
+            scope,
+            summarize: true,
+        }
+    }
+
+    #[test]
+    fn test_repeats_suppressed() {
+        let mut dedup = AlertDeduplicator::new();
+        let config = config(DedupScope::Rule);
+
+        assert_eq!(dedup.offer(alert(0, vec![1]), &config).len(), 1);
+        assert!(dedup.offer(alert(2 * SECOND, vec![2]), &config).is_empty());
+        assert!(dedup.offer(alert(4 * SECOND, vec![3]), &config).is_empty());
+    }
+
+    #[test]
+    fn test_summary_flushed_after_cooldown() {
+        let mut dedup = AlertDeduplicator::new();
+        let config = config(DedupScope::Rule);
+        dedup.offer(alert(0, vec![1]), &config);
+        dedup.offer(alert(2 * SECOND, vec![2]), &config);
+        dedup.offer(alert(4 * SECOND, vec![3]), &config);
+
+        assert!(dedup.flush(0, 9 * SECOND).is_empty());
+
+        let summaries = dedup.flush(0, 10 * SECOND);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].suppressed, 2);
+        assert_eq!(summaries[0].track_ids, vec![2, 3]);
+        assert!(dedup.is_empty());
+    }
+
+    #[test]
+    fn test_new_alert_after_window_carries_summary() {
+        let mut dedup = AlertDeduplicator::new();
+        let config = config(DedupScope::Rule);
+
+        dedup.offer(alert(0, vec![1]), &config);
+        dedup.offer(alert(SECOND, vec![1]), &config);
+
+        let out = dedup.offer(alert(11 * SECOND, vec![1]), &config);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].suppressed, 1);
+        assert_eq!(out[1].suppressed, 0);
+    }
+
+    #[test]
+    fn test_track_scope() {
+        let mut dedup = AlertDeduplicator::new();
+        let config = config(DedupScope::Track);
+
+        assert_eq!(dedup.offer(alert(0, vec![1]), &config).len(), 1);
+        // Track 2 is new, so the alert goes out
+        assert_eq!(dedup.offer(alert(SECOND, vec![1, 2]), &config).len(), 1);
+        assert!(
+            dedup
+                .offer(alert(2 * SECOND, vec![2, 1]), &config)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_sources_are_independent() {
+        let mut dedup = AlertDeduplicator::new();
+        let config = CooldownConfig {
+            summarize: false,
+            ..config(DedupScope::Rule)
+        };
+
+        dedup.offer(alert(0, vec![]), &config);
+        let mut other = alert(SECOND, vec![]);
+        other.source_id = 1;
+        assert_eq!(dedup.offer(other, &config).len(), 1);
+
+        dedup.offer(alert(2 * SECOND, vec![]), &config);
+        assert!(dedup.flush(0, 20 * SECOND).is_empty());
+    }
+}
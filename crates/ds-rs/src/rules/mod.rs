@@ -0,0 +1,646 @@
+//! Event rules evaluated over detection and tracking output
+//!
+//! Rules are declared in the `[rules]` section of the application config:
+//!
+//! ```toml
+//! [[rules.zones]]
+//! name = "entrance"
+//! polygon = [[0.0, 600.0], [800.0, 600.0], [800.0, 1080.0], [0.0, 1080.0]]
+//!
+//! [[rules.rules]]
+//! name = "crowd-at-entrance"
+//! condition = { type = "count", class = "person", zone = "entrance", min = 3 }
+//! for_secs = 10.0
+//! actions = [
+//!     { type = "log" },
+//!     { type = "webhook", url = "http://alerts.local/hooks/crowd" },
+//! ]
+//! ```
+//!
+//! [`RuleEngine::evaluate`] is fed the objects of each frame and returns a
+//! [`RuleAlert`] when a rule's condition has held for `for_secs`. A rule
+//! fires once per episode and re-arms when its condition stops holding.
+//! [`RuleEngine::attach`] runs the engine on the frames passing a pad.
+
+pub mod actions;
+pub mod alerting;
+mod signing;
+pub(crate) mod transport;
+pub mod webhook;
+
+use crate::inference::InferenceFilters;
+use crate::metadata::{MetadataExtractor, ObjectMeta, cpu_detections};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+pub use actions::{ActionConfig, ActionHandler, ActionRegistry};
+pub use alerting::{AlertDeduplicator, CooldownConfig, DedupScope};
+pub use webhook::{WebhookConfig, WebhookHandle, WebhookQueue, WebhookSender};
+
+/// Errors from rule configuration and action execution
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("Invalid rule '{rule}': {reason}")]
+    InvalidRule { rule: String, reason: String },
+
+    #[error("No handler registered for action '{0}'")]
+    NoHandler(String),
+
+    #[error("Action '{action}' failed: {reason}")]
+    ActionFailed { action: String, reason: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RuleError>;
+
+/// Named polygon in frame pixel coordinates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+
+    /// Restrict the zone to one source; `None` applies it to all
+    #[serde(default)]
+    pub source_id: Option<u32>,
+
+    /// Vertices in order, at least three
+    pub polygon: Vec<[f32; 2]>,
+}
+
+impl Zone {
+    /// Even-odd point-in-polygon test
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let n = self.polygon.len();
+        let mut inside = false;
+        for i in 0..n {
+            let [xi, yi] = self.polygon[i];
+            let [xj, yj] = self.polygon[(i + n - 1) % n];
+            if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Whether an object stands in the zone, judged at its bottom center
+    pub fn contains_object(&self, object: &ObjectMeta) -> bool {
+        let bbox = &object.rect_params;
+        self.contains(bbox.left + bbox.width / 2.0, bbox.top + bbox.height)
+    }
+
+    fn applies_to(&self, source_id: u32) -> bool {
+        self.source_id.is_none_or(|id| id == source_id)
+    }
+}
+
+/// What a rule watches for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// Number of matching objects is within `[min, max]`
+    Count {
+        #[serde(default)]
+        class: Option<String>,
+        #[serde(default)]
+        zone: Option<String>,
+        min: usize,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+
+    /// Some tracked object has stayed in the zone for `seconds`
+    Dwell {
+        #[serde(default)]
+        class: Option<String>,
+        zone: String,
+        seconds: f64,
+    },
+}
+
+impl Condition {
+    fn zone(&self) -> Option<&str> {
+        match self {
+            Condition::Count { zone, .. } => zone.as_deref(),
+            Condition::Dwell { zone, .. } => Some(zone),
+        }
+    }
+
+    fn class(&self) -> Option<&str> {
+        match self {
+            Condition::Count { class, .. } | Condition::Dwell { class, .. } => class.as_deref(),
+        }
+    }
+}
+
+/// A condition and the actions run when it fires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Only evaluate frames from this source
+    #[serde(default)]
+    pub source_id: Option<u32>,
+
+    pub condition: Condition,
+
+    /// How long the condition must hold before the rule fires
+    #[serde(default)]
+    pub for_secs: f64,
+
+    #[serde(default)]
+    pub actions: Vec<ActionConfig>,
+
+    /// Overrides the rules-wide cooldown
+    #[serde(default)]
+    pub cooldown: Option<CooldownConfig>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// `[rules]` section of the application config
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RulesConfig {
+    #[serde(default)]
+    pub zones: Vec<Zone>,
+
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+
+    /// Cooldown for rules without their own; no deduplication when unset
+    #[serde(default)]
+    pub cooldown: Option<CooldownConfig>,
+}
+
+impl RulesConfig {
+    /// Check zone references and thresholds
+    pub fn validate(&self) -> Result<()> {
+        for zone in &self.zones {
+            if zone.polygon.len() < 3 {
+                return Err(RuleError::InvalidRule {
+                    rule: zone.name.clone(),
+                    reason: "zone polygon needs at least three points".to_string(),
+                });
+            }
+        }
+
+        for rule in &self.rules {
+            let invalid = |reason: String| RuleError::InvalidRule {
+                rule: rule.name.clone(),
+                reason,
+            };
+
+            if let Some(zone) = rule.condition.zone()
+                && !self.zones.iter().any(|z| z.name == zone)
+            {
+                return Err(invalid(format!("unknown zone '{}'", zone)));
+            }
+            if let Condition::Count {
+                min,
+                max: Some(max),
+                ..
+            } = rule.condition
+                && max < min
+            {
+                return Err(invalid(format!("max {} is below min {}", max, min)));
+            }
+            if !rule.for_secs.is_finite() || rule.for_secs < 0.0 {
+                return Err(invalid(
+                    "for_secs must be a non-negative number".to_string(),
+                ));
+            }
+        }
+
+        let mut names: Vec<&str> = self.rules.iter().map(|r| r.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(RuleError::InvalidRule {
+                rule: pair[0].to_string(),
+                reason: "duplicate rule name".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A fired rule, passed to every action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleAlert {
+    pub rule: String,
+    pub source_id: u32,
+
+    /// Frame timestamp in nanoseconds
+    pub timestamp: u64,
+
+    /// Objects that satisfied the condition
+    pub count: usize,
+    pub track_ids: Vec<u64>,
+
+    #[serde(default)]
+    pub zone: Option<String>,
+
+    /// Non-zero for a summary: the number of repeats held back during the
+    /// rule's cooldown
+    #[serde(default)]
+    pub suppressed: usize,
+}
+
+fn class_matches(class: Option<&str>, object: &ObjectMeta) -> bool {
+    match class {
+        None => true,
+        Some(class) => {
+            class.eq_ignore_ascii_case(&object.obj_label)
+                || class.parse::<i32>().is_ok_and(|id| id == object.class_id)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    /// When the condition started holding
+    since: Option<u64>,
+    fired: bool,
+
+    /// Dwell rules: when each track entered the zone
+    entered: HashMap<u64, u64>,
+}
+
+/// Evaluates [`RulesConfig`] against incoming frames
+pub struct RuleEngine {
+    config: RulesConfig,
+    actions: ActionRegistry,
+    state: HashMap<(usize, u32), RuleState>,
+    dedup: AlertDeduplicator,
+}
+
+impl RuleEngine {
+    /// Validate the rules and set up the built-in action handlers
+    pub fn new(config: RulesConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            actions: ActionRegistry::default(),
+            state: HashMap::new(),
+            dedup: AlertDeduplicator::new(),
+        })
+    }
+
+    pub fn config(&self) -> &RulesConfig {
+        &self.config
+    }
+
+    /// Handlers used by [`run_actions`](Self::run_actions), e.g. to plug in
+    /// recording or snapshot support
+    pub fn actions_mut(&mut self) -> &mut ActionRegistry {
+        &mut self.actions
+    }
+
+    /// Evaluate every rule for one frame of one source
+    pub fn evaluate(
+        &mut self,
+        source_id: u32,
+        timestamp: u64,
+        objects: &[ObjectMeta],
+    ) -> Vec<RuleAlert> {
+        let mut alerts = Vec::new();
+
+        for (index, rule) in self.config.rules.iter().enumerate() {
+            if !rule.enabled || rule.source_id.is_some_and(|id| id != source_id) {
+                continue;
+            }
+
+            let zone = rule.condition.zone().and_then(|name| {
+                self.config
+                    .zones
+                    .iter()
+                    .find(|z| z.name == name && z.applies_to(source_id))
+            });
+            // Zone restricted to other sources: the rule cannot hold here
+            if rule.condition.zone().is_some() && zone.is_none() {
+                continue;
+            }
+
+            let matching: Vec<&ObjectMeta> = objects
+                .iter()
+                .filter(|obj| class_matches(rule.condition.class(), obj))
+                .filter(|obj| zone.is_none_or(|z| z.contains_object(obj)))
+                .collect();
+
+            let state = self.state.entry((index, source_id)).or_default();
+            let (holds, involved): (bool, Vec<&ObjectMeta>) = match &rule.condition {
+                Condition::Count { min, max, .. } => {
+                    let n = matching.len();
+                    (n >= *min && max.is_none_or(|max| n <= max), matching)
+                }
+                Condition::Dwell { seconds, .. } => {
+                    let tracked: Vec<&ObjectMeta> =
+                        matching.into_iter().filter(|o| o.is_tracked()).collect();
+                    state
+                        .entered
+                        .retain(|id, _| tracked.iter().any(|o| o.object_id == *id));
+                    for object in &tracked {
+                        state.entered.entry(object.object_id).or_insert(timestamp);
+                    }
+
+                    let min_ns = (seconds * 1e9) as u64;
+                    let dwelling: Vec<&ObjectMeta> = tracked
+                        .into_iter()
+                        .filter(|o| timestamp.saturating_sub(state.entered[&o.object_id]) >= min_ns)
+                        .collect();
+                    (!dwelling.is_empty(), dwelling)
+                }
+            };
+
+            if !holds {
+                state.since = None;
+                state.fired = false;
+                continue;
+            }
+
+            let since = *state.since.get_or_insert(timestamp);
+            let held_ns = timestamp.saturating_sub(since);
+            if !state.fired && held_ns >= (rule.for_secs * 1e9) as u64 {
+                state.fired = true;
+                alerts.push(RuleAlert {
+                    rule: rule.name.clone(),
+                    source_id,
+                    timestamp,
+                    count: involved.len(),
+                    track_ids: involved
+                        .iter()
+                        .filter(|o| o.is_tracked())
+                        .map(|o| o.object_id)
+                        .collect(),
+                    zone: rule.condition.zone().map(str::to_string),
+                    suppressed: 0,
+                });
+            }
+        }
+
+        alerts
+    }
+
+    /// Run the actions of the rule that produced `alert`. Every action is
+    /// attempted; the result for each is returned in order.
+    pub fn run_actions(&self, alert: &RuleAlert) -> Vec<Result<()>> {
+        self.config
+            .rules
+            .iter()
+            .find(|rule| rule.name == alert.rule)
+            .map(|rule| {
+                rule.actions
+                    .iter()
+                    .map(|action| self.actions.execute(action, alert))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Apply each rule's cooldown to freshly fired alerts and collect
+    /// summaries of windows that closed by `timestamp`
+    pub fn throttle(
+        &mut self,
+        source_id: u32,
+        timestamp: u64,
+        alerts: Vec<RuleAlert>,
+    ) -> Vec<RuleAlert> {
+        let mut out = self.dedup.flush(source_id, timestamp);
+        for alert in alerts {
+            let cooldown = self
+                .config
+                .rules
+                .iter()
+                .find(|rule| rule.name == alert.rule)
+                .and_then(|rule| rule.cooldown.as_ref())
+                .or(self.config.cooldown.as_ref());
+            match cooldown {
+                Some(cooldown) => out.extend(self.dedup.offer(alert, cooldown)),
+                None => out.push(alert),
+            }
+        }
+        out
+    }
+
+    /// Evaluate a frame, apply cooldowns and run the actions of every alert
+    /// that goes out, logging failures
+    pub fn process(
+        &mut self,
+        source_id: u32,
+        timestamp: u64,
+        objects: &[ObjectMeta],
+    ) -> Vec<RuleAlert> {
+        let fired = self.evaluate(source_id, timestamp, objects);
+        let alerts = self.throttle(source_id, timestamp, fired);
+        for alert in &alerts {
+            for result in self.run_actions(alert) {
+                if let Err(e) = result {
+                    log::warn!("Rule '{}': {}", alert.rule, e);
+                }
+            }
+        }
+        alerts
+    }
+
+    /// Forget condition state for a source, e.g. after it is removed
+    pub fn reset_source(&mut self, source_id: u32) {
+        self.state.retain(|(_, id), _| *id != source_id);
+    }
+
+    /// Process every buffer reaching `pad`: the detections a `cpudetector`
+    /// attached (as source 0) or else the frames of its batch metadata,
+    /// each narrowed by its source's entry in `filters`
+    pub fn attach(
+        engine: &Arc<Mutex<Self>>,
+        pad: &gst::Pad,
+        filters: &InferenceFilters,
+    ) -> Option<gst::PadProbeId> {
+        let engine = engine.clone();
+        let filters = filters.clone();
+        let extractor = MetadataExtractor::new();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let timestamp = buffer.pts().map_or(0, gst::ClockTime::nseconds);
+            let mut engine = engine.lock().unwrap();
+            if let Some(mut frame) = cpu_detections(buffer) {
+                filters.retain_objects(0, &mut frame.objects);
+                engine.process(0, timestamp, &frame.objects);
+            } else if let Ok(mut batch) = extractor.extract_batch_meta(buffer) {
+                for frame in batch.frames_mut() {
+                    filters.retain_objects(frame.source_id, frame.objects_mut());
+                    engine.process(frame.source_id, frame.buf_pts, frame.objects());
+                }
+            }
+            gst::PadProbeReturn::Ok
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::BoundingBox;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn person(track_id: u64, x: f32) -> ObjectMeta {
+        let mut obj = ObjectMeta::new(track_id);
+        obj.set_class(0, "person");
+        obj.rect_params = BoundingBox::new(x, 50.0, 20.0, 40.0);
+        obj
+    }
+
+    fn config(toml_str: &str) -> RulesConfig {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    const ZONE: &str = r#"
+        [[zones]]
+        name = "A"
+        polygon = [[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0]]
+    "#;
+
+    #[test]
+    fn test_zone_contains() {
+        let rules = config(ZONE);
+        let zone = &rules.zones[0];
+        assert!(zone.contains(50.0, 50.0));
+        assert!(!zone.contains(150.0, 50.0));
+        assert!(zone.contains_object(&person(1, 10.0)));
+        assert!(!zone.contains_object(&person(1, 200.0)));
+    }
+
+    #[test]
+    fn test_count_rule_with_duration() {
+        let rules = config(&format!(
+            r#"{ZONE}
+            [[rules]]
+            name = "crowd"
+            condition = {{ type = "count", class = "person", zone = "A", min = 3 }}
+            for_secs = 10.0
+            "#
+        ));
+        let mut engine = RuleEngine::new(rules).unwrap();
+        let crowd = [person(1, 0.0), person(2, 20.0), person(3, 40.0)];
+
+        assert!(engine.evaluate(0, 0, &crowd).is_empty());
+        assert!(engine.evaluate(0, 5 * SECOND, &crowd).is_empty());
+
+        let alerts = engine.evaluate(0, 10 * SECOND, &crowd);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].count, 3);
+        assert_eq!(alerts[0].zone.as_deref(), Some("A"));
+
+        // Fires once per episode
+        assert!(engine.evaluate(0, 11 * SECOND, &crowd).is_empty());
+
+        // One person leaves the zone, then the crowd forms again
+        let thinned = [person(1, 0.0), person(2, 20.0), person(3, 400.0)];
+        assert!(engine.evaluate(0, 12 * SECOND, &thinned).is_empty());
+        assert!(engine.evaluate(0, 13 * SECOND, &crowd).is_empty());
+        assert_eq!(engine.evaluate(0, 23 * SECOND, &crowd).len(), 1);
+    }
+
+    #[test]
+    fn test_dwell_rule() {
+        let rules = config(&format!(
+            r#"{ZONE}
+            [[rules]]
+            name = "loitering"
+            condition = {{ type = "dwell", zone = "A", seconds = 30.0 }}
+            "#
+        ));
+        let mut engine = RuleEngine::new(rules).unwrap();
+
+        assert!(engine.evaluate(0, 0, &[person(1, 0.0)]).is_empty());
+        // Track 1 leaves and comes back, restarting its dwell time
+        assert!(engine.evaluate(0, 20 * SECOND, &[]).is_empty());
+        assert!(
+            engine
+                .evaluate(0, 40 * SECOND, &[person(1, 0.0)])
+                .is_empty()
+        );
+
+        let alerts = engine.evaluate(0, 70 * SECOND, &[person(1, 0.0)]);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].track_ids, vec![1]);
+    }
+
+    #[test]
+    fn test_rules_are_per_source() {
+        let rules = config(
+            r#"
+            [[rules]]
+            name = "any-person"
+            source_id = 1
+            condition = { type = "count", class = "0", min = 1 }
+            "#,
+        );
+        let mut engine = RuleEngine::new(rules).unwrap();
+
+        assert!(engine.evaluate(0, 0, &[person(1, 0.0)]).is_empty());
+        assert_eq!(engine.evaluate(1, 0, &[person(1, 0.0)]).len(), 1);
+    }
+
+    #[test]
+    fn test_cooldown_throttles_flapping_rule() {
+        let rules = config(
+            r#"
+            [cooldown]
+            cooldown_secs = 60.0
+
+            [[rules]]
+            name = "any-person"
+            condition = { type = "count", class = "person", min = 1 }
+            "#,
+        );
+        let mut engine = RuleEngine::new(rules).unwrap();
+
+        // Person flickers in and out every second
+        let mut delivered = Vec::new();
+        for second in 0..30u64 {
+            let objects = if second % 2 == 0 {
+                vec![person(1, 0.0)]
+            } else {
+                vec![]
+            };
+            delivered.extend(engine.process(0, second * SECOND, &objects));
+        }
+        assert_eq!(delivered.len(), 1);
+
+        let summary = engine.throttle(0, 61 * SECOND, Vec::new());
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].suppressed, 14);
+    }
+
+    #[test]
+    fn test_validation() {
+        let unknown_zone = config(
+            r#"
+            [[rules]]
+            name = "r"
+            condition = { type = "dwell", zone = "B", seconds = 1.0 }
+            "#,
+        );
+        assert!(RuleEngine::new(unknown_zone).is_err());
+
+        let bad_range = config(
+            r#"
+            [[rules]]
+            name = "r"
+            condition = { type = "count", min = 3, max = 1 }
+            "#,
+        );
+        assert!(bad_range.validate().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! HTTP and MQTT clients for rule actions
+//!
+//! Requests go through a shared blocking `reqwest` client (`POST` for
+//! webhooks, `PUT` for object uploads) over `http://` or `https://`. MQTT
+//! messages are published at QoS 0 through a `rumqttc` client per broker,
+//! whose event loop runs on a background thread.
+
+use super::{Result, RuleError};
+use rumqttc::{Client, MqttOptions, QoS};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::thread;
+use std::time::Duration;
+
+fn failed(action: &str, reason: impl ToString) -> RuleError {
+    RuleError::ActionFailed {
+        action: action.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Reject values that would end a header line or split an MQTT topic
+fn check_field(action: &str, what: &str, value: &str) -> Result<()> {
+    if value.contains(['\r', '\n']) {
+        return Err(failed(
+            action,
+            format!("{} {:?} contains a line break", what, value),
+        ));
+    }
+    Ok(())
+}
+
+static HTTP: LazyLock<reqwest::blocking::Client> = LazyLock::new(reqwest::blocking::Client::new);
+
+/// POST `body` and return the response status code
+pub(super) fn http_post(
+    url: &str,
+    headers: &HashMap<String, String>,
+    content_type: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<u16> {
+    http_request("webhook", "POST", url, headers, content_type, body, timeout)
+}
+
+/// Send one request with `body` and return the response status code
+pub(crate) fn http_request(
+    action: &str,
+    method: &str,
+    url: &str,
+    headers: &HashMap<String, String>,
+    content_type: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<u16> {
+    check_field(action, "content type", content_type)?;
+    for (name, value) in headers {
+        check_field(action, "header name", name)?;
+        check_field(action, "header value", value)?;
+    }
+    let method = reqwest::Method::from_bytes(method.as_bytes())
+        .map_err(|e| failed(action, format!("invalid method {}: {}", method, e)))?;
+    let url = reqwest::Url::parse(url)
+        .map_err(|e| failed(action, format!("invalid URL '{}': {}", url, e)))?;
+
+    let mut request = HTTP
+        .request(method, url.clone())
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body.to_vec());
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let response = request
+        .send()
+        .map_err(|e| failed(action, format!("request to {} failed: {}", url, e)))?;
+    Ok(response.status().as_u16())
+}
+
+/// Split `host[:port]`, defaulting to the standard MQTT port
+fn parse_broker(broker: &str) -> Result<(String, u16)> {
+    let (host, port) = match broker.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .map_err(|_| failed("mqtt", format!("invalid port in '{}'", broker)))?;
+            (host, port)
+        }
+        None => (broker, 1883),
+    };
+    if host.is_empty() {
+        return Err(failed("mqtt", format!("missing host in '{}'", broker)));
+    }
+    Ok((host.to_string(), port))
+}
+
+/// Connection to one broker. The event loop connects, reconnects and sends
+/// queued messages on its own thread, which ends once the publisher is
+/// dropped.
+pub(super) struct MqttPublisher {
+    broker: String,
+    client: Client,
+}
+
+impl MqttPublisher {
+    pub(super) fn connect(broker: &str, client_id: &str, timeout: Duration) -> Result<Self> {
+        let (host, port) = parse_broker(broker)?;
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        let (client, mut connection) = Client::new(options, 64);
+
+        let name = broker.to_string();
+        thread::Builder::new()
+            .name("mqtt".to_string())
+            .spawn(move || {
+                for event in connection.iter() {
+                    if let Err(e) = event {
+                        log::warn!("MQTT broker {}: {}", name, e);
+                        thread::sleep(timeout);
+                    }
+                }
+            })
+            .map_err(|e| failed("mqtt", e))?;
+
+        Ok(Self {
+            broker: broker.to_string(),
+            client,
+        })
+    }
+
+    /// Queue one message at QoS 0 without waiting for the network
+    pub(super) fn publish(&self, topic: &str, payload: &[u8]) -> Result<()> {
+        check_field("mqtt", "topic", topic)?;
+        self.client
+            .try_publish(topic, QoS::AtMostOnce, false, payload.to_vec())
+            .map_err(|e| failed("mqtt", format!("cannot publish to {}: {}", self.broker, e)))
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        let _ = self.client.try_disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_breaks_are_rejected() {
+        assert!(check_field("webhook", "header value", "token").is_ok());
+        assert!(check_field("webhook", "header value", "a\r\nX-Injected: 1").is_err());
+        assert!(check_field("mqtt", "topic", "alerts\n").is_err());
+    }
+
+    #[test]
+    fn test_header_injection_is_not_sent() {
+        let mut headers = HashMap::new();
+        headers.insert("X-Token".to_string(), "a\r\nHost: evil".to_string());
+        let sent = http_request(
+            "webhook",
+            "POST",
+            "http://127.0.0.1:9/",
+            &headers,
+            "application/json",
+            b"{}",
+            Duration::from_millis(100),
+        );
+        assert!(
+            matches!(sent, Err(RuleError::ActionFailed { reason, .. }) if reason.contains("line break"))
+        );
+    }
+
+    #[test]
+    fn test_parse_broker() {
+        assert_eq!(
+            parse_broker("10.0.0.2:8883").unwrap(),
+            ("10.0.0.2".to_string(), 8883)
+        );
+        assert_eq!(
+            parse_broker("broker.local").unwrap(),
+            ("broker.local".to_string(), 1883)
+        );
+        assert!(parse_broker(":1883").is_err());
+        assert!(parse_broker("broker:mqtt").is_err());
+    }
+}
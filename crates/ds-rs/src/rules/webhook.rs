@@ -0,0 +1,471 @@
+//! Signed webhook delivery with retries
+//!
+//! Payloads are POSTed as JSON. When a secret is configured each request
+//! carries `X-DS-Timestamp` (Unix seconds) and
+//! `X-DS-Signature: sha256=<hex>`, the HMAC-SHA256 of `"{timestamp}.{body}"`,
+//! which receivers can check with [`verify_signature`]. Failed deliveries
+//! are retried with exponential backoff; payloads that still fail are
+//! logged and, if configured, appended to a dead-letter file.
+
+use super::signing::{hmac_sha256, to_hex};
+use super::{Result, RuleError, transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SIGNATURE_HEADER: &str = "X-DS-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-DS-Timestamp";
+
+/// Where and how to deliver webhooks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// `http://` or `https://` endpoint
+    pub url: String,
+
+    /// Shared secret for HMAC signatures; unsigned when unset
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Retries after the first attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// JSON-lines file receiving payloads that could not be delivered
+    #[serde(default)]
+    pub dead_letter: Option<PathBuf>,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    30_000
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            headers: HashMap::new(),
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            timeout_ms: default_timeout_ms(),
+            dead_letter: None,
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u64.checked_shl(retry).unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+/// Compute the `X-DS-Signature` value for a request
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    format!(
+        "sha256={}",
+        to_hex(&hmac_sha256(secret.as_bytes(), &message))
+    )
+}
+
+/// Check a received signature in constant time
+pub fn verify_signature(secret: &str, timestamp: u64, body: &[u8], signature: &str) -> bool {
+    let expected = sign(secret, timestamp, body);
+    expected.len() == signature.len()
+        && expected
+            .bytes()
+            .zip(signature.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Delivers payloads to one endpoint
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    config: WebhookConfig,
+}
+
+impl WebhookSender {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &WebhookConfig {
+        &self.config
+    }
+
+    /// POST once; `Ok(true)` on success, `Ok(false)` when the error is
+    /// worth retrying
+    fn attempt(&self, body: &[u8]) -> Result<bool> {
+        let mut headers = self.config.headers.clone();
+        if let Some(secret) = &self.config.secret {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            headers.insert(TIMESTAMP_HEADER.to_string(), timestamp.to_string());
+            headers.insert(SIGNATURE_HEADER.to_string(), sign(secret, timestamp, body));
+        }
+
+        match transport::http_post(
+            &self.config.url,
+            &headers,
+            "application/json",
+            body,
+            Duration::from_millis(self.config.timeout_ms),
+        ) {
+            Ok(status) if (200..300).contains(&status) => Ok(true),
+            // Rate limiting and server errors may clear up; other client
+            // errors will not
+            Ok(status) if status == 429 || status >= 500 => {
+                log::debug!("Webhook {} returned HTTP {}", self.config.url, status);
+                Ok(false)
+            }
+            Ok(status) => Err(RuleError::ActionFailed {
+                action: "webhook".to_string(),
+                reason: format!("{} returned HTTP {}", self.config.url, status),
+            }),
+            Err(e) => {
+                log::debug!("Webhook {} failed: {}", self.config.url, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Deliver `body`, retrying with backoff. Undeliverable payloads go to
+    /// the dead-letter file before the error is returned.
+    pub fn deliver(&self, body: &[u8]) -> Result<()> {
+        let mut last_error = String::from("no attempt made");
+
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                thread::sleep(self.config.backoff(attempt - 1));
+            }
+            match self.attempt(body) {
+                Ok(true) => return Ok(()),
+                Ok(false) => last_error = format!("gave up after {} attempts", attempt + 1),
+                Err(e) => {
+                    last_error = e.to_string();
+                    break;
+                }
+            }
+        }
+
+        self.dead_letter(body, &last_error);
+        Err(RuleError::ActionFailed {
+            action: "webhook".to_string(),
+            reason: format!("{}: {}", self.config.url, last_error),
+        })
+    }
+
+    pub fn send_json<T: Serialize>(&self, payload: &T) -> Result<()> {
+        let body = serde_json::to_vec(payload).map_err(|e| RuleError::ActionFailed {
+            action: "webhook".to_string(),
+            reason: e.to_string(),
+        })?;
+        self.deliver(&body)
+    }
+
+    fn dead_letter(&self, body: &[u8], error: &str) {
+        log::error!(
+            "Webhook delivery to {} failed ({}): {}",
+            self.config.url,
+            error,
+            String::from_utf8_lossy(body)
+        );
+
+        let Some(path) = &self.config.dead_letter else {
+            return;
+        };
+        let payload: serde_json::Value = serde_json::from_slice(body)
+            .unwrap_or_else(|_| String::from_utf8_lossy(body).into_owned().into());
+        let entry = serde_json::json!({
+            "url": self.config.url,
+            "error": error,
+            "payload": payload,
+        });
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", entry));
+        if let Err(e) = written {
+            log::error!("Cannot write dead letter to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Move delivery to a background thread so callers never block on the
+    /// network
+    pub fn spawn(self) -> WebhookQueue {
+        let (sender, receiver): (Sender<Job>, Receiver<Job>) = mpsc::channel();
+        let worker = thread::Builder::new()
+            .name("webhook".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    match job {
+                        // Failures are already logged and dead-lettered
+                        Job::Deliver(body) => {
+                            let _ = self.deliver(&body);
+                        }
+                        Job::Stop => break,
+                    }
+                }
+            })
+            .expect("failed to spawn webhook thread");
+
+        WebhookQueue {
+            handle: WebhookHandle { sender },
+            worker: Some(worker),
+        }
+    }
+}
+
+enum Job {
+    Deliver(Vec<u8>),
+    Stop,
+}
+
+/// Cloneable handle for queueing payloads from other threads
+#[derive(Clone)]
+pub struct WebhookHandle {
+    sender: Sender<Job>,
+}
+
+impl WebhookHandle {
+    pub fn enqueue(&self, body: Vec<u8>) -> Result<()> {
+        self.sender
+            .send(Job::Deliver(body))
+            .map_err(|_| RuleError::ActionFailed {
+                action: "webhook".to_string(),
+                reason: "delivery thread stopped".to_string(),
+            })
+    }
+
+    pub fn enqueue_json<T: Serialize>(&self, payload: &T) -> Result<()> {
+        let body = serde_json::to_vec(payload).map_err(|e| RuleError::ActionFailed {
+            action: "webhook".to_string(),
+            reason: e.to_string(),
+        })?;
+        self.enqueue(body)
+    }
+}
+
+/// Background [`WebhookSender`]. Dropping it delivers everything queued so
+/// far, then stops the thread.
+pub struct WebhookQueue {
+    handle: WebhookHandle,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WebhookQueue {
+    pub fn enqueue(&self, body: Vec<u8>) -> Result<()> {
+        self.handle.enqueue(body)
+    }
+
+    pub fn enqueue_json<T: Serialize>(&self, payload: &T) -> Result<()> {
+        self.handle.enqueue_json(payload)
+    }
+
+    pub fn handle(&self) -> WebhookHandle {
+        self.handle.clone()
+    }
+}
+
+impl Drop for WebhookQueue {
+    fn drop(&mut self) {
+        let _ = self.handle.sender.send(Job::Stop);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    /// Serve one response per status code, returning the received headers
+    fn serve(statuses: Vec<u16>) -> (String, JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut head = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    // Header names arrive lowercased
+                    head.push(line.trim_end().to_ascii_lowercase());
+                }
+                let length: usize = head
+                    .iter()
+                    .find_map(|l| l.strip_prefix("content-length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                head.push(String::from_utf8(body).unwrap());
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                requests.push(head);
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn header<'a>(request: &'a [String], name: &str) -> &'a str {
+        request
+            .iter()
+            .find_map(|l| l.strip_prefix(&format!("{}: ", name.to_ascii_lowercase())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let signature = sign("secret", 1_700_000_000, b"{}");
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_signature("secret", 1_700_000_000, b"{}", &signature));
+        assert!(!verify_signature(
+            "secret",
+            1_700_000_001,
+            b"{}",
+            &signature
+        ));
+        assert!(!verify_signature("other", 1_700_000_000, b"{}", &signature));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let config = WebhookConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            ..WebhookConfig::new("http://localhost/")
+        };
+        assert_eq!(config.backoff(0), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(400));
+        assert_eq!(config.backoff(10), Duration::from_millis(1000));
+        assert_eq!(config.backoff(200), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_retry_then_signed_delivery() {
+        let (url, server) = serve(vec![503, 200]);
+        let sender = WebhookSender::new(WebhookConfig {
+            secret: Some("s3cret".to_string()),
+            initial_backoff_ms: 1,
+            ..WebhookConfig::new(url)
+        });
+
+        sender
+            .send_json(&serde_json::json!({ "event": "test" }))
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        let last = &requests[1];
+        let body = last.last().unwrap();
+        let timestamp: u64 = header(last, TIMESTAMP_HEADER).parse().unwrap();
+        assert!(verify_signature(
+            "s3cret",
+            timestamp,
+            body.as_bytes(),
+            header(last, SIGNATURE_HEADER)
+        ));
+    }
+
+    #[test]
+    fn test_dead_letter_after_retries() {
+        let (url, server) = serve(vec![500, 500]);
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letter = dir.path().join("dead.jsonl");
+        let sender = WebhookSender::new(WebhookConfig {
+            max_retries: 1,
+            initial_backoff_ms: 1,
+            dead_letter: Some(dead_letter.clone()),
+            ..WebhookConfig::new(url.clone())
+        });
+
+        assert!(sender.send_json(&serde_json::json!({ "n": 1 })).is_err());
+        server.join().unwrap();
+
+        let line = std::fs::read_to_string(&dead_letter).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(entry["url"], url.as_str());
+        assert_eq!(entry["payload"]["n"], 1);
+    }
+
+    #[test]
+    fn test_client_error_is_not_retried() {
+        let (url, server) = serve(vec![400]);
+        let sender = WebhookSender::new(WebhookConfig {
+            initial_backoff_ms: 1,
+            ..WebhookConfig::new(url)
+        });
+
+        assert!(sender.deliver(b"{}").is_err());
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_queue_delivers_on_drop() {
+        let (url, server) = serve(vec![200, 200]);
+        let queue = WebhookSender::new(WebhookConfig::new(url)).spawn();
+        queue.enqueue(b"{\"a\":1}".to_vec()).unwrap();
+        queue.enqueue_json(&serde_json::json!({ "b": 2 })).unwrap();
+        drop(queue);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].last().unwrap(), "{\"a\":1}");
+        assert_eq!(requests[1].last().unwrap(), "{\"b\":2}");
+    }
+}
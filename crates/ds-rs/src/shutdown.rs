@@ -0,0 +1,245 @@
+//! Coordinated shutdown across pipelines, sources and helper threads.
+//!
+//! A [`ShutdownCoordinator`] owns a cancellation [`ShutdownToken`] that long
+//! running loops poll or block on. Calling [`ShutdownCoordinator::shutdown`]
+//! cancels the token, then runs each registered stop action on its own thread
+//! so a hung component cannot block the others, and reports anything that
+//! failed or exceeded the timeout.
+
+use crate::error::{DeepStreamError, Result};
+use crate::pipeline::Pipeline;
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+type StopFn = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Cloneable cancellation flag shared with every subsystem.
+#[derive(Clone, Default)]
+pub struct ShutdownToken {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    pub fn cancel(&self) {
+        let (lock, cvar) = &*self.inner;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+    }
+
+    /// Block until cancelled or the timeout elapses. Returns true if cancelled.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let guard = lock.lock().unwrap();
+        let (guard, _) = cvar
+            .wait_timeout_while(guard, timeout, |cancelled| !*cancelled)
+            .unwrap();
+        *guard
+    }
+}
+
+/// Outcome of a coordinated shutdown
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    pub stopped: Vec<String>,
+    pub failed: Vec<(String, String)>,
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Stops registered components in reverse registration order within a
+/// shared timeout budget.
+pub struct ShutdownCoordinator {
+    token: ShutdownToken,
+    timeout: Duration,
+    components: Mutex<Vec<(String, StopFn)>>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            token: ShutdownToken::new(),
+            timeout,
+            components: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn token(&self) -> ShutdownToken {
+        self.token.clone()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Register a component with the action that stops it
+    pub fn register<F>(&self, name: impl Into<String>, stop: F)
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        self.components
+            .lock()
+            .unwrap()
+            .push((name.into(), Box::new(stop)));
+    }
+
+    /// Register a pipeline: send EOS, wait for it to drain, then go to NULL.
+    ///
+    /// Live pipelines may never deliver EOS, so draining is bounded by half
+    /// the coordinator timeout and the pipeline is stopped regardless.
+    pub fn register_pipeline(&self, name: impl Into<String>, pipeline: Arc<Pipeline>) {
+        let eos_timeout = self.timeout / 2;
+        self.register(name, move || stop_pipeline(&pipeline, eos_timeout));
+    }
+
+    /// Register a worker thread that exits once the token is cancelled
+    pub fn register_thread(&self, name: impl Into<String>, handle: std::thread::JoinHandle<()>) {
+        let name = name.into();
+        let thread_name = name.clone();
+        self.register(name, move || {
+            handle
+                .join()
+                .map_err(|_| DeepStreamError::Unknown(format!("Thread '{}' panicked", thread_name)))
+        });
+    }
+
+    /// Cancel the token without waiting for components
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn shutdown(&self) -> ShutdownReport {
+        let started = Instant::now();
+        self.token.cancel();
+
+        let components = std::mem::take(&mut *self.components.lock().unwrap());
+        let mut report = ShutdownReport::default();
+
+        for (name, stop) in components.into_iter().rev() {
+            log::info!("Stopping {}", name);
+
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(stop());
+            });
+
+            let remaining = self.timeout.saturating_sub(started.elapsed());
+            match rx.recv_timeout(remaining) {
+                Ok(Ok(())) => report.stopped.push(name),
+                Ok(Err(e)) => {
+                    log::error!("Failed to stop {}: {}", name, e);
+                    report.failed.push((name, e.to_string()));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    log::error!("Timed out stopping {}", name);
+                    report.failed.push((name, "timed out".to_string()));
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    report
+                        .failed
+                        .push((name, "stop action panicked".to_string()));
+                }
+            }
+        }
+
+        report.elapsed = started.elapsed();
+        report
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+/// Drain a pipeline with EOS and set it to NULL.
+pub fn stop_pipeline(pipeline: &Pipeline, eos_timeout: Duration) -> Result<()> {
+    if pipeline.is_playing() || pipeline.is_paused() {
+        pipeline.send_eos()?;
+        if let Err(e) = pipeline.wait_for_eos(Some(eos_timeout)) {
+            log::warn!("Pipeline {} did not drain: {}", pipeline.name(), e);
+        }
+    }
+
+    pipeline.stop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_wait() {
+        let token = ShutdownToken::new();
+        assert!(!token.wait_timeout(Duration::from_millis(10)));
+
+        let remote = token.clone();
+        let handle = std::thread::spawn(move || remote.wait_timeout(Duration::from_secs(5)));
+        token.cancel();
+        assert!(handle.join().unwrap());
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_shutdown_reports_failed_components() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_millis(200));
+
+        coordinator.register("hung", || {
+            std::thread::sleep(Duration::from_secs(2));
+            Ok(())
+        });
+        coordinator.register("ok", || Ok(()));
+        coordinator.register("broken", || {
+            Err(DeepStreamError::Pipeline("refused".to_string()))
+        });
+
+        let token = coordinator.token();
+        let worker = std::thread::spawn(move || {
+            token.wait_timeout(Duration::from_secs(5));
+        });
+        coordinator.register_thread("worker", worker);
+
+        let report = coordinator.shutdown();
+
+        assert_eq!(report.stopped, vec!["worker".to_string(), "ok".to_string()]);
+        let failed: Vec<&str> = report.failed.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(failed, vec!["broken", "hung"]);
+    }
+
+    #[test]
+    fn test_register_pipeline_stops_it() {
+        gstreamer::init().unwrap();
+
+        let pipeline = Arc::new(Pipeline::new("shutdown-test").unwrap());
+        let src = gstreamer::ElementFactory::make("videotestsrc")
+            .property("is-live", true)
+            .build()
+            .unwrap();
+        let sink = gstreamer::ElementFactory::make("fakesink").build().unwrap();
+        pipeline.add_many(&[&src, &sink]).unwrap();
+        pipeline.link_elements(&src, &sink).unwrap();
+        pipeline.play().unwrap();
+
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(2));
+        coordinator.register_pipeline("pipeline", pipeline.clone());
+        let report = coordinator.shutdown();
+
+        assert!(report.is_clean());
+        assert_eq!(report.stopped, vec!["pipeline".to_string()]);
+        assert_eq!(pipeline.current_state().unwrap(), gstreamer::State::Null);
+    }
+}
@@ -0,0 +1,286 @@
+//! Timestamp alignment of sources feeding one streammux
+//!
+//! A file source added to a playing pipeline starts its timestamps at
+//! zero, so its first frames are already late next to live RTSP sources
+//! whose timestamps follow the pipeline clock, and the streammux batches
+//! the two kinds unevenly. The [`TimestampAligner`] sets a pad offset on
+//! each source's output when its first buffer arrives: sources in
+//! [`AlignMode::Arrival`] are shifted to the pipeline's running time at
+//! that moment, sources in [`AlignMode::Keep`] are left as they are. A
+//! fixed offset per source is added on top in both modes. Every source
+//! keeps its timestamps unless the config asks for arrival alignment:
+//!
+//! ```toml
+//! [alignment]
+//! file = "arrival"
+//! live = "keep"
+//! ```
+
+use super::SourceId;
+use super::recovery::SourceKind;
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// How a source's timestamps are shifted before batching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignMode {
+    /// Timestamps as the source made them
+    #[default]
+    Keep,
+    /// The first buffer is moved to the pipeline's running time when it
+    /// arrives, and the rest follow
+    Arrival,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampAlignment {
+    /// Files and other sources that are not live
+    pub file: AlignMode,
+    /// RTSP and HTTP sources, which are timestamped against the pipeline
+    /// clock already
+    pub live: AlignMode,
+}
+
+impl TimestampAlignment {
+    pub fn mode_for(&self, uri: &str) -> AlignMode {
+        match SourceKind::from_uri(uri) {
+            SourceKind::Rtsp | SourceKind::Http => self.live,
+            SourceKind::File | SourceKind::Other => self.file,
+        }
+    }
+}
+
+/// Pad offset that moves a buffer at `running_time` to `now`
+pub fn arrival_offset(running_time: gst::ClockTime, now: gst::ClockTime) -> i64 {
+    now.nseconds() as i64 - running_time.nseconds() as i64
+}
+
+#[derive(Default)]
+struct AlignedSource {
+    pads: Vec<glib::WeakRef<gst::Pad>>,
+    /// Offset found when the first buffer arrived; zero in `Keep` mode
+    arrival: Option<i64>,
+}
+
+#[derive(Default)]
+struct AlignerState {
+    config: TimestampAlignment,
+    sources: HashMap<SourceId, AlignedSource>,
+    /// Fixed offsets in nanoseconds, kept across restarts of a source
+    extra: HashMap<SourceId, i64>,
+}
+
+impl AlignerState {
+    fn offset(&self, id: SourceId) -> Option<i64> {
+        let arrival = self.sources.get(&id)?.arrival?;
+        Some(arrival + self.extra.get(&id).copied().unwrap_or(0))
+    }
+}
+
+/// Pad offsets of every source, see the [module docs](self)
+#[derive(Default)]
+pub struct TimestampAligner {
+    state: Arc<Mutex<AlignerState>>,
+}
+
+impl TimestampAligner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> TimestampAlignment {
+        self.state.lock().unwrap().config.clone()
+    }
+
+    /// Modes for sources added from now on
+    pub fn set_config(&self, config: TimestampAlignment) {
+        self.state.lock().unwrap().config = config;
+    }
+
+    /// Offset in nanoseconds applied to the source's timestamps, once its
+    /// first buffer has arrived
+    pub fn offset(&self, id: SourceId) -> Option<i64> {
+        self.state.lock().unwrap().offset(id)
+    }
+
+    /// Add `offset_ns` to the source's timestamps on top of its alignment,
+    /// right away if it is playing
+    pub fn set_extra_offset(&self, id: SourceId, offset_ns: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.extra.insert(id, offset_ns);
+        let Some(offset) = state.offset(id) else {
+            return;
+        };
+        for pad in state.sources[&id]
+            .pads
+            .iter()
+            .filter_map(|pad| pad.upgrade())
+        {
+            pad.set_offset(offset);
+        }
+    }
+
+    /// Align the output pads of a source bin, including ones it adds later.
+    /// `pipeline` gives the running time sources are aligned to.
+    pub fn attach(&self, id: SourceId, uri: &str, bin: &gst::Element, pipeline: &gst::Element) {
+        let mode = {
+            let mut state = self.state.lock().unwrap();
+            state.sources.insert(id, AlignedSource::default());
+            state.config.mode_for(uri)
+        };
+
+        let state = Arc::downgrade(&self.state);
+        let pipeline = pipeline.downgrade();
+        let align = move |pad: &gst::Pad| {
+            if let Some(aligner) = state.upgrade() {
+                let mut aligner = aligner.lock().unwrap();
+                if let Some(source) = aligner.sources.get_mut(&id) {
+                    source.pads.push(pad.downgrade());
+                }
+            }
+            let state = state.clone();
+            let pipeline = pipeline.clone();
+            // Blocking, so the buffer waits in the probe until the offset
+            // is set; the pad then sends its segment again with the
+            // offset before it pushes the buffer
+            let probe = gst::PadProbeType::BUFFER | gst::PadProbeType::BLOCK;
+            pad.add_probe(probe, move |pad, info| {
+                let Some(state) = state.upgrade() else {
+                    return gst::PadProbeReturn::Remove;
+                };
+                let arrival = match mode {
+                    AlignMode::Keep => Some(0),
+                    AlignMode::Arrival => {
+                        let running_time =
+                            info.buffer().and_then(|buffer| running_time(pad, buffer));
+                        let now = pipeline.upgrade().and_then(|p| p.current_running_time());
+                        running_time
+                            .zip(now)
+                            .map(|(running_time, now)| arrival_offset(running_time, now))
+                    }
+                };
+                let Some(arrival) = arrival else {
+                    // Not timestamped yet; let it through and try again
+                    // with the next buffer
+                    return gst::PadProbeReturn::Pass;
+                };
+
+                let mut state = state.lock().unwrap();
+                let Some(source) = state.sources.get_mut(&id) else {
+                    return gst::PadProbeReturn::Remove;
+                };
+                // The first pad to see a buffer sets the offset for all
+                let arrival = *source.arrival.get_or_insert(arrival);
+                let offset = arrival + state.extra.get(&id).copied().unwrap_or(0);
+                pad.set_offset(offset);
+                gst::PadProbeReturn::Remove
+            });
+        };
+
+        for pad in bin.src_pads() {
+            align(&pad);
+        }
+        bin.connect_pad_added(move |_, pad| {
+            if pad.direction() == gst::PadDirection::Src {
+                align(pad);
+            }
+        });
+    }
+
+    /// Forget the source's pads, keeping its fixed offset for when it is
+    /// added again
+    pub fn detach(&self, id: SourceId) {
+        self.state.lock().unwrap().sources.remove(&id);
+    }
+
+    /// Forget everything about the source
+    pub fn remove(&self, id: SourceId) {
+        let mut state = self.state.lock().unwrap();
+        state.sources.remove(&id);
+        state.extra.remove(&id);
+    }
+}
+
+/// Running time of a buffer in the segment last sent on `pad`
+fn running_time(pad: &gst::Pad, buffer: &gst::BufferRef) -> Option<gst::ClockTime> {
+    let pts = buffer.pts()?;
+    let event = pad.sticky_event::<gst::event::Segment>(0)?;
+    let segment = event.segment().downcast_ref::<gst::ClockTime>()?;
+    segment.to_running_time(pts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_modes_keep_timestamps() {
+        let alignment = TimestampAlignment::default();
+        assert_eq!(alignment.mode_for("file:///lobby.mp4"), AlignMode::Keep);
+        assert_eq!(alignment.mode_for("/lobby.mp4"), AlignMode::Keep);
+        assert_eq!(
+            alignment.mode_for("rtsp://gate.local/stream"),
+            AlignMode::Keep
+        );
+        assert_eq!(
+            alignment.mode_for("https://cdn.local/live.m3u8"),
+            AlignMode::Keep
+        );
+    }
+
+    #[test]
+    fn test_mode_per_kind_from_config() {
+        let alignment: TimestampAlignment = toml::from_str(r#"file = "arrival""#).unwrap();
+        assert_eq!(alignment.mode_for("file:///lobby.mp4"), AlignMode::Arrival);
+        assert_eq!(alignment.live, AlignMode::Keep);
+    }
+
+    #[test]
+    fn test_arrival_offset() {
+        let second = gst::ClockTime::from_seconds(1);
+        assert_eq!(
+            arrival_offset(gst::ClockTime::ZERO, second * 5),
+            5_000_000_000
+        );
+        assert_eq!(arrival_offset(second * 5, second * 2), -3_000_000_000);
+    }
+
+    #[test]
+    fn test_extra_offset_added_to_arrival() {
+        let aligner = TimestampAligner::new();
+        let id = SourceId(3);
+        aligner.set_extra_offset(id, 40_000_000);
+        assert_eq!(aligner.offset(id), None);
+
+        aligner.state.lock().unwrap().sources.insert(
+            id,
+            AlignedSource {
+                pads: Vec::new(),
+                arrival: Some(1_000_000_000),
+            },
+        );
+        assert_eq!(aligner.offset(id), Some(1_040_000_000));
+    }
+
+    #[test]
+    fn test_extra_offset_kept_until_removed() {
+        let aligner = TimestampAligner::new();
+        let id = SourceId(3);
+        aligner.set_extra_offset(id, 40_000_000);
+
+        // A restart keeps the fixed offset until the source is removed
+        aligner.detach(id);
+        assert_eq!(
+            aligner.state.lock().unwrap().extra.get(&id),
+            Some(&40_000_000)
+        );
+        aligner.remove(id);
+        assert!(aligner.state.lock().unwrap().extra.is_empty());
+    }
+}
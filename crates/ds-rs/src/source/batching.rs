@@ -0,0 +1,204 @@
+//! Streammux batching that follows the number of active sources
+//!
+//! nvstreammux forms batches of `batch-size` frames and pushes a partial
+//! batch once `batched-push-timeout` passes. Set once for the most sources
+//! the pipeline may ever have, every batch waits out the timeout for
+//! frames that will not come and inference runs on mostly empty batches.
+//! With a [`BatchPolicy`] enabled, both properties are recomputed whenever
+//! a source is added, removed, paused or resumed. Muxers without a
+//! `batch-size` property, such as the Standard backend's compositor, are
+//! left alone.
+
+use super::{SourceEvent, SourceEventHandler, SourceManager, SourceState};
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, Weak};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchPolicy {
+    /// Smallest batch, kept even with fewer sources
+    pub min_batch_size: u32,
+    /// Largest batch; more sources than this share several batches per frame
+    pub max_batch_size: u32,
+    /// Frame rate the sources run at, which bounds how long a batch waits
+    pub frame_rate: f64,
+    /// Fixed `batched-push-timeout` in microseconds instead of one derived
+    /// from the frame rate
+    pub push_timeout_us: Option<u32>,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            min_batch_size: 1,
+            max_batch_size: 30,
+            frame_rate: 30.0,
+            push_timeout_us: None,
+        }
+    }
+}
+
+impl BatchPolicy {
+    /// `batch-size` for `sources` active sources
+    pub fn batch_size(&self, sources: usize) -> u32 {
+        let min = self.min_batch_size.max(1);
+        let max = self.max_batch_size.max(min);
+        (sources.min(u32::MAX as usize) as u32).clamp(min, max)
+    }
+
+    /// `batched-push-timeout` for `sources` active sources: one frame
+    /// interval, shared out between the batches that interval needs when
+    /// there are more sources than fit in one
+    pub fn push_timeout_us(&self, sources: usize) -> i32 {
+        if let Some(timeout) = self.push_timeout_us {
+            return timeout.min(i32::MAX as u32) as i32;
+        }
+        let interval = 1_000_000.0 / self.frame_rate.max(1.0);
+        let batches = sources.div_ceil(self.batch_size(sources) as usize).max(1);
+        (interval / batches as f64) as i32
+    }
+}
+
+/// Keeps a streammux's batching in step with its sources
+#[derive(Default)]
+pub struct MuxBatcher {
+    policy: Mutex<Option<BatchPolicy>>,
+    /// Batch size and timeout last set on the muxer
+    applied: Mutex<Option<(u32, i32)>>,
+}
+
+impl MuxBatcher {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Recompute the batching of `manager`'s muxer after every event from
+    /// `handler` that changes how many sources are active
+    pub fn follow(self: &Arc<Self>, handler: &SourceEventHandler, manager: Weak<SourceManager>) {
+        let batcher = Arc::downgrade(self);
+        handler.register_callback(move |event| {
+            if !matches!(
+                event,
+                SourceEvent::SourceAdded { .. }
+                    | SourceEvent::SourceRemoved { .. }
+                    | SourceEvent::StateChanged { .. }
+            ) {
+                return;
+            }
+            if let (Some(batcher), Some(manager)) = (batcher.upgrade(), manager.upgrade()) {
+                batcher.update(&manager);
+            }
+        });
+    }
+
+    pub fn policy(&self) -> Option<BatchPolicy> {
+        self.policy.lock().unwrap().clone()
+    }
+
+    /// Batch to `policy` from now on, or keep the muxer's current settings
+    /// with `None`
+    pub fn set_policy(&self, manager: &SourceManager, policy: Option<BatchPolicy>) {
+        let enabled = policy.is_some();
+        *self.policy.lock().unwrap() = policy;
+        *self.applied.lock().unwrap() = None;
+        if enabled {
+            self.update(manager);
+        }
+    }
+
+    /// Batch size and timeout last set on the muxer
+    pub fn applied(&self) -> Option<(u32, i32)> {
+        *self.applied.lock().unwrap()
+    }
+
+    fn update(&self, manager: &SourceManager) {
+        let Some(policy) = self.policy() else {
+            return;
+        };
+        let Some(mux) = manager.get_streammux() else {
+            return;
+        };
+        if mux.find_property("batch-size").is_none() {
+            return;
+        }
+
+        let sources = active_sources(manager);
+        let settings = (policy.batch_size(sources), policy.push_timeout_us(sources));
+        let mut applied = self.applied.lock().unwrap();
+        if *applied == Some(settings) {
+            return;
+        }
+        // The property types differ between muxer versions
+        mux.set_property_from_str("batch-size", &settings.0.to_string());
+        if mux.find_property("batched-push-timeout").is_some() {
+            mux.set_property_from_str("batched-push-timeout", &settings.1.to_string());
+        }
+        log::info!(
+            "Streammux batch-size {} with {}us push timeout for {} active sources",
+            settings.0,
+            settings.1,
+            sources
+        );
+        *applied = Some(settings);
+    }
+}
+
+/// Sources that are not paused, since the muxer gets no frames from those
+fn active_sources(manager: &SourceManager) -> usize {
+    manager
+        .list_sources()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&id| {
+            manager
+                .get_source_info(id)
+                .is_ok_and(|info| info.state != SourceState::Paused)
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> BatchPolicy {
+        BatchPolicy {
+            max_batch_size: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_batch_size_follows_source_count() {
+        let policy = policy();
+        assert_eq!(policy.batch_size(0), 1);
+        assert_eq!(policy.batch_size(3), 3);
+        assert_eq!(policy.batch_size(9), 4);
+    }
+
+    #[test]
+    fn test_push_timeout_per_batch() {
+        let policy = policy();
+        assert_eq!(policy.push_timeout_us(3), 33_333);
+        // Nine sources in batches of four need three batches per frame
+        assert_eq!(policy.push_timeout_us(9), 11_111);
+    }
+
+    #[test]
+    fn test_fixed_push_timeout() {
+        let fixed = BatchPolicy {
+            push_timeout_us: Some(40_000),
+            ..policy()
+        };
+        assert_eq!(fixed.push_timeout_us(9), 40_000);
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        let policy: BatchPolicy = toml::from_str("max_batch_size = 8").unwrap();
+        assert_eq!(policy.batch_size(16), 8);
+        assert_eq!(policy.min_batch_size, 1);
+    }
+}
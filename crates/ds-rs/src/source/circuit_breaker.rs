@@ -1,6 +1,11 @@
+use serde::Serialize;
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Number of state transitions kept per breaker
+const HISTORY_LIMIT: usize = 32;
 
 /// State of the circuit breaker
 #[derive(Debug, Clone, PartialEq)]
@@ -16,6 +21,56 @@ pub enum CircuitState {
     },
 }
 
+impl CircuitState {
+    pub fn kind(&self) -> CircuitStateKind {
+        match self {
+            CircuitState::Closed => CircuitStateKind::Closed,
+            CircuitState::Open { .. } => CircuitStateKind::Open,
+            CircuitState::HalfOpen { .. } => CircuitStateKind::HalfOpen,
+        }
+    }
+}
+
+/// Circuit state without its bookkeeping, for reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitStateKind {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// A recorded change of circuit state
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitTransition {
+    pub at: SystemTime,
+    pub from: CircuitStateKind,
+    pub to: CircuitStateKind,
+    pub reason: String,
+    /// Triggered by an operator rather than by request outcomes
+    pub manual: bool,
+}
+
+/// Point-in-time view of a breaker for monitoring and control endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitBreakerSnapshot {
+    pub name: String,
+    pub state: CircuitStateKind,
+    /// Why the circuit opened, while it is open
+    pub open_reason: Option<String>,
+    /// Held open by a manual trip until reset
+    pub held_open: bool,
+    /// Failures inside the current window
+    pub recent_failures: usize,
+    pub total_requests: usize,
+    pub successful_requests: usize,
+    pub failed_requests: usize,
+    pub rejected_requests: usize,
+    pub circuit_opens: usize,
+    /// Oldest first
+    pub transitions: Vec<CircuitTransition>,
+}
+
 /// Configuration for circuit breaker behavior
 #[derive(Debug, Clone)]
 pub struct CircuitBreakerConfig {
@@ -66,6 +121,8 @@ pub struct CircuitBreaker {
     failure_times: Arc<Mutex<VecDeque<Instant>>>,
     success_count: Arc<Mutex<usize>>,
     metrics: Arc<Mutex<CircuitMetrics>>,
+    transitions: Arc<Mutex<VecDeque<CircuitTransition>>>,
+    held_open: AtomicBool,
 }
 
 impl CircuitBreaker {
@@ -77,9 +134,44 @@ impl CircuitBreaker {
             failure_times: Arc::new(Mutex::new(VecDeque::new())),
             success_count: Arc::new(Mutex::new(0)),
             metrics: Arc::new(Mutex::new(CircuitMetrics::default())),
+            transitions: Arc::new(Mutex::new(VecDeque::new())),
+            held_open: AtomicBool::new(false),
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Move to `new_state`, recording the change if the kind of state differs.
+    /// Callers hold the state lock.
+    fn transition(
+        &self,
+        state: &mut CircuitState,
+        new_state: CircuitState,
+        reason: &str,
+        manual: bool,
+    ) {
+        let from = state.kind();
+        let to = new_state.kind();
+        *state = new_state;
+        if from == to {
+            return;
+        }
+
+        let mut transitions = self.transitions.lock().unwrap();
+        if transitions.len() == HISTORY_LIMIT {
+            transitions.pop_front();
+        }
+        transitions.push_back(CircuitTransition {
+            at: SystemTime::now(),
+            from,
+            to,
+            reason: reason.to_string(),
+            manual,
+        });
+    }
+
     /// Check if a request should be allowed
     pub fn should_allow_request(&self) -> bool {
         let mut state = self.state.lock().unwrap();
@@ -89,11 +181,18 @@ impl CircuitBreaker {
             CircuitState::Closed => true,
             CircuitState::Open { opened_at, .. } => {
                 // Check if it's time to transition to half-open
-                if now.duration_since(*opened_at) >= self.config.open_duration {
-                    *state = CircuitState::HalfOpen {
-                        started_at: now,
-                        test_count: 0,
-                    };
+                if !self.held_open.load(Ordering::SeqCst)
+                    && now.duration_since(*opened_at) >= self.config.open_duration
+                {
+                    self.transition(
+                        &mut state,
+                        CircuitState::HalfOpen {
+                            started_at: now,
+                            test_count: 0,
+                        },
+                        "open duration elapsed",
+                        false,
+                    );
                     true
                 } else {
                     // Still open, reject request
@@ -141,7 +240,12 @@ impl CircuitBreaker {
 
                 // Check if we should close the circuit
                 if *success_count >= self.config.success_threshold {
-                    *state = CircuitState::Closed;
+                    self.transition(
+                        &mut state,
+                        CircuitState::Closed,
+                        "recovered in half-open",
+                        false,
+                    );
                     *success_count = 0;
 
                     // Clear failure history
@@ -196,10 +300,15 @@ impl CircuitBreaker {
             CircuitState::Closed => {
                 // Check if we should open the circuit
                 if failures.len() >= self.config.failure_threshold {
-                    *state = CircuitState::Open {
-                        opened_at: now,
-                        reason: reason.clone(),
-                    };
+                    self.transition(
+                        &mut state,
+                        CircuitState::Open {
+                            opened_at: now,
+                            reason: reason.clone(),
+                        },
+                        &reason,
+                        false,
+                    );
                     metrics.circuit_opens += 1;
 
                     log::warn!(
@@ -212,10 +321,15 @@ impl CircuitBreaker {
             }
             CircuitState::HalfOpen { .. } => {
                 // Failure in half-open state, reopen circuit
-                *state = CircuitState::Open {
-                    opened_at: now,
-                    reason: reason.clone(),
-                };
+                self.transition(
+                    &mut state,
+                    CircuitState::Open {
+                        opened_at: now,
+                        reason: reason.clone(),
+                    },
+                    &reason,
+                    false,
+                );
                 metrics.circuit_opens += 1;
 
                 // Reset success count
@@ -257,10 +371,96 @@ impl CircuitBreaker {
         }
     }
 
-    /// Reset the circuit breaker
+    /// Failures inside the current window
+    pub fn recent_failures(&self) -> usize {
+        let failures = self.failure_times.lock().unwrap();
+        let cutoff = Instant::now().checked_sub(self.config.window_duration);
+        failures
+            .iter()
+            .filter(|&&at| cutoff.is_none_or(|cutoff| at >= cutoff))
+            .count()
+    }
+
+    /// Recorded state transitions, oldest first
+    pub fn transitions(&self) -> Vec<CircuitTransition> {
+        self.transitions.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn is_held_open(&self) -> bool {
+        self.held_open.load(Ordering::SeqCst)
+    }
+
+    pub fn snapshot(&self) -> CircuitBreakerSnapshot {
+        let state = self.get_state();
+        let open_reason = match &state {
+            CircuitState::Open { reason, .. } => Some(reason.clone()),
+            _ => None,
+        };
+        let metrics = self.get_metrics();
+
+        CircuitBreakerSnapshot {
+            name: self.name.clone(),
+            state: state.kind(),
+            open_reason,
+            held_open: self.is_held_open(),
+            recent_failures: self.recent_failures(),
+            total_requests: metrics.total_requests,
+            successful_requests: metrics.successful_requests,
+            failed_requests: metrics.failed_requests,
+            rejected_requests: metrics.rejected_requests,
+            circuit_opens: metrics.circuit_opens,
+            transitions: self.transitions(),
+        }
+    }
+
+    /// Open the circuit on operator request. It stays open, rejecting
+    /// requests, until [`close`](Self::close) or [`reset`](Self::reset).
+    pub fn trip(&self, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        self.held_open.store(true, Ordering::SeqCst);
+        if !matches!(*state, CircuitState::Open { .. }) {
+            self.metrics.lock().unwrap().circuit_opens += 1;
+        }
+        self.transition(
+            &mut state,
+            CircuitState::Open {
+                opened_at: Instant::now(),
+                reason: reason.to_string(),
+            },
+            reason,
+            true,
+        );
+        *self.success_count.lock().unwrap() = 0;
+
+        log::warn!(
+            "Circuit breaker '{}' tripped manually: {}",
+            self.name,
+            reason
+        );
+    }
+
+    /// Close the circuit on operator request, forgetting recent failures but
+    /// keeping metrics and history
+    pub fn close(&self, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        self.held_open.store(false, Ordering::SeqCst);
+        self.transition(&mut state, CircuitState::Closed, reason, true);
+        self.failure_times.lock().unwrap().clear();
+        *self.success_count.lock().unwrap() = 0;
+
+        log::info!(
+            "Circuit breaker '{}' closed manually: {}",
+            self.name,
+            reason
+        );
+    }
+
+    /// Reset the circuit breaker, including metrics and history
     pub fn reset(&self) {
         let mut state = self.state.lock().unwrap();
         *state = CircuitState::Closed;
+        self.held_open.store(false, Ordering::SeqCst);
+        self.transitions.lock().unwrap().clear();
 
         let mut failures = self.failure_times.lock().unwrap();
         failures.clear();
@@ -277,7 +477,7 @@ impl CircuitBreaker {
     /// Force the circuit to a specific state (for testing/management)
     pub fn force_state(&self, new_state: CircuitState) {
         let mut state = self.state.lock().unwrap();
-        *state = new_state;
+        self.transition(&mut state, new_state, "forced", true);
 
         log::info!(
             "Circuit breaker '{}' forced to state: {:?}",
@@ -309,12 +509,29 @@ impl CircuitBreakerManager {
             .clone()
     }
 
+    /// Get an existing circuit breaker
+    pub fn get(&self, name: &str) -> Option<Arc<CircuitBreaker>> {
+        self.breakers.lock().unwrap().get(name).cloned()
+    }
+
     /// Get all circuit breakers
     pub fn get_all(&self) -> Vec<Arc<CircuitBreaker>> {
         let breakers = self.breakers.lock().unwrap();
         breakers.values().cloned().collect()
     }
 
+    /// Drop a circuit breaker, e.g. when its source is removed
+    pub fn remove(&self, name: &str) -> Option<Arc<CircuitBreaker>> {
+        self.breakers.lock().unwrap().remove(name)
+    }
+
+    /// Snapshots of every breaker, sorted by name
+    pub fn snapshots(&self) -> Vec<CircuitBreakerSnapshot> {
+        let mut snapshots: Vec<_> = self.get_all().iter().map(|b| b.snapshot()).collect();
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        snapshots
+    }
+
     /// Reset all circuit breakers
     pub fn reset_all(&self) {
         let breakers = self.breakers.lock().unwrap();
@@ -423,6 +640,79 @@ mod tests {
         assert_eq!(metrics.failed_requests, 1);
     }
 
+    #[test]
+    fn test_manual_trip_holds_open() {
+        let config = CircuitBreakerConfig {
+            open_duration: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new("test".to_string(), config);
+
+        breaker.trip("maintenance");
+        assert!(!breaker.should_allow_request());
+
+        // A manual trip holds past the open duration
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!breaker.should_allow_request());
+
+        let snapshot = breaker.snapshot();
+        assert_eq!(snapshot.state, CircuitStateKind::Open);
+        assert_eq!(snapshot.circuit_opens, 1);
+        assert_eq!(snapshot.rejected_requests, 2);
+    }
+
+    #[test]
+    fn test_manual_close() {
+        let breaker = CircuitBreaker::new("test".to_string(), CircuitBreakerConfig::default());
+        breaker.trip("maintenance");
+
+        breaker.close("maintenance done");
+        assert!(breaker.should_allow_request());
+
+        let snapshot = breaker.snapshot();
+        assert_eq!(snapshot.state, CircuitStateKind::Closed);
+        assert_eq!(snapshot.transitions.len(), 2);
+        assert!(snapshot.transitions.iter().all(|t| t.manual));
+        assert_eq!(snapshot.transitions[0].to, CircuitStateKind::Open);
+        assert_eq!(snapshot.transitions[0].reason, "maintenance");
+    }
+
+    #[test]
+    fn test_transition_history() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            success_threshold: 1,
+            open_duration: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new("test".to_string(), config);
+
+        breaker.record_failure("Error 1".to_string());
+        assert!(breaker.transitions().is_empty());
+        assert_eq!(breaker.recent_failures(), 1);
+
+        breaker.record_failure("Error 2".to_string());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(breaker.should_allow_request());
+        breaker.record_success();
+
+        let kinds: Vec<_> = breaker
+            .transitions()
+            .iter()
+            .map(|t| (t.from, t.to))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (CircuitStateKind::Closed, CircuitStateKind::Open),
+                (CircuitStateKind::Open, CircuitStateKind::HalfOpen),
+                (CircuitStateKind::HalfOpen, CircuitStateKind::Closed),
+            ]
+        );
+        assert_eq!(breaker.transitions()[0].reason, "Error 2");
+        assert_eq!(breaker.recent_failures(), 0);
+    }
+
     #[test]
     fn test_circuit_breaker_manager() {
         let manager = CircuitBreakerManager::new();
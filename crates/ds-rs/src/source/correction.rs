@@ -0,0 +1,249 @@
+//! Per-source video corrections ahead of the streammux
+//!
+//! Deinterlacing, rotation and flips, cropping and cropping to an aspect
+//! ratio run in a small bin between a source and the streammux (or its
+//! fallback slate). Every element is present whatever the settings, so any
+//! of them can be changed while the source plays. The elements work on
+//! system memory, which suits the Standard backend and sources decoded
+//! without NVMM.
+
+use super::manager::{install_stage, remove_stage};
+use super::{SourceId, SourceManager};
+use crate::error::{DeepStreamError, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeinterlaceMode {
+    #[default]
+    Off,
+    /// Deinterlace only frames flagged as interlaced
+    Auto,
+    /// Deinterlace every frame
+    On,
+}
+
+impl DeinterlaceMode {
+    fn nick(&self) -> &'static str {
+        match self {
+            Self::Off => "disabled",
+            Self::Auto => "auto",
+            Self::On => "interlaced",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    #[default]
+    Identity,
+    /// Clockwise
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+impl Orientation {
+    fn nick(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Rotate90 => "90r",
+            Self::Rotate180 => "180",
+            Self::Rotate270 => "90l",
+            Self::FlipHorizontal => "horiz",
+            Self::FlipVertical => "vert",
+        }
+    }
+}
+
+/// Pixels to cut from each edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Crop {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoCorrection {
+    pub deinterlace: DeinterlaceMode,
+    pub orientation: Orientation,
+    /// Crop the picture to this shape, e.g. `"16:9"`
+    pub aspect_ratio: Option<String>,
+    pub crop: Crop,
+}
+
+impl VideoCorrection {
+    /// Nothing to correct
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `aspect_ratio` as a fraction
+    pub fn aspect_fraction(&self) -> Result<Option<(i32, i32)>> {
+        let Some(ratio) = self.aspect_ratio.as_deref() else {
+            return Ok(None);
+        };
+        let invalid = || {
+            DeepStreamError::InvalidInput(format!(
+                "Invalid aspect ratio: {}. Use width:height, e.g. 16:9.",
+                ratio
+            ))
+        };
+        let (num, den) = ratio.split_once([':', '/']).ok_or_else(invalid)?;
+        let num: i32 = num.trim().parse().map_err(|_| invalid())?;
+        let den: i32 = den.trim().parse().map_err(|_| invalid())?;
+        if num <= 0 || den <= 0 {
+            return Err(invalid());
+        }
+        Ok(Some((num, den)))
+    }
+}
+
+/// The correction bin in front of one source
+pub struct CorrectionStage {
+    id: SourceId,
+    bin: gst::Bin,
+    deinterlace: gst::Element,
+    flip: gst::Element,
+    crop: gst::Element,
+    aspect: gst::Element,
+    current: Mutex<VideoCorrection>,
+}
+
+impl CorrectionStage {
+    pub fn new(id: SourceId, correction: &VideoCorrection) -> Result<Self> {
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("correction-{}-{}", factory, id.0))
+                .build()
+                .map_err(|_| DeepStreamError::ElementCreation {
+                    element: format!("{} for source {}", factory, id),
+                })
+        };
+
+        let bin = gst::Bin::builder()
+            .name(format!("correction-bin-{:02}", id.0))
+            .build();
+        let convert = make("videoconvert")?;
+        let deinterlace = make("deinterlace")?;
+        let flip = make("videoflip")?;
+        let crop = make("videocrop")?;
+        let aspect = make("aspectratiocrop")?;
+
+        let chain = [&convert, &deinterlace, &flip, &crop, &aspect];
+        bin.add_many(chain)?;
+        gst::Element::link_many(chain)?;
+
+        let sink = convert
+            .static_pad("sink")
+            .expect("videoconvert has a sink pad");
+        let src = aspect
+            .static_pad("src")
+            .expect("aspectratiocrop has a src pad");
+        bin.add_pad(
+            &gst::GhostPad::builder_with_target(&sink)?
+                .name("sink")
+                .build(),
+        )?;
+        bin.add_pad(
+            &gst::GhostPad::builder_with_target(&src)?
+                .name("src")
+                .build(),
+        )?;
+
+        let stage = Self {
+            id,
+            bin,
+            deinterlace,
+            flip,
+            crop,
+            aspect,
+            current: Mutex::new(VideoCorrection::default()),
+        };
+        stage.apply(correction)?;
+        Ok(stage)
+    }
+
+    /// Change the corrections, also while playing
+    pub fn apply(&self, correction: &VideoCorrection) -> Result<()> {
+        let (num, den) = correction.aspect_fraction()?.unwrap_or((0, 1));
+
+        self.deinterlace
+            .set_property_from_str("mode", correction.deinterlace.nick());
+        self.flip
+            .set_property_from_str("video-direction", correction.orientation.nick());
+        self.crop.set_property("top", correction.crop.top as i32);
+        self.crop
+            .set_property("bottom", correction.crop.bottom as i32);
+        self.crop.set_property("left", correction.crop.left as i32);
+        self.crop
+            .set_property("right", correction.crop.right as i32);
+        self.aspect
+            .set_property("aspect-ratio", gst::Fraction::new(num, den));
+
+        *self.current.lock().unwrap() = correction.clone();
+        Ok(())
+    }
+
+    pub fn current(&self) -> VideoCorrection {
+        self.current.lock().unwrap().clone()
+    }
+
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    /// Where the source's video pad links
+    pub fn sink_pad(&self) -> gst::Pad {
+        self.bin
+            .static_pad("sink")
+            .expect("correction bin has a sink pad")
+    }
+
+    /// Put the bin in the pipeline, feeding `downstream` or, without one,
+    /// the source's streammux pad
+    pub fn install(&self, manager: &SourceManager, downstream: Option<&gst::Pad>) -> Result<()> {
+        install_stage(manager, self.id, self.bin.upcast_ref(), downstream)
+    }
+
+    pub fn remove(&self, manager: &SourceManager) -> Result<()> {
+        remove_stage(manager, self.bin.upcast_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correction_config() {
+        let correction: VideoCorrection = toml::from_str(
+            "deinterlace = \"auto\"\norientation = \"rotate90\"\naspect_ratio = \"16:9\"\n\n[crop]\ntop = 8\n",
+        )
+        .unwrap();
+        assert_eq!(correction.orientation, Orientation::Rotate90);
+        assert_eq!(correction.crop.top, 8);
+        assert_eq!(correction.aspect_fraction().unwrap(), Some((16, 9)));
+        assert!(!correction.is_identity());
+        assert!(VideoCorrection::default().is_identity());
+    }
+
+    #[test]
+    fn test_invalid_aspect_ratio() {
+        let bad = VideoCorrection {
+            aspect_ratio: Some("wide".to_string()),
+            ..Default::default()
+        };
+        assert!(bad.aspect_fraction().is_err());
+    }
+}
@@ -0,0 +1,415 @@
+//! What a source does when it runs out of data
+//!
+//! Each source's output pads get an event probe that catches EOS before it
+//! reaches the streammux. `Loop` and `Freeze` are handled right there;
+//! the other policies emit [`SourceEvent::Eos`] and the controller acts on
+//! it off the streaming thread. Every source that ends also posts a
+//! `stream-eos` message on the bus, as nvstreammux does on DeepStream.
+//!
+//! A looping source never sends EOS: once it starts, its demuxer (or the
+//! source element itself) gets a non-flushing segment seek, so it ends
+//! with segment-done instead and is seeked back to the start from there.
+//! Nothing is flushed, so the streammux keeps batching the other sources.
+
+use super::{SourceEvent, SourceEventHandler, SourceId};
+use crate::error::{DeepStreamError, Result};
+use crate::messages::stream_eos_message;
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+
+/// URI of the pattern that replaces a source under [`EosPolicy::Standby`]
+pub const STANDBY_URI: &str = "videotestsrc://";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EosPolicy {
+    /// Let EOS through to the muxer and keep the source until removed
+    #[default]
+    Forward,
+    /// Take the source out of the pipeline and the streammux
+    Remove,
+    /// Seek back to the start and keep playing
+    Loop,
+    /// Swallow EOS so the muxer keeps the last frame
+    Freeze,
+    /// Swap in a test pattern under the same source ID
+    Standby,
+}
+
+impl EosPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Remove => "remove",
+            Self::Loop => "loop",
+            Self::Freeze => "freeze",
+            Self::Standby => "standby",
+        }
+    }
+}
+
+impl FromStr for EosPolicy {
+    type Err = DeepStreamError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "forward" => Ok(Self::Forward),
+            "remove" => Ok(Self::Remove),
+            "loop" => Ok(Self::Loop),
+            "freeze" | "hold" => Ok(Self::Freeze),
+            "standby" => Ok(Self::Standby),
+            _ => Err(DeepStreamError::InvalidInput(format!(
+                "Unknown EOS policy: {}. Use forward, remove, loop, freeze or standby.",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for EosPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// EOS policy of every source, falling back to a default
+#[derive(Debug, Default)]
+pub struct EosPolicies {
+    default: Mutex<EosPolicy>,
+    sources: Mutex<HashMap<SourceId, EosPolicy>>,
+}
+
+impl EosPolicies {
+    pub fn new(default: EosPolicy) -> Self {
+        Self {
+            default: Mutex::new(default),
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, id: SourceId) -> EosPolicy {
+        self.sources
+            .lock()
+            .unwrap()
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| self.default_policy())
+    }
+
+    pub fn set(&self, id: SourceId, policy: EosPolicy) {
+        self.sources.lock().unwrap().insert(id, policy);
+    }
+
+    /// Go back to the default for a source
+    pub fn clear(&self, id: SourceId) {
+        self.sources.lock().unwrap().remove(&id);
+    }
+
+    pub fn default_policy(&self) -> EosPolicy {
+        *self.default.lock().unwrap()
+    }
+
+    pub fn set_default_policy(&self, policy: EosPolicy) {
+        *self.default.lock().unwrap() = policy;
+    }
+}
+
+/// Where a looping source is seeked, and whether it is in segment mode
+#[derive(Default)]
+struct LoopState {
+    target: Option<gst::Pad>,
+    /// Set while the source ends in segment-done rather than EOS
+    segment_mode: bool,
+}
+
+/// Catch EOS on every output pad of a source bin, present and future
+pub fn install_eos_probe(
+    bin: &gst::Element,
+    id: SourceId,
+    policies: Arc<EosPolicies>,
+    events: Weak<SourceEventHandler>,
+) {
+    let probe = move |pad: &gst::Pad| {
+        let policies = policies.clone();
+        let events = events.clone();
+        let state = Mutex::new(LoopState::default());
+        pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |pad, info| {
+            let Some(gst::PadProbeData::Event(event)) = &info.data else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let event_type = event.type_();
+            let policy = policies.get(id);
+            let mut state = state.lock().unwrap();
+            match event_type {
+                gst::EventType::Segment if policy == EosPolicy::Loop && !state.segment_mode => {
+                    if state.target.is_none() {
+                        state.target = seek_pad(pad);
+                        if let Some(target) = &state.target {
+                            keep_running_time(target);
+                        }
+                    }
+                    state.segment_mode = seek_to_start(state.target.as_ref(), id);
+                    return gst::PadProbeReturn::Ok;
+                }
+                // A flushing seek from elsewhere ends segment mode
+                gst::EventType::FlushStop => {
+                    state.segment_mode = false;
+                    return gst::PadProbeReturn::Ok;
+                }
+                gst::EventType::SegmentDone if state.segment_mode => {
+                    if policy == EosPolicy::Loop {
+                        seek_to_start(state.target.as_ref(), id);
+                        return gst::PadProbeReturn::Drop;
+                    }
+                    // No longer looping, so this is the source's EOS
+                    state.segment_mode = false;
+                    info.data = Some(gst::PadProbeData::Event(gst::event::Eos::new()));
+                }
+                gst::EventType::Eos if policy == EosPolicy::Loop => {
+                    log::warn!("Source {} cannot loop, keeping its last frame", id);
+                }
+                gst::EventType::Eos => {}
+                _ => return gst::PadProbeReturn::Ok,
+            }
+            drop(state);
+
+            if let Some(bin) = pad.parent_element() {
+                let _ = bin.post_message(stream_eos_message(&bin, id.0 as u32));
+            }
+            if let Some(events) = events.upgrade() {
+                let _ = events.emit(SourceEvent::Eos { id });
+            }
+            match policy {
+                EosPolicy::Forward => gst::PadProbeReturn::Ok,
+                _ => gst::PadProbeReturn::Drop,
+            }
+        });
+    };
+
+    for pad in bin.src_pads() {
+        probe(&pad);
+    }
+    bin.connect_pad_added(move |_, pad| {
+        if pad.direction() == gst::PadDirection::Src {
+            probe(pad);
+        }
+    });
+}
+
+/// Seek `target` back to the start in segment mode, off the streaming
+/// thread. False if there is nothing to seek.
+fn seek_to_start(target: Option<&gst::Pad>, id: SourceId) -> bool {
+    let Some(target) = target.cloned() else {
+        return false;
+    };
+    let Some(element) = target.parent_element() else {
+        return false;
+    };
+    element.call_async(move |_| {
+        let seek = gst::event::Seek::new(
+            1.0,
+            gst::SeekFlags::SEGMENT,
+            gst::SeekType::Set,
+            gst::ClockTime::ZERO,
+            gst::SeekType::None,
+            gst::ClockTime::NONE,
+        );
+        if !target.send_event(seek) {
+            log::warn!("Failed to loop source {}", id);
+        }
+    });
+    true
+}
+
+/// Src pad of the demuxer, or failing that the source element, that feeds
+/// `pad` of a source bin. A bin only sends seeks to its sinks, so a source
+/// bin has to be seeked from in here.
+fn seek_pad(pad: &gst::Pad) -> Option<gst::Pad> {
+    let mut pad = pad.clone();
+    loop {
+        if let Some(ghost) = pad.downcast_ref::<gst::GhostPad>() {
+            pad = ghost.target()?;
+            continue;
+        }
+        // The inside of a ghost sink pad; carry on past the bin's input
+        if let Some(ghost) = pad
+            .parent()
+            .and_then(|parent| parent.downcast::<gst::GhostPad>().ok())
+        {
+            pad = ghost.peer()?;
+            continue;
+        }
+
+        let element = pad.parent_element()?;
+        let demuxer = element
+            .factory()
+            .is_some_and(|factory| factory.klass().contains("Demux"));
+        let input = pad
+            .iterate_internal_links()
+            .into_iter()
+            .filter_map(|link| link.ok())
+            .find(|link| link.direction() == gst::PadDirection::Sink);
+        match input {
+            Some(input) if !demuxer => pad = input.peer()?,
+            _ => return Some(pad),
+        }
+    }
+}
+
+/// Shift each loop on `pad` to start where the last one ended, for
+/// elements whose segments start the running time over after a seek
+fn keep_running_time(pad: &gst::Pad) {
+    #[derive(Default)]
+    struct Loop {
+        /// Running time at the end of the last buffer, offset included
+        end: Option<i64>,
+        new_segment: bool,
+    }
+    let state = Mutex::new(Loop::default());
+    let probe = gst::PadProbeType::BUFFER | gst::PadProbeType::EVENT_DOWNSTREAM;
+    pad.add_probe(probe, move |pad, info| {
+        let mut state = state.lock().unwrap();
+        match &info.data {
+            Some(gst::PadProbeData::Event(event)) => match event.type_() {
+                gst::EventType::Segment => state.new_segment = true,
+                gst::EventType::FlushStop => *state = Loop::default(),
+                _ => {}
+            },
+            Some(gst::PadProbeData::Buffer(buffer)) => {
+                let Some(start) = running_time(pad, buffer.pts()) else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                let mut start = start + pad.offset();
+                if std::mem::take(&mut state.new_segment)
+                    && let Some(end) = state.end
+                    && start < end
+                {
+                    // The pad sends its segment again with the new offset
+                    // before this buffer
+                    pad.set_offset(pad.offset() + end - start);
+                    start = end;
+                }
+                let duration = buffer.duration().map_or(0, |d| d.nseconds() as i64);
+                state.end = Some(start + duration);
+            }
+            _ => {}
+        }
+        gst::PadProbeReturn::Ok
+    });
+}
+
+/// Running time in nanoseconds of `pts` in the segment last sent on `pad`
+fn running_time(pad: &gst::Pad, pts: Option<gst::ClockTime>) -> Option<i64> {
+    let event = pad.sticky_event::<gst::event::Segment>(0)?;
+    let segment = event.segment().downcast_ref::<gst::ClockTime>()?;
+    Some(segment.to_running_time(pts?)?.nseconds() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_parse_eos_policy() {
+        assert_eq!("Loop".parse::<EosPolicy>().unwrap(), EosPolicy::Loop);
+        assert_eq!("hold".parse::<EosPolicy>().unwrap(), EosPolicy::Freeze);
+        assert!("rewind".parse::<EosPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_eos_policies() {
+        let policies = EosPolicies::default();
+        assert_eq!(policies.get(SourceId(1)), EosPolicy::Forward);
+        policies.set(SourceId(1), EosPolicy::Standby);
+        policies.set_default_policy(EosPolicy::Remove);
+        assert_eq!(policies.get(SourceId(1)), EosPolicy::Standby);
+        assert_eq!(policies.get(SourceId(2)), EosPolicy::Remove);
+    }
+
+    /// A 10-frame source: 100 fps up to a stop at 100 ms
+    fn short_source() -> gst::Bin {
+        let bin = gst::Bin::with_name("short-source");
+        let src = gst::ElementFactory::make("videotestsrc").build().unwrap();
+        let caps = gst::ElementFactory::make("capsfilter")
+            .property(
+                "caps",
+                gst::Caps::builder("video/x-raw")
+                    .field("width", 64)
+                    .field("height", 48)
+                    .field("framerate", gst::Fraction::new(100, 1))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        bin.add_many([&src, &caps]).unwrap();
+        src.link(&caps).unwrap();
+        let output = gst::GhostPad::with_target(&caps.static_pad("src").unwrap()).unwrap();
+        bin.add_pad(&output).unwrap();
+
+        // Applied when the source starts
+        let stop = gst::event::Seek::new(
+            1.0,
+            gst::SeekFlags::ACCURATE,
+            gst::SeekType::Set,
+            gst::ClockTime::ZERO,
+            gst::SeekType::Set,
+            gst::ClockTime::from_mseconds(100),
+        );
+        assert!(src.send_event(stop));
+        bin
+    }
+
+    #[test]
+    fn test_loop_plays_again() {
+        gst::init().unwrap();
+        let pipeline = gst::Pipeline::with_name("eos-loop");
+        let source = short_source();
+        let sink = gst::ElementFactory::make("fakesink")
+            .property("sync", false)
+            .build()
+            .unwrap();
+        pipeline.add_many([source.upcast_ref(), &sink]).unwrap();
+        source.link(&sink).unwrap();
+
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+        sink.static_pad("sink")
+            .unwrap()
+            .add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                gst::PadProbeReturn::Ok
+            });
+        let policies = Arc::new(EosPolicies::new(EosPolicy::Loop));
+        install_eos_probe(source.upcast_ref(), SourceId(0), policies, Weak::new());
+
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let bus = pipeline.bus().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while frames.load(Ordering::SeqCst) <= 30 && Instant::now() < deadline {
+            let message = bus.timed_pop_filtered(
+                gst::ClockTime::from_mseconds(50),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            );
+            assert!(message.is_none(), "{:?}", message);
+        }
+        pipeline.set_state(gst::State::Null).unwrap();
+
+        assert!(frames.load(Ordering::SeqCst) > 30);
+    }
+
+    #[test]
+    fn test_clear_falls_back_to_default() {
+        let policies = EosPolicies::default();
+        policies.set(SourceId(1), EosPolicy::Standby);
+        policies.set_default_policy(EosPolicy::Remove);
+        policies.clear(SourceId(1));
+        assert_eq!(policies.get(SourceId(1)), EosPolicy::Remove);
+    }
+}
@@ -1,9 +1,19 @@
+use super::isolation::catch_panic;
 use super::{SourceId, SourceState};
-use crate::error::Result;
+use crate::error::{DeepStreamError, ErrorClassification, Result, classify};
+use crate::pipeline::SourceMessageHandler;
+use crate::rules::WebhookHandle;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::sync::mpsc::{Receiver, Sender, channel};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Events buffered per async subscriber before it starts lagging
+const BROADCAST_CAPACITY: usize = 256;
+
+type EventCallback = Arc<dyn Fn(&SourceEvent) + Send + Sync + 'static>;
 
 #[derive(Debug, Clone)]
 pub enum SourceEvent {
@@ -33,26 +43,153 @@ pub enum SourceEvent {
     Error {
         id: SourceId,
         error: String,
+        classification: ErrorClassification,
     },
     Warning {
         id: SourceId,
         warning: String,
     },
+    /// A restart is scheduled after `delay`
+    RecoveryAttempt {
+        id: SourceId,
+        attempt: usize,
+        delay: Duration,
+    },
+    Recovered {
+        id: SourceId,
+        attempts: usize,
+    },
+    /// Recovery was given up, the source stays down
+    RecoveryFailed {
+        id: SourceId,
+        reason: String,
+    },
+    /// Periodic playback position of a file source, see
+    /// [`ProgressReporter`](super::ProgressReporter)
+    Progress {
+        id: SourceId,
+        position: Duration,
+        duration: Option<Duration>,
+        /// Playing but the position has not moved for the stall timeout
+        stalled: bool,
+    },
+}
+
+impl SourceEvent {
+    pub fn source_id(&self) -> SourceId {
+        match self {
+            SourceEvent::SourceAdded { id, .. }
+            | SourceEvent::SourceRemoved { id }
+            | SourceEvent::StateChanged { id, .. }
+            | SourceEvent::PadAdded { id, .. }
+            | SourceEvent::PadRemoved { id, .. }
+            | SourceEvent::Eos { id }
+            | SourceEvent::Error { id, .. }
+            | SourceEvent::Warning { id, .. }
+            | SourceEvent::RecoveryAttempt { id, .. }
+            | SourceEvent::Recovered { id, .. }
+            | SourceEvent::RecoveryFailed { id, .. }
+            | SourceEvent::Progress { id, .. } => *id,
+        }
+    }
+
+    /// Error event classified from the error that caused it
+    pub fn error(id: SourceId, error: &DeepStreamError) -> Self {
+        SourceEvent::Error {
+            id,
+            error: error.to_string(),
+            classification: classify(error),
+        }
+    }
+
+    /// JSON form used for webhook notifications
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        let id = self.source_id().0;
+        match self {
+            SourceEvent::SourceAdded { uri, .. } => {
+                json!({ "event": "source_added", "source_id": id, "uri": uri })
+            }
+            SourceEvent::SourceRemoved { .. } => {
+                json!({ "event": "source_removed", "source_id": id })
+            }
+            SourceEvent::StateChanged {
+                old_state,
+                new_state,
+                ..
+            } => json!({
+                "event": "state_changed",
+                "source_id": id,
+                "old_state": format!("{:?}", old_state),
+                "new_state": format!("{:?}", new_state),
+            }),
+            SourceEvent::PadAdded { pad_name, .. } => {
+                json!({ "event": "pad_added", "source_id": id, "pad": pad_name })
+            }
+            SourceEvent::PadRemoved { pad_name, .. } => {
+                json!({ "event": "pad_removed", "source_id": id, "pad": pad_name })
+            }
+            SourceEvent::Eos { .. } => json!({ "event": "eos", "source_id": id }),
+            SourceEvent::Error {
+                error,
+                classification,
+                ..
+            } => json!({
+                "event": "error",
+                "source_id": id,
+                "message": error,
+                "severity": format!("{:?}", classification.severity),
+                "category": format!("{:?}", classification.category),
+                "action": format!("{:?}", classification.action),
+            }),
+            SourceEvent::Warning { warning, .. } => {
+                json!({ "event": "warning", "source_id": id, "message": warning })
+            }
+            SourceEvent::RecoveryAttempt { attempt, delay, .. } => json!({
+                "event": "recovery_attempt",
+                "source_id": id,
+                "attempt": attempt,
+                "delay_ms": delay.as_millis() as u64,
+            }),
+            SourceEvent::Recovered { attempts, .. } => {
+                json!({ "event": "recovered", "source_id": id, "attempts": attempts })
+            }
+            SourceEvent::RecoveryFailed { reason, .. } => {
+                json!({ "event": "recovery_failed", "source_id": id, "reason": reason })
+            }
+            SourceEvent::Progress {
+                position,
+                duration,
+                stalled,
+                ..
+            } => json!({
+                "event": "progress",
+                "source_id": id,
+                "position_ms": position.as_millis() as u64,
+                "duration_ms": duration.map(|duration| duration.as_millis() as u64),
+                "stalled": stalled,
+            }),
+        }
+    }
 }
 
 pub struct SourceEventHandler {
     sender: Sender<SourceEvent>,
     receiver: Arc<Mutex<Receiver<SourceEvent>>>,
-    callbacks: Arc<Mutex<Vec<Box<dyn Fn(&SourceEvent) + Send + 'static>>>>,
+    broadcast: broadcast::Sender<SourceEvent>,
+    callbacks: Arc<Mutex<Vec<EventCallback>>>,
 }
 
 impl SourceEventHandler {
     pub fn new() -> Self {
         let (sender, receiver) = channel();
+        let (broadcast, _) = broadcast::channel(BROADCAST_CAPACITY);
 
         Self {
             sender,
             receiver: Arc::new(Mutex::new(receiver)),
+            broadcast,
             callbacks: Arc::new(Mutex::new(Vec::new())),
         }
     }
@@ -61,29 +198,78 @@ impl SourceEventHandler {
         self.sender.clone()
     }
 
+    /// Async receiver of every event emitted from now on. A subscriber
+    /// that falls more than 256 events behind gets `RecvError::Lagged`.
+    pub fn subscribe(&self) -> broadcast::Receiver<SourceEvent> {
+        self.broadcast.subscribe()
+    }
+
     pub fn emit(&self, event: SourceEvent) -> Result<()> {
-        println!("Emitting event: {:?}", event);
+        // Progress comes every second per file source
+        if matches!(event, SourceEvent::Progress { .. }) {
+            log::trace!("Emitting event: {:?}", event);
+        } else {
+            println!("Emitting event: {:?}", event);
+        }
 
-        if let Ok(callbacks) = self.callbacks.lock() {
-            for callback in callbacks.iter() {
-                callback(&event);
+        // Callbacks run without the lock held so they can emit events or
+        // register callbacks themselves
+        let callbacks = self
+            .callbacks
+            .lock()
+            .map(|callbacks| callbacks.clone())
+            .unwrap_or_default();
+        let mut panics = Vec::new();
+        for callback in &callbacks {
+            if let Err(msg) = catch_panic(|| callback(&event)) {
+                log::error!("Source event callback panicked on {:?}: {}", event, msg);
+                panics.push(msg);
             }
         }
+        // Reported once, after the event itself; a callback that panics on
+        // the error event too is only logged
+        let failed = (!matches!(event, SourceEvent::Error { .. })).then(|| event.source_id());
+
+        // Having no async subscribers is fine
+        let _ = self.broadcast.send(event.clone());
 
         self.sender.send(event).map_err(|e| {
             crate::error::DeepStreamError::Unknown(format!("Failed to send event: {}", e))
-        })
+        })?;
+
+        if let Some(id) = failed {
+            for msg in panics {
+                let error = DeepStreamError::ProcessingFailed {
+                    reason: format!("Source event callback panicked: {}", msg),
+                };
+                self.emit(SourceEvent::error(id, &error))?;
+            }
+        }
+        Ok(())
     }
 
     pub fn register_callback<F>(&self, callback: F)
     where
-        F: Fn(&SourceEvent) + Send + 'static,
+        F: Fn(&SourceEvent) + Send + Sync + 'static,
     {
         if let Ok(mut callbacks) = self.callbacks.lock() {
-            callbacks.push(Box::new(callback));
+            callbacks.push(Arc::new(callback));
         }
     }
 
+    /// Queue every lifecycle event for delivery to a webhook; periodic
+    /// progress is left out
+    pub fn forward_to_webhook(&self, webhook: WebhookHandle) {
+        self.register_callback(move |event| {
+            if matches!(event, SourceEvent::Progress { .. }) {
+                return;
+            }
+            if let Err(e) = webhook.enqueue_json(&event.to_json()) {
+                log::warn!("Dropping webhook for {:?}: {}", event, e);
+            }
+        });
+    }
+
     pub fn poll_event(&self) -> Option<SourceEvent> {
         if let Ok(receiver) = self.receiver.lock() {
             receiver.try_recv().ok()
@@ -109,6 +295,46 @@ impl Default for SourceEventHandler {
     }
 }
 
+fn source_state(state: gst::State) -> SourceState {
+    match state {
+        gst::State::Null => SourceState::Stopped,
+        gst::State::Ready => SourceState::Idle,
+        gst::State::Paused => SourceState::Paused,
+        gst::State::Playing => SourceState::Playing,
+        _ => SourceState::Idle,
+    }
+}
+
+/// Source events from routed bus messages. EOS is left out: the source's
+/// EOS probe already reports it.
+impl SourceMessageHandler for SourceEventHandler {
+    fn on_error(&self, id: SourceId, error: &DeepStreamError, debug: Option<&str>) {
+        let _ = self.emit(SourceEvent::Error {
+            id,
+            error: format!("{} ({:?})", error, debug),
+            classification: classify(error),
+        });
+    }
+
+    fn on_warning(&self, id: SourceId, warning: &gst::glib::Error, debug: Option<&str>) {
+        let _ = self.emit(SourceEvent::Warning {
+            id,
+            warning: format!("{} ({:?})", warning, debug),
+        });
+    }
+
+    fn on_state_changed(&self, id: SourceId, old: gst::State, new: gst::State) {
+        let (old_state, new_state) = (source_state(old), source_state(new));
+        if old_state != new_state {
+            let _ = self.emit(SourceEvent::StateChanged {
+                id,
+                old_state,
+                new_state,
+            });
+        }
+    }
+}
+
 pub fn handle_bus_message(
     msg: &gst::Message,
     source_id: Option<SourceId>,
@@ -133,6 +359,7 @@ pub fn handle_bus_message(
                 event_handler.emit(SourceEvent::Error {
                     id,
                     error: error_msg,
+                    classification: classify(&DeepStreamError::from_gst_message(err)),
                 })?;
             }
         }
@@ -152,22 +379,8 @@ pub fn handle_bus_message(
         }
         MessageView::StateChanged(state_changed) => {
             if let Some(id) = source_id {
-                let old = match state_changed.old() {
-                    gst::State::Null => SourceState::Stopped,
-                    gst::State::Ready => SourceState::Idle,
-                    gst::State::Paused => SourceState::Paused,
-                    gst::State::Playing => SourceState::Playing,
-                    _ => SourceState::Idle,
-                };
-
-                let new = match state_changed.current() {
-                    gst::State::Null => SourceState::Stopped,
-                    gst::State::Ready => SourceState::Idle,
-                    gst::State::Paused => SourceState::Paused,
-                    gst::State::Playing => SourceState::Playing,
-                    _ => SourceState::Idle,
-                };
-
+                let old = source_state(state_changed.old());
+                let new = source_state(state_changed.current());
                 if old != new {
                     event_handler.emit(SourceEvent::StateChanged {
                         id,
@@ -253,6 +466,19 @@ impl EosTracker {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_event_json() {
+        let event = SourceEvent::StateChanged {
+            id: SourceId(3),
+            old_state: SourceState::Paused,
+            new_state: SourceState::Playing,
+        };
+        let json = event.to_json();
+        assert_eq!(json["event"], "state_changed");
+        assert_eq!(json["source_id"], 3);
+        assert_eq!(json["new_state"], "Playing");
+    }
+
     #[test]
     fn test_event_handler() {
         let handler = SourceEventHandler::new();
@@ -277,6 +503,39 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_event_subscription() {
+        let handler = SourceEventHandler::new();
+        let mut events = handler.subscribe();
+
+        handler
+            .emit(SourceEvent::error(
+                SourceId(2),
+                &DeepStreamError::Timeout("rtsp read".to_string()),
+            ))
+            .unwrap();
+        handler
+            .emit(SourceEvent::RecoveryAttempt {
+                id: SourceId(2),
+                attempt: 1,
+                delay: Duration::from_millis(500),
+            })
+            .unwrap();
+
+        match events.recv().await.unwrap() {
+            SourceEvent::Error { classification, .. } => {
+                assert_eq!(
+                    classification.category,
+                    crate::error::ErrorCategory::Network
+                );
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+        let attempt = events.recv().await.unwrap();
+        assert_eq!(attempt.source_id(), SourceId(2));
+        assert_eq!(attempt.to_json()["delay_ms"], 500);
+    }
+
     #[test]
     fn test_eos_tracker() {
         let tracker = EosTracker::new(5);
@@ -0,0 +1,323 @@
+//! Fallback slate shown in place of a failed source
+//!
+//! With a slate configured, a source reaches the streammux through an
+//! `input-selector` whose other input is a live test pattern captioned
+//! "signal lost". A watchdog switches the selector to the slate when the
+//! source stops delivering frames or reports an error, and back to the
+//! source once frames flow again, so its tile never goes black or freezes.
+//! The slate is drawn in system memory and, when the source delivers NVMM
+//! buffers, copied into device memory by `nvvideoconvert` so both inputs
+//! carry the same caps. A restarted source keeps its slate bin, which
+//! stays on air until the new source's first frame.
+
+use super::manager::{install_stage, remove_stage};
+use super::{SourceId, SourceManager};
+use crate::error::{DeepStreamError, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the watchdog looks at frame arrival
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlateConfig {
+    /// `videotestsrc` pattern behind the caption
+    pub pattern: String,
+    pub text: String,
+    /// Show the slate after this long without a frame from the source
+    pub timeout_ms: u64,
+}
+
+impl Default for SlateConfig {
+    fn default() -> Self {
+        Self {
+            pattern: "smpte".to_string(),
+            text: "SIGNAL LOST".to_string(),
+            timeout_ms: 2000,
+        }
+    }
+}
+
+/// Whether the slate should be on air, given how long ago the source's
+/// last frame arrived (`None` if it never sent one)
+pub fn slate_wanted(last_frame_age: Option<Duration>, failed: bool, timeout: Duration) -> bool {
+    failed || last_frame_age.is_none_or(|age| age > timeout)
+}
+
+/// The selector bin in front of one source
+pub struct FallbackSlate {
+    id: SourceId,
+    bin: gst::Bin,
+    selector: gst::Element,
+    live_pad: gst::Pad,
+    slate_pad: gst::Pad,
+    timeout: Duration,
+    epoch: Instant,
+    /// Milliseconds after `epoch` of the last live frame, 0 for none yet
+    last_frame_ms: AtomicU64,
+    failed: AtomicBool,
+}
+
+impl FallbackSlate {
+    pub fn new(id: SourceId, config: &SlateConfig) -> Result<Arc<Self>> {
+        let make = |factory: &str, name: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("fallback-{}-{}", name, id.0))
+                .build()
+                .map_err(|_| DeepStreamError::ElementCreation {
+                    element: format!("{} for source {} slate", factory, id),
+                })
+        };
+
+        let bin = gst::Bin::builder()
+            .name(format!("fallback-bin-{:02}", id.0))
+            .build();
+        let selector = make("input-selector", "selector")?;
+        let pattern = make("videotestsrc", "pattern")?;
+        pattern.set_property("is-live", true);
+        pattern.set_property_from_str("pattern", &config.pattern);
+        let overlay = make("textoverlay", "caption")?;
+        overlay.set_property("text", &config.text);
+        overlay.set_property_from_str("valignment", "center");
+        overlay.set_property_from_str("halignment", "center");
+        overlay.set_property("font-desc", "Sans Bold 32");
+        let convert = make("videoconvert", "convert")?;
+        let scale = make("videoscale", "scale")?;
+        let raw_caps = make("capsfilter", "raw-caps")?;
+        // Without DeepStream sources only deliver system memory
+        let upload = if gst::ElementFactory::find("nvvideoconvert").is_some() {
+            make("nvvideoconvert", "upload")?
+        } else {
+            make("identity", "upload")?
+        };
+        let caps = make("capsfilter", "caps")?;
+
+        let chain = [
+            &pattern, &overlay, &convert, &scale, &raw_caps, &upload, &caps,
+        ];
+        bin.add(&selector)?;
+        bin.add_many(chain)?;
+        gst::Element::link_many(chain)?;
+
+        let request = |what: &str| {
+            selector
+                .request_pad_simple("sink_%u")
+                .ok_or_else(|| DeepStreamError::PadNotFound {
+                    element: "input-selector".to_string(),
+                    pad: format!("{} input for source {}", what, id),
+                })
+        };
+        let live_pad = request("live")?;
+        let slate_pad = request("slate")?;
+        caps.static_pad("src")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: "capsfilter".to_string(),
+                pad: "src".to_string(),
+            })?
+            .link(&slate_pad)
+            .map_err(|e| DeepStreamError::PadLinking(format!("Slate for {}: {:?}", id, e)))?;
+
+        let live_ghost = gst::GhostPad::builder_with_target(&live_pad)?
+            .name("live")
+            .build();
+        let selector_src =
+            selector
+                .static_pad("src")
+                .ok_or_else(|| DeepStreamError::PadNotFound {
+                    element: "input-selector".to_string(),
+                    pad: "src".to_string(),
+                })?;
+        let src_ghost = gst::GhostPad::builder_with_target(&selector_src)?
+            .name("src")
+            .build();
+        bin.add_pad(&live_ghost)?;
+        bin.add_pad(&src_ghost)?;
+
+        // Nothing to show until the source delivers its first frame
+        selector.set_property("active-pad", &slate_pad);
+
+        let slate = Arc::new(Self {
+            id,
+            bin,
+            selector,
+            live_pad,
+            slate_pad,
+            timeout: Duration::from_millis(config.timeout_ms),
+            epoch: Instant::now(),
+            last_frame_ms: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+        });
+        slate.watch_live_input(&raw_caps, &caps);
+        Ok(slate)
+    }
+
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    pub fn bin(&self) -> &gst::Bin {
+        &self.bin
+    }
+
+    /// Where the source's video pad links
+    pub fn live_input(&self) -> gst::Pad {
+        self.bin
+            .static_pad("live")
+            .expect("fallback bin has a live pad")
+    }
+
+    pub fn is_showing_slate(&self) -> bool {
+        self.selector
+            .property::<Option<gst::Pad>>("active-pad")
+            .as_ref()
+            == Some(&self.slate_pad)
+    }
+
+    pub fn show_slate(&self) {
+        if !self.is_showing_slate() {
+            log::info!("Source {} lost, showing fallback slate", self.id);
+            self.selector.set_property("active-pad", &self.slate_pad);
+        }
+    }
+
+    pub fn show_live(&self) {
+        if self.is_showing_slate() {
+            log::info!("Source {} back, leaving fallback slate", self.id);
+            self.selector.set_property("active-pad", &self.live_pad);
+        }
+    }
+
+    /// Put the slate up now rather than waiting for frames to stop; it
+    /// comes down again with the next frame from the source
+    pub fn report_error(&self) {
+        self.failed.store(true, Ordering::SeqCst);
+        self.show_slate();
+    }
+
+    /// Switch to whichever input should be on air
+    pub fn check(&self) {
+        let last = self.last_frame_ms.load(Ordering::SeqCst);
+        let age = (last > 0).then(|| {
+            self.epoch
+                .elapsed()
+                .saturating_sub(Duration::from_millis(last))
+        });
+        if slate_wanted(age, self.failed.load(Ordering::SeqCst), self.timeout) {
+            self.show_slate();
+        } else {
+            self.show_live();
+        }
+    }
+
+    /// Put the slate bin in the pipeline, feeding `downstream` or, without
+    /// one, the streammux pad the source itself would use
+    pub fn install(&self, manager: &SourceManager, downstream: Option<&gst::Pad>) -> Result<()> {
+        install_stage(manager, self.id, self.bin.upcast_ref(), downstream)
+    }
+
+    /// Check the slate every [`WATCHDOG_INTERVAL`] until it is dropped
+    pub fn start_watchdog(self: &Arc<Self>) {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        thread::Builder::new()
+            .name(format!("slate-watchdog-{}", self.id.0))
+            .spawn(move || {
+                while let Some(slate) = weak.upgrade() {
+                    slate.check();
+                    drop(slate);
+                    thread::sleep(WATCHDOG_INTERVAL);
+                }
+            })
+            .ok();
+    }
+
+    /// Note live frames and make the slate match the source's caps, so
+    /// switching does not renegotiate the muxer input. `raw_caps` gets the
+    /// same caps in system memory, where the slate is drawn.
+    fn watch_live_input(self: &Arc<Self>, raw_caps: &gst::Element, slate_caps: &gst::Element) {
+        let weak = Arc::downgrade(self);
+        let raw_caps = raw_caps.downgrade();
+        let slate_caps = slate_caps.downgrade();
+        self.live_pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::EVENT_DOWNSTREAM,
+            move |_, info| {
+                let Some(slate) = weak.upgrade() else {
+                    return gst::PadProbeReturn::Remove;
+                };
+                match &info.data {
+                    Some(gst::PadProbeData::Buffer(_)) => {
+                        let now = slate.epoch.elapsed().as_millis().max(1) as u64;
+                        slate.last_frame_ms.store(now, Ordering::SeqCst);
+                        slate.failed.store(false, Ordering::SeqCst);
+                    }
+                    Some(gst::PadProbeData::Event(event)) => {
+                        if let gst::EventView::Caps(caps) = event.view()
+                            && let (Some(raw), Some(filter)) =
+                                (raw_caps.upgrade(), slate_caps.upgrade())
+                        {
+                            let caps = caps.caps_owned();
+                            raw.set_property("caps", system_memory(&caps));
+                            filter.set_property("caps", &caps);
+                        }
+                    }
+                    _ => {}
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+}
+
+/// `caps` with their memory features dropped, so they describe the same
+/// frames in system memory
+fn system_memory(caps: &gst::Caps) -> gst::Caps {
+    let mut raw = caps.clone();
+    raw.make_mut().set_features_simple(None);
+    raw
+}
+
+/// Take a slate out of the pipeline once its source is gone
+pub fn remove_slate(manager: &SourceManager, slate: &FallbackSlate) -> Result<()> {
+    remove_stage(manager, slate.bin().upcast_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slate_wanted() {
+        let timeout = Duration::from_secs(2);
+        assert!(slate_wanted(None, false, timeout));
+        assert!(!slate_wanted(
+            Some(Duration::from_millis(40)),
+            false,
+            timeout
+        ));
+        assert!(slate_wanted(Some(Duration::from_secs(3)), false, timeout));
+        assert!(slate_wanted(Some(Duration::from_millis(40)), true, timeout));
+    }
+
+    #[test]
+    fn test_slate_drawn_in_system_memory() {
+        gst::init().unwrap();
+
+        let nvmm = gst::Caps::builder("video/x-raw")
+            .features(["memory:NVMM"])
+            .field("format", "NV12")
+            .field("width", 1280)
+            .field("height", 720)
+            .build();
+        let raw = system_memory(&nvmm);
+        assert!(
+            raw.features(0)
+                .is_none_or(|features| !features.contains("memory:NVMM"))
+        );
+        assert_eq!(raw.structure(0), nvmm.structure(0));
+    }
+}
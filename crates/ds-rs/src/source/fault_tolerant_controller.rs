@@ -1,9 +1,9 @@
 use super::{
     SourceController, SourceEvent, SourceId,
-    circuit_breaker::{CircuitBreakerConfig, CircuitBreakerManager},
-    recovery::{RecoveryConfig, RecoveryManager},
+    circuit_breaker::CircuitBreakerManager,
+    recovery::{RecoveryManager, RecoveryPolicy, RecoveryState},
 };
-use crate::error::Result;
+use crate::error::{RecoveryAction, Result};
 use crate::pipeline::Pipeline;
 use gstreamer as gst;
 use std::collections::HashMap;
@@ -29,7 +29,7 @@ impl FaultTolerantSourceController {
         let ft_controller = Self {
             inner: controller.clone(),
             recovery_managers: Arc::new(Mutex::new(HashMap::new())),
-            circuit_breaker: Arc::new(CircuitBreakerManager::new()),
+            circuit_breaker: controller.circuit_breakers(),
             source_uris: Arc::new(Mutex::new(HashMap::new())),
         };
 
@@ -44,26 +44,91 @@ impl FaultTolerantSourceController {
         let recovery_managers = self.recovery_managers.clone();
         let circuit_breaker = self.circuit_breaker.clone();
         let source_uris = self.source_uris.clone();
+        // Weak so the handler does not keep itself alive through its callback
+        let events = Arc::downgrade(&self.inner.get_event_handler());
 
         self.inner
             .get_event_handler()
             .register_callback(move |event| {
-                if let SourceEvent::Error { id, error } = event {
+                if let SourceEvent::Error {
+                    id,
+                    error,
+                    classification,
+                } = event
+                {
                     eprintln!("Source {} error: {}", id, error);
+                    let notify = |event: SourceEvent| {
+                        if let Some(events) = events.upgrade() {
+                            let _ = events.emit(event);
+                        }
+                    };
+
+                    let breaker = circuit_breaker.get(&id.to_string());
+                    if let Some(breaker) = &breaker {
+                        breaker.record_failure(error.clone());
+                        if !breaker.should_allow_request() {
+                            eprintln!("Source {} circuit open, not recovering", id);
+                            notify(SourceEvent::RecoveryFailed {
+                                id: *id,
+                                reason: "circuit breaker open".to_string(),
+                            });
+                            return;
+                        }
+                    }
+
+                    if matches!(
+                        classification.action,
+                        RecoveryAction::FailSource | RecoveryAction::NoRecovery
+                    ) {
+                        notify(SourceEvent::RecoveryFailed {
+                            id: *id,
+                            reason: classification.description.clone(),
+                        });
+                        return;
+                    }
 
                     // Try to recover the source
-                    if let Some(uri) = source_uris.lock().unwrap().get(id).cloned() {
-                        if let Some(recovery_mgr) = recovery_managers.lock().unwrap().get(id) {
-                            if recovery_mgr.should_retry() {
-                                // Simple recovery: wait and reconnect
-                                let backoff = recovery_mgr.calculate_backoff(1); // Simple retry count
-                                thread::sleep(backoff);
-
-                                // Try to restart the source
-                                if controller.restart_source(*id).is_ok() {
+                    if source_uris.lock().unwrap().contains_key(id) {
+                        let recovery_mgr = recovery_managers.lock().unwrap().get(id).cloned();
+                        if let Some(recovery_mgr) = recovery_mgr {
+                            let Some(backoff) = recovery_mgr.start_recovery() else {
+                                notify(SourceEvent::RecoveryFailed {
+                                    id: *id,
+                                    reason: "retry limit reached".to_string(),
+                                });
+                                return;
+                            };
+                            let attempt = match recovery_mgr.get_state() {
+                                RecoveryState::Retrying { attempt, .. } => attempt + 1,
+                                _ => 1,
+                            };
+                            notify(SourceEvent::RecoveryAttempt {
+                                id: *id,
+                                attempt,
+                                delay: backoff,
+                            });
+                            thread::sleep(backoff);
+
+                            // Try to restart the source
+                            match controller.restart_source(*id) {
+                                Ok(()) => {
                                     recovery_mgr.mark_recovered();
-                                } else {
-                                    recovery_mgr.mark_failed(error.clone());
+                                    if let Some(breaker) = &breaker {
+                                        breaker.record_success();
+                                    }
+                                    notify(SourceEvent::Recovered {
+                                        id: *id,
+                                        attempts: attempt,
+                                    });
+                                }
+                                Err(e) => {
+                                    recovery_mgr.mark_failed(e.to_string());
+                                    if !recovery_mgr.should_retry() {
+                                        notify(SourceEvent::RecoveryFailed {
+                                            id: *id,
+                                            reason: e.to_string(),
+                                        });
+                                    }
                                 }
                             }
                         }
@@ -73,23 +138,23 @@ impl FaultTolerantSourceController {
     }
 
     pub fn add_source(&self, uri: &str) -> Result<SourceId> {
-        let id = self.inner.add_source(uri)?;
+        let policy = self.inner.recovery_policies().for_uri(uri).clone();
+        self.add_source_with_policy(uri, policy)
+    }
+
+    pub fn add_source_with_policy(&self, uri: &str, policy: RecoveryPolicy) -> Result<SourceId> {
+        let id = self.inner.add_source_with_policy(uri, policy.clone())?;
 
         // Track URI for recovery
         self.source_uris.lock().unwrap().insert(id, uri.to_string());
 
-        // Set up recovery manager with default config
-        let recovery_mgr = Arc::new(RecoveryManager::new(RecoveryConfig::default()));
+        // Set up recovery manager with the source's policy
+        let recovery_mgr = Arc::new(RecoveryManager::new(policy.to_config()));
         self.recovery_managers
             .lock()
             .unwrap()
             .insert(id, recovery_mgr);
 
-        // Create circuit breaker for this source
-        let cb_config = CircuitBreakerConfig::default();
-        self.circuit_breaker
-            .get_or_create(format!("source-{}", id), cb_config);
-
         Ok(id)
     }
 
@@ -110,6 +175,10 @@ impl FaultTolerantSourceController {
         self.inner.restart_source(id)
     }
 
+    pub fn circuit_breakers(&self) -> Arc<CircuitBreakerManager> {
+        self.circuit_breaker.clone()
+    }
+
     pub fn get_inner(&self) -> Arc<SourceController> {
         self.inner.clone()
     }
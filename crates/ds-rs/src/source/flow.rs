@@ -0,0 +1,109 @@
+//! Per-source flow control in front of the streammux
+//!
+//! Every source feeds the streammux through a `valve` of its own, nearest
+//! the muxer of all its stages. Pausing a source closes its valve instead
+//! of changing the source's state, so the other sources and the batch go
+//! on untouched and the paused source keeps its muxer pad. Where the valve
+//! supports it, dropped frames become gap events, which tell downstream
+//! the source is idle rather than stalled. A file source still decodes
+//! while paused, so it resumes further into the file.
+
+use super::manager::{install_stage, remove_stage};
+use super::{SourceId, SourceManager};
+use crate::error::{DeepStreamError, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+
+pub struct FlowGate {
+    id: SourceId,
+    valve: gst::Element,
+}
+
+impl FlowGate {
+    pub fn new(id: SourceId) -> Result<Self> {
+        let valve = gst::ElementFactory::make("valve")
+            .name(format!("flow-valve-{:02}", id.0))
+            .property("drop", false)
+            .build()
+            .map_err(|_| DeepStreamError::ElementCreation {
+                element: "valve".to_string(),
+            })?;
+        if valve.find_property("drop-mode").is_some() {
+            valve.set_property_from_str("drop-mode", "transform-to-gap");
+        }
+        Ok(Self { id, valve })
+    }
+
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    /// Where the source, or its next stage, links
+    pub fn sink_pad(&self) -> gst::Pad {
+        self.valve.static_pad("sink").expect("valve has a sink pad")
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.valve.set_property("drop", paused);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.valve.property::<bool>("drop")
+    }
+
+    /// Put the valve in the pipeline, feeding `downstream` or, without one,
+    /// the source's streammux pad
+    pub fn install(&self, manager: &SourceManager, downstream: Option<&gst::Pad>) -> Result<()> {
+        install_stage(manager, self.id, &self.valve, downstream)
+    }
+
+    pub fn remove(&self, manager: &SourceManager) -> Result<()> {
+        remove_stage(manager, &self.valve)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_paused_gate_holds_frames() {
+        let _ = gst::init();
+
+        let gate = FlowGate::new(SourceId(3)).unwrap();
+        assert_eq!(gate.valve.name(), "flow-valve-03");
+        let pipeline = gst::Pipeline::new();
+        let src = gst::ElementFactory::make("videotestsrc")
+            .property("is-live", true)
+            .build()
+            .unwrap();
+        let sink = gst::ElementFactory::make("fakesink")
+            .property("signal-handoffs", true)
+            .property("sync", false)
+            .build()
+            .unwrap();
+        pipeline.add_many([&src, &gate.valve, &sink]).unwrap();
+        gst::Element::link_many([&src, &gate.valve, &sink]).unwrap();
+
+        let frames = Arc::new(AtomicU64::new(0));
+        let counter = frames.clone();
+        sink.connect("handoff", false, move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            None
+        });
+
+        gate.set_paused(true);
+        assert!(gate.is_paused());
+        pipeline.set_state(gst::State::Playing).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(frames.load(Ordering::SeqCst), 0);
+
+        gate.set_paused(false);
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(frames.load(Ordering::SeqCst) > 0);
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use super::SourceId;
+use super::health_probe::{HealthProbe, HealthScorer};
 use crate::error::Result;
 use gst::prelude::*;
 use gstreamer as gst;
@@ -70,6 +71,14 @@ pub struct HealthConfig {
     pub check_interval: Duration,
     /// Number of consecutive failures before marking unhealthy
     pub failure_threshold: usize,
+    /// Composite score below which a source is degraded
+    pub degraded_score: f64,
+    /// Composite score below which a source is unhealthy
+    pub unhealthy_score: f64,
+    /// How far above a threshold the score must rise to recover
+    pub hysteresis_margin: f64,
+    /// Consecutive checks above threshold plus margin needed to recover
+    pub recovery_checks: usize,
 }
 
 impl Default for HealthConfig {
@@ -81,6 +90,10 @@ impl Default for HealthConfig {
             window_size_secs: 10,
             check_interval: Duration::from_secs(5),
             failure_threshold: 3,
+            degraded_score: 0.9,
+            unhealthy_score: 0.4,
+            hysteresis_margin: 0.05,
+            recovery_checks: 2,
         }
     }
 }
@@ -104,6 +117,11 @@ pub trait HealthMonitor: Send + Sync {
 
     /// Reset health metrics
     fn reset_metrics(&self);
+
+    /// Composite score from 0.0 to 1.0 at the last check, if known
+    fn health_score(&self) -> Option<f64> {
+        None
+    }
 }
 
 /// Frame rate calculator with sliding window
@@ -154,23 +172,30 @@ pub struct SourceHealthMonitor {
     config: HealthConfig,
     metrics: Arc<Mutex<HealthMetrics>>,
     frame_calculator: Arc<Mutex<FrameRateCalculator>>,
-    consecutive_failures: Arc<Mutex<usize>>,
+    scorer: Arc<Mutex<HealthScorer>>,
     last_check: Arc<Mutex<Instant>>,
 }
 
 impl SourceHealthMonitor {
     pub fn new(source_id: SourceId, config: HealthConfig) -> Self {
         let window = Duration::from_secs(config.window_size_secs);
+        let scorer = HealthScorer::with_default_probes(config.clone());
         Self {
             source_id,
             config,
             metrics: Arc::new(Mutex::new(HealthMetrics::default())),
             frame_calculator: Arc::new(Mutex::new(FrameRateCalculator::new(window))),
-            consecutive_failures: Arc::new(Mutex::new(0)),
+            scorer: Arc::new(Mutex::new(scorer)),
             last_check: Arc::new(Mutex::new(Instant::now())),
         }
     }
 
+    /// Add a probe to the composite score alongside the built-in
+    /// buffer-flow and latency probes
+    pub fn add_probe(&self, probe: Arc<dyn HealthProbe>, weight: f64) {
+        self.scorer.lock().unwrap().add_probe(probe, weight);
+    }
+
     /// Install a pad probe to monitor buffer flow
     pub fn install_probe(&self, pad: &gst::Pad) -> Result<()> {
         let metrics = self.metrics.clone();
@@ -199,60 +224,18 @@ impl SourceHealthMonitor {
 
 impl HealthMonitor for SourceHealthMonitor {
     fn check_health(&self) -> HealthStatus {
-        let metrics = self.metrics.lock().unwrap();
-        let mut failures = self.consecutive_failures.lock().unwrap();
+        let mut metrics = self.metrics.lock().unwrap();
         let mut last_check = self.last_check.lock().unwrap();
 
         let now = Instant::now();
-        let time_since_check = now - *last_check;
+        metrics.time_since_last_check = now - *last_check;
         *last_check = now;
 
-        // Check frame rate
-        if metrics.avg_frame_rate < self.config.min_frame_rate && metrics.total_frames > 10 {
-            *failures += 1;
-            if *failures >= self.config.failure_threshold {
-                return HealthStatus::Unhealthy {
-                    reason: format!("Frame rate too low: {:.1} fps", metrics.avg_frame_rate),
-                };
-            } else {
-                return HealthStatus::Degraded {
-                    reason: format!("Frame rate degraded: {:.1} fps", metrics.avg_frame_rate),
-                };
-            }
-        }
-
-        // Check buffer underruns
-        if metrics.buffer_underruns > self.config.max_buffer_underruns {
-            *failures += 1;
-            return HealthStatus::Unhealthy {
-                reason: format!("Too many buffer underruns: {}", metrics.buffer_underruns),
-            };
-        }
-
-        // Check network latency
-        if let Some(latency) = metrics.network_latency_ms {
-            if latency > self.config.max_network_latency_ms {
-                *failures += 1;
-                return HealthStatus::Degraded {
-                    reason: format!("High network latency: {:.1}ms", latency),
-                };
-            }
-        }
-
-        // Check if we're receiving frames
-        if let Some(last_frame) = metrics.last_frame_time {
-            let time_since_frame = now - last_frame;
-            if time_since_frame > Duration::from_secs(5) {
-                *failures += 1;
-                return HealthStatus::Unhealthy {
-                    reason: format!("No frames for {} seconds", time_since_frame.as_secs()),
-                };
-            }
-        }
+        self.scorer.lock().unwrap().evaluate(&metrics, now)
+    }
 
-        // Reset consecutive failures on healthy check
-        *failures = 0;
-        HealthStatus::Healthy
+    fn health_score(&self) -> Option<f64> {
+        self.scorer.lock().unwrap().score()
     }
 
     fn get_metrics(&self) -> HealthMetrics {
@@ -298,8 +281,7 @@ impl HealthMonitor for SourceHealthMonitor {
         let mut calculator = self.frame_calculator.lock().unwrap();
         calculator.timestamps.clear();
 
-        let mut failures = self.consecutive_failures.lock().unwrap();
-        *failures = 0;
+        self.scorer.lock().unwrap().reset();
     }
 }
 
@@ -421,6 +403,24 @@ mod tests {
         assert!(matches!(status, HealthStatus::Unhealthy { .. }));
     }
 
+    #[test]
+    fn test_custom_probe() {
+        use super::super::health_probe::DecoderErrorProbe;
+
+        let monitor = SourceHealthMonitor::new(SourceId(0), HealthConfig::default());
+        let decoder = Arc::new(DecoderErrorProbe::new(Duration::from_secs(10), 0.5));
+        monitor.add_probe(decoder.clone(), 1.0);
+
+        for _ in 0..10 {
+            decoder.record_error();
+        }
+
+        // Buffer flow is fine, decoder probe is at zero
+        let status = monitor.check_health();
+        assert!(matches!(status, HealthStatus::Degraded { .. }));
+        assert_eq!(monitor.health_score(), Some(0.5));
+    }
+
     #[test]
     fn test_metrics_reset() {
         let monitor = SourceHealthMonitor::new(SourceId(0), HealthConfig::default());
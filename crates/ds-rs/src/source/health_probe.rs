@@ -0,0 +1,582 @@
+//! Pluggable health probes and composite scoring
+//!
+//! Each [`HealthProbe`] rates one aspect of a source between 0.0 (broken)
+//! and 1.0 (fine). A [`HealthScorer`] combines the readings into a weighted
+//! score and maps it onto [`HealthStatus`] with hysteresis, so a score
+//! hovering around a threshold does not flap between states.
+
+use super::health::{HealthConfig, HealthMetrics, HealthStatus};
+use crate::error::{DeepStreamError, Result};
+use gst::prelude::*;
+use gstreamer as gst;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// One probe's verdict
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeReading {
+    /// 0.0 (broken) to 1.0 (fine)
+    pub score: f64,
+    /// Why the score is below 1.0
+    pub reason: Option<String>,
+}
+
+impl ProbeReading {
+    pub fn healthy() -> Self {
+        Self {
+            score: 1.0,
+            reason: None,
+        }
+    }
+
+    pub fn impaired(score: f64, reason: impl Into<String>) -> Self {
+        Self {
+            score: score.clamp(0.0, 1.0),
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// A source of health signal
+pub trait HealthProbe: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Rate the source, or `None` if the probe has no data yet
+    fn sample(&self, metrics: &HealthMetrics, now: Instant) -> Option<ProbeReading>;
+}
+
+/// Frame rate, stalls and buffer underruns from [`HealthMetrics`]
+pub struct BufferFlowProbe {
+    pub min_frame_rate: f64,
+    pub max_buffer_underruns: usize,
+    /// No buffer for this long counts as a stall
+    pub stall_timeout: Duration,
+}
+
+impl BufferFlowProbe {
+    pub fn from_config(config: &HealthConfig) -> Self {
+        Self {
+            min_frame_rate: config.min_frame_rate,
+            max_buffer_underruns: config.max_buffer_underruns,
+            stall_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl HealthProbe for BufferFlowProbe {
+    fn name(&self) -> &str {
+        "buffer-flow"
+    }
+
+    fn sample(&self, metrics: &HealthMetrics, now: Instant) -> Option<ProbeReading> {
+        if let Some(last_frame) = metrics.last_frame_time {
+            let since = now.saturating_duration_since(last_frame);
+            if since > self.stall_timeout {
+                return Some(ProbeReading::impaired(
+                    0.0,
+                    format!("No frames for {} seconds", since.as_secs()),
+                ));
+            }
+        }
+
+        if metrics.buffer_underruns > self.max_buffer_underruns {
+            return Some(ProbeReading::impaired(
+                0.0,
+                format!("Too many buffer underruns: {}", metrics.buffer_underruns),
+            ));
+        }
+
+        if metrics.total_frames > 10
+            && self.min_frame_rate > 0.0
+            && metrics.avg_frame_rate < self.min_frame_rate
+        {
+            return Some(ProbeReading::impaired(
+                metrics.avg_frame_rate / self.min_frame_rate,
+                format!("Frame rate too low: {:.1} fps", metrics.avg_frame_rate),
+            ));
+        }
+
+        Some(ProbeReading::healthy())
+    }
+}
+
+/// Network latency reported through [`HealthMetrics::network_latency_ms`]
+pub struct LatencyProbe {
+    pub max_latency_ms: f64,
+}
+
+impl HealthProbe for LatencyProbe {
+    fn name(&self) -> &str {
+        "latency"
+    }
+
+    fn sample(&self, metrics: &HealthMetrics, _now: Instant) -> Option<ProbeReading> {
+        let latency = metrics.network_latency_ms?;
+        if latency > self.max_latency_ms {
+            Some(ProbeReading::impaired(
+                self.max_latency_ms / latency,
+                format!("High network latency: {:.1}ms", latency),
+            ))
+        } else {
+            Some(ProbeReading::healthy())
+        }
+    }
+}
+
+/// Share of frames that failed to decode over a sliding window
+pub struct DecoderErrorProbe {
+    window: Duration,
+    /// Error rate at which the score reaches 0.0
+    max_error_rate: f64,
+    events: Mutex<VecDeque<(Instant, bool)>>,
+}
+
+impl DecoderErrorProbe {
+    pub fn new(window: Duration, max_error_rate: f64) -> Self {
+        Self {
+            window,
+            max_error_rate,
+            events: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record_frame(&self) {
+        self.record(false);
+    }
+
+    pub fn record_error(&self) {
+        self.record(true);
+    }
+
+    fn record(&self, error: bool) {
+        let now = Instant::now();
+        let mut events = self.events.lock().unwrap();
+        events.push_back((now, error));
+        Self::prune(&mut events, now, self.window);
+    }
+
+    fn prune(events: &mut VecDeque<(Instant, bool)>, now: Instant, window: Duration) {
+        while let Some(&(at, _)) = events.front() {
+            if now.saturating_duration_since(at) > window {
+                events.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl Default for DecoderErrorProbe {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30), 0.1)
+    }
+}
+
+impl HealthProbe for DecoderErrorProbe {
+    fn name(&self) -> &str {
+        "decoder-errors"
+    }
+
+    fn sample(&self, _metrics: &HealthMetrics, now: Instant) -> Option<ProbeReading> {
+        let mut events = self.events.lock().unwrap();
+        Self::prune(&mut events, now, self.window);
+        if events.is_empty() {
+            return None;
+        }
+
+        let errors = events.iter().filter(|(_, error)| *error).count();
+        let rate = errors as f64 / events.len() as f64;
+        if errors == 0 {
+            Some(ProbeReading::healthy())
+        } else {
+            Some(ProbeReading::impaired(
+                1.0 - rate / self.max_error_rate.max(f64::EPSILON),
+                format!("Decoder errors: {:.1}% of frames", rate * 100.0),
+            ))
+        }
+    }
+}
+
+/// Cumulative RTP receiver statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RtcpStats {
+    pub packets_received: u64,
+    pub packets_lost: u64,
+    pub jitter_ms: f64,
+}
+
+/// Packet loss and jitter from RTCP receiver statistics
+pub struct RtcpStatsProbe {
+    /// Loss fraction at which the score reaches 0.0
+    pub max_loss_fraction: f64,
+    pub max_jitter_ms: f64,
+    stats: Mutex<(Option<RtcpStats>, Option<RtcpStats>)>,
+}
+
+impl RtcpStatsProbe {
+    pub fn new(max_loss_fraction: f64, max_jitter_ms: f64) -> Self {
+        Self {
+            max_loss_fraction,
+            max_jitter_ms,
+            stats: Mutex::new((None, None)),
+        }
+    }
+
+    /// Record the latest cumulative counters; loss is rated on the change
+    /// since the previous update
+    pub fn update(&self, stats: RtcpStats) {
+        let mut guard = self.stats.lock().unwrap();
+        guard.0 = guard.1.take();
+        guard.1 = Some(stats);
+    }
+
+    /// Read the `stats` structure of an `rtpjitterbuffer`
+    pub fn update_from_jitterbuffer(&self, jitterbuffer: &gst::Element) -> Result<()> {
+        if jitterbuffer.find_property("stats").is_none() {
+            return Err(DeepStreamError::InvalidInput(format!(
+                "{} has no stats property",
+                jitterbuffer.name()
+            )));
+        }
+
+        let stats = jitterbuffer.property::<gst::Structure>("stats");
+        self.update(RtcpStats {
+            packets_received: stats.get::<u64>("num-pushed").unwrap_or(0),
+            packets_lost: stats.get::<u64>("num-lost").unwrap_or(0),
+            jitter_ms: stats.get::<u64>("avg-jitter").unwrap_or(0) as f64 / 1e6,
+        });
+        Ok(())
+    }
+}
+
+impl Default for RtcpStatsProbe {
+    fn default() -> Self {
+        Self::new(0.05, 100.0)
+    }
+}
+
+impl HealthProbe for RtcpStatsProbe {
+    fn name(&self) -> &str {
+        "rtcp"
+    }
+
+    fn sample(&self, _metrics: &HealthMetrics, _now: Instant) -> Option<ProbeReading> {
+        let (previous, latest) = *self.stats.lock().unwrap();
+        let latest = latest?;
+        let previous = previous.unwrap_or_default();
+
+        let received = latest
+            .packets_received
+            .saturating_sub(previous.packets_received);
+        let lost = latest.packets_lost.saturating_sub(previous.packets_lost);
+        let loss = if received + lost == 0 {
+            0.0
+        } else {
+            lost as f64 / (received + lost) as f64
+        };
+
+        let loss_score = 1.0 - loss / self.max_loss_fraction.max(f64::EPSILON);
+        let jitter_score = if latest.jitter_ms > self.max_jitter_ms {
+            self.max_jitter_ms / latest.jitter_ms
+        } else {
+            1.0
+        };
+
+        if loss_score >= 1.0 && jitter_score >= 1.0 {
+            Some(ProbeReading::healthy())
+        } else if loss_score <= jitter_score {
+            Some(ProbeReading::impaired(
+                loss_score,
+                format!("Packet loss: {:.1}%", loss * 100.0),
+            ))
+        } else {
+            Some(ProbeReading::impaired(
+                jitter_score,
+                format!("High jitter: {:.1}ms", latest.jitter_ms),
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+/// Weighted combination of probes with hysteresis
+///
+/// A worse score takes effect immediately. Recovering to a better state
+/// needs the score to clear the threshold by `hysteresis_margin` for
+/// `recovery_checks` consecutive checks, and a source that stays degraded
+/// for `failure_threshold` checks escalates to unhealthy.
+pub struct HealthScorer {
+    probes: Vec<(Arc<dyn HealthProbe>, f64)>,
+    config: HealthConfig,
+    level: Level,
+    score: Option<f64>,
+    recovering: usize,
+    degraded_checks: usize,
+}
+
+impl HealthScorer {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            probes: Vec::new(),
+            config,
+            level: Level::Healthy,
+            score: None,
+            recovering: 0,
+            degraded_checks: 0,
+        }
+    }
+
+    /// Scorer with the built-in buffer-flow and latency probes
+    pub fn with_default_probes(config: HealthConfig) -> Self {
+        let mut scorer = Self::new(config);
+        scorer.add_probe(Arc::new(BufferFlowProbe::from_config(&scorer.config)), 1.0);
+        scorer.add_probe(
+            Arc::new(LatencyProbe {
+                max_latency_ms: scorer.config.max_network_latency_ms,
+            }),
+            1.0,
+        );
+        scorer
+    }
+
+    pub fn add_probe(&mut self, probe: Arc<dyn HealthProbe>, weight: f64) {
+        self.probes.push((probe, weight.max(0.0)));
+    }
+
+    pub fn probe_names(&self) -> Vec<&str> {
+        self.probes.iter().map(|(probe, _)| probe.name()).collect()
+    }
+
+    /// Score from the last evaluation, `None` if no probe had data
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    fn level_for(&self, score: f64) -> Level {
+        if score < self.config.unhealthy_score {
+            Level::Unhealthy
+        } else if score < self.config.degraded_score {
+            Level::Degraded
+        } else {
+            Level::Healthy
+        }
+    }
+
+    pub fn evaluate(&mut self, metrics: &HealthMetrics, now: Instant) -> HealthStatus {
+        let mut weighted = 0.0;
+        let mut total_weight = 0.0;
+        let mut reasons = Vec::new();
+        for (probe, weight) in &self.probes {
+            if let Some(reading) = probe.sample(metrics, now) {
+                weighted += reading.score.clamp(0.0, 1.0) * weight;
+                total_weight += weight;
+                reasons.extend(reading.reason);
+            }
+        }
+
+        if total_weight <= 0.0 {
+            self.score = None;
+            return HealthStatus::Unknown;
+        }
+        let score = weighted / total_weight;
+        self.score = Some(score);
+
+        let target = self.level_for(score);
+        if target > self.level {
+            self.level = target;
+            self.recovering = 0;
+        } else if target < self.level {
+            let cleared = self.level_for(score - self.config.hysteresis_margin);
+            if cleared < self.level {
+                self.recovering += 1;
+                if self.recovering >= self.config.recovery_checks.max(1) {
+                    self.level = cleared;
+                    self.recovering = 0;
+                    self.degraded_checks = 0;
+                }
+            } else {
+                self.recovering = 0;
+            }
+        } else {
+            self.recovering = 0;
+        }
+
+        if target == Level::Healthy {
+            self.degraded_checks = 0;
+        } else {
+            self.degraded_checks += 1;
+            if self.level == Level::Degraded
+                && self.degraded_checks >= self.config.failure_threshold.max(1)
+            {
+                self.level = Level::Unhealthy;
+            }
+        }
+
+        let reason = if reasons.is_empty() {
+            format!("Health score {:.2}", score)
+        } else {
+            reasons.join("; ")
+        };
+        match self.level {
+            Level::Healthy => HealthStatus::Healthy,
+            Level::Degraded => HealthStatus::Degraded { reason },
+            Level::Unhealthy => HealthStatus::Unhealthy { reason },
+        }
+    }
+
+    /// Forget state, keeping the registered probes
+    pub fn reset(&mut self) {
+        self.level = Level::Healthy;
+        self.score = None;
+        self.recovering = 0;
+        self.degraded_checks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(Mutex<Option<f64>>);
+
+    impl Fixed {
+        fn new(score: f64) -> Arc<Self> {
+            Arc::new(Self(Mutex::new(Some(score))))
+        }
+
+        fn set(&self, score: f64) {
+            *self.0.lock().unwrap() = Some(score);
+        }
+    }
+
+    impl HealthProbe for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn sample(&self, _metrics: &HealthMetrics, _now: Instant) -> Option<ProbeReading> {
+            self.0.lock().unwrap().map(|score| ProbeReading {
+                score,
+                reason: None,
+            })
+        }
+    }
+
+    fn config() -> HealthConfig {
+        HealthConfig {
+            degraded_score: 0.8,
+            unhealthy_score: 0.4,
+            hysteresis_margin: 0.1,
+            recovery_checks: 2,
+            failure_threshold: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_weighted_score() {
+        let mut scorer = HealthScorer::new(config());
+        scorer.add_probe(Fixed::new(1.0), 3.0);
+        scorer.add_probe(Fixed::new(0.0), 1.0);
+
+        let status = scorer.evaluate(&HealthMetrics::default(), Instant::now());
+        assert_eq!(scorer.score(), Some(0.75));
+        assert!(matches!(status, HealthStatus::Degraded { .. }));
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let probe = Fixed::new(0.5);
+        let mut scorer = HealthScorer::new(config());
+        scorer.add_probe(probe.clone(), 1.0);
+        let metrics = HealthMetrics::default();
+        let now = Instant::now();
+
+        assert!(matches!(
+            scorer.evaluate(&metrics, now),
+            HealthStatus::Degraded { .. }
+        ));
+
+        // Just above the threshold is not enough to recover
+        probe.set(0.85);
+        assert!(matches!(
+            scorer.evaluate(&metrics, now),
+            HealthStatus::Degraded { .. }
+        ));
+
+        // Clearing the margin for two checks is
+        probe.set(0.95);
+        assert!(matches!(
+            scorer.evaluate(&metrics, now),
+            HealthStatus::Degraded { .. }
+        ));
+        assert_eq!(scorer.evaluate(&metrics, now), HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn test_persistent_degradation_escalates() {
+        let mut scorer = HealthScorer::new(HealthConfig {
+            failure_threshold: 3,
+            ..config()
+        });
+        scorer.add_probe(Fixed::new(0.6), 1.0);
+        let metrics = HealthMetrics::default();
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                scorer.evaluate(&metrics, now),
+                HealthStatus::Degraded { .. }
+            ));
+        }
+        assert!(matches!(
+            scorer.evaluate(&metrics, now),
+            HealthStatus::Unhealthy { .. }
+        ));
+    }
+
+    #[test]
+    fn test_decoder_error_probe() {
+        let metrics = HealthMetrics::default();
+        let decoder = DecoderErrorProbe::new(Duration::from_secs(10), 0.2);
+        assert!(decoder.sample(&metrics, Instant::now()).is_none());
+
+        for i in 0..20 {
+            if i % 10 == 0 {
+                decoder.record_error();
+            } else {
+                decoder.record_frame();
+            }
+        }
+        let reading = decoder.sample(&metrics, Instant::now()).unwrap();
+        assert!((reading.score - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rtcp_stats_probe() {
+        let metrics = HealthMetrics::default();
+        let rtcp = RtcpStatsProbe::new(0.1, 50.0);
+        assert!(rtcp.sample(&metrics, Instant::now()).is_none());
+
+        rtcp.update(RtcpStats {
+            packets_received: 1000,
+            packets_lost: 0,
+            jitter_ms: 10.0,
+        });
+        rtcp.update(RtcpStats {
+            packets_received: 1095,
+            packets_lost: 5,
+            jitter_ms: 10.0,
+        });
+        let reading = rtcp.sample(&metrics, Instant::now()).unwrap();
+        assert!((reading.score - 0.5).abs() < 1e-9);
+        assert_eq!(reading.reason.as_deref(), Some("Packet loss: 5.0%"));
+    }
+}
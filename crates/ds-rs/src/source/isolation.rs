@@ -1,7 +1,9 @@
-use super::{SourceId, SourceInfo};
+use super::{SourceEvent, SourceEventHandler, SourceId, SourceInfo};
 use crate::error::{DeepStreamError, Result};
+use gstreamer as gst;
+use std::any::Any;
 use std::panic::{self, AssertUnwindSafe};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
 use std::time::Duration;
 
@@ -99,13 +101,7 @@ impl ErrorBoundary {
             }
             Err(panic_info) => {
                 self.record_panic();
-                let msg = if let Some(s) = panic_info.downcast_ref::<String>() {
-                    s.clone()
-                } else if let Some(s) = panic_info.downcast_ref::<&str>() {
-                    (*s).to_string()
-                } else {
-                    "Unknown panic".to_string()
-                };
+                let msg = panic_message(panic_info.as_ref());
 
                 log::error!("Source {} panicked: {}", self.source_id, msg);
                 IsolationResult::Panic(msg)
@@ -133,13 +129,7 @@ impl ErrorBoundary {
                     let _ = tx.send(IsolationResult::Error(e));
                 }
                 Err(panic_info) => {
-                    let msg = if let Some(s) = panic_info.downcast_ref::<String>() {
-                        s.clone()
-                    } else if let Some(s) = panic_info.downcast_ref::<&str>() {
-                        (*s).to_string()
-                    } else {
-                        "Unknown panic".to_string()
-                    };
+                    let msg = panic_message(panic_info.as_ref());
 
                     log::error!("Source {} thread panicked: {}", source_id, msg);
                     let _ = tx.send(IsolationResult::Panic(msg));
@@ -282,6 +272,82 @@ impl IsolatedSource {
     }
 }
 
+/// Text of a panic payload
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+/// Run `f`, turning a panic into its message
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> std::result::Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+/// Runs user callbacks, such as probes and event handlers, so that a panic
+/// stays in the callback: it is logged and, when the guard belongs to a
+/// source, reported as an error event on that source
+#[derive(Clone)]
+pub struct PanicGuard {
+    name: String,
+    source: Option<SourceId>,
+    events: Option<Weak<SourceEventHandler>>,
+}
+
+impl PanicGuard {
+    /// Guard for the callback called `name` in logs and error events
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source: None,
+            events: None,
+        }
+    }
+
+    /// Report panics as errors of source `id`
+    pub fn for_source(mut self, id: SourceId, events: &Arc<SourceEventHandler>) -> Self {
+        self.source = Some(id);
+        self.events = Some(Arc::downgrade(events));
+        self
+    }
+
+    /// Run `f`; `None` if it panicked
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> Option<T> {
+        catch_panic(f).map_err(|msg| self.report(&msg)).ok()
+    }
+
+    /// Wrap a pad probe. A probe that panics is removed so it cannot fail
+    /// on every buffer, and the data it was handed flows on.
+    pub fn probe<F>(
+        self,
+        probe: F,
+    ) -> impl Fn(&gst::Pad, &mut gst::PadProbeInfo) -> gst::PadProbeReturn + Send + Sync + 'static
+    where
+        F: Fn(&gst::Pad, &mut gst::PadProbeInfo) -> gst::PadProbeReturn + Send + Sync + 'static,
+    {
+        move |pad, info| {
+            self.run(|| probe(pad, info))
+                .unwrap_or(gst::PadProbeReturn::Remove)
+        }
+    }
+
+    fn report(&self, msg: &str) {
+        let error = DeepStreamError::ProcessingFailed {
+            reason: format!("{} panicked: {}", self.name, msg),
+        };
+        log::error!("{}", error);
+        if let (Some(id), Some(events)) = (self.source, self.events.as_ref())
+            && let Some(events) = events.upgrade()
+        {
+            let _ = events.emit(SourceEvent::error(id, &error));
+        }
+    }
+}
+
 /// Manager for isolated sources
 pub struct IsolationManager {
     sources: Arc<Mutex<std::collections::HashMap<SourceId, Arc<IsolatedSource>>>>,
@@ -342,6 +408,7 @@ impl IsolationManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use gstreamer::prelude::*;
 
     #[test]
     fn test_error_boundary_success() {
@@ -389,6 +456,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_panic_guard_reports_on_source() {
+        let _ = gst::init();
+        let events = Arc::new(SourceEventHandler::new());
+        let guard = PanicGuard::new("test probe").for_source(SourceId(3), &events);
+
+        assert_eq!(guard.run(|| 7), Some(7));
+        assert_eq!(guard.run(|| -> i32 { panic!("bad frame") }), None);
+        match events.poll_event() {
+            Some(SourceEvent::Error { id, error, .. }) => {
+                assert_eq!(id, SourceId(3));
+                assert!(error.contains("test probe panicked: bad frame"));
+            }
+            other => panic!("Expected error event, got {:?}", other),
+        }
+
+        // The stream keeps flowing past a probe that panics
+        let pipeline = gst::parse::launch("videotestsrc num-buffers=3 name=src ! fakesink")
+            .unwrap()
+            .downcast::<gst::Pipeline>()
+            .unwrap();
+        let pad = pipeline
+            .by_name("src")
+            .and_then(|src| src.static_pad("src"))
+            .unwrap();
+        pad.add_probe(
+            gst::PadProbeType::BUFFER,
+            guard.probe(|_, _| panic!("probe bug")),
+        );
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let msg = pipeline
+            .bus()
+            .unwrap()
+            .timed_pop_filtered(
+                gst::ClockTime::from_seconds(5),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            )
+            .unwrap();
+        assert_eq!(msg.type_(), gst::MessageType::Eos);
+        pipeline.set_state(gst::State::Null).unwrap();
+        assert!(matches!(
+            events.poll_event(),
+            Some(SourceEvent::Error { .. })
+        ));
+        // Removed after the first panic
+        assert!(events.poll_event().is_none());
+    }
+
     #[test]
     fn test_isolated_source_quarantine() {
         let mut source = IsolatedSource::new(SourceId(0), IsolationPolicy::Basic);
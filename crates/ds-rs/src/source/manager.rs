@@ -1,3 +1,4 @@
+use super::uri::{SourceUri, redact};
 use super::{SourceId, SourceInfo, SourceManager, SourceState, VideoSource};
 use crate::error::{DeepStreamError, Result};
 use gstreamer as gst;
@@ -33,10 +34,10 @@ impl SourceAddition for SourceManager {
             "[{:.3}] Adding source {} with URI: {}",
             now.as_secs_f64(),
             id,
-            uri
+            redact(uri)
         );
 
-        let mut video_source = VideoSource::new(id, uri)?;
+        let mut video_source = self.new_video_source(id, uri)?;
 
         video_source.connect_pad_added_default(streammux)?;
 
@@ -92,7 +93,7 @@ impl SourceAddition for SourceManager {
             match self.add_video_source(uri) {
                 Ok(id) => source_ids.push(id),
                 Err(e) => {
-                    eprintln!("Failed to add source {}: {:?}", uri, e);
+                    eprintln!("Failed to add source {}: {:?}", redact(uri), e);
                     for added_id in &source_ids {
                         if let Err(remove_err) = self.remove_source(*added_id) {
                             eprintln!("Failed to rollback source {}: {:?}", added_id, remove_err);
@@ -107,6 +108,140 @@ impl SourceAddition for SourceManager {
     }
 }
 
+/// Streammux input for a source: `sink_<id>`, or the next compositor pad
+/// placed in the 2-column grid
+pub fn request_mux_pad(streammux: &gst::Element, id: SourceId) -> Result<gst::Pad> {
+    let is_compositor = streammux
+        .factory()
+        .map(|f| f.name() == "compositor")
+        .unwrap_or(false);
+
+    let pad = if is_compositor {
+        streammux.request_pad_simple("sink_%u").inspect(|pad| {
+            pad.set_property("xpos", ((id.0 % 2) * 640) as i32);
+            pad.set_property("ypos", ((id.0 / 2) * 480) as i32);
+        })
+    } else {
+        let pad_name = format!("sink_{}", id.0);
+        streammux
+            .static_pad(&pad_name)
+            .filter(|p| !p.is_linked())
+            .or_else(|| streammux.request_pad_simple(&pad_name))
+    };
+    pad.ok_or_else(|| DeepStreamError::PadLinking(format!("No streammux pad for source {}", id)))
+}
+
+/// Add a stage bin with `src` pad to the pipeline, feeding `downstream`
+/// or, without one, the source's streammux pad. The bin is taken out
+/// again if it cannot be linked.
+pub fn install_stage(
+    manager: &SourceManager,
+    id: SourceId,
+    bin: &gst::Element,
+    downstream: Option<&gst::Pad>,
+) -> Result<()> {
+    let pipeline = manager
+        .get_pipeline()
+        .ok_or_else(|| DeepStreamError::NotInitialized("Pipeline not set".to_string()))?;
+
+    pipeline.add_element(bin)?;
+    let target = match downstream {
+        Some(pad) => Ok(pad.clone()),
+        None => manager
+            .get_streammux()
+            .ok_or_else(|| DeepStreamError::NotInitialized("Streammux not set".to_string()))
+            .and_then(|mux| request_mux_pad(mux, id)),
+    };
+    let linked = target.and_then(|target| {
+        let src = bin
+            .static_pad("src")
+            .ok_or_else(|| DeepStreamError::PadNotFound {
+                element: bin.name().to_string(),
+                pad: "src".to_string(),
+            })?;
+        src.link(&target).map(|_| ()).map_err(|e| {
+            DeepStreamError::PadLinking(format!(
+                "Failed to link {} for source {}: {:?}",
+                bin.name(),
+                id,
+                e
+            ))
+        })
+    });
+    if let Err(e) = linked {
+        let _ = pipeline.remove_element(bin);
+        return Err(e);
+    }
+    bin.sync_state_with_parent()?;
+    Ok(())
+}
+
+/// Take a stage bin out of the pipeline once its source is gone
+pub fn remove_stage(manager: &SourceManager, bin: &gst::Element) -> Result<()> {
+    let _ = bin.set_state(gst::State::Null);
+    if let Some(pipeline) = manager.get_pipeline() {
+        pipeline.remove_element(bin)?;
+    }
+    Ok(())
+}
+
+/// Add a source whose video pad links to `input` instead of the
+/// streammux, for sources with processing stages in front of the muxer
+pub fn add_source_linked_to(
+    manager: &SourceManager,
+    id: SourceId,
+    uri: &str,
+    input: &gst::Pad,
+) -> Result<()> {
+    let pipeline = manager
+        .get_pipeline()
+        .ok_or_else(|| DeepStreamError::NotInitialized("Pipeline not set".to_string()))?;
+    let streammux = manager
+        .get_streammux()
+        .ok_or_else(|| DeepStreamError::NotInitialized("Streammux not set".to_string()))?;
+
+    let mut video_source = manager.new_video_source(id, uri)?;
+    if uri != "videotestsrc://" {
+        let input = input.clone();
+        video_source.connect_pad_added(streammux, move |_, pad, source_id, _| {
+            let caps = pad.current_caps().unwrap_or_else(|| pad.query_caps(None));
+            let is_video = caps
+                .structure(0)
+                .is_some_and(|s| s.name().starts_with("video/"));
+            if !is_video || input.is_linked() {
+                return;
+            }
+            if let Err(e) = pad.link(&input) {
+                eprintln!("Failed to link source {}: {:?}", source_id, e);
+            }
+        })?;
+    }
+
+    pipeline.add_element(video_source.element())?;
+    if uri == "videotestsrc://" {
+        let src = video_source
+            .element()
+            .static_pad("src")
+            .ok_or_else(|| DeepStreamError::Pipeline("Test source has no src pad".into()))?;
+        src.link(input).map_err(|e| {
+            DeepStreamError::PadLinking(format!("Failed to link test source {}: {:?}", id, e))
+        })?;
+    }
+    video_source.element().sync_state_with_parent()?;
+    video_source.update_state(SourceState::Playing)?;
+
+    manager.add_source(
+        id,
+        SourceInfo {
+            id,
+            uri: uri.to_string(),
+            source: video_source,
+            state: SourceState::Playing,
+            enabled: true,
+        },
+    )
+}
+
 pub struct SourceAddConfig {
     pub uri: String,
     pub buffer_size: Option<i32>,
@@ -140,26 +275,16 @@ impl SourceAddConfig {
     }
 }
 
+/// Check that `uri` is a URI the source bin can handle; plain paths are
+/// rejected here, see [`SourceUri::parse`] to turn them into URIs
 pub fn validate_uri(uri: &str) -> Result<()> {
-    if uri.is_empty() {
-        return Err(DeepStreamError::InvalidInput(
-            "URI cannot be empty".to_string(),
-        ));
-    }
-
-    if !uri.starts_with("file://")
-        && !uri.starts_with("rtsp://")
-        && !uri.starts_with("http://")
-        && !uri.starts_with("https://")
-        && !uri.starts_with("rtspt://")
-    {
+    if !uri.is_empty() && !uri.contains("://") {
         return Err(DeepStreamError::InvalidInput(format!(
-            "Invalid URI scheme. Supported: file://, rtsp://, http://, https://. Got: {}",
+            "Expected a URI such as file:///path, got: {}",
             uri
         )));
     }
-
-    Ok(())
+    SourceUri::parse(uri).map(|_| ())
 }
 
 pub fn create_source_bin_with_config(
@@ -1,16 +1,28 @@
 #![allow(unused)]
+pub mod alignment;
+pub mod batching;
 pub mod circuit_breaker;
 pub mod controller;
+pub mod correction;
+pub mod credentials;
+pub mod eos;
 pub mod events;
+pub mod fallback;
 pub mod fault_tolerant_controller;
+pub mod flow;
 pub mod health;
+pub mod health_probe;
 pub mod isolation;
 pub mod manager;
+pub mod progress;
 pub mod recovery;
 pub mod removal;
+pub mod stats;
 pub mod synchronization;
+pub mod uri;
 pub mod video_source;
 
+use crate::backend::GpuPlacement;
 use crate::error::{DeepStreamError, Result};
 use crate::pipeline::Pipeline;
 use gstreamer as gst;
@@ -18,18 +30,36 @@ use std::collections::HashMap;
 use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, RwLock};
 
+pub use alignment::{AlignMode, TimestampAligner, TimestampAlignment};
+pub use batching::{BatchPolicy, MuxBatcher};
 pub use circuit_breaker::{
-    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerManager, CircuitState,
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerManager, CircuitBreakerSnapshot,
+    CircuitState, CircuitStateKind, CircuitTransition,
 };
 pub use controller::SourceController;
+pub use correction::{CorrectionStage, Crop, DeinterlaceMode, Orientation, VideoCorrection};
+pub use credentials::{CredentialSpec, CredentialStore, ResolvedUri};
+pub use eos::{EosPolicies, EosPolicy};
 pub use events::{SourceEvent, SourceEventHandler};
+pub use fallback::{FallbackSlate, SlateConfig};
 pub use fault_tolerant_controller::FaultTolerantSourceController;
+pub use flow::FlowGate;
 pub use health::{HealthConfig, HealthMonitor, HealthStatus, SourceHealthMonitor};
-pub use isolation::{ErrorBoundary, IsolatedSource, IsolationManager, IsolationPolicy};
+pub use health_probe::{
+    BufferFlowProbe, DecoderErrorProbe, HealthProbe, HealthScorer, LatencyProbe, ProbeReading,
+    RtcpStats, RtcpStatsProbe,
+};
+pub use isolation::{ErrorBoundary, IsolatedSource, IsolationManager, IsolationPolicy, PanicGuard};
 pub use manager::SourceAddition;
-pub use recovery::{RecoveryConfig, RecoveryManager, RecoveryState, RecoveryStats};
+pub use progress::{ProgressConfig, ProgressReporter, SourceProgress};
+pub use recovery::{
+    RecoveryConfig, RecoveryManager, RecoveryPolicies, RecoveryPolicy, RecoveryState,
+    RecoveryStats, SourceKind,
+};
 pub use removal::SourceRemoval;
+pub use stats::{QueueLevel, SourceStats, SourceStatsCollector, SourceStatsRegistry};
 pub use synchronization::SourceSynchronizer;
+pub use uri::{Credentials, SourceUri, UriScheme, UriValidator};
 pub use video_source::VideoSource;
 
 pub const MAX_NUM_SOURCES: usize = 30;
@@ -69,6 +99,9 @@ pub struct SourceManager {
     source_enabled: Arc<RwLock<Vec<bool>>>,
     pipeline: Option<Arc<Pipeline>>,
     streammux: Option<gst::Element>,
+    credentials: Arc<CredentialStore>,
+    gpu_placement: RwLock<Option<Arc<GpuPlacement>>>,
+    decoder: RwLock<Option<String>>,
 }
 
 impl SourceManager {
@@ -83,6 +116,9 @@ impl SourceManager {
             source_enabled: Arc::new(RwLock::new(source_enabled)),
             pipeline: None,
             streammux: None,
+            credentials: Arc::new(CredentialStore::new()),
+            gpu_placement: RwLock::new(None),
+            decoder: RwLock::new(None),
         }
     }
 
@@ -98,6 +134,51 @@ impl SourceManager {
         self.streammux = Some(streammux);
     }
 
+    /// Named credentials that `{cred:name}` source URIs refer to
+    pub fn credentials(&self) -> Arc<CredentialStore> {
+        self.credentials.clone()
+    }
+
+    pub fn set_credentials(&mut self, credentials: Arc<CredentialStore>) {
+        self.credentials = credentials;
+    }
+
+    /// GPUs that sources added from now on decode on
+    pub fn set_gpu_placement(&self, placement: Option<Arc<GpuPlacement>>) {
+        *self.gpu_placement.write().unwrap() = placement;
+    }
+
+    pub fn gpu_placement(&self) -> Option<Arc<GpuPlacement>> {
+        self.gpu_placement.read().unwrap().clone()
+    }
+
+    /// Decoder factory sources added from now on plug, over the others
+    pub fn set_decoder(&self, decoder: Option<String>) {
+        *self.decoder.write().unwrap() = decoder;
+    }
+
+    pub fn decoder(&self) -> Option<String> {
+        self.decoder.read().unwrap().clone()
+    }
+
+    /// Source bin for `uri` with its credentials, inline or named, moved
+    /// from the URI onto the network source element, and its decoder,
+    /// the configured one if any, on the GPU placed for it
+    pub(crate) fn new_video_source(&self, id: SourceId, uri: &str) -> Result<VideoSource> {
+        let resolved = self.credentials.resolve(uri)?;
+        let source = VideoSource::new(id, &resolved.uri)?;
+        if let Some(credentials) = resolved.credentials {
+            source.set_credentials(credentials);
+        }
+        if let Some(placement) = self.gpu_placement() {
+            source.set_gpu_id(placement.assign(id, uri));
+        }
+        if let Some(decoder) = self.decoder() {
+            source.set_decoder(&decoder);
+        }
+        Ok(source)
+    }
+
     pub fn get_max_sources(&self) -> usize {
         self.max_sources
     }
@@ -170,6 +251,9 @@ impl SourceManager {
 
         // Mark as disabled to free the slot
         self.mark_source_enabled(id, false)?;
+        if let Some(placement) = self.gpu_placement() {
+            placement.release(id);
+        }
         Ok(info)
     }
 
@@ -185,6 +269,19 @@ impl SourceManager {
             .ok_or_else(|| DeepStreamError::InvalidInput(format!("Source {} not found", id)))
     }
 
+    /// The source's bin, without cloning its [`VideoSource`]
+    pub fn source_element(&self, id: SourceId) -> Result<gst::Element> {
+        let sources = self
+            .sources
+            .read()
+            .map_err(|_| DeepStreamError::Unknown("Failed to lock sources".to_string()))?;
+
+        sources
+            .get(&id)
+            .map(|info| info.source.element().clone())
+            .ok_or_else(|| DeepStreamError::InvalidInput(format!("Source {} not found", id)))
+    }
+
     pub fn get_source_info(&self, id: SourceId) -> Result<SourceInfo> {
         let sources = self
             .sources
@@ -0,0 +1,247 @@
+//! Playback position and duration of file sources
+//!
+//! Position and duration are queried on each source's bin, not on the
+//! pipeline, whose position is the running time of the muxed output. A
+//! [`ProgressReporter`] emits [`SourceEvent::Progress`] for every file
+//! source at an interval, and marks a source stalled when its position has
+//! not moved for a while although it is playing.
+
+use super::uri::{SourceUri, UriScheme};
+use super::{SourceEvent, SourceEventHandler, SourceId, SourceManager};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where a source is in its media
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceProgress {
+    pub position: Duration,
+    /// `None` for live sources and files whose length is not known yet
+    pub duration: Option<Duration>,
+}
+
+impl SourceProgress {
+    /// Query a source bin; `None` until it knows its position
+    pub fn query(element: &gst::Element) -> Option<Self> {
+        let position = element.query_position::<gst::ClockTime>()?;
+        let duration = element.query_duration::<gst::ClockTime>();
+        Some(Self {
+            position: Duration::from_nanos(position.nseconds()),
+            duration: duration.map(|duration| Duration::from_nanos(duration.nseconds())),
+        })
+    }
+
+    /// Share of the media played, from 0 to 1
+    pub fn fraction(&self) -> Option<f64> {
+        self.duration
+            .filter(|duration| !duration.is_zero())
+            .map(|duration| (self.position.as_secs_f64() / duration.as_secs_f64()).min(1.0))
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.duration
+            .map(|duration| duration.saturating_sub(self.position))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProgressConfig {
+    /// Time between progress events
+    pub interval: Duration,
+    /// A playing source whose position has not moved this long is stalled
+    pub stall_timeout: Duration,
+}
+
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            stall_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// When each source's position last moved
+#[derive(Debug, Default)]
+struct StallDetector {
+    moved: HashMap<SourceId, (Duration, Instant)>,
+}
+
+impl StallDetector {
+    /// Whether `id` is stalled at `progress`. Paused sources and sources
+    /// at the end of their media are not, and start the timeout over.
+    fn update(
+        &mut self,
+        id: SourceId,
+        progress: &SourceProgress,
+        playing: bool,
+        now: Instant,
+        timeout: Duration,
+    ) -> bool {
+        let finished = progress.remaining().is_some_and(|left| left.is_zero());
+        match self.moved.get_mut(&id) {
+            Some((position, since)) if playing && !finished && *position == progress.position => {
+                now.duration_since(*since) >= timeout
+            }
+            Some(moved) => {
+                *moved = (progress.position, now);
+                false
+            }
+            None => {
+                self.moved.insert(id, (progress.position, now));
+                false
+            }
+        }
+    }
+
+    fn retain(&mut self, ids: &[SourceId]) {
+        self.moved.retain(|id, _| ids.contains(id));
+    }
+}
+
+fn is_file_source(uri: &str) -> bool {
+    SourceUri::parse(uri).is_ok_and(|uri| uri.scheme() == UriScheme::File)
+}
+
+/// Periodic [`SourceEvent::Progress`] for file sources; stops when dropped
+/// or when the controller it reports for is gone
+pub struct ProgressReporter {
+    stop: Arc<AtomicBool>,
+}
+
+impl ProgressReporter {
+    pub(super) fn start(
+        manager: Weak<SourceManager>,
+        events: Weak<SourceEventHandler>,
+        config: ProgressConfig,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        thread::spawn(move || {
+            let mut stalls = StallDetector::default();
+            while !stopped.load(Ordering::Relaxed) {
+                thread::sleep(config.interval);
+                let (Some(manager), Some(events)) = (manager.upgrade(), events.upgrade()) else {
+                    break;
+                };
+                let Ok(ids) = manager.list_sources() else {
+                    continue;
+                };
+                stalls.retain(&ids);
+
+                for id in ids {
+                    let Ok(info) = manager.get_source_info(id) else {
+                        continue;
+                    };
+                    if !is_file_source(&info.uri) {
+                        continue;
+                    }
+                    let Ok(element) = manager.source_element(id) else {
+                        continue;
+                    };
+                    let Some(progress) = SourceProgress::query(&element) else {
+                        continue;
+                    };
+                    let playing = element.current_state() == gst::State::Playing;
+                    let stalled =
+                        stalls.update(id, &progress, playing, Instant::now(), config.stall_timeout);
+                    let _ = events.emit(SourceEvent::Progress {
+                        id,
+                        position: progress.position,
+                        duration: progress.duration,
+                        stalled,
+                    });
+                }
+            }
+        });
+        Self { stop }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(position_secs: u64) -> SourceProgress {
+        SourceProgress {
+            position: Duration::from_secs(position_secs),
+            duration: Some(Duration::from_secs(60)),
+        }
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_progress_fraction() {
+        assert_eq!(progress(15).fraction(), Some(0.25));
+        assert_eq!(progress(15).remaining(), Some(Duration::from_secs(45)));
+    }
+
+    #[test]
+    fn test_live_progress_has_no_fraction() {
+        let live = SourceProgress {
+            position: Duration::from_secs(15),
+            duration: None,
+        };
+        assert_eq!(live.fraction(), None);
+    }
+
+    #[test]
+    fn test_stall_after_timeout() {
+        let mut stalls = StallDetector::default();
+        let id = SourceId(0);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!stalls.update(id, &progress(1), true, at(0), TIMEOUT));
+        assert!(!stalls.update(id, &progress(1), true, at(4), TIMEOUT));
+        assert!(stalls.update(id, &progress(1), true, at(5), TIMEOUT));
+
+        // Moving again clears it
+        assert!(!stalls.update(id, &progress(2), true, at(6), TIMEOUT));
+    }
+
+    #[test]
+    fn test_paused_source_not_stalled() {
+        let mut stalls = StallDetector::default();
+        let id = SourceId(0);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!stalls.update(id, &progress(2), true, at(0), TIMEOUT));
+        assert!(!stalls.update(id, &progress(2), false, at(20), TIMEOUT));
+        assert!(!stalls.update(id, &progress(2), true, at(21), TIMEOUT));
+    }
+
+    #[test]
+    fn test_finished_file_not_stalled() {
+        let mut stalls = StallDetector::default();
+        let id = SourceId(0);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!stalls.update(id, &progress(60), true, at(0), TIMEOUT));
+        assert!(!stalls.update(id, &progress(60), true, at(10), TIMEOUT));
+    }
+
+    #[test]
+    fn test_retain_forgets_removed_sources() {
+        let mut stalls = StallDetector::default();
+        stalls.update(SourceId(0), &progress(1), true, Instant::now(), TIMEOUT);
+
+        stalls.retain(&[]);
+        assert!(stalls.moved.is_empty());
+    }
+}
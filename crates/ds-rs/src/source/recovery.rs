@@ -1,4 +1,6 @@
 use rand::{Rng, thread_rng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -41,6 +43,126 @@ impl Default for RecoveryConfig {
     }
 }
 
+/// Kind of source as far as recovery is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Rtsp,
+    File,
+    Http,
+    Other,
+}
+
+impl SourceKind {
+    pub fn from_uri(uri: &str) -> Self {
+        let scheme = uri.split_once("://").map(|(scheme, _)| scheme);
+        match scheme.map(str::to_ascii_lowercase).as_deref() {
+            Some("rtsp" | "rtspt" | "rtsps") => SourceKind::Rtsp,
+            Some("file") | None => SourceKind::File,
+            Some("http" | "https") => SourceKind::Http,
+            Some(_) => SourceKind::Other,
+        }
+    }
+}
+
+/// Serializable retry settings, turned into a [`RecoveryConfig`] per source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryPolicy {
+    /// `None` retries forever
+    pub max_retries: Option<usize>,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub backoff_multiplier: f64,
+    pub jitter_factor: f64,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: Some(3),
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60_000,
+            backoff_multiplier: 2.0,
+            jitter_factor: 0.3,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// Keep reconnecting with capped exponential backoff
+    pub fn retry_forever() -> Self {
+        Self {
+            max_retries: None,
+            ..Self::default()
+        }
+    }
+
+    /// Give up after `max_retries` attempts
+    pub fn limited(max_retries: usize) -> Self {
+        Self {
+            max_retries: Some(max_retries),
+            ..Self::default()
+        }
+    }
+
+    pub fn to_config(&self) -> RecoveryConfig {
+        RecoveryConfig {
+            max_retries: self.max_retries.unwrap_or(usize::MAX),
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+            backoff_multiplier: self.backoff_multiplier,
+            jitter_factor: self.jitter_factor.clamp(0.0, 1.0),
+            ..RecoveryConfig::default()
+        }
+    }
+}
+
+/// Recovery policies by source kind, with per-source overrides
+///
+/// A source's policy is the first of: its entry in `sources` (keyed by URI),
+/// the policy for its [`SourceKind`], then `default`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryPolicies {
+    pub default: RecoveryPolicy,
+    pub rtsp: Option<RecoveryPolicy>,
+    pub file: Option<RecoveryPolicy>,
+    pub http: Option<RecoveryPolicy>,
+    pub sources: HashMap<String, RecoveryPolicy>,
+}
+
+impl Default for RecoveryPolicies {
+    fn default() -> Self {
+        Self {
+            default: RecoveryPolicy::default(),
+            // Cameras come back; a broken file does not
+            rtsp: Some(RecoveryPolicy::retry_forever()),
+            file: Some(RecoveryPolicy::limited(3)),
+            http: None,
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl RecoveryPolicies {
+    pub fn for_kind(&self, kind: SourceKind) -> &RecoveryPolicy {
+        let policy = match kind {
+            SourceKind::Rtsp => self.rtsp.as_ref(),
+            SourceKind::File => self.file.as_ref(),
+            SourceKind::Http => self.http.as_ref(),
+            SourceKind::Other => None,
+        };
+        policy.unwrap_or(&self.default)
+    }
+
+    pub fn for_uri(&self, uri: &str) -> &RecoveryPolicy {
+        self.sources
+            .get(uri)
+            .unwrap_or_else(|| self.for_kind(SourceKind::from_uri(uri)))
+    }
+}
+
 /// Represents the current state of recovery attempts
 #[derive(Debug, Clone, PartialEq)]
 pub enum RecoveryState {
@@ -324,6 +446,54 @@ mod tests {
         assert!(!manager.should_retry());
     }
 
+    #[test]
+    fn test_policy_selection() {
+        let mut policies = RecoveryPolicies::default();
+        policies.sources.insert(
+            "rtsp://10.0.0.5/flaky".to_string(),
+            RecoveryPolicy::limited(1),
+        );
+
+        assert_eq!(policies.for_uri("rtsp://10.0.0.5/cam").max_retries, None);
+        assert_eq!(
+            policies.for_uri("rtsp://10.0.0.5/flaky").max_retries,
+            Some(1)
+        );
+        assert_eq!(policies.for_uri("file:///a.mp4").max_retries, Some(3));
+        assert_eq!(policies.for_uri("/videos/a.mp4").max_retries, Some(3));
+        assert_eq!(policies.for_uri("http://host/a.mp4"), &policies.default);
+
+        let policies: RecoveryPolicies = toml::from_str(
+            r#"
+            [default]
+            max_retries = 5
+
+            [http]
+            initial_backoff_ms = 250
+            "#,
+        )
+        .unwrap();
+        assert_eq!(policies.default.max_retries, Some(5));
+        assert_eq!(policies.for_kind(SourceKind::Http).initial_backoff_ms, 250);
+        // Kinds left out of the file keep their defaults
+        assert_eq!(policies.for_kind(SourceKind::Rtsp).max_retries, None);
+    }
+
+    #[test]
+    fn test_retry_forever() {
+        let manager = RecoveryManager::new(RecoveryConfig {
+            jitter_factor: 0.0,
+            ..RecoveryPolicy::retry_forever().to_config()
+        });
+
+        for _ in 0..50 {
+            assert!(manager.start_recovery().is_some());
+            manager.mark_failed("Connection refused".to_string());
+        }
+        assert!(manager.should_retry());
+        assert_eq!(manager.calculate_backoff(50), Duration::from_secs(60));
+    }
+
     #[test]
     fn test_recovery_statistics() {
         let manager = RecoveryManager::new(RecoveryConfig::default());
@@ -0,0 +1,370 @@
+//! Per-source statistics for dashboards
+//!
+//! Pad probes keep the counters current: one on the source's output pad
+//! counts decoded frames and records the negotiated caps, and one on the
+//! decoder's input pad counts encoded bytes. Frame rate and bitrate are
+//! measured over the last few seconds when a snapshot is taken, and queue
+//! levels are read from the source bin at the same time.
+
+use super::{SourceEvent, SourceEventHandler, SourceId};
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer::glib;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Span over which frame rate and bitrate are measured
+const RATE_WINDOW: Duration = Duration::from_secs(3);
+
+/// Fill level of one queue inside a source bin
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueLevel {
+    pub name: String,
+    pub buffers: u32,
+    pub bytes: u32,
+    pub time: Duration,
+}
+
+/// Snapshot of one source, as returned by
+/// [`SourceController::get_source_stats`](super::SourceController::get_source_stats)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceStats {
+    /// Caps negotiated on the source's output pad
+    pub caps: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Encoded format fed to the decoder, e.g. `h264`
+    pub codec: Option<String>,
+    /// Factory name of the decoder element
+    pub decoder: Option<String>,
+    pub fps: f64,
+    pub bitrate_bps: f64,
+    pub frames: u64,
+    pub encoded_bytes: u64,
+    pub queues: Vec<QueueLevel>,
+    pub errors: u64,
+    pub warnings: u64,
+    pub last_error: Option<String>,
+}
+
+/// Amounts seen over the trailing [`RATE_WINDOW`]
+#[derive(Default)]
+struct RateWindow {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateWindow {
+    fn add(&mut self, now: Instant, amount: u64) {
+        self.samples.push_back((now, amount));
+        while let Some(&(at, _)) = self.samples.front() {
+            if now.saturating_duration_since(at) <= RATE_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    /// Per-second rate between the first and last sample in the window
+    fn rate(&self, now: Instant) -> f64 {
+        let mut recent = self
+            .samples
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) <= RATE_WINDOW);
+        let Some(&(first, _)) = recent.next() else {
+            return 0.0;
+        };
+        let (last, total) = recent.fold((first, 0u64), |(_, total), &(at, amount)| {
+            (at, total + amount)
+        });
+        let span = last.saturating_duration_since(first).as_secs_f64();
+        if span > 0.0 { total as f64 / span } else { 0.0 }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    stats: SourceStats,
+    frames: RateWindow,
+    bytes: RateWindow,
+}
+
+/// Counters for one source, fed by pad probes and source events
+#[derive(Default)]
+pub struct SourceStatsCollector {
+    counters: Mutex<Counters>,
+    bin: Mutex<Option<glib::WeakRef<gst::Element>>>,
+}
+
+impl SourceStatsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_frame(&self, now: Instant) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.stats.frames += 1;
+        counters.frames.add(now, 1);
+    }
+
+    pub fn record_encoded(&self, bytes: u64, now: Instant) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.stats.encoded_bytes += bytes;
+        counters.bytes.add(now, bytes);
+    }
+
+    pub fn set_caps(&self, caps: &gst::CapsRef) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.stats.caps = Some(caps.to_string());
+        if let Some(structure) = caps.structure(0) {
+            counters.stats.width = structure.get::<i32>("width").ok();
+            counters.stats.height = structure.get::<i32>("height").ok();
+        }
+    }
+
+    pub fn set_decoder(&self, decoder: &str, input_caps: Option<&gst::CapsRef>) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.stats.decoder = Some(decoder.to_string());
+        if let Some(structure) = input_caps.and_then(|caps| caps.structure(0)) {
+            let name = structure.name().as_str();
+            let codec = name.strip_prefix("video/x-").unwrap_or(name);
+            counters.stats.codec = Some(codec.to_string());
+        }
+    }
+
+    /// Count errors and warnings reported for this source
+    pub fn record_event(&self, event: &SourceEvent) {
+        let mut counters = self.counters.lock().unwrap();
+        match event {
+            SourceEvent::Error { error, .. } => {
+                counters.stats.errors += 1;
+                counters.stats.last_error = Some(error.clone());
+            }
+            SourceEvent::Warning { .. } => counters.stats.warnings += 1,
+            _ => {}
+        }
+    }
+
+    /// Install probes on a source bin's output pads and on any decoder
+    /// that appears inside it
+    pub fn attach(self: &Arc<Self>, bin: &gst::Element) {
+        *self.bin.lock().unwrap() = Some(bin.downgrade());
+
+        for pad in bin.src_pads() {
+            self.probe_output(&pad);
+        }
+        let collector = Arc::downgrade(self);
+        bin.connect_pad_added(move |_, pad| {
+            if let Some(collector) = collector.upgrade()
+                && pad.direction() == gst::PadDirection::Src
+            {
+                collector.probe_output(pad);
+            }
+        });
+
+        if let Some(bin) = bin.downcast_ref::<gst::Bin>() {
+            let collector = Arc::downgrade(self);
+            bin.connect_deep_element_added(move |_, _, element| {
+                let is_decoder = element
+                    .factory()
+                    .is_some_and(|factory| factory.klass().contains("Decoder"));
+                if let Some(collector) = collector.upgrade()
+                    && is_decoder
+                {
+                    collector.probe_decoder(element);
+                }
+            });
+        }
+    }
+
+    fn probe_output(self: &Arc<Self>, pad: &gst::Pad) {
+        let collector = Arc::downgrade(self);
+        pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::EVENT_DOWNSTREAM,
+            move |_, info| {
+                let Some(collector) = collector.upgrade() else {
+                    return gst::PadProbeReturn::Remove;
+                };
+                match &info.data {
+                    Some(gst::PadProbeData::Buffer(_)) => collector.record_frame(Instant::now()),
+                    Some(gst::PadProbeData::Event(event)) => {
+                        if let gst::EventView::Caps(caps) = event.view() {
+                            collector.set_caps(caps.caps());
+                        }
+                    }
+                    _ => {}
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+
+    fn probe_decoder(self: &Arc<Self>, decoder: &gst::Element) {
+        let name = decoder
+            .factory()
+            .map(|factory| factory.name().to_string())
+            .unwrap_or_else(|| decoder.name().to_string());
+        self.set_decoder(&name, None);
+
+        let Some(sink) = decoder.static_pad("sink") else {
+            return;
+        };
+        let collector = Arc::downgrade(self);
+        sink.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::EVENT_DOWNSTREAM,
+            move |_, info| {
+                let Some(collector) = collector.upgrade() else {
+                    return gst::PadProbeReturn::Remove;
+                };
+                match &info.data {
+                    Some(gst::PadProbeData::Buffer(buffer)) => {
+                        collector.record_encoded(buffer.size() as u64, Instant::now())
+                    }
+                    Some(gst::PadProbeData::Event(event)) => {
+                        if let gst::EventView::Caps(caps) = event.view() {
+                            collector.set_decoder(&name, Some(caps.caps()));
+                        }
+                    }
+                    _ => {}
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+
+    pub fn snapshot(&self) -> SourceStats {
+        let now = Instant::now();
+        let mut stats = {
+            let counters = self.counters.lock().unwrap();
+            let mut stats = counters.stats.clone();
+            stats.fps = counters.frames.rate(now);
+            stats.bitrate_bps = counters.bytes.rate(now) * 8.0;
+            stats
+        };
+
+        let bin = self
+            .bin
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|bin| bin.upgrade());
+        if let Some(bin) = bin.as_ref().and_then(|bin| bin.downcast_ref::<gst::Bin>()) {
+            stats.queues = queue_levels(bin);
+        }
+        stats
+    }
+}
+
+/// Levels of every `queue`/`queue2` inside `bin`
+fn queue_levels(bin: &gst::Bin) -> Vec<QueueLevel> {
+    bin.iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter(|element| element.find_property("current-level-buffers").is_some())
+        .map(|queue| QueueLevel {
+            name: queue.name().to_string(),
+            buffers: queue.property::<u32>("current-level-buffers"),
+            bytes: queue.property::<u32>("current-level-bytes"),
+            time: Duration::from_nanos(queue.property::<u64>("current-level-time")),
+        })
+        .collect()
+}
+
+/// Collectors for every source of a controller
+#[derive(Default)]
+pub struct SourceStatsRegistry {
+    sources: Mutex<HashMap<SourceId, Arc<SourceStatsCollector>>>,
+}
+
+impl SourceStatsRegistry {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Count errors and warnings from `handler` against their sources
+    pub fn follow(self: &Arc<Self>, handler: &SourceEventHandler) {
+        let registry = Arc::downgrade(self);
+        handler.register_callback(move |event| {
+            if let Some(registry) = registry.upgrade()
+                && let Some(collector) = registry.collector(event.source_id())
+            {
+                collector.record_event(event);
+            }
+        });
+    }
+
+    /// Start collecting for a source whose bin is `bin`
+    pub fn track(&self, id: SourceId, bin: &gst::Element) {
+        let collector = Arc::new(SourceStatsCollector::new());
+        collector.attach(bin);
+        self.sources.lock().unwrap().insert(id, collector);
+    }
+
+    pub fn untrack(&self, id: SourceId) {
+        self.sources.lock().unwrap().remove(&id);
+    }
+
+    pub fn collector(&self, id: SourceId) -> Option<Arc<SourceStatsCollector>> {
+        self.sources.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn get(&self, id: SourceId) -> Option<SourceStats> {
+        self.collector(id).map(|collector| collector.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three seconds of 10 fps video at 1000 bytes per frame
+    fn collector_at_10fps(start: Instant) -> SourceStatsCollector {
+        let collector = SourceStatsCollector::new();
+        for i in 0..=30u32 {
+            let at = start + Duration::from_millis(100) * i;
+            collector.record_frame(at);
+            collector.record_encoded(1_000, at);
+        }
+        collector
+    }
+
+    #[test]
+    fn test_rates() {
+        let start = Instant::now();
+        let collector = collector_at_10fps(start);
+
+        let counters = collector.counters.lock().unwrap();
+        let now = start + Duration::from_secs(3);
+        assert!((counters.frames.rate(now) - 10.0).abs() < 0.01);
+        assert!((counters.bytes.rate(now) - 10_000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_stale_rate_is_zero() {
+        let start = Instant::now();
+        let collector = collector_at_10fps(start);
+
+        // Nothing recent means no rate rather than a stale one
+        let counters = collector.counters.lock().unwrap();
+        assert_eq!(counters.frames.rate(start + Duration::from_secs(13)), 0.0);
+    }
+
+    #[test]
+    fn test_counters() {
+        let collector = collector_at_10fps(Instant::now());
+        collector.record_event(&SourceEvent::Warning {
+            id: SourceId(0),
+            warning: "late".to_string(),
+        });
+        collector.record_event(&SourceEvent::error(
+            SourceId(0),
+            &crate::error::DeepStreamError::Timeout("read".to_string()),
+        ));
+
+        let stats = collector.snapshot();
+        assert_eq!((stats.frames, stats.encoded_bytes), (31, 31_000));
+        assert_eq!((stats.errors, stats.warnings), (1, 1));
+        assert_eq!(stats.last_error.as_deref(), Some("Timeout: read"));
+    }
+}
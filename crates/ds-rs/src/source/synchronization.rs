@@ -1,3 +1,4 @@
+use super::alignment::{TimestampAligner, TimestampAlignment};
 use super::{SourceId, SourceManager, SourceState};
 use crate::error::{DeepStreamError, Result};
 use gstreamer as gst;
@@ -8,21 +9,33 @@ use std::time::Duration;
 
 pub struct SourceSynchronizer {
     manager: Arc<SourceManager>,
+    aligner: TimestampAligner,
 }
 
 impl SourceSynchronizer {
     pub fn new(manager: Arc<SourceManager>) -> Self {
-        Self { manager }
+        Self {
+            manager,
+            aligner: TimestampAligner::new(),
+        }
     }
 
     pub fn sync_source_with_pipeline(&self, source_id: SourceId) -> Result<()> {
-        let _pipeline = self
+        let pipeline = self
             .manager
             .get_pipeline()
             .ok_or_else(|| DeepStreamError::NotInitialized("Pipeline not set".to_string()))?;
 
         let source = self.manager.get_source(source_id)?;
 
+        // Before any data flows, so the first buffer is aligned too
+        self.aligner.attach(
+            source_id,
+            source.uri(),
+            source.element(),
+            pipeline.gst_pipeline().upcast_ref(),
+        );
+
         // Use sync_state_with_parent() to properly synchronize with pipeline
         // This ensures the element inherits the pipeline's clock and base time
         let element = source.element();
@@ -31,6 +44,19 @@ impl SourceSynchronizer {
         Ok(())
     }
 
+    /// How sources added from now on are aligned for batching
+    pub fn set_alignment(&self, alignment: TimestampAlignment) {
+        self.aligner.set_config(alignment);
+    }
+
+    pub fn alignment(&self) -> TimestampAlignment {
+        self.aligner.config()
+    }
+
+    pub fn aligner(&self) -> &TimestampAligner {
+        &self.aligner
+    }
+
     pub fn wait_for_state(
         &self,
         source_id: SourceId,
@@ -1,6 +1,9 @@
+use super::isolation::PanicGuard;
+use super::uri::Credentials;
 use super::{SourceId, SourceState};
 use crate::error::{DeepStreamError, Result};
 use gstreamer as gst;
+use gstreamer::glib;
 use gstreamer::prelude::*;
 use std::sync::{Arc, Mutex};
 
@@ -24,9 +27,14 @@ impl Clone for VideoSource {
     }
 }
 
+/// Name of the bin a source's elements are in
+pub fn source_bin_name(source_id: SourceId) -> String {
+    format!("source-bin-{:02}", source_id.0)
+}
+
 impl VideoSource {
     pub fn new(source_id: SourceId, uri: &str) -> Result<Self> {
-        let bin_name = format!("source-bin-{:02}", source_id.0);
+        let bin_name = source_bin_name(source_id);
 
         // Handle special test source URI
         let (source_bin, final_uri) = if uri == "videotestsrc://" {
@@ -114,6 +122,7 @@ impl VideoSource {
     {
         let source_id = self.source_id;
         let streammux_weak = streammux.downgrade();
+        let guard = PanicGuard::new(&format!("pad-added callback of source {}", source_id));
 
         let handler_id = self.source_bin.connect_pad_added(move |decodebin, pad| {
             let now = std::time::SystemTime::now()
@@ -128,7 +137,7 @@ impl VideoSource {
             );
 
             if let Some(streammux) = streammux_weak.upgrade() {
-                callback(decodebin, pad, source_id, &streammux);
+                guard.run(|| callback(decodebin, pad, source_id, &streammux));
             } else {
                 eprintln!(
                     "[{}] Failed to upgrade streammux weak reference for source {}",
@@ -450,6 +459,75 @@ impl VideoSource {
         &self.uri
     }
 
+    /// Set `credentials` on the network source element uridecodebin
+    /// creates, through `user-id` and `user-pw` where it has them
+    pub fn set_credentials(&self, credentials: Credentials) {
+        if self.source_bin.find_property("uri").is_none() {
+            return;
+        }
+        self.source_bin.connect("source-setup", false, move |args| {
+            let Ok(source) = args[1].get::<gst::Element>() else {
+                return None;
+            };
+            if source.find_property("user-id").is_some() {
+                source.set_property("user-id", &credentials.username);
+                if let Some(password) = &credentials.password {
+                    source.set_property("user-pw", password);
+                }
+            } else {
+                log::warn!("{} takes no credentials, ignoring them", source.name());
+            }
+            None
+        });
+    }
+
+    /// Have uridecodebin plug `decoder` over the other decoders for caps it
+    /// takes. Only this source's bin is affected, unlike raising the rank.
+    pub fn set_decoder(&self, decoder: &str) {
+        if self.source_bin.find_property("uri").is_none() {
+            return;
+        }
+        let Some(preferred) = gst::ElementFactory::find(decoder) else {
+            log::warn!("Decoder {} is not installed, keeping the default", decoder);
+            return;
+        };
+        let Some(results) =
+            glib::Type::from_name("GstAutoplugSelectResult").and_then(glib::EnumClass::with_type)
+        else {
+            return;
+        };
+        self.source_bin
+            .connect("autoplug-select", false, move |args| {
+                let skip = match (
+                    args[2].get::<gst::Caps>(),
+                    args[3].get::<gst::ElementFactory>(),
+                ) {
+                    (Ok(caps), Ok(factory)) => {
+                        factory != preferred
+                            && factory.has_type(gst::ElementFactoryType::DECODER)
+                            && preferred.can_sink_any_caps(&caps)
+                    }
+                    _ => false,
+                };
+                // GST_AUTOPLUG_SELECT_TRY or GST_AUTOPLUG_SELECT_SKIP
+                results.to_value(if skip { 2 } else { 0 })
+            });
+    }
+
+    /// Put the decoder and converters uridecodebin plugs on `gpu`
+    pub fn set_gpu_id(&self, gpu: u32) {
+        if self.source_bin.find_property("uri").is_none() {
+            return;
+        }
+        self.source_bin
+            .connect("deep-element-added", false, move |args| {
+                if let Ok(element) = args[2].get::<gst::Element>() {
+                    crate::backend::gpu::set_gpu_id(&element, gpu);
+                }
+                None
+            });
+    }
+
     pub fn current_state(&self) -> SourceState {
         self.state
             .lock()
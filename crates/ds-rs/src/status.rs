@@ -0,0 +1,246 @@
+//! Small HTTP server for status and debug endpoints
+//!
+//! Requests are served by axum on a runtime of the server's own, so the
+//! pipeline does not need one. Handlers are registered per path and may be
+//! added while the server runs; they answer `GET` (and `HEAD`) requests and
+//! run on the blocking pool. [`Application::serve_status`](crate::app::Application::serve_status)
+//! starts one with the standard endpoints.
+
+use crate::error::Result;
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::{Method, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Default)]
+pub struct StatusRequest {
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl StatusResponse {
+    pub fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_vec_pretty(value) {
+            Ok(body) => Self {
+                status: 200,
+                content_type: "application/json".to_string(),
+                body,
+            },
+            Err(e) => Self::text(500, &format!("Cannot serialize response: {}", e)),
+        }
+    }
+
+    pub fn text(status: u16, text: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8".to_string(),
+            body: text.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn bytes(content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: content_type.to_string(),
+            body,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "Not found")
+    }
+}
+
+type Handler = Arc<dyn Fn(&StatusRequest) -> StatusResponse + Send + Sync>;
+type Routes = Arc<RwLock<HashMap<String, Handler>>>;
+
+impl IntoResponse for StatusResponse {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let headers = [
+            (header::CONTENT_TYPE, self.content_type),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ];
+        (status, headers, self.body).into_response()
+    }
+}
+
+pub struct StatusServer {
+    address: SocketAddr,
+    routes: Routes,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatusServer {
+    /// Listen on `address`; port 0 picks a free port
+    pub fn bind(address: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let routes: Routes = Arc::default();
+        let (shutdown, stopped) = oneshot::channel();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let app = Router::new().fallback(dispatch).with_state(routes.clone());
+        let thread = std::thread::Builder::new()
+            .name("status-http".to_string())
+            .spawn(move || {
+                let served = runtime.block_on(async move {
+                    let listener = tokio::net::TcpListener::from_std(listener)?;
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(async {
+                            let _ = stopped.await;
+                        })
+                        .await
+                });
+                if let Err(e) = served {
+                    log::warn!("Status server stopped: {}", e);
+                }
+            })?;
+
+        log::info!("Status endpoints on http://{}", address);
+        Ok(Self {
+            address,
+            routes,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    /// Answer `GET path` with `handler`, replacing any earlier handler
+    pub fn route<F>(&self, path: &str, handler: F)
+    where
+        F: Fn(&StatusRequest) -> StatusResponse + Send + Sync + 'static,
+    {
+        self.routes
+            .write()
+            .unwrap()
+            .insert(path.to_string(), Arc::new(handler));
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Registered paths, sorted
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.routes.read().unwrap().keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Stop accepting connections and wait for open requests to finish
+    pub fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn dispatch(
+    State(routes): State<Routes>,
+    method: Method,
+    uri: Uri,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    if method != Method::GET && method != Method::HEAD {
+        return StatusResponse::text(405, "Only GET is supported").into_response();
+    }
+
+    let request = StatusRequest {
+        path: uri.path().trim_end_matches('/').to_string(),
+        query,
+    };
+    let handler = routes.read().unwrap().get(&request.path).cloned();
+    let Some(handler) = handler else {
+        return StatusResponse::not_found().into_response();
+    };
+    match tokio::task::spawn_blocking(move || handler(&request)).await {
+        Ok(response) => response.into_response(),
+        Err(e) => StatusResponse::text(500, &format!("Handler failed: {}", e)).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn request(address: SocketAddr, method: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n",
+            method, path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        request(address, "GET", path)
+    }
+
+    #[test]
+    fn test_status_routes() {
+        let mut server = StatusServer::bind("127.0.0.1:0").unwrap();
+        server.route("/api/v1/echo", |request| {
+            StatusResponse::json(&request.query.get("name"))
+        });
+
+        let response = get(server.local_addr(), "/api/v1/echo/?name=cam1");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\"cam1\""));
+        assert!(get(server.local_addr(), "/missing").starts_with("HTTP/1.1 404"));
+        assert_eq!(server.paths(), ["/api/v1/echo"]);
+        server.stop();
+    }
+
+    #[test]
+    fn test_query_is_decoded() {
+        let server = StatusServer::bind("127.0.0.1:0").unwrap();
+        server.route("/echo", |request| {
+            StatusResponse::json(&request.query.get("name"))
+        });
+
+        let response = get(server.local_addr(), "/echo?name=front%20door");
+        assert!(response.ends_with("\"front door\""));
+    }
+
+    #[test]
+    fn test_only_get_is_served() {
+        let server = StatusServer::bind("127.0.0.1:0").unwrap();
+        server.route("/echo", |_| StatusResponse::text(200, "ok"));
+
+        assert!(request(server.local_addr(), "POST", "/echo").starts_with("HTTP/1.1 405"));
+        assert!(request(server.local_addr(), "HEAD", "/echo").starts_with("HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,694 @@
+//! Software multi-object trackers
+//!
+//! [`TrackerAlgorithm`] assigns track IDs to per-frame detections. Three
+//! association strategies are provided:
+//!
+//! - [`IouTracker`]: match each detection to the track whose last box
+//!   overlaps it most
+//! - [`SortTracker`]: match against boxes predicted by a constant-velocity
+//!   Kalman filter and only report tracks after `min_hits` frames
+//! - [`ByteTracker`]: SORT-style matching of confident detections, then a
+//!   second pass that keeps tracks alive with low-confidence detections
+//!
+//! All of them keep their bookkeeping in an [`ObjectTracker`], so
+//! [`TrackStatus`] and [`Trajectory`] look the same whichever is selected.
+
+use super::{ObjectTracker, TrackStatus, TrackerState, TrackingStats, Trajectory};
+use crate::metadata::{BoundingBox, ObjectMeta};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Association strategy used by the software tracker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackerAlgorithmKind {
+    Iou,
+    #[default]
+    Sort,
+    ByteTrack,
+}
+
+impl fmt::Display for TrackerAlgorithmKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TrackerAlgorithmKind::Iou => "iou",
+            TrackerAlgorithmKind::Sort => "sort",
+            TrackerAlgorithmKind::ByteTrack => "bytetrack",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for TrackerAlgorithmKind {
+    type Err = super::TrackingError;
+
+    fn from_str(s: &str) -> super::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "iou" => Ok(TrackerAlgorithmKind::Iou),
+            "sort" => Ok(TrackerAlgorithmKind::Sort),
+            "bytetrack" | "byte" => Ok(TrackerAlgorithmKind::ByteTrack),
+            other => Err(super::TrackingError::TrackingFailed(format!(
+                "Unknown tracker algorithm '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Software tracker settings, `[tracking]` in the application config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackerAlgorithmConfig {
+    pub algorithm: TrackerAlgorithmKind,
+
+    /// Minimum IoU for a detection to continue a track
+    pub iou_threshold: f32,
+
+    /// Frames a lost track is kept before removal
+    pub max_age: u32,
+
+    /// Consecutive hits before a SORT/ByteTrack track is reported
+    pub min_hits: u32,
+
+    /// ByteTrack: detections at or above this are matched first and may
+    /// start new tracks
+    pub high_threshold: f32,
+
+    /// ByteTrack: detections below this are ignored
+    pub low_threshold: f32,
+
+    pub max_tracks: usize,
+    pub max_history: usize,
+}
+
+impl Default for TrackerAlgorithmConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: TrackerAlgorithmKind::default(),
+            iou_threshold: 0.3,
+            max_age: 30,
+            min_hits: 3,
+            high_threshold: 0.6,
+            low_threshold: 0.1,
+            max_tracks: 256,
+            max_history: 50,
+        }
+    }
+}
+
+impl TrackerAlgorithmConfig {
+    pub fn with_algorithm(algorithm: TrackerAlgorithmKind) -> Self {
+        Self {
+            algorithm,
+            ..Self::default()
+        }
+    }
+
+    /// Create the configured tracker
+    pub fn build(&self) -> Box<dyn TrackerAlgorithm> {
+        match self.algorithm {
+            TrackerAlgorithmKind::Iou => Box::new(IouTracker::new(self.clone())),
+            TrackerAlgorithmKind::Sort => Box::new(SortTracker::new(self.clone())),
+            TrackerAlgorithmKind::ByteTrack => Box::new(ByteTracker::new(self.clone())),
+        }
+    }
+}
+
+/// A frame-by-frame multi-object tracker
+pub trait TrackerAlgorithm: Send {
+    fn kind(&self) -> TrackerAlgorithmKind;
+
+    /// Associate one frame of detections with tracks. Returns the reported
+    /// objects with `object_id` set to their track ID; detections that were
+    /// not associated or whose track is not yet confirmed are omitted.
+    fn update(&mut self, detections: &[ObjectMeta], timestamp: u64) -> Vec<ObjectMeta>;
+
+    fn track_status(&self, track_id: u64) -> Option<&TrackStatus>;
+
+    fn trajectory(&self, track_id: u64) -> Option<&Trajectory>;
+
+    fn stats(&self) -> TrackingStats;
+
+    /// Drop all tracks, e.g. after a source restarts
+    fn reset(&mut self);
+}
+
+/// Constant-velocity Kalman filter for one box coordinate
+#[derive(Debug, Clone)]
+struct Kalman1D {
+    x: f32,
+    v: f32,
+    p: [[f32; 2]; 2],
+}
+
+impl Kalman1D {
+    const PROCESS_NOISE: f32 = 1.0;
+    const MEASUREMENT_NOISE: f32 = 10.0;
+
+    fn new(x: f32) -> Self {
+        Self {
+            x,
+            v: 0.0,
+            p: [[10.0, 0.0], [0.0, 1000.0]],
+        }
+    }
+
+    fn predict(&mut self) {
+        self.x += self.v;
+        let [[p00, p01], [p10, p11]] = self.p;
+        self.p = [
+            [p00 + p01 + p10 + p11 + Self::PROCESS_NOISE, p01 + p11],
+            [p10 + p11, p11 + Self::PROCESS_NOISE],
+        ];
+    }
+
+    fn correct(&mut self, z: f32) {
+        let [[p00, p01], [p10, p11]] = self.p;
+        let s = p00 + Self::MEASUREMENT_NOISE;
+        let (k0, k1) = (p00 / s, p10 / s);
+        let residual = z - self.x;
+
+        self.x += k0 * residual;
+        self.v += k1 * residual;
+        self.p = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+}
+
+/// Box motion model tracking center and size independently
+#[derive(Debug, Clone)]
+struct BoxFilter {
+    axes: [Kalman1D; 4],
+}
+
+impl BoxFilter {
+    fn new(bbox: &BoundingBox) -> Self {
+        let (cx, cy) = bbox.center();
+        Self {
+            axes: [
+                Kalman1D::new(cx),
+                Kalman1D::new(cy),
+                Kalman1D::new(bbox.width),
+                Kalman1D::new(bbox.height),
+            ],
+        }
+    }
+
+    fn predict(&mut self) -> BoundingBox {
+        self.axes.iter_mut().for_each(Kalman1D::predict);
+        self.bbox()
+    }
+
+    fn correct(&mut self, bbox: &BoundingBox) -> BoundingBox {
+        let (cx, cy) = bbox.center();
+        for (axis, z) in self.axes.iter_mut().zip([cx, cy, bbox.width, bbox.height]) {
+            axis.correct(z);
+        }
+        self.bbox()
+    }
+
+    fn bbox(&self) -> BoundingBox {
+        let [cx, cy, w, h] = [0, 1, 2, 3].map(|i| self.axes[i].x);
+        let (w, h) = (w.max(0.0), h.max(0.0));
+        BoundingBox::new(cx - w / 2.0, cy - h / 2.0, w, h)
+    }
+}
+
+/// Greedy IoU matching: repeatedly take the best remaining pair above the
+/// threshold. Tracks and detections of different classes never match.
+fn associate(
+    tracks: &[(u64, i32, BoundingBox)],
+    detections: &[&ObjectMeta],
+    iou_threshold: f32,
+) -> Vec<(u64, usize)> {
+    let mut candidates = Vec::new();
+    for (t, (_, class_id, bbox)) in tracks.iter().enumerate() {
+        for (d, det) in detections.iter().enumerate() {
+            if det.class_id != *class_id {
+                continue;
+            }
+            let iou = bbox.iou(&det.rect_params);
+            if iou >= iou_threshold {
+                candidates.push((iou, t, d));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut used_tracks = vec![false; tracks.len()];
+    let mut used_dets = vec![false; detections.len()];
+    let mut matches = Vec::new();
+    for (_, t, d) in candidates {
+        if !used_tracks[t] && !used_dets[d] {
+            used_tracks[t] = true;
+            used_dets[d] = true;
+            matches.push((tracks[t].0, d));
+        }
+    }
+    matches
+}
+
+/// State shared by all algorithms
+struct TrackerCore {
+    config: TrackerAlgorithmConfig,
+    tracker: ObjectTracker,
+    classes: HashMap<u64, i32>,
+    filters: HashMap<u64, BoxFilter>,
+    use_motion: bool,
+
+    /// Tracks hit or created in the current frame
+    touched: Vec<u64>,
+}
+
+impl TrackerCore {
+    fn new(config: TrackerAlgorithmConfig, use_motion: bool) -> Self {
+        Self {
+            tracker: ObjectTracker::new(config.max_tracks, config.max_age, config.max_history),
+            config,
+            classes: HashMap::new(),
+            filters: HashMap::new(),
+            use_motion,
+            touched: Vec::new(),
+        }
+    }
+
+    /// Expected box of every live track for this frame
+    fn predict(&mut self) -> Vec<(u64, i32, BoundingBox)> {
+        let mut predicted = Vec::new();
+        for track_id in self.tracker.track_ids() {
+            let class_id = self.classes.get(&track_id).copied().unwrap_or(-1);
+            let bbox = match self.filters.get_mut(&track_id) {
+                Some(filter) => filter.predict(),
+                None => match self.tracker.get_trajectory(track_id) {
+                    Some(trajectory) => match trajectory.current_bbox() {
+                        Some(bbox) => bbox.clone(),
+                        None => continue,
+                    },
+                    None => continue,
+                },
+            };
+            predicted.push((track_id, class_id, bbox));
+        }
+        predicted.sort_by_key(|(id, _, _)| *id);
+        predicted
+    }
+
+    fn is_lost(&self, track_id: u64) -> bool {
+        self.tracker
+            .get_track_status(track_id)
+            .is_some_and(|status| status.state == TrackerState::Lost)
+    }
+
+    fn hit(&mut self, track_id: u64, detection: &ObjectMeta, timestamp: u64) -> ObjectMeta {
+        let mut object = detection.clone();
+        object.object_id = track_id;
+        object.tracker_confidence = detection.confidence;
+        let _ = self.tracker.update_track(track_id, &object, timestamp);
+        self.finish(track_id, object)
+    }
+
+    fn spawn(&mut self, detection: &ObjectMeta, timestamp: u64) -> ObjectMeta {
+        let track_id = self.tracker.create_track_at(detection, timestamp);
+        self.classes.insert(track_id, detection.class_id);
+        if self.use_motion {
+            self.filters
+                .insert(track_id, BoxFilter::new(&detection.rect_params));
+        }
+
+        let mut object = detection.clone();
+        object.object_id = track_id;
+        self.finish(track_id, object)
+    }
+
+    fn finish(&mut self, track_id: u64, mut object: ObjectMeta) -> ObjectMeta {
+        self.touched.push(track_id);
+        let estimate = match self.filters.get_mut(&track_id) {
+            Some(filter) => filter.correct(&object.rect_params),
+            None => object.rect_params.clone(),
+        };
+        let confidence = object.confidence;
+        object.detector_bbox_info = object.rect_params.clone();
+        object.set_tracker_bbox(estimate, confidence);
+        if let Some(status) = self.tracker.get_track_status(track_id) {
+            object.tracking_age = status.age;
+        }
+        object
+    }
+
+    fn confirmed(&self, track_id: u64, frame: u64) -> bool {
+        let min_hits = self.config.min_hits.max(1);
+        frame <= min_hits as u64
+            || self
+                .tracker
+                .get_track_status(track_id)
+                .is_some_and(|status| status.hits >= min_hits)
+    }
+
+    /// Mark tracks not touched this frame as missed and drop expired ones
+    fn end_frame(&mut self) {
+        let touched = std::mem::take(&mut self.touched);
+        for track_id in self.tracker.track_ids() {
+            if !touched.contains(&track_id) {
+                let _ = self.tracker.mark_missed(track_id);
+            }
+        }
+        self.tracker.cleanup_tracks();
+
+        let live = self.tracker.track_ids();
+        self.classes.retain(|id, _| live.contains(id));
+        self.filters.retain(|id, _| live.contains(id));
+    }
+
+    fn reset(&mut self) {
+        self.tracker = ObjectTracker::new(
+            self.config.max_tracks,
+            self.config.max_age,
+            self.config.max_history,
+        );
+        self.classes.clear();
+        self.filters.clear();
+        self.touched.clear();
+    }
+}
+
+macro_rules! delegate_core {
+    ($kind:expr) => {
+        fn kind(&self) -> TrackerAlgorithmKind {
+            $kind
+        }
+
+        fn track_status(&self, track_id: u64) -> Option<&TrackStatus> {
+            self.core.tracker.get_track_status(track_id)
+        }
+
+        fn trajectory(&self, track_id: u64) -> Option<&Trajectory> {
+            self.core.tracker.get_trajectory(track_id)
+        }
+
+        fn stats(&self) -> TrackingStats {
+            self.core.tracker.get_stats()
+        }
+
+        fn reset(&mut self) {
+            self.core.reset();
+            self.frame = 0;
+        }
+    };
+}
+
+/// Association on box overlap with the previous frame only
+pub struct IouTracker {
+    core: TrackerCore,
+    frame: u64,
+}
+
+impl IouTracker {
+    pub fn new(config: TrackerAlgorithmConfig) -> Self {
+        Self {
+            core: TrackerCore::new(config, false),
+            frame: 0,
+        }
+    }
+}
+
+impl TrackerAlgorithm for IouTracker {
+    delegate_core!(TrackerAlgorithmKind::Iou);
+
+    fn update(&mut self, detections: &[ObjectMeta], timestamp: u64) -> Vec<ObjectMeta> {
+        self.frame += 1;
+        let tracks = self.core.predict();
+        let dets: Vec<&ObjectMeta> = detections.iter().collect();
+        let matches = associate(&tracks, &dets, self.core.config.iou_threshold);
+
+        let mut output = Vec::with_capacity(dets.len());
+        let mut matched_dets = vec![false; dets.len()];
+        for &(track_id, d) in &matches {
+            matched_dets[d] = true;
+            output.push(self.core.hit(track_id, dets[d], timestamp));
+        }
+        for (d, det) in dets.iter().enumerate() {
+            if !matched_dets[d] {
+                output.push(self.core.spawn(det, timestamp));
+            }
+        }
+
+        self.core.end_frame();
+        output
+    }
+}
+
+/// Kalman-predicted IoU association (Bewley et al., 2016)
+pub struct SortTracker {
+    core: TrackerCore,
+    frame: u64,
+}
+
+impl SortTracker {
+    pub fn new(config: TrackerAlgorithmConfig) -> Self {
+        Self {
+            core: TrackerCore::new(config, true),
+            frame: 0,
+        }
+    }
+}
+
+impl TrackerAlgorithm for SortTracker {
+    delegate_core!(TrackerAlgorithmKind::Sort);
+
+    fn update(&mut self, detections: &[ObjectMeta], timestamp: u64) -> Vec<ObjectMeta> {
+        self.frame += 1;
+        let tracks = self.core.predict();
+        let dets: Vec<&ObjectMeta> = detections.iter().collect();
+        let matches = associate(&tracks, &dets, self.core.config.iou_threshold);
+
+        let mut output = Vec::with_capacity(dets.len());
+        let mut matched_dets = vec![false; dets.len()];
+        for &(track_id, d) in &matches {
+            matched_dets[d] = true;
+            let object = self.core.hit(track_id, dets[d], timestamp);
+            if self.core.confirmed(track_id, self.frame) {
+                output.push(object);
+            }
+        }
+        for (d, det) in dets.iter().enumerate() {
+            if !matched_dets[d] {
+                let object = self.core.spawn(det, timestamp);
+                if self.core.confirmed(object.object_id, self.frame) {
+                    output.push(object);
+                }
+            }
+        }
+
+        self.core.end_frame();
+        output
+    }
+}
+
+/// Two-stage association keeping tracks alive through low-confidence
+/// detections (Zhang et al., 2022)
+pub struct ByteTracker {
+    core: TrackerCore,
+    frame: u64,
+}
+
+impl ByteTracker {
+    pub fn new(config: TrackerAlgorithmConfig) -> Self {
+        Self {
+            core: TrackerCore::new(config, true),
+            frame: 0,
+        }
+    }
+}
+
+impl TrackerAlgorithm for ByteTracker {
+    delegate_core!(TrackerAlgorithmKind::ByteTrack);
+
+    fn update(&mut self, detections: &[ObjectMeta], timestamp: u64) -> Vec<ObjectMeta> {
+        self.frame += 1;
+        let config = self.core.config.clone();
+        let tracks = self.core.predict();
+
+        let (high, low): (Vec<&ObjectMeta>, Vec<&ObjectMeta>) = detections
+            .iter()
+            .filter(|det| det.confidence >= config.low_threshold)
+            .partition(|det| det.confidence >= config.high_threshold);
+
+        // First pass: confident detections against every track
+        let first = associate(&tracks, &high, config.iou_threshold);
+        let matched: Vec<u64> = first.iter().map(|(id, _)| *id).collect();
+
+        // Second pass: weak detections only against tracks that were being
+        // followed last frame, so noise does not revive lost tracks
+        let remaining: Vec<_> = tracks
+            .iter()
+            .filter(|(id, _, _)| !matched.contains(id) && !self.core.is_lost(*id))
+            .cloned()
+            .collect();
+        let second = associate(&remaining, &low, config.iou_threshold);
+
+        let mut output = Vec::with_capacity(detections.len());
+        let mut matched_high = vec![false; high.len()];
+        for &(track_id, d) in &first {
+            matched_high[d] = true;
+            let object = self.core.hit(track_id, high[d], timestamp);
+            if self.core.confirmed(track_id, self.frame) {
+                output.push(object);
+            }
+        }
+        for &(track_id, d) in &second {
+            let object = self.core.hit(track_id, low[d], timestamp);
+            if self.core.confirmed(track_id, self.frame) {
+                output.push(object);
+            }
+        }
+
+        // Only confident leftovers start tracks; weak leftovers are dropped
+        for (d, det) in high.iter().enumerate() {
+            if !matched_high[d] {
+                let object = self.core.spawn(det, timestamp);
+                if self.core.confirmed(object.object_id, self.frame) {
+                    output.push(object);
+                }
+            }
+        }
+
+        self.core.end_frame();
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(x: f32, confidence: f32) -> ObjectMeta {
+        let mut obj = ObjectMeta::new_untracked();
+        obj.class_id = 0;
+        obj.set_detection_bbox(BoundingBox::new(x, 100.0, 50.0, 50.0), confidence);
+        obj
+    }
+
+    fn run(tracker: &mut dyn TrackerAlgorithm, frames: &[Vec<ObjectMeta>]) -> Vec<Vec<u64>> {
+        frames
+            .iter()
+            .enumerate()
+            .map(|(i, dets)| {
+                let mut ids: Vec<u64> = tracker
+                    .update(dets, i as u64 * 33_000_000)
+                    .iter()
+                    .map(|obj| obj.object_id)
+                    .collect();
+                ids.sort_unstable();
+                ids
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_algorithm_selection() {
+        for kind in [
+            TrackerAlgorithmKind::Iou,
+            TrackerAlgorithmKind::Sort,
+            TrackerAlgorithmKind::ByteTrack,
+        ] {
+            let tracker = TrackerAlgorithmConfig::with_algorithm(kind).build();
+            assert_eq!(tracker.kind(), kind);
+            assert_eq!(
+                kind.to_string().parse::<TrackerAlgorithmKind>().unwrap(),
+                kind
+            );
+        }
+        assert!("deepsort".parse::<TrackerAlgorithmKind>().is_err());
+    }
+
+    #[test]
+    fn test_iou_tracker_keeps_ids() {
+        let mut tracker = IouTracker::new(TrackerAlgorithmConfig::default());
+        let frames: Vec<_> = (0..5)
+            .map(|i| {
+                vec![
+                    detection(100.0 + i as f32 * 5.0, 0.9),
+                    detection(400.0, 0.9),
+                ]
+            })
+            .collect();
+
+        let ids = run(&mut tracker, &frames);
+        assert!(ids.iter().all(|frame| frame.len() == 2));
+        assert!(ids.iter().all(|frame| frame == &ids[0]));
+
+        let trajectory = tracker.trajectory(ids[0][0]).unwrap();
+        assert_eq!(trajectory.history().len(), 5);
+        assert_eq!(tracker.stats().active_tracks, 2);
+    }
+
+    #[test]
+    fn test_sort_bridges_missed_detection() {
+        let config = TrackerAlgorithmConfig {
+            min_hits: 1,
+            ..TrackerAlgorithmConfig::default()
+        };
+        let mut sort = SortTracker::new(config.clone());
+        let mut iou = IouTracker::new(config);
+
+        // 20 px/frame with one dropped detection: after the gap the object
+        // has moved 40 px and barely overlaps its last box
+        let frames: Vec<_> = (0..10)
+            .map(|i| match i {
+                6 => vec![],
+                _ => vec![detection(i as f32 * 20.0, 0.9)],
+            })
+            .collect();
+
+        let sort_ids = run(&mut sort, &frames);
+        let iou_ids = run(&mut iou, &frames);
+
+        assert!(
+            sort_ids
+                .iter()
+                .filter(|frame| !frame.is_empty())
+                .all(|frame| frame == &vec![1])
+        );
+        assert_eq!(iou_ids.last().unwrap(), &vec![2]);
+    }
+
+    #[test]
+    fn test_sort_min_hits() {
+        let mut tracker = SortTracker::new(TrackerAlgorithmConfig::default());
+        let mut frames = vec![vec![detection(100.0, 0.9)]; 4];
+        // A new object appears after the warm-up period
+        for frame in frames.iter_mut().skip(3) {
+            frame.push(detection(400.0, 0.9));
+        }
+        frames.push(vec![detection(100.0, 0.9), detection(400.0, 0.9)]);
+        frames.push(vec![detection(100.0, 0.9), detection(400.0, 0.9)]);
+
+        let ids = run(&mut tracker, &frames);
+        assert_eq!(ids[3].len(), 1);
+        assert_eq!(ids[4].len(), 1);
+        assert_eq!(ids[5].len(), 2);
+    }
+
+    #[test]
+    fn test_bytetrack_low_confidence_association() {
+        let config = TrackerAlgorithmConfig::with_algorithm(TrackerAlgorithmKind::ByteTrack);
+        let mut tracker = ByteTracker::new(config);
+
+        let mut frames = vec![vec![detection(100.0, 0.9)]; 3];
+        // Occluded: confidence drops below the high threshold
+        frames.push(vec![detection(102.0, 0.3)]);
+        frames.push(vec![detection(104.0, 0.9)]);
+        // Weak detection far from any track must not start one
+        frames.push(vec![detection(104.0, 0.9), detection(600.0, 0.3)]);
+
+        let ids = run(&mut tracker, &frames);
+        assert_eq!(ids[3], ids[2]);
+        assert_eq!(ids[4], ids[2]);
+        assert_eq!(ids[5], ids[2]);
+        assert_eq!(tracker.stats().total_tracks, 1);
+    }
+}
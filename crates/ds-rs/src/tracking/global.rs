@@ -0,0 +1,459 @@
+//! Cross-stream association of per-source tracks
+//!
+//! Each source's tracker hands out its own track IDs. When cameras overlap,
+//! [`GlobalTrackRegistry`] merges tracks that refer to the same object into
+//! one global ID, using a ground-plane homography per source and/or
+//! appearance embeddings supplied by a re-identification model.
+
+use super::{Result, TrackingError};
+use crate::metadata::{BoundingBox, FrameMeta, ObjectMeta};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Image to ground-plane projection for one source
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Homography(pub [[f64; 3]; 3]);
+
+impl Homography {
+    pub fn identity() -> Self {
+        Self([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    /// Project an image point, `None` if it maps to infinity
+    pub fn project(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let h = &self.0;
+        let w = h[2][0] * x + h[2][1] * y + h[2][2];
+        if w.abs() < f64::EPSILON {
+            return None;
+        }
+        Some((
+            (h[0][0] * x + h[0][1] * y + h[0][2]) / w,
+            (h[1][0] * x + h[1][1] * y + h[1][2]) / w,
+        ))
+    }
+
+    /// Ground position of a box, taken at the bottom center where the
+    /// object touches the floor
+    pub fn project_bbox(&self, bbox: &BoundingBox) -> Option<(f64, f64)> {
+        self.project(
+            (bbox.left + bbox.width / 2.0) as f64,
+            (bbox.top + bbox.height) as f64,
+        )
+    }
+}
+
+impl Default for Homography {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Thresholds for merging tracks across sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobalTrackConfig {
+    /// Maximum ground-plane distance between observations of one object
+    pub max_distance: f64,
+
+    /// Minimum cosine similarity between embeddings of one object
+    pub min_similarity: f32,
+
+    /// Weight of the appearance cost relative to the distance cost
+    pub appearance_weight: f64,
+
+    /// Global tracks not observed for this long (ns) are dropped
+    pub ttl: u64,
+
+    /// Blend factor for the stored embedding on each observation
+    pub embedding_momentum: f32,
+}
+
+impl Default for GlobalTrackConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 1.0,
+            min_similarity: 0.7,
+            appearance_weight: 0.5,
+            ttl: 5_000_000_000,
+            embedding_momentum: 0.9,
+        }
+    }
+}
+
+/// One local track observed in one frame
+#[derive(Debug, Clone)]
+pub struct LocalObservation {
+    pub source_id: u32,
+    pub track_id: u64,
+    pub class_id: i32,
+    pub bbox: BoundingBox,
+    pub embedding: Option<Vec<f32>>,
+    pub timestamp: u64,
+}
+
+impl LocalObservation {
+    pub fn from_object(source_id: u32, object: &ObjectMeta, timestamp: u64) -> Self {
+        Self {
+            source_id,
+            track_id: object.object_id,
+            class_id: object.class_id,
+            bbox: object.rect_params.clone(),
+            embedding: None,
+            timestamp,
+        }
+    }
+
+    pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+}
+
+/// A merged track and the local tracks currently assigned to it
+#[derive(Debug, Clone)]
+pub struct GlobalTrack {
+    pub global_id: u64,
+    pub class_id: i32,
+
+    /// Last known ground-plane position
+    pub position: Option<(f64, f64)>,
+
+    /// Running appearance embedding, L2-normalized
+    pub embedding: Option<Vec<f32>>,
+
+    pub last_seen: u64,
+
+    /// Local track ID per source
+    pub members: HashMap<u32, u64>,
+}
+
+fn normalize(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> Option<f32> {
+    (a.len() == b.len() && !a.is_empty()).then(|| a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+/// Registry mapping `(source, local track)` pairs to global IDs
+pub struct GlobalTrackRegistry {
+    config: GlobalTrackConfig,
+    calibrations: HashMap<u32, Homography>,
+    tracks: HashMap<u64, GlobalTrack>,
+    assignments: HashMap<(u32, u64), u64>,
+    next_global_id: u64,
+}
+
+impl GlobalTrackRegistry {
+    pub fn new(config: GlobalTrackConfig) -> Self {
+        Self {
+            config,
+            calibrations: HashMap::new(),
+            tracks: HashMap::new(),
+            assignments: HashMap::new(),
+            next_global_id: 1,
+        }
+    }
+
+    /// Register the ground-plane projection for a source. Sources without a
+    /// calibration can only be associated by appearance.
+    pub fn set_calibration(&mut self, source_id: u32, homography: Homography) {
+        self.calibrations.insert(source_id, homography);
+    }
+
+    pub fn global_id(&self, source_id: u32, track_id: u64) -> Option<u64> {
+        self.assignments.get(&(source_id, track_id)).copied()
+    }
+
+    pub fn get(&self, global_id: u64) -> Option<&GlobalTrack> {
+        self.tracks.get(&global_id)
+    }
+
+    pub fn tracks(&self) -> impl Iterator<Item = &GlobalTrack> {
+        self.tracks.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Assign a global ID to one observation
+    pub fn observe(&mut self, mut observation: LocalObservation) -> Result<u64> {
+        if let Some(embedding) = &observation.embedding
+            && embedding.is_empty()
+        {
+            return Err(TrackingError::TrackingFailed(format!(
+                "Empty embedding for track {} on source {}",
+                observation.track_id, observation.source_id
+            )));
+        }
+
+        let key = (observation.source_id, observation.track_id);
+        let position = self
+            .calibrations
+            .get(&observation.source_id)
+            .and_then(|h| h.project_bbox(&observation.bbox));
+        let embedding = observation.embedding.take().map(normalize);
+
+        let global_id = match self.assignments.get(&key) {
+            Some(&id) if self.tracks.contains_key(&id) => id,
+            _ => {
+                let id = self
+                    .best_match(&observation, position, embedding.as_deref())
+                    .unwrap_or_else(|| self.create(observation.class_id));
+                self.assignments.insert(key, id);
+                id
+            }
+        };
+
+        let momentum = self.config.embedding_momentum.clamp(0.0, 1.0);
+        let track = self
+            .tracks
+            .get_mut(&global_id)
+            .expect("assigned global track exists");
+        track
+            .members
+            .insert(observation.source_id, observation.track_id);
+        track.last_seen = track.last_seen.max(observation.timestamp);
+        if position.is_some() {
+            track.position = position;
+        }
+        if let Some(new) = embedding {
+            track.embedding = Some(match track.embedding.take() {
+                Some(old) if old.len() == new.len() => normalize(
+                    old.iter()
+                        .zip(&new)
+                        .map(|(o, n)| momentum * o + (1.0 - momentum) * n)
+                        .collect(),
+                ),
+                _ => new,
+            });
+        }
+
+        Ok(global_id)
+    }
+
+    /// Assign global IDs to every tracked object in a frame. Returns
+    /// `(local track ID, global ID)` pairs.
+    pub fn observe_frame(&mut self, frame: &FrameMeta, timestamp: u64) -> Result<Vec<(u64, u64)>> {
+        frame
+            .objects()
+            .iter()
+            .filter(|object| object.is_tracked())
+            .map(|object| {
+                let observation = LocalObservation::from_object(frame.source_id, object, timestamp);
+                self.observe(observation)
+                    .map(|global_id| (object.object_id, global_id))
+            })
+            .collect()
+    }
+
+    /// Forget a local track, e.g. when its tracker removes it
+    pub fn release(&mut self, source_id: u32, track_id: u64) {
+        if let Some(global_id) = self.assignments.remove(&(source_id, track_id))
+            && let Some(track) = self.tracks.get_mut(&global_id)
+            && track.members.get(&source_id) == Some(&track_id)
+        {
+            track.members.remove(&source_id);
+        }
+    }
+
+    /// Drop global tracks not observed since `now - ttl`
+    pub fn expire(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(self.config.ttl);
+        self.tracks.retain(|_, track| track.last_seen >= cutoff);
+        let tracks = &self.tracks;
+        self.assignments.retain(|_, id| tracks.contains_key(id));
+    }
+
+    fn create(&mut self, class_id: i32) -> u64 {
+        let global_id = self.next_global_id;
+        self.next_global_id += 1;
+        self.tracks.insert(
+            global_id,
+            GlobalTrack {
+                global_id,
+                class_id,
+                position: None,
+                embedding: None,
+                last_seen: 0,
+                members: HashMap::new(),
+            },
+        );
+        global_id
+    }
+
+    /// Lowest-cost global track this observation may join. A candidate must
+    /// share the class, not already hold a track from the same source, and
+    /// pass every cue available on both sides.
+    fn best_match(
+        &self,
+        observation: &LocalObservation,
+        position: Option<(f64, f64)>,
+        embedding: Option<&[f32]>,
+    ) -> Option<u64> {
+        let cutoff = observation.timestamp.saturating_sub(self.config.ttl);
+        let mut best: Option<(f64, u64)> = None;
+
+        for track in self.tracks.values() {
+            if track.class_id != observation.class_id
+                || track.last_seen < cutoff
+                || track.members.contains_key(&observation.source_id)
+            {
+                continue;
+            }
+
+            let mut cost = 0.0;
+            let mut cues = 0;
+
+            if let (Some((x, y)), Some((tx, ty))) = (position, track.position) {
+                let distance = ((x - tx).powi(2) + (y - ty).powi(2)).sqrt();
+                if distance > self.config.max_distance {
+                    continue;
+                }
+                cost += distance / self.config.max_distance.max(f64::EPSILON);
+                cues += 1;
+            }
+
+            if let (Some(e), Some(te)) = (embedding, track.embedding.as_deref())
+                && let Some(similarity) = cosine(e, te)
+            {
+                if similarity < self.config.min_similarity {
+                    continue;
+                }
+                cost += self.config.appearance_weight * (1.0 - similarity as f64);
+                cues += 1;
+            }
+
+            if cues == 0 {
+                continue;
+            }
+            if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                best = Some((cost, track.global_id));
+            }
+        }
+
+        best.map(|(_, id)| id)
+    }
+}
+
+impl Default for GlobalTrackRegistry {
+    fn default() -> Self {
+        Self::new(GlobalTrackConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(source_id: u32, track_id: u64, left: f32) -> LocalObservation {
+        let mut object = ObjectMeta::new(track_id);
+        object.class_id = 0;
+        object.rect_params = BoundingBox::new(left, 0.0, 10.0, 20.0);
+        LocalObservation::from_object(source_id, &object, 1_000)
+    }
+
+    /// Second camera whose image is shifted 100 px to the right
+    fn shifted() -> Homography {
+        Homography([[1.0, 0.0, -100.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    #[test]
+    fn test_homography_projection() {
+        let (x, y) = shifted()
+            .project_bbox(&BoundingBox::new(100.0, 0.0, 10.0, 20.0))
+            .unwrap();
+        assert_eq!((x, y), (5.0, 20.0));
+
+        let degenerate = Homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]]);
+        assert!(degenerate.project(1.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_spatial_association() {
+        let mut registry = GlobalTrackRegistry::new(GlobalTrackConfig {
+            max_distance: 5.0,
+            ..GlobalTrackConfig::default()
+        });
+        registry.set_calibration(0, Homography::identity());
+        registry.set_calibration(1, shifted());
+
+        let a = registry.observe(observation(0, 7, 50.0)).unwrap();
+        let b = registry.observe(observation(1, 3, 151.0)).unwrap();
+        let c = registry.observe(observation(1, 4, 400.0)).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(registry.global_id(1, 3), Some(a));
+        assert_eq!(registry.get(a).unwrap().members.len(), 2);
+    }
+
+    #[test]
+    fn test_same_source_never_merges() {
+        let mut registry = GlobalTrackRegistry::default();
+        registry.set_calibration(0, Homography::identity());
+
+        let a = registry.observe(observation(0, 1, 50.0)).unwrap();
+        let b = registry.observe(observation(0, 2, 50.0)).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_appearance_association() {
+        let mut registry = GlobalTrackRegistry::default();
+
+        let a = registry
+            .observe(observation(0, 1, 0.0).with_embedding(vec![1.0, 0.0, 0.0]))
+            .unwrap();
+        let b = registry
+            .observe(observation(1, 1, 900.0).with_embedding(vec![0.9, 0.1, 0.0]))
+            .unwrap();
+        let c = registry
+            .observe(observation(2, 1, 0.0).with_embedding(vec![0.0, 1.0, 0.0]))
+            .unwrap();
+        // No calibration and no embedding: nothing to compare
+        let d = registry.observe(observation(3, 1, 0.0)).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+        assert!(
+            registry
+                .observe(observation(4, 1, 0.0).with_embedding(vec![]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_release_keeps_global_track() {
+        let mut registry = GlobalTrackRegistry::default();
+        let a = registry.observe(observation(0, 1, 0.0)).unwrap();
+
+        registry.release(0, 1);
+        assert_eq!(registry.global_id(0, 1), None);
+        assert!(registry.get(a).unwrap().members.is_empty());
+    }
+
+    #[test]
+    fn test_expire_drops_stale_tracks() {
+        let mut registry = GlobalTrackRegistry::default();
+        let ttl = GlobalTrackConfig::default().ttl;
+        registry.observe(observation(0, 1, 0.0)).unwrap();
+
+        registry.expire(1_000 + ttl);
+        assert!(!registry.is_empty());
+
+        registry.expire(1_000 + ttl + 1);
+        assert!(registry.is_empty());
+        assert_eq!(registry.global_id(0, 1), None);
+    }
+}
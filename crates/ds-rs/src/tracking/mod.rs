@@ -1,9 +1,24 @@
 //! Object tracking and trajectory management
 
+pub mod algorithm;
+pub mod global;
+pub mod smoothing;
+
 use crate::metadata::{BoundingBox, ObjectMeta};
 use std::collections::{HashMap, VecDeque};
 use thiserror::Error;
 
+pub use algorithm::{
+    ByteTracker, IouTracker, SortTracker, TrackerAlgorithm, TrackerAlgorithmConfig,
+    TrackerAlgorithmKind,
+};
+pub use global::{
+    GlobalTrack, GlobalTrackConfig, GlobalTrackRegistry, Homography, LocalObservation,
+};
+pub use smoothing::{
+    DetectionSmoother, SmoothedFrame, SmoothingConfig, SmoothingEvent, SmoothingParams,
+};
+
 /// Errors that can occur during tracking operations
 #[derive(Debug, Error)]
 pub enum TrackingError {
@@ -233,6 +248,11 @@ impl ObjectTracker {
 
     /// Create new track
     pub fn create_track(&mut self, object: &ObjectMeta) -> u64 {
+        self.create_track_at(object, 0)
+    }
+
+    /// Create new track whose trajectory starts at `timestamp`
+    pub fn create_track_at(&mut self, object: &ObjectMeta, timestamp: u64) -> u64 {
         let track_id = self.next_track_id;
         self.next_track_id += 1;
 
@@ -240,7 +260,7 @@ impl ObjectTracker {
         status.update_hit(object.confidence);
 
         let mut trajectory = Trajectory::new(track_id, self.max_history);
-        trajectory.add_position(&object.rect_params, 0);
+        trajectory.add_position(&object.rect_params, timestamp);
 
         self.tracks.insert(track_id, status);
         self.trajectories.insert(track_id, trajectory);
@@ -335,6 +355,11 @@ impl ObjectTracker {
         self.trajectories.get(&track_id)
     }
 
+    /// IDs of all tracks, in any state
+    pub fn track_ids(&self) -> Vec<u64> {
+        self.tracks.keys().copied().collect()
+    }
+
     /// Get all active tracks
     pub fn active_tracks(&self) -> Vec<u64> {
         self.tracks
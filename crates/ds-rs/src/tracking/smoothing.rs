@@ -0,0 +1,304 @@
+//! Temporal smoothing of tracked detections
+//!
+//! Detector output jitters from frame to frame and objects drop out for a
+//! frame or two. [`DetectionSmoother`] applies an exponential moving average
+//! to each track's box and only reports a track once it has been seen in N of
+//! the last M frames, and only drops it after it has been missing in N of the
+//! last M frames, so overlays and events stay stable.
+
+use crate::metadata::{BoundingBox, ObjectMeta};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Smoothing parameters for one class
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SmoothingParams {
+    /// Weight of the newest box in the EMA (1.0 disables smoothing)
+    pub box_alpha: f32,
+
+    /// Frames a track must be present in, within `window`, to appear
+    pub appear_hits: usize,
+
+    /// Frames a track must be missing in, within `window`, to disappear
+    pub disappear_misses: usize,
+
+    /// Number of recent frames considered
+    pub window: usize,
+}
+
+impl Default for SmoothingParams {
+    fn default() -> Self {
+        Self {
+            box_alpha: 0.5,
+            appear_hits: 3,
+            disappear_misses: 4,
+            window: 5,
+        }
+    }
+}
+
+/// Smoothing configuration with per-class overrides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmoothingConfig {
+    #[serde(default)]
+    pub default: SmoothingParams,
+
+    /// Overrides keyed by class ID
+    #[serde(default)]
+    pub classes: HashMap<i32, SmoothingParams>,
+}
+
+impl SmoothingConfig {
+    pub fn params_for(&self, class_id: i32) -> SmoothingParams {
+        self.classes.get(&class_id).copied().unwrap_or(self.default)
+    }
+}
+
+/// Appearance changes reported after hysteresis
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmoothingEvent {
+    Appeared { track_id: u64, class_id: i32 },
+    Disappeared { track_id: u64, class_id: i32 },
+}
+
+/// Output of one [`DetectionSmoother::update`] call
+#[derive(Debug, Clone, Default)]
+pub struct SmoothedFrame {
+    /// Confirmed objects with smoothed boxes, including ones briefly missing
+    pub objects: Vec<ObjectMeta>,
+    pub events: Vec<SmoothingEvent>,
+}
+
+struct SmoothedTrack {
+    object: ObjectMeta,
+    bbox: BoundingBox,
+    presence: VecDeque<bool>,
+    confirmed: bool,
+}
+
+impl SmoothedTrack {
+    fn record(&mut self, present: bool, window: usize) {
+        self.presence.push_back(present);
+        while self.presence.len() > window.max(1) {
+            self.presence.pop_front();
+        }
+    }
+
+    fn count(&self, present: bool) -> usize {
+        self.presence.iter().filter(|&&p| p == present).count()
+    }
+}
+
+fn blend(previous: &BoundingBox, current: &BoundingBox, alpha: f32) -> BoundingBox {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mix = |old: f32, new: f32| old + alpha * (new - old);
+    BoundingBox::new(
+        mix(previous.left, current.left),
+        mix(previous.top, current.top),
+        mix(previous.width, current.width),
+        mix(previous.height, current.height),
+    )
+}
+
+/// Per-stream smoothing stage keyed by track ID
+pub struct DetectionSmoother {
+    config: SmoothingConfig,
+    tracks: HashMap<u64, SmoothedTrack>,
+}
+
+impl DetectionSmoother {
+    pub fn new(config: SmoothingConfig) -> Self {
+        Self {
+            config,
+            tracks: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SmoothingConfig {
+        &self.config
+    }
+
+    /// Number of tracks currently held, confirmed or not
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Feed one frame of detections. Untracked objects pass through
+    /// unchanged since they can't be matched across frames.
+    pub fn update(&mut self, objects: &[ObjectMeta]) -> SmoothedFrame {
+        let mut frame = SmoothedFrame::default();
+        let mut seen = Vec::with_capacity(objects.len());
+
+        for object in objects {
+            if !object.is_tracked() {
+                frame.objects.push(object.clone());
+                continue;
+            }
+            seen.push(object.object_id);
+
+            let params = self.config.params_for(object.class_id);
+            let track = self
+                .tracks
+                .entry(object.object_id)
+                .or_insert_with(|| SmoothedTrack {
+                    object: object.clone(),
+                    bbox: object.rect_params.clone(),
+                    presence: VecDeque::with_capacity(params.window),
+                    confirmed: false,
+                });
+
+            track.bbox = blend(&track.bbox, &object.rect_params, params.box_alpha);
+            track.object = object.clone();
+            track.record(true, params.window);
+        }
+
+        let mut removed = Vec::new();
+        for (&track_id, track) in self.tracks.iter_mut() {
+            let params = self.config.params_for(track.object.class_id);
+            if !seen.contains(&track_id) {
+                track.record(false, params.window);
+            }
+
+            let class_id = track.object.class_id;
+            if !track.confirmed && track.count(true) >= params.appear_hits {
+                track.confirmed = true;
+                frame
+                    .events
+                    .push(SmoothingEvent::Appeared { track_id, class_id });
+            }
+
+            // A threshold larger than the window could never be reached
+            let misses = params.disappear_misses.clamp(1, params.window.max(1));
+            if track.count(false) >= misses {
+                if track.confirmed {
+                    frame
+                        .events
+                        .push(SmoothingEvent::Disappeared { track_id, class_id });
+                }
+                removed.push(track_id);
+                continue;
+            }
+
+            if track.confirmed {
+                let mut object = track.object.clone();
+                object.rect_params = track.bbox.clone();
+                frame.objects.push(object);
+            }
+        }
+
+        for track_id in removed {
+            self.tracks.remove(&track_id);
+        }
+
+        frame.objects.sort_by_key(|object| object.object_id);
+        frame
+    }
+
+    /// Forget all tracks, e.g. after a source restarts
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+}
+
+impl Default for DetectionSmoother {
+    fn default() -> Self {
+        Self::new(SmoothingConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(track_id: u64, class_id: i32, left: f32) -> ObjectMeta {
+        let mut obj = ObjectMeta::new(track_id);
+        obj.class_id = class_id;
+        obj.rect_params = BoundingBox::new(left, 0.0, 10.0, 10.0);
+        obj
+    }
+
+    #[test]
+    fn test_appearance_hysteresis() {
+        let mut smoother = DetectionSmoother::default();
+
+        assert!(smoother.update(&[object(1, 0, 0.0)]).objects.is_empty());
+        assert!(smoother.update(&[object(1, 0, 0.0)]).objects.is_empty());
+
+        let frame = smoother.update(&[object(1, 0, 0.0)]);
+        assert_eq!(frame.objects.len(), 1);
+        assert_eq!(
+            frame.events,
+            vec![SmoothingEvent::Appeared {
+                track_id: 1,
+                class_id: 0
+            }]
+        );
+
+        // A single missed frame keeps the object on screen
+        let frame = smoother.update(&[]);
+        assert_eq!(frame.objects.len(), 1);
+        assert!(frame.events.is_empty());
+    }
+
+    #[test]
+    fn test_disappearance_after_misses() {
+        let mut smoother = DetectionSmoother::default();
+        for _ in 0..5 {
+            smoother.update(&[object(1, 0, 0.0)]);
+        }
+
+        let mut events = Vec::new();
+        for _ in 0..4 {
+            events.extend(smoother.update(&[]).events);
+        }
+
+        assert_eq!(
+            events,
+            vec![SmoothingEvent::Disappeared {
+                track_id: 1,
+                class_id: 0
+            }]
+        );
+        assert_eq!(smoother.track_count(), 0);
+    }
+
+    #[test]
+    fn test_box_ema() {
+        let mut smoother = DetectionSmoother::default();
+        for _ in 0..3 {
+            smoother.update(&[object(1, 0, 0.0)]);
+        }
+
+        let frame = smoother.update(&[object(1, 0, 100.0)]);
+        assert_eq!(frame.objects[0].rect_params.left, 50.0);
+    }
+
+    #[test]
+    fn test_class_override() {
+        let mut config = SmoothingConfig::default();
+        config.classes.insert(
+            2,
+            SmoothingParams {
+                box_alpha: 1.0,
+                appear_hits: 1,
+                disappear_misses: 1,
+                window: 1,
+            },
+        );
+        let mut smoother = DetectionSmoother::new(config);
+
+        let frame = smoother.update(&[object(2, 2, 0.0)]);
+        assert_eq!(frame.objects.len(), 1);
+
+        let frame = smoother.update(&[object(2, 2, 100.0)]);
+        assert_eq!(frame.objects[0].rect_params.left, 100.0);
+    }
+
+    #[test]
+    fn test_untracked_pass_through() {
+        let mut smoother = DetectionSmoother::default();
+        let frame = smoother.update(&[ObjectMeta::new_untracked()]);
+        assert_eq!(frame.objects.len(), 1);
+        assert_eq!(smoother.track_count(), 0);
+    }
+}
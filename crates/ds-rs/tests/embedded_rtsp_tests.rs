@@ -0,0 +1,78 @@
+use ds::{Pipeline, SourceController};
+use ds_rs as ds;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use source_videos::network::NetworkProfile;
+use source_videos::{EmbeddedServer, TestRtspStream};
+use std::sync::Arc;
+
+fn create_test_pipeline() -> (Arc<Pipeline>, gst::Element) {
+    ds::init().expect("Failed to initialize");
+
+    let pipeline = Pipeline::builder("embedded-rtsp-test")
+        .backend(ds::BackendType::Standard)
+        .build()
+        .expect("Failed to create pipeline");
+
+    let streammux = gst::ElementFactory::make("compositor")
+        .name("test-compositor")
+        .property_from_str("background", "black")
+        .build()
+        .expect("Failed to create compositor element");
+
+    let sink = gst::ElementFactory::make("fakesink")
+        .name("test-sink")
+        .property("sync", false)
+        .property("async", false)
+        .build()
+        .expect("Failed to create fakesink");
+
+    pipeline
+        .add_many(&[&streammux, &sink])
+        .expect("Failed to add elements");
+    streammux
+        .link(&sink)
+        .expect("Failed to link compositor to sink");
+
+    (Arc::new(pipeline), streammux)
+}
+
+#[tokio::test]
+async fn test_rtsp_source_from_embedded_server() {
+    let mut server = EmbeddedServer::builder()
+        .port(18754)
+        .add_test_pattern("cam1", "ball")
+        .build()
+        .expect("Failed to build embedded server");
+    server
+        .start()
+        .await
+        .expect("Failed to start embedded server");
+
+    let url = server.url("cam1").await.expect("cam1 should be mounted");
+    assert_eq!(url, "rtsp://127.0.0.1:18754/cam1");
+
+    let (pipeline, streammux) = create_test_pipeline();
+    let controller = SourceController::new(pipeline, streammux);
+
+    let source_id = controller.add_source(&url).expect("Failed to add source");
+    assert_eq!(controller.num_active_sources().unwrap(), 1);
+
+    controller
+        .remove_source(source_id)
+        .expect("Failed to remove source");
+    server.stop().await.expect("Failed to stop embedded server");
+}
+
+source_videos::rtsp_test!(fn test_rtsp_source_over_poor_network(url) with
+    TestRtspStream::ball().with_profile(NetworkProfile::Poor) => {
+    let (pipeline, streammux) = create_test_pipeline();
+    let controller = SourceController::new(pipeline, streammux);
+
+    let source_id = controller.add_source(url).expect("Failed to add source");
+    assert_eq!(controller.num_active_sources().unwrap(), 1);
+
+    controller
+        .remove_source(source_id)
+        .expect("Failed to remove source");
+});
@@ -1,8 +1,10 @@
 //! Tests for multi-stream detection pipeline functionality
 
+use ds_rs::multistream::RecyclePolicy;
 use ds_rs::{
-    MetricsCollector, MultiStreamConfig, MultiStreamConfigBuilder, MultiStreamManager, Pipeline,
-    PipelinePool, ResourceLimits, ResourceManager, StreamCoordinator, StreamPriority, init,
+    MetricsCollector, MultiStreamConfig, MultiStreamConfigBuilder, MultiStreamEvent,
+    MultiStreamManager, Pipeline, PipelinePool, ResourceLimits, ResourceManager, StreamCoordinator,
+    StreamPriority, init,
 };
 use std::sync::Arc;
 use std::thread;
@@ -123,6 +125,79 @@ fn test_remove_stream() {
     assert_eq!(manager.get_all_stream_states().len(), 0);
 }
 
+#[test]
+fn test_migrate_stream() {
+    setup().unwrap();
+
+    let pipeline = Arc::new(Pipeline::new("test-pipeline").unwrap());
+    let streammux = gstreamer::ElementFactory::make("identity")
+        .name("test-mux")
+        .build()
+        .unwrap();
+
+    pipeline.add_element(&streammux).unwrap();
+
+    let config = create_test_config();
+    let manager = MultiStreamManager::new(pipeline, streammux, config).unwrap();
+
+    let source_id = manager.add_stream("file:///test.mp4").unwrap();
+    let from = manager.get_all_stream_states()[0].pipeline_id;
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let report = runtime.block_on(manager.migrate_stream(source_id)).unwrap();
+    assert_eq!(report.from, from);
+    assert_ne!(report.to, from);
+    assert!(report.overlapped_frames > 0);
+    assert!(manager.migration(source_id).is_none());
+    assert_eq!(manager.get_all_stream_states()[0].pipeline_id, report.to);
+
+    // Frames both pipelines processed were delivered once
+    let stats = manager.dedup_stats();
+    assert_eq!(stats.duplicates, report.overlapped_frames as u64);
+}
+
+#[test]
+fn test_recycle_pipelines() {
+    setup().unwrap();
+
+    let pipeline = Arc::new(Pipeline::new("test-pipeline").unwrap());
+    let streammux = gstreamer::ElementFactory::make("identity")
+        .name("test-mux")
+        .build()
+        .unwrap();
+
+    pipeline.add_element(&streammux).unwrap();
+
+    let config = MultiStreamConfigBuilder::new()
+        .max_streams(4)
+        .recycle_policy(RecyclePolicy {
+            max_frames: Some(3),
+            ..Default::default()
+        })
+        .worker_threads(2)
+        .build();
+    let manager = MultiStreamManager::new(pipeline, streammux, config).unwrap();
+
+    let source_id = manager.add_stream("file:///test.mp4").unwrap();
+    let from = manager.get_all_stream_states()[0].pipeline_id;
+    thread::sleep(Duration::from_millis(200));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let recycled = runtime.block_on(manager.recycle_pipelines()).unwrap();
+    assert!(recycled.contains(&from));
+    assert_ne!(manager.get_all_stream_states()[0].pipeline_id, from);
+
+    let events = manager.events();
+    let recycled_events = std::iter::from_fn(|| events.try_recv())
+        .filter(|event| {
+            matches!(event, MultiStreamEvent::PipelineRecycled { pipeline_id, .. } if *pipeline_id == from)
+        })
+        .count();
+    assert_eq!(recycled_events, 1);
+
+    manager.remove_stream(source_id).unwrap();
+}
+
 #[test]
 fn test_resource_limits() {
     setup().unwrap();
@@ -231,6 +306,37 @@ fn test_stream_coordinator() {
     assert!(found_high || found_low);
 }
 
+#[test]
+fn test_stream_coordinator_event_order() {
+    setup().unwrap();
+
+    let coordinator = StreamCoordinator::new();
+    let source = ds_rs::SourceId(1);
+
+    // Two frames in flight; the later one finishes first
+    let first = coordinator.begin_event(source);
+    let second = coordinator.begin_event(source);
+    coordinator.publish(MultiStreamEvent::StreamRemoved { source_id: source });
+    second.complete(MultiStreamEvent::DetectionProcessed {
+        source_id: source,
+        count: 2,
+    });
+    assert!(coordinator.events().try_recv().is_none());
+
+    first.complete(MultiStreamEvent::DetectionProcessed {
+        source_id: source,
+        count: 1,
+    });
+    let events = coordinator.events();
+    let order: Vec<_> = std::iter::from_fn(|| events.try_recv())
+        .map(|event| match event {
+            MultiStreamEvent::DetectionProcessed { count, .. } => count,
+            _ => 0,
+        })
+        .collect();
+    assert_eq!(order, [1, 2, 0]);
+}
+
 #[test]
 fn test_resource_manager() {
     setup().unwrap();
@@ -81,6 +81,7 @@ async fn main() -> Result<()> {
             duration: None,
             num_buffers: None,
             is_live: false,
+            tags: Vec::new(),
         };
 
         server_builder = server_builder.add_source(config);
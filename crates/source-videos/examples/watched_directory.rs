@@ -224,5 +224,6 @@ fn create_test_config(path: &std::path::Path) -> source_videos::VideoSourceConfi
         duration: None,
         num_buffers: None,
         is_live: false,
+        tags: Vec::new(),
     }
 }
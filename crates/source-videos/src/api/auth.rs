@@ -74,7 +74,7 @@ pub async fn auth_middleware(
     let path = request.uri().path();
 
     // Always allow health check endpoints
-    if path.starts_with("/api/v1/health") || path == "/api/v1/metrics" {
+    if path.starts_with("/api/v1/health") || path == "/api/v1/metrics" || path == "/metrics" {
         return Ok(next.run(request).await);
     }
 
@@ -18,6 +18,7 @@ pub enum ApiError {
     InternalError(String),
     ServiceUnavailable(String),
     ValidationError(String),
+    TooManyRequests(String),
 }
 
 impl ApiError {
@@ -52,6 +53,10 @@ impl ApiError {
     pub fn validation(msg: impl Into<String>) -> Self {
         Self::ValidationError(msg.into())
     }
+
+    pub fn too_many_requests(msg: impl Into<String>) -> Self {
+        Self::TooManyRequests(msg.into())
+    }
 }
 
 impl fmt::Display for ApiError {
@@ -65,6 +70,7 @@ impl fmt::Display for ApiError {
             Self::InternalError(msg) => write!(f, "Internal Error: {}", msg),
             Self::ServiceUnavailable(msg) => write!(f, "Service Unavailable: {}", msg),
             Self::ValidationError(msg) => write!(f, "Validation Error: {}", msg),
+            Self::TooManyRequests(msg) => write!(f, "Too Many Requests: {}", msg),
         }
     }
 }
@@ -86,6 +92,7 @@ impl IntoResponse for ApiError {
             Self::ValidationError(msg) => {
                 (StatusCode::UNPROCESSABLE_ENTITY, "validation_error", msg)
             }
+            Self::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, "too_many_requests", msg),
         };
 
         let body = Json(json!({
@@ -107,6 +114,7 @@ impl From<crate::SourceVideoError> for ApiError {
         match err {
             SourceVideoError::Configuration(_) => Self::BadRequest(err.to_string()),
             SourceVideoError::SourceNotFound(_) => Self::NotFound(err.to_string()),
+            SourceVideoError::InvalidPattern(_) => Self::BadRequest(err.to_string()),
             SourceVideoError::Server(_) => Self::ServiceUnavailable(err.to_string()),
             SourceVideoError::Resource(_) => Self::Conflict(err.to_string()),
             SourceVideoError::Pipeline(_) => Self::ServiceUnavailable(err.to_string()),
@@ -0,0 +1,339 @@
+//! Prometheus metrics
+//!
+//! `GET /metrics` serves the server's state in the Prometheus text format,
+//! so test infrastructure can be scraped and alerted on like production
+//! services. Request latencies are recorded by [`track_requests`] on the
+//! API routes; everything else is read from the components at scrape time.
+
+use super::ApiState;
+use crate::SourceState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use crate::exposition::{CONTENT_TYPE, Exposition};
+
+/// Upper bounds of the request latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    /// Cumulative counts per bucket of [`LATENCY_BUCKETS`]
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Latencies of API requests by method, route and status
+#[derive(Debug, Default)]
+pub struct RequestMetrics {
+    requests: Mutex<BTreeMap<(String, String, u16), Histogram>>,
+}
+
+impl RequestMetrics {
+    pub fn observe(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests
+                .entry((method.to_string(), route.to_string(), status))
+                .or_default()
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    fn write(&self, out: &mut Exposition) {
+        const NAME: &str = "source_videos_api_request_duration_seconds";
+        out.family(NAME, "histogram", "Control API request latencies");
+        let requests = match self.requests.lock() {
+            Ok(requests) => requests.clone(),
+            Err(_) => return,
+        };
+        for ((method, route, status), histogram) in requests {
+            let status = status.to_string();
+            let labels = [
+                ("method", method.as_str()),
+                ("route", route.as_str()),
+                ("status", status.as_str()),
+            ];
+            let bucket = format!("{}_bucket", NAME);
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let le = bound.to_string();
+                out.sample_with(&bucket, &labels, ("le", &le), *count as f64);
+            }
+            out.sample_with(&bucket, &labels, ("le", "+Inf"), histogram.count as f64);
+            out.sample(&format!("{}_sum", NAME), &labels, histogram.sum);
+            out.sample(&format!("{}_count", NAME), &labels, histogram.count as f64);
+        }
+    }
+}
+
+/// Record how long each API request took, by the route it matched
+pub async fn track_requests(
+    State(state): State<Arc<ApiState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.requests.observe(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+fn state_label(state: &SourceState) -> &'static str {
+    match state {
+        SourceState::Created => "created",
+        SourceState::Playing => "playing",
+        SourceState::Paused => "paused",
+        SourceState::Stopped => "stopped",
+        SourceState::Error(_) => "error",
+    }
+}
+
+/// Everything `/metrics` serves
+pub async fn render(state: &ApiState) -> String {
+    let mut out = Exposition::new();
+
+    out.family("source_videos_sources", "gauge", "Sources by state");
+    let mut by_state: BTreeMap<&str, u64> = BTreeMap::new();
+    for source in state.source_manager.list_sources() {
+        *by_state.entry(state_label(&source.state)).or_default() += 1;
+    }
+    for (source_state, count) in by_state {
+        out.sample(
+            "source_videos_sources",
+            &[("state", source_state)],
+            count as f64,
+        );
+    }
+
+    out.family(
+        "source_videos_pipeline_restarts_total",
+        "counter",
+        "Pipeline rebuilds per source",
+    );
+    for (source, count) in sorted(state.source_manager.restart_counts()) {
+        out.sample(
+            "source_videos_pipeline_restarts_total",
+            &[("source", source.as_str())],
+            count as f64,
+        );
+    }
+
+    if let Some(watch_events) = &state.watch_events {
+        out.family(
+            "source_videos_watcher_events_total",
+            "counter",
+            "File system events received from watchers",
+        );
+        for (event, count) in sorted(watch_events.snapshot()) {
+            out.sample(
+                "source_videos_watcher_events_total",
+                &[("event", event)],
+                count as f64,
+            );
+        }
+    }
+
+    if let Some(server) = &state.rtsp_server {
+        write_rtsp(&mut out, &*server.read().await);
+    }
+
+    write_network(&mut out, state).await;
+    state.requests.write(&mut out);
+    out.finish()
+}
+
+fn write_rtsp(out: &mut Exposition, server: &crate::RtspServer) {
+    let report = server.transport_log().report();
+    let mut clients: BTreeMap<String, u64> = server
+        .list_sources()
+        .into_iter()
+        .map(|mount| (mount, 0))
+        .collect();
+    for session in &report.sessions {
+        if let Some(mount) = &session.mount {
+            *clients.entry(mount.clone()).or_default() += 1;
+        }
+    }
+
+    out.family(
+        "source_videos_rtsp_clients",
+        "gauge",
+        "RTSP clients playing each mount",
+    );
+    for (mount, count) in clients {
+        out.sample(
+            "source_videos_rtsp_clients",
+            &[("mount", mount.as_str())],
+            count as f64,
+        );
+    }
+
+    out.family(
+        "source_videos_rtsp_bytes_sent_total",
+        "counter",
+        "RTP bytes sent per mount",
+    );
+    for (mount, bytes) in sorted(server.traffic_log().bytes_sent()) {
+        out.sample(
+            "source_videos_rtsp_bytes_sent_total",
+            &[("mount", mount.as_str())],
+            bytes as f64,
+        );
+    }
+
+    out.family(
+        "source_videos_rtsp_sessions_total",
+        "counter",
+        "RTSP sessions by negotiated transport",
+    );
+    for (transport, count) in [
+        ("udp", report.totals.udp),
+        ("udp_multicast", report.totals.udp_multicast),
+        ("tcp", report.totals.tcp),
+    ] {
+        out.sample(
+            "source_videos_rtsp_sessions_total",
+            &[("transport", transport)],
+            count as f64,
+        );
+    }
+}
+
+async fn write_network(out: &mut Exposition, state: &ApiState) {
+    let conditions = state.get_network_status().await;
+
+    out.family(
+        "source_videos_network_simulation_active",
+        "gauge",
+        "Whether network simulation is configured",
+    );
+    out.sample(
+        "source_videos_network_simulation_active",
+        &[],
+        if conditions.is_some() { 1.0 } else { 0.0 },
+    );
+    let Some(conditions) = conditions else {
+        return;
+    };
+
+    let gauges = [
+        (
+            "source_videos_network_packet_loss_percent",
+            "Simulated packet loss",
+            conditions.packet_loss as f64,
+        ),
+        (
+            "source_videos_network_latency_ms",
+            "Simulated added latency",
+            conditions.latency_ms as f64,
+        ),
+        (
+            "source_videos_network_jitter_ms",
+            "Simulated jitter",
+            conditions.jitter_ms as f64,
+        ),
+        (
+            "source_videos_network_bandwidth_kbps",
+            "Simulated bandwidth limit, 0 when unlimited",
+            conditions.bandwidth_kbps as f64,
+        ),
+        (
+            "source_videos_network_connection_dropped",
+            "Whether the simulated connection is down",
+            if conditions.connection_dropped {
+                1.0
+            } else {
+                0.0
+            },
+        ),
+    ];
+    for (name, help, value) in gauges {
+        out.family(name, "gauge", help);
+        out.sample(name, &[], value);
+    }
+}
+
+fn sorted<K: Ord, V>(map: HashMap<K, V>) -> Vec<(K, V)> {
+    let mut entries: Vec<_> = map.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exposition_format() {
+        let requests = RequestMetrics::default();
+        requests.observe("GET", "/api/v1/sources", 200, Duration::from_millis(20));
+        requests.observe("GET", "/api/v1/sources", 200, Duration::from_secs(2));
+
+        let mut out = Exposition::new();
+        requests.write(&mut out);
+        let text = out.finish();
+
+        let labels = "method=\"GET\",route=\"/api/v1/sources\",status=\"200\"";
+        assert!(text.contains(&format!(
+            "source_videos_api_request_duration_seconds_bucket{{{},le=\"0.01\"}} 0\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "source_videos_api_request_duration_seconds_bucket{{{},le=\"0.025\"}} 1\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "source_videos_api_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "source_videos_api_request_duration_seconds_count{{{}}} 2\n",
+            labels
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_unread_watcher_counts_are_left_out() {
+        let watchers = Arc::new(tokio::sync::RwLock::new(crate::WatcherManager::new()));
+        let busy = watchers.write().await;
+        let state = ApiState::new(
+            None,
+            Arc::new(crate::VideoSourceManager::new()),
+            watchers.clone(),
+        );
+        drop(busy);
+
+        let text = render(&state).await;
+        assert!(!text.contains("source_videos_watcher_events_total"));
+        assert!(text.contains("source_videos_network_simulation_active 0\n"));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{AppConfig, Result, RtspServer, SourceVideoError, VideoSourceManager, WatcherManager};
+use crate::{
+    AppConfig, Result, RtspServer, ServerFarm, SourceVideoError, VideoSourceManager, WatcherManager,
+};
 use axum::{
     Router,
     extract::State,
@@ -13,6 +15,7 @@ use tokio::sync::RwLock;
 
 pub mod auth;
 pub mod error;
+pub mod metrics;
 pub mod models;
 pub mod routes;
 pub mod state;
@@ -25,6 +28,7 @@ pub struct ControlApi {
     state: Arc<ApiState>,
     router: Router,
     bind_address: SocketAddr,
+    port_retries: u16,
 }
 
 impl ControlApi {
@@ -45,6 +49,7 @@ impl ControlApi {
             state,
             router,
             bind_address,
+            port_retries: 0,
         })
     }
 
@@ -58,6 +63,7 @@ impl ControlApi {
             state,
             router: Router::new(),
             bind_address,
+            port_retries: 0,
         };
 
         api.router = Self::create_router(api.state.clone());
@@ -65,10 +71,85 @@ impl ControlApi {
         Ok(api)
     }
 
+    /// Size limits and rate limit of source previews.
+    pub fn with_preview_settings(mut self, settings: crate::PreviewSettings) -> Self {
+        let previews = Arc::new(crate::PreviewCache::new(settings));
+        let state = (*self.state).clone().with_previews(previews);
+        self.state = Arc::new(state);
+        self.router = Self::create_router(self.state.clone());
+        self
+    }
+
+    /// Share a chaos controller, e.g. one started from the command line,
+    /// instead of the API's own.
+    pub fn with_chaos(mut self, chaos: Arc<crate::ChaosController>) -> Self {
+        let state = (*self.state).clone().with_chaos(chaos);
+        self.state = Arc::new(state);
+        self.router = Self::create_router(self.state.clone());
+        self
+    }
+
+    /// Attach a server farm so its instances can be managed through the API.
+    pub fn with_farm(mut self, farm: Arc<ServerFarm>) -> Self {
+        let state = (*self.state).clone().with_farm(farm);
+        self.state = Arc::new(state);
+        self.router = Self::create_router(self.state.clone());
+        self
+    }
+
     pub fn set_bind_address(&mut self, address: SocketAddr) {
         self.bind_address = address;
     }
 
+    /// Try up to `retries` following ports if the bind port is taken.
+    pub fn set_port_retries(&mut self, retries: u16) {
+        self.port_retries = retries;
+    }
+
+    /// The configured address, or the bound one after [`bind`](Self::bind).
+    pub fn local_address(&self) -> SocketAddr {
+        self.bind_address
+    }
+
+    /// Bind the listener, resolving port `0` and retrying on conflicts.
+    pub async fn bind(&mut self) -> Result<TcpListener> {
+        let requested = self.bind_address;
+        let mut last_error = None;
+
+        for port in crate::ports::candidates(requested.port(), self.port_retries) {
+            let address = SocketAddr::new(requested.ip(), port);
+            match TcpListener::bind(address).await {
+                Ok(listener) => {
+                    self.bind_address = listener.local_addr().unwrap_or(address);
+                    if self.bind_address != requested {
+                        info!(
+                            "Control API bound to {} (requested {})",
+                            self.bind_address, requested
+                        );
+                    }
+                    *self.state.api_address.write().await = Some(self.bind_address);
+                    return Ok(listener);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                    debug!("Control API port {} already in use", port);
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    last_error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let range = crate::ports::describe_range(requested.port(), self.port_retries);
+        Err(SourceVideoError::config(format!(
+            "Failed to bind to {}:{}: {}",
+            requested.ip(),
+            range,
+            last_error.map_or_else(|| "no port available".to_string(), |e| e.to_string())
+        )))
+    }
+
     fn create_router(state: Arc<ApiState>) -> Router {
         let api_v1 = Router::new()
             // Health endpoints
@@ -84,6 +165,30 @@ impl ControlApi {
             .route("/sources/{id}", delete(routes::sources::remove_source))
             .route("/sources/{id}", put(routes::sources::update_source))
             .route("/sources/batch", post(routes::sources::batch_operations))
+            .route(
+                "/sources/group/{tag}/start-synced",
+                post(routes::sources::start_group_synced),
+            )
+            .route(
+                "/sources/{id}/preview",
+                get(routes::sources::preview_source),
+            )
+            // Live pattern control
+            .route("/sources/patterns", get(routes::patterns::list_patterns))
+            .route("/sources/{id}/pattern", get(routes::patterns::get_pattern))
+            .route("/sources/{id}/pattern", put(routes::patterns::set_pattern))
+            .route(
+                "/sources/{id}/rotation",
+                put(routes::patterns::set_rotation),
+            )
+            .route(
+                "/sources/{id}/rotation",
+                delete(routes::patterns::clear_rotation),
+            )
+            .route(
+                "/sources/{id}/rotation/next",
+                post(routes::patterns::next_pattern),
+            )
             // Server control
             .route("/server/start", post(routes::server::start_server))
             .route("/server/stop", post(routes::server::stop_server))
@@ -91,6 +196,19 @@ impl ControlApi {
             .route("/server/status", get(routes::server::server_status))
             .route("/server/info", get(routes::server::server_info))
             .route("/server/urls", get(routes::server::list_urls))
+            .route("/server/sessions", get(routes::server::list_sessions))
+            // Chaos testing
+            .route("/chaos", get(routes::chaos::chaos_status))
+            .route("/chaos/start", post(routes::chaos::start_chaos))
+            .route("/chaos/stop", post(routes::chaos::stop_chaos))
+            // Playback control of file mounts
+            .route("/playback", get(routes::playback::playback_status))
+            .route("/playback/pause", post(routes::playback::pause))
+            .route("/playback/resume", post(routes::playback::resume))
+            .route("/playback/step", post(routes::playback::step))
+            // Time-shift buffers
+            .route("/dvr", get(routes::dvr::list_dvr))
+            .route("/dvr/{*path}", get(routes::dvr::dvr_file))
             // Configuration
             .route("/config", get(routes::config::get_config))
             .route("/config", put(routes::config::update_config))
@@ -110,9 +228,55 @@ impl ControlApi {
             .route("/watch/start", post(routes::operations::start_watching))
             .route("/watch/stop", post(routes::operations::stop_watching))
             .route("/watch/status", get(routes::operations::watch_status))
+            .route("/watch/{id}", get(routes::operations::get_watcher))
+            .route("/watch/{id}/pause", post(routes::operations::pause_watcher))
+            .route(
+                "/watch/{id}/resume",
+                post(routes::operations::resume_watcher),
+            )
+            .route(
+                "/watch/{id}/filter",
+                put(routes::operations::set_watcher_filter),
+            )
+            .route(
+                "/watch/{id}/filter",
+                delete(routes::operations::clear_watcher_filter),
+            )
+            .route("/operations", get(routes::operations::list_operations))
+            .route("/operations/scan", post(routes::operations::start_scan))
+            .route(
+                "/operations/generate",
+                post(routes::operations::start_generate),
+            )
+            .route("/operations/{id}", get(routes::operations::get_operation))
+            .route(
+                "/operations/{id}",
+                delete(routes::operations::cancel_operation),
+            )
+            // Unified event stream
+            .route("/events", get(routes::events::stream_events))
+            .route("/events/recent", get(routes::events::recent_events))
+            // Server farm
+            .route("/farm", get(routes::farm::farm_status))
+            .route("/farm/instances/{id}", get(routes::farm::get_instance))
+            .route(
+                "/farm/instances/{id}/sources",
+                post(routes::farm::add_instance_source),
+            )
+            .route(
+                "/farm/instances/{id}/sources/{mount}",
+                delete(routes::farm::remove_instance_source),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                metrics::track_requests,
+            ))
             .with_state(state.clone());
 
         Router::new()
+            // Prometheus scrape endpoint, at the path scrapers default to
+            .route("/metrics", get(routes::health::prometheus_metrics))
+            .with_state(state.clone())
             .nest("/api/v1", api_v1)
             .layer(middleware::from_fn_with_state(
                 state.clone(),
@@ -122,10 +286,8 @@ impl ControlApi {
             .layer(tower_http::trace::TraceLayer::new_for_http())
     }
 
-    pub async fn bind_and_serve(self) -> Result<()> {
-        let listener = TcpListener::bind(self.bind_address).await.map_err(|e| {
-            SourceVideoError::config(format!("Failed to bind to {}: {}", self.bind_address, e))
-        })?;
+    pub async fn bind_and_serve(mut self) -> Result<()> {
+        let listener = self.bind().await?;
 
         info!("Control API listening on http://{}", self.bind_address);
         info!(
@@ -142,13 +304,19 @@ impl ControlApi {
     }
 
     pub async fn serve_with_shutdown(
-        self,
+        mut self,
         shutdown: impl std::future::Future<Output = ()> + Send + 'static,
     ) -> Result<()> {
-        let listener = TcpListener::bind(self.bind_address).await.map_err(|e| {
-            SourceVideoError::config(format!("Failed to bind to {}: {}", self.bind_address, e))
-        })?;
+        let listener = self.bind().await?;
+        self.serve_listener(listener, shutdown).await
+    }
 
+    /// Serve on a listener obtained from [`bind`](Self::bind).
+    pub async fn serve_listener(
+        self,
+        listener: TcpListener,
+        shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
         info!("Control API listening on http://{}", self.bind_address);
 
         axum::serve(listener, self.router)
@@ -188,4 +356,43 @@ mod tests {
         let api = ControlApi::new(None, source_manager, watcher_manager).unwrap();
         assert!(!api.bind_address.to_string().is_empty());
     }
+
+    fn new_api(address: &str) -> ControlApi {
+        let source_manager = Arc::new(VideoSourceManager::new());
+        let watcher_manager = Arc::new(RwLock::new(WatcherManager::new()));
+        let mut api = ControlApi::new(None, source_manager, watcher_manager).unwrap();
+        api.set_bind_address(address.parse().unwrap());
+        api
+    }
+
+    #[tokio::test]
+    async fn test_bind_ephemeral_port() {
+        let mut api = new_api("127.0.0.1:0");
+        let _listener = api.bind().await.unwrap();
+
+        let bound = api.local_address();
+        assert_ne!(bound.port(), 0);
+        assert_eq!(*api.state().api_address.read().await, Some(bound));
+    }
+
+    #[tokio::test]
+    async fn test_bind_taken_port_fails() {
+        let mut first = new_api("127.0.0.1:0");
+        let _listener = first.bind().await.unwrap();
+
+        let mut second = new_api(&first.local_address().to_string());
+        assert!(second.bind().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_retries_next_port() {
+        let mut first = new_api("127.0.0.1:0");
+        let _listener = first.bind().await.unwrap();
+        let taken = first.local_address();
+
+        let mut second = new_api(&taken.to_string());
+        second.set_port_retries(10);
+        let _listener = second.bind().await.unwrap();
+        assert!(second.local_address().port() > taken.port());
+    }
 }
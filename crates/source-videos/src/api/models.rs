@@ -1,5 +1,8 @@
 use crate::config_types::{FileContainer, Framerate, Resolution, VideoFormat};
-use crate::{SourceInfo, SourceState, TestPattern, VideoSourceConfig, VideoSourceType};
+use crate::{
+    ChaosConfig, ChaosEvent, EventFilter, FarmInstanceInfo, SourceInfo, SourceState, TestPattern,
+    VideoSourceConfig, VideoSourceType, WatcherStatus,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -19,6 +22,43 @@ pub struct AddSourceRequest {
     pub duration: Option<u64>,
     #[serde(default)]
     pub is_live: bool,
+    /// Groups the source belongs to, for synchronized starts
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl AddSourceRequest {
+    pub fn into_source_config(self) -> VideoSourceConfig {
+        let source_type = match self.source_type {
+            SourceTypeRequest::TestPattern { pattern } => VideoSourceType::TestPattern { pattern },
+            SourceTypeRequest::File { path, container } => VideoSourceType::File {
+                path,
+                container: container.unwrap_or(FileContainer::Mp4),
+            },
+            SourceTypeRequest::Rtsp { mount_point, port } => VideoSourceType::Rtsp {
+                mount_point,
+                port: port.unwrap_or(8554),
+            },
+        };
+
+        VideoSourceConfig {
+            name: self.name,
+            source_type,
+            resolution: self.resolution.unwrap_or(Resolution {
+                width: 1920,
+                height: 1080,
+            }),
+            framerate: self.framerate.unwrap_or(Framerate {
+                numerator: 30,
+                denominator: 1,
+            }),
+            format: self.format.unwrap_or(VideoFormat::I420),
+            duration: self.duration,
+            num_buffers: None,
+            is_live: self.is_live,
+            tags: self.tags,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +112,8 @@ pub struct UpdateSourceRequest {
     pub framerate: Option<Framerate>,
     #[serde(default)]
     pub format: Option<VideoFormat>,
+    #[serde(default)]
+    pub pattern: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,8 +156,12 @@ pub struct BatchResult {
 // Server Control Models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StartServerRequest {
+    /// Port to listen on; `0` picks an ephemeral port
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Further ports tried when `port` is already in use
+    #[serde(default)]
+    pub port_retries: u16,
     #[serde(default = "default_address")]
     pub address: String,
     #[serde(default)]
@@ -147,6 +193,9 @@ pub struct ServerInfoResponse {
     pub capabilities: Vec<String>,
     pub supported_formats: Vec<String>,
     pub max_sources: Option<usize>,
+    /// Ports actually bound, useful when started with port 0
+    #[serde(default)]
+    pub ports: crate::ports::BoundPorts,
 }
 
 // Configuration Models
@@ -229,6 +278,32 @@ pub struct NetworkConditionsResponse {
     pub connection_dropped: bool,
 }
 
+// Playback Models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackRequest {
+    /// Mount point of a file source
+    pub mount: String,
+    /// Frames to send when stepping; one by default
+    #[serde(default)]
+    pub frames: Option<u64>,
+}
+
+// Chaos Models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartChaosRequest {
+    #[serde(flatten)]
+    pub config: ChaosConfig,
+    /// Timeline to replay instead of rolling new faults
+    #[serde(default)]
+    pub replay: Option<Vec<ChaosEvent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartChaosResponse {
+    /// Seed to pass again to get the same faults; none for a replay
+    pub seed: Option<u64>,
+}
+
 // Operations Models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateVideoRequest {
@@ -282,6 +357,21 @@ pub struct PatternResponse {
     pub animated: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventQuery {
+    /// Comma-separated topics; all topics when absent
+    #[serde(default)]
+    pub topics: Option<String>,
+    /// Only events with a greater ID
+    #[serde(default)]
+    pub since: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetPatternRequest {
+    pub pattern: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StartWatchingRequest {
     pub directory: String,
@@ -291,6 +381,10 @@ pub struct StartWatchingRequest {
     pub auto_reload: bool,
     #[serde(default = "default_debounce")]
     pub debounce_ms: u64,
+    /// Report the video files already in the directory as initial Created
+    /// events
+    #[serde(default)]
+    pub initial_sync: bool,
 }
 
 fn default_debounce() -> u64 {
@@ -300,15 +394,55 @@ fn default_debounce() -> u64 {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WatchStatusResponse {
     pub active: bool,
-    pub watchers: Vec<WatcherInfo>,
+    pub watchers: Vec<WatcherStatus>,
 }
 
+/// Events a watcher passes on; empty lists pass everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchFilterRequest {
+    /// Event types such as `created`, `modified` or `deleted`
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+    /// File name patterns with `*` wildcards
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+}
+
+impl WatchFilterRequest {
+    pub fn to_filter(&self) -> EventFilter {
+        let filter = self
+            .event_types
+            .iter()
+            .fold(EventFilter::new(), |f, t| f.with_event_type(t.clone()));
+        let filter = self.extensions.iter().fold(filter, |f, ext| {
+            f.with_include_extension(ext.trim_start_matches('.').to_string())
+        });
+        let filter = self.exclude_extensions.iter().fold(filter, |f, ext| {
+            f.with_exclude_extension(ext.trim_start_matches('.').to_string())
+        });
+        let filter = self
+            .patterns
+            .iter()
+            .fold(filter, |f, p| f.with_include_pattern(p.clone()));
+        self.exclude_patterns
+            .iter()
+            .fold(filter, |f, p| f.with_exclude_pattern(p.clone()))
+    }
+}
+
+// Server Farm Models
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WatcherInfo {
-    pub id: String,
-    pub directory: String,
-    pub recursive: bool,
-    pub events_received: u64,
+pub struct FarmStatusResponse {
+    pub enabled: bool,
+    pub instance_count: usize,
+    pub total_sources: usize,
+    pub instances: Vec<FarmInstanceInfo>,
 }
 
 // Health Check Models
@@ -327,13 +461,54 @@ pub struct LivenessResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadinessResponse {
     pub ready: bool,
+    pub status: HealthState,
     pub components: HashMap<String, ComponentStatus>,
 }
 
+/// Health of a component or of the service as a whole.
+///
+/// `Degraded` still counts as ready; only `Unhealthy` fails readiness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl HealthState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthState::Healthy => "healthy",
+            HealthState::Degraded => "degraded",
+            HealthState::Unhealthy => "unhealthy",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentStatus {
     pub healthy: bool,
+    pub status: HealthState,
     pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ComponentStatus {
+    pub fn new(status: HealthState, message: impl Into<String>) -> Self {
+        Self {
+            healthy: status != HealthState::Unhealthy,
+            status,
+            message: Some(message.into()),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,48 @@
+use crate::api::{
+    ApiError, ApiResult, ApiState,
+    models::{StartChaosRequest, StartChaosResponse, SuccessResponse},
+};
+use crate::{ChaosController, ChaosStatus};
+use axum::{Json, extract::State};
+use std::sync::Arc;
+
+fn controller(state: &ApiState) -> ApiResult<&Arc<ChaosController>> {
+    state
+        .chaos
+        .as_ref()
+        .ok_or_else(|| ApiError::service_unavailable("Chaos mode needs a running RTSP server"))
+}
+
+pub async fn chaos_status(State(state): State<Arc<ApiState>>) -> ApiResult<Json<ChaosStatus>> {
+    Ok(Json(controller(&state)?.status()))
+}
+
+pub async fn start_chaos(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<StartChaosRequest>,
+) -> ApiResult<Json<StartChaosResponse>> {
+    let chaos = controller(&state)?;
+    let seed = match req.replay {
+        Some(events) => {
+            chaos.replay(events)?;
+            None
+        }
+        None => Some(chaos.start(req.config)?),
+    };
+    Ok(Json(StartChaosResponse { seed }))
+}
+
+pub async fn stop_chaos(State(state): State<Arc<ApiState>>) -> ApiResult<Json<SuccessResponse>> {
+    let stopped = controller(&state)?.stop().await;
+    Ok(Json(SuccessResponse {
+        success: stopped,
+        message: Some(
+            if stopped {
+                "Chaos mode stopped, faults undone"
+            } else {
+                "Chaos mode was not running"
+            }
+            .to_string(),
+        ),
+    }))
+}
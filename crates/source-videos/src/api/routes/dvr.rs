@@ -0,0 +1,73 @@
+use crate::api::{ApiError, ApiResult, ApiState};
+use crate::rtsp::dvr::{self, DvrStatus};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+pub async fn list_dvr(State(state): State<Arc<ApiState>>) -> ApiResult<Json<Vec<DvrStatus>>> {
+    let server = state
+        .rtsp_server
+        .as_ref()
+        .ok_or_else(|| ApiError::service_unavailable("DVR needs a running RTSP server"))?;
+    Ok(Json(server.read().await.dvr_status()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DvrQuery {
+    /// Seconds behind live the playlist asks players to start at
+    pub offset: Option<u32>,
+}
+
+/// Playlist or segment of a mount's time-shift buffer, at
+/// `{mount}/playlist.m3u8` and `{mount}/{segment}`
+pub async fn dvr_file(
+    State(state): State<Arc<ApiState>>,
+    Path(path): Path<String>,
+    Query(query): Query<DvrQuery>,
+) -> ApiResult<Response> {
+    let (mount, file) = path
+        .rsplit_once('/')
+        .ok_or_else(|| ApiError::not_found(format!("No DVR file at {}", path)))?;
+    let server = state
+        .rtsp_server
+        .as_ref()
+        .ok_or_else(|| ApiError::service_unavailable("DVR needs a running RTSP server"))?;
+    let recorder = server
+        .read()
+        .await
+        .dvr(mount)
+        .ok_or_else(|| ApiError::not_found(format!("No DVR buffer for /{}", mount)))?;
+
+    if file == dvr::PLAYLIST {
+        let playlist = tokio::fs::read_to_string(recorder.playlist_path())
+            .await
+            .map_err(|_| ApiError::not_found("No segments recorded yet"))?;
+        let playlist = match query.offset {
+            Some(offset) => {
+                dvr::with_start_offset(&playlist, offset.min(recorder.config().window_secs))
+            }
+            None => playlist,
+        };
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "application/vnd.apple.mpegurl"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            playlist,
+        )
+            .into_response());
+    }
+
+    let segment = recorder
+        .segment_path(file)
+        .ok_or_else(|| ApiError::not_found(format!("No DVR file {}", file)))?;
+    let bytes = tokio::fs::read(segment)
+        .await
+        .map_err(|_| ApiError::not_found(format!("Segment {} is no longer buffered", file)))?;
+    Ok(([(header::CONTENT_TYPE, "video/mp2t")], bytes).into_response())
+}
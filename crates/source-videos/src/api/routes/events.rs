@@ -0,0 +1,59 @@
+use crate::api::{ApiError, ApiResult, ApiState, models::EventQuery};
+use crate::bus::{Envelope, Topic};
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+fn topics(query: &EventQuery) -> ApiResult<HashSet<Topic>> {
+    match &query.topics {
+        Some(list) => Topic::parse_list(list).map_err(|e| ApiError::bad_request(e.to_string())),
+        None => Ok(HashSet::new()),
+    }
+}
+
+fn to_sse(envelope: &Envelope) -> Option<Event> {
+    Event::default()
+        .id(envelope.id.to_string())
+        .event(envelope.topic.name())
+        .json_data(envelope)
+        .ok()
+}
+
+/// Server-sent events for the requested topics, replaying kept events
+/// newer than `since` first
+pub async fn stream_events(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<EventQuery>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let topics = topics(&query)?;
+    // Subscribe before taking the backlog so nothing falls in between
+    let subscription = state.events.subscribe(topics.clone());
+    let backlog = state.events.recent(&topics, query.since);
+    let replayed = backlog.last().map_or(query.since, |envelope| envelope.id);
+
+    let live = BroadcastStream::new(subscription.into_receiver()).filter_map(move |item| {
+        let envelope = item.ok()?;
+        let wanted =
+            envelope.id > replayed && (topics.is_empty() || topics.contains(&envelope.topic));
+        if wanted { to_sse(&envelope) } else { None }
+    });
+    let stream = tokio_stream::iter(backlog.iter().filter_map(to_sse).collect::<Vec<_>>())
+        .chain(live)
+        .map(Ok);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub async fn recent_events(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<EventQuery>,
+) -> ApiResult<Json<Vec<Envelope>>> {
+    let topics = topics(&query)?;
+    Ok(Json(state.events.recent(&topics, query.since)))
+}
@@ -0,0 +1,85 @@
+use crate::FarmInstanceInfo;
+use crate::api::{
+    ApiError, ApiResult, ApiState,
+    models::{AddSourceRequest, FarmStatusResponse, SuccessResponse},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use std::sync::Arc;
+
+pub async fn farm_status(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<Json<FarmStatusResponse>> {
+    let Some(farm) = &state.farm else {
+        return Ok(Json(FarmStatusResponse {
+            enabled: false,
+            instance_count: 0,
+            total_sources: 0,
+            instances: vec![],
+        }));
+    };
+
+    let instances = farm.infos().await;
+    let total_sources = instances.iter().map(|i| i.source_count).sum();
+
+    Ok(Json(FarmStatusResponse {
+        enabled: true,
+        instance_count: instances.len(),
+        total_sources,
+        instances,
+    }))
+}
+
+pub async fn get_instance(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<FarmInstanceInfo>> {
+    let farm = state
+        .farm
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("Server farm is not enabled"))?;
+
+    let instance = farm
+        .instance(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Farm instance '{}' not found", id)))?;
+
+    Ok(Json(instance.info().await))
+}
+
+pub async fn add_instance_source(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+    Json(req): Json<AddSourceRequest>,
+) -> ApiResult<Json<FarmInstanceInfo>> {
+    let farm = state
+        .farm
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("Server farm is not enabled"))?;
+
+    farm.add_source(&id, req.into_source_config()).await?;
+
+    let instance = farm
+        .instance(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Farm instance '{}' not found", id)))?;
+
+    Ok(Json(instance.info().await))
+}
+
+pub async fn remove_instance_source(
+    State(state): State<Arc<ApiState>>,
+    Path((id, mount)): Path<(String, String)>,
+) -> ApiResult<Json<SuccessResponse>> {
+    let farm = state
+        .farm
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("Server farm is not enabled"))?;
+
+    farm.remove_source(&id, &mount).await?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        message: Some(format!("Removed '{}' from farm instance '{}'", mount, id)),
+    }))
+}
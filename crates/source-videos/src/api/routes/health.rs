@@ -1,11 +1,25 @@
-use crate::api::{ApiResult, ApiState, models::*};
-use axum::{Json, extract::State};
+use crate::SourceState;
+use crate::api::{ApiResult, ApiState, metrics, models::*};
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// How long a health check waits for the file watchers before reporting
+/// them degraded
+const WATCHER_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub async fn health_check(State(state): State<Arc<ApiState>>) -> ApiResult<Json<HealthResponse>> {
+    let components = collect_components(&state).await;
 
-pub async fn health_check(State(_state): State<Arc<ApiState>>) -> ApiResult<Json<HealthResponse>> {
     Ok(Json(HealthResponse {
-        status: "healthy".to_string(),
+        status: overall_status(&components).as_str().to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
         version: env!("CARGO_PKG_VERSION").to_string(),
     }))
@@ -15,80 +29,242 @@ pub async fn liveness(State(_state): State<Arc<ApiState>>) -> ApiResult<Json<Liv
     Ok(Json(LivenessResponse { alive: true }))
 }
 
-pub async fn readiness(State(state): State<Arc<ApiState>>) -> ApiResult<Json<ReadinessResponse>> {
-    let mut components = HashMap::new();
+/// Readiness probe suitable for Docker/Kubernetes health checks.
+///
+/// Responds with 503 when any component is unhealthy so that `curl -f`
+/// style probes fail; degraded components are reported but stay ready.
+pub async fn readiness(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<(StatusCode, Json<ReadinessResponse>)> {
+    let components = collect_components(&state).await;
+    let status = overall_status(&components);
+    let ready = status != HealthState::Unhealthy;
 
-    // Check source manager
-    components.insert(
-        "source_manager".to_string(),
-        ComponentStatus {
-            healthy: true,
-            message: Some(format!(
-                "{} sources active",
-                state.source_manager.list_sources().len()
-            )),
-        },
-    );
-
-    // Check RTSP server
-    let rtsp_status = if state.rtsp_server.is_some() {
-        ComponentStatus {
-            healthy: true,
-            message: Some("RTSP server running".to_string()),
-        }
+    let code = if ready {
+        StatusCode::OK
     } else {
-        ComponentStatus {
-            healthy: true,
-            message: Some("RTSP server not started".to_string()),
-        }
+        StatusCode::SERVICE_UNAVAILABLE
     };
-    components.insert("rtsp_server".to_string(), rtsp_status);
 
-    // Check watcher manager
-    let watcher_status = ComponentStatus {
-        healthy: true,
-        message: Some("Watcher available".to_string()),
+    Ok((
+        code,
+        Json(ReadinessResponse {
+            ready,
+            status,
+            components,
+        }),
+    ))
+}
+
+pub async fn metrics(State(state): State<Arc<ApiState>>) -> ApiResult<Json<MetricsResponse>> {
+    let source_count = state.source_manager.list_sources().len() as u64;
+
+    // These would be tracked in a real implementation
+    let metrics = MetricsResponse {
+        source_count,
+        active_connections: 0,
+        total_requests: 0,
+        error_count: 0,
+        uptime_seconds: 0,
+        cpu_usage: None,
+        memory_usage_mb: None,
     };
-    components.insert("file_watcher".to_string(), watcher_status);
 
-    // Check network simulator
+    Ok(Json(metrics))
+}
+
+/// The server's state in the Prometheus text format.
+pub async fn prometheus_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        metrics::render(&state).await,
+    )
+}
+
+/// Worst status across all components.
+pub fn overall_status(components: &HashMap<String, ComponentStatus>) -> HealthState {
+    components
+        .values()
+        .map(|c| c.status)
+        .max()
+        .unwrap_or(HealthState::Healthy)
+}
+
+pub async fn collect_components(state: &ApiState) -> HashMap<String, ComponentStatus> {
+    let mut components = HashMap::new();
+
+    components.insert("source_manager".to_string(), source_manager_status(state));
+    components.insert("rtsp_server".to_string(), rtsp_server_status(state).await);
+    components.insert("file_watcher".to_string(), watcher_status(state).await);
+
+    if state.farm.is_some() {
+        components.insert("farm".to_string(), farm_status(state).await);
+    }
+
     let network_status = {
         let sim = state.network_simulator.read().await;
-        ComponentStatus {
-            healthy: true,
-            message: Some(format!(
+        ComponentStatus::new(
+            HealthState::Healthy,
+            format!(
                 "Network simulator {}",
                 if sim.is_some() {
                     "configured"
                 } else {
                     "not configured"
                 }
-            )),
-        }
+            ),
+        )
     };
     components.insert("network_simulator".to_string(), network_status);
 
-    let all_healthy = components.values().all(|c| c.healthy);
+    components
+}
 
-    Ok(Json(ReadinessResponse {
-        ready: all_healthy,
-        components,
+fn source_manager_status(state: &ApiState) -> ComponentStatus {
+    let sources = state.source_manager.list_sources();
+    let failed: Vec<&str> = sources
+        .iter()
+        .filter(|s| matches!(s.state, SourceState::Error(_)))
+        .map(|s| s.name.as_str())
+        .collect();
+
+    let status = if failed.is_empty() {
+        HealthState::Healthy
+    } else if failed.len() == sources.len() {
+        HealthState::Unhealthy
+    } else {
+        HealthState::Degraded
+    };
+
+    ComponentStatus::new(
+        status,
+        format!(
+            "{} sources active, {} in error",
+            sources.len(),
+            failed.len()
+        ),
+    )
+    .with_details(serde_json::json!({
+        "total": sources.len(),
+        "failed": failed,
     }))
 }
 
-pub async fn metrics(State(state): State<Arc<ApiState>>) -> ApiResult<Json<MetricsResponse>> {
-    let source_count = state.source_manager.list_sources().len() as u64;
+async fn rtsp_server_status(state: &ApiState) -> ComponentStatus {
+    let Some(rtsp_server) = &state.rtsp_server else {
+        return ComponentStatus::new(HealthState::Healthy, "RTSP server not configured");
+    };
 
-    // These would be tracked in a real implementation
-    let metrics = MetricsResponse {
-        source_count,
-        active_connections: 0,
-        total_requests: 0,
-        error_count: 0,
-        uptime_seconds: 0,
-        cpu_usage: None,
-        memory_usage_mb: None,
+    let server = rtsp_server.read().await;
+    let mounts = server.list_sources();
+    let attached = server.is_attached();
+
+    let (status, message) = if !attached {
+        (HealthState::Unhealthy, "RTSP server not attached")
+    } else if mounts.is_empty() {
+        (HealthState::Unhealthy, "RTSP server has no mounts serving")
+    } else {
+        (HealthState::Healthy, "RTSP server running")
     };
 
-    Ok(Json(metrics))
+    ComponentStatus::new(status, message).with_details(serde_json::json!({
+        "attached": attached,
+        "address": server.get_address(),
+        "port": server.get_port(),
+        "mounts": mounts,
+    }))
+}
+
+async fn watcher_status(state: &ApiState) -> ComponentStatus {
+    let Ok(manager) = timeout(WATCHER_LOCK_TIMEOUT, state.watcher_manager.read()).await else {
+        return ComponentStatus::new(
+            HealthState::Degraded,
+            "Watcher manager did not respond; it may be stuck",
+        );
+    };
+
+    let watchers = manager.list_watchers();
+    let stopped: Vec<&str> = watchers
+        .iter()
+        .copied()
+        .filter(|id| !manager.is_watching(id))
+        .collect();
+
+    let status = if stopped.is_empty() {
+        HealthState::Healthy
+    } else if stopped.len() == watchers.len() {
+        HealthState::Unhealthy
+    } else {
+        HealthState::Degraded
+    };
+
+    ComponentStatus::new(
+        status,
+        format!("{} watchers, {} not running", watchers.len(), stopped.len()),
+    )
+    .with_details(serde_json::json!({
+        "watchers": watchers,
+        "stopped": stopped,
+    }))
+}
+
+async fn farm_status(state: &ApiState) -> ComponentStatus {
+    let Some(farm) = &state.farm else {
+        return ComponentStatus::new(HealthState::Healthy, "Server farm not configured");
+    };
+
+    let mut serving = 0;
+    let mut idle = Vec::new();
+    for instance in farm.instances() {
+        let server = instance.server();
+        let server = server.read().await;
+        if server.is_attached() && !server.list_sources().is_empty() {
+            serving += 1;
+        } else {
+            idle.push(instance.id().to_string());
+        }
+    }
+
+    let status = if idle.is_empty() {
+        HealthState::Healthy
+    } else if serving == 0 {
+        HealthState::Unhealthy
+    } else {
+        HealthState::Degraded
+    };
+
+    ComponentStatus::new(
+        status,
+        format!("{} of {} instances serving", serving, farm.instance_count()),
+    )
+    .with_details(serde_json::json!({ "not_serving": idle }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_status_is_worst_component() {
+        let mut components = HashMap::new();
+        assert_eq!(overall_status(&components), HealthState::Healthy);
+
+        components.insert(
+            "a".to_string(),
+            ComponentStatus::new(HealthState::Healthy, "ok"),
+        );
+        components.insert(
+            "b".to_string(),
+            ComponentStatus::new(HealthState::Degraded, "slow"),
+        );
+        assert_eq!(overall_status(&components), HealthState::Degraded);
+
+        components.insert(
+            "c".to_string(),
+            ComponentStatus::new(HealthState::Unhealthy, "down"),
+        );
+        assert_eq!(overall_status(&components), HealthState::Unhealthy);
+        assert!(!components["c"].healthy);
+        assert!(components["b"].healthy);
+    }
 }
@@ -1,6 +1,12 @@
+pub mod chaos;
 pub mod config;
+pub mod dvr;
+pub mod events;
+pub mod farm;
 pub mod health;
 pub mod network;
 pub mod operations;
+pub mod patterns;
+pub mod playback;
 pub mod server;
 pub mod sources;
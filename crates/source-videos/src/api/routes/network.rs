@@ -97,31 +97,66 @@ pub async fn set_conditions(
         },
     };
 
-    state.apply_custom_network_conditions(conditions).await?;
+    // Selected sources change on their own; otherwise every mount does
+    let message = match &req.sources {
+        Some(sources) => {
+            let mounts = state
+                .apply_mount_network_conditions(Some(sources), conditions)
+                .await?;
+            format!("Applied custom network conditions to {}", mounts.join(", "))
+        }
+        None => {
+            state
+                .apply_custom_network_conditions(conditions.clone())
+                .await?;
+            if state.rtsp_server.is_some() {
+                state
+                    .apply_mount_network_conditions(None, conditions)
+                    .await?;
+            }
+            "Applied custom network conditions".to_string()
+        }
+    };
 
     Ok(Json(SuccessResponse {
         success: true,
-        message: Some("Applied custom network conditions".to_string()),
+        message: Some(message),
     }))
 }
 
+fn conditions_response(conditions: &NetworkConditions) -> NetworkConditionsResponse {
+    NetworkConditionsResponse {
+        packet_loss: conditions.packet_loss,
+        latency_ms: conditions.latency_ms,
+        bandwidth_kbps: conditions.bandwidth_kbps,
+        jitter_ms: conditions.jitter_ms,
+        connection_dropped: conditions.connection_dropped,
+    }
+}
+
 pub async fn get_status(
     State(state): State<Arc<ApiState>>,
 ) -> ApiResult<Json<NetworkStatusResponse>> {
     let conditions = state.get_network_status().await;
+    let per_source = match &state.rtsp_server {
+        Some(server) => Some(
+            server
+                .read()
+                .await
+                .mount_conditions()
+                .iter()
+                .map(|(mount, conditions)| (mount.clone(), conditions_response(conditions)))
+                .collect(),
+        ),
+        None => None,
+    };
 
     if let Some(conditions) = conditions {
         Ok(Json(NetworkStatusResponse {
             active: true,
             profile: None,
-            conditions: NetworkConditionsResponse {
-                packet_loss: conditions.packet_loss,
-                latency_ms: conditions.latency_ms,
-                bandwidth_kbps: conditions.bandwidth_kbps,
-                jitter_ms: conditions.jitter_ms,
-                connection_dropped: conditions.connection_dropped,
-            },
-            per_source: None,
+            conditions: conditions_response(&conditions),
+            per_source,
         }))
     } else {
         Ok(Json(NetworkStatusResponse {
@@ -134,7 +169,7 @@ pub async fn get_status(
                 jitter_ms: 0,
                 connection_dropped: false,
             },
-            per_source: None,
+            per_source,
         }))
     }
 }
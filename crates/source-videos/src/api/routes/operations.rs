@@ -1,6 +1,16 @@
 use crate::api::{ApiError, ApiResult, ApiState, models::*};
-use crate::{DirectoryConfig, DirectoryScanner, FilterConfig, TestPattern, generate_test_file};
-use axum::{Json, extract::State};
+use crate::config::{FileContainer, VideoSourceType};
+use crate::operation::{Operation, OperationStatus};
+use crate::watch::events::EVENT_TYPES;
+use crate::{
+    DirectoryConfig, DirectoryScanner, FileGenerator, FilterConfig, StorageManager, TestPattern,
+    VideoSourceConfig, WatcherManager, WatcherStatus, generate_test_file,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -27,6 +37,16 @@ pub async fn scan_directory(
     State(state): State<Arc<ApiState>>,
     Json(req): Json<ScanDirectoryRequest>,
 ) -> ApiResult<Json<ScanDirectoryResponse>> {
+    let response = scan(&state, req, None)
+        .map_err(|e| ApiError::internal(format!("Failed to scan directory: {}", e)))?;
+    Ok(Json(response))
+}
+
+fn scan(
+    state: &ApiState,
+    req: ScanDirectoryRequest,
+    operation: Option<&Operation>,
+) -> crate::Result<ScanDirectoryResponse> {
     let filters = if !req.include.is_empty() || !req.exclude.is_empty() {
         Some(FilterConfig {
             include: req.include,
@@ -45,41 +65,130 @@ pub async fn scan_directory(
         mount_prefix: None,
     };
 
+    // In the background, sources are added as their files are found
     let mut scanner = DirectoryScanner::new(dir_config);
-    let source_configs = scanner
-        .scan()
-        .map_err(|e| ApiError::internal(format!("Failed to scan directory: {}", e)))?;
+    let source_configs: Box<dyn Iterator<Item = VideoSourceConfig>> = match operation {
+        Some(_) => Box::new(scanner.scan_stream()?),
+        None => Box::new(scanner.scan()?.into_iter()),
+    };
 
-    let found_count = source_configs.len();
+    let mut found_count = 0;
     let mut added_count = 0;
     let mut sources = Vec::new();
 
-    if req.add_to_server {
-        for config in source_configs {
-            match state.source_manager.add_source(config.clone()) {
-                Ok(id) => {
-                    added_count += 1;
-                    sources.push(SourceResponse {
-                        id,
-                        name: config.name.clone(),
-                        uri: format!("file://{}", config.name),
-                        state: "ready".to_string(),
-                        source_type: "file".to_string(),
-                        created_at: Some(chrono::Utc::now().to_rfc3339()),
-                        metadata: None,
-                    });
-                }
-                Err(_) => {
-                    // Skip files that couldn't be added
-                }
+    for config in source_configs {
+        found_count += 1;
+        if let Some(operation) = operation {
+            operation.check_cancelled()?;
+            operation.set_message(format!("{} files found", found_count));
+        }
+        if !req.add_to_server {
+            continue;
+        }
+        match state.source_manager.add_source(config.clone()) {
+            Ok(id) => {
+                added_count += 1;
+                sources.push(SourceResponse {
+                    id,
+                    name: config.name.clone(),
+                    uri: format!("file://{}", config.name),
+                    state: "ready".to_string(),
+                    source_type: "file".to_string(),
+                    created_at: Some(chrono::Utc::now().to_rfc3339()),
+                    metadata: None,
+                });
+            }
+            Err(_) => {
+                // Skip files that couldn't be added
             }
         }
     }
 
-    Ok(Json(ScanDirectoryResponse {
+    Ok(ScanDirectoryResponse {
         found_count,
         added_count,
         sources,
+    })
+}
+
+/// Scan in the background; poll `/operations/{id}` for the result
+pub async fn start_scan(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<ScanDirectoryRequest>,
+) -> ApiResult<(StatusCode, Json<OperationStatus>)> {
+    let worker = state.clone();
+    let operation = state.operations.spawn("scan", move |operation| {
+        let response = scan(&worker, req, Some(operation))?;
+        Ok(serde_json::to_value(response).unwrap_or_default())
+    });
+    Ok((StatusCode::ACCEPTED, Json(operation.status())))
+}
+
+/// Generate a file in the background with the requested resolution and
+/// framerate; poll `/operations/{id}` for progress
+pub async fn start_generate(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<GenerateVideoRequest>,
+) -> ApiResult<(StatusCode, Json<OperationStatus>)> {
+    crate::markers::parse_pattern(&req.pattern)
+        .map_err(|e| ApiError::bad_request(format!("Invalid pattern: {}", e)))?;
+
+    let mut config = VideoSourceConfig::test_pattern("api-gen", &req.pattern);
+    config.duration = Some(req.duration);
+    config.resolution.width = req.resolution.width;
+    config.resolution.height = req.resolution.height;
+    config.framerate.numerator = req.framerate.numerator;
+    config.framerate.denominator = req.framerate.denominator;
+    config.source_type = VideoSourceType::File {
+        path: req.output.clone(),
+        container: FileContainer::Mp4,
+    };
+
+    let storage = state.current_config.read().await.storage.clone();
+    let operation = state.operations.spawn("generate", move |operation| {
+        let mut generator = FileGenerator::new(config, &req.output).with_operation(operation);
+        if let Some(storage) = storage {
+            let manager = StorageManager::for_output(std::path::Path::new(&req.output), storage)?;
+            generator = generator.with_storage(Arc::new(manager));
+        }
+        generator.generate()?;
+        Ok(serde_json::json!({ "output": req.output }))
+    });
+    Ok((StatusCode::ACCEPTED, Json(operation.status())))
+}
+
+pub async fn list_operations(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<Json<Vec<OperationStatus>>> {
+    Ok(Json(state.operations.list()))
+}
+
+pub async fn get_operation(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<OperationStatus>> {
+    state
+        .get_operation_status(&id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Operation {} not found", id)))
+}
+
+pub async fn cancel_operation(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<SuccessResponse>> {
+    if state.get_operation_status(&id).is_none() {
+        return Err(ApiError::not_found(format!("Operation {} not found", id)));
+    }
+    if !state.operations.cancel(&id) {
+        return Err(ApiError::conflict(format!(
+            "Operation {} has already finished",
+            id
+        )));
+    }
+    Ok(Json(SuccessResponse {
+        success: true,
+        message: Some(format!("Cancelling operation {}", id)),
     }))
 }
 
@@ -108,10 +217,16 @@ pub async fn start_watching(
     let mut watcher_manager = state.watcher_manager.write().await;
     let path = PathBuf::from(&req.directory);
 
-    let watcher_id = watcher_manager
-        .add_directory_watcher(&path, req.recursive)
-        .await
-        .map_err(|e| ApiError::internal(format!("Failed to start watching: {}", e)))?;
+    let watcher_id = if req.initial_sync {
+        watcher_manager
+            .add_synced_directory_watcher(&path, req.recursive)
+            .await
+    } else {
+        watcher_manager
+            .add_directory_watcher(&path, req.recursive)
+            .await
+    }
+    .map_err(|e| ApiError::internal(format!("Failed to start watching: {}", e)))?;
 
     Ok(Json(SuccessResponse {
         success: true,
@@ -124,7 +239,10 @@ pub async fn start_watching(
 
 pub async fn stop_watching(State(state): State<Arc<ApiState>>) -> ApiResult<Json<SuccessResponse>> {
     let mut watcher_manager = state.watcher_manager.write().await;
-    watcher_manager.stop_all();
+    watcher_manager
+        .stop_all()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to stop watching: {}", e)))?;
 
     Ok(Json(SuccessResponse {
         success: true,
@@ -136,21 +254,83 @@ pub async fn watch_status(
     State(state): State<Arc<ApiState>>,
 ) -> ApiResult<Json<WatchStatusResponse>> {
     let watcher_manager = state.watcher_manager.read().await;
+    let watchers = watcher_manager.statuses();
 
-    // Check if any watchers are active
-    let active = true; // Simplified - would check actual watcher state
-
-    // Get watcher info
-    let watchers = if active {
-        vec![WatcherInfo {
-            id: "default".to_string(),
-            directory: ".".to_string(),
-            recursive: true,
-            events_received: 0,
-        }]
-    } else {
-        vec![]
-    };
+    Ok(Json(WatchStatusResponse {
+        active: watchers.iter().any(|w| w.watching),
+        watchers,
+    }))
+}
+
+fn watcher_status(watcher_manager: &WatcherManager, id: &str) -> ApiResult<Json<WatcherStatus>> {
+    watcher_manager
+        .statuses()
+        .into_iter()
+        .find(|w| w.id == id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No watcher with ID {}", id)))
+}
+
+pub async fn get_watcher(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WatcherStatus>> {
+    watcher_status(&*state.watcher_manager.read().await, &id)
+}
 
-    Ok(Json(WatchStatusResponse { active, watchers }))
+pub async fn pause_watcher(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WatcherStatus>> {
+    let mut watcher_manager = state.watcher_manager.write().await;
+    watcher_manager
+        .pause_watcher(&id)
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    watcher_status(&watcher_manager, &id)
+}
+
+pub async fn resume_watcher(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WatcherStatus>> {
+    let mut watcher_manager = state.watcher_manager.write().await;
+    watcher_manager
+        .resume_watcher(&id)
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    watcher_status(&watcher_manager, &id)
+}
+
+pub async fn set_watcher_filter(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+    Json(req): Json<WatchFilterRequest>,
+) -> ApiResult<Json<WatcherStatus>> {
+    if let Some(unknown) = req
+        .event_types
+        .iter()
+        .find(|t| !EVENT_TYPES.contains(&t.to_lowercase().as_str()))
+    {
+        return Err(ApiError::validation(format!(
+            "Unknown event type '{}', expected one of {}",
+            unknown,
+            EVENT_TYPES.join(", ")
+        )));
+    }
+
+    let mut watcher_manager = state.watcher_manager.write().await;
+    watcher_manager
+        .set_filter(&id, Some(req.to_filter()))
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    watcher_status(&watcher_manager, &id)
+}
+
+pub async fn clear_watcher_filter(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<WatcherStatus>> {
+    let mut watcher_manager = state.watcher_manager.write().await;
+    watcher_manager
+        .set_filter(&id, None)
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    watcher_status(&watcher_manager, &id)
 }
@@ -0,0 +1,78 @@
+use crate::api::{
+    ApiError, ApiResult, ApiState,
+    models::{SetPatternRequest, SuccessResponse},
+};
+use crate::rotation::{PatternController, PatternStatus, RotationSchedule};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use std::sync::Arc;
+
+async fn controller(state: &ApiState) -> ApiResult<Arc<PatternController>> {
+    let server = state
+        .rtsp_server
+        .as_ref()
+        .ok_or_else(|| ApiError::service_unavailable("RTSP server not running"))?;
+    Ok(server.read().await.pattern_controller())
+}
+
+fn status(controller: &PatternController, id: &str) -> ApiResult<Json<PatternStatus>> {
+    controller
+        .status(id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No test pattern source '{}'", id)))
+}
+
+pub async fn list_patterns(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<Json<Vec<PatternStatus>>> {
+    Ok(Json(controller(&state).await?.statuses()))
+}
+
+pub async fn get_pattern(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<PatternStatus>> {
+    status(&*controller(&state).await?, &id)
+}
+
+pub async fn set_pattern(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+    Json(req): Json<SetPatternRequest>,
+) -> ApiResult<Json<PatternStatus>> {
+    let controller = controller(&state).await?;
+    controller.set_pattern(&id, &req.pattern)?;
+    status(&controller, &id)
+}
+
+pub async fn set_rotation(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+    Json(schedule): Json<RotationSchedule>,
+) -> ApiResult<Json<PatternStatus>> {
+    let controller = controller(&state).await?;
+    controller.set_schedule(&id, schedule)?;
+    status(&controller, &id)
+}
+
+pub async fn clear_rotation(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<SuccessResponse>> {
+    controller(&state).await?.clear_schedule(&id)?;
+    Ok(Json(SuccessResponse {
+        success: true,
+        message: Some(format!("Stopped pattern rotation for {}", id)),
+    }))
+}
+
+pub async fn next_pattern(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<PatternStatus>> {
+    let controller = controller(&state).await?;
+    controller.advance(&id)?;
+    status(&controller, &id)
+}
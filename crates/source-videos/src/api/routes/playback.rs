@@ -0,0 +1,47 @@
+use crate::api::{ApiError, ApiResult, ApiState, models::PlaybackRequest};
+use crate::{PlaybackStatus, RtspServer};
+use axum::{Json, extract::State};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+fn server(state: &ApiState) -> ApiResult<&Arc<RwLock<RtspServer>>> {
+    state.rtsp_server.as_ref().ok_or_else(|| {
+        ApiError::service_unavailable("Playback control needs a running RTSP server")
+    })
+}
+
+pub async fn playback_status(
+    State(state): State<Arc<ApiState>>,
+) -> ApiResult<Json<Vec<PlaybackStatus>>> {
+    Ok(Json(server(&state)?.read().await.playback_status()))
+}
+
+pub async fn pause(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<PlaybackRequest>,
+) -> ApiResult<Json<PlaybackStatus>> {
+    Ok(Json(server(&state)?.read().await.pause_mount(&req.mount)?))
+}
+
+pub async fn resume(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<PlaybackRequest>,
+) -> ApiResult<Json<PlaybackStatus>> {
+    Ok(Json(server(&state)?.read().await.resume_mount(&req.mount)?))
+}
+
+pub async fn step(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<PlaybackRequest>,
+) -> ApiResult<Json<PlaybackStatus>> {
+    let frames = req.frames.unwrap_or(1);
+    if frames == 0 {
+        return Err(ApiError::bad_request("frames must be at least 1"));
+    }
+    Ok(Json(
+        server(&state)?
+            .read()
+            .await
+            .step_mount(&req.mount, frames)?,
+    ))
+}
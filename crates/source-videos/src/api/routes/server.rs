@@ -5,6 +5,9 @@ use crate::api::{
         SuccessResponse,
     },
 };
+use crate::bus::SystemEvent;
+use crate::ports::{BoundEndpoint, BoundPorts};
+use crate::rtsp::transport::TransportReport;
 use crate::{RtspServerBuilder, VideoSourceConfig, VideoSourceType};
 use axum::{Json, extract::State};
 use std::sync::Arc;
@@ -25,8 +28,8 @@ pub async fn start_server(
 
         return Ok(Json(ServerStatusResponse {
             running: true,
-            port: Some(req.port),
-            address: Some(req.address.clone()),
+            port: Some(server.get_port()),
+            address: Some(server.get_address().to_string()),
             source_count: urls.len(),
             uptime_seconds: None,
             urls,
@@ -34,7 +37,10 @@ pub async fn start_server(
     }
 
     // Create and start new server
-    let mut builder = RtspServerBuilder::new().port(req.port);
+    let mut builder = RtspServerBuilder::new()
+        .port(req.port)
+        .port_retries(req.port_retries)
+        .address(req.address.clone());
 
     // Apply network profile if specified
     if let Some(profile_str) = &req.network_profile {
@@ -89,6 +95,7 @@ pub async fn start_server(
             duration: source_req.duration,
             num_buffers: None,
             is_live: source_req.is_live,
+            tags: source_req.tags.clone(),
         };
 
         builder = builder.add_source(config);
@@ -101,6 +108,10 @@ pub async fn start_server(
     server
         .start()
         .map_err(|e| ApiError::internal(format!("Failed to start RTSP server: {}", e)))?;
+    state.events.publish(SystemEvent::ServerStarted {
+        address: server.get_address().to_string(),
+        port: server.get_port(),
+    });
 
     let urls = server
         .list_sources()
@@ -116,7 +127,7 @@ pub async fn start_server(
 
     Ok(Json(ServerStatusResponse {
         running: true,
-        port: Some(req.port),
+        port: Some(server_arc.read().await.get_port()),
         address: Some(req.address),
         source_count: urls.len(),
         uptime_seconds: Some(0),
@@ -125,12 +136,16 @@ pub async fn start_server(
 }
 
 pub async fn stop_server(State(state): State<Arc<ApiState>>) -> ApiResult<Json<SuccessResponse>> {
-    if state.rtsp_server.is_none() {
+    let Some(server) = &state.rtsp_server else {
         return Err(ApiError::not_found("RTSP server is not running"));
-    }
+    };
 
-    // In a real implementation, you'd properly stop the server here
-    // For now, we'll just indicate success
+    let server = server.read().await;
+    server.stop()?;
+    state.events.publish(SystemEvent::ServerStopped {
+        address: server.get_address().to_string(),
+        port: server.get_port(),
+    });
 
     Ok(Json(SuccessResponse {
         success: true,
@@ -172,8 +187,8 @@ pub async fn server_status(
 
         Ok(Json(ServerStatusResponse {
             running: true,
-            port: Some(8554), // Would need to get from server config
-            address: Some("0.0.0.0".to_string()),
+            port: Some(server.get_port()),
+            address: Some(server.get_address().to_string()),
             source_count: urls.len(),
             uptime_seconds: None,
             urls,
@@ -191,8 +206,25 @@ pub async fn server_status(
 }
 
 pub async fn server_info(
-    State(_state): State<Arc<ApiState>>,
+    State(state): State<Arc<ApiState>>,
 ) -> ApiResult<Json<ServerInfoResponse>> {
+    let mut ports = BoundPorts::default();
+    if let Some(rtsp_server) = &state.rtsp_server {
+        let server = rtsp_server.read().await;
+        if server.is_attached() {
+            ports.rtsp = Some(BoundEndpoint {
+                address: server.get_address().to_string(),
+                port: server.get_port(),
+            });
+        }
+    }
+    if let Some(address) = *state.api_address.read().await {
+        ports.api = Some(BoundEndpoint {
+            address: address.ip().to_string(),
+            port: address.port(),
+        });
+    }
+
     Ok(Json(ServerInfoResponse {
         version: env!("CARGO_PKG_VERSION").to_string(),
         capabilities: vec![
@@ -210,6 +242,7 @@ pub async fn server_info(
             "mov".to_string(),
         ],
         max_sources: None,
+        ports,
     }))
 }
 
@@ -227,3 +260,11 @@ pub async fn list_urls(State(state): State<Arc<ApiState>>) -> ApiResult<Json<Vec
         Ok(Json(vec![]))
     }
 }
+
+/// Transports negotiated by the RTSP sessions of connected clients
+pub async fn list_sessions(State(state): State<Arc<ApiState>>) -> ApiResult<Json<TransportReport>> {
+    let Some(rtsp_server) = &state.rtsp_server else {
+        return Err(ApiError::not_found("RTSP server is not running"));
+    };
+    Ok(Json(rtsp_server.read().await.transport_log().report()))
+}
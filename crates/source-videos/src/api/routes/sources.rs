@@ -5,13 +5,18 @@ use crate::api::{
         SourceResponse, SourceTypeRequest, SuccessResponse, UpdateSourceRequest,
     },
 };
-use crate::{VideoSourceConfig, VideoSourceType};
+use crate::manager::sync_point;
+use crate::runtime::events::ConfigurationEvent;
+use crate::{SyncedStart, VideoSourceConfig, VideoSourceType};
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::header,
     response::IntoResponse,
 };
+use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub async fn list_sources(
@@ -36,6 +41,92 @@ pub async fn get_source(
     Ok(Json(SourceResponse::from(source)))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SyncedStartQuery {
+    /// Time from the request to the shared start, for the pipelines to
+    /// get ready
+    #[serde(default = "default_sync_delay_ms")]
+    pub delay_ms: u64,
+}
+
+fn default_sync_delay_ms() -> u64 {
+    200
+}
+
+/// Restart the sources and RTSP mounts tagged `tag` with one shared base
+/// time
+pub async fn start_group_synced(
+    State(state): State<Arc<ApiState>>,
+    Path(tag): Path<String>,
+    Query(query): Query<SyncedStartQuery>,
+) -> ApiResult<Json<SyncedStart>> {
+    let (clock, base_time) = sync_point(Duration::from_millis(query.delay_ms));
+
+    // State changes block, and the manager's lock is not an async one
+    let manager = state.source_manager.clone();
+    let sources = {
+        let (tag, clock) = (tag.clone(), clock.clone());
+        tokio::task::spawn_blocking(move || manager.start_group_at(&tag, &clock, base_time))
+            .await
+            .map_err(|e| ApiError::internal(format!("Synced start failed: {}", e)))??
+    };
+    let mounts = match &state.rtsp_server {
+        Some(server) => server.read().await.sync_group(&tag, &clock, base_time),
+        None => Vec::new(),
+    };
+    if sources.is_empty() && mounts.is_empty() {
+        return Err(ApiError::not_found(format!("No sources tagged '{}'", tag)));
+    }
+
+    Ok(Json(SyncedStart {
+        tag,
+        base_time_ns: base_time.nseconds(),
+        sources,
+        mounts,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewQuery {
+    pub width: Option<u32>,
+}
+
+/// Current frame of a running source as a JPEG
+pub async fn preview_source(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<String>,
+    Query(query): Query<PreviewQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let source = state.source_manager.get_source(&id)?;
+    let pipeline = state.source_manager.get_pipeline(&source.id)?;
+    let width = state.previews.width_for(query.width);
+
+    let jpeg = match state.previews.reserve(&source.id, width) {
+        Ok(Some(jpeg)) => jpeg,
+        Ok(None) => {
+            let previews = state.previews.clone();
+            tokio::task::spawn_blocking(move || previews.encode(&source.id, &pipeline, width))
+                .await
+                .map_err(|e| ApiError::internal(format!("Preview task failed: {}", e)))??
+        }
+        Err(wait) => {
+            return Err(ApiError::too_many_requests(format!(
+                "Preview of {} was just encoded at another width; retry in {}ms",
+                id,
+                wait.as_millis().max(1)
+            )));
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        jpeg.as_ref().clone(),
+    ))
+}
+
 pub async fn add_source(
     State(state): State<Arc<ApiState>>,
     Json(req): Json<AddSourceRequest>,
@@ -67,9 +158,13 @@ pub async fn add_source(
         duration: req.duration,
         num_buffers: None,
         is_live: req.is_live,
+        tags: req.tags.clone(),
     };
 
     let source_id = state.source_manager.add_source(config)?;
+    state.events.publish(ConfigurationEvent::SourceAdded {
+        source: req.name.clone(),
+    });
 
     // If RTSP server is running, add the source to it as well
     if let Some(rtsp_server) = &state.rtsp_server {
@@ -99,7 +194,13 @@ pub async fn remove_source(
     }
 
     // Remove from source manager
+    if let Ok(source) = state.source_manager.get_source(&id) {
+        state.previews.forget(&source.id);
+    }
     state.source_manager.remove_source(&id)?;
+    state
+        .events
+        .publish(ConfigurationEvent::SourceRemoved { source: id.clone() });
 
     Ok(Json(SuccessResponse {
         success: true,
@@ -119,10 +220,52 @@ pub async fn update_source(
         .find(|s| s.id == id || s.name == id)
         .ok_or_else(|| ApiError::not_found(format!("Source '{}' not found", id)))?;
 
-    // For now, we'll return the existing source since update isn't fully implemented
-    // In a full implementation, you'd update the source properties here
+    let mut config = state.source_manager.get_source_config(&existing.id)?;
+    if let Some(name) = req.name {
+        config.name = name;
+    }
+    if let Some(resolution) = req.resolution {
+        config.resolution = resolution;
+    }
+    if let Some(framerate) = req.framerate {
+        config.framerate = framerate;
+    }
+    if let Some(format) = req.format {
+        config.format = format;
+    }
+    if let Some(new_pattern) = req.pattern {
+        match &mut config.source_type {
+            VideoSourceType::TestPattern { pattern } => *pattern = new_pattern,
+            _ => {
+                return Err(ApiError::bad_request(
+                    "Only test pattern sources have a pattern",
+                ));
+            }
+        }
+    }
+
+    state
+        .source_manager
+        .update_source(&existing.id, config.clone())?;
+    state.events.publish(ConfigurationEvent::SourceUpdated {
+        source: config.name.clone(),
+    });
+
+    // Keep a served mount for this source in step, at the same mount point
+    if let Some(rtsp_server) = &state.rtsp_server {
+        let mut server = rtsp_server.write().await;
+        let mount_point = format!("/{}", existing.name);
+        if server.list_sources().contains(&mount_point) {
+            server.update_source(&mount_point, config.clone())?;
+        }
+    }
+
+    let source = state
+        .source_manager
+        .get_source(&config.name)
+        .map_err(|_| ApiError::internal("Failed to retrieve updated source"))?;
 
-    Ok(Json(SourceResponse::from(existing.clone())))
+    Ok(Json(SourceResponse::from(source)))
 }
 
 pub async fn batch_operations(
@@ -177,6 +320,7 @@ pub async fn batch_operations(
                     duration: operation.source.duration,
                     num_buffers: None,
                     is_live: operation.source.is_live,
+                    tags: operation.source.tags.clone(),
                 };
 
                 match state.source_manager.add_source(config) {
@@ -1,8 +1,13 @@
+use crate::api::metrics::RequestMetrics;
+use crate::bus::{EventHub, SystemEvent};
+use crate::operation::{OperationRegistry, OperationStatus};
+use crate::preview::PreviewCache;
 use crate::{
-    AppConfig, RtspServer, VideoSourceManager, WatcherManager,
+    AppConfig, ChaosController, RtspServer, ServerFarm, VideoSourceManager, WatchEventCounts,
+    WatcherManager,
     network::{GStreamerNetworkSimulator, NetworkConditions, NetworkController, NetworkProfile},
 };
-use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -13,27 +18,23 @@ pub struct ApiState {
     pub watcher_manager: Arc<RwLock<WatcherManager>>,
     pub network_simulator: Arc<RwLock<Option<GStreamerNetworkSimulator>>>,
     pub current_config: Arc<RwLock<AppConfig>>,
-    pub operation_status: Arc<RwLock<HashMap<String, OperationStatus>>>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct OperationStatus {
-    pub id: String,
-    pub operation: String,
-    pub status: Status,
-    pub started_at: String,
-    pub completed_at: Option<String>,
-    pub result: Option<serde_json::Value>,
-    pub error: Option<String>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Status {
-    Pending,
-    Running,
-    Completed,
-    Failed,
+    /// Background generation and scans started through the API
+    pub operations: Arc<OperationRegistry>,
+    pub farm: Option<Arc<ServerFarm>>,
+    /// Address the control API is listening on, once bound
+    pub api_address: Arc<RwLock<Option<SocketAddr>>>,
+    /// Events from every subsystem, served on `/events`
+    pub events: Arc<EventHub>,
+    /// Recent JPEG previews of sources, served on `/sources/{id}/preview`
+    pub previews: Arc<PreviewCache>,
+    /// Latencies of API requests, served on `/metrics`
+    pub requests: Arc<RequestMetrics>,
+    /// Events received by the watcher manager, served on `/metrics`
+    /// `None` when the watcher manager was locked at startup; `/metrics`
+    /// then leaves the watcher counts out rather than reporting zeros
+    pub watch_events: Option<Arc<WatchEventCounts>>,
+    /// Random faults on the RTSP server's mounts, when there is a server
+    pub chaos: Option<Arc<ChaosController>>,
 }
 
 impl ApiState {
@@ -42,16 +43,55 @@ impl ApiState {
         source_manager: Arc<VideoSourceManager>,
         watcher_manager: Arc<RwLock<WatcherManager>>,
     ) -> Self {
+        let events = Arc::new(EventHub::new());
+        if tokio::runtime::Handle::try_current().is_ok() {
+            events.bridge(source_manager.get_event_bus().subscribe());
+        }
+        // Taken now, so `/metrics` reads them without locking the manager
+        let watch_events = watcher_manager
+            .try_read()
+            .map(|manager| manager.event_counts())
+            .ok();
+        if watch_events.is_none() {
+            log::warn!("Watcher manager busy; watcher events are left out of /metrics");
+        }
+
+        let chaos = rtsp_server
+            .as_ref()
+            .map(|server| ChaosController::new(server.clone(), None));
+
         Self {
             rtsp_server,
             source_manager,
             watcher_manager,
             network_simulator: Arc::new(RwLock::new(None)),
             current_config: Arc::new(RwLock::new(AppConfig::default())),
-            operation_status: Arc::new(RwLock::new(HashMap::new())),
+            operations: Arc::new(OperationRegistry::new()),
+            farm: None,
+            api_address: Arc::new(RwLock::new(None)),
+            events,
+            previews: Arc::new(PreviewCache::default()),
+            requests: Arc::new(RequestMetrics::default()),
+            watch_events,
+            chaos,
         }
     }
 
+    pub fn with_previews(mut self, previews: Arc<PreviewCache>) -> Self {
+        self.previews = previews;
+        self
+    }
+
+    pub fn with_chaos(mut self, chaos: Arc<ChaosController>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    pub fn with_farm(mut self, farm: Arc<ServerFarm>) -> Self {
+        self.farm = Some(farm);
+        self
+    }
+
     pub async fn get_or_create_rtsp_server(
         &self,
         port: u16,
@@ -66,42 +106,8 @@ impl ApiState {
         ))
     }
 
-    pub async fn track_operation(&self, id: String, operation: String) -> String {
-        let status = OperationStatus {
-            id: id.clone(),
-            operation,
-            status: Status::Running,
-            started_at: chrono::Utc::now().to_rfc3339(),
-            completed_at: None,
-            result: None,
-            error: None,
-        };
-
-        self.operation_status
-            .write()
-            .await
-            .insert(id.clone(), status);
-        id
-    }
-
-    pub async fn complete_operation(&self, id: &str, result: serde_json::Value) {
-        if let Some(status) = self.operation_status.write().await.get_mut(id) {
-            status.status = Status::Completed;
-            status.completed_at = Some(chrono::Utc::now().to_rfc3339());
-            status.result = Some(result);
-        }
-    }
-
-    pub async fn fail_operation(&self, id: &str, error: String) {
-        if let Some(status) = self.operation_status.write().await.get_mut(id) {
-            status.status = Status::Failed;
-            status.completed_at = Some(chrono::Utc::now().to_rfc3339());
-            status.error = Some(error);
-        }
-    }
-
-    pub async fn get_operation_status(&self, id: &str) -> Option<OperationStatus> {
-        self.operation_status.read().await.get(id).cloned()
+    pub fn get_operation_status(&self, id: &str) -> Option<OperationStatus> {
+        self.operations.get(id).map(|op| op.status())
     }
 
     pub async fn apply_network_profile(
@@ -118,6 +124,10 @@ impl ApiState {
             sim.apply_profile(profile);
         }
 
+        self.events.publish(SystemEvent::NetworkChanged {
+            profile: profile.to_string(),
+            source: None,
+        });
         Ok(())
     }
 
@@ -135,9 +145,51 @@ impl ApiState {
             sim.apply_conditions(conditions);
         }
 
+        self.events.publish(SystemEvent::NetworkChanged {
+            profile: "custom".to_string(),
+            source: None,
+        });
         Ok(())
     }
 
+    /// Change the conditions of running RTSP mounts in place: those matched
+    /// by `sources`, as mount points or source names, or else all of them.
+    /// Returns the mounts changed.
+    pub async fn apply_mount_network_conditions(
+        &self,
+        sources: Option<&[String]>,
+        conditions: NetworkConditions,
+    ) -> Result<Vec<String>, crate::SourceVideoError> {
+        let server = self
+            .rtsp_server
+            .as_ref()
+            .ok_or_else(|| crate::SourceVideoError::server("No RTSP server is running"))?;
+        let server = server.read().await;
+
+        let mut mounts = match sources {
+            Some(sources) => sources
+                .iter()
+                .map(|source| {
+                    server
+                        .resolve_mount(source)
+                        .ok_or_else(|| crate::SourceVideoError::SourceNotFound(source.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => server.list_sources(),
+        };
+        mounts.sort();
+        mounts.dedup();
+
+        for mount in &mounts {
+            server.set_mount_conditions(mount, conditions.clone())?;
+            self.events.publish(SystemEvent::NetworkChanged {
+                profile: "custom".to_string(),
+                source: Some(mount.clone()),
+            });
+        }
+        Ok(mounts)
+    }
+
     pub async fn reset_network(&self) -> Result<(), crate::SourceVideoError> {
         let mut sim_guard = self.network_simulator.write().await;
 
@@ -145,6 +197,7 @@ impl ApiState {
             sim.reset();
         }
 
+        self.events.publish(SystemEvent::NetworkReset);
         Ok(())
     }
 
@@ -236,6 +236,20 @@ impl VideoSource for LoopingVideoSource {
     fn get_pipeline(&self) -> Option<&gst::Pipeline> {
         self.inner_source.get_pipeline()
     }
+
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        let pipeline = self.inner_source.synced_pipeline()?;
+        self.setup_loop_handling()?;
+
+        if let Ok(mut looping) = self.is_looping.lock() {
+            *looping = true;
+        }
+        Ok(pipeline)
+    }
+
+    fn mark_playing(&mut self) {
+        self.inner_source.mark_playing()
+    }
 }
 
 pub struct GaplessLooper {
@@ -0,0 +1,215 @@
+//! A/V sync test signal served over RTSP
+//!
+//! The `avsync` test pattern is a black picture that flashes white and a
+//! silent audio track that beeps at the same running time, once per
+//! [`AvSyncSignal::period`]. The media has two streams: H.264 video on
+//! `pay0` and PCMU audio on `pay1`. A receiver that notes when each flash
+//! and each beep reaches its sinks gets the A/V offset of its pipeline and
+//! how it drifts, as in broadcast lip-sync tests; `ds bench --avsync`
+//! does this.
+
+use crate::config_types::VideoSourceConfig;
+use crate::error::SourceVideoError;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+/// Test pattern name that serves the A/V sync signal
+pub const AVSYNC_PATTERN: &str = "avsync";
+
+/// PCMU only carries 8 kHz mono
+pub const SAMPLE_RATE: u32 = 8000;
+
+/// Audio buffer length in samples, 10 ms
+const SAMPLES_PER_BUFFER: u32 = 80;
+
+/// Luma of the flash, white in limited range
+const FLASH_LUMA: u8 = 235;
+
+pub fn is_avsync(pattern: &str) -> bool {
+    pattern.eq_ignore_ascii_case(AVSYNC_PATTERN)
+}
+
+/// Error for the pattern where a local pipeline or file would play it
+pub(crate) fn only_on_rtsp() -> SourceVideoError {
+    SourceVideoError::InvalidPattern(format!(
+        "{} is only served on RTSP mounts, e.g. with `serve --pattern {}`",
+        AVSYNC_PATTERN, AVSYNC_PATTERN
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvSyncSignal {
+    /// Time from one flash and beep to the next
+    pub period: Duration,
+    /// How long each flash and beep lasts
+    pub pulse: Duration,
+    pub tone_hz: f64,
+    /// Beep amplitude, from 0 to 1
+    pub volume: f64,
+}
+
+impl Default for AvSyncSignal {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(1),
+            pulse: Duration::from_millis(100),
+            tone_hz: 1000.0,
+            volume: 0.8,
+        }
+    }
+}
+
+impl AvSyncSignal {
+    /// Whether running time `time` falls in a flash and beep
+    pub fn is_pulse(&self, time: Duration) -> bool {
+        let period = self.period.as_nanos().max(1);
+        time.as_nanos() % period < self.pulse.as_nanos()
+    }
+
+    /// Audio samples starting at running time `start`: the tone during a
+    /// pulse, silence otherwise
+    pub fn fill_samples(&self, start: Duration, rate: u32, samples: &mut [i16]) {
+        let amplitude = self.volume.clamp(0.0, 1.0) * i16::MAX as f64;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let time = start + Duration::from_nanos(i as u64 * 1_000_000_000 / rate as u64);
+            *sample = if self.is_pulse(time) {
+                let phase = TAU * self.tone_hz * time.as_secs_f64();
+                (phase.sin() * amplitude) as i16
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Launch string for `config`; `network_sim` is put before the video
+    /// payloader as in other test patterns
+    pub fn launch_string(
+        &self,
+        config: &VideoSourceConfig,
+        bitrate_kbps: u32,
+        network_sim: &str,
+    ) -> String {
+        format!(
+            "( videotestsrc name=source pattern=black is-live=true ! \
+             video/x-raw,width={},height={},framerate={}/{},format=I420 ! \
+             identity name=flash ! videoconvert ! \
+             x264enc tune=zerolatency speed-preset=ultrafast bitrate={} ! \
+             {} \
+             rtph264pay name=pay0 pt=96 config-interval=1 \
+             audiotestsrc wave=silence is-live=true samplesperbuffer={} ! \
+             audio/x-raw,format=S16LE,rate={},channels=1 ! \
+             identity name=beep ! mulawenc ! rtppcmupay name=pay1 pt=0 )",
+            config.resolution.width,
+            config.resolution.height,
+            config.framerate.numerator,
+            config.framerate.denominator,
+            bitrate_kbps,
+            network_sim,
+            SAMPLES_PER_BUFFER,
+            SAMPLE_RATE
+        )
+    }
+
+    /// Draw the flashes and beeps into a media made from
+    /// [`launch_string`](Self::launch_string)
+    pub fn attach(&self, bin: &gst::Bin) {
+        let (Some(flash), Some(beep)) = (
+            bin.by_name("flash").and_then(|e| e.static_pad("src")),
+            bin.by_name("beep").and_then(|e| e.static_pad("src")),
+        ) else {
+            log::warn!("No flash or beep element in {}", bin.name());
+            return;
+        };
+
+        let signal = *self;
+        flash.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let time = info.buffer().and_then(|buffer| running_time(pad, buffer));
+            if !time.is_some_and(|time| signal.is_pulse(time)) {
+                return gst::PadProbeReturn::Ok;
+            }
+            let Some(video_info) = pad
+                .current_caps()
+                .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok())
+            else {
+                return gst::PadProbeReturn::Ok;
+            };
+            if let Some(buffer) = info.buffer_mut()
+                && let Ok(mut frame) = gst_video::VideoFrameRef::from_buffer_ref_writable(
+                    buffer.make_mut(),
+                    &video_info,
+                )
+                && let Ok(luma) = frame.plane_data_mut(0)
+            {
+                // Chroma of the black pattern is already neutral
+                luma.fill(FLASH_LUMA);
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+        beep.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(start) = info.buffer().and_then(|buffer| running_time(pad, buffer)) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            if let Some(buffer) = info.buffer_mut()
+                && let Ok(mut map) = buffer.make_mut().map_writable()
+            {
+                let mut samples = vec![0i16; map.len() / 2];
+                signal.fill_samples(start, SAMPLE_RATE, &mut samples);
+                for (bytes, sample) in map.chunks_exact_mut(2).zip(samples) {
+                    bytes.copy_from_slice(&sample.to_le_bytes());
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+/// Running time of a buffer in the segment on `pad`
+fn running_time(pad: &gst::Pad, buffer: &gst::BufferRef) -> Option<Duration> {
+    let pts = buffer.pts()?;
+    let event = pad.sticky_event::<gst::event::Segment>(0)?;
+    let segment = event.segment().downcast_ref::<gst::ClockTime>()?;
+    let running_time = segment.to_running_time(pts)?;
+    Some(Duration::from_nanos(running_time.nseconds()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_is_avsync() {
+        assert!(is_avsync("avsync"));
+        assert!(is_avsync("AVSync"));
+        assert!(!is_avsync("smpte"));
+    }
+
+    #[test]
+    fn test_pulses_line_up() {
+        let signal = AvSyncSignal::default();
+        assert!(signal.is_pulse(ms(0)));
+        assert!(signal.is_pulse(ms(99)));
+        assert!(!signal.is_pulse(ms(100)));
+        assert!(!signal.is_pulse(ms(999)));
+        assert!(signal.is_pulse(ms(3000)));
+    }
+
+    #[test]
+    fn test_fill_samples() {
+        // A buffer starting just before a pulse is silent up to it
+        let signal = AvSyncSignal::default();
+        let mut samples = [0i16; SAMPLES_PER_BUFFER as usize];
+        signal.fill_samples(ms(995), SAMPLE_RATE, &mut samples);
+        assert!(samples[..40].iter().all(|&s| s == 0));
+        assert!(samples[40..].iter().any(|&s| s != 0));
+        let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak as f64 <= 0.8 * i16::MAX as f64 + 1.0);
+    }
+}
@@ -0,0 +1,331 @@
+//! One event stream for every subsystem
+//!
+//! The source manager, runtime configuration and file watchers each keep
+//! their own [`EventBus`](crate::runtime::events::EventBus). An [`EventHub`]
+//! bridges those buses and takes events from the API and network layers
+//! directly, tagging each with a [`Topic`] so subscribers can pick the
+//! parts they care about. The control API serves the hub as server-sent
+//! events on `/api/v1/events`.
+
+use crate::error::SourceVideoError;
+use crate::runtime::events::ConfigurationEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+
+/// Events kept for late subscribers and `/events/recent`
+const RECENT_EVENTS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topic {
+    Sources,
+    Config,
+    Network,
+    Files,
+    Server,
+}
+
+impl Topic {
+    pub fn all() -> [Topic; 5] {
+        [
+            Self::Sources,
+            Self::Config,
+            Self::Network,
+            Self::Files,
+            Self::Server,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sources => "sources",
+            Self::Config => "config",
+            Self::Network => "network",
+            Self::Files => "files",
+            Self::Server => "server",
+        }
+    }
+
+    /// Parse a comma-separated topic list; empty means every topic
+    pub fn parse_list(list: &str) -> Result<HashSet<Topic>, SourceVideoError> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl FromStr for Topic {
+    type Err = SourceVideoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|topic| topic.name() == s.to_lowercase())
+            .ok_or_else(|| SourceVideoError::config(format!("Unknown event topic: {}", s)))
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum SystemEvent {
+    /// Anything from a configuration [`EventBus`](crate::runtime::events::EventBus)
+    Config(ConfigurationEvent),
+    SourceState {
+        source: String,
+        state: String,
+    },
+    NetworkChanged {
+        /// Profile name, or `custom` for explicit conditions
+        profile: String,
+        source: Option<String>,
+    },
+    NetworkReset,
+    ServerStarted {
+        address: String,
+        port: u16,
+    },
+    ServerStopped {
+        address: String,
+        port: u16,
+    },
+}
+
+impl SystemEvent {
+    pub fn topic(&self) -> Topic {
+        match self {
+            Self::Config(event) => match event {
+                ConfigurationEvent::SourceAdded { .. }
+                | ConfigurationEvent::SourceRemoved { .. }
+                | ConfigurationEvent::SourceUpdated { .. }
+                | ConfigurationEvent::SourceError { .. } => Topic::Sources,
+                ConfigurationEvent::FileSystemChange { .. } => Topic::Files,
+                _ => Topic::Config,
+            },
+            Self::SourceState { .. } => Topic::Sources,
+            Self::NetworkChanged { .. } | Self::NetworkReset => Topic::Network,
+            Self::ServerStarted { .. } | Self::ServerStopped { .. } => Topic::Server,
+        }
+    }
+}
+
+impl From<ConfigurationEvent> for SystemEvent {
+    fn from(event: ConfigurationEvent) -> Self {
+        Self::Config(event)
+    }
+}
+
+/// An event as delivered to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Increasing sequence number, for resuming after a reconnect
+    pub id: u64,
+    pub topic: Topic,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub event: SystemEvent,
+}
+
+pub struct EventHub {
+    sender: broadcast::Sender<Envelope>,
+    next_id: AtomicU64,
+    recent: Mutex<VecDeque<Envelope>>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        Self::with_capacity(1024)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            next_id: AtomicU64::new(1),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
+        }
+    }
+
+    pub fn publish(&self, event: impl Into<SystemEvent>) -> u64 {
+        let event = event.into();
+        let envelope = Envelope {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            topic: event.topic(),
+            timestamp: chrono::Utc::now(),
+            event,
+        };
+        let id = envelope.id;
+
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_EVENTS {
+                recent.pop_front();
+            }
+            recent.push_back(envelope.clone());
+        }
+        // No subscribers is fine; the event is still kept in `recent`
+        let _ = self.sender.send(envelope);
+        id
+    }
+
+    /// Subscribe to some topics; an empty set means all of them
+    pub fn subscribe(&self, topics: HashSet<Topic>) -> Subscription {
+        Subscription {
+            receiver: self.sender.subscribe(),
+            topics,
+        }
+    }
+
+    pub fn subscribe_all(&self) -> Subscription {
+        self.subscribe(HashSet::new())
+    }
+
+    /// Kept events on `topics` newer than `since`
+    pub fn recent(&self, topics: &HashSet<Topic>, since: u64) -> Vec<Envelope> {
+        self.recent
+            .lock()
+            .map(|recent| {
+                recent
+                    .iter()
+                    .filter(|envelope| envelope.id > since)
+                    .filter(|envelope| topics.is_empty() || topics.contains(&envelope.topic))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Forward everything from a configuration bus into the hub
+    pub fn bridge(
+        self: &std::sync::Arc<Self>,
+        mut receiver: broadcast::Receiver<ConfigurationEvent>,
+    ) -> tokio::task::JoinHandle<()> {
+        let hub = std::sync::Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => match hub.upgrade() {
+                        Some(hub) => {
+                            hub.publish(event);
+                        }
+                        None => break,
+                    },
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::warn!("Event bridge missed {} events", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Subscription {
+    receiver: broadcast::Receiver<Envelope>,
+    topics: HashSet<Topic>,
+}
+
+impl Subscription {
+    pub fn topics(&self) -> &HashSet<Topic> {
+        &self.topics
+    }
+
+    pub fn wants(&self, envelope: &Envelope) -> bool {
+        self.topics.is_empty() || self.topics.contains(&envelope.topic)
+    }
+
+    /// Next event on a subscribed topic; `None` once the hub is gone.
+    /// Events dropped because the subscriber fell behind are skipped.
+    pub async fn recv(&mut self) -> Option<Envelope> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(envelope) if self.wants(&envelope) => return Some(envelope),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Event subscriber missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    pub fn into_receiver(self) -> broadcast::Receiver<Envelope> {
+        self.receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::time::{Duration, timeout};
+
+    #[tokio::test]
+    async fn test_topic_subscription() {
+        let hub = Arc::new(EventHub::new());
+        let mut network = hub.subscribe(Topic::parse_list("network").unwrap());
+
+        hub.publish(ConfigurationEvent::SourceAdded {
+            source: "cam".to_string(),
+        });
+        hub.publish(SystemEvent::NetworkChanged {
+            profile: "3g".to_string(),
+            source: None,
+        });
+
+        let envelope = timeout(Duration::from_secs(1), network.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((envelope.id, envelope.topic), (2, Topic::Network));
+
+        let sources = Topic::parse_list("sources").unwrap();
+        assert_eq!(hub.recent(&sources, 0).len(), 1);
+        assert!(hub.recent(&HashSet::new(), 2).is_empty());
+        assert!(Topic::parse_list("sources,bogus").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bridge_config_bus() {
+        let hub = Arc::new(EventHub::new());
+        let bus = crate::runtime::events::EventBus::new();
+        let mut files = hub.subscribe(HashSet::from([Topic::Files]));
+        hub.bridge(bus.subscribe());
+
+        bus.emit(ConfigurationEvent::FileSystemChange {
+            event_type: "created".to_string(),
+            path: "a.mp4".into(),
+            source_id: None,
+            watcher_id: "w".to_string(),
+        })
+        .await;
+
+        let envelope = timeout(Duration::from_secs(1), files.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            envelope.event,
+            SystemEvent::Config(ConfigurationEvent::FileSystemChange { .. })
+        ));
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["topic"], "files");
+        assert_eq!(json["event"]["type"], "config");
+    }
+}
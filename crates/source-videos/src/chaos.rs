@@ -0,0 +1,589 @@
+//! Chaos mode: random faults on the RTSP server's mounts
+//!
+//! Every `interval_ms` each mount without a fault rolls for one: its media
+//! is dropped, its network profile degraded, it is unmounted, or its
+//! pipeline gets an EOS. Faults other than EOS are undone `fault_ms`
+//! later. Rolls come from a seeded generator, and every injection and
+//! recovery is appended to a timeline that can be saved as JSON lines and
+//! replayed, so a failure found by chance can be reproduced exactly.
+//!
+//! A seed gives the same faults as long as the server serves the same
+//! mounts; replaying a timeline does not depend on that.
+
+use crate::config_types::VideoSourceConfig;
+use crate::error::{Result, SourceVideoError};
+use crate::network::NetworkProfile;
+use crate::rtsp::RtspServer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How often a running chaos task applies due events
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// Seed of the fault generator; a random one is picked when not set
+    pub seed: Option<u64>,
+    /// Time between rolls
+    pub interval_ms: u64,
+    /// How long a fault lasts before it is undone
+    pub fault_ms: u64,
+    /// Chance per roll that a mount's media is dropped
+    pub drop: f64,
+    /// Chance per roll that a mount gets one of `profiles`
+    pub degrade: f64,
+    /// Chance per roll that a mount is unmounted
+    pub remove: f64,
+    /// Chance per roll that a mount's media gets an EOS
+    pub eos: f64,
+    /// Network profiles degraded mounts get
+    pub profiles: Vec<String>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            interval_ms: 5000,
+            fault_ms: 3000,
+            drop: 0.05,
+            degrade: 0.05,
+            remove: 0.02,
+            eos: 0.02,
+            profiles: vec![
+                "poor".to_string(),
+                "noisy".to_string(),
+                "satellite".to_string(),
+            ],
+        }
+    }
+}
+
+impl ChaosConfig {
+    pub fn validate(&self) -> Result<()> {
+        let chances = [self.drop, self.degrade, self.remove, self.eos];
+        if chances.iter().any(|p| !(0.0..=1.0).contains(p)) || chances.iter().sum::<f64>() > 1.0 {
+            return Err(SourceVideoError::config(
+                "Chaos probabilities must be between 0 and 1 and add up to at most 1",
+            ));
+        }
+        if self.interval_ms == 0 {
+            return Err(SourceVideoError::config("Chaos interval must not be 0"));
+        }
+        for profile in &self.profiles {
+            profile
+                .parse::<NetworkProfile>()
+                .map_err(SourceVideoError::config)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "fault", rename_all = "snake_case")]
+pub enum Fault {
+    /// Media discarded, as if the link went down
+    Drop,
+    /// Mount switched to a network profile while clients stay connected
+    Degrade { profile: String },
+    /// Mount taken away
+    Remove,
+    /// End of stream sent into the media; nothing to undo
+    Eos,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosAction {
+    Inject,
+    Recover,
+}
+
+/// One entry of a chaos timeline
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosEvent {
+    /// Time since chaos started
+    pub at_ms: u64,
+    pub mount: String,
+    pub action: ChaosAction,
+    #[serde(flatten)]
+    pub fault: Fault,
+}
+
+/// Seeded fault generator
+pub struct ChaosSchedule {
+    config: ChaosConfig,
+    rng: StdRng,
+    next_roll: u64,
+    /// Faulted mounts and when they recover
+    active: BTreeMap<String, (Fault, u64)>,
+}
+
+impl ChaosSchedule {
+    pub fn new(config: ChaosConfig, seed: u64) -> Self {
+        Self {
+            next_roll: config.interval_ms,
+            config,
+            rng: StdRng::seed_from_u64(seed),
+            active: BTreeMap::new(),
+        }
+    }
+
+    /// Events due by `now_ms`, in time order, rolling for the mounts
+    /// currently served
+    pub fn step(&mut self, now_ms: u64, mounts: &[String]) -> Vec<ChaosEvent> {
+        let mut events = Vec::new();
+        while self.next_roll <= now_ms {
+            let at = self.next_roll;
+            events.extend(self.recover_due(at));
+            events.extend(self.roll(at, mounts));
+            self.next_roll += self.config.interval_ms.max(1);
+        }
+        events.extend(self.recover_due(now_ms));
+        events
+    }
+
+    fn recover_due(&mut self, at: u64) -> Vec<ChaosEvent> {
+        let due: Vec<String> = self
+            .active
+            .iter()
+            .filter(|(_, (_, until))| *until <= at)
+            .map(|(mount, _)| mount.clone())
+            .collect();
+        let mut events: Vec<ChaosEvent> = due
+            .into_iter()
+            .filter_map(|mount| {
+                let (fault, until) = self.active.remove(&mount)?;
+                Some(ChaosEvent {
+                    at_ms: until,
+                    mount,
+                    action: ChaosAction::Recover,
+                    fault,
+                })
+            })
+            .collect();
+        events.sort_by_key(|event| event.at_ms);
+        events
+    }
+
+    fn roll(&mut self, at: u64, mounts: &[String]) -> Vec<ChaosEvent> {
+        let mut mounts = mounts.to_vec();
+        mounts.sort();
+
+        let mut events = Vec::new();
+        for mount in mounts {
+            if self.active.contains_key(&mount) {
+                continue;
+            }
+            let Some(fault) = self.pick() else {
+                continue;
+            };
+            if fault != Fault::Eos {
+                self.active
+                    .insert(mount.clone(), (fault.clone(), at + self.config.fault_ms));
+            }
+            events.push(ChaosEvent {
+                at_ms: at,
+                mount,
+                action: ChaosAction::Inject,
+                fault,
+            });
+        }
+        events
+    }
+
+    fn pick(&mut self) -> Option<Fault> {
+        let mut roll: f64 = self.rng.r#gen();
+        for (chance, fault) in [
+            (self.config.drop, Fault::Drop),
+            (
+                self.config.degrade,
+                Fault::Degrade {
+                    profile: String::new(),
+                },
+            ),
+            (self.config.remove, Fault::Remove),
+            (self.config.eos, Fault::Eos),
+        ] {
+            if roll >= chance {
+                roll -= chance;
+                continue;
+            }
+            if let Fault::Degrade { .. } = fault {
+                if self.config.profiles.is_empty() {
+                    return None;
+                }
+                let index = self.rng.gen_range(0..self.config.profiles.len());
+                return Some(Fault::Degrade {
+                    profile: self.config.profiles[index].clone(),
+                });
+            }
+            return Some(fault);
+        }
+        None
+    }
+}
+
+/// Read a timeline saved as JSON lines
+pub fn load_timeline(path: &Path) -> Result<Vec<ChaosEvent>> {
+    let content = std::fs::read_to_string(path)?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| {
+                SourceVideoError::config(format!(
+                    "Invalid chaos event in {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChaosStatus {
+    pub running: bool,
+    /// Seed of the current or last random run
+    pub seed: Option<u64>,
+    pub replay: bool,
+    /// Events of the current or last run
+    pub timeline: Vec<ChaosEvent>,
+}
+
+enum EventSource {
+    Random(ChaosSchedule),
+    Replay(VecDeque<ChaosEvent>),
+}
+
+struct ChaosRun {
+    stop: Arc<AtomicBool>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+/// Starts and stops chaos on one RTSP server
+pub struct ChaosController {
+    server: Arc<RwLock<RtspServer>>,
+    /// File each event of the current run is appended to as a JSON line;
+    /// emptied when a run starts, so it holds one timeline to replay
+    log: Option<PathBuf>,
+    timeline: Arc<Mutex<Vec<ChaosEvent>>>,
+    run: Mutex<Option<ChaosRun>>,
+    last: Mutex<(Option<u64>, bool)>,
+}
+
+impl ChaosController {
+    pub fn new(server: Arc<RwLock<RtspServer>>, log: Option<PathBuf>) -> Arc<Self> {
+        Arc::new(Self {
+            server,
+            log,
+            timeline: Arc::default(),
+            run: Mutex::new(None),
+            last: Mutex::new((None, false)),
+        })
+    }
+
+    /// Start random faults; returns the seed in use
+    pub fn start(&self, config: ChaosConfig) -> Result<u64> {
+        config.validate()?;
+        let seed = config.seed.unwrap_or_else(rand::random);
+        log::info!("Starting chaos mode with seed {}", seed);
+        self.spawn(
+            EventSource::Random(ChaosSchedule::new(config, seed)),
+            Some(seed),
+        )?;
+        Ok(seed)
+    }
+
+    /// Apply a recorded timeline at its original times
+    pub fn replay(&self, events: Vec<ChaosEvent>) -> Result<()> {
+        log::info!("Replaying {} chaos events", events.len());
+        let mut events = events;
+        events.sort_by_key(|event| event.at_ms);
+        self.spawn(EventSource::Replay(events.into()), None)
+    }
+
+    /// Stop and undo the faults still in place; false when not running
+    pub async fn stop(&self) -> bool {
+        let Some(run) = self.run.lock().unwrap().take() else {
+            return false;
+        };
+        run.stop.store(true, Ordering::SeqCst);
+        let _ = run.handle.await;
+        log::info!("Chaos mode stopped");
+        true
+    }
+
+    pub fn status(&self) -> ChaosStatus {
+        let running = self
+            .run
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|run| !run.handle.is_finished());
+        let (seed, replay) = *self.last.lock().unwrap();
+        ChaosStatus {
+            running,
+            seed,
+            replay,
+            timeline: self.timeline.lock().unwrap().clone(),
+        }
+    }
+
+    fn spawn(&self, source: EventSource, seed: Option<u64>) -> Result<()> {
+        let mut run = self.run.lock().unwrap();
+        if run.as_ref().is_some_and(|run| !run.handle.is_finished()) {
+            return Err(SourceVideoError::resource("Chaos mode is already running"));
+        }
+
+        *self.last.lock().unwrap() = (seed, matches!(source, EventSource::Replay(_)));
+        self.timeline.lock().unwrap().clear();
+        let stop = Arc::new(AtomicBool::new(false));
+        let task = ChaosTask {
+            server: self.server.clone(),
+            log: self.log.clone(),
+            timeline: self.timeline.clone(),
+            active: BTreeMap::new(),
+            removed: HashMap::new(),
+        };
+        let handle = tokio::spawn(task.run(source, stop.clone()));
+        *run = Some(ChaosRun { stop, handle });
+        Ok(())
+    }
+}
+
+struct ChaosTask {
+    server: Arc<RwLock<RtspServer>>,
+    log: Option<PathBuf>,
+    timeline: Arc<Mutex<Vec<ChaosEvent>>>,
+    /// Faults injected and not yet undone
+    active: BTreeMap<String, Fault>,
+    /// Configurations of unmounted sources, to put them back
+    removed: HashMap<String, VideoSourceConfig>,
+}
+
+impl ChaosTask {
+    async fn run(mut self, mut source: EventSource, stop: Arc<AtomicBool>) {
+        if let Some(path) = &self.log
+            && let Err(e) = std::fs::File::create(path)
+        {
+            log::warn!("Failed to truncate chaos log {}: {}", path.display(), e);
+        }
+        let started = Instant::now();
+        let mut ticks = tokio::time::interval(TICK);
+        loop {
+            ticks.tick().await;
+            let now = started.elapsed().as_millis() as u64;
+
+            if stop.load(Ordering::SeqCst) {
+                let pending: Vec<_> = std::mem::take(&mut self.active).into_iter().collect();
+                for (mount, fault) in pending {
+                    self.apply(ChaosEvent {
+                        at_ms: now,
+                        mount,
+                        action: ChaosAction::Recover,
+                        fault,
+                    })
+                    .await;
+                }
+                break;
+            }
+
+            let events = match &mut source {
+                EventSource::Random(schedule) => {
+                    let mounts = self.server.read().await.list_sources();
+                    schedule.step(now, &mounts)
+                }
+                EventSource::Replay(queue) => {
+                    let mut due = Vec::new();
+                    while queue.front().is_some_and(|event| event.at_ms <= now) {
+                        due.extend(queue.pop_front());
+                    }
+                    due
+                }
+            };
+            for event in events {
+                self.apply(event).await;
+            }
+
+            if let EventSource::Replay(queue) = &source
+                && queue.is_empty()
+            {
+                log::info!("Chaos replay finished");
+                break;
+            }
+        }
+    }
+
+    async fn apply(&mut self, event: ChaosEvent) {
+        log::info!(
+            "Chaos: {:?} {:?} on {} at {} ms",
+            event.action,
+            event.fault,
+            event.mount,
+            event.at_ms
+        );
+        if let Err(e) = self.inject_or_recover(&event).await {
+            log::warn!("Chaos event on {} failed: {}", event.mount, e);
+        }
+        match event.action {
+            ChaosAction::Inject if event.fault != Fault::Eos => {
+                self.active.insert(event.mount.clone(), event.fault.clone());
+            }
+            ChaosAction::Recover => {
+                self.active.remove(&event.mount);
+            }
+            _ => {}
+        }
+        self.record(event);
+    }
+
+    async fn inject_or_recover(&mut self, event: &ChaosEvent) -> Result<()> {
+        let mount = event.mount.as_str();
+        match (event.action, &event.fault) {
+            (action, Fault::Drop) => {
+                self.server
+                    .read()
+                    .await
+                    .set_mount_dropped(mount, action == ChaosAction::Inject);
+            }
+            (ChaosAction::Inject, Fault::Degrade { profile }) => {
+                let profile = profile.parse().map_err(SourceVideoError::config)?;
+                self.server
+                    .write()
+                    .await
+                    .set_mount_network(mount, Some(profile))?;
+            }
+            (ChaosAction::Recover, Fault::Degrade { .. }) => {
+                self.server.write().await.set_mount_network(mount, None)?;
+            }
+            (ChaosAction::Inject, Fault::Remove) => {
+                let mut server = self.server.write().await;
+                let config = server.source_config(mount).ok_or_else(|| {
+                    SourceVideoError::SourceNotFound(format!("No source mounted at {}", mount))
+                })?;
+                server.remove_source(mount)?;
+                self.removed.insert(mount.to_string(), config);
+            }
+            (ChaosAction::Recover, Fault::Remove) => {
+                if let Some(config) = self.removed.remove(mount) {
+                    self.server.write().await.add_source_at(mount, config)?;
+                }
+            }
+            (ChaosAction::Inject, Fault::Eos) => {
+                let ended = self.server.read().await.inject_eos(mount);
+                log::debug!("Sent EOS to {} media of {}", ended, mount);
+            }
+            (ChaosAction::Recover, Fault::Eos) => {}
+        }
+        Ok(())
+    }
+
+    fn record(&self, event: ChaosEvent) {
+        if let Some(path) = &self.log {
+            let line = serde_json::to_string(&event).unwrap_or_default();
+            let written = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(e) = written {
+                log::warn!("Failed to write chaos log {}: {}", path.display(), e);
+            }
+        }
+        self.timeline.lock().unwrap().push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fault a second, each lasting 1.5 s
+    fn config() -> ChaosConfig {
+        ChaosConfig {
+            interval_ms: 1000,
+            fault_ms: 1500,
+            drop: 0.2,
+            degrade: 0.2,
+            remove: 0.2,
+            eos: 0.2,
+            ..ChaosConfig::default()
+        }
+    }
+
+    /// Twenty seconds of chaos over three mounts with `seed`
+    fn run(seed: u64) -> Vec<ChaosEvent> {
+        let mounts: Vec<String> = ["/a", "/b", "/c"].iter().map(|m| m.to_string()).collect();
+        let mut schedule = ChaosSchedule::new(config(), seed);
+        (1..=20)
+            .flat_map(|second| schedule.step(second * 1000, &mounts))
+            .collect()
+    }
+
+    #[test]
+    fn test_schedule_is_reproducible() {
+        let events = run(42);
+        assert_eq!(events, run(42));
+        assert!(!events.is_empty());
+        assert!(events.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+    }
+
+    #[test]
+    fn test_faults_recover() {
+        // Every fault but EOS is undone after fault_ms, one fault per mount
+        let events = run(42);
+        for event in events.iter().filter(|e| e.action == ChaosAction::Inject) {
+            let recovery = events.iter().find(|e| {
+                e.action == ChaosAction::Recover && e.mount == event.mount && e.at_ms > event.at_ms
+            });
+            match event.fault {
+                Fault::Eos => {}
+                _ if event.at_ms + 1500 <= 20_000 => {
+                    let recovery = recovery.unwrap();
+                    assert_eq!(recovery.at_ms, event.at_ms + 1500);
+                    assert_eq!(recovery.fault, event.fault);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_json() {
+        let line = serde_json::to_string(&ChaosEvent {
+            at_ms: 5000,
+            mount: "/a".to_string(),
+            action: ChaosAction::Inject,
+            fault: Fault::Degrade {
+                profile: "poor".to_string(),
+            },
+        })
+        .unwrap();
+        assert_eq!(
+            line,
+            r#"{"at_ms":5000,"mount":"/a","action":"inject","fault":"degrade","profile":"poor"}"#
+        );
+    }
+
+    #[test]
+    fn test_probabilities_validated() {
+        assert!(config().validate().is_ok());
+        let too_likely = ChaosConfig {
+            drop: 0.9,
+            eos: 0.2,
+            ..ChaosConfig::default()
+        };
+        assert!(too_likely.validate().is_err());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod loader;
 pub mod validator;
+#[cfg(feature = "watch")]
 pub mod watcher;
 
 // Re-export types from the config_types module
@@ -8,4 +9,5 @@ pub use crate::config_types::*;
 // Re-export commonly used types
 pub use loader::{AtomicConfigLoader, ConfigLoader, TomlConfigLoader};
 pub use validator::DefaultConfigValidator;
+#[cfg(feature = "watch")]
 pub use watcher::{ConfigBroadcaster, ConfigEvent, ConfigWatcher};
@@ -161,6 +161,15 @@ impl super::loader::ConfigValidator for DefaultConfigValidator {
             ));
         }
 
+        if let Some(profile) = &config.network_profile
+            && profile.parse::<crate::network::NetworkProfile>().is_err()
+        {
+            return Err(SourceVideoError::config(format!(
+                "Unknown network profile: {}",
+                profile
+            )));
+        }
+
         // Check for duplicate source names
         let mut source_names = HashSet::new();
         let mut rtsp_mount_points = HashSet::new();
@@ -223,6 +232,11 @@ impl super::loader::ConfigValidator for DefaultConfigValidator {
                     "bar",
                 ];
 
+                // Config sources play in local pipelines
+                if crate::avsync::is_avsync(pattern) {
+                    return Err(crate::avsync::only_on_rtsp());
+                }
+
                 if !valid_patterns.contains(&pattern.as_str()) {
                     return Err(SourceVideoError::config(format!(
                         "Unknown test pattern: {}",
@@ -398,4 +412,14 @@ mod tests {
                 .is_err()
         );
     }
+
+    #[test]
+    fn test_avsync_config_source_is_rejected() {
+        let validator = DefaultConfigValidator::new();
+
+        let smpte = VideoSourceConfig::test_pattern("bars", "smpte");
+        assert!(validator.validate_source(&smpte).is_ok());
+        let avsync = VideoSourceConfig::test_pattern("sync", "avsync");
+        assert!(validator.validate_source(&avsync).is_err());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::error::{Result, SourceVideoError};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,6 +28,10 @@ pub struct VideoSourceConfig {
 
     #[serde(default = "default_is_live")]
     pub is_live: bool,
+
+    /// Group names, such as the cameras of one rig started together
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -86,6 +91,9 @@ pub enum FileContainer {
     Mkv,
     Avi,
     WebM,
+    MpegTs,
+    #[serde(rename = "fmp4")]
+    FragmentedMp4,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -157,9 +165,14 @@ pub struct WatchConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RtspServerConfig {
+    /// Port to listen on; `0` picks an ephemeral port
     #[serde(default = "default_rtsp_port")]
     pub port: u16,
 
+    /// Further ports tried after `port` when it is already in use
+    #[serde(default)]
+    pub port_retries: u16,
+
     #[serde(default = "default_rtsp_address")]
     pub address: String,
 
@@ -168,6 +181,26 @@ pub struct RtspServerConfig {
 
     #[serde(default)]
     pub authentication: Option<BasicAuthConfig>,
+
+    /// Tenant namespaces, each mounted under `/{name}/`
+    #[serde(default)]
+    pub namespaces: Vec<NamespaceConfig>,
+
+    /// RTP transports every mount accepts: any, tcp or udp
+    #[serde(default)]
+    pub transport: crate::rtsp::transport::TransportMode,
+
+    /// Transport overrides by mount point
+    #[serde(default)]
+    pub mount_transports: HashMap<String, crate::rtsp::transport::TransportMode>,
+
+    /// Time-shift buffers by mount point
+    #[serde(default)]
+    pub dvr: HashMap<String, crate::rtsp::dvr::DvrConfig>,
+
+    /// Where DVR segments are written; a temporary directory by default
+    #[serde(default)]
+    pub dvr_dir: Option<std::path::PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -176,6 +209,60 @@ pub struct BasicAuthConfig {
     pub password: String,
 }
 
+/// A group of mounts under one path prefix with its own credentials and
+/// limits, standing in for one tenant's cameras
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceConfig {
+    pub name: String,
+
+    /// Most mounts the namespace may hold, aliases included
+    #[serde(default)]
+    pub max_mounts: Option<usize>,
+
+    /// Encoder bitrate ceiling for each stream in the namespace
+    #[serde(default)]
+    pub bandwidth_kbps: Option<u32>,
+
+    /// Credentials clients need for the namespace's mounts; without them
+    /// the mounts are public
+    #[serde(default)]
+    pub authentication: Option<BasicAuthConfig>,
+
+    /// Sources mounted in the namespace when the server is created
+    #[serde(default)]
+    pub sources: Vec<VideoSourceConfig>,
+}
+
+impl NamespaceConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            max_mounts: None,
+            bandwidth_kbps: None,
+            authentication: None,
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn with_max_mounts(mut self, max: usize) -> Self {
+        self.max_mounts = Some(max);
+        self
+    }
+
+    pub fn with_bandwidth(mut self, kbps: u32) -> Self {
+        self.bandwidth_kbps = Some(kbps);
+        self
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.authentication = Some(BasicAuthConfig {
+            username: username.into(),
+            password: password.into(),
+        });
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     #[serde(default)]
@@ -189,6 +276,17 @@ pub struct AppConfig {
 
     #[serde(default)]
     pub output_dir: Option<String>,
+
+    /// Quota and free-space limits for the directories files are generated in
+    #[serde(default)]
+    pub storage: Option<crate::storage::StorageConfig>,
+
+    /// Network profile simulated for all sources, by name (`3g`, `poor`, ...)
+    #[serde(default)]
+    pub network_profile: Option<String>,
+
+    #[serde(default)]
+    pub watch: Option<WatchConfig>,
 }
 
 impl VideoSourceConfig {
@@ -204,6 +302,7 @@ impl VideoSourceConfig {
             duration: None,
             num_buffers: None,
             is_live: true,
+            tags: Vec::new(),
         }
     }
 
@@ -220,6 +319,7 @@ impl VideoSourceConfig {
             duration: Some(10),
             num_buffers: None,
             is_live: false,
+            tags: Vec::new(),
         }
     }
 
@@ -236,9 +336,19 @@ impl VideoSourceConfig {
             duration: None,
             num_buffers: None,
             is_live: true,
+            tags: Vec::new(),
         }
     }
 
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
     pub fn get_uri(&self) -> String {
         match &self.source_type {
             VideoSourceType::TestPattern { .. } => {
@@ -278,9 +388,15 @@ impl Default for RtspServerConfig {
     fn default() -> Self {
         Self {
             port: default_rtsp_port(),
+            port_retries: 0,
             address: default_rtsp_address(),
             max_connections: default_max_connections(),
             authentication: None,
+            namespaces: Vec::new(),
+            transport: Default::default(),
+            mount_transports: HashMap::new(),
+            dvr: HashMap::new(),
+            dvr_dir: None,
         }
     }
 }
@@ -295,6 +411,9 @@ impl Default for AppConfig {
             ],
             log_level: default_log_level(),
             output_dir: None,
+            storage: None,
+            network_profile: None,
+            watch: None,
         }
     }
 }
@@ -314,19 +433,45 @@ impl VideoFormat {
 impl FileContainer {
     pub fn muxer_name(&self) -> &str {
         match self {
-            FileContainer::Mp4 => "mp4mux",
+            FileContainer::Mp4 | FileContainer::FragmentedMp4 => "mp4mux",
             FileContainer::Mkv => "matroskamux",
             FileContainer::Avi => "avimux",
             FileContainer::WebM => "webmmux",
+            FileContainer::MpegTs => "mpegtsmux",
+        }
+    }
+
+    /// Name as written in configuration files
+    pub fn name(&self) -> &str {
+        match self {
+            FileContainer::FragmentedMp4 => "fmp4",
+            FileContainer::MpegTs => "mpegts",
+            other => other.extension(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "mp4" => Ok(FileContainer::Mp4),
+            "fmp4" | "fragmented-mp4" => Ok(FileContainer::FragmentedMp4),
+            "mkv" | "matroska" => Ok(FileContainer::Mkv),
+            "avi" => Ok(FileContainer::Avi),
+            "webm" => Ok(FileContainer::WebM),
+            "ts" | "mpegts" => Ok(FileContainer::MpegTs),
+            _ => Err(SourceVideoError::config(format!(
+                "Unknown container: {}",
+                name
+            ))),
         }
     }
 
     pub fn extension(&self) -> &str {
         match self {
-            FileContainer::Mp4 => "mp4",
+            FileContainer::Mp4 | FileContainer::FragmentedMp4 => "mp4",
             FileContainer::Mkv => "mkv",
             FileContainer::Avi => "avi",
             FileContainer::WebM => "webm",
+            FileContainer::MpegTs => "ts",
         }
     }
 }
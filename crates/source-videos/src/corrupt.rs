@@ -0,0 +1,358 @@
+//! Intentionally broken files for demuxer and decoder resilience tests
+//!
+//! A [`CorruptionPreset`] is applied by [`FileGenerator`](crate::FileGenerator)
+//! in up to two places: an element inserted before the encoder (dropped
+//! frames, orientation tags) and a rewrite of the finished file (truncation,
+//! index damage, cutting the leading keyframe).
+
+use crate::config_types::FileContainer;
+use crate::error::{Result, SourceVideoError};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// MPEG-TS packet size; cuts are aligned to it so the demuxer can resync
+const TS_PACKET_SIZE: usize = 188;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorruptionPreset {
+    /// Cut the file inside the moov box, leaving `keep_fraction` of it
+    TruncatedMoov { keep_fraction: f64 },
+    /// MPEG-TS with the first `skip_secs` removed, so it starts mid-GOP
+    MissingInitialKeyframe { skip_secs: f64 },
+    /// Randomly drop frames so frame durations vary
+    VariableFramerate { drop_probability: f64 },
+    /// Orientation tag written to the track header; 90, 180 or 270
+    RotatedMetadata { degrees: u32 },
+    /// Point every chunk offset in the sample table past the end of the file
+    BrokenIndex,
+}
+
+impl CorruptionPreset {
+    /// Every preset with its default parameters
+    pub fn all() -> Vec<Self> {
+        [
+            "truncated_moov",
+            "missing_keyframe",
+            "vfr",
+            "rotated",
+            "broken_index",
+        ]
+        .into_iter()
+        .filter_map(|name| Self::from_name(name).ok())
+        .collect()
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "truncated_moov" => Ok(Self::TruncatedMoov { keep_fraction: 0.5 }),
+            "missing_keyframe" | "missing_initial_keyframe" => {
+                Ok(Self::MissingInitialKeyframe { skip_secs: 1.0 })
+            }
+            "vfr" | "variable_framerate" => Ok(Self::VariableFramerate {
+                drop_probability: 0.3,
+            }),
+            "rotated" | "rotated_metadata" => Ok(Self::RotatedMetadata { degrees: 90 }),
+            "broken_index" => Ok(Self::BrokenIndex),
+            _ => Err(SourceVideoError::config(format!(
+                "Unknown corruption preset: {}",
+                name
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TruncatedMoov { .. } => "truncated_moov",
+            Self::MissingInitialKeyframe { .. } => "missing_keyframe",
+            Self::VariableFramerate { .. } => "vfr",
+            Self::RotatedMetadata { .. } => "rotated",
+            Self::BrokenIndex => "broken_index",
+        }
+    }
+
+    /// Container the preset only works with
+    pub fn required_container(&self) -> Option<FileContainer> {
+        match self {
+            Self::TruncatedMoov { .. } | Self::RotatedMetadata { .. } | Self::BrokenIndex => {
+                Some(FileContainer::Mp4)
+            }
+            Self::MissingInitialKeyframe { .. } => Some(FileContainer::MpegTs),
+            Self::VariableFramerate { .. } => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::TruncatedMoov { keep_fraction } if !(0.0..1.0).contains(keep_fraction) => {
+                Err(SourceVideoError::config("keep_fraction must be in [0, 1)"))
+            }
+            Self::MissingInitialKeyframe { skip_secs } if *skip_secs <= 0.0 => {
+                Err(SourceVideoError::config("skip_secs must be positive"))
+            }
+            Self::VariableFramerate { drop_probability }
+                if !(0.0..1.0).contains(drop_probability) =>
+            {
+                Err(SourceVideoError::config(
+                    "drop_probability must be in [0, 1)",
+                ))
+            }
+            Self::RotatedMetadata { degrees } if ![90, 180, 270].contains(degrees) => Err(
+                SourceVideoError::config("Rotation must be 90, 180 or 270 degrees"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Element to insert before the encoder, if the preset needs one
+    pub fn create_filter(&self) -> Result<Option<gst::Element>> {
+        let element = match self {
+            Self::VariableFramerate { drop_probability } => {
+                let identity = gst::ElementFactory::make("identity")
+                    .name("corrupt-vfr")
+                    .build()
+                    .map_err(|_| SourceVideoError::element("identity"))?;
+                identity.set_property("drop-probability", *drop_probability as f32);
+                identity
+            }
+            Self::RotatedMetadata { degrees } => {
+                let taginject = gst::ElementFactory::make("taginject")
+                    .name("corrupt-rotation")
+                    .build()
+                    .map_err(|_| SourceVideoError::element("taginject"))?;
+                taginject.set_property("tags", format!("image-orientation=rotate-{}", degrees));
+                taginject
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(element))
+    }
+
+    /// Damage the finished file at `path`, `duration_secs` long
+    pub fn apply(&self, path: &Path, duration_secs: f64) -> Result<()> {
+        match self {
+            Self::TruncatedMoov { keep_fraction } => truncate_moov(path, *keep_fraction),
+            Self::MissingInitialKeyframe { skip_secs } => {
+                cut_leading(path, skip_secs / duration_secs.max(f64::EPSILON))
+            }
+            Self::BrokenIndex => break_index(path),
+            Self::VariableFramerate { .. } | Self::RotatedMetadata { .. } => Ok(()),
+        }
+    }
+}
+
+/// A box found in an ISO BMFF (MP4) file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mp4Box {
+    offset: usize,
+    header: usize,
+    size: usize,
+}
+
+impl Mp4Box {
+    fn body(&self) -> usize {
+        self.offset + self.header
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.size
+    }
+}
+
+/// First box of type `kind` among the sibling boxes in `data[start..end]`
+fn find_box(data: &[u8], start: usize, end: usize, kind: &[u8; 4]) -> Option<Mp4Box> {
+    let mut offset = start;
+    while offset + 8 <= end {
+        let size = u32::from_be_bytes(data[offset..offset + 4].try_into().ok()?) as usize;
+        let (size, header) = match size {
+            0 => (end - offset, 8),
+            1 if offset + 16 <= end => (
+                u64::from_be_bytes(data[offset + 8..offset + 16].try_into().ok()?) as usize,
+                16,
+            ),
+            size => (size, 8),
+        };
+        if size < header {
+            return None;
+        }
+        if &data[offset + 4..offset + 8] == kind {
+            return Some(Mp4Box {
+                offset,
+                header,
+                size,
+            });
+        }
+        offset = offset.checked_add(size)?;
+    }
+    None
+}
+
+/// Box at the end of a path of nested boxes, e.g. moov/trak/mdia
+fn find_path(data: &[u8], path: &[&[u8; 4]]) -> Option<Mp4Box> {
+    let (mut start, mut end) = (0, data.len());
+    let mut found = None;
+    for kind in path {
+        let mp4_box = find_box(data, start, end, kind)?;
+        start = mp4_box.body();
+        end = mp4_box.end().min(data.len());
+        found = Some(mp4_box);
+    }
+    found
+}
+
+fn not_mp4(path: &Path, what: &str) -> SourceVideoError {
+    SourceVideoError::config(format!("{} has no {} box", path.display(), what))
+}
+
+fn truncate_moov(path: &Path, keep_fraction: f64) -> Result<()> {
+    let data = std::fs::read(path)?;
+    let moov = find_box(&data, 0, data.len(), b"moov").ok_or_else(|| not_mp4(path, "moov"))?;
+    let keep = ((moov.size as f64 * keep_fraction) as usize).max(moov.header);
+    let length = (moov.offset + keep).min(data.len());
+    std::fs::write(path, &data[..length])?;
+    Ok(())
+}
+
+fn break_index(path: &Path) -> Result<()> {
+    let mut data = std::fs::read(path)?;
+    let stco = find_path(
+        &data,
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stco"],
+    );
+    let co64 = find_path(
+        &data,
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"co64"],
+    );
+    let (table, width) = match (stco, co64) {
+        (Some(table), _) => (table, 4),
+        (None, Some(table)) => (table, 8),
+        (None, None) => return Err(not_mp4(path, "chunk offset")),
+    };
+
+    // Version and flags, then the entry count
+    let count_at = table.body() + 4;
+    let count = u32::from_be_bytes(
+        data.get(count_at..count_at + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| not_mp4(path, "chunk offset"))?,
+    ) as usize;
+    let past_end = data.len() as u64 + 4096;
+    for i in 0..count {
+        let at = count_at + 4 + i * width;
+        if at + width > table.end().min(data.len()) {
+            break;
+        }
+        let bogus = past_end + i as u64;
+        if width == 4 {
+            data[at..at + 4].copy_from_slice(&(bogus as u32).to_be_bytes());
+        } else {
+            data[at..at + 8].copy_from_slice(&bogus.to_be_bytes());
+        }
+    }
+    std::fs::write(path, &data)?;
+    Ok(())
+}
+
+/// Remove the leading `fraction` of an MPEG-TS file
+fn cut_leading(path: &Path, fraction: f64) -> Result<()> {
+    let data = std::fs::read(path)?;
+    let cut = (data.len() as f64 * fraction.clamp(0.0, 1.0)) as usize;
+    let cut = cut / TS_PACKET_SIZE * TS_PACKET_SIZE;
+    std::fs::write(path, &data[cut..])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn sample_mp4() -> Vec<u8> {
+        let mut stco = vec![0, 0, 0, 0, 0, 0, 0, 2];
+        stco.extend_from_slice(&48u32.to_be_bytes());
+        stco.extend_from_slice(&64u32.to_be_bytes());
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stco", &stco));
+        let minf = mp4_box(b"minf", &stbl);
+        let mdia = mp4_box(b"mdia", &minf);
+        let trak = mp4_box(b"trak", &mdia);
+
+        let mut data = mp4_box(b"ftyp", b"isom");
+        data.extend(mp4_box(b"mdat", &[0xAB; 32]));
+        data.extend(mp4_box(b"moov", &trak));
+        data
+    }
+
+    #[test]
+    fn test_broken_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.mp4");
+        let original = sample_mp4();
+        std::fs::write(&path, &original).unwrap();
+
+        CorruptionPreset::BrokenIndex.apply(&path, 10.0).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), original.len());
+        let stco = find_path(
+            &data,
+            &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stco"],
+        )
+        .unwrap();
+        let first = u32::from_be_bytes(data[stco.body() + 8..stco.body() + 12].try_into().unwrap());
+        assert!(first as usize > data.len());
+    }
+
+    #[test]
+    fn test_truncated_moov() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.mp4");
+        let original = sample_mp4();
+        std::fs::write(&path, &original).unwrap();
+
+        CorruptionPreset::TruncatedMoov { keep_fraction: 0.5 }
+            .apply(&path, 10.0)
+            .unwrap();
+        let moov = find_box(&original, 0, original.len(), b"moov").unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize,
+            moov.offset + moov.size / 2
+        );
+    }
+
+    #[test]
+    fn test_cut_leading_is_packet_aligned() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.ts");
+        std::fs::write(&path, vec![0x47; TS_PACKET_SIZE * 10]).unwrap();
+
+        CorruptionPreset::MissingInitialKeyframe { skip_secs: 1.0 }
+            .apply(&path, 4.0)
+            .unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize,
+            TS_PACKET_SIZE * 8
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let presets = CorruptionPreset::all();
+        assert_eq!(presets.len(), 5);
+        for preset in &presets {
+            assert!(preset.validate().is_ok());
+            assert_eq!(&CorruptionPreset::from_name(preset.name()).unwrap(), preset);
+        }
+        assert!(
+            CorruptionPreset::RotatedMetadata { degrees: 45 }
+                .validate()
+                .is_err()
+        );
+    }
+}
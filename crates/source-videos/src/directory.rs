@@ -1,15 +1,27 @@
 use crate::config_types::{
     DirectoryConfig, FileContainer, FilterConfig, VideoSourceConfig, VideoSourceType,
 };
+use crate::duplicates::{DuplicateGroup, DuplicatePolicy, content_key, find_duplicates};
 use crate::error::{Result, SourceVideoError};
 use crate::file_utils::{detect_container_format, is_video_file, path_to_mount_point};
+use crate::operation::Operation;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use walkdir::{DirEntry, WalkDir};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Sources buffered between the scan threads and a slow [`ScanStream`]
+/// reader before the threads wait
+const STREAM_BUFFER: usize = 64;
 
 pub struct DirectoryScanner {
     config: DirectoryConfig,
     discovered_files: Vec<PathBuf>,
+    concurrency: usize,
+    duplicate_policy: DuplicatePolicy,
+    duplicates: Vec<DuplicateGroup>,
 }
 
 impl DirectoryScanner {
@@ -17,39 +29,77 @@ impl DirectoryScanner {
         Self {
             config,
             discovered_files: Vec::new(),
+            concurrency: default_concurrency(),
+            duplicate_policy: DuplicatePolicy::default(),
+            duplicates: Vec::new(),
         }
     }
 
-    pub fn scan(&mut self) -> Result<Vec<VideoSourceConfig>> {
-        let path = Path::new(&self.config.path);
+    /// Read at most `threads` directories at once
+    pub fn with_concurrency(mut self, threads: usize) -> Self {
+        self.concurrency = threads.max(1);
+        self
+    }
 
-        if !path.exists() {
-            return Err(SourceVideoError::config(format!(
-                "Directory does not exist: {}",
-                self.config.path
-            )));
-        }
+    /// Check found files for identical content and mount only the first
+    /// copy, see [`DuplicatePolicy`]. [`scan_stream`](Self::scan_stream)
+    /// keeps whichever copy turns up first.
+    pub fn with_duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
 
-        if !path.is_dir() {
-            return Err(SourceVideoError::config(format!(
-                "Path is not a directory: {}",
-                self.config.path
-            )));
-        }
+    pub fn scan(&mut self) -> Result<Vec<VideoSourceConfig>> {
+        self.scan_inner(None)
+    }
+
+    /// [`scan`](Self::scan) as part of `operation`: stops once it is
+    /// cancelled and reports how many files it has found so far
+    pub fn scan_operation(&mut self, operation: &Operation) -> Result<Vec<VideoSourceConfig>> {
+        self.scan_inner(Some(operation))
+    }
 
+    fn scan_inner(&mut self, operation: Option<&Operation>) -> Result<Vec<VideoSourceConfig>> {
+        self.validate()?;
         self.discovered_files.clear();
 
-        let walker = if self.config.recursive {
-            WalkDir::new(path)
-        } else {
-            WalkDir::new(path).max_depth(1)
-        };
+        let found = Mutex::new(Vec::new());
+        self.walk(
+            &|| operation.is_some_and(Operation::is_cancelled),
+            &|path| {
+                let mut found = found.lock().unwrap();
+                found.push(path);
+                if let Some(operation) = operation
+                    && found.len() % 100 == 0
+                {
+                    operation.set_message(format!("{} video files found", found.len()));
+                }
+            },
+        );
+        if let Some(operation) = operation {
+            operation.check_cancelled()?;
+        }
 
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
-            if self.should_include_entry(&entry) {
-                self.discovered_files.push(entry.path().to_path_buf());
+        // Threads finish in any order; sorting keeps names stable between scans
+        let mut files = found.into_inner().unwrap();
+        files.sort();
+        self.duplicates.clear();
+        if self.duplicate_policy != DuplicatePolicy::Keep {
+            if let Some(operation) = operation {
+                operation.set_message(format!("Checking {} files for duplicates", files.len()));
+            }
+            self.duplicates = find_duplicates(&files);
+            let copies: HashSet<&PathBuf> = self
+                .duplicates
+                .iter()
+                .flat_map(|group| &group.duplicates)
+                .collect();
+            if !copies.is_empty() {
+                log::info!("Skipping {} duplicate video files", copies.len());
             }
+            files.retain(|file| !copies.contains(file));
         }
+        self.discovered_files = files;
 
         log::info!(
             "Discovered {} video files in directory: {}",
@@ -61,20 +111,147 @@ impl DirectoryScanner {
         Ok(configs)
     }
 
+    /// Scan on background threads and yield each source as soon as its
+    /// file is found, so callers can mount sources while a large tree is
+    /// still being read. Names are numbered in the order files turn up,
+    /// which varies between runs. Dropping the stream stops the scan.
+    pub fn scan_stream(&self) -> Result<ScanStream> {
+        self.validate()?;
+
+        let (sender, receiver) = mpsc::sync_channel(STREAM_BUFFER);
+        let stop = Arc::new(AtomicBool::new(false));
+        let scanner = Self {
+            config: self.config.clone(),
+            discovered_files: Vec::new(),
+            concurrency: self.concurrency,
+            duplicate_policy: self.duplicate_policy,
+            duplicates: Vec::new(),
+        };
+        let duplicates = Arc::new(Mutex::new(StreamDuplicates::default()));
+        let stopped = stop.clone();
+        let found_duplicates = duplicates.clone();
+        std::thread::spawn(move || {
+            let index = AtomicUsize::new(0);
+            scanner.walk(&|| stopped.load(Ordering::SeqCst), &|path| {
+                // Hashed outside the lock, which only guards the bookkeeping
+                if scanner.duplicate_policy != DuplicatePolicy::Keep {
+                    match content_key(&path) {
+                        Ok(key) if found_duplicates.lock().unwrap().is_copy(key, &path) => {
+                            return;
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::warn!("Cannot hash {}: {}", path.display(), e),
+                    }
+                }
+                let index = index.fetch_add(1, Ordering::SeqCst);
+                match scanner.source_config(index, &path) {
+                    Ok(config) => {
+                        found_duplicates
+                            .lock()
+                            .unwrap()
+                            .names
+                            .insert(path, config.name.clone());
+                        if sender.send(config).is_err() {
+                            stopped.store(true, Ordering::SeqCst);
+                        }
+                    }
+                    Err(e) => log::warn!("Skipping {}: {}", path.display(), e),
+                }
+            });
+        });
+
+        Ok(ScanStream {
+            receiver,
+            stop,
+            policy: self.duplicate_policy,
+            duplicates,
+        })
+    }
+
     pub fn scan_async(&mut self) -> Result<Vec<VideoSourceConfig>> {
         // For now, just use synchronous scanning
         // Future: Implement background scanning with progress updates
         self.scan()
     }
 
-    fn should_include_entry(&self, entry: &DirEntry) -> bool {
-        let path = entry.path();
+    fn validate(&self) -> Result<()> {
+        let path = Path::new(&self.config.path);
 
-        // Skip directories
-        if path.is_dir() {
-            return false;
+        if !path.exists() {
+            return Err(SourceVideoError::config(format!(
+                "Directory does not exist: {}",
+                self.config.path
+            )));
+        }
+
+        if !path.is_dir() {
+            return Err(SourceVideoError::config(format!(
+                "Path is not a directory: {}",
+                self.config.path
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Read directories on up to `concurrency` threads and call `found` for
+    /// every video file that passes the filters. Directory symlinks are
+    /// followed, but each real directory is read once, so a link back up
+    /// the tree ends there instead of looping.
+    fn walk(&self, stop: &(dyn Fn() -> bool + Sync), found: &(dyn Fn(PathBuf) + Sync)) {
+        let root = PathBuf::from(&self.config.path);
+        let max_depth = if self.config.recursive { usize::MAX } else { 0 };
+        let visited = Mutex::new(HashSet::new());
+        if let Ok(canonical) = root.canonicalize() {
+            visited.lock().unwrap().insert(canonical);
         }
+        let queue = WalkQueue::new(root);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.concurrency {
+                scope.spawn(|| {
+                    while let Some((dir, depth)) = queue.next(stop) {
+                        let entries = match std::fs::read_dir(&dir) {
+                            Ok(entries) => entries,
+                            Err(e) => {
+                                log::warn!("Cannot read directory {}: {}", dir.display(), e);
+                                queue.done();
+                                continue;
+                            }
+                        };
+                        for entry in entries.filter_map(|e| e.ok()) {
+                            if stop() {
+                                break;
+                            }
+                            let path = entry.path();
+                            // is_dir follows symlinks
+                            if !path.is_dir() {
+                                if self.should_include(&path) {
+                                    found(path);
+                                }
+                            } else if depth < max_depth {
+                                match path.canonicalize() {
+                                    Ok(real) if visited.lock().unwrap().insert(real) => {
+                                        queue.push(path, depth + 1);
+                                    }
+                                    Ok(_) => log::debug!(
+                                        "Skipping {}: directory already scanned",
+                                        path.display()
+                                    ),
+                                    Err(e) => {
+                                        log::warn!("Cannot resolve {}: {}", path.display(), e)
+                                    }
+                                }
+                            }
+                        }
+                        queue.done();
+                    }
+                });
+            }
+        });
+    }
 
+    fn should_include(&self, path: &Path) -> bool {
         // Check if it's a video file
         if !is_video_file(path) {
             return false;
@@ -167,54 +344,239 @@ impl DirectoryScanner {
     }
 
     fn create_source_configs(&self) -> Result<Vec<VideoSourceConfig>> {
-        let mut configs = Vec::new();
-
-        for (index, file_path) in self.discovered_files.iter().enumerate() {
-            let mount_point = path_to_mount_point(
-                file_path,
-                &self.config.path,
-                self.config.mount_prefix.as_deref(),
-            )?;
-
-            let container = detect_container_format(file_path).unwrap_or(FileContainer::Mp4);
-
-            let source_name = format!(
-                "{}_{}",
-                file_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("file"),
-                index
-            );
-
-            let config = VideoSourceConfig {
-                name: source_name,
-                source_type: VideoSourceType::File {
-                    path: file_path.to_string_lossy().to_string(),
-                    container,
-                },
-                resolution: crate::config_types::Resolution {
-                    width: 1920,
-                    height: 1080,
-                },
-                framerate: crate::config_types::Framerate {
-                    numerator: 30,
-                    denominator: 1,
-                },
-                format: crate::config_types::VideoFormat::I420,
-                duration: None,
-                num_buffers: None,
-                is_live: false,
+        self.discovered_files
+            .iter()
+            .enumerate()
+            .map(|(index, file_path)| self.source_config(index, file_path))
+            .collect()
+    }
+
+    fn source_config(&self, index: usize, file_path: &Path) -> Result<VideoSourceConfig> {
+        path_to_mount_point(
+            file_path,
+            &self.config.path,
+            self.config.mount_prefix.as_deref(),
+        )?;
+
+        let container = detect_container_format(file_path).unwrap_or(FileContainer::Mp4);
+
+        Ok(VideoSourceConfig {
+            name: source_name(index, file_path),
+            source_type: VideoSourceType::File {
+                path: file_path.to_string_lossy().to_string(),
+                container,
+            },
+            resolution: crate::config_types::Resolution {
+                width: 1920,
+                height: 1080,
+            },
+            framerate: crate::config_types::Framerate {
+                numerator: 30,
+                denominator: 1,
+            },
+            format: crate::config_types::VideoFormat::I420,
+            duration: None,
+            num_buffers: None,
+            is_live: false,
+            tags: Vec::new(),
+        })
+    }
+
+    pub fn get_discovered_files(&self) -> &[PathBuf] {
+        &self.discovered_files
+    }
+
+    /// Copies found by the last [`scan`](Self::scan), when checking for
+    /// duplicates
+    pub fn duplicates(&self) -> &[DuplicateGroup] {
+        &self.duplicates
+    }
+
+    /// `(alias, source)` name pairs for serving each skipped copy under its
+    /// own name with [`RtspServer::add_alias`](crate::RtspServer::add_alias);
+    /// empty unless the policy is [`DuplicatePolicy::Alias`]
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        if self.duplicate_policy != DuplicatePolicy::Alias {
+            return Vec::new();
+        }
+        let mut aliases = Vec::new();
+        for group in &self.duplicates {
+            let Some(index) = self
+                .discovered_files
+                .iter()
+                .position(|file| *file == group.original)
+            else {
+                continue;
             };
+            let source = source_name(index, &group.original);
+            for copy in &group.duplicates {
+                let alias = format!("{}_alias{}", file_stem(copy), aliases.len());
+                aliases.push((alias, source.clone()));
+            }
+        }
+        aliases
+    }
+}
+
+fn file_stem(path: &Path) -> &str {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("file")
+}
+
+fn source_name(index: usize, path: &Path) -> String {
+    format!("{}_{}", file_stem(path), index)
+}
+
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map_or(4, |threads| threads.get())
+        .min(8)
+}
+
+/// Sources from [`DirectoryScanner::scan_stream`], in the order their files
+/// are found
+pub struct ScanStream {
+    receiver: Receiver<VideoSourceConfig>,
+    stop: Arc<AtomicBool>,
+    policy: DuplicatePolicy,
+    duplicates: Arc<Mutex<StreamDuplicates>>,
+}
+
+impl ScanStream {
+    /// Stop reading directories; sources already found still come out
+    pub fn cancel(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Copies skipped so far, when checking for duplicates
+    pub fn duplicates(&self) -> Vec<DuplicateGroup> {
+        self.duplicates.lock().unwrap().groups.clone()
+    }
+
+    /// Like [`DirectoryScanner::aliases`], for the copies skipped so far
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        if self.policy != DuplicatePolicy::Alias {
+            return Vec::new();
+        }
+        let duplicates = self.duplicates.lock().unwrap();
+        let mut aliases = Vec::new();
+        for group in &duplicates.groups {
+            let Some(source) = duplicates.names.get(&group.original) else {
+                continue;
+            };
+            for copy in &group.duplicates {
+                let alias = format!("{}_alias{}", file_stem(copy), aliases.len());
+                aliases.push((alias, source.clone()));
+            }
+        }
+        aliases
+    }
+}
 
-            configs.push(config);
+/// Content of the files a [`ScanStream`] has yielded, to skip later copies
+#[derive(Default)]
+struct StreamDuplicates {
+    /// First file seen with each content key
+    originals: HashMap<(u64, u64), PathBuf>,
+    groups: Vec<DuplicateGroup>,
+    /// Source name of each yielded file
+    names: HashMap<PathBuf, String>,
+}
+
+impl StreamDuplicates {
+    /// Record `path`, whose content has `key`, and tell whether a file
+    /// with that content came first
+    fn is_copy(&mut self, key: (u64, u64), path: &Path) -> bool {
+        let Some(original) = self.originals.get(&key) else {
+            self.originals.insert(key, path.to_path_buf());
+            return false;
+        };
+        log::info!(
+            "Skipping {}: same content as {}",
+            path.display(),
+            original.display()
+        );
+        match self
+            .groups
+            .iter_mut()
+            .find(|group| group.original == *original)
+        {
+            Some(group) => group.duplicates.push(path.to_path_buf()),
+            None => self.groups.push(DuplicateGroup {
+                original: original.clone(),
+                duplicates: vec![path.to_path_buf()],
+            }),
         }
+        true
+    }
+}
 
-        Ok(configs)
+impl Iterator for ScanStream {
+    type Item = VideoSourceConfig;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
     }
+}
 
-    pub fn get_discovered_files(&self) -> &[PathBuf] {
-        &self.discovered_files
+impl Drop for ScanStream {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Directories waiting to be read, shared by the scan threads
+struct WalkQueue {
+    state: Mutex<WalkState>,
+    ready: Condvar,
+}
+
+struct WalkState {
+    dirs: VecDeque<(PathBuf, usize)>,
+    /// Threads reading a directory, which may queue more
+    busy: usize,
+}
+
+impl WalkQueue {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            state: Mutex::new(WalkState {
+                dirs: VecDeque::from([(root, 0)]),
+                busy: 0,
+            }),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// Next directory and its depth; `None` once `stop` says so, or the
+    /// queue is empty and no thread can add to it
+    fn next(&self, stop: &(dyn Fn() -> bool + Sync)) -> Option<(PathBuf, usize)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if stop() || (state.dirs.is_empty() && state.busy == 0) {
+                self.ready.notify_all();
+                return None;
+            }
+            if let Some(dir) = state.dirs.pop_front() {
+                state.busy += 1;
+                return Some(dir);
+            }
+            // Wake up now and then to notice `stop`
+            state = self
+                .ready
+                .wait_timeout(state, Duration::from_millis(50))
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn push(&self, dir: PathBuf, depth: usize) {
+        self.state.lock().unwrap().dirs.push_back((dir, depth));
+        self.ready.notify_one();
+    }
+
+    fn done(&self) {
+        self.state.lock().unwrap().busy -= 1;
+        self.ready.notify_all();
     }
 }
 
@@ -291,6 +653,7 @@ impl BatchSourceLoader {
                     duration: None,
                     num_buffers: None,
                     is_live: false,
+                    tags: Vec::new(),
                 };
 
                 all_configs.push(config);
@@ -352,4 +715,54 @@ mod tests {
         assert_eq!(loader.file_lists.len(), 1);
         assert_eq!(loader.directories.len(), 0);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parallel_scan_survives_symlink_loop() {
+        let dir = TempDir::new().unwrap();
+        for sub in ["a", "a/b", "c"] {
+            fs::create_dir_all(dir.path().join(sub)).unwrap();
+            fs::write(dir.path().join(sub).join("clip.mp4"), b"").unwrap();
+        }
+        // Points back up the tree
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("a/b/up")).unwrap();
+
+        let config = DirectoryConfig {
+            path: dir.path().display().to_string(),
+            recursive: true,
+            filters: None,
+            lazy_loading: false,
+            mount_prefix: None,
+        };
+        let mut scanner = DirectoryScanner::new(config).with_concurrency(4);
+        assert_eq!(scanner.scan().unwrap().len(), 3);
+
+        let streamed: Vec<_> = scanner.scan_stream().unwrap().collect();
+        assert_eq!(streamed.len(), 3);
+    }
+
+    #[test]
+    fn test_scan_stream_skips_duplicates() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.mp4"), b"same content").unwrap();
+        fs::write(dir.path().join("b.mp4"), b"same content").unwrap();
+        fs::write(dir.path().join("c.mp4"), b"other content").unwrap();
+
+        let config = DirectoryConfig {
+            path: dir.path().display().to_string(),
+            recursive: false,
+            filters: None,
+            lazy_loading: false,
+            mount_prefix: None,
+        };
+        let scanner = DirectoryScanner::new(config).with_duplicates(DuplicatePolicy::Alias);
+        let mut stream = scanner.scan_stream().unwrap();
+        let streamed: Vec<_> = stream.by_ref().collect();
+
+        assert_eq!(streamed.len(), 2);
+        assert_eq!(stream.duplicates().len(), 1);
+        let aliases = stream.aliases();
+        assert_eq!(aliases.len(), 1);
+        assert!(streamed.iter().any(|source| source.name == aliases[0].1));
+    }
 }
@@ -0,0 +1,151 @@
+//! Duplicate videos found by content rather than name
+//!
+//! Mirrored media trees often hold the same file under several names.
+//! Files are first grouped by size, and only files sharing a size are
+//! read: their first, middle and last [`SAMPLE_SIZE`] bytes are hashed.
+//! That is enough to tell real copies from different videos of the same
+//! length without reading whole files.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Bytes hashed at each of the three sample points
+pub const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// What a directory scan does with files whose content it has seen before
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Mount every file
+    #[default]
+    Keep,
+    /// Mount only the first of each set of copies
+    Skip,
+    /// Mount the first copy and serve the others as aliases of its mount
+    Alias,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "keep" => Ok(Self::Keep),
+            "skip" => Ok(Self::Skip),
+            "alias" => Ok(Self::Alias),
+            _ => Err(format!(
+                "Unknown duplicate policy '{}', expected keep, skip or alias",
+                s
+            )),
+        }
+    }
+}
+
+/// Files with the same content; `original` sorts first
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub original: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Size and sample hash of a file
+pub fn content_key(path: &Path) -> std::io::Result<(u64, u64)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0u8; SAMPLE_SIZE as usize];
+
+    let mut offsets = vec![0];
+    if size > SAMPLE_SIZE {
+        offsets.push(size / 2 - SAMPLE_SIZE / 2);
+        offsets.push(size - SAMPLE_SIZE);
+    }
+    for offset in offsets {
+        file.seek(SeekFrom::Start(offset))?;
+        let read = file.read(&mut buffer)?;
+        hasher.write(&buffer[..read]);
+    }
+    Ok((size, hasher.finish()))
+}
+
+/// Sets of files in `files` with the same content. Files that cannot be
+/// read are left out.
+pub fn find_duplicates(files: &[PathBuf]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        if let Ok(metadata) = std::fs::metadata(file) {
+            by_size.entry(metadata.len()).or_default().push(file);
+        }
+    }
+
+    let mut by_content: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    for candidates in by_size.into_values().filter(|files| files.len() > 1) {
+        for file in candidates {
+            match content_key(file) {
+                Ok(key) => by_content.entry(key).or_default().push(file.clone()),
+                Err(e) => log::warn!("Cannot hash {}: {}", file.display(), e),
+            }
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_content
+        .into_values()
+        .filter(|files| files.len() > 1)
+        .map(|mut files| {
+            files.sort();
+            let original = files.remove(0);
+            DuplicateGroup {
+                original,
+                duplicates: files,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.original.cmp(&b.original));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_duplicates_by_content() {
+        let dir = TempDir::new().unwrap();
+        let large: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        let mut changed = large.clone();
+        // Same size, differs only in the middle sample
+        changed[150_000] ^= 0xff;
+
+        let files: Vec<PathBuf> = [
+            ("b.mp4", &large),
+            ("a.mp4", &large),
+            ("c.mp4", &changed),
+            ("d.mp4", &large[..10].to_vec()),
+        ]
+        .into_iter()
+        .map(|(name, data)| {
+            let path = dir.path().join(name);
+            fs::write(&path, data).unwrap();
+            path
+        })
+        .collect();
+
+        let groups = find_duplicates(&files);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].original, dir.path().join("a.mp4"));
+        assert_eq!(groups[0].duplicates, [dir.path().join("b.mp4")]);
+    }
+
+    #[test]
+    fn test_parse_duplicate_policy() {
+        assert_eq!("Alias".parse(), Ok(DuplicatePolicy::Alias));
+        assert!("merge".parse::<DuplicatePolicy>().is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::config_types::VideoSourceConfig;
+use crate::error::{Result, SourceVideoError};
+use crate::network::NetworkProfile;
+use crate::rtsp::{RtspServer, RtspServerBuilder};
+use gstreamer::glib;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tokio::sync::{RwLock, broadcast};
+
+/// Lifecycle notifications published by an [`EmbeddedServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbeddedEvent {
+    Started { address: String, port: u16 },
+    SourceAdded { mount_point: String, url: String },
+    SourceRemoved { mount_point: String },
+    Stopped,
+}
+
+/// Builder for an in-process RTSP server.
+///
+/// ```no_run
+/// # async fn example() -> source_videos::Result<()> {
+/// use source_videos::EmbeddedServer;
+///
+/// let mut server = EmbeddedServer::builder()
+///     .port(18554)
+///     .add_test_pattern("cam1", "smpte")
+///     .build()?;
+///
+/// server.start().await?;
+/// let url = server.url("cam1").await.unwrap();
+/// // ... point a pipeline at `url` ...
+/// server.stop().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EmbeddedServerBuilder {
+    inner: RtspServerBuilder,
+    event_capacity: usize,
+}
+
+impl EmbeddedServerBuilder {
+    pub fn new() -> Self {
+        Self {
+            inner: RtspServerBuilder::new().address("127.0.0.1"),
+            event_capacity: 64,
+        }
+    }
+
+    /// Port to listen on; `0` picks a free one, see [`EmbeddedServer::port`]
+    pub fn port(mut self, port: u16) -> Self {
+        self.inner = self.inner.port(port);
+        self
+    }
+
+    pub fn port_retries(mut self, retries: u16) -> Self {
+        self.inner = self.inner.port_retries(retries);
+        self
+    }
+
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.inner = self.inner.address(address);
+        self
+    }
+
+    pub fn add_source(mut self, config: VideoSourceConfig) -> Self {
+        self.inner = self.inner.add_source(config);
+        self
+    }
+
+    pub fn add_test_pattern(mut self, name: &str, pattern: &str) -> Self {
+        self.inner = self.inner.add_test_pattern(name, pattern);
+        self
+    }
+
+    pub fn network_profile(mut self, profile: NetworkProfile) -> Self {
+        self.inner = self.inner.network_profile(profile);
+        self
+    }
+
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity;
+        self
+    }
+
+    pub fn build(self) -> Result<EmbeddedServer> {
+        crate::init()?;
+
+        let server = self.inner.build()?;
+        let (events, _) = broadcast::channel(self.event_capacity.max(1));
+
+        Ok(EmbeddedServer {
+            server: Arc::new(RwLock::new(server)),
+            events,
+            context: glib::MainContext::new(),
+            main_loop: None,
+            thread: None,
+        })
+    }
+}
+
+impl Default for EmbeddedServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handle to an RTSP server running inside the current process.
+///
+/// The server is attached to a private GLib main context that is driven by a
+/// dedicated thread, so callers do not need to iterate the default context
+/// the way the CLI does. Dropping the handle stops the server.
+pub struct EmbeddedServer {
+    server: Arc<RwLock<RtspServer>>,
+    events: broadcast::Sender<EmbeddedEvent>,
+    context: glib::MainContext,
+    main_loop: Option<glib::MainLoop>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EmbeddedServer {
+    pub fn builder() -> EmbeddedServerBuilder {
+        EmbeddedServerBuilder::new()
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Err(SourceVideoError::server("Embedded server already running"));
+        }
+
+        let server = self.server.read().await;
+        server.start_with_context(Some(&self.context))?;
+
+        let main_loop = glib::MainLoop::new(Some(&self.context), false);
+        let loop_clone = main_loop.clone();
+        let context = self.context.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("embedded-rtsp".to_string())
+            .spawn(move || {
+                if let Err(e) = context.with_thread_default(|| loop_clone.run()) {
+                    log::error!("Failed to run embedded RTSP main loop: {}", e);
+                }
+            })
+            .map_err(SourceVideoError::from)?;
+
+        self.main_loop = Some(main_loop);
+        self.thread = Some(thread);
+
+        let _ = self.events.send(EmbeddedEvent::Started {
+            address: server.get_address().to_string(),
+            port: server.get_port(),
+        });
+
+        log::info!(
+            "Embedded RTSP server running on {}:{}",
+            server.get_address(),
+            server.get_port()
+        );
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        let Some(main_loop) = self.main_loop.take() else {
+            return Ok(());
+        };
+
+        let result = self.server.read().await.stop();
+        main_loop.quit();
+
+        if let Some(thread) = self.thread.take() {
+            tokio::task::spawn_blocking(move || thread.join())
+                .await
+                .map_err(|e| SourceVideoError::server(format!("Join failed: {}", e)))?
+                .map_err(|_| SourceVideoError::server("Embedded RTSP thread panicked"))?;
+        }
+
+        let _ = self.events.send(EmbeddedEvent::Stopped);
+        result
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.main_loop.is_some()
+    }
+
+    /// Add a source and return its RTSP URL.
+    pub async fn add_source(&self, config: VideoSourceConfig) -> Result<String> {
+        let mut server = self.server.write().await;
+        let mount_point = server.add_source(config)?;
+        let url = server.get_url(&mount_point);
+
+        let _ = self.events.send(EmbeddedEvent::SourceAdded {
+            mount_point,
+            url: url.clone(),
+        });
+        Ok(url)
+    }
+
+    pub async fn add_test_pattern(&self, name: &str, pattern: &str) -> Result<String> {
+        self.add_source(VideoSourceConfig::test_pattern(name, pattern))
+            .await
+    }
+
+    pub async fn remove_source(&self, mount_point: &str) -> Result<()> {
+        self.server.write().await.remove_source(mount_point)?;
+
+        let _ = self.events.send(EmbeddedEvent::SourceRemoved {
+            mount_point: normalize_mount(mount_point),
+        });
+        Ok(())
+    }
+
+    /// URL for a mount point or source name.
+    pub async fn url(&self, mount_point: &str) -> Option<String> {
+        let server = self.server.read().await;
+        let mount = normalize_mount(mount_point);
+        server
+            .list_sources()
+            .contains(&mount)
+            .then(|| server.get_url(&mount))
+    }
+
+    pub async fn urls(&self) -> Vec<String> {
+        let server = self.server.read().await;
+        let mut urls: Vec<String> = server
+            .list_sources()
+            .iter()
+            .map(|mount| server.get_url(mount))
+            .collect();
+        urls.sort();
+        urls
+    }
+
+    pub async fn port(&self) -> u16 {
+        self.server.read().await.get_port()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EmbeddedEvent> {
+        self.events.subscribe()
+    }
+
+    /// Shared access to the underlying server, e.g. for the control API.
+    pub fn rtsp_server(&self) -> Arc<RwLock<RtspServer>> {
+        self.server.clone()
+    }
+}
+
+impl Drop for EmbeddedServer {
+    fn drop(&mut self) {
+        if let Some(main_loop) = self.main_loop.take() {
+            if let Ok(server) = self.server.try_read() {
+                let _ = server.stop();
+            }
+            main_loop.quit();
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+fn normalize_mount(mount_point: &str) -> String {
+    if mount_point.starts_with('/') {
+        mount_point.to_string()
+    } else {
+        format!("/{}", mount_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_embedded_server_lifecycle() {
+        let mut server = EmbeddedServer::builder()
+            .port(18654)
+            .add_test_pattern("cam1", "smpte")
+            .build()
+            .unwrap();
+        let mut events = server.subscribe();
+
+        server.start().await.unwrap();
+        assert!(server.is_running());
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            EmbeddedEvent::Started { port: 18654, .. }
+        ));
+
+        assert_eq!(
+            server.url("cam1").await.as_deref(),
+            Some("rtsp://127.0.0.1:18654/cam1")
+        );
+
+        let url = server.add_test_pattern("cam2", "ball").await.unwrap();
+        assert!(url.ends_with("/cam2"));
+        assert_eq!(server.urls().await.len(), 2);
+
+        server.remove_source("cam2").await.unwrap();
+        assert!(server.url("cam2").await.is_none());
+
+        server.stop().await.unwrap();
+        assert!(!server.is_running());
+
+        let mut saw_stopped = false;
+        while let Ok(event) = events.try_recv() {
+            saw_stopped |= event == EmbeddedEvent::Stopped;
+        }
+        assert!(saw_stopped);
+    }
+}
@@ -0,0 +1,441 @@
+//! Encoder and container settings for generated files
+//!
+//! An [`EncodingConfig`] picks the codec, rate control, pixel format and
+//! container of one generated file. An [`EncodingMatrix`] expands lists of
+//! those settings into every compatible combination, for building
+//! codec-compatibility test suites.
+
+use crate::config_types::FileContainer;
+use crate::error::{Result, SourceVideoError};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp8,
+    Vp9,
+}
+
+impl VideoCodec {
+    pub fn all() -> &'static [VideoCodec] {
+        &[Self::H264, Self::H265, Self::Vp8, Self::Vp9]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::H264 => "h264",
+            Self::H265 => "h265",
+            Self::Vp8 => "vp8",
+            Self::Vp9 => "vp9",
+        }
+    }
+
+    pub fn encoder_name(&self) -> &'static str {
+        match self {
+            Self::H264 => "x264enc",
+            Self::H265 => "x265enc",
+            Self::Vp8 => "vp8enc",
+            Self::Vp9 => "vp9enc",
+        }
+    }
+
+    /// Parser needed between the encoder and the muxer, if any
+    pub fn parser_name(&self) -> Option<&'static str> {
+        match self {
+            Self::H264 => Some("h264parse"),
+            Self::H265 => Some("h265parse"),
+            Self::Vp8 | Self::Vp9 => None,
+        }
+    }
+
+    /// Highest CRF / constant quality level the encoder accepts
+    pub fn max_crf(&self) -> u32 {
+        match self {
+            Self::H264 | Self::H265 => 51,
+            Self::Vp8 | Self::Vp9 => 63,
+        }
+    }
+
+    /// Highest bitrate in kbit/s the encoder's bitrate property takes
+    pub fn max_bitrate_kbps(&self) -> u32 {
+        match self {
+            Self::H264 => 2_048_000,
+            Self::H265 => 102_400,
+            // target-bitrate is a gint in bit/s
+            Self::Vp8 | Self::Vp9 => i32::MAX as u32 / 1000,
+        }
+    }
+
+    pub fn is_compatible(&self, container: &FileContainer) -> bool {
+        use FileContainer::*;
+        match self {
+            Self::H264 => matches!(container, Mp4 | FragmentedMp4 | Mkv | Avi | MpegTs),
+            Self::H265 => matches!(container, Mp4 | FragmentedMp4 | Mkv | MpegTs),
+            Self::Vp8 => matches!(container, WebM | Mkv),
+            Self::Vp9 => matches!(container, WebM | Mkv | Mp4 | FragmentedMp4),
+        }
+    }
+
+    /// Codec used when only a container is given
+    pub fn default_for(container: &FileContainer) -> Self {
+        match container {
+            FileContainer::WebM => Self::Vp8,
+            _ => Self::H264,
+        }
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for VideoCodec {
+    type Err = SourceVideoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "h264" | "avc" | "x264" => Ok(Self::H264),
+            "h265" | "hevc" | "x265" => Ok(Self::H265),
+            "vp8" => Ok(Self::Vp8),
+            "vp9" => Ok(Self::Vp9),
+            _ => Err(SourceVideoError::config(format!("Unknown codec: {}", s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingConfig {
+    pub codec: VideoCodec,
+    pub container: FileContainer,
+
+    /// Target bitrate; the encoder default when neither this nor `crf` is set
+    pub bitrate_kbps: Option<u32>,
+
+    /// Constant quality level, mutually exclusive with `bitrate_kbps`
+    pub crf: Option<u32>,
+
+    /// GStreamer raw format fed to the encoder, e.g. "I420" or "Y444"
+    pub pixel_format: Option<String>,
+
+    /// Fragment length for fragmented MP4
+    pub fragment_duration_ms: u32,
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::H264,
+            container: FileContainer::Mp4,
+            bitrate_kbps: None,
+            crf: None,
+            pixel_format: None,
+            fragment_duration_ms: 1000,
+        }
+    }
+}
+
+impl EncodingConfig {
+    /// The container's default codec with encoder defaults
+    pub fn for_container(container: FileContainer) -> Self {
+        Self {
+            codec: VideoCodec::default_for(&container),
+            container,
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.codec.is_compatible(&self.container) {
+            return Err(SourceVideoError::config(format!(
+                "Codec {} cannot be stored in {:?}",
+                self.codec, self.container
+            )));
+        }
+        if self.bitrate_kbps.is_some() && self.crf.is_some() {
+            return Err(SourceVideoError::config(
+                "Set either a bitrate or a CRF, not both",
+            ));
+        }
+        if let Some(bitrate) = self.bitrate_kbps
+            && !(1..=self.codec.max_bitrate_kbps()).contains(&bitrate)
+        {
+            return Err(SourceVideoError::config(format!(
+                "Bitrate {} kbps is out of range for {} (1-{})",
+                bitrate,
+                self.codec,
+                self.codec.max_bitrate_kbps()
+            )));
+        }
+        if let Some(crf) = self.crf
+            && crf > self.codec.max_crf()
+        {
+            return Err(SourceVideoError::config(format!(
+                "CRF {} is out of range for {} (0-{})",
+                crf,
+                self.codec,
+                self.codec.max_crf()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Short description of the settings, usable in file names, e.g.
+    /// "h264-crf23-I420-mpegts"
+    pub fn label(&self) -> String {
+        let mut label = self.codec.name().to_string();
+        if let Some(bitrate) = self.bitrate_kbps {
+            label.push_str(&format!("-{}k", bitrate));
+        }
+        if let Some(crf) = self.crf {
+            label.push_str(&format!("-crf{}", crf));
+        }
+        if let Some(format) = &self.pixel_format {
+            label.push_str(&format!("-{}", format));
+        }
+        label.push_str(&format!("-{}", self.container.name()));
+        label
+    }
+
+    /// Bitrate used to estimate output sizes
+    pub fn estimated_bitrate_bps(&self) -> Option<u64> {
+        self.bitrate_kbps.map(|kbps| kbps as u64 * 1000)
+    }
+
+    pub fn create_encoder(&self) -> Result<gst::Element> {
+        let name = self.codec.encoder_name();
+        let encoder = gst::ElementFactory::make(name)
+            .name("encoder")
+            .build()
+            .map_err(|_| SourceVideoError::element(name))?;
+
+        // use string values for enum properties
+        match self.codec {
+            VideoCodec::H264 => {
+                encoder.set_property_from_str("speed-preset", "ultrafast");
+                encoder.set_property_from_str("tune", "zerolatency");
+                if let Some(bitrate) = self.bitrate_kbps {
+                    encoder.set_property("bitrate", bitrate);
+                }
+                if let Some(crf) = self.crf {
+                    encoder.set_property_from_str("pass", "qual");
+                    encoder.set_property("quantizer", crf);
+                }
+            }
+            VideoCodec::H265 => {
+                encoder.set_property_from_str("speed-preset", "ultrafast");
+                encoder.set_property_from_str("tune", "zerolatency");
+                if let Some(bitrate) = self.bitrate_kbps {
+                    encoder.set_property("bitrate", bitrate);
+                }
+                if let Some(crf) = self.crf {
+                    encoder.set_property("option-string", format!("crf={}", crf));
+                }
+            }
+            VideoCodec::Vp8 | VideoCodec::Vp9 => {
+                encoder.set_property("deadline", 1i64);
+                if let Some(bitrate) = self.bitrate_kbps {
+                    encoder.set_property_from_str("end-usage", "cbr");
+                    let bps = u64::from(bitrate) * 1000;
+                    let bps = i32::try_from(bps).map_err(|_| {
+                        SourceVideoError::config(format!(
+                            "Bitrate {} kbps is too high for {}",
+                            bitrate, self.codec
+                        ))
+                    })?;
+                    encoder.set_property("target-bitrate", bps);
+                }
+                if let Some(crf) = self.crf {
+                    encoder.set_property_from_str("end-usage", "cq");
+                    encoder.set_property("cq-level", crf as i32);
+                }
+            }
+        }
+        Ok(encoder)
+    }
+
+    pub fn create_parser(&self) -> Result<Option<gst::Element>> {
+        self.codec
+            .parser_name()
+            .map(|name| {
+                gst::ElementFactory::make(name)
+                    .name("parser")
+                    .build()
+                    .map_err(|_| SourceVideoError::element(name))
+            })
+            .transpose()
+    }
+
+    pub fn create_muxer(&self) -> Result<gst::Element> {
+        let name = self.container.muxer_name();
+        let muxer = gst::ElementFactory::make(name)
+            .name("muxer")
+            .build()
+            .map_err(|_| SourceVideoError::element(name))?;
+        if self.container == FileContainer::FragmentedMp4 {
+            muxer.set_property("fragment-duration", self.fragment_duration_ms);
+        }
+        Ok(muxer)
+    }
+}
+
+/// Lists of settings to combine; empty lists fall back to the defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingMatrix {
+    pub codecs: Vec<VideoCodec>,
+    pub containers: Vec<FileContainer>,
+    pub bitrates_kbps: Vec<u32>,
+    pub crf: Vec<u32>,
+    pub pixel_formats: Vec<String>,
+
+    /// Test patterns to encode with every combination
+    pub patterns: Vec<String>,
+}
+
+impl EncodingMatrix {
+    /// Load from a TOML or JSON file, chosen by extension
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        crate::file_utils::load_toml_or_json(path.as_ref(), "matrix")
+    }
+
+    /// Every valid combination; incompatible codec/container pairs are
+    /// skipped
+    pub fn combinations(&self) -> Vec<EncodingConfig> {
+        fn or_default<T: Clone>(values: &[T], default: T) -> Vec<T> {
+            if values.is_empty() {
+                vec![default]
+            } else {
+                values.to_vec()
+            }
+        }
+
+        let codecs = or_default(&self.codecs, VideoCodec::H264);
+        let containers = or_default(&self.containers, FileContainer::Mp4);
+        let formats: Vec<Option<String>> = or_default(
+            &self
+                .pixel_formats
+                .iter()
+                .cloned()
+                .map(Some)
+                .collect::<Vec<_>>(),
+            None,
+        );
+        let mut rates: Vec<(Option<u32>, Option<u32>)> = self
+            .bitrates_kbps
+            .iter()
+            .map(|&bitrate| (Some(bitrate), None))
+            .chain(self.crf.iter().map(|&crf| (None, Some(crf))))
+            .collect();
+        if rates.is_empty() {
+            rates.push((None, None));
+        }
+
+        let mut combinations = Vec::new();
+        for codec in &codecs {
+            for container in &containers {
+                for (bitrate_kbps, crf) in &rates {
+                    for pixel_format in &formats {
+                        let config = EncodingConfig {
+                            codec: *codec,
+                            container: container.clone(),
+                            bitrate_kbps: *bitrate_kbps,
+                            crf: *crf,
+                            pixel_format: pixel_format.clone(),
+                            ..Default::default()
+                        };
+                        match config.validate() {
+                            Ok(()) => combinations.push(config),
+                            Err(e) => log::debug!("Skipping {}: {}", config.label(), e),
+                        }
+                    }
+                }
+            }
+        }
+        combinations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(EncodingConfig::default().validate().is_ok());
+        assert!(
+            EncodingConfig::for_container(FileContainer::WebM)
+                .validate()
+                .is_ok()
+        );
+
+        let config = EncodingConfig {
+            codec: VideoCodec::Vp8,
+            container: FileContainer::MpegTs,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = EncodingConfig {
+            bitrate_kbps: Some(2000),
+            crf: Some(23),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = EncodingConfig {
+            crf: Some(60),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = EncodingConfig {
+            codec: VideoCodec::Vp9,
+            container: FileContainer::WebM,
+            bitrate_kbps: Some(3_000_000),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_matrix_combinations() {
+        let matrix: EncodingMatrix = toml::from_str(
+            r#"
+            codecs = ["h264", "vp9"]
+            containers = ["mpegts", "webm", "fmp4"]
+            bitrates_kbps = [1000]
+            crf = [23]
+            pixel_formats = ["I420"]
+            "#,
+        )
+        .unwrap();
+
+        let labels: Vec<String> = matrix.combinations().iter().map(|c| c.label()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "h264-1000k-I420-mpegts",
+                "h264-crf23-I420-mpegts",
+                "h264-1000k-I420-fmp4",
+                "h264-crf23-I420-fmp4",
+                "vp9-1000k-I420-webm",
+                "vp9-crf23-I420-webm",
+                "vp9-1000k-I420-fmp4",
+                "vp9-crf23-I420-fmp4",
+            ]
+        );
+
+        assert_eq!(EncodingMatrix::default().combinations().len(), 1);
+    }
+}
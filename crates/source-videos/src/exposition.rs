@@ -0,0 +1,79 @@
+//! Prometheus text exposition format
+//!
+//! A writer for the text format `/metrics` endpoints serve, shared by the
+//! control API and by anything else exporting metrics, e.g. ds-rs.
+
+use std::fmt::Write;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Prometheus text format writer
+#[derive(Default)]
+pub struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    /// A sample with one more label, e.g. a bucket's `le` or a quantile
+    pub fn sample_with(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        extra: (&str, &str),
+        value: f64,
+    ) {
+        let mut labels = labels.to_vec();
+        labels.push(extra);
+        self.sample(name, &labels, value);
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_are_escaped() {
+        let mut out = Exposition::new();
+        out.family("up", "gauge", "Up");
+        out.sample("up", &[("mount", "/a\"b")], 1.0);
+        out.sample_with("up", &[], ("quantile", "0.5"), 0.25);
+        let text = out.finish();
+
+        assert!(text.starts_with("# HELP up Up\n# TYPE up gauge\n"));
+        assert!(text.contains("up{mount=\"/a\\\"b\"} 1\n"));
+        assert!(text.contains("up{quantile=\"0.5\"} 0.25\n"));
+    }
+}
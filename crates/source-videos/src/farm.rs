@@ -0,0 +1,379 @@
+use crate::config_types::VideoSourceConfig;
+use crate::error::{Result, SourceVideoError};
+use crate::rtsp::{RtspServer, RtspServerBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Configuration for a farm of RTSP server instances.
+///
+/// Instances are spread round-robin across `addresses`; each address gets
+/// consecutive ports starting at `base_port`. With a single address this
+/// yields one instance per port in `base_port..`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FarmConfig {
+    #[serde(default = "default_instances")]
+    pub instances: usize,
+
+    #[serde(default = "default_base_port")]
+    pub base_port: u16,
+
+    /// Highest port the farm may use (inclusive)
+    #[serde(default)]
+    pub max_port: Option<u16>,
+
+    #[serde(default = "default_addresses")]
+    pub addresses: Vec<String>,
+
+    /// Test patterns assigned to instance sources, cycled in order
+    #[serde(default = "default_patterns")]
+    pub patterns: Vec<String>,
+
+    #[serde(default = "default_sources_per_instance")]
+    pub sources_per_instance: usize,
+}
+
+impl Default for FarmConfig {
+    fn default() -> Self {
+        Self {
+            instances: default_instances(),
+            base_port: default_base_port(),
+            max_port: None,
+            addresses: default_addresses(),
+            patterns: default_patterns(),
+            sources_per_instance: default_sources_per_instance(),
+        }
+    }
+}
+
+impl FarmConfig {
+    /// Compute the (address, port) pair for every instance.
+    pub fn endpoints(&self) -> Result<Vec<(String, u16)>> {
+        if self.instances == 0 {
+            return Err(SourceVideoError::config(
+                "Farm must have at least one instance",
+            ));
+        }
+
+        if self.addresses.is_empty() {
+            return Err(SourceVideoError::config(
+                "Farm must have at least one bind address",
+            ));
+        }
+
+        let max_port = self.max_port.unwrap_or(u16::MAX);
+        let mut endpoints = Vec::with_capacity(self.instances);
+
+        for index in 0..self.instances {
+            let address = &self.addresses[index % self.addresses.len()];
+            let offset = index / self.addresses.len();
+            let port = (self.base_port as usize) + offset;
+
+            if port > max_port as usize {
+                return Err(SourceVideoError::config(format!(
+                    "Port range {}-{} is too small for {} instances on {} address(es)",
+                    self.base_port,
+                    max_port,
+                    self.instances,
+                    self.addresses.len()
+                )));
+            }
+
+            endpoints.push((address.clone(), port as u16));
+        }
+
+        Ok(endpoints)
+    }
+
+    fn source_configs(&self, instance_index: usize) -> Vec<VideoSourceConfig> {
+        if self.patterns.is_empty() {
+            return Vec::new();
+        }
+
+        (0..self.sources_per_instance)
+            .map(|slot| {
+                let pattern = &self.patterns
+                    [(instance_index * self.sources_per_instance + slot) % self.patterns.len()];
+                VideoSourceConfig::test_pattern(format!("cam{}", slot + 1), pattern.as_str())
+            })
+            .collect()
+    }
+}
+
+/// A single RTSP server managed by a [`ServerFarm`].
+pub struct FarmInstance {
+    id: String,
+    address: String,
+    port: u16,
+    server: Arc<RwLock<RtspServer>>,
+}
+
+impl FarmInstance {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn server(&self) -> Arc<RwLock<RtspServer>> {
+        self.server.clone()
+    }
+
+    pub async fn info(&self) -> FarmInstanceInfo {
+        let server = self.server.read().await;
+        let urls: Vec<String> = server
+            .list_sources()
+            .into_iter()
+            .map(|mount| server.get_url(&mount))
+            .collect();
+
+        FarmInstanceInfo {
+            id: self.id.clone(),
+            address: self.address.clone(),
+            port: self.port,
+            source_count: urls.len(),
+            urls,
+        }
+    }
+}
+
+/// Snapshot of a farm instance for reporting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FarmInstanceInfo {
+    pub id: String,
+    pub address: String,
+    pub port: u16,
+    pub source_count: usize,
+    pub urls: Vec<String>,
+}
+
+/// Manages several RTSP server instances, each with its own source set,
+/// to simulate a large camera estate on a single machine.
+pub struct ServerFarm {
+    config: FarmConfig,
+    instances: Vec<FarmInstance>,
+}
+
+impl ServerFarm {
+    pub fn new(config: FarmConfig) -> Result<Self> {
+        let endpoints = config.endpoints()?;
+        let mut instances = Vec::with_capacity(endpoints.len());
+
+        for (index, (address, port)) in endpoints.into_iter().enumerate() {
+            let mut builder = RtspServerBuilder::new().port(port).address(address.clone());
+
+            for source in config.source_configs(index) {
+                builder = builder.add_source(source);
+            }
+
+            let server = builder.build()?;
+
+            instances.push(FarmInstance {
+                id: format!("instance-{}", index + 1),
+                address,
+                port,
+                server: Arc::new(RwLock::new(server)),
+            });
+        }
+
+        log::info!("Created server farm with {} instances", instances.len());
+
+        Ok(Self { config, instances })
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        for instance in &self.instances {
+            instance.server.read().await.start()?;
+        }
+
+        log::info!("Started {} farm instances", self.instances.len());
+        Ok(())
+    }
+
+    /// Stop every instance, continuing past failures and reporting the first.
+    pub async fn stop(&self) -> Result<()> {
+        let mut first_error = None;
+
+        for instance in &self.instances {
+            if let Err(e) = instance.server.read().await.stop() {
+                log::error!("Failed to stop farm {}: {}", instance.id, e);
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn config(&self) -> &FarmConfig {
+        &self.config
+    }
+
+    pub fn instances(&self) -> &[FarmInstance] {
+        &self.instances
+    }
+
+    pub fn instance(&self, id: &str) -> Option<&FarmInstance> {
+        self.instances.iter().find(|i| i.id == id)
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub async fn add_source(&self, instance_id: &str, config: VideoSourceConfig) -> Result<String> {
+        let instance = self.require_instance(instance_id)?;
+        let mut server = instance.server.write().await;
+        server.add_source(config)
+    }
+
+    pub async fn remove_source(&self, instance_id: &str, mount_point: &str) -> Result<()> {
+        let instance = self.require_instance(instance_id)?;
+        let mut server = instance.server.write().await;
+        server.remove_source(mount_point)
+    }
+
+    pub async fn infos(&self) -> Vec<FarmInstanceInfo> {
+        let mut infos = Vec::with_capacity(self.instances.len());
+        for instance in &self.instances {
+            infos.push(instance.info().await);
+        }
+        infos
+    }
+
+    pub async fn urls(&self) -> Vec<String> {
+        self.infos()
+            .await
+            .into_iter()
+            .flat_map(|info| info.urls)
+            .collect()
+    }
+
+    fn require_instance(&self, id: &str) -> Result<&FarmInstance> {
+        self.instance(id)
+            .ok_or_else(|| SourceVideoError::SourceNotFound(format!("farm instance '{}'", id)))
+    }
+}
+
+fn default_instances() -> usize {
+    4
+}
+
+fn default_base_port() -> u16 {
+    8554
+}
+
+fn default_addresses() -> Vec<String> {
+    vec!["0.0.0.0".to_string()]
+}
+
+fn default_patterns() -> Vec<String> {
+    vec!["smpte".to_string(), "ball".to_string(), "snow".to_string()]
+}
+
+fn default_sources_per_instance() -> usize {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_single_address() {
+        let config = FarmConfig {
+            instances: 3,
+            base_port: 9000,
+            ..Default::default()
+        };
+
+        let endpoints = config.endpoints().unwrap();
+        assert_eq!(endpoints.len(), 3);
+        assert_eq!(endpoints[0], ("0.0.0.0".to_string(), 9000));
+        assert_eq!(endpoints[2], ("0.0.0.0".to_string(), 9002));
+    }
+
+    #[test]
+    fn test_endpoints_multiple_addresses() {
+        let config = FarmConfig {
+            instances: 4,
+            base_port: 9000,
+            addresses: vec!["127.0.0.1".to_string(), "127.0.0.2".to_string()],
+            ..Default::default()
+        };
+
+        let endpoints = config.endpoints().unwrap();
+        assert_eq!(endpoints[0], ("127.0.0.1".to_string(), 9000));
+        assert_eq!(endpoints[1], ("127.0.0.2".to_string(), 9000));
+        assert_eq!(endpoints[2], ("127.0.0.1".to_string(), 9001));
+        assert_eq!(endpoints[3], ("127.0.0.2".to_string(), 9001));
+    }
+
+    #[test]
+    fn test_endpoints_port_range_exhausted() {
+        let config = FarmConfig {
+            instances: 5,
+            base_port: 9000,
+            max_port: Some(9002),
+            ..Default::default()
+        };
+
+        assert!(config.endpoints().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_farm_creation() {
+        gstreamer::init().unwrap();
+
+        let config = FarmConfig {
+            instances: 2,
+            base_port: 18554,
+            addresses: vec!["127.0.0.1".to_string()],
+            sources_per_instance: 2,
+            ..Default::default()
+        };
+
+        let farm = ServerFarm::new(config).unwrap();
+        assert_eq!(farm.instance_count(), 2);
+
+        let infos = farm.infos().await;
+        assert_eq!(infos[0].port, 18554);
+        assert_eq!(infos[1].port, 18555);
+        assert!(infos.iter().all(|i| i.source_count == 2));
+
+        assert!(farm.instance("instance-1").is_some());
+        assert!(farm.instance("instance-9").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_farm_stop() {
+        gstreamer::init().unwrap();
+
+        let config = FarmConfig {
+            instances: 2,
+            base_port: 18654,
+            addresses: vec!["127.0.0.1".to_string()],
+            sources_per_instance: 1,
+            ..Default::default()
+        };
+
+        let farm = ServerFarm::new(config).unwrap();
+        farm.start().await.unwrap();
+        for instance in farm.instances() {
+            assert!(instance.server.read().await.is_attached());
+        }
+
+        farm.stop().await.unwrap();
+        for instance in farm.instances() {
+            assert!(!instance.server.read().await.is_attached());
+        }
+    }
+}
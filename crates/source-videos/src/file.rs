@@ -1,12 +1,22 @@
 use crate::config::{FileContainer, VideoSourceConfig};
+use crate::corrupt::CorruptionPreset;
+use crate::encoding::EncodingConfig;
 use crate::error::{Result, SourceVideoError};
-use crate::patterns::TestPattern;
+use crate::markers;
+use crate::operation::Operation;
 use crate::pipeline::builder::{CapsBuilder, ElementBuilder, PipelineBuilder};
+use crate::storage::StorageManager;
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use std::collections::VecDeque;
+use std::fmt;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+
+/// x264enc's default bitrate, used to estimate output sizes
+const ESTIMATED_BITRATE_BPS: u64 = 2_048_000;
 
 pub struct FileGenerator {
     config: VideoSourceConfig,
@@ -14,6 +24,31 @@ pub struct FileGenerator {
     pipeline: Option<gst::Pipeline>,
     bus_watch: Option<gst::bus::BusWatchGuard>,
     completion: Arc<Mutex<Option<Result<()>>>>,
+    storage: Option<Arc<StorageManager>>,
+    progress: Option<Arc<dyn Fn(f64) + Send + Sync>>,
+    cancel: Option<CancelHandle>,
+    encoding: Option<EncodingConfig>,
+    corruption: Option<CorruptionPreset>,
+}
+
+/// Cancels file generation from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
 }
 
 impl FileGenerator {
@@ -24,17 +59,138 @@ impl FileGenerator {
             pipeline: None,
             bus_watch: None,
             completion: Arc::new(Mutex::new(None)),
+            storage: None,
+            progress: None,
+            cancel: None,
+            encoding: None,
+            corruption: None,
+        }
+    }
+
+    /// Deliberately damage the output; the container is switched to the
+    /// one the preset requires. Only [`generate`](Self::generate) applies
+    /// the file rewrite.
+    pub fn with_corruption(mut self, preset: CorruptionPreset) -> Self {
+        self.corruption = Some(preset);
+        self
+    }
+
+    /// Encode with these settings instead of the defaults for the
+    /// configured container
+    pub fn with_encoding(mut self, encoding: EncodingConfig) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub fn encoding(&self) -> EncodingConfig {
+        let mut encoding = match (&self.encoding, &self.config.source_type) {
+            (Some(encoding), _) => encoding.clone(),
+            (None, crate::config::VideoSourceType::File { container, .. }) => {
+                EncodingConfig::for_container(container.clone())
+            }
+            (None, _) => EncodingConfig::default(),
+        };
+        if let Some(container) = self
+            .corruption
+            .as_ref()
+            .and_then(CorruptionPreset::required_container)
+        {
+            encoding.container = container;
+        }
+        encoding
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(corruption) = &self.corruption {
+            corruption.validate()?;
+        }
+        self.encoding().validate()
+    }
+
+    /// Report percent complete (0-100) while [`generate`](Self::generate)
+    /// waits
+    pub fn with_progress(mut self, progress: impl Fn(f64) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stop generating, with an error, once `cancel` is triggered
+    pub fn with_cancel(mut self, cancel: CancelHandle) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Report progress on `operation` and stop when it is cancelled
+    pub fn with_operation(self, operation: &Operation) -> Self {
+        let progress = operation.clone();
+        self.with_progress(move |percent| progress.set_progress(percent))
+            .with_cancel(operation.cancel_handle())
+    }
+
+    pub fn output_path(&self) -> &Path {
+        &self.output_path
+    }
+
+    /// Check quota and free space with `storage` before generating
+    pub fn with_storage(mut self, storage: Arc<StorageManager>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Length of the generated video in seconds
+    fn expected_seconds(&self) -> f64 {
+        let framerate = self.config.framerate.numerator as f64
+            / self.config.framerate.denominator.max(1) as f64;
+        match (self.config.duration, self.config.num_buffers) {
+            (Some(duration), _) => duration as f64,
+            (None, Some(num_buffers)) if framerate > 0.0 => num_buffers as f64 / framerate,
+            _ => 60.0,
+        }
+    }
+
+    /// Rough output size from the duration and the target bitrate, or the
+    /// encoder's default bitrate
+    pub fn estimated_size(&self) -> u64 {
+        let bitrate = self
+            .encoding()
+            .estimated_bitrate_bps()
+            .unwrap_or(ESTIMATED_BITRATE_BPS);
+        (self.expected_seconds() * bitrate as f64 / 8.0) as u64
+    }
+
+    fn report_progress(&self) {
+        let (Some(progress), Some(pipeline)) = (&self.progress, &self.pipeline) else {
+            return;
+        };
+        if let Some(position) = pipeline.query_position::<gst::ClockTime>() {
+            let percent = position.seconds_f64() / self.expected_seconds() * 100.0;
+            progress(percent.clamp(0.0, 100.0));
+        }
+    }
+
+    fn reserve_space(&self) -> Result<()> {
+        match &self.storage {
+            Some(storage) => storage.reserve(&self.output_path, self.estimated_size()),
+            None => Ok(()),
         }
     }
 
     pub fn generate(&mut self) -> Result<()> {
+        self.validate()?;
+        self.reserve_space()?;
         self.create_pipeline()?;
         self.setup_bus_watch();
         self.start_pipeline()?;
-        self.wait_for_completion()
+        self.wait_for_completion()?;
+        if let Some(corruption) = &self.corruption {
+            corruption.apply(&self.output_path, self.expected_seconds())?;
+        }
+        Ok(())
     }
 
     pub fn generate_async(&mut self) -> Result<()> {
+        self.validate()?;
+        self.reserve_space()?;
         self.create_pipeline()?;
         self.setup_bus_watch();
         self.start_pipeline()
@@ -46,9 +202,11 @@ impl FileGenerator {
 
         let src = ElementBuilder::videotestsrc(Some("source"))?;
 
+        let mut marker = None;
         if let crate::config::VideoSourceType::TestPattern { pattern } = &self.config.source_type {
-            let _pattern = TestPattern::from_str(pattern)?; // Validate pattern exists
-            src.set_property_from_str("pattern", pattern);
+            let (pattern, kind) = markers::parse_pattern(pattern)?;
+            src.set_property_from_str("pattern", &pattern.to_gst_pattern().to_string());
+            marker = kind;
         } else {
             src.set_property_from_str("pattern", "smpte");
         }
@@ -74,58 +232,43 @@ impl FileGenerator {
         let capsfilter = ElementBuilder::capsfilter(Some("filter"), &caps)?;
         let videoconvert = ElementBuilder::videoconvert(Some("convert"))?;
 
-        let (encoder, muxer) = self.create_encoder_muxer()?;
-
-        let filesink = ElementBuilder::filesink(Some("sink"), &self.output_path.to_string_lossy())?;
-
+        let encoding = self.encoding();
+        let mut chain = vec![videoconvert];
+        if let Some(corruption) = &self.corruption {
+            chain.extend(corruption.create_filter()?);
+        }
+        if let Some(format) = &encoding.pixel_format {
+            let caps = gst::Caps::builder("video/x-raw")
+                .field("format", format.as_str())
+                .build();
+            chain.push(ElementBuilder::capsfilter(Some("encoder-format"), &caps)?);
+        }
+        chain.push(encoding.create_encoder()?);
+        chain.extend(encoding.create_parser()?);
+        chain.push(encoding.create_muxer()?);
+        chain.push(ElementBuilder::filesink(
+            Some("sink"),
+            &self.output_path.to_string_lossy(),
+        )?);
+
+        let mut elements = vec![src.clone(), capsfilter.clone()];
+        elements.extend(chain.iter().cloned());
         builder = builder
-            .add_many(vec![
-                src.clone(),
-                capsfilter.clone(),
-                videoconvert.clone(),
-                encoder.clone(),
-                muxer.clone(),
-                filesink.clone(),
-            ])?
+            .add_many(elements)?
             .link_elements(&src, &capsfilter)?
-            .link_elements(&capsfilter, &videoconvert)?
-            .link_elements(&videoconvert, &encoder)?
-            .link_elements(&encoder, &muxer)?
-            .link_elements(&muxer, &filesink)?;
-
-        self.pipeline = Some(builder.build());
-        Ok(())
-    }
+            .link_elements(&capsfilter, &chain[0])?;
+        for pair in chain.windows(2) {
+            builder = builder.link_elements(&pair[0], &pair[1])?;
+        }
 
-    fn create_encoder_muxer(&self) -> Result<(gst::Element, gst::Element)> {
-        let container = if let crate::config::VideoSourceType::File { container, .. } =
-            &self.config.source_type
+        if let Some(kind) = marker
+            && let Some(pad) = capsfilter.static_pad("src")
         {
-            container
-        } else {
-            &FileContainer::Mp4
-        };
-
-        let encoder = match container {
-            FileContainer::Mp4 | FileContainer::Mkv | FileContainer::Avi => {
-                let enc = ElementBuilder::x264enc(Some("encoder"))?;
-                // use string values for enum properties
-                enc.set_property_from_str("speed-preset", "ultrafast");
-                enc.set_property_from_str("tune", "zerolatency");
-                enc
-            }
-            FileContainer::WebM => gst::ElementFactory::make("vp8enc")
-                .name("encoder")
-                .build()
-                .map_err(|_| SourceVideoError::element("vp8enc"))?,
-        };
-
-        let muxer = gst::ElementFactory::make(container.muxer_name())
-            .name("muxer")
-            .build()
-            .map_err(|_| SourceVideoError::element(container.muxer_name()))?;
+            markers::attach_marker_overlay(&pad, kind, &self.config.name);
+        }
 
-        Ok((encoder, muxer))
+        self.pipeline = Some(builder.build());
+        Ok(())
     }
 
     fn setup_bus_watch(&mut self) {
@@ -187,7 +330,7 @@ impl FileGenerator {
         }
     }
 
-    fn wait_for_completion(&self) -> Result<()> {
+    fn wait_for_completion(&mut self) -> Result<()> {
         let timeout = Duration::from_secs(self.config.duration.unwrap_or(60) + 10);
         let start = std::time::Instant::now();
 
@@ -195,12 +338,28 @@ impl FileGenerator {
             if let Ok(comp) = self.completion.lock() {
                 if let Some(result) = &*comp {
                     match result {
-                        Ok(_) => return Ok(()),
+                        Ok(_) => {
+                            if let Some(progress) = &self.progress {
+                                progress(100.0);
+                            }
+                            return Ok(());
+                        }
                         Err(e) => return Err(SourceVideoError::pipeline(e.to_string())),
                     }
                 }
             }
 
+            if self.cancel.as_ref().is_some_and(CancelHandle::is_cancelled) {
+                self.stop()?;
+                // A partial file is not a usable video
+                let _ = std::fs::remove_file(&self.output_path);
+                return Err(SourceVideoError::pipeline(format!(
+                    "Generation of {} cancelled",
+                    self.output_path.display()
+                )));
+            }
+            self.report_progress();
+
             if start.elapsed() > timeout {
                 return Err(SourceVideoError::Timeout(timeout.as_secs()));
             }
@@ -222,75 +381,386 @@ impl FileGenerator {
     }
 }
 
+/// Progress of one file in a batch, tagged with its index in the batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchProgress {
+    Started {
+        index: usize,
+        path: PathBuf,
+    },
+    Progress {
+        index: usize,
+        path: PathBuf,
+        percent: f64,
+        eta: Option<Duration>,
+    },
+    Completed {
+        index: usize,
+        path: PathBuf,
+        elapsed: Duration,
+    },
+    Failed {
+        index: usize,
+        path: PathBuf,
+        error: String,
+    },
+    /// Not started because the batch was cancelled
+    Skipped {
+        index: usize,
+        path: PathBuf,
+    },
+}
+
+type ProgressCallback = Arc<dyn Fn(&BatchProgress) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+/// Outcome of a batch, in the order files were added
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchReport {
+    pub generated: Vec<GeneratedFile>,
+    pub failed: Vec<(PathBuf, String)>,
+    pub skipped: Vec<PathBuf>,
+    pub workers: usize,
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+
+    pub fn total(&self) -> usize {
+        self.generated.len() + self.failed.len() + self.skipped.len()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.generated.iter().map(|file| file.bytes).sum()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Generated {}/{} files ({:.1} MB) in {:.2}s with {} workers",
+            self.generated.len(),
+            self.total(),
+            self.total_bytes() as f64 / (1024.0 * 1024.0),
+            self.elapsed.as_secs_f64(),
+            self.workers
+        )?;
+        for file in &self.generated {
+            writeln!(
+                f,
+                "  ok      {} ({:.2}s)",
+                file.path.display(),
+                file.elapsed.as_secs_f64()
+            )?;
+        }
+        for (path, error) in &self.failed {
+            writeln!(f, "  failed  {}: {}", path.display(), error)?;
+        }
+        for path in &self.skipped {
+            writeln!(f, "  skipped {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of workers used when none is given: one per CPU core
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+#[derive(Clone)]
+struct BatchJob {
+    config: VideoSourceConfig,
+    path: PathBuf,
+    encoding: Option<EncodingConfig>,
+    corruption: Option<CorruptionPreset>,
+}
+
 pub struct BatchFileGenerator {
-    configs: Vec<(VideoSourceConfig, PathBuf)>,
+    configs: Vec<BatchJob>,
+    storage: Option<Arc<StorageManager>>,
+    progress: Option<ProgressCallback>,
+    cancel: CancelHandle,
 }
 
 impl BatchFileGenerator {
     pub fn new() -> Self {
         Self {
             configs: Vec::new(),
+            storage: None,
+            progress: None,
+            cancel: CancelHandle::new(),
         }
     }
 
+    /// Check quota and free space before each file; generation stops at
+    /// the first file that does not fit
+    pub fn with_storage(mut self, storage: Arc<StorageManager>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Called from worker threads for every [`BatchProgress`] update
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&BatchProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Receive progress updates on a channel instead of a callback
+    pub fn progress_channel(&mut self) -> mpsc::Receiver<BatchProgress> {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        self.progress = Some(Arc::new(move |progress: &BatchProgress| {
+            if let Ok(tx) = tx.lock() {
+                let _ = tx.send(progress.clone());
+            }
+        }));
+        rx
+    }
+
+    /// Handle that cancels the batch: running files stop and are removed,
+    /// files not yet started are skipped
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
     pub fn add(&mut self, config: VideoSourceConfig, output_path: impl AsRef<Path>) {
-        self.configs
-            .push((config, output_path.as_ref().to_path_buf()));
+        self.configs.push(BatchJob {
+            config,
+            path: output_path.as_ref().to_path_buf(),
+            encoding: None,
+            corruption: None,
+        });
     }
 
-    pub fn generate_all(&self) -> Result<Vec<PathBuf>> {
-        let mut generated_files = Vec::new();
+    pub fn add_with_encoding(
+        &mut self,
+        config: VideoSourceConfig,
+        encoding: EncodingConfig,
+        output_path: impl AsRef<Path>,
+    ) {
+        self.configs.push(BatchJob {
+            config,
+            path: output_path.as_ref().to_path_buf(),
+            encoding: Some(encoding),
+            corruption: None,
+        });
+    }
 
-        for (config, path) in &self.configs {
-            log::info!("Generating file: {}", path.display());
+    pub fn add_corrupted(
+        &mut self,
+        config: VideoSourceConfig,
+        encoding: EncodingConfig,
+        preset: CorruptionPreset,
+        output_path: impl AsRef<Path>,
+    ) {
+        self.configs.push(BatchJob {
+            config,
+            path: output_path.as_ref().to_path_buf(),
+            encoding: Some(encoding),
+            corruption: Some(preset),
+        });
+    }
 
-            let mut generator = FileGenerator::new(config.clone(), path);
-            generator.generate()?;
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
 
-            generated_files.push(path.clone());
-            log::info!("Successfully generated: {}", path.display());
-        }
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
 
-        Ok(generated_files)
+    pub fn generate_all(&self) -> Result<Vec<PathBuf>> {
+        self.generate_parallel(1)
     }
 
+    /// Generate with at most `max_parallel` files in flight, failing on the
+    /// first error. See [`run`](Self::run) for a full report.
     pub fn generate_parallel(&self, max_parallel: usize) -> Result<Vec<PathBuf>> {
-        use std::sync::mpsc;
-        use std::thread;
+        let report = self.run(max_parallel);
+        if let Some((path, error)) = report.failed.first() {
+            return Err(SourceVideoError::pipeline(format!(
+                "Failed to generate {}: {}",
+                path.display(),
+                error
+            )));
+        }
+        if !report.skipped.is_empty() {
+            return Err(SourceVideoError::pipeline(format!(
+                "Batch cancelled with {} files not generated",
+                report.skipped.len()
+            )));
+        }
+        Ok(report.generated.into_iter().map(|file| file.path).collect())
+    }
 
+    /// Generate every file on a pool of `workers` threads and report what
+    /// happened to each
+    pub fn run(&self, workers: usize) -> BatchReport {
+        let start = Instant::now();
+        let workers = workers.clamp(1, self.configs.len().max(1));
+        let queue: Arc<Mutex<VecDeque<_>>> = Arc::new(Mutex::new(
+            self.configs.iter().cloned().enumerate().collect(),
+        ));
         let (tx, rx) = mpsc::channel();
-        let configs = Arc::new(self.configs.clone());
-        let mut handles = Vec::new();
 
-        let chunks: Vec<_> = configs
-            .chunks(max_parallel)
-            .map(|chunk| chunk.to_vec())
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let queue = queue.clone();
+                let tx = tx.clone();
+                let storage = self.storage.clone();
+                let progress = self.progress.clone();
+                let cancel = self.cancel.clone();
+                std::thread::spawn(move || {
+                    loop {
+                        let Some((index, job)) = queue.lock().unwrap().pop_front() else {
+                            break;
+                        };
+                        let outcome = generate_one(index, job, &storage, &progress, &cancel);
+                        if tx.send((index, outcome)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
             .collect();
+        drop(tx);
 
-        for chunk in chunks {
-            let tx = tx.clone();
-            let handle = thread::spawn(move || {
-                for (config, path) in chunk {
-                    let result = FileGenerator::new(config, &path).generate();
-                    tx.send((path, result)).unwrap();
-                }
-            });
-            handles.push(handle);
+        let mut outcomes: Vec<(usize, Outcome)> = rx.into_iter().collect();
+        for handle in handles {
+            if handle.join().is_err() {
+                log::error!("File generation worker panicked");
+            }
         }
+        outcomes.sort_by_key(|(index, _)| *index);
 
-        drop(tx);
-
-        let mut generated_files = Vec::new();
-        for (path, result) in rx {
-            result?;
-            generated_files.push(path);
+        let mut report = BatchReport {
+            workers,
+            ..Default::default()
+        };
+        for (_, outcome) in outcomes {
+            match outcome {
+                Outcome::Generated(file) => report.generated.push(file),
+                Outcome::Failed(path, error) => report.failed.push((path, error)),
+                Outcome::Skipped(path) => report.skipped.push(path),
+            }
         }
+        report.elapsed = start.elapsed();
+        report
+    }
+}
 
-        for handle in handles {
-            handle.join().expect("Thread panicked");
+impl Default for BatchFileGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Outcome {
+    Generated(GeneratedFile),
+    Failed(PathBuf, String),
+    Skipped(PathBuf),
+}
+
+fn generate_one(
+    index: usize,
+    job: BatchJob,
+    storage: &Option<Arc<StorageManager>>,
+    progress: &Option<ProgressCallback>,
+    cancel: &CancelHandle,
+) -> Outcome {
+    let BatchJob {
+        config,
+        path,
+        encoding,
+        corruption,
+    } = job;
+    let notify = |update: BatchProgress| {
+        if let Some(progress) = progress {
+            progress(&update);
         }
+    };
+
+    if cancel.is_cancelled() {
+        notify(BatchProgress::Skipped {
+            index,
+            path: path.clone(),
+        });
+        return Outcome::Skipped(path);
+    }
 
-        Ok(generated_files)
+    log::info!("Generating file: {}", path.display());
+    notify(BatchProgress::Started {
+        index,
+        path: path.clone(),
+    });
+
+    let start = Instant::now();
+    let mut generator = FileGenerator::new(config, &path).with_cancel(cancel.clone());
+    if let Some(encoding) = encoding {
+        generator = generator.with_encoding(encoding);
+    }
+    if let Some(corruption) = corruption {
+        generator = generator.with_corruption(corruption);
+    }
+    if let Some(storage) = storage {
+        generator = generator.with_storage(storage.clone());
+    }
+    if let Some(progress) = progress.clone() {
+        let path = path.clone();
+        generator = generator.with_progress(move |percent| {
+            let elapsed = start.elapsed();
+            let eta =
+                (percent > 0.0).then(|| elapsed.mul_f64((100.0 - percent).max(0.0) / percent));
+            progress(&BatchProgress::Progress {
+                index,
+                path: path.clone(),
+                percent,
+                eta,
+            });
+        });
+    }
+
+    match generator.generate() {
+        Ok(()) => {
+            let elapsed = start.elapsed();
+            log::info!("Successfully generated: {}", path.display());
+            notify(BatchProgress::Completed {
+                index,
+                path: path.clone(),
+                elapsed,
+            });
+            Outcome::Generated(GeneratedFile {
+                bytes: path.metadata().map(|m| m.len()).unwrap_or(0),
+                path,
+                elapsed,
+            })
+        }
+        Err(e) => {
+            let error = e.to_string();
+            log::error!("Failed to generate {}: {}", path.display(), error);
+            notify(BatchProgress::Failed {
+                index,
+                path: path.clone(),
+                error: error.clone(),
+            });
+            Outcome::Failed(path, error)
+        }
     }
 }
 
@@ -336,4 +806,38 @@ mod tests {
 
         assert_eq!(batch.configs.len(), 2);
     }
+
+    #[test]
+    fn test_cancelled_batch_skips_files() {
+        let mut batch = BatchFileGenerator::new();
+        batch.add(VideoSourceConfig::test_pattern("a", "smpte"), "/tmp/a.mp4");
+        batch.add(VideoSourceConfig::test_pattern("b", "ball"), "/tmp/b.mp4");
+        let progress = batch.progress_channel();
+
+        batch.cancel_handle().cancel();
+        let report = batch.run(4);
+
+        assert_eq!(report.workers, 2);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(!report.is_success());
+        assert!(matches!(
+            progress.try_recv().unwrap(),
+            BatchProgress::Skipped { .. }
+        ));
+        assert!(batch.generate_parallel(2).is_err());
+        assert!(report.to_string().contains("Generated 0/2 files"));
+    }
+
+    #[test]
+    fn test_estimated_size() {
+        let mut config = VideoSourceConfig::test_pattern("test", "smpte");
+        config.duration = Some(8);
+        let generator = FileGenerator::new(config.clone(), "/tmp/test.mp4");
+        assert_eq!(generator.estimated_size(), 2_048_000);
+
+        config.duration = None;
+        config.num_buffers = Some(240);
+        let generator = FileGenerator::new(config, "/tmp/test.mp4");
+        assert_eq!(generator.estimated_size(), 2_048_000);
+    }
 }
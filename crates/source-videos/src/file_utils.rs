@@ -1,8 +1,26 @@
 use crate::config_types::FileContainer;
 use crate::error::{Result, SourceVideoError};
 use mime_guess::from_path;
+use serde::de::DeserializeOwned;
 use std::path::{Path, PathBuf};
 
+/// Read `path` as JSON if its extension says so and as TOML otherwise;
+/// errors name it as a `what`
+pub fn load_toml_or_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let content = std::fs::read_to_string(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let parsed = if is_json {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&content).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|e| {
+        SourceVideoError::config(format!("Invalid {} {}: {}", what, path.display(), e))
+    })
+}
+
 /// Common video file extensions
 const VIDEO_EXTENSIONS: &[&str] = &[
     "mp4", "avi", "mkv", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "3gp", "ogv", "ts",
@@ -41,6 +59,7 @@ pub fn detect_container_format(path: &Path) -> Option<FileContainer> {
         "mkv" | "mka" => Some(FileContainer::Mkv),
         "avi" | "divx" => Some(FileContainer::Avi),
         "webm" => Some(FileContainer::WebM),
+        "ts" | "m2ts" | "mts" => Some(FileContainer::MpegTs),
         _ => None,
     }
 }
@@ -237,6 +256,10 @@ mod tests {
             detect_container_format(Path::new("stream.webm")),
             Some(FileContainer::WebM)
         );
+        assert_eq!(
+            detect_container_format(Path::new("capture.ts")),
+            Some(FileContainer::MpegTs)
+        );
         assert_eq!(detect_container_format(Path::new("unknown.xyz")), None);
     }
 
@@ -1,47 +1,102 @@
 #![allow(unused)]
 
+#[cfg(feature = "api")]
 pub mod api;
 pub mod auto_repeat;
+pub mod avsync;
+pub mod bus;
+#[cfg(feature = "network-sim")]
+pub mod chaos;
 pub mod config;
 pub mod config_types;
+pub mod corrupt;
 pub mod directory;
+pub mod duplicates;
+pub mod embedded;
+pub mod encoding;
 pub mod error;
+pub mod exposition;
+pub mod farm;
 pub mod file;
 pub mod file_source;
 pub mod file_utils;
 pub mod manager;
+pub mod markers;
 pub mod network;
+pub mod operation;
 pub mod patterns;
 pub mod pipeline;
+pub mod ports;
+pub mod preview;
+#[cfg(feature = "repl")]
 pub mod repl;
+pub mod rotation;
 pub mod rtsp;
 pub mod runtime;
+pub mod scene;
+pub mod service;
+pub mod shutdown;
 pub mod source;
+pub mod storage;
+pub mod testing;
 pub mod watch;
 
 pub use auto_repeat::{
     AutoRepeatManager, LoopConfig, LoopingVideoSource, create_looping_source,
     enable_auto_repeat_for_source,
 };
+pub use avsync::AvSyncSignal;
+pub use bus::{Envelope, EventHub, Subscription, SystemEvent, Topic};
+#[cfg(feature = "network-sim")]
+pub use chaos::{ChaosConfig, ChaosController, ChaosEvent, ChaosStatus};
 pub use config_types::{
-    AppConfig, DirectoryConfig, FileListConfig, FilterConfig, RtspServerConfig, VideoSourceConfig,
-    VideoSourceType, WatchConfig,
+    AppConfig, DirectoryConfig, FileListConfig, FilterConfig, NamespaceConfig, RtspServerConfig,
+    VideoSourceConfig, VideoSourceType, WatchConfig,
 };
-pub use directory::{BatchSourceLoader, DirectoryScanner};
+pub use corrupt::CorruptionPreset;
+pub use directory::{BatchSourceLoader, DirectoryScanner, ScanStream};
+pub use duplicates::{DuplicateGroup, DuplicatePolicy};
+pub use embedded::{EmbeddedEvent, EmbeddedServer, EmbeddedServerBuilder};
+pub use encoding::{EncodingConfig, EncodingMatrix, VideoCodec};
 pub use error::{Result, SourceVideoError};
-pub use file::{BatchFileGenerator, FileGenerator, generate_test_file};
+pub use farm::{FarmConfig, FarmInstanceInfo, ServerFarm};
+pub use file::{
+    BatchFileGenerator, BatchProgress, BatchReport, CancelHandle, FileGenerator, GeneratedFile,
+    default_workers, generate_test_file,
+};
 pub use file_source::{FileSourceFactory, FileVideoSource};
 pub use file_utils::{VideoMetadata, detect_container_format, is_video_file, path_to_mount_point};
-pub use manager::{ManagerSnapshot, SourceInfo, SourceManagerBuilder, VideoSourceManager};
+pub use manager::{
+    ManagerSnapshot, SourceInfo, SourceManagerBuilder, SyncedStart, VideoSourceManager,
+};
+pub use markers::MarkerKind;
+pub use operation::{Operation, OperationRegistry, OperationStatus};
 pub use patterns::{PatternRotator, TestPattern};
+pub use ports::{BoundEndpoint, BoundPorts};
+pub use preview::{PreviewCache, PreviewSettings};
+#[cfg(feature = "repl")]
 pub use repl::{EnhancedRepl, ReplContext};
+pub use rotation::{PatternController, PatternStatus, RotationSchedule};
+pub use rtsp::dvr::{DvrConfig, DvrStatus};
+pub use rtsp::playback::PlaybackStatus;
+pub use rtsp::shaping::{ClientShaper, ShapingRule};
+pub use rtsp::traffic::TrafficLog;
+pub use rtsp::transport::{TransportLog, TransportMode};
 pub use rtsp::{RtspServer, RtspServerBuilder, create_test_rtsp_server};
 pub use runtime::{RuntimeManager, events::ConfigurationEvent};
-pub use source::{SourceState, VideoSource};
+pub use scene::{Annotation, SceneConfig, SceneGenerator, SceneObject, Trajectory};
+pub use shutdown::{ShutdownCoordinator, ShutdownReport, ShutdownToken};
+pub use source::{SourceState, SourceUpdate, VideoSource};
+pub use storage::{CleanupPolicy, StorageConfig, StorageEvent, StorageManager, StorageStatus};
+pub use testing::{RtspStreamGuard, TestRtspStream};
 pub use watch::events::{
     EventFilter, EventRouter, FileEventHandler, FileEventMetadata, FileSystemEvent,
 };
-pub use watch::{DirectoryWatcher, FileWatcher, WatcherManager};
+#[cfg(feature = "watch")]
+pub use watch::{
+    DirectoryWatcher, FileWatcher, WatchEventCounts, WatchEventReceiver, WatcherManager,
+    WatcherStatus,
+};
 
 use once_cell::sync::OnceCell;
 
@@ -180,6 +235,22 @@ impl SourceVideos {
         }
     }
 
+    /// Apply a watcher's event to the served sources, once the RTSP
+    /// server is running
+    pub fn handle_file_event(&mut self, event: &FileSystemEvent) -> Result<()> {
+        match &mut self.rtsp_server {
+            Some(server) => server.handle_file_event(event),
+            None => {
+                log::debug!(
+                    "No RTSP server, ignoring {} event for {}",
+                    event.event_type(),
+                    event.path().display()
+                );
+                Ok(())
+            }
+        }
+    }
+
     pub fn manager(&self) -> &VideoSourceManager {
         &self.manager
     }
@@ -12,12 +12,13 @@ use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::{Shell, generate};
 use gstreamer::glib::prelude::*;
 use regex::Regex;
-use tokio::signal;
 use tokio::sync::RwLock;
 
 use source_videos::{
-    AppConfig, EnhancedRepl, Result, SourceVideoError, SourceVideos, TestPattern,
-    VideoSourceConfig, api::ControlApi, create_test_rtsp_server, generate_test_file,
+    AppConfig, BatchFileGenerator, BatchProgress, BoundEndpoint, BoundPorts, DuplicatePolicy,
+    EnhancedRepl, Result, ShutdownCoordinator, SourceVideoError, SourceVideos, StorageConfig,
+    StorageManager, TestPattern, TransportMode, VideoSourceConfig, api::ControlApi,
+    create_test_rtsp_server, default_workers, service::SystemdNotifier, shutdown::wait_for_signal,
 };
 
 #[derive(Parser)]
@@ -48,6 +49,9 @@ EXAMPLES:
   # Monitor directory changes in real-time
   source-videos monitor -d /videos --recursive --metrics
 
+  # Simulate 16 cameras on ports 8554-8569 with a control API
+  source-videos farm --instances 16 --base-port 8554 --api
+
   # Generate shell completions
   source-videos completions bash > /etc/bash_completion.d/source-videos
 ")]
@@ -63,6 +67,81 @@ struct Cli {
     config: Option<PathBuf>,
 }
 
+#[derive(clap::Args)]
+struct GenerateArgs {
+    /// Test patterns; several comma-separated patterns produce one file
+    /// each, named <output stem>-<pattern>
+    #[arg(short, long, default_value = "smpte", value_delimiter = ',')]
+    pattern: Vec<String>,
+
+    #[arg(short, long, default_value_t = 10)]
+    duration: u64,
+
+    /// Output file, or output directory with --matrix
+    #[arg(short, long)]
+    output: PathBuf,
+
+    #[arg(long, default_value_t = 1920)]
+    width: u32,
+
+    #[arg(long, default_value_t = 1080)]
+    height: u32,
+
+    #[arg(long, default_value_t = 30)]
+    fps: i32,
+
+    /// Files generated concurrently (defaults to the number of CPU cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Video codec: h264, h265, vp8 or vp9
+    #[arg(long)]
+    codec: Option<String>,
+
+    /// Container: mp4, fmp4, mkv, avi, webm or mpegts (defaults to the
+    /// output extension)
+    #[arg(long)]
+    container: Option<String>,
+
+    /// Target bitrate in kbit/s
+    #[arg(long, conflicts_with = "crf")]
+    bitrate: Option<u32>,
+
+    /// Constant quality level
+    #[arg(long)]
+    crf: Option<u32>,
+
+    /// Raw format fed to the encoder, e.g. I420, NV12 or Y444
+    #[arg(long)]
+    pixel_format: Option<String>,
+
+    /// TOML or JSON file listing codecs, containers, bitrates, CRFs and
+    /// pixel formats; every compatible combination is generated into the
+    /// output directory
+    #[arg(long, conflicts_with_all = ["codec", "container", "bitrate", "crf", "pixel_format"])]
+    matrix: Option<PathBuf>,
+
+    /// Deliberately broken output for resilience tests: truncated_moov,
+    /// missing_keyframe, vfr, rotated, broken_index or all. Each preset
+    /// produces <output stem>-<pattern>-<preset>
+    #[arg(long, value_delimiter = ',', conflicts_with = "matrix")]
+    corrupt: Vec<String>,
+
+    /// Render a synthetic scene described in a TOML or JSON file, writing
+    /// COCO and MOT ground truth next to the video. The pattern "scene"
+    /// renders a random scene instead.
+    #[arg(long, conflicts_with_all = ["matrix", "corrupt"])]
+    scene: Option<PathBuf>,
+
+    /// Objects in a random scene
+    #[arg(long, default_value_t = 5)]
+    objects: usize,
+
+    /// Seed for a random scene
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
 #[derive(Clone, Copy, ValueEnum)]
 enum PlaylistMode {
     Sequential,
@@ -87,18 +166,40 @@ enum OutputFormat {
 #[derive(Subcommand)]
 enum Commands {
     Serve {
-        #[arg(short, long, default_value_t = 8554)]
+        #[arg(
+            short,
+            long,
+            default_value_t = 8554,
+            help = "RTSP port (0 = pick a free port)"
+        )]
         port: u16,
 
         #[arg(long, help = "Enable REST API server")]
         api: bool,
 
-        #[arg(long, default_value_t = 3000, help = "API server port")]
+        #[arg(
+            long,
+            default_value_t = 3000,
+            help = "API server port (0 = pick a free port)"
+        )]
         api_port: u16,
 
         #[arg(long, default_value = "0.0.0.0", help = "API server bind address")]
         api_address: String,
 
+        #[arg(
+            long = "port-retries",
+            default_value_t = 0,
+            help = "Try up to N following ports when the RTSP or API port is in use"
+        )]
+        port_retries: u16,
+
+        #[arg(
+            long = "report-ports",
+            help = "Print the bound ports as a single JSON line on stdout once listening"
+        )]
+        report_ports: bool,
+
         #[arg(short, long, default_value = "0.0.0.0")]
         address: String,
 
@@ -143,6 +244,13 @@ enum Commands {
         #[arg(long = "mount-prefix", help = "Prefix for RTSP mount points")]
         mount_prefix: Option<String>,
 
+        #[arg(
+            long = "duplicates",
+            default_value = "keep",
+            help = "Files with identical content: keep, skip or alias"
+        )]
+        duplicates: DuplicatePolicy,
+
         #[arg(long = "lazy", help = "Enable lazy loading of sources")]
         lazy_loading: bool,
 
@@ -219,26 +327,60 @@ enum Commands {
             value_delimiter = ','
         )]
         per_source_network: Vec<String>,
-    },
-    Generate {
-        #[arg(short, long, default_value = "smpte")]
-        pattern: String,
 
-        #[arg(short, long, default_value_t = 10)]
-        duration: u64,
+        #[arg(
+            long = "client-bandwidth",
+            help = "Per-client bandwidth in kbps (format: client_ip=kbps, '*' for all other clients)",
+            value_delimiter = ','
+        )]
+        client_bandwidth: Vec<String>,
 
-        #[arg(short, long)]
-        output: PathBuf,
+        #[arg(
+            long = "transport",
+            default_value = "any",
+            help = "RTP transports clients may use: any, tcp (interleaved) or udp"
+        )]
+        transport: TransportMode,
 
-        #[arg(long, default_value_t = 1920)]
-        width: u32,
+        #[arg(
+            long = "mount-transport",
+            help = "Per-mount transport (format: mount:any|tcp|udp)",
+            value_delimiter = ','
+        )]
+        mount_transport: Vec<String>,
 
-        #[arg(long, default_value_t = 1080)]
-        height: u32,
+        #[arg(
+            long = "dvr",
+            help = "Keep a time-shift buffer of a mount, served as HLS by the API (format: mount:minutes)",
+            value_delimiter = ','
+        )]
+        dvr: Vec<String>,
+
+        #[arg(
+            long = "chaos",
+            help = "Inject random faults into the mounts: drops, degraded profiles, removals and EOS"
+        )]
+        chaos: bool,
 
-        #[arg(long, default_value_t = 30)]
-        fps: i32,
+        #[arg(
+            long = "chaos-seed",
+            help = "Seed for --chaos, to get the faults of an earlier run again"
+        )]
+        chaos_seed: Option<u64>,
+
+        #[arg(
+            long = "chaos-log",
+            help = "Append chaos events to a file as JSON lines"
+        )]
+        chaos_log: Option<PathBuf>,
+
+        #[arg(
+            long = "chaos-replay",
+            help = "Replay the chaos events of a --chaos-log file"
+        )]
+        chaos_replay: Option<PathBuf>,
     },
+    Generate(GenerateArgs),
     List,
     Interactive,
     Test {
@@ -415,6 +557,50 @@ enum Commands {
         metrics: bool,
     },
 
+    /// Launch a farm of RTSP servers across a port range to simulate many cameras
+    Farm {
+        #[arg(long, default_value_t = 4, help = "Number of RTSP server instances")]
+        instances: usize,
+
+        #[arg(long = "base-port", default_value_t = 8554)]
+        base_port: u16,
+
+        #[arg(long = "max-port", help = "Highest port the farm may use")]
+        max_port: Option<u16>,
+
+        #[arg(
+            long = "addresses",
+            value_delimiter = ',',
+            default_value = "0.0.0.0",
+            help = "Bind addresses; instances are spread across them round-robin"
+        )]
+        addresses: Vec<String>,
+
+        #[arg(long, value_delimiter = ',', default_value = "smpte,ball,snow")]
+        patterns: Vec<String>,
+
+        #[arg(long = "sources-per-instance", default_value_t = 1)]
+        sources_per_instance: usize,
+
+        #[arg(long, help = "Enable REST API server")]
+        api: bool,
+
+        #[arg(long, default_value_t = 3000, help = "API server port")]
+        api_port: u16,
+
+        #[arg(long, default_value = "0.0.0.0", help = "API server bind address")]
+        api_address: String,
+
+        #[arg(long)]
+        duration: Option<u64>,
+    },
+
+    /// Run `serve` under systemd or as a Windows service
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+
     /// Generate shell completions
     Completions {
         #[arg(value_enum)]
@@ -425,6 +611,64 @@ enum Commands {
     HelpAll,
 }
 
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// Print a systemd unit (Type=notify) that runs `serve` with the given arguments
+    SystemdUnit {
+        #[arg(short, long, help = "Write the unit to this file instead of stdout")]
+        output: Option<PathBuf>,
+
+        #[arg(long, help = "User to run the service as")]
+        user: Option<String>,
+
+        #[arg(long, default_value = "on-failure", help = "systemd Restart= policy")]
+        restart: String,
+
+        #[arg(long, default_value_t = 5)]
+        restart_sec: u64,
+
+        #[arg(long, help = "Enable the systemd watchdog with this interval")]
+        watchdog_sec: Option<u64>,
+
+        /// Arguments passed to `serve`
+        #[arg(last = true)]
+        serve_args: Vec<String>,
+    },
+
+    /// Register `serve` as an auto-restarting Windows service
+    Install {
+        #[arg(long, default_value = source_videos::service::DEFAULT_SERVICE_NAME)]
+        name: String,
+
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "Seconds before restarting after a failure"
+        )]
+        restart_delay: u64,
+
+        /// Arguments passed to `serve`
+        #[arg(last = true)]
+        serve_args: Vec<String>,
+    },
+
+    /// Stop and remove the Windows service
+    Uninstall {
+        #[arg(long, default_value = source_videos::service::DEFAULT_SERVICE_NAME)]
+        name: String,
+    },
+
+    /// Entry point used by the Windows Service Control Manager
+    #[command(hide = true)]
+    Run {
+        #[arg(long, default_value = source_videos::service::DEFAULT_SERVICE_NAME)]
+        name: String,
+
+        #[arg(last = true)]
+        serve_args: Vec<String>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -446,76 +690,10 @@ async fn main() -> Result<()> {
     };
 
     match cli.command {
-        Commands::Serve {
-            port,
-            api,
-            api_port,
-            api_address,
-            address,
-            duration,
-            patterns,
-            directory,
-            recursive,
-            files,
-            include,
-            exclude,
-            mount_prefix,
-            lazy_loading,
-            watch,
-            auto_repeat,
-            reload_on_change,
-            watch_interval_ms,
-            max_loops,
-            seamless_loop,
-            network_profile,
-            network_scenario,
-            packet_loss,
-            latency_ms,
-            bandwidth_kbps,
-            jitter_ms,
-            network_drop,
-            per_source_network,
-        } => {
-            serve_command(
-                port,
-                api,
-                api_port,
-                api_address,
-                address,
-                duration,
-                patterns,
-                directory,
-                recursive,
-                files,
-                include,
-                exclude,
-                mount_prefix,
-                lazy_loading,
-                watch,
-                auto_repeat,
-                reload_on_change,
-                watch_interval_ms,
-                max_loops,
-                seamless_loop,
-                network_profile,
-                network_scenario,
-                packet_loss,
-                latency_ms,
-                bandwidth_kbps,
-                jitter_ms,
-                network_drop,
-                per_source_network,
-            )
-            .await
+        command @ Commands::Serve { .. } => {
+            run_serve(command, Arc::new(ShutdownCoordinator::default())).await
         }
-        Commands::Generate {
-            pattern,
-            duration,
-            output,
-            width,
-            height,
-            fps,
-        } => generate_command(pattern, duration, output, width, height, fps).await,
+        Commands::Generate(args) => generate_command(args, config.storage).await,
         Commands::List => list_command().await,
         Commands::Interactive => enhanced_interactive_command().await,
         Commands::Test { port } => test_command(port).await,
@@ -635,16 +813,137 @@ async fn main() -> Result<()> {
             )
             .await
         }
+        Commands::Farm {
+            instances,
+            base_port,
+            max_port,
+            addresses,
+            patterns,
+            sources_per_instance,
+            api,
+            api_port,
+            api_address,
+            duration,
+        } => {
+            farm_command(
+                instances,
+                base_port,
+                max_port,
+                addresses,
+                patterns,
+                sources_per_instance,
+                api,
+                api_port,
+                api_address,
+                duration,
+            )
+            .await
+        }
+        Commands::Service { action } => service_command(action).await,
         Commands::Completions { shell } => completions_command(shell).await,
         Commands::HelpAll => help_all_command().await,
     }
 }
 
+/// Run the `serve` subcommand with an externally owned shutdown coordinator.
+async fn run_serve(command: Commands, coordinator: Arc<ShutdownCoordinator>) -> Result<()> {
+    match command {
+        Commands::Serve {
+            port,
+            api,
+            api_port,
+            api_address,
+            port_retries,
+            report_ports,
+            address,
+            duration,
+            patterns,
+            directory,
+            recursive,
+            files,
+            include,
+            exclude,
+            mount_prefix,
+            duplicates,
+            lazy_loading,
+            watch,
+            auto_repeat,
+            reload_on_change,
+            watch_interval_ms,
+            max_loops,
+            seamless_loop,
+            network_profile,
+            network_scenario,
+            packet_loss,
+            latency_ms,
+            bandwidth_kbps,
+            jitter_ms,
+            network_drop,
+            per_source_network,
+            client_bandwidth,
+            transport,
+            mount_transport,
+            dvr,
+            chaos,
+            chaos_seed,
+            chaos_log,
+            chaos_replay,
+        } => {
+            serve_command(
+                port,
+                api,
+                api_port,
+                api_address,
+                port_retries,
+                report_ports,
+                address,
+                duration,
+                patterns,
+                directory,
+                recursive,
+                files,
+                include,
+                exclude,
+                mount_prefix,
+                duplicates,
+                lazy_loading,
+                watch,
+                auto_repeat,
+                reload_on_change,
+                watch_interval_ms,
+                max_loops,
+                seamless_loop,
+                network_profile,
+                network_scenario,
+                packet_loss,
+                latency_ms,
+                bandwidth_kbps,
+                jitter_ms,
+                network_drop,
+                per_source_network,
+                client_bandwidth,
+                transport,
+                mount_transport,
+                dvr,
+                chaos,
+                chaos_seed,
+                chaos_log,
+                chaos_replay,
+                coordinator,
+            )
+            .await
+        }
+        _ => unreachable!("run_serve called with a non-serve command"),
+    }
+}
+
 async fn serve_command(
     port: u16,
     api: bool,
     api_port: u16,
     api_address: String,
+    port_retries: u16,
+    report_ports: bool,
     address: String,
     duration: Option<u64>,
     patterns: Vec<String>,
@@ -654,6 +953,7 @@ async fn serve_command(
     include: Vec<String>,
     exclude: Vec<String>,
     mount_prefix: Option<String>,
+    duplicates: DuplicatePolicy,
     lazy_loading: bool,
     watch: bool,
     auto_repeat: bool,
@@ -669,14 +969,24 @@ async fn serve_command(
     jitter_ms: Option<u32>,
     network_drop: Option<String>,
     per_source_network: Vec<String>,
+    client_bandwidth: Vec<String>,
+    transport: TransportMode,
+    mount_transport: Vec<String>,
+    dvr: Vec<String>,
+    chaos: bool,
+    chaos_seed: Option<u64>,
+    chaos_log: Option<PathBuf>,
+    chaos_replay: Option<PathBuf>,
+    coordinator: Arc<ShutdownCoordinator>,
 ) -> Result<()> {
     use source_videos::network::{
         GStreamerNetworkSimulator, NetworkConditions, NetworkController, NetworkProfile,
         NetworkScenario, ScenarioPlayer,
     };
     use source_videos::{
-        DirectoryConfig, DirectoryScanner, FileListConfig, FilterConfig, LoopConfig,
-        RtspServerBuilder, VideoSourceManager, WatcherManager, create_looping_source,
+        ChaosConfig, ChaosController, DirectoryConfig, DirectoryScanner, DvrConfig, EventFilter,
+        FileListConfig, FilterConfig, LoopConfig, RtspServerBuilder, VideoSourceManager,
+        WatcherManager, create_looping_source,
     };
     use std::str::FromStr;
     use std::time::Duration;
@@ -761,6 +1071,26 @@ async fn serve_command(
         }
     }
 
+    // Parse per-client bandwidth shaping
+    let mut client_rates = Vec::new();
+    for spec in client_bandwidth {
+        match spec
+            .split_once('=')
+            .map(|(host, kbps)| (host, kbps.parse::<u32>()))
+        {
+            Some((host, Ok(kbps))) => {
+                println!("Clients from '{}' will be shaped to {} kbps", host, kbps);
+                client_rates.push((host.to_string(), kbps));
+            }
+            _ => {
+                eprintln!(
+                    "Invalid client-bandwidth format: '{}' (expected 'client_ip=kbps')",
+                    spec
+                );
+            }
+        }
+    }
+
     // Parse network drop simulation
     let network_drop_config = if let Some(drop_spec) = network_drop {
         let parts: Vec<&str> = drop_spec.split(',').collect();
@@ -793,7 +1123,49 @@ async fn serve_command(
     };
 
     // Build server with initial patterns
-    let mut server_builder = RtspServerBuilder::new().port(port);
+    let mut server_builder = RtspServerBuilder::new()
+        .port(port)
+        .port_retries(port_retries)
+        .address(address.clone());
+
+    for (host, kbps) in &client_rates {
+        server_builder = server_builder.client_bandwidth(host, *kbps);
+    }
+
+    server_builder = server_builder.transport(transport);
+    for spec in &mount_transport {
+        match spec
+            .split_once(':')
+            .map(|(mount, mode)| (mount, mode.parse::<TransportMode>()))
+        {
+            Some((mount, Ok(mode))) => {
+                println!("Mount '{}' accepts {:?} transport", mount, mode);
+                server_builder = server_builder.mount_transport(mount, mode);
+            }
+            Some((_, Err(e))) => eprintln!("Invalid mount-transport '{}': {}", spec, e),
+            None => eprintln!(
+                "Invalid mount-transport format: '{}' (expected 'mount:tcp')",
+                spec
+            ),
+        }
+    }
+
+    for spec in &dvr {
+        match spec
+            .split_once(':')
+            .map(|(mount, minutes)| (mount, minutes.parse::<u32>()))
+        {
+            Some((mount, Ok(minutes))) => {
+                println!(
+                    "Mount '{}' keeps the last {} minutes for DVR",
+                    mount, minutes
+                );
+                server_builder = server_builder.mount_dvr(mount, DvrConfig::minutes(minutes));
+            }
+            Some((_, Err(e))) => eprintln!("Invalid dvr '{}': {}", spec, e),
+            None => eprintln!("Invalid dvr format: '{}' (expected 'mount:5')", spec),
+        }
+    }
 
     // Apply global network profile if set
     if let Some(profile) = global_network_profile {
@@ -828,8 +1200,14 @@ async fn serve_command(
         }
     }
 
-    // Scan directory for video files if specified
-    if let Some(ref dir_path) = directory {
+    // Scan directory for video files if specified. A watched directory is
+    // not scanned here: its watcher reports the files already in it as
+    // initial events, handled like the changes that follow.
+    let mut aliases = Vec::new();
+    if watch && (mount_prefix.is_some() || duplicates != DuplicatePolicy::Keep) {
+        eprintln!("--mount-prefix and --duplicates are ignored with --watch");
+    }
+    if let Some(ref dir_path) = directory.as_ref().filter(|_| !watch) {
         let filters = if !include.is_empty() || !exclude.is_empty() {
             Some(FilterConfig {
                 include: include.clone(),
@@ -853,10 +1231,19 @@ async fn serve_command(
             dir_path.display(),
             recursive
         );
-        let mut scanner = DirectoryScanner::new(dir_config);
+        let mut scanner = DirectoryScanner::new(dir_config).with_duplicates(duplicates);
         let source_configs = scanner.scan()?;
 
         println!("Found {} video files in directory", source_configs.len());
+        let copies: usize = scanner
+            .duplicates()
+            .iter()
+            .map(|g| g.duplicates.len())
+            .sum();
+        if copies > 0 {
+            println!("Skipped {} duplicate files", copies);
+        }
+        aliases = scanner.aliases();
 
         for config in source_configs {
             server_builder = server_builder.add_source(config);
@@ -900,6 +1287,7 @@ async fn serve_command(
                 duration: None,
                 num_buffers: None,
                 is_live: false,
+                tags: Vec::new(),
             };
 
             server_builder = server_builder.add_source(config);
@@ -909,10 +1297,14 @@ async fn serve_command(
 
     // Build and start the server
     let mut server = server_builder.build()?;
+    for (alias, source) in aliases {
+        server.add_alias(&alias, &source)?;
+    }
 
     // Create shared state for API if enabled
     let rtsp_server_arc = Arc::new(RwLock::new(server));
     let source_manager_arc = Arc::new(VideoSourceManager::new());
+    let chaos_controller = ChaosController::new(rtsp_server_arc.clone(), chaos_log);
 
     // Set up file watching if enabled
     let watcher_manager_arc = if watch && directory.is_some() {
@@ -920,7 +1312,18 @@ async fn serve_command(
         let mut manager = WatcherManager::new();
 
         if let Some(ref dir_path) = directory {
-            let watcher_id = manager.add_directory_watcher(dir_path, recursive).await?;
+            let watcher_id = manager
+                .add_synced_directory_watcher(dir_path, recursive)
+                .await?;
+            if !include.is_empty() || !exclude.is_empty() {
+                let filter = include
+                    .iter()
+                    .fold(EventFilter::new(), |f, p| f.with_include_pattern(p.clone()));
+                let filter = exclude
+                    .iter()
+                    .fold(filter, |f, p| f.with_exclude_pattern(p.clone()));
+                manager.set_filter(&watcher_id, Some(filter))?;
+            }
             println!(
                 "Started watching directory: {} (ID: {})",
                 dir_path.display(),
@@ -933,41 +1336,86 @@ async fn serve_command(
         Arc::new(RwLock::new(WatcherManager::new()))
     };
 
+    let shutdown_token = coordinator.token();
+
+    {
+        let rtsp_server = rtsp_server_arc.clone();
+        coordinator.register("rtsp_server", move || async move {
+            rtsp_server.read().await.stop()
+        });
+    }
+
     // Start API server if enabled
-    let api_handle = if api {
+    let mut bound_ports = BoundPorts::default();
+    if api {
         let api_bind_address: std::net::SocketAddr = format!("{}:{}", api_address, api_port)
             .parse()
             .map_err(|e| SourceVideoError::config(format!("Invalid API address: {}", e)))?;
 
-        let api_server = ControlApi::new(
+        let mut api_server = ControlApi::new(
             Some(rtsp_server_arc.clone()),
             source_manager_arc.clone(),
             watcher_manager_arc.clone(),
-        )?;
+        )?
+        .with_chaos(chaos_controller.clone());
+        api_server.set_bind_address(api_bind_address);
+        api_server.set_port_retries(port_retries);
 
-        println!("Starting API server on http://{}:{}", api_address, api_port);
-        println!(
-            "API documentation available at http://{}:{}/api/docs",
-            api_address, api_port
-        );
+        let listener = api_server.bind().await?;
+        let bound = api_server.local_address();
+        bound_ports.api = Some(BoundEndpoint {
+            address: bound.ip().to_string(),
+            port: bound.port(),
+        });
 
-        let mut api_server = api_server;
-        api_server.set_bind_address(api_bind_address);
+        println!("Starting API server on http://{}", bound);
+        println!("API documentation available at http://{}/api/docs", bound);
 
-        Some(tokio::spawn(async move {
-            if let Err(e) = api_server.bind_and_serve().await {
+        let api_token = shutdown_token.clone();
+        let handle = tokio::spawn(async move {
+            if let Err(e) = api_server
+                .serve_listener(listener, async move { api_token.cancelled().await })
+                .await
+            {
                 eprintln!("API server error: {}", e);
             }
-        }))
-    } else {
-        None
-    };
+        });
+        coordinator.register_task("api_server", handle);
+    }
 
-    let mut watcher_manager = if watch && directory.is_some() {
-        Some(watcher_manager_arc.clone())
-    } else {
-        None
-    };
+    // Events are read outside the manager's lock, so the API can pause,
+    // resume and filter watchers while the task waits for the next one
+    if watch && directory.is_some() {
+        if let Some(mut events) = watcher_manager_arc.write().await.take_receiver() {
+            let rtsp_server = rtsp_server_arc.clone();
+            let handle = tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    println!(
+                        "File system event: {:?} - {}",
+                        event.event_type(),
+                        event.path().display()
+                    );
+
+                    // Handle the event through the RTSP server directly
+                    if let Err(e) = rtsp_server.write().await.handle_file_event(&event) {
+                        eprintln!("Error handling file event: {}", e);
+                    }
+                }
+            });
+            // Stopping the watchers ends the task
+            let watchers = watcher_manager_arc.clone();
+            coordinator.register("file_watcher", move || async move {
+                {
+                    let mut watchers = watchers.write().await;
+                    watchers.close();
+                    watchers.stop_all().await?;
+                }
+                handle.await.map_err(|e| {
+                    SourceVideoError::resource(format!("File event task failed: {}", e))
+                })
+            });
+        }
+    }
 
     // Print auto-repeat configuration
     if auto_repeat {
@@ -988,9 +1436,57 @@ async fn serve_command(
         let mut server = rtsp_server_arc.write().await;
         server.start()?;
 
+        if server.get_port() != port {
+            println!("RTSP server bound to port {}", server.get_port());
+        }
         for mount in server.list_sources() {
             println!("Stream available at: {}", server.get_url(&mount));
         }
+
+        bound_ports.rtsp = Some(BoundEndpoint {
+            address: server.get_address().to_string(),
+            port: server.get_port(),
+        });
+    }
+
+    if let Some(path) = &chaos_replay {
+        let events = source_videos::chaos::load_timeline(path)?;
+        println!(
+            "Replaying {} chaos events from {}",
+            events.len(),
+            path.display()
+        );
+        chaos_controller.replay(events)?;
+    } else if chaos {
+        let seed = chaos_controller.start(ChaosConfig {
+            seed: chaos_seed,
+            ..ChaosConfig::default()
+        })?;
+        println!("Chaos mode enabled (seed {})", seed);
+    }
+    {
+        // Registered after the RTSP server so its faults are undone first
+        let chaos = chaos_controller.clone();
+        coordinator.register("chaos", move || async move {
+            chaos.stop().await;
+            Ok(())
+        });
+    }
+
+    if report_ports {
+        println!("{}", bound_ports.to_json_line());
+    }
+
+    // Tell systemd we are up once the RTSP server is accepting clients
+    let notifier = SystemdNotifier::from_env();
+    if let Err(e) = notifier.ready() {
+        log::warn!("{}", e);
+    }
+    if let Some(rtsp) = &bound_ports.rtsp {
+        let _ = notifier.status(&format!("Serving RTSP on {}:{}", rtsp.address, rtsp.port));
+    }
+    if let Some(handle) = notifier.spawn_watchdog(shutdown_token.clone()) {
+        coordinator.register_task("systemd_watchdog", handle);
     }
 
     // Set up network scenario player if configured
@@ -1039,7 +1535,7 @@ async fn serve_command(
         println!("Server will run for {} seconds", duration);
         let end_time = std::time::Instant::now() + Duration::from_secs(duration);
 
-        while std::time::Instant::now() < end_time {
+        while std::time::Instant::now() < end_time && !shutdown_token.is_cancelled() {
             // Iterate the GLib main context
             main_context.iteration(false);
 
@@ -1084,27 +1580,13 @@ async fn serve_command(
         println!("Press Ctrl+C to stop the server");
 
         tokio::select! {
-            _ = signal::ctrl_c() => {
-                println!("Received Ctrl+C, stopping...");
+            _ = wait_for_signal(&shutdown_token) => {
+                println!("Shutdown requested, stopping...");
             }
             _ = async {
                 loop {
                     main_context.iteration(false);
 
-                    // Check for file system events if watching is enabled
-                    if let Some(ref manager_arc) = watcher_manager {
-                        let mut manager = manager_arc.write().await;
-                        if let Some(event) = manager.recv().await {
-                            println!("File system event: {:?} - {}", event.event_type(), event.path().display());
-
-                            // Handle the event through the RTSP server directly
-                            let mut server = rtsp_server_arc.write().await;
-                            if let Err(e) = server.handle_file_event(&event) {
-                                eprintln!("Error handling file event: {}", e);
-                            }
-                        }
-                    }
-
                     // Update network scenario if active
                     if let Some(ref player) = scenario_player {
                         player.update();
@@ -1138,34 +1620,257 @@ async fn serve_command(
         }
     }
 
+    let _ = notifier.stopping();
+    finish_shutdown(&coordinator).await;
     println!("Server stopped");
     Ok(())
 }
 
-async fn generate_command(
-    pattern: String,
-    duration: u64,
-    output: PathBuf,
-    width: u32,
-    height: u32,
-    fps: i32,
-) -> Result<()> {
-    println!("Generating test video with pattern '{}'", pattern);
-    println!("Output: {}", output.display());
+/// Stop all registered components and report those that did not stop cleanly.
+async fn finish_shutdown(coordinator: &ShutdownCoordinator) {
+    let report = coordinator.shutdown().await;
+
+    for (component, reason) in &report.failed {
+        eprintln!("Component '{}' failed to stop: {}", component, reason);
+    }
+
+    if report.is_clean() {
+        println!("All components stopped cleanly in {}ms", report.elapsed_ms);
+    }
+}
+
+async fn generate_command(args: GenerateArgs, storage: Option<StorageConfig>) -> Result<()> {
+    use source_videos::{
+        CorruptionPreset, EncodingConfig, EncodingMatrix, VideoCodec, config_types::FileContainer,
+        detect_container_format,
+    };
+
+    let GenerateArgs {
+        pattern: patterns,
+        duration,
+        output,
+        width,
+        height,
+        fps,
+        jobs,
+        ..
+    } = &args;
+    let (duration, width, height, fps) = (*duration, *width, *height, *fps);
+
+    if args.scene.is_some() || patterns.iter().any(|pattern| pattern == "scene") {
+        return generate_scene_command(&args);
+    }
+
+    let source_config = |pattern: &str| {
+        let mut config = VideoSourceConfig::test_pattern(pattern, pattern);
+        config.duration = Some(duration);
+        config.resolution.width = width;
+        config.resolution.height = height;
+        config.framerate.numerator = fps;
+        config.framerate.denominator = 1;
+        config
+    };
+
+    let mut batch = BatchFileGenerator::new();
+    if let Some(matrix_path) = &args.matrix {
+        let matrix = EncodingMatrix::from_file(matrix_path)?;
+        let combinations = matrix.combinations();
+        if combinations.is_empty() {
+            return Err(SourceVideoError::config(format!(
+                "{} has no compatible codec/container combination",
+                matrix_path.display()
+            )));
+        }
+        let patterns = if matrix.patterns.is_empty() {
+            patterns
+        } else {
+            &matrix.patterns
+        };
+
+        fs::create_dir_all(output)?;
+        for pattern in patterns {
+            for encoding in &combinations {
+                let path = output.join(format!(
+                    "{}-{}.{}",
+                    pattern,
+                    encoding.label(),
+                    encoding.container.extension()
+                ));
+                batch.add_with_encoding(source_config(pattern), encoding.clone(), path);
+            }
+        }
+    } else {
+        let container = match &args.container {
+            Some(name) => FileContainer::from_name(name)?,
+            None => detect_container_format(output).unwrap_or(FileContainer::Mp4),
+        };
+        let encoding = EncodingConfig {
+            codec: match &args.codec {
+                Some(name) => name.parse()?,
+                None => VideoCodec::default_for(&container),
+            },
+            container,
+            bitrate_kbps: args.bitrate,
+            crf: args.crf,
+            pixel_format: args.pixel_format.clone(),
+            ..Default::default()
+        };
+        encoding.validate()?;
+
+        let corruptions: Vec<Option<CorruptionPreset>> = if args.corrupt.is_empty() {
+            vec![None]
+        } else if args.corrupt.iter().any(|name| name == "all") {
+            CorruptionPreset::all().into_iter().map(Some).collect()
+        } else {
+            args.corrupt
+                .iter()
+                .map(|name| CorruptionPreset::from_name(name).map(Some))
+                .collect::<Result<_>>()?
+        };
+
+        let stem = output
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "test".to_string());
+        for pattern in patterns {
+            for corruption in &corruptions {
+                let Some(preset) = corruption else {
+                    let path = if patterns.len() == 1 {
+                        output.clone()
+                    } else {
+                        output.with_file_name(format!(
+                            "{}-{}.{}",
+                            stem,
+                            pattern,
+                            encoding.container.extension()
+                        ))
+                    };
+                    batch.add_with_encoding(source_config(pattern), encoding.clone(), path);
+                    continue;
+                };
+
+                let container = preset
+                    .required_container()
+                    .unwrap_or_else(|| encoding.container.clone());
+                let path = output.with_file_name(format!(
+                    "{}-{}-{}.{}",
+                    stem,
+                    pattern,
+                    preset.name(),
+                    container.extension()
+                ));
+                batch.add_corrupted(
+                    source_config(pattern),
+                    encoding.clone(),
+                    preset.clone(),
+                    path,
+                );
+            }
+        }
+        println!("Encoding: {}", encoding.label());
+    }
+
+    // The config's quota and free-space limits apply to the directory the
+    // files are written to
+    if let Some(storage) = storage {
+        let manager = if args.matrix.is_some() {
+            StorageManager::new(output, storage)?
+        } else {
+            StorageManager::for_output(output, storage)?
+        };
+        batch = batch.with_storage(Arc::new(manager));
+    }
+
+    let jobs = jobs.unwrap_or_else(default_workers);
+    println!(
+        "Generating {} test video(s) with {} worker(s)",
+        batch.len(),
+        jobs.min(batch.len())
+    );
     println!("Duration: {} seconds", duration);
     println!("Resolution: {}x{}", width, height);
     println!("Framerate: {} fps", fps);
 
-    let start = std::time::Instant::now();
+    let progress = batch.progress_channel();
+    let cancel = batch.cancel_handle();
+    let mut worker = tokio::task::spawn_blocking(move || batch.run(jobs));
+
+    let printer = std::thread::spawn(move || {
+        for update in progress {
+            match update {
+                BatchProgress::Started { path, .. } => println!("  started  {}", path.display()),
+                BatchProgress::Progress {
+                    path, percent, eta, ..
+                } => {
+                    let eta = eta
+                        .map(|eta| format!("{:.0}s", eta.as_secs_f64()))
+                        .unwrap_or_else(|| "?".to_string());
+                    println!("  {:>5.1}%   {} (ETA {})", percent, path.display(), eta);
+                }
+                BatchProgress::Completed { path, .. } => println!("  done     {}", path.display()),
+                BatchProgress::Failed { path, error, .. } => {
+                    println!("  failed   {}: {}", path.display(), error)
+                }
+                BatchProgress::Skipped { .. } => {}
+            }
+        }
+    });
+
+    let report = tokio::select! {
+        report = &mut worker => report,
+        _ = tokio::signal::ctrl_c() => {
+            println!("Cancelling generation...");
+            cancel.cancel();
+            worker.await
+        }
+    }
+    .map_err(|e| SourceVideoError::config(format!("Generation task failed: {}", e)))?;
+    let _ = printer.join();
 
-    generate_test_file(&pattern, duration, &output)?;
+    println!("\n{}", report);
+    if report.is_success() {
+        Ok(())
+    } else {
+        Err(SourceVideoError::pipeline(format!(
+            "{} of {} files were not generated",
+            report.total() - report.generated.len(),
+            report.total()
+        )))
+    }
+}
+
+fn generate_scene_command(args: &GenerateArgs) -> Result<()> {
+    use source_videos::{SceneConfig, SceneGenerator};
+
+    let scene = match &args.scene {
+        Some(path) => SceneConfig::from_file(path)?,
+        None => SceneConfig {
+            width: args.width,
+            height: args.height,
+            fps: args.fps.max(1) as u32,
+            duration_secs: args.duration as f64,
+            ..Default::default()
+        }
+        .with_random_objects(args.objects, args.seed)?,
+    };
 
-    let elapsed = start.elapsed();
     println!(
-        "Generated successfully in {:.2} seconds",
-        elapsed.as_secs_f64()
+        "Rendering {}x{} scene with {} objects for {} frames",
+        scene.width,
+        scene.height,
+        scene.objects.len(),
+        scene.frame_count()
     );
+    let start = std::time::Instant::now();
+    let (coco, mot) = SceneGenerator::new(scene, &args.output).generate()?;
 
+    println!("Video: {}", args.output.display());
+    println!("COCO ground truth: {}", coco.display());
+    println!("MOT ground truth: {}", mot.display());
+    println!(
+        "Generated successfully in {:.2} seconds",
+        start.elapsed().as_secs_f64()
+    );
     Ok(())
 }
 
@@ -1185,6 +1890,13 @@ async fn list_command() -> Result<()> {
         println!("  {:?}", pattern);
     }
 
+    println!("\nFrame markers (frame number and source ID in every frame):");
+    println!("  <pattern>+qr         - QR code, e.g. ball+qr");
+    println!("  <pattern>+aruco      - ArUco markers, e.g. snow+aruco");
+
+    println!("\nA/V sync (RTSP only, with an audio track):");
+    println!("  avsync               - White flash and beep together once a second");
+
     Ok(())
 }
 
@@ -1352,7 +2064,7 @@ async fn serve_files_command(
     // Set up signal handlers
     if signal_handlers {
         println!("Signal handlers enabled for graceful shutdown");
-        // Signal handling is already implemented below with tokio::signal::ctrl_c()
+        // SIGINT/SIGTERM are routed through the shutdown coordinator below
     }
 
     // Logging already set up in main
@@ -1597,6 +2309,109 @@ async fn simulate_command(
     Ok(())
 }
 
+async fn farm_command(
+    instances: usize,
+    base_port: u16,
+    max_port: Option<u16>,
+    addresses: Vec<String>,
+    patterns: Vec<String>,
+    sources_per_instance: usize,
+    api: bool,
+    api_port: u16,
+    api_address: String,
+    duration: Option<u64>,
+) -> Result<()> {
+    use source_videos::{FarmConfig, ServerFarm, VideoSourceManager, WatcherManager};
+
+    let config = FarmConfig {
+        instances,
+        base_port,
+        max_port,
+        addresses,
+        patterns,
+        sources_per_instance,
+    };
+
+    println!(
+        "Starting server farm with {} instances from port {}",
+        instances, base_port
+    );
+
+    let farm = Arc::new(ServerFarm::new(config)?);
+    farm.start().await?;
+
+    let coordinator = ShutdownCoordinator::default();
+    let shutdown_token = coordinator.token();
+    {
+        let farm = farm.clone();
+        coordinator.register("farm", move || async move { farm.stop().await });
+    }
+
+    for info in farm.infos().await {
+        println!("{} ({}:{})", info.id, info.address, info.port);
+        for url in &info.urls {
+            println!("  {}", url);
+        }
+    }
+
+    if api {
+        let api_bind_address: std::net::SocketAddr = format!("{}:{}", api_address, api_port)
+            .parse()
+            .map_err(|e| SourceVideoError::config(format!("Invalid API address: {}", e)))?;
+
+        let mut api_server = ControlApi::new(
+            None,
+            Arc::new(VideoSourceManager::new()),
+            Arc::new(RwLock::new(WatcherManager::new())),
+        )?
+        .with_farm(farm.clone());
+        api_server.set_bind_address(api_bind_address);
+
+        println!("Starting API server on http://{}:{}", api_address, api_port);
+
+        let api_token = shutdown_token.clone();
+        let handle = tokio::spawn(async move {
+            if let Err(e) = api_server
+                .serve_with_shutdown(async move { api_token.cancelled().await })
+                .await
+            {
+                eprintln!("API server error: {}", e);
+            }
+        });
+        coordinator.register_task("api_server", handle);
+    }
+
+    let main_context = gstreamer::glib::MainContext::default();
+    let end_time = duration.map(|d| std::time::Instant::now() + Duration::from_secs(d));
+
+    if let Some(duration) = duration {
+        println!("Farm will run for {} seconds", duration);
+    } else {
+        println!("Press Ctrl+C to stop the farm");
+    }
+
+    tokio::select! {
+        _ = wait_for_signal(&shutdown_token) => {
+            println!("Shutdown requested, stopping...");
+        }
+        _ = async {
+            loop {
+                main_context.iteration(false);
+
+                if end_time.is_some_and(|end| std::time::Instant::now() >= end) {
+                    break;
+                }
+
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        } => {}
+    }
+
+    finish_shutdown(&coordinator).await;
+    println!("Server farm stopped");
+    Ok(())
+}
+
 async fn completions_command(shell: Shell) -> Result<()> {
     let mut app = <Cli as clap::CommandFactory>::command();
     let app_name = app.get_name().to_string();
@@ -1778,6 +2593,92 @@ For API documentation, start with --api and visit:
     Ok(())
 }
 
+async fn service_command(action: ServiceAction) -> Result<()> {
+    use source_videos::service::SystemdUnit;
+
+    match action {
+        ServiceAction::SystemdUnit {
+            output,
+            user,
+            restart,
+            restart_sec,
+            watchdog_sec,
+            serve_args,
+        } => {
+            let exe = std::env::current_exe()?;
+            let mut exec_start = vec![exe.display().to_string(), "serve".to_string()];
+            exec_start.extend(serve_args);
+
+            let mut unit = SystemdUnit::new(exec_start);
+            unit.user = user;
+            unit.restart = restart;
+            unit.restart_sec = restart_sec;
+            unit.watchdog_sec = watchdog_sec;
+
+            match output {
+                Some(path) => {
+                    fs::write(&path, unit.render())?;
+                    println!("Wrote systemd unit to {}", path.display());
+                }
+                None => print!("{}", unit.render()),
+            }
+            Ok(())
+        }
+        #[cfg(windows)]
+        ServiceAction::Install {
+            name,
+            restart_delay,
+            serve_args,
+        } => {
+            use source_videos::service::windows::{InstallOptions, install};
+
+            let mut arguments = vec!["service".into(), "run".into()];
+            arguments.push(format!("--name={}", name).into());
+            arguments.push("--".into());
+            arguments.extend(serve_args.into_iter().map(Into::into));
+
+            install(&InstallOptions {
+                name: name.clone(),
+                arguments,
+                restart_delay: Duration::from_secs(restart_delay),
+                ..Default::default()
+            })?;
+            println!("Installed Windows service '{}'", name);
+            Ok(())
+        }
+        #[cfg(windows)]
+        ServiceAction::Uninstall { name } => {
+            source_videos::service::windows::uninstall(&name)?;
+            println!("Removed Windows service '{}'", name);
+            Ok(())
+        }
+        #[cfg(windows)]
+        ServiceAction::Run { name, serve_args } => {
+            let cli = Cli::try_parse_from(
+                ["source-videos".to_string(), "serve".to_string()]
+                    .into_iter()
+                    .chain(serve_args),
+            )
+            .map_err(|e| SourceVideoError::config(format!("Invalid serve arguments: {}", e)))?;
+
+            let runtime = tokio::runtime::Handle::current();
+            tokio::task::spawn_blocking(move || {
+                source_videos::service::windows::run(&name, move |coordinator| {
+                    runtime.block_on(run_serve(cli.command, coordinator))
+                })
+            })
+            .await
+            .map_err(|e| SourceVideoError::server(format!("Service thread failed: {}", e)))?
+        }
+        #[cfg(not(windows))]
+        ServiceAction::Install { .. }
+        | ServiceAction::Uninstall { .. }
+        | ServiceAction::Run { .. } => Err(SourceVideoError::config(
+            "Windows services are only supported on Windows; use `service systemd-unit` on Linux",
+        )),
+    }
+}
+
 // Helper functions
 
 fn daemonize(pid_file: Option<PathBuf>) -> Result<()> {
@@ -2034,6 +2935,7 @@ fn create_file_source_config(name: &str, file: &PathBuf) -> Result<VideoSourceCo
         duration: None,
         num_buffers: None,
         is_live: false,
+        tags: Vec::new(),
     })
 }
 
@@ -2054,7 +2956,7 @@ async fn start_enhanced_server(
         server_builder = server_builder.add_source(config);
     }
 
-    let mut server = server_builder.build()?;
+    let server = server_builder.build()?;
     server.start()?;
 
     if let Some(interval) = status_interval {
@@ -2067,12 +2969,17 @@ async fn start_enhanced_server(
         });
     }
 
-    tokio::select! {
-        _ = signal::ctrl_c() => {
-            println!("Received Ctrl+C, stopping...");
-        }
+    let coordinator = ShutdownCoordinator::default();
+    let server = Arc::new(server);
+    {
+        let server = server.clone();
+        coordinator.register("rtsp_server", move || async move { server.stop() });
     }
 
+    wait_for_signal(&coordinator.token()).await;
+    println!("Shutdown requested, stopping...");
+    finish_shutdown(&coordinator).await;
+
     Ok(())
 }
 
@@ -2103,17 +3010,22 @@ async fn start_playlist_server(
         server_builder = server_builder.add_source(config);
     }
 
-    let mut server = server_builder.build()?;
+    let server = server_builder.build()?;
     server.start()?;
 
     println!("Playlist server started on port {}", port);
 
-    tokio::select! {
-        _ = signal::ctrl_c() => {
-            println!("Received Ctrl+C, stopping...");
-        }
+    let coordinator = ShutdownCoordinator::default();
+    let server = Arc::new(server);
+    {
+        let server = server.clone();
+        coordinator.register("rtsp_server", move || async move { server.stop() });
     }
 
+    wait_for_signal(&coordinator.token()).await;
+    println!("Shutdown requested, stopping...");
+    finish_shutdown(&coordinator).await;
+
     Ok(())
 }
 
@@ -5,8 +5,13 @@ use crate::config_types::{
 use crate::directory::{BatchSourceLoader, DirectoryScanner};
 use crate::error::{Result, SourceVideoError};
 use crate::runtime::events::{ConfigurationEvent, EventBus};
-use crate::source::{SourceState, VideoSource, create_source};
-use crate::watch::{DirectoryWatcher, FileSystemEvent, WatcherManager};
+use crate::source::{SourceState, SourceUpdate, VideoSource, create_source};
+use crate::watch::FileSystemEvent;
+#[cfg(feature = "watch")]
+use crate::watch::WatcherManager;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -16,10 +21,13 @@ use uuid::Uuid;
 pub struct VideoSourceManager {
     sources: Arc<RwLock<HashMap<String, Box<dyn VideoSource>>>>,
     name_to_id: Arc<RwLock<HashMap<String, String>>>,
+    #[cfg(feature = "watch")]
     watcher_manager: Option<WatcherManager>,
     watch_config: Option<WatchConfig>,
     event_bus: Arc<EventBus>,
     path_to_source: Arc<RwLock<HashMap<PathBuf, String>>>,
+    /// Pipeline rebuilds per source name, kept after the source goes
+    restarts: Arc<RwLock<HashMap<String, u64>>>,
 }
 
 impl VideoSourceManager {
@@ -27,10 +35,12 @@ impl VideoSourceManager {
         Self {
             sources: Arc::new(RwLock::new(HashMap::new())),
             name_to_id: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "watch")]
             watcher_manager: None,
             watch_config: None,
             event_bus: Arc::new(EventBus::new()),
             path_to_source: Arc::new(RwLock::new(HashMap::new())),
+            restarts: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -191,6 +201,73 @@ impl VideoSourceManager {
         }
     }
 
+    /// Restart the sources tagged `tag` together on the system clock with
+    /// one base time, `delay` from now. Their running times, and the
+    /// timestamps of live sources, stay aligned as with genlocked cameras.
+    /// The delay has to cover the pipelines' state changes.
+    pub fn start_group_synced(&self, tag: &str, delay: Duration) -> Result<SyncedStart> {
+        let (clock, base_time) = sync_point(delay);
+        let sources = self.start_group_at(tag, &clock, base_time)?;
+        if sources.is_empty() {
+            return Err(SourceVideoError::SourceNotFound(format!(
+                "No sources tagged '{}'",
+                tag
+            )));
+        }
+        Ok(SyncedStart {
+            tag: tag.to_string(),
+            base_time_ns: base_time.nseconds(),
+            sources,
+            mounts: Vec::new(),
+        })
+    }
+
+    /// Restart the sources tagged `tag` on `clock` with `base_time`, e.g.
+    /// one shared with RTSP mounts; returns their IDs, possibly none
+    pub fn start_group_at(
+        &self,
+        tag: &str,
+        clock: &gst::Clock,
+        base_time: gst::ClockTime,
+    ) -> Result<Vec<String>> {
+        // Pipelines are taken under the lock but change state outside it
+        let mut group = {
+            let mut sources = self.sources.write().map_err(|_| {
+                SourceVideoError::resource("Failed to acquire write lock on sources")
+            })?;
+            let mut group = Vec::new();
+            for (id, source) in sources.iter_mut() {
+                if source.get_config().is_some_and(|c| c.has_tag(tag)) {
+                    group.push((id.clone(), source.synced_pipeline()?));
+                }
+            }
+            group
+        };
+        group.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (_, pipeline) in &group {
+            crate::source::play_synced(pipeline, clock, base_time)?;
+        }
+
+        let mut sources = self
+            .sources
+            .write()
+            .map_err(|_| SourceVideoError::resource("Failed to acquire write lock on sources"))?;
+        for (id, _) in &group {
+            if let Some(source) = sources.get_mut(id) {
+                source.mark_playing();
+            }
+        }
+
+        log::info!(
+            "Started {} sources tagged '{}' with base time {}",
+            group.len(),
+            tag,
+            base_time
+        );
+        Ok(group.into_iter().map(|(id, _)| id).collect())
+    }
+
     pub fn clear_all(&self) -> Result<()> {
         let mut sources = self
             .sources
@@ -218,9 +295,36 @@ impl VideoSourceManager {
             .unwrap_or(0)
     }
 
+    /// Apply a new configuration to a running source, keeping its ID.
+    ///
+    /// Caps and pattern changes are renegotiated on the live pipeline; other
+    /// changes rebuild the pipeline in place. Sources that cannot be
+    /// reconfigured are replaced.
     pub fn update_source(&self, id_or_name: &str, config: VideoSourceConfig) -> Result<()> {
         let id = self.resolve_id(id_or_name)?;
 
+        {
+            let mut sources = self.sources.write().map_err(|_| {
+                SourceVideoError::resource("Failed to acquire write lock on sources")
+            })?;
+            let source = sources
+                .get_mut(&id)
+                .ok_or_else(|| SourceVideoError::SourceNotFound(id_or_name.to_string()))?;
+
+            if source.get_name() == config.name {
+                match source.reconfigure(config.clone()) {
+                    Ok(update) => {
+                        if update == SourceUpdate::Restart {
+                            self.count_restart(&config.name);
+                        }
+                        log::info!("Updated source '{}' ({:?})", id_or_name, update);
+                        return Ok(());
+                    }
+                    Err(e) => log::debug!("Replacing source '{}': {}", id_or_name, e),
+                }
+            }
+        }
+
         // Get the current source to preserve its state
         let current_state = {
             let sources = self.sources.read().map_err(|_| {
@@ -237,7 +341,9 @@ impl VideoSourceManager {
         self.remove_source(&id)?;
 
         // Add the new source with updated config
+        let name = config.name.clone();
         let new_id = self.add_source(config)?;
+        self.count_restart(&name);
 
         // Try to restore the previous state
         match current_state {
@@ -281,6 +387,33 @@ impl VideoSourceManager {
         Ok(())
     }
 
+    pub fn get_source_config(&self, id_or_name: &str) -> Result<VideoSourceConfig> {
+        let id = self.resolve_id(id_or_name)?;
+        let sources = self
+            .sources
+            .read()
+            .map_err(|_| SourceVideoError::resource("Failed to acquire read lock on sources"))?;
+        sources
+            .get(&id)
+            .and_then(|source| source.get_config().cloned())
+            .ok_or_else(|| SourceVideoError::SourceNotFound(id_or_name.to_string()))
+    }
+
+    /// Pipeline of a source that has been started
+    pub fn get_pipeline(&self, id_or_name: &str) -> Result<gstreamer::Pipeline> {
+        let id = self.resolve_id(id_or_name)?;
+        let sources = self
+            .sources
+            .read()
+            .map_err(|_| SourceVideoError::resource("Failed to acquire read lock on sources"))?;
+        let source = sources
+            .get(&id)
+            .ok_or_else(|| SourceVideoError::SourceNotFound(id_or_name.to_string()))?;
+        source.get_pipeline().cloned().ok_or_else(|| {
+            SourceVideoError::pipeline(format!("Source '{}' has no pipeline", id_or_name))
+        })
+    }
+
     pub fn get_source_configs(&self) -> Vec<(String, SourceState)> {
         self.sources
             .read()
@@ -393,6 +526,7 @@ impl VideoSourceManager {
                 duration: None,
                 num_buffers: None,
                 is_live: false,
+                tags: Vec::new(),
             };
 
             source_configs.push(source_config);
@@ -413,6 +547,7 @@ impl VideoSourceManager {
 
     // File watching methods
 
+    #[cfg(feature = "watch")]
     pub fn enable_file_watching(&mut self, config: WatchConfig) {
         self.watch_config = Some(config);
         if self.watcher_manager.is_none() {
@@ -421,6 +556,7 @@ impl VideoSourceManager {
         log::info!("File watching enabled");
     }
 
+    #[cfg(feature = "watch")]
     pub async fn add_watched_directory<P: AsRef<Path>>(
         &mut self,
         path: P,
@@ -477,6 +613,7 @@ impl VideoSourceManager {
         Ok(())
     }
 
+    #[cfg(feature = "watch")]
     pub async fn add_watched_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         if self.watcher_manager.is_none() {
             return Err(SourceVideoError::config("File watching not enabled"));
@@ -512,6 +649,7 @@ impl VideoSourceManager {
             duration: None,
             num_buffers: None,
             is_live: false,
+            tags: Vec::new(),
         };
 
         let source_id = if let Some(ref watch_config) = self.watch_config {
@@ -592,6 +730,7 @@ impl VideoSourceManager {
         Ok(id)
     }
 
+    #[cfg(feature = "watch")]
     async fn start_file_watching_task(&self) -> Result<()> {
         let sources = Arc::clone(&self.sources);
         let name_to_id = Arc::clone(&self.name_to_id);
@@ -673,6 +812,7 @@ impl VideoSourceManager {
             duration: None,
             num_buffers: None,
             is_live: false,
+            tags: Vec::new(),
         };
 
         let source_id = if let Some(ref watch_config) = self.watch_config {
@@ -708,6 +848,9 @@ impl VideoSourceManager {
 
             // Restart the source
             self.start_source(&source_id)?;
+            if let Ok(info) = self.get_source(&source_id) {
+                self.count_restart(&info.name);
+            }
 
             log::info!("Reloaded source for modified file: {}", path.display());
         }
@@ -744,17 +887,31 @@ impl VideoSourceManager {
     }
 
     pub async fn stop_watching(&mut self) -> Result<()> {
-        if let Some(ref mut watcher_manager) = self.watcher_manager {
+        #[cfg(feature = "watch")]
+        if let Some(mut watcher_manager) = self.watcher_manager.take() {
             watcher_manager.stop_all().await?;
             log::info!("Stopped all file watchers");
         }
 
-        self.watcher_manager = None;
         self.watch_config = None;
 
         Ok(())
     }
 
+    /// How often each source's pipeline was rebuilt, by source name
+    pub fn restart_counts(&self) -> HashMap<String, u64> {
+        self.restarts
+            .read()
+            .map(|restarts| restarts.clone())
+            .unwrap_or_default()
+    }
+
+    fn count_restart(&self, name: &str) {
+        if let Ok(mut restarts) = self.restarts.write() {
+            *restarts.entry(name.to_string()).or_insert(0) += 1;
+        }
+    }
+
     pub fn get_event_bus(&self) -> Arc<EventBus> {
         Arc::clone(&self.event_bus)
     }
@@ -788,6 +945,26 @@ pub struct SourceInfo {
     pub state: SourceState,
 }
 
+/// Sources started together by [`VideoSourceManager::start_group_synced`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedStart {
+    pub tag: String,
+    /// System clock time at which every source's running time was zero
+    pub base_time_ns: u64,
+    /// IDs of the started sources
+    pub sources: Vec<String>,
+    /// RTSP mounts whose media start on the same base time
+    #[serde(default)]
+    pub mounts: Vec<String>,
+}
+
+/// The system clock and a base time `delay` from now
+pub fn sync_point(delay: Duration) -> (gst::Clock, gst::ClockTime) {
+    let clock = gst::SystemClock::obtain();
+    let base_time = clock.time() + gst::ClockTime::from_nseconds(delay.as_nanos() as u64);
+    (clock, base_time)
+}
+
 #[derive(Debug, Clone)]
 pub struct ManagerSnapshot {
     pub sources: Vec<SourceInfo>,
@@ -893,6 +1070,54 @@ mod tests {
         assert!(info.is_stopped());
     }
 
+    #[test]
+    fn test_start_group_synced() {
+        gstreamer::init().unwrap();
+
+        let manager = VideoSourceManager::new();
+        let left = manager
+            .add_source(VideoSourceConfig::test_pattern("left", "smpte").with_tag("rig"))
+            .unwrap();
+        let right = manager
+            .add_source(VideoSourceConfig::test_pattern("right", "ball").with_tag("rig"))
+            .unwrap();
+        manager
+            .add_source(VideoSourceConfig::test_pattern("other", "snow"))
+            .unwrap();
+
+        let started = manager
+            .start_group_synced("rig", Duration::from_millis(100))
+            .unwrap();
+        let mut expected = vec![left.clone(), right.clone()];
+        expected.sort();
+        assert_eq!(started.sources, expected);
+
+        let base_times: Vec<_> = [&left, &right]
+            .iter()
+            .map(|id| manager.get_pipeline(id).unwrap().base_time().unwrap())
+            .collect();
+        assert_eq!(base_times[0], base_times[1]);
+        assert_eq!(base_times[0].nseconds(), started.base_time_ns);
+        assert!(manager.get_source("left").unwrap().is_playing());
+    }
+
+    #[test]
+    fn test_start_group_synced_without_sources() {
+        gstreamer::init().unwrap();
+
+        let manager = VideoSourceManager::new();
+        manager
+            .add_source(VideoSourceConfig::test_pattern("other", "snow"))
+            .unwrap();
+        assert!(manager.start_group_synced("none", Duration::ZERO).is_err());
+        assert!(
+            manager
+                .start_group_at("none", &gst::SystemClock::obtain(), gst::ClockTime::ZERO)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_builder() {
         gstreamer::init().unwrap();
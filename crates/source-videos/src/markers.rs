@@ -0,0 +1,324 @@
+//! Machine-readable markers burned into test patterns
+//!
+//! Appending `+qr` or `+aruco` to a pattern name (`ball+qr`, or just `qr`
+//! for SMPTE bars) draws a marker in the top-left corner of every frame
+//! that carries the source ID and the frame number, so any consumer can
+//! detect dropped, duplicated or reordered frames and line up recordings of
+//! several streams.
+//!
+//! - QR codes hold the text `DSRS;src=<name>;id=<source id>;frame=<n>`.
+//! - ArUco uses three markers from the original ArUco dictionary (ids
+//!   0-1023) side by side: the source ID, `frame % 1024` and
+//!   `(frame / 1024) % 1024`.
+
+use crate::error::{Result, SourceVideoError};
+use crate::patterns::TestPattern;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use qrcodegen::{QrCode, QrCodeEcc};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rows of the original ArUco dictionary: two data bits per row, one
+/// meaning white
+const ARUCO_WORDS: [u8; 4] = [0x10, 0x17, 0x09, 0x0e];
+
+/// Margin between the frame edge and the marker, in pixels
+const MARGIN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Qr,
+    Aruco,
+}
+
+impl FromStr for MarkerKind {
+    type Err = SourceVideoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "qr" => Ok(Self::Qr),
+            "aruco" => Ok(Self::Aruco),
+            _ => Err(SourceVideoError::InvalidPattern(format!(
+                "Unknown marker: {}. Use qr or aruco.",
+                s
+            ))),
+        }
+    }
+}
+
+/// Split a pattern name into the videotestsrc pattern and its marker.
+/// `avsync` is not a videotestsrc pattern; only RTSP mounts serve it.
+pub fn parse_pattern(pattern: &str) -> Result<(TestPattern, Option<MarkerKind>)> {
+    if crate::avsync::is_avsync(pattern) {
+        return Err(crate::avsync::only_on_rtsp());
+    }
+    if let Some((base, marker)) = pattern.rsplit_once('+') {
+        return Ok((TestPattern::from_str(base)?, Some(marker.parse()?)));
+    }
+    match pattern.parse() {
+        Ok(marker) => Ok((TestPattern::Smpte, Some(marker))),
+        Err(_) => Ok((TestPattern::from_str(pattern)?, None)),
+    }
+}
+
+/// Numeric source ID for a source name: its trailing number when it has
+/// one (`camera-7` is 7), otherwise a hash; always below 1024 so it fits
+/// an ArUco marker
+pub fn marker_source_id(name: &str) -> u32 {
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if let Ok(id) = name[name.len() - digits..].parse::<u32>() {
+        return id % 1024;
+    }
+    // FNV-1a
+    let hash = name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    hash % 1024
+}
+
+/// 7x7 cells of an original-dictionary ArUco marker, `true` meaning black
+pub fn aruco_bits(id: u16) -> [[bool; 7]; 7] {
+    let mut bits = [[true; 7]; 7];
+    for (y, row) in bits.iter_mut().skip(1).take(5).enumerate() {
+        let word = ARUCO_WORDS[((id >> (2 * (4 - y))) & 0x3) as usize];
+        for (x, cell) in row.iter_mut().skip(1).take(5).enumerate() {
+            *cell = (word >> (4 - x)) & 1 == 0;
+        }
+    }
+    bits
+}
+
+/// Inverse of [`aruco_bits`] for an upright marker
+pub fn decode_aruco(bits: &[[bool; 7]; 7]) -> Option<u16> {
+    let border = (0..7).all(|i| bits[0][i] && bits[6][i] && bits[i][0] && bits[i][6]);
+    if !border {
+        return None;
+    }
+    let mut id = 0u16;
+    for row in &bits[1..6] {
+        let word = row[1..6]
+            .iter()
+            .fold(0u8, |word, &black| (word << 1) | u8::from(!black));
+        let value = ARUCO_WORDS.iter().position(|&w| w == word)?;
+        id = (id << 2) | value as u16;
+    }
+    Some(id)
+}
+
+/// Cells to draw for one frame, `true` meaning black, quiet zone included
+pub fn marker_cells(kind: MarkerKind, source_name: &str, frame: u64) -> Vec<Vec<bool>> {
+    let source_id = marker_source_id(source_name);
+    match kind {
+        MarkerKind::Qr => {
+            let text = format!("DSRS;src={};id={};frame={}", source_name, source_id, frame);
+            let Ok(qr) = QrCode::encode_text(&text, QrCodeEcc::Low) else {
+                return Vec::new();
+            };
+            let quiet = 4;
+            let size = qr.size() + 2 * quiet;
+            (0..size)
+                .map(|y| {
+                    (0..size)
+                        .map(|x| qr.get_module(x - quiet, y - quiet))
+                        .collect()
+                })
+                .collect()
+        }
+        MarkerKind::Aruco => {
+            let ids = [
+                source_id as u16,
+                (frame % 1024) as u16,
+                ((frame / 1024) % 1024) as u16,
+            ];
+            let mut cells = vec![vec![false; 9 * ids.len()]; 9];
+            for (i, id) in ids.into_iter().enumerate() {
+                for (y, row) in aruco_bits(id).iter().enumerate() {
+                    for (x, &black) in row.iter().enumerate() {
+                        cells[y + 1][i * 9 + x + 1] = black;
+                    }
+                }
+            }
+            cells
+        }
+    }
+}
+
+/// Draw a marker with a running frame count on every buffer leaving `pad`
+pub fn attach_marker_overlay(
+    pad: &gst::Pad,
+    kind: MarkerKind,
+    source_name: &str,
+) -> Option<gst::PadProbeId> {
+    let source_name = source_name.to_string();
+    let frame = AtomicU64::new(0);
+    pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+        let number = frame.fetch_add(1, Ordering::Relaxed);
+        let Some(caps) = pad.current_caps() else {
+            return gst::PadProbeReturn::Ok;
+        };
+        let Ok(video_info) = gst_video::VideoInfo::from_caps(&caps) else {
+            return gst::PadProbeReturn::Ok;
+        };
+        if let Some(buffer) = info.buffer_mut() {
+            let cells = marker_cells(kind, &source_name, number);
+            if let Ok(mut frame) =
+                gst_video::VideoFrameRef::from_buffer_ref_writable(buffer.make_mut(), &video_info)
+            {
+                draw_cells(&mut frame, &cells);
+            }
+        }
+        gst::PadProbeReturn::Ok
+    })
+}
+
+fn draw_cells(frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>, cells: &[Vec<bool>]) {
+    let rows = cells.len();
+    let cols = cells.first().map_or(0, Vec::len);
+    if rows == 0 || cols == 0 {
+        return;
+    }
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let cell = (height / 4 / rows).min(width / 2 / cols).max(1);
+
+    for (row, line) in cells.iter().enumerate() {
+        for (col, &black) in line.iter().enumerate() {
+            let x = MARGIN + col * cell;
+            let y = MARGIN + row * cell;
+            if x + cell > width || y + cell > height {
+                continue;
+            }
+            fill(frame, x, y, cell, black);
+        }
+    }
+}
+
+/// Paint a `size` x `size` square black or white
+fn fill(
+    frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    x: usize,
+    y: usize,
+    size: usize,
+    black: bool,
+) {
+    use gst_video::VideoFormat as F;
+
+    let format = frame.format();
+    let strides: Vec<usize> = frame.plane_stride().iter().map(|&s| s as usize).collect();
+    let mut paint = |plane: u32, x: usize, y: usize, w: usize, h: usize, pixel: &[u8]| {
+        let stride = strides[plane as usize];
+        let Ok(data) = frame.plane_data_mut(plane) else {
+            return;
+        };
+        for row in y..y + h {
+            let start = row * stride + x * pixel.len();
+            let Some(line) = data.get_mut(start..start + w * pixel.len()) else {
+                return;
+            };
+            for chunk in line.chunks_exact_mut(pixel.len()) {
+                chunk.copy_from_slice(pixel);
+            }
+        }
+    };
+
+    let (luma, value) = if black { (16, 0) } else { (235, 255) };
+    match format {
+        F::I420 | F::Yv12 => {
+            paint(0, x, y, size, size, &[luma]);
+            let (cx, cy, cs) = (x / 2, y / 2, size.div_ceil(2));
+            paint(1, cx, cy, cs, cs, &[128]);
+            paint(2, cx, cy, cs, cs, &[128]);
+        }
+        F::Nv12 | F::Nv21 => {
+            paint(0, x, y, size, size, &[luma]);
+            paint(
+                1,
+                x / 2,
+                y / 2,
+                size.div_ceil(2),
+                size.div_ceil(2),
+                &[128, 128],
+            );
+        }
+        F::Rgba | F::Bgra | F::Rgbx | F::Bgrx => {
+            paint(0, x, y, size, size, &[value, value, value, 255]);
+        }
+        F::Argb | F::Abgr | F::Xrgb | F::Xbgr => {
+            paint(0, x, y, size, size, &[255, value, value, value]);
+        }
+        F::Rgb | F::Bgr => paint(0, x, y, size, size, &[value, value, value]),
+        other => log::debug!("Markers are not drawn on {:?} frames", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(
+            parse_pattern("ball+qr").unwrap(),
+            (TestPattern::Ball, Some(MarkerKind::Qr))
+        );
+        assert_eq!(
+            parse_pattern("aruco").unwrap(),
+            (TestPattern::Smpte, Some(MarkerKind::Aruco))
+        );
+        assert_eq!(parse_pattern("snow").unwrap(), (TestPattern::Snow, None));
+        assert!(parse_pattern("ball+barcode").is_err());
+        assert!(
+            matches!(parse_pattern("avsync"), Err(SourceVideoError::InvalidPattern(reason)) if reason.contains("RTSP"))
+        );
+    }
+
+    #[test]
+    fn test_aruco_round_trip() {
+        for id in [0, 1, 2, 511, 1000, 1023] {
+            assert_eq!(decode_aruco(&aruco_bits(id)), Some(id));
+        }
+        // Id 0 is all 0x10 rows: white cell then four black cells
+        assert_eq!(
+            aruco_bits(0)[1],
+            [true, false, true, true, true, true, true]
+        );
+
+        let cells = marker_cells(MarkerKind::Aruco, "camera-7", 1025);
+        assert_eq!((cells.len(), cells[0].len()), (9, 27));
+        let marker = |index: usize| {
+            let mut bits = [[false; 7]; 7];
+            for (y, row) in bits.iter_mut().enumerate() {
+                for (x, bit) in row.iter_mut().enumerate() {
+                    *bit = cells[y + 1][index * 9 + x + 1];
+                }
+            }
+            decode_aruco(&bits)
+        };
+        assert_eq!(
+            (marker(0), marker(1), marker(2)),
+            (Some(7), Some(1), Some(1))
+        );
+    }
+
+    #[test]
+    fn test_source_id_from_trailing_number() {
+        assert_eq!(marker_source_id("camera-7"), 7);
+        assert_eq!(marker_source_id("cam2049"), 1);
+    }
+
+    #[test]
+    fn test_source_id_hashed_from_name() {
+        assert!(marker_source_id("lobby") < 1024);
+        assert_eq!(marker_source_id("lobby"), marker_source_id("lobby"));
+    }
+
+    #[test]
+    fn test_qr_cells() {
+        let cells = marker_cells(MarkerKind::Qr, "lobby", 42);
+        assert!(cells.len() >= 21 + 8);
+        assert!(cells.iter().all(|row| row.len() == cells.len()));
+        assert_ne!(cells, marker_cells(MarkerKind::Qr, "lobby", 43));
+    }
+}
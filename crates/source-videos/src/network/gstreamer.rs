@@ -165,92 +165,14 @@ impl GStreamerNetworkSimulator {
             Err(_) => return,
         };
 
-        if elements.using_netsim {
-            // Apply conditions to netsim element
-            if let Some(ref netsim) = elements.netsim {
-                // Convert percentage to 0.0-1.0 probability for netsim
-                let drop_prob = (conditions.packet_loss / 100.0).clamp(0.0, 1.0);
-                netsim.set_property("drop-probability", drop_prob);
-
-                // Packet duplication
-                let dup_prob = (conditions.duplicate_probability / 100.0).clamp(0.0, 1.0);
-                netsim.set_property("duplicate-probability", dup_prob);
-
-                // Delay configuration
-                if conditions.delay_probability > 0.0 {
-                    let delay_prob = (conditions.delay_probability / 100.0).clamp(0.0, 1.0);
-                    netsim.set_property("delay-probability", delay_prob);
-
-                    // Set delay range
-                    let min_delay = if conditions.min_delay_ms > 0 {
-                        conditions.min_delay_ms as i32
-                    } else {
-                        conditions.latency_ms as i32
-                    };
-                    let max_delay = if conditions.max_delay_ms > 0 {
-                        conditions.max_delay_ms as i32
-                    } else {
-                        (conditions.latency_ms + conditions.jitter_ms) as i32
-                    };
-
-                    netsim.set_property("min-delay", min_delay);
-                    netsim.set_property("max-delay", max_delay);
-                } else if conditions.latency_ms > 0 {
-                    // Use uniform delay for latency simulation
-                    netsim.set_property("delay-probability", 1.0f32);
-                    netsim.set_property("min-delay", conditions.latency_ms as i32);
-                    let max_delay = (conditions.latency_ms + conditions.jitter_ms) as i32;
-                    netsim.set_property("max-delay", max_delay);
-                } else {
-                    netsim.set_property("delay-probability", 0.0f32);
-                }
-
-                // Packet reordering control
-                netsim.set_property("allow-reordering", conditions.allow_reordering);
-
-                // Bandwidth limiting
-                if conditions.bandwidth_kbps > 0 {
-                    netsim.set_property("max-kbps", conditions.bandwidth_kbps as i32);
-                    // Set bucket size for burst tolerance (1 second worth of data)
-                    let bucket_size = conditions.bandwidth_kbps as i32;
-                    netsim.set_property("max-bucket-size", bucket_size);
-                } else {
-                    netsim.set_property("max-kbps", -1i32); // unlimited
-                    netsim.set_property("max-bucket-size", -1i32);
-                }
-            }
-        } else {
-            // Fallback mode: Use old implementation with queue + identity
-            if let Some(ref identity) = elements.identity {
-                let drop_prob = (conditions.packet_loss / 100.0) as f32;
-                identity.set_property("drop-probability", drop_prob);
-
-                // Apply latency
-                if conditions.latency_ms > 0 {
-                    let latency_ns = conditions.latency_ms as u64 * 1_000_000;
-                    identity.set_property("datarate", latency_ns as i32);
-                }
-            }
-
-            // Apply bandwidth limits to queue
-            if let Some(ref queue) = elements.queue {
-                if conditions.bandwidth_kbps > 0 {
-                    let buffer_bytes = (conditions.bandwidth_kbps * 1000 / 8) as u32;
-                    queue.set_property("max-size-bytes", buffer_bytes);
-                    queue.set_property("max-size-buffers", 0u32);
-                    queue.set_property("max-size-time", 1_000_000_000u64);
-                } else {
-                    queue.set_property("max-size-bytes", 0u32);
-                    queue.set_property("max-size-buffers", 1000u32);
-                    queue.set_property("max-size-time", 0u64);
-                }
-            }
-        }
-
-        // Apply connection drops to valve (both modes)
-        if let Some(ref valve) = elements.valve {
-            valve.set_property("drop", conditions.connection_dropped);
-        }
+        let netsim = elements.netsim.as_ref().filter(|_| elements.using_netsim);
+        configure_elements(
+            &conditions,
+            netsim,
+            elements.queue.as_ref(),
+            elements.identity.as_ref(),
+            elements.valve.as_ref(),
+        );
     }
 
     /// Get the simulator instance
@@ -322,6 +244,100 @@ impl NetworkController for GStreamerNetworkSimulator {
     }
 }
 
+/// Set `conditions` on simulation elements: `netsim` when available,
+/// otherwise the `queue` and `identity` fallback, and `valve` for drops
+pub fn configure_elements(
+    conditions: &NetworkConditions,
+    netsim: Option<&gst::Element>,
+    queue: Option<&gst::Element>,
+    identity: Option<&gst::Element>,
+    valve: Option<&gst::Element>,
+) {
+    if let Some(netsim) = netsim {
+        // Convert percentage to 0.0-1.0 probability for netsim
+        let drop_prob = (conditions.packet_loss / 100.0).clamp(0.0, 1.0);
+        netsim.set_property("drop-probability", drop_prob);
+
+        // Packet duplication
+        let dup_prob = (conditions.duplicate_probability / 100.0).clamp(0.0, 1.0);
+        netsim.set_property("duplicate-probability", dup_prob);
+
+        // Delay configuration
+        if conditions.delay_probability > 0.0 {
+            let delay_prob = (conditions.delay_probability / 100.0).clamp(0.0, 1.0);
+            netsim.set_property("delay-probability", delay_prob);
+
+            // Set delay range
+            let min_delay = if conditions.min_delay_ms > 0 {
+                conditions.min_delay_ms as i32
+            } else {
+                conditions.latency_ms as i32
+            };
+            let max_delay = if conditions.max_delay_ms > 0 {
+                conditions.max_delay_ms as i32
+            } else {
+                (conditions.latency_ms + conditions.jitter_ms) as i32
+            };
+
+            netsim.set_property("min-delay", min_delay);
+            netsim.set_property("max-delay", max_delay);
+        } else if conditions.latency_ms > 0 {
+            // Use uniform delay for latency simulation
+            netsim.set_property("delay-probability", 1.0f32);
+            netsim.set_property("min-delay", conditions.latency_ms as i32);
+            let max_delay = (conditions.latency_ms + conditions.jitter_ms) as i32;
+            netsim.set_property("max-delay", max_delay);
+        } else {
+            netsim.set_property("delay-probability", 0.0f32);
+        }
+
+        // Packet reordering control
+        netsim.set_property("allow-reordering", conditions.allow_reordering);
+
+        // Bandwidth limiting
+        if conditions.bandwidth_kbps > 0 {
+            netsim.set_property("max-kbps", conditions.bandwidth_kbps as i32);
+            // Set bucket size for burst tolerance (1 second worth of data)
+            let bucket_size = conditions.bandwidth_kbps as i32;
+            netsim.set_property("max-bucket-size", bucket_size);
+        } else {
+            netsim.set_property("max-kbps", -1i32); // unlimited
+            netsim.set_property("max-bucket-size", -1i32);
+        }
+    } else {
+        // Fallback mode: Use old implementation with queue + identity
+        if let Some(identity) = identity {
+            let drop_prob = (conditions.packet_loss / 100.0) as f32;
+            identity.set_property("drop-probability", drop_prob);
+
+            // Apply latency
+            if conditions.latency_ms > 0 {
+                let latency_ns = conditions.latency_ms as u64 * 1_000_000;
+                identity.set_property("datarate", latency_ns as i32);
+            }
+        }
+
+        // Apply bandwidth limits to queue
+        if let Some(queue) = queue {
+            if conditions.bandwidth_kbps > 0 {
+                let buffer_bytes = (conditions.bandwidth_kbps * 1000 / 8) as u32;
+                queue.set_property("max-size-bytes", buffer_bytes);
+                queue.set_property("max-size-buffers", 0u32);
+                queue.set_property("max-size-time", 1_000_000_000u64);
+            } else {
+                queue.set_property("max-size-bytes", 0u32);
+                queue.set_property("max-size-buffers", 1000u32);
+                queue.set_property("max-size-time", 0u64);
+            }
+        }
+    }
+
+    // Apply connection drops to valve (both modes)
+    if let Some(valve) = valve {
+        valve.set_property("drop", conditions.connection_dropped);
+    }
+}
+
 /// Helper to add network simulation to a pipeline builder
 pub fn add_network_simulation(
     pipeline: &gst::Pipeline,
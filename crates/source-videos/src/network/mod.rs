@@ -1,14 +1,18 @@
 pub mod gstreamer;
 pub mod profiles;
+#[cfg(feature = "network-sim")]
 pub mod scenarios;
 pub mod simulator;
+pub mod stream;
 
 use std::time::Duration;
 
 pub use gstreamer::GStreamerNetworkSimulator;
 pub use profiles::{NetworkProfile, StandardProfiles};
+#[cfg(feature = "network-sim")]
 pub use scenarios::{NetworkScenario, ScenarioConfig, ScenarioPlayer};
 pub use simulator::{NetworkSimulator, SimulationConfig};
+pub use stream::StreamImpairment;
 
 /// Network conditions to simulate
 #[derive(Debug, Clone)]
@@ -0,0 +1,183 @@
+//! Network impairment of a single stream
+//!
+//! Every pipeline carrying a stream gets impairment elements of its own from
+//! [`launch_fragment`]: `netsim`, or `queue ! identity` without
+//! gst-plugins-bad, then a `valve` for connection drops. They are looked up
+//! by name once the pipeline is built, so changing a stream's conditions
+//! updates each of its running pipelines in place and leaves every other
+//! stream alone.
+
+use super::gstreamer::configure_elements;
+use super::{NetworkConditions, NetworkController, NetworkProfile, NetworkSimulator};
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use std::sync::{Arc, Mutex};
+
+const NETSIM: &str = "impair_netsim";
+const QUEUE: &str = "impair_queue";
+const IDENTITY: &str = "impair_identity";
+const VALVE: &str = "impair_valve";
+
+/// Launch fragment placing a stream's impairment elements, to go in front
+/// of the element they feed
+pub fn launch_fragment() -> String {
+    if gst::ElementFactory::find("netsim").is_some() {
+        format!("netsim name={} ! valve name={} ! ", NETSIM, VALVE)
+    } else {
+        format!(
+            "queue name={} max-size-buffers=1000 max-size-bytes=0 max-size-time=0 leaky=2 ! \
+             identity name={} sync=true ! valve name={} ! ",
+            QUEUE, IDENTITY, VALVE
+        )
+    }
+}
+
+/// Impairment elements of one running pipeline
+struct PipelineElements {
+    netsim: Option<glib::WeakRef<gst::Element>>,
+    queue: Option<glib::WeakRef<gst::Element>>,
+    identity: Option<glib::WeakRef<gst::Element>>,
+    valve: glib::WeakRef<gst::Element>,
+}
+
+impl PipelineElements {
+    /// Set `conditions` if the pipeline still exists
+    fn configure(&self, conditions: &NetworkConditions) -> bool {
+        let Some(valve) = self.valve.upgrade() else {
+            return false;
+        };
+        let netsim = self.netsim.as_ref().and_then(|e| e.upgrade());
+        let queue = self.queue.as_ref().and_then(|e| e.upgrade());
+        let identity = self.identity.as_ref().and_then(|e| e.upgrade());
+        configure_elements(
+            conditions,
+            netsim.as_ref(),
+            queue.as_ref(),
+            identity.as_ref(),
+            Some(&valve),
+        );
+        true
+    }
+}
+
+/// Network conditions of one stream, applied to all pipelines carrying it
+pub struct StreamImpairment {
+    simulator: NetworkSimulator,
+    pipelines: Mutex<Vec<PipelineElements>>,
+}
+
+impl StreamImpairment {
+    pub fn new(conditions: NetworkConditions) -> Arc<Self> {
+        let simulator = NetworkSimulator::new();
+        simulator.apply_conditions(conditions);
+        Arc::new(Self {
+            simulator,
+            pipelines: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn with_profile(profile: NetworkProfile) -> Arc<Self> {
+        Self::new(profile.into_conditions())
+    }
+
+    /// Take over the impairment elements of a pipeline built with
+    /// [`launch_fragment`]; they get the current conditions straight away
+    pub fn attach(&self, bin: &gst::Bin) {
+        let Some(valve) = bin.by_name(VALVE) else {
+            log::warn!("No impairment elements in {}", bin.name());
+            return;
+        };
+        let elements = PipelineElements {
+            netsim: bin.by_name(NETSIM).map(|e| e.downgrade()),
+            queue: bin.by_name(QUEUE).map(|e| e.downgrade()),
+            identity: bin.by_name(IDENTITY).map(|e| e.downgrade()),
+            valve: valve.downgrade(),
+        };
+        elements.configure(&self.simulator.get_conditions());
+        self.pipelines.lock().unwrap().push(elements);
+    }
+
+    /// Pipelines currently impaired
+    pub fn pipeline_count(&self) -> usize {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        pipelines.retain(|elements| elements.valve.upgrade().is_some());
+        pipelines.len()
+    }
+
+    fn apply(&self) {
+        let conditions = self.simulator.get_conditions();
+        self.pipelines
+            .lock()
+            .unwrap()
+            .retain(|elements| elements.configure(&conditions));
+    }
+}
+
+impl NetworkController for StreamImpairment {
+    fn apply_conditions(&self, conditions: NetworkConditions) {
+        self.simulator.apply_conditions(conditions);
+        self.apply();
+    }
+
+    fn get_conditions(&self) -> NetworkConditions {
+        self.simulator.get_conditions()
+    }
+
+    fn drop_connection(&self) {
+        self.simulator.drop_connection();
+        self.apply();
+    }
+
+    fn restore_connection(&self) {
+        self.simulator.restore_connection();
+        self.apply();
+    }
+
+    fn apply_profile(&self, profile: NetworkProfile) {
+        self.simulator.apply_profile(profile);
+        self.apply();
+    }
+
+    fn reset(&self) {
+        self.simulator.reset();
+        self.apply();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline() -> gst::Bin {
+        let launch = format!("videotestsrc ! {}fakesink", launch_fragment());
+        gst::parse::launch(&launch)
+            .unwrap()
+            .downcast::<gst::Bin>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_streams_are_impaired_independently() {
+        gst::init().unwrap();
+
+        let first = StreamImpairment::new(NetworkConditions::perfect());
+        let second = StreamImpairment::new(NetworkConditions::perfect());
+        let first_pipeline = pipeline();
+        let second_pipeline = pipeline();
+        first.attach(&first_pipeline);
+        second.attach(&second_pipeline);
+        assert_eq!(first.pipeline_count(), 1);
+
+        first.drop_connection();
+        let dropped = |bin: &gst::Bin| bin.by_name(VALVE).unwrap().property::<bool>("drop");
+        assert!(dropped(&first_pipeline));
+        assert!(!dropped(&second_pipeline));
+
+        first.restore_connection();
+        assert!(!dropped(&first_pipeline));
+
+        drop(first_pipeline);
+        assert_eq!(first.pipeline_count(), 0);
+    }
+}
@@ -0,0 +1,314 @@
+//! Long-running work with progress, cancellation and a result
+//!
+//! File generation and directory scans can take minutes. Started through an
+//! [`OperationRegistry`] they run on their own thread as an [`Operation`]:
+//! the work reports progress on it and checks it for cancellation, and its
+//! result or error stays in the registry for the API (`/operations`) and
+//! the REPL (`operations`) to show. ds-rs runs its background work, such
+//! as adding a batch of sources, through the same registry.
+
+use crate::error::{Result, SourceVideoError};
+use crate::file::CancelHandle;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Finished operations kept for status queries; older ones are dropped
+const MAX_FINISHED: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl Status {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OperationStatus {
+    pub id: String,
+    pub operation: String,
+    pub status: Status,
+    /// Percent complete, once the work knows how far along it is
+    pub progress: Option<f64>,
+    /// What the work is doing right now
+    pub message: Option<String>,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// One piece of long-running work; clones share the same status
+#[derive(Clone)]
+pub struct Operation {
+    status: Arc<Mutex<OperationStatus>>,
+    cancel: CancelHandle,
+}
+
+impl Operation {
+    pub fn new(id: impl Into<String>, operation: impl Into<String>) -> Self {
+        Self {
+            status: Arc::new(Mutex::new(OperationStatus {
+                id: id.into(),
+                operation: operation.into(),
+                status: Status::Pending,
+                progress: None,
+                message: None,
+                started_at: chrono::Utc::now().to_rfc3339(),
+                completed_at: None,
+                result: None,
+                error: None,
+            })),
+            cancel: CancelHandle::new(),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        self.status.lock().unwrap().id.clone()
+    }
+
+    pub fn status(&self) -> OperationStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Record progress in percent (0-100)
+    pub fn set_progress(&self, percent: f64) {
+        self.status.lock().unwrap().progress = Some(percent.clamp(0.0, 100.0));
+    }
+
+    pub fn set_message(&self, message: impl Into<String>) {
+        self.status.lock().unwrap().message = Some(message.into());
+    }
+
+    /// Ask the work to stop; it notices at its next check
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Token for code that takes a [`CancelHandle`], such as
+    /// [`FileGenerator::with_cancel`](crate::FileGenerator::with_cancel)
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Error out of the work if it was cancelled
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(SourceVideoError::resource(format!(
+                "Operation {} cancelled",
+                self.id()
+            )));
+        }
+        Ok(())
+    }
+
+    fn start(&self) {
+        self.status.lock().unwrap().status = Status::Running;
+    }
+
+    fn finish(&self, result: std::result::Result<serde_json::Value, String>) {
+        let cancelled = self.is_cancelled();
+        let mut status = self.status.lock().unwrap();
+        status.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        match result {
+            Ok(value) => {
+                status.status = Status::Completed;
+                status.progress = Some(100.0);
+                status.result = Some(value);
+            }
+            Err(e) => {
+                status.status = if cancelled {
+                    Status::Cancelled
+                } else {
+                    Status::Failed
+                };
+                status.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Starts operations and keeps track of them
+#[derive(Default)]
+pub struct OperationRegistry {
+    operations: Mutex<VecDeque<Operation>>,
+    next_id: AtomicU64,
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` on its own thread as a new operation called `name`; the
+    /// value it returns becomes the operation's result. Work that panics
+    /// fails the operation instead of leaving it running.
+    pub fn spawn<F>(&self, name: &str, work: F) -> Operation
+    where
+        F: FnOnce(&Operation) -> Result<serde_json::Value> + Send + 'static,
+    {
+        let id = format!("op-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let operation = Operation::new(id, name);
+        self.insert(operation.clone());
+
+        let worker = operation.clone();
+        let name = name.to_string();
+        std::thread::spawn(move || {
+            worker.start();
+            let result = match catch_unwind(AssertUnwindSafe(|| work(&worker))) {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(payload) => Err(format!(
+                    "Operation panicked: {}",
+                    panic_message(payload.as_ref())
+                )),
+            };
+            if let Err(e) = &result {
+                log::warn!("Operation {} ({}) failed: {}", worker.id(), name, e);
+            }
+            worker.finish(result);
+        });
+        operation
+    }
+
+    fn insert(&self, operation: Operation) {
+        let mut operations = self.operations.lock().unwrap();
+        operations.push_back(operation);
+        let finished = operations
+            .iter()
+            .filter(|op| op.status().status.is_finished())
+            .count();
+        if finished > MAX_FINISHED {
+            // Oldest finished first, running ones are never dropped
+            if let Some(index) = operations
+                .iter()
+                .position(|op| op.status().status.is_finished())
+            {
+                operations.remove(index);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Operation> {
+        self.operations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|op| op.id() == id)
+            .cloned()
+    }
+
+    /// Status of every operation, oldest first
+    pub fn list(&self) -> Vec<OperationStatus> {
+        self.operations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(Operation::status)
+            .collect()
+    }
+
+    /// Cancel operation `id`; false if there is no such operation or it
+    /// has already finished
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.get(id) {
+            Some(op) if !op.status().status.is_finished() => {
+                op.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait_finished(op: &Operation) -> OperationStatus {
+        let start = Instant::now();
+        loop {
+            let status = op.status();
+            if status.status.is_finished() || start.elapsed() > Duration::from_secs(5) {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_completed_operation_keeps_result() {
+        let registry = OperationRegistry::new();
+
+        let done = registry.spawn("count", |op| {
+            op.set_progress(50.0);
+            op.set_message("halfway");
+            Ok(serde_json::json!({ "count": 3 }))
+        });
+        let status = wait_finished(&done);
+        assert_eq!(status.status, Status::Completed);
+        assert_eq!(status.progress, Some(100.0));
+        assert_eq!(status.result.unwrap()["count"], 3);
+    }
+
+    #[test]
+    fn test_cancel_operation() {
+        let registry = OperationRegistry::new();
+
+        let endless = registry.spawn("endless", |op| {
+            loop {
+                op.check_cancelled()?;
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+        assert!(registry.cancel(&endless.id()));
+        let status = wait_finished(&endless);
+        assert_eq!(status.status, Status::Cancelled);
+        assert!(status.error.unwrap().contains("cancelled"));
+        // Already finished
+        assert!(!registry.cancel(&endless.id()));
+    }
+
+    #[test]
+    fn test_operations_listed_in_order() {
+        let registry = OperationRegistry::new();
+        wait_finished(&registry.spawn("first", |_| Ok(serde_json::Value::Null)));
+        wait_finished(&registry.spawn("second", |_| Ok(serde_json::Value::Null)));
+
+        let ids: Vec<_> = registry.list().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, ["op-1", "op-2"]);
+    }
+
+    #[test]
+    fn test_panicking_operation_fails() {
+        let registry = OperationRegistry::new();
+
+        let broken = registry.spawn("broken", |_| panic!("bad input"));
+        let status = wait_finished(&broken);
+        assert_eq!(status.status, Status::Failed);
+        assert!(status.error.unwrap().contains("bad input"));
+    }
+}
@@ -2,6 +2,7 @@ pub mod builder;
 
 use crate::config::{FileContainer, VideoSourceConfig, VideoSourceType};
 use crate::error::{Result, SourceVideoError};
+use crate::markers;
 use crate::patterns::TestPattern;
 use gstreamer as gst;
 use gstreamer::prelude::*;
@@ -12,6 +13,19 @@ pub trait PipelineFactory: Send + Sync {
     fn get_name(&self) -> &str;
 }
 
+/// Raw video caps for a source's resolution, framerate and format
+pub fn video_caps(config: &VideoSourceConfig) -> gst::Caps {
+    gst::Caps::builder("video/x-raw")
+        .field("width", config.resolution.width as i32)
+        .field("height", config.resolution.height as i32)
+        .field(
+            "framerate",
+            gst::Fraction::new(config.framerate.numerator, config.framerate.denominator),
+        )
+        .field("format", config.format.to_caps_string())
+        .build()
+}
+
 pub struct TestPatternPipeline;
 pub struct FileSinkPipeline;
 pub struct RtspSourcePipeline;
@@ -21,14 +35,13 @@ impl TestPatternPipeline {
         Arc::new(Self)
     }
 
-    fn create_test_src(&self, name: Option<&str>, pattern: &str) -> Result<gst::Element> {
+    fn create_test_src(&self, name: Option<&str>, pattern: TestPattern) -> Result<gst::Element> {
         let src = gst::ElementFactory::make("videotestsrc")
             .name(name.unwrap_or("testsrc"))
             .build()
             .map_err(|_| SourceVideoError::element("videotestsrc"))?;
 
-        let _pattern = TestPattern::from_str(pattern)?; // Validate pattern exists
-        src.set_property_from_str("pattern", pattern);
+        src.set_property_from_str("pattern", &pattern.to_gst_pattern().to_string());
 
         Ok(src)
     }
@@ -41,6 +54,7 @@ impl PipelineFactory for TestPatternPipeline {
             .build();
 
         if let VideoSourceType::TestPattern { pattern } = &config.source_type {
+            let (pattern, marker) = markers::parse_pattern(pattern)?;
             let src = self.create_test_src(Some("source"), pattern)?;
 
             src.set_property("is-live", config.is_live);
@@ -54,17 +68,7 @@ impl PipelineFactory for TestPatternPipeline {
                 .build()
                 .map_err(|_| SourceVideoError::element("capsfilter"))?;
 
-            let caps = gst::Caps::builder("video/x-raw")
-                .field("width", config.resolution.width as i32)
-                .field("height", config.resolution.height as i32)
-                .field(
-                    "framerate",
-                    gst::Fraction::new(config.framerate.numerator, config.framerate.denominator),
-                )
-                .field("format", config.format.to_caps_string())
-                .build();
-
-            capsfilter.set_property("caps", &caps);
+            capsfilter.set_property("caps", video_caps(config));
 
             let sink = gst::ElementFactory::make("fakesink")
                 .name("sink")
@@ -78,6 +82,12 @@ impl PipelineFactory for TestPatternPipeline {
             gst::Element::link_many([&src, &capsfilter, &sink])
                 .map_err(|_| SourceVideoError::pipeline("Failed to link elements"))?;
 
+            if let Some(kind) = marker
+                && let Some(pad) = capsfilter.static_pad("src")
+            {
+                markers::attach_marker_overlay(&pad, kind, &config.name);
+            }
+
             Ok(pipeline)
         } else {
             Err(SourceVideoError::config(
@@ -98,8 +108,8 @@ impl FileSinkPipeline {
 
     fn create_encoder(&self, format: &FileContainer) -> Result<gst::Element> {
         let encoder_name = match format {
-            FileContainer::Mp4 | FileContainer::Mkv | FileContainer::Avi => "x264enc",
             FileContainer::WebM => "vp8enc",
+            _ => "x264enc",
         };
 
         gst::ElementFactory::make(encoder_name)
@@ -0,0 +1,118 @@
+//! Port selection shared by the RTSP server and the control API.
+//!
+//! A requested port of `0` asks the OS for an ephemeral port. Any other port
+//! may be given a retry budget: when it is already taken, the next ports up
+//! to `port + retries` are tried in order.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+
+/// Ports to try for a requested `port` and retry budget.
+pub fn candidates(port: u16, retries: u16) -> impl Iterator<Item = u16> {
+    let end = if port == 0 {
+        0
+    } else {
+        port.saturating_add(retries)
+    };
+    port..=end
+}
+
+/// Whether something else is already listening on `address:port`.
+pub fn is_in_use(address: &str, port: u16) -> bool {
+    if port == 0 {
+        return false;
+    }
+
+    matches!(
+        TcpListener::bind((address, port)),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse
+    )
+}
+
+/// Ask the OS for a port that is currently free on `address`.
+pub fn free_port(address: &str) -> Result<u16> {
+    let listener = TcpListener::bind((address, 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Human-readable description of a port and its retry range.
+pub fn describe_range(port: u16, retries: u16) -> String {
+    if port == 0 || retries == 0 {
+        port.to_string()
+    } else {
+        format!("{}-{}", port, port.saturating_add(retries))
+    }
+}
+
+/// An address the process ended up listening on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundEndpoint {
+    pub address: String,
+    pub port: u16,
+}
+
+/// Machine-readable summary of the ports chosen at startup.
+///
+/// Printed as a single JSON line by `serve --report-ports` so wrappers
+/// that pass port `0` can discover where the servers actually listen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundPorts {
+    pub rtsp: Option<BoundEndpoint>,
+    pub api: Option<BoundEndpoint>,
+}
+
+impl BoundPorts {
+    pub fn to_json_line(&self) -> String {
+        let mut value = serde_json::json!({ "event": "listening" });
+        if let (Some(map), Ok(serde_json::Value::Object(ports))) =
+            (value.as_object_mut(), serde_json::to_value(self))
+        {
+            map.extend(ports);
+        }
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidates(0, 5).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(candidates(8554, 0).collect::<Vec<_>>(), vec![8554]);
+        assert_eq!(
+            candidates(8554, 2).collect::<Vec<_>>(),
+            vec![8554, 8555, 8556]
+        );
+        assert_eq!(candidates(u16::MAX, 3).count(), 1);
+    }
+
+    #[test]
+    fn test_is_in_use() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert!(is_in_use("127.0.0.1", port));
+        assert!(!is_in_use("127.0.0.1", 0));
+        drop(listener);
+        assert!(!is_in_use("127.0.0.1", port));
+    }
+
+    #[test]
+    fn test_report_json_line() {
+        let report = BoundPorts {
+            rtsp: Some(BoundEndpoint {
+                address: "0.0.0.0".to_string(),
+                port: 40123,
+            }),
+            api: None,
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&report.to_json_line()).unwrap();
+        assert_eq!(value["event"], "listening");
+        assert_eq!(value["rtsp"]["port"], 40123);
+        assert!(value["api"].is_null());
+    }
+}
@@ -0,0 +1,302 @@
+//! Still frames of running sources
+//!
+//! Each source pipeline gets a preview tap when it is built: a `tee` after
+//! the raw video feeds a leaky queue and an appsink named [`PREVIEW_SINK`]
+//! that keeps only the newest frame. [`PreviewCache`] turns that frame
+//! into a JPEG on request, scaled to the asked width. It encodes each
+//! source at most once per `min_interval`: sooner requests get the last
+//! JPEG again when they ask for its width and are turned away otherwise,
+//! so polling web UIs cannot keep the encoder busy.
+
+use crate::error::{Result, SourceVideoError};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Name of the appsink holding a pipeline's newest frame
+pub const PREVIEW_SINK: &str = "preview";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreviewSettings {
+    /// Width used when a request does not ask for one
+    pub default_width: u32,
+    /// Largest width a request may ask for
+    pub max_width: u32,
+    /// Shortest time between two encodes of a source; requests in between
+    /// get the last JPEG when they ask for its width
+    pub min_interval_ms: u64,
+    /// How long to wait for a frame from a source that has none yet
+    pub timeout_ms: u64,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            default_width: 320,
+            max_width: 1280,
+            min_interval_ms: 500,
+            timeout_ms: 2000,
+        }
+    }
+}
+
+/// Branch the raw video after the pipeline's `filter` (or `source`)
+/// element into a preview appsink. Call before the pipeline starts.
+pub fn install_tap(pipeline: &gst::Pipeline) -> Result<()> {
+    let upstream = pipeline
+        .by_name("filter")
+        .or_else(|| pipeline.by_name("source"))
+        .ok_or_else(|| SourceVideoError::pipeline("No source element to tap for previews"))?;
+    let src_pad = upstream
+        .static_pad("src")
+        .ok_or_else(|| SourceVideoError::pipeline("Source element has no src pad"))?;
+
+    let tee = gst::ElementFactory::make("tee")
+        .name("preview-tee")
+        .property("allow-not-linked", true)
+        .build()
+        .map_err(|_| SourceVideoError::element("tee"))?;
+    let queue = gst::ElementFactory::make("queue")
+        .name("preview-queue")
+        .property("max-size-buffers", 1u32)
+        .property("max-size-bytes", 0u32)
+        .property("max-size-time", 0u64)
+        .property_from_str("leaky", "downstream")
+        .build()
+        .map_err(|_| SourceVideoError::element("queue"))?;
+    let sink = gst_app::AppSink::builder()
+        .name(PREVIEW_SINK)
+        .max_buffers(1)
+        .drop(true)
+        .sync(false)
+        .build();
+    // Must not hold up the pipeline's state changes
+    sink.set_property("async", false);
+
+    pipeline
+        .add_many([&tee, &queue, sink.upcast_ref()])
+        .map_err(|_| SourceVideoError::pipeline("Failed to add preview tap"))?;
+
+    let peer = src_pad.peer();
+    if let Some(peer) = &peer {
+        src_pad
+            .unlink(peer)
+            .map_err(|_| SourceVideoError::pipeline("Failed to unlink for preview tap"))?;
+    }
+    upstream
+        .link(&tee)
+        .map_err(|_| SourceVideoError::linking(upstream.name().as_str(), "preview-tee"))?;
+    if let Some(peer) = peer {
+        let tee_src = tee
+            .request_pad_simple("src_%u")
+            .ok_or_else(|| SourceVideoError::pipeline("Failed to get tee pad"))?;
+        tee_src
+            .link(&peer)
+            .map_err(|_| SourceVideoError::pipeline("Failed to relink after preview tap"))?;
+    }
+    gst::Element::link_many([&tee, &queue, sink.upcast_ref()])
+        .map_err(|_| SourceVideoError::pipeline("Failed to link preview tap"))?;
+    Ok(())
+}
+
+/// Newest frame of `pipeline` as a JPEG `width` pixels wide
+pub fn snapshot(pipeline: &gst::Pipeline, width: u32, timeout: Duration) -> Result<Vec<u8>> {
+    let sink = pipeline
+        .by_name(PREVIEW_SINK)
+        .and_then(|sink| sink.downcast::<gst_app::AppSink>().ok())
+        .ok_or_else(|| SourceVideoError::pipeline("Source has no preview tap"))?;
+    let sample = sink
+        .try_pull_sample(gst::ClockTime::from_mseconds(timeout.as_millis() as u64))
+        .ok_or_else(|| SourceVideoError::pipeline("No frame available, is the source playing?"))?;
+
+    let info = sample
+        .caps()
+        .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+        .ok_or_else(|| SourceVideoError::pipeline("Preview frame has no video caps"))?;
+    let (width, height) = scaled_size(info.width(), info.height(), width);
+    let caps = gst::Caps::builder("image/jpeg")
+        .field("width", width as i32)
+        .field("height", height as i32)
+        .build();
+
+    let jpeg = gst_video::convert_sample(&sample, &caps, gst::ClockTime::from_seconds(5))
+        .map_err(|e| SourceVideoError::pipeline(format!("Failed to encode preview: {}", e)))?;
+    let buffer = jpeg
+        .buffer()
+        .ok_or_else(|| SourceVideoError::pipeline("Encoded preview is empty"))?;
+    let map = buffer
+        .map_readable()
+        .map_err(|_| SourceVideoError::pipeline("Failed to read encoded preview"))?;
+    Ok(map.to_vec())
+}
+
+/// `width` (never wider than the source) by the height keeping the
+/// aspect ratio, both even for the encoder
+pub fn scaled_size(source_width: u32, source_height: u32, width: u32) -> (u32, u32) {
+    let width = width.min(source_width).max(2) & !1;
+    let height = (source_height as u64 * width as u64 / source_width.max(1) as u64) as u32;
+    (width, height.max(2) & !1)
+}
+
+/// Last JPEG per source, to rate limit encoding
+pub struct PreviewCache {
+    settings: PreviewSettings,
+    last: Mutex<HashMap<String, CachedPreview>>,
+}
+
+struct CachedPreview {
+    /// When the last encode of the source started
+    at: Instant,
+    /// Width and JPEG of the last encode that succeeded
+    jpeg: Option<(u32, Arc<Vec<u8>>)>,
+}
+
+impl PreviewCache {
+    pub fn new(settings: PreviewSettings) -> Self {
+        Self {
+            settings,
+            last: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn settings(&self) -> &PreviewSettings {
+        &self.settings
+    }
+
+    /// Width a request for `requested` pixels is served at
+    pub fn width_for(&self, requested: Option<u32>) -> u32 {
+        requested
+            .unwrap_or(self.settings.default_width)
+            .clamp(16, self.settings.max_width.max(16))
+    }
+
+    /// Whether a request for source `id` at `width` may be served: the
+    /// JPEG made within the minimum interval when it has that width,
+    /// `Ok(None)` when a new one may be encoded with
+    /// [`encode`](Self::encode), or how long to wait when the source was
+    /// encoded at another width within the interval. Each source is
+    /// encoded at most once per interval, whatever the widths asked for.
+    pub fn reserve(
+        &self,
+        id: &str,
+        width: u32,
+    ) -> std::result::Result<Option<Arc<Vec<u8>>>, Duration> {
+        let min_interval = Duration::from_millis(self.settings.min_interval_ms);
+        let mut last = self.last.lock().unwrap();
+        match last.get_mut(id) {
+            Some(cached) if cached.at.elapsed() < min_interval => match &cached.jpeg {
+                Some((cached_width, jpeg)) if *cached_width == width => Ok(Some(jpeg.clone())),
+                _ => Err(min_interval - cached.at.elapsed()),
+            },
+            Some(cached) => {
+                cached.at = Instant::now();
+                Ok(None)
+            }
+            None => {
+                last.insert(
+                    id.to_string(),
+                    CachedPreview {
+                        at: Instant::now(),
+                        jpeg: None,
+                    },
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// JPEG of source `id` at `width`, after [`reserve`](Self::reserve)
+    /// allowed it. A source that has stopped producing frames gets its
+    /// last JPEG rather than an error.
+    pub fn encode(&self, id: &str, pipeline: &gst::Pipeline, width: u32) -> Result<Arc<Vec<u8>>> {
+        let timeout = Duration::from_millis(self.settings.timeout_ms);
+        let jpeg = match snapshot(pipeline, width, timeout) {
+            Ok(jpeg) => Arc::new(jpeg),
+            Err(e) => {
+                return match self
+                    .last
+                    .lock()
+                    .unwrap()
+                    .get(id)
+                    .and_then(|c| c.jpeg.as_ref())
+                {
+                    Some((cached_width, jpeg)) if *cached_width == width => Ok(jpeg.clone()),
+                    _ => Err(e),
+                };
+            }
+        };
+        if let Some(cached) = self.last.lock().unwrap().get_mut(id) {
+            cached.jpeg = Some((width, jpeg.clone()));
+        }
+        Ok(jpeg)
+    }
+
+    /// Drop the cached JPEG of a removed source
+    pub fn forget(&self, id: &str) {
+        self.last.lock().unwrap().remove(id);
+    }
+}
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        Self::new(PreviewSettings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_size() {
+        assert_eq!(scaled_size(1920, 1080, 320), (320, 180));
+        assert_eq!(scaled_size(640, 480, 4000), (640, 480));
+        assert_eq!(scaled_size(1920, 1080, 101), (100, 56));
+    }
+
+    #[test]
+    fn test_requested_width_clamped() {
+        let cache = PreviewCache::default();
+        assert_eq!(cache.width_for(None), 320);
+        assert_eq!(cache.width_for(Some(1)), 16);
+        assert_eq!(cache.width_for(Some(10_000)), 1280);
+    }
+
+    #[test]
+    fn test_install_tap() {
+        gst::init().unwrap();
+        let pipeline = gst::parse::launch("videotestsrc name=source ! fakesink name=sink")
+            .unwrap()
+            .downcast::<gst::Pipeline>()
+            .unwrap();
+        install_tap(&pipeline).unwrap();
+        assert!(pipeline.by_name(PREVIEW_SINK).is_some());
+        let sink_pad = pipeline
+            .by_name("sink")
+            .unwrap()
+            .static_pad("sink")
+            .unwrap();
+        assert!(sink_pad.is_linked());
+    }
+
+    #[test]
+    fn test_sources_are_encoded_once_per_interval() {
+        let cache = PreviewCache::default();
+        assert_eq!(cache.reserve("cam", 320), Ok(None));
+        assert!(cache.reserve("cam", 320).is_err());
+        assert!(cache.reserve("cam", 640).is_err());
+        assert_eq!(cache.reserve("other", 640), Ok(None));
+
+        let cache = PreviewCache::new(PreviewSettings {
+            min_interval_ms: 0,
+            ..Default::default()
+        });
+        assert_eq!(cache.reserve("cam", 320), Ok(None));
+        assert_eq!(cache.reserve("cam", 640), Ok(None));
+    }
+}
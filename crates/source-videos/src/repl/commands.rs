@@ -1,5 +1,7 @@
 use super::{ReplContext, output::ReplOutput};
-use crate::{Result, SourceVideoError, TestPattern};
+use crate::operation::Status;
+use crate::watch::events::{EVENT_TYPES, EventFilter};
+use crate::{DirectoryConfig, DirectoryScanner, Result, SourceVideoError, TestPattern};
 use async_trait::async_trait;
 use colored::Colorize;
 use comfy_table::{Cell, Color, Table, presets};
@@ -52,6 +54,7 @@ pub fn register_commands(commands: &mut HashMap<String, Box<dyn ReplCommand>>) {
     commands.insert("status".to_string(), Box::new(StatusCommand));
     commands.insert("metrics".to_string(), Box::new(MetricsCommand));
     commands.insert("watch".to_string(), Box::new(WatchCommand));
+    commands.insert("watchers".to_string(), Box::new(WatchersCommand));
     commands.insert("health".to_string(), Box::new(HealthCommand));
 
     // Configuration commands
@@ -63,8 +66,14 @@ pub fn register_commands(commands: &mut HashMap<String, Box<dyn ReplCommand>>) {
     commands.insert("help".to_string(), Box::new(HelpCommand));
     commands.insert("?".to_string(), Box::new(HelpCommand)); // Alias
     commands.insert("patterns".to_string(), Box::new(PatternsCommand));
+    commands.insert("rotate".to_string(), Box::new(RotateCommand));
+    commands.insert("playback".to_string(), Box::new(PlaybackCommand));
     commands.insert("examples".to_string(), Box::new(ExamplesCommand));
 
+    // Background operations
+    commands.insert("operations".to_string(), Box::new(OperationsCommand));
+    commands.insert("ops".to_string(), Box::new(OperationsCommand)); // Alias
+
     // Scripting commands
     commands.insert("run".to_string(), Box::new(RunCommand));
     commands.insert("record".to_string(), Box::new(RecordCommand));
@@ -93,10 +102,10 @@ impl ReplCommand for AddCommand {
         }
 
         let source_type = args[0];
-        let mut sv = context.source_videos.write().await;
 
         match source_type {
             "pattern" => {
+                let mut sv = context.source_videos.write().await;
                 if args.len() < 2 {
                     output.print_error("Usage: add pattern <pattern_name> [mount_name]");
                     return Ok(CommandResult::Continue);
@@ -124,18 +133,41 @@ impl ReplCommand for AddCommand {
 
                 let path = PathBuf::from(args[1]);
                 let recursive = args.contains(&"--recursive") || args.contains(&"-r");
-                let watch = args.contains(&"--watch") || args.contains(&"-w");
+                if args.contains(&"--watch") || args.contains(&"-w") {
+                    output.print_warning("Watching is not started from 'add directory'");
+                }
 
-                output.print_info(&format!(
-                    "Adding directory: {} (recursive: {}, watch: {})",
-                    path.display(),
+                let config = DirectoryConfig {
+                    path: path.to_string_lossy().to_string(),
                     recursive,
-                    watch
-                ));
+                    filters: None,
+                    lazy_loading: false,
+                    mount_prefix: None,
+                };
+                let source_videos = context.source_videos.clone();
+                let operation = context.operations.spawn("scan", move |operation| {
+                    // Mount files as they are found; dropping the stream
+                    // on cancel stops the scan
+                    let mut found = 0;
+                    let mut added = 0;
+                    for config in DirectoryScanner::new(config).scan_stream()? {
+                        operation.check_cancelled()?;
+                        found += 1;
+                        if source_videos.blocking_write().add_source(config).is_ok() {
+                            added += 1;
+                        }
+                        operation.set_message(format!("Added {} of {} files found", added, found));
+                    }
+                    Ok(serde_json::json!({ "found_count": found, "added_count": added }))
+                });
 
-                // This is a simplified implementation - in a full implementation,
-                // we would integrate with the directory scanning and watching system
-                output.print_success("Directory source added (placeholder implementation)");
+                output.print_success(&format!(
+                    "Scanning {} in the background as {} (recursive: {})",
+                    path.display(),
+                    operation.id(),
+                    recursive
+                ));
+                output.print_info("Use 'operations' to follow it");
             }
             "file" => {
                 if args.len() < 2 {
@@ -281,6 +313,85 @@ impl ReplCommand for ListCommand {
     }
 }
 
+// Operation Commands
+
+struct OperationsCommand;
+
+#[async_trait]
+impl ReplCommand for OperationsCommand {
+    async fn execute(
+        &self,
+        args: &[&str],
+        context: &mut ReplContext,
+        output: &ReplOutput,
+    ) -> Result<CommandResult> {
+        if let ["cancel", id] = args {
+            if context.operations.cancel(id) {
+                output.print_success(&format!("Cancelling operation {}", id));
+            } else {
+                output.print_error(&format!("No running operation {}", id));
+            }
+            return Ok(CommandResult::Continue);
+        }
+
+        let operations = context.operations.list();
+        if operations.is_empty() {
+            output.print_info("No operations");
+            return Ok(CommandResult::Continue);
+        }
+
+        let mut table = Table::new();
+        table.load_preset(presets::UTF8_FULL).set_header(vec![
+            Cell::new("ID").fg(Color::Cyan),
+            Cell::new("Operation").fg(Color::Cyan),
+            Cell::new("Status").fg(Color::Cyan),
+            Cell::new("Progress").fg(Color::Cyan),
+            Cell::new("Details").fg(Color::Cyan),
+        ]);
+
+        for op in operations {
+            let status_color = match op.status {
+                Status::Completed => Color::Green,
+                Status::Running | Status::Pending => Color::Yellow,
+                Status::Failed | Status::Cancelled => Color::Red,
+            };
+            let details = op
+                .error
+                .or(op.message)
+                .or_else(|| op.result.map(|result| result.to_string()))
+                .unwrap_or_default();
+
+            table.add_row(vec![
+                Cell::new(&op.id),
+                Cell::new(&op.operation),
+                Cell::new(format!("{:?}", op.status).to_lowercase()).fg(status_color),
+                Cell::new(
+                    op.progress
+                        .map(|percent| format!("{:.0}%", percent))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                Cell::new(details),
+            ]);
+        }
+
+        output.print_table(table);
+        Ok(CommandResult::Continue)
+    }
+
+    fn name(&self) -> &'static str {
+        "operations"
+    }
+    fn description(&self) -> &'static str {
+        "List or cancel background operations"
+    }
+    fn usage(&self) -> &'static str {
+        "operations [cancel <id>]"
+    }
+    fn examples(&self) -> Vec<&'static str> {
+        vec!["operations", "ops cancel op-1"]
+    }
+}
+
 // Network Commands
 
 struct NetworkCommand;
@@ -519,6 +630,7 @@ impl ReplCommand for HelpCommand {
                         ("list", "List all sources"),
                         ("modify", "Modify source properties"),
                         ("inspect", "Show detailed source info"),
+                        ("operations", "List or cancel background scans"),
                     ],
                 ),
                 (
@@ -534,6 +646,7 @@ impl ReplCommand for HelpCommand {
                         ("serve", "Start RTSP server"),
                         ("stop", "Stop RTSP server"),
                         ("status", "Show server status"),
+                        ("playback", "Pause, resume or step file mounts"),
                     ],
                 ),
                 (
@@ -541,6 +654,7 @@ impl ReplCommand for HelpCommand {
                     vec![
                         ("metrics", "Show performance metrics"),
                         ("watch", "Watch source in real-time"),
+                        ("watchers", "Pause, resume or filter file watchers"),
                         ("health", "Check system health"),
                     ],
                 ),
@@ -634,6 +748,305 @@ impl ReplCommand for PatternsCommand {
     }
 }
 
+struct RotateCommand;
+
+#[async_trait]
+impl ReplCommand for RotateCommand {
+    async fn execute(
+        &self,
+        args: &[&str],
+        context: &mut ReplContext,
+        output: &ReplOutput,
+    ) -> Result<CommandResult> {
+        let sv = context.source_videos.read().await;
+        let Some(server) = sv.rtsp_server() else {
+            output.print_error("RTSP server not running. Start it with 'serve'.");
+            return Ok(CommandResult::Continue);
+        };
+        let controller = server.pattern_controller();
+
+        let result = match args {
+            [] => {
+                let statuses = controller.statuses();
+                if statuses.is_empty() {
+                    output.print_info("No test pattern sources");
+                }
+                for status in statuses {
+                    let schedule = match &status.schedule {
+                        Some(schedule) => format!(
+                            "{} every {}",
+                            schedule.patterns.join(","),
+                            schedule
+                                .interval_secs
+                                .map_or("trigger".to_string(), |secs| format!("{}s", secs))
+                        ),
+                        None => "fixed".to_string(),
+                    };
+                    output.print_info(&format!(
+                        "  {:15} {:12} {} ({} clients)",
+                        status.source.bright_white(),
+                        status.pattern,
+                        schedule,
+                        status.live_elements
+                    ));
+                }
+                Ok(String::new())
+            }
+            [source, "next"] => controller
+                .advance(source)
+                .map(|pattern| format!("{} switched to {}", source, pattern)),
+            [source, "stop"] => controller
+                .clear_schedule(source)
+                .map(|_| format!("Stopped rotating {}", source)),
+            [source, "set", pattern] => controller
+                .set_pattern(source, pattern)
+                .map(|pattern| format!("{} switched to {}", source, pattern)),
+            [source, patterns, rest @ ..] if rest.len() <= 1 => {
+                let interval = match rest.first().map(|secs| secs.parse::<u64>()) {
+                    Some(Ok(secs)) => Some(secs),
+                    Some(Err(_)) => {
+                        output.print_error(&format!("Invalid interval: {}", rest[0]));
+                        return Ok(CommandResult::Continue);
+                    }
+                    None => None,
+                };
+                let schedule = crate::rotation::RotationSchedule::new(
+                    patterns.split(',').map(str::to_string).collect(),
+                    interval,
+                );
+                controller
+                    .set_schedule(source, schedule)
+                    .map(|pattern| format!("Rotating {} starting with {}", source, pattern))
+            }
+            _ => {
+                output.print_error(&format!("Usage: {}", self.usage()));
+                return Ok(CommandResult::Continue);
+            }
+        };
+
+        match result {
+            Ok(message) if message.is_empty() => {}
+            Ok(message) => output.print_success(&message),
+            Err(e) => output.print_error(&format!("Failed to change pattern: {}", e)),
+        }
+
+        Ok(CommandResult::Continue)
+    }
+
+    fn name(&self) -> &'static str {
+        "rotate"
+    }
+    fn description(&self) -> &'static str {
+        "Change or rotate the pattern of a live test source"
+    }
+    fn usage(&self) -> &'static str {
+        "rotate [<source> <p1,p2,...> [seconds] | <source> next | <source> stop | <source> set <pattern>]"
+    }
+    fn examples(&self) -> Vec<&'static str> {
+        vec![
+            "rotate",
+            "rotate test-1 smpte,ball,snow 10",
+            "rotate test-1 smpte,ball",
+            "rotate test-1 next",
+            "rotate test-1 set checkers-8",
+            "rotate test-1 stop",
+        ]
+    }
+}
+
+struct PlaybackCommand;
+
+#[async_trait]
+impl ReplCommand for PlaybackCommand {
+    async fn execute(
+        &self,
+        args: &[&str],
+        context: &mut ReplContext,
+        output: &ReplOutput,
+    ) -> Result<CommandResult> {
+        let sv = context.source_videos.read().await;
+        let Some(server) = sv.rtsp_server() else {
+            output.print_error("RTSP server not running. Start it with 'serve'.");
+            return Ok(CommandResult::Continue);
+        };
+
+        let result = match args {
+            [] => {
+                let statuses = server.playback_status();
+                if statuses.is_empty() {
+                    output.print_info("No file mounts");
+                }
+                for status in statuses {
+                    output.print_info(&format!(
+                        "  {:20} {:8} frame {} ({} media)",
+                        status.mount.bright_white(),
+                        if status.paused { "paused" } else { "playing" },
+                        status.frame,
+                        status.media
+                    ));
+                }
+                return Ok(CommandResult::Continue);
+            }
+            [mount, "pause"] => server.pause_mount(mount),
+            [mount, "resume"] => server.resume_mount(mount),
+            [mount, "step", rest @ ..] if rest.len() <= 1 => {
+                match rest.first().map_or(Ok(1), |frames| frames.parse::<u64>()) {
+                    Ok(frames) if frames > 0 => server.step_mount(mount, frames),
+                    _ => {
+                        output.print_error(&format!("Invalid frame count: {}", rest[0]));
+                        return Ok(CommandResult::Continue);
+                    }
+                }
+            }
+            _ => {
+                output.print_error(&format!("Usage: {}", self.usage()));
+                return Ok(CommandResult::Continue);
+            }
+        };
+
+        match result {
+            Ok(status) => output.print_success(&format!(
+                "{} {} at frame {}",
+                status.mount,
+                if status.paused { "paused" } else { "playing" },
+                status.frame
+            )),
+            Err(e) => output.print_error(&format!("Playback control failed: {}", e)),
+        }
+
+        Ok(CommandResult::Continue)
+    }
+
+    fn name(&self) -> &'static str {
+        "playback"
+    }
+    fn description(&self) -> &'static str {
+        "Pause, resume or step the frames of a file mount"
+    }
+    fn usage(&self) -> &'static str {
+        "playback [<mount> pause | <mount> resume | <mount> step [frames]]"
+    }
+    fn examples(&self) -> Vec<&'static str> {
+        vec![
+            "playback",
+            "playback /clip pause",
+            "playback /clip step",
+            "playback /clip step 10",
+            "playback /clip resume",
+        ]
+    }
+}
+
+struct WatchersCommand;
+
+#[async_trait]
+impl ReplCommand for WatchersCommand {
+    async fn execute(
+        &self,
+        args: &[&str],
+        context: &mut ReplContext,
+        output: &ReplOutput,
+    ) -> Result<CommandResult> {
+        let mut watchers = context.watchers.write().await;
+
+        let result = match args {
+            [] => {
+                let statuses = watchers.statuses();
+                if statuses.is_empty() {
+                    output.print_info("No watchers");
+                }
+                for status in statuses {
+                    output.print_info(&format!(
+                        "  {:38} {:8} {:10} backlog {}",
+                        status.id.bright_white(),
+                        if status.paused { "paused" } else { "active" },
+                        if status.filtered {
+                            "filtered"
+                        } else {
+                            "all events"
+                        },
+                        status.backlog
+                    ));
+                }
+                return Ok(CommandResult::Continue);
+            }
+            ["add", path, rest @ ..] => {
+                let recursive = rest.contains(&"--recursive") || rest.contains(&"-r");
+                let added = if rest.contains(&"--initial") {
+                    watchers.add_synced_directory_watcher(path, recursive).await
+                } else {
+                    watchers.add_directory_watcher(path, recursive).await
+                };
+                match added {
+                    Ok(id) => {
+                        output.print_success(&format!("Watching {} as {}", path, id));
+                        return Ok(CommandResult::Continue);
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            [id, "pause"] => watchers.pause_watcher(id),
+            [id, "resume"] => watchers.resume_watcher(id),
+            [id, "unfilter"] => watchers.set_filter(id, None),
+            [id, "filter", terms @ ..] if !terms.is_empty() => {
+                // Event type names, anything else is an extension
+                let filter = terms.iter().fold(EventFilter::new(), |filter, term| {
+                    let term = term.to_lowercase();
+                    if EVENT_TYPES.contains(&term.as_str()) {
+                        filter.with_event_type(term)
+                    } else {
+                        filter.with_include_extension(term.trim_start_matches('.').to_string())
+                    }
+                });
+                watchers.set_filter(id, Some(filter))
+            }
+            _ => {
+                output.print_error(&format!("Usage: {}", self.usage()));
+                return Ok(CommandResult::Continue);
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                let id = args[0];
+                output.print_success(&format!(
+                    "{} {}, backlog {}",
+                    id,
+                    if watchers.is_paused(id) {
+                        "paused"
+                    } else {
+                        "active"
+                    },
+                    watchers.backlog_len(id).unwrap_or(0)
+                ));
+            }
+            Err(e) => output.print_error(&format!("Watcher control failed: {}", e)),
+        }
+
+        Ok(CommandResult::Continue)
+    }
+
+    fn name(&self) -> &'static str {
+        "watchers"
+    }
+    fn description(&self) -> &'static str {
+        "Pause, resume or filter file watchers"
+    }
+    fn usage(&self) -> &'static str {
+        "watchers [add <dir> [--recursive] [--initial] | <id> pause | <id> resume | <id> filter <event types and extensions> | <id> unfilter]"
+    }
+    fn examples(&self) -> Vec<&'static str> {
+        vec![
+            "watchers",
+            "watchers add /media/incoming --recursive --initial",
+            "watchers <id> pause",
+            "watchers <id> filter created .mp4 .mkv",
+            "watchers <id> unfilter",
+            "watchers <id> resume",
+        ]
+    }
+}
+
 // Placeholder implementations for remaining commands
 
 macro_rules! placeholder_command {
@@ -38,6 +38,9 @@ impl ReplCompleter {
             "help".to_string(),
             "?".to_string(),
             "patterns".to_string(),
+            "rotate".to_string(),
+            "playback".to_string(),
+            "watchers".to_string(),
             "examples".to_string(),
             // Scripting
             "run".to_string(),
@@ -1,4 +1,5 @@
-use crate::{Result, SourceVideoError, SourceVideos};
+use crate::operation::OperationRegistry;
+use crate::{Result, SourceVideoError, SourceVideos, WatcherManager};
 use colored::Colorize;
 use comfy_table::{Cell, Color, Table, presets};
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
@@ -104,10 +105,21 @@ pub struct ReplContext {
     pub start_time: Instant,
     pub command_history: Vec<String>,
     pub variables: HashMap<String, String>,
+    /// Scans and other work running in the background
+    pub operations: Arc<OperationRegistry>,
+    pub watchers: Arc<RwLock<WatcherManager>>,
 }
 
 impl ReplContext {
     pub fn new(source_videos: SourceVideos) -> Self {
+        Self::with_watchers(source_videos, Arc::new(RwLock::new(WatcherManager::new())))
+    }
+
+    /// A context controlling `watchers`, e.g. those of a running server
+    pub fn with_watchers(
+        source_videos: SourceVideos,
+        watchers: Arc<RwLock<WatcherManager>>,
+    ) -> Self {
         Self {
             source_videos: Arc::new(RwLock::new(source_videos)),
             output_format: OutputFormat::Text,
@@ -115,6 +127,8 @@ impl ReplContext {
             start_time: Instant::now(),
             command_history: Vec::new(),
             variables: HashMap::new(),
+            operations: Arc::new(OperationRegistry::new()),
+            watchers,
         }
     }
 
@@ -132,6 +146,16 @@ pub struct EnhancedRepl {
 
 impl EnhancedRepl {
     pub fn new(source_videos: SourceVideos) -> Result<Self> {
+        Self::with_watchers(source_videos, Arc::new(RwLock::new(WatcherManager::new())))
+    }
+
+    /// A REPL whose `watchers` commands control `watchers`. Unless their
+    /// events are already received elsewhere, the REPL applies them to its
+    /// sources.
+    pub fn with_watchers(
+        source_videos: SourceVideos,
+        watchers: Arc<RwLock<WatcherManager>>,
+    ) -> Result<Self> {
         let mut editor = Editor::new()
             .map_err(|e| SourceVideoError::config(format!("Failed to create editor: {}", e)))?;
         editor.set_helper(Some(ReplHelper::new()));
@@ -148,7 +172,7 @@ impl EnhancedRepl {
             let _ = editor.load_history(history_path);
         }
 
-        let context = ReplContext::new(source_videos);
+        let context = ReplContext::with_watchers(source_videos, watchers);
         let output = ReplOutput::new();
         let mut commands: HashMap<String, Box<dyn ReplCommand>> = HashMap::new();
 
@@ -165,6 +189,7 @@ impl EnhancedRepl {
 
     pub async fn run(&mut self) -> Result<()> {
         self.output.print_welcome(&self.context);
+        self.receive_watch_events().await;
 
         loop {
             let prompt = self.get_prompt();
@@ -214,6 +239,22 @@ impl EnhancedRepl {
         Ok(())
     }
 
+    /// Apply watcher events to the sources in the background, without
+    /// holding the watchers' lock while waiting for them
+    async fn receive_watch_events(&self) {
+        let Some(mut events) = self.context.watchers.write().await.take_receiver() else {
+            return;
+        };
+        let source_videos = self.context.source_videos.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if let Err(e) = source_videos.write().await.handle_file_event(&event) {
+                    log::warn!("Failed to apply {} event: {}", event.event_type(), e);
+                }
+            }
+        });
+    }
+
     fn get_prompt(&self) -> String {
         if self.context.verbose {
             format!("[{}] > ", self.format_uptime())
@@ -0,0 +1,341 @@
+//! Runtime pattern changes and rotation schedules for live test sources
+//!
+//! Every media built for a test pattern source registers its `videotestsrc`
+//! here. Changing a pattern sets the element's `pattern` property in place,
+//! so connected RTSP clients keep their session and simply see the new
+//! picture from the next frame on.
+
+use crate::error::{Result, SourceVideoError};
+use crate::patterns::{PatternRotator, TestPattern};
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// How often the scheduler thread checks for due rotations
+const TICK: Duration = Duration::from_millis(250);
+
+/// Patterns to cycle through, every `interval_secs` or only when triggered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationSchedule {
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+impl RotationSchedule {
+    pub fn new(patterns: Vec<String>, interval_secs: Option<u64>) -> Self {
+        Self {
+            patterns,
+            interval_secs,
+        }
+    }
+
+    pub fn parse_patterns(&self) -> Result<Vec<TestPattern>> {
+        if self.patterns.is_empty() {
+            return Err(SourceVideoError::config(
+                "Rotation schedule needs at least one pattern",
+            ));
+        }
+        if self.interval_secs == Some(0) {
+            return Err(SourceVideoError::config(
+                "Rotation interval must be at least one second",
+            ));
+        }
+        self.patterns
+            .iter()
+            .map(|name| TestPattern::from_str(name))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternStatus {
+    pub source: String,
+    pub pattern: String,
+    pub schedule: Option<RotationSchedule>,
+    pub rotations: u64,
+    pub live_elements: usize,
+}
+
+struct SourcePatterns {
+    pattern: TestPattern,
+    elements: Vec<glib::WeakRef<gst::Element>>,
+    schedule: Option<(RotationSchedule, PatternRotator)>,
+    changed_at: Instant,
+    rotations: u64,
+}
+
+impl SourcePatterns {
+    fn new(pattern: TestPattern) -> Self {
+        Self {
+            pattern,
+            elements: Vec::new(),
+            schedule: None,
+            changed_at: Instant::now(),
+            rotations: 0,
+        }
+    }
+
+    fn apply(&mut self, pattern: TestPattern) {
+        self.pattern = pattern;
+        self.changed_at = Instant::now();
+        self.elements.retain(|weak| match weak.upgrade() {
+            Some(element) => {
+                element.set_property_from_str("pattern", &pattern.to_gst_pattern().to_string());
+                true
+            }
+            None => false,
+        });
+    }
+
+    fn due(&self, now: Instant) -> bool {
+        match &self.schedule {
+            Some((
+                RotationSchedule {
+                    interval_secs: Some(secs),
+                    ..
+                },
+                _,
+            )) => now.duration_since(self.changed_at) >= Duration::from_secs(*secs),
+            _ => false,
+        }
+    }
+
+    fn status(&self, source: &str) -> PatternStatus {
+        PatternStatus {
+            source: source.to_string(),
+            pattern: self.pattern.to_string(),
+            schedule: self.schedule.as_ref().map(|(schedule, _)| schedule.clone()),
+            rotations: self.rotations,
+            live_elements: self
+                .elements
+                .iter()
+                .filter(|weak| weak.upgrade().is_some())
+                .count(),
+        }
+    }
+}
+
+/// Registry of the live pattern elements of every test source
+#[derive(Default)]
+pub struct PatternController {
+    sources: Mutex<HashMap<String, SourcePatterns>>,
+    scheduler_running: AtomicBool,
+}
+
+impl PatternController {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Declare a test source so its pattern can be changed before any
+    /// client has connected
+    pub fn add_source(&self, source: &str, initial: TestPattern) {
+        self.sources
+            .lock()
+            .unwrap()
+            .entry(source.to_string())
+            .or_insert_with(|| SourcePatterns::new(initial));
+    }
+
+    /// Track a source's `videotestsrc`; it is switched to the source's
+    /// current pattern straight away so new clients join the rotation
+    pub fn register(&self, source: &str, element: &gst::Element, initial: TestPattern) {
+        let mut sources = self.sources.lock().unwrap();
+        let entry = sources
+            .entry(source.to_string())
+            .or_insert_with(|| SourcePatterns::new(initial));
+        if entry.pattern != initial {
+            element.set_property_from_str("pattern", &entry.pattern.to_gst_pattern().to_string());
+        }
+        entry.elements.push(element.downgrade());
+    }
+
+    /// Forget a source, e.g. when its mount is removed
+    pub fn unregister(&self, source: &str) {
+        self.sources.lock().unwrap().remove(source);
+    }
+
+    pub fn set_pattern(&self, source: &str, pattern: &str) -> Result<TestPattern> {
+        self.switch(source, TestPattern::from_str(pattern)?)
+    }
+
+    pub fn switch(&self, source: &str, pattern: TestPattern) -> Result<TestPattern> {
+        self.with_source(source, |entry| {
+            entry.apply(pattern);
+            Ok(pattern)
+        })
+    }
+
+    /// Start rotating through `schedule`, switching to its first pattern now
+    pub fn set_schedule(
+        self: &Arc<Self>,
+        source: &str,
+        schedule: RotationSchedule,
+    ) -> Result<TestPattern> {
+        let mut rotator = PatternRotator::new(schedule.parse_patterns()?);
+        let timed = schedule.interval_secs.is_some();
+        let first = self.with_source(source, |entry| {
+            let first = rotator.next();
+            entry.apply(first);
+            entry.schedule = Some((schedule, rotator));
+            Ok(first)
+        })?;
+        if timed {
+            self.ensure_scheduler();
+        }
+        Ok(first)
+    }
+
+    pub fn clear_schedule(&self, source: &str) -> Result<()> {
+        self.with_source(source, |entry| {
+            entry.schedule = None;
+            Ok(())
+        })
+    }
+
+    /// Move a scheduled source on to its next pattern
+    pub fn advance(&self, source: &str) -> Result<TestPattern> {
+        self.with_source(source, |entry| {
+            let Some((_, rotator)) = entry.schedule.as_mut() else {
+                return Err(SourceVideoError::config(format!(
+                    "Source {} has no rotation schedule",
+                    source
+                )));
+            };
+            let next = rotator.next();
+            entry.apply(next);
+            entry.rotations += 1;
+            Ok(next)
+        })
+    }
+
+    /// Rotate every source whose interval has elapsed
+    pub fn tick(&self) -> Vec<(String, TestPattern)> {
+        let now = Instant::now();
+        let mut rotated = Vec::new();
+        let mut sources = self.sources.lock().unwrap();
+        for (name, entry) in sources.iter_mut().filter(|(_, entry)| entry.due(now)) {
+            if let Some((_, rotator)) = entry.schedule.as_mut() {
+                let next = rotator.next();
+                entry.apply(next);
+                entry.rotations += 1;
+                log::debug!("Rotated {} to {}", name, next);
+                rotated.push((name.clone(), next));
+            }
+        }
+        rotated
+    }
+
+    pub fn status(&self, source: &str) -> Option<PatternStatus> {
+        self.sources
+            .lock()
+            .unwrap()
+            .get(source)
+            .map(|entry| entry.status(source))
+    }
+
+    pub fn statuses(&self) -> Vec<PatternStatus> {
+        let sources = self.sources.lock().unwrap();
+        let mut statuses: Vec<_> = sources
+            .iter()
+            .map(|(name, entry)| entry.status(name))
+            .collect();
+        statuses.sort_by(|a, b| a.source.cmp(&b.source));
+        statuses
+    }
+
+    fn with_source<T>(
+        &self,
+        source: &str,
+        f: impl FnOnce(&mut SourcePatterns) -> Result<T>,
+    ) -> Result<T> {
+        let mut sources = self.sources.lock().unwrap();
+        let entry = sources
+            .get_mut(source)
+            .ok_or_else(|| SourceVideoError::SourceNotFound(source.to_string()))?;
+        f(entry)
+    }
+
+    /// Run timed rotations on a background thread that exits with the
+    /// controller
+    fn ensure_scheduler(self: &Arc<Self>) {
+        if self.scheduler_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let weak: Weak<Self> = Arc::downgrade(self);
+        std::thread::Builder::new()
+            .name("pattern-rotation".to_string())
+            .spawn(move || {
+                while let Some(controller) = weak.upgrade() {
+                    controller.tick();
+                    drop(controller);
+                    std::thread::sleep(TICK);
+                }
+            })
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source "cam" rotating between ball and snow on trigger only
+    fn scheduled() -> Arc<PatternController> {
+        let controller = PatternController::new();
+        controller.add_source("cam", TestPattern::Smpte);
+        let schedule = RotationSchedule::new(vec!["ball".into(), "snow".into()], None);
+        assert_eq!(
+            controller.set_schedule("cam", schedule).unwrap(),
+            TestPattern::Ball
+        );
+        controller
+    }
+
+    #[test]
+    fn test_schedule_rotation() {
+        let controller = scheduled();
+        assert_eq!(controller.advance("cam").unwrap(), TestPattern::Snow);
+        assert_eq!(controller.advance("cam").unwrap(), TestPattern::Ball);
+
+        let status = controller.status("cam").unwrap();
+        assert_eq!((status.pattern.as_str(), status.rotations), ("Ball", 2));
+    }
+
+    #[test]
+    fn test_trigger_only_schedule() {
+        // Trigger-only schedules never rotate on their own
+        assert!(scheduled().tick().is_empty());
+    }
+
+    #[test]
+    fn test_clear_schedule() {
+        let controller = scheduled();
+        controller.clear_schedule("cam").unwrap();
+        assert!(controller.advance("cam").is_err());
+    }
+
+    #[test]
+    fn test_unknown_source() {
+        assert!(
+            PatternController::new()
+                .set_pattern("missing", "ball")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_zero_interval_rejected() {
+        assert!(
+            RotationSchedule::new(vec!["ball".into()], Some(0))
+                .parse_patterns()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Time-shift buffers for mounts
+//!
+//! The media of a mount with DVR enabled tee their encoded video into HLS
+//! segments, keeping a rolling window of the last `window_secs`. The
+//! recording is what clients are served, patterns, markers and faults
+//! included. A [`DvrRecorder`] keeps one media of the mount playing, so the
+//! window fills while no client is connected. The control API serves that
+//! window next to the RTSP mount, so clients that implement time-shifted
+//! viewing can seek back within it or start at an offset behind live.
+
+use crate::error::{Result, SourceVideoError};
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::glib::translate::IntoGlib;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name of the playlist in a recording's directory
+pub const PLAYLIST: &str = "playlist.m3u8";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DvrConfig {
+    /// How far back the buffer reaches, in seconds
+    pub window_secs: u32,
+    /// Length of each HLS segment, in seconds
+    pub segment_secs: u32,
+}
+
+impl Default for DvrConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 300,
+            segment_secs: 2,
+        }
+    }
+}
+
+impl DvrConfig {
+    pub fn minutes(minutes: u32) -> Self {
+        Self {
+            window_secs: minutes.saturating_mul(60),
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.segment_secs == 0 {
+            return Err(SourceVideoError::config("DVR segments must be at least 1s"));
+        }
+        if self.window_secs < self.segment_secs {
+            return Err(SourceVideoError::config(format!(
+                "DVR window of {}s is shorter than one {}s segment",
+                self.window_secs, self.segment_secs
+            )));
+        }
+        Ok(())
+    }
+
+    /// Segments listed in the playlist
+    fn segments(&self) -> u32 {
+        self.window_secs.div_ceil(self.segment_secs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DvrStatus {
+    pub mount: String,
+    pub window_secs: u32,
+    pub segment_secs: u32,
+    /// Segments on disk, including up to two already out of the window
+    pub segments: usize,
+}
+
+/// Launch fragment splitting off the encoded video for recording, placed
+/// after the encoder
+pub(crate) const TEE_ELEMENT: &str = "tee name=dvr ! queue ! ";
+
+/// hlssink2 is in gst-plugins-bad, so may be missing
+pub fn check_available() -> Result<()> {
+    if gst::ElementFactory::find("hlssink2").is_none() {
+        return Err(SourceVideoError::element(
+            "hlssink2 (gst-plugins-bad), needed for DVR",
+        ));
+    }
+    Ok(())
+}
+
+/// Encoder keyframe interval at `fps` giving every segment a keyframe to
+/// start playback from
+pub(crate) fn key_int_max(config: &DvrConfig, fps: i32) -> u32 {
+    (fps.max(1) as u32) * config.segment_secs
+}
+
+/// Launch fragment recording the `dvr` tee into `dir`, added inside the
+/// media's bin
+pub(crate) fn branch(config: &DvrConfig, dir: &Path) -> String {
+    let location = dir.join("segment%05d.ts");
+    format!(
+        "dvr. ! queue ! h264parse ! \
+         hlssink2 name=dvr-sink target-duration={} playlist-length={} max-files={} \
+         location=\"{}\" playlist-location=\"{}\"",
+        config.segment_secs,
+        config.segments(),
+        // Segments just out of the window may still be being fetched
+        config.segments() + 2,
+        location.to_string_lossy().replace('\\', "/"),
+        dir.join(PLAYLIST).to_string_lossy().replace('\\', "/"),
+    )
+}
+
+/// Keeps a media of a mount recording into a rolling HLS window; stops
+/// it and deletes the recording when dropped
+pub struct DvrRecorder {
+    mount: String,
+    config: DvrConfig,
+    dir: PathBuf,
+    media: rtsp_server::RTSPMedia,
+    main_loop: glib::MainLoop,
+}
+
+impl DvrRecorder {
+    /// Start the media of `factory`, built with
+    /// [`MediaFactoryBuilder::dvr`](super::factory::MediaFactoryBuilder::dvr),
+    /// served at `mount` and recording into `dir`. A shared factory hands
+    /// the same media to clients.
+    pub fn start(
+        mount: &str,
+        factory: &rtsp_server::RTSPMediaFactory,
+        config: DvrConfig,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        config.validate()?;
+        check_available()?;
+
+        let url = gstreamer_rtsp::RTSPUrl::parse(&format!("rtsp://localhost{}", mount))
+            .1
+            .ok_or_else(|| SourceVideoError::config(format!("Invalid mount {}", mount)))?;
+        let media = factory
+            .construct(&url)
+            .map_err(|e| SourceVideoError::pipeline(format!("DVR media of {}: {}", mount, e)))?;
+
+        // The media's bus is watched from a thread of its own
+        let thread = rtsp_server::RTSPThread::new(rtsp_server::RTSPThreadType::Media)
+            .ok_or_else(|| SourceVideoError::resource("No thread for DVR media"))?;
+        let main_loop = thread.loop_();
+        std::thread::Builder::new()
+            .name("dvr-media".to_string())
+            .spawn({
+                let main_loop = main_loop.clone();
+                move || main_loop.run()
+            })?;
+        if let Err(e) = media.prepare(Some(thread)) {
+            main_loop.quit();
+            return Err(SourceVideoError::pipeline(format!(
+                "DVR media of {}: {}",
+                mount, e
+            )));
+        }
+
+        // Clients pause the media when the last of them stops; keep it
+        // recording regardless
+        let pipeline = media_pipeline(&media)?;
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|e| SourceVideoError::StateChange(e.to_string()))?;
+        let weak = pipeline.downgrade();
+        media.connect_new_state(move |_, state| {
+            if state == gst::State::Paused.into_glib()
+                && let Some(pipeline) = weak.upgrade()
+            {
+                let _ = pipeline.set_state(gst::State::Playing);
+            }
+        });
+
+        log::info!(
+            "Recording {} into a {}s DVR window at {}",
+            mount,
+            config.window_secs,
+            dir.display()
+        );
+        Ok(Self {
+            mount: mount.to_string(),
+            config,
+            dir,
+            media,
+            main_loop,
+        })
+    }
+
+    pub fn config(&self) -> &DvrConfig {
+        &self.config
+    }
+
+    pub fn playlist_path(&self) -> PathBuf {
+        self.dir.join(PLAYLIST)
+    }
+
+    /// Path of a segment named in the playlist, or `None` for any other
+    /// name so requests cannot reach outside the recording
+    pub fn segment_path(&self, name: &str) -> Option<PathBuf> {
+        let valid = name.starts_with("segment")
+            && name.ends_with(".ts")
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+        valid.then(|| self.dir.join(name))
+    }
+
+    pub fn status(&self) -> DvrStatus {
+        let segments = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_name().to_string_lossy().ends_with(".ts"))
+                    .count()
+            })
+            .unwrap_or(0);
+        DvrStatus {
+            mount: self.mount.clone(),
+            window_secs: self.config.window_secs,
+            segment_secs: self.config.segment_secs,
+            segments,
+        }
+    }
+}
+
+/// Pipeline the media's element runs in
+fn media_pipeline(media: &rtsp_server::RTSPMedia) -> Result<gst::Pipeline> {
+    media
+        .element()
+        .parent()
+        .and_then(|parent| parent.downcast::<gst::Pipeline>().ok())
+        .ok_or_else(|| SourceVideoError::pipeline("DVR media has no pipeline"))
+}
+
+impl Drop for DvrRecorder {
+    fn drop(&mut self) {
+        if let Ok(pipeline) = media_pipeline(&self.media) {
+            let _ = pipeline.set_state(gst::State::Null);
+        }
+        let _ = self.media.unprepare();
+        self.main_loop.quit();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Ask players to start `offset_secs` behind the live edge
+pub fn with_start_offset(playlist: &str, offset_secs: u32) -> String {
+    let tag = format!("#EXT-X-START:TIME-OFFSET=-{}", offset_secs);
+    let mut lines: Vec<&str> = playlist
+        .lines()
+        .filter(|line| !line.starts_with("#EXT-X-START"))
+        .collect();
+    let at = lines
+        .iter()
+        .position(|line| line.starts_with("#EXTM3U"))
+        .map_or(0, |header| header + 1);
+    lines.insert(at, &tag);
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_offset() {
+        let playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXTINF:2.0,\nsegment00001.ts\n";
+        let shifted = with_start_offset(playlist, 30);
+        assert!(shifted.starts_with("#EXTM3U\n#EXT-X-START:TIME-OFFSET=-30\n"));
+    }
+
+    #[test]
+    fn test_start_offset_replaced() {
+        let playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXTINF:2.0,\nsegment00001.ts\n";
+        let shifted = with_start_offset(&with_start_offset(playlist, 30), 10);
+        assert_eq!(shifted.matches("#EXT-X-START").count(), 1);
+        assert!(shifted.contains("TIME-OFFSET=-10\n"));
+    }
+
+    #[test]
+    fn test_segments_for_window() {
+        assert_eq!(DvrConfig::minutes(5).segments(), 150);
+    }
+
+    #[test]
+    fn test_keyframe_every_segment() {
+        assert_eq!(key_int_max(&DvrConfig::default(), 30), 60);
+    }
+
+    #[test]
+    fn test_window_shorter_than_segment_rejected() {
+        assert!(
+            DvrConfig {
+                window_secs: 1,
+                segment_secs: 2
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
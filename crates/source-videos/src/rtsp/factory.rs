@@ -1,32 +1,71 @@
+use crate::avsync::{self, AvSyncSignal};
 use crate::config_types::VideoSourceConfig;
 use crate::error::{Result, SourceVideoError};
-use crate::network::NetworkProfile;
+use crate::markers::{self, MarkerKind};
+use crate::network::{NetworkProfile, StreamImpairment, stream};
 use crate::patterns::TestPattern;
+use crate::rotation::PatternController;
+use crate::rtsp::dvr::{self, DvrConfig};
+use crate::rtsp::playback::PLAYBACK_ELEMENT;
+use crate::rtsp::shaping::{ClientShaper, SHAPER_ELEMENT};
+use crate::rtsp::transport::TransportMode;
+use gstreamer as gst;
 use gstreamer_rtsp_server as rtsp_server;
 use gstreamer_rtsp_server::prelude::*;
+use std::sync::Arc;
 
 pub struct MediaFactoryBuilder {
     launch_string: Option<String>,
+    config: Option<VideoSourceConfig>,
+    bitrate_kbps: u32,
     shared: bool,
     eos_shutdown: bool,
     latency: u32,
-    network_profile: Option<NetworkProfile>,
+    impairment: Option<Arc<StreamImpairment>>,
+    marker: Option<(MarkerKind, String)>,
+    avsync: Option<AvSyncSignal>,
+    patterns: Option<(Arc<PatternController>, String, TestPattern)>,
+    shaper: Option<Arc<ClientShaper>>,
+    dvr: Option<(DvrConfig, std::path::PathBuf)>,
+    transport: TransportMode,
 }
 
+/// Launch fragment marking where frame markers are drawn
+const MARKER_ELEMENT: &str = "identity name=markers ! ";
+
+/// Encoder bitrate when no limit is set
+const DEFAULT_BITRATE_KBPS: u32 = 2000;
+
 impl MediaFactoryBuilder {
     pub fn new() -> Self {
         Self {
             launch_string: None,
+            config: None,
+            bitrate_kbps: DEFAULT_BITRATE_KBPS,
             shared: true,
             eos_shutdown: false,
             latency: 200,
-            network_profile: None,
+            impairment: None,
+            marker: None,
+            avsync: None,
+            patterns: None,
+            shaper: None,
+            dvr: None,
+            transport: TransportMode::Any,
         }
     }
 
+    /// Serve `config`; its launch string is made in [`build`](Self::build),
+    /// so settings such as the network profile apply in any order
     pub fn from_config(mut self, config: &VideoSourceConfig) -> Result<Self> {
-        let launch = self.create_launch_string(config)?;
-        self.launch_string = Some(launch);
+        self.config = Some(config.clone());
+        if let crate::config_types::VideoSourceType::TestPattern { pattern } = &config.source_type {
+            if avsync::is_avsync(pattern) {
+                self.avsync = Some(AvSyncSignal::default());
+            } else if let (_, Some(kind)) = markers::parse_pattern(pattern)? {
+                self.marker = Some((kind, config.name.clone()));
+            }
+        }
         Ok(self)
     }
 
@@ -35,6 +74,12 @@ impl MediaFactoryBuilder {
         self
     }
 
+    /// Cap the encoder bitrate of a source built [`from_config`](Self::from_config)
+    pub fn max_bitrate(mut self, kbps: u32) -> Self {
+        self.bitrate_kbps = self.bitrate_kbps.min(kbps.max(1));
+        self
+    }
+
     pub fn shared(mut self, shared: bool) -> Self {
         self.shared = shared;
         self
@@ -50,21 +95,122 @@ impl MediaFactoryBuilder {
         self
     }
 
-    pub fn network_profile(mut self, profile: NetworkProfile) -> Self {
-        self.network_profile = Some(profile);
+    pub fn network_profile(self, profile: NetworkProfile) -> Self {
+        self.impairment(StreamImpairment::with_profile(profile))
+    }
+
+    /// Impair each media through `impairment`, whose conditions can be
+    /// changed while clients are connected. A launch string given directly
+    /// must contain [`stream::launch_fragment`].
+    pub fn impairment(mut self, impairment: Arc<StreamImpairment>) -> Self {
+        self.impairment = Some(impairment);
+        self
+    }
+
+    /// Draw frame markers in each media's `markers` element; the launch
+    /// string must contain one
+    pub fn marker(mut self, kind: MarkerKind, source_name: impl Into<String>) -> Self {
+        self.marker = Some((kind, source_name.into()));
+        self
+    }
+
+    /// Flash and beep in each media's `flash` and `beep` elements; the
+    /// launch string must come from [`AvSyncSignal::launch_string`]
+    pub fn avsync(mut self, signal: AvSyncSignal) -> Self {
+        self.avsync = Some(signal);
+        self
+    }
+
+    /// Register each media's `source` element with `controller` so the
+    /// pattern can be changed while clients are connected
+    pub fn pattern_controller(
+        mut self,
+        controller: Arc<PatternController>,
+        source_name: impl Into<String>,
+        initial: TestPattern,
+    ) -> Self {
+        self.patterns = Some((controller, source_name.into(), initial));
+        self
+    }
+
+    /// Pace each client of a source built [`from_config`](Self::from_config)
+    /// by `shaper`'s rules. Every client then gets a pipeline of its own.
+    pub fn client_shaping(mut self, shaper: Arc<ClientShaper>) -> Self {
+        self.shaper = Some(shaper);
+        self.shared = false;
+        self
+    }
+
+    /// Record the encoded video of each media of a source built
+    /// [`from_config`](Self::from_config) into a rolling HLS window in
+    /// `dir`, see [`DvrRecorder`](super::dvr::DvrRecorder)
+    pub fn dvr(mut self, config: DvrConfig, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.dvr = Some((config, dir.into()));
+        self
+    }
+
+    /// Restrict the RTP transports clients may set up
+    pub fn transport(mut self, mode: TransportMode) -> Self {
+        self.transport = mode;
         self
     }
 
     pub fn build(self) -> Result<rtsp_server::RTSPMediaFactory> {
-        let launch = self
-            .launch_string
-            .ok_or_else(|| SourceVideoError::config("No launch string provided"))?;
+        let launch = match (&self.launch_string, &self.config) {
+            (Some(launch), _) => launch.clone(),
+            (None, Some(config)) => self.create_launch_string(config)?,
+            (None, None) => return Err(SourceVideoError::config("No launch string provided")),
+        };
 
         let factory = rtsp_server::RTSPMediaFactory::new();
         factory.set_launch(&launch);
         factory.set_shared(self.shared);
         factory.set_eos_shutdown(self.eos_shutdown);
         factory.set_latency(self.latency);
+        factory.set_protocols(self.transport.protocols());
+
+        if self.marker.is_some()
+            || self.avsync.is_some()
+            || self.patterns.is_some()
+            || self.shaper.is_some()
+            || self.impairment.is_some()
+        {
+            let marker = self.marker;
+            let avsync = self.avsync;
+            let patterns = self.patterns;
+            let shaper = self.shaper;
+            let impairment = self.impairment;
+            factory.connect_media_configure(move |_, media| {
+                let element = media.element();
+                let bin = element.downcast_ref::<gst::Bin>();
+                if let Some((kind, source_name)) = &marker {
+                    let pad = bin
+                        .and_then(|bin| bin.by_name("markers"))
+                        .and_then(|markers| markers.static_pad("src"));
+                    match pad {
+                        Some(pad) => {
+                            markers::attach_marker_overlay(&pad, *kind, source_name);
+                        }
+                        None => log::warn!("No markers element in media for {}", source_name),
+                    }
+                }
+                if let (Some(signal), Some(bin)) = (&avsync, bin) {
+                    signal.attach(bin);
+                }
+                if let Some((controller, source_name, initial)) = &patterns {
+                    match bin.and_then(|bin| bin.by_name("source")) {
+                        Some(source) => controller.register(source_name, &source, *initial),
+                        None => log::warn!("No source element in media for {}", source_name),
+                    }
+                }
+                if let Some(shaper) = &shaper {
+                    shaper.attach(media);
+                }
+                if let (Some(impairment), Some(bin)) = (&impairment, bin) {
+                    impairment.attach(bin);
+                }
+            });
+        }
 
         // RTCP is enabled by default in GStreamer RTSP server
         // The enable-rtcp property doesn't exist on RTSPMediaFactory
@@ -74,36 +220,54 @@ impl MediaFactoryBuilder {
     }
 
     fn create_launch_string(&self, config: &VideoSourceConfig) -> Result<String> {
-        // Create network simulation elements if profile is set
-        let network_sim = if let Some(profile) = self.network_profile {
-            let conditions = profile.into_conditions();
-            format!(
-                "queue max-size-buffers=1000 max-size-bytes=0 max-size-time=0 leaky=2 ! \
-                 identity drop-probability={} sync=true ! \
-                 valve drop={} ! ",
-                conditions.packet_loss / 100.0,
-                conditions.connection_dropped
-            )
+        // Each media gets its own impairment elements, set up in build()
+        let network_sim = if self.impairment.is_some() {
+            stream::launch_fragment()
         } else {
             String::new()
         };
+        let network_sim = if self.shaper.is_some() {
+            format!("{}{}", SHAPER_ELEMENT, network_sim)
+        } else {
+            network_sim
+        };
+        // Recorded before shaping and impairment, as the source sends it
+        let (network_sim, encoder_options) = match &self.dvr {
+            Some((dvr, _)) => {
+                let fps = config.framerate.numerator / config.framerate.denominator.max(1);
+                (
+                    format!("{}{}", dvr::TEE_ELEMENT, network_sim),
+                    format!(" key-int-max={}", dvr::key_int_max(dvr, fps)),
+                )
+            }
+            None => (network_sim, String::new()),
+        };
+
+        // Only set for the avsync test pattern
+        if let Some(signal) = &self.avsync {
+            let launch = signal.launch_string(config, self.bitrate_kbps, &network_sim);
+            return Ok(self.with_dvr_branch(launch));
+        }
 
         let launch = match &config.source_type {
             crate::config_types::VideoSourceType::TestPattern { pattern } => {
-                let _pattern = TestPattern::from_str(pattern)?; // Validate pattern
+                let (pattern, marker) = markers::parse_pattern(pattern)?;
                 format!(
-                    "( videotestsrc pattern={} is-live=true ! \
+                    "( videotestsrc name=source pattern={} is-live=true ! \
                      video/x-raw,width={},height={},framerate={}/{},format={} ! \
-                     videoconvert ! \
-                     x264enc tune=zerolatency speed-preset=ultrafast bitrate=2000 ! \
+                     {}videoconvert ! \
+                     x264enc tune=zerolatency speed-preset=ultrafast bitrate={}{} ! \
                      {} \
                      rtph264pay name=pay0 pt=96 config-interval=1 )",
-                    pattern,
+                    pattern.to_gst_pattern(),
                     config.resolution.width,
                     config.resolution.height,
                     config.framerate.numerator,
                     config.framerate.denominator,
                     config.format.to_caps_string(),
+                    if marker.is_some() { MARKER_ELEMENT } else { "" },
+                    self.bitrate_kbps,
+                    encoder_options,
                     network_sim
                 )
             }
@@ -116,10 +280,16 @@ impl MediaFactoryBuilder {
                      videoconvert ! \
                      videoscale ! \
                      video/x-raw,width={},height={} ! \
-                     x264enc tune=zerolatency speed-preset=ultrafast bitrate=2000 ! \
+                     {}x264enc tune=zerolatency speed-preset=ultrafast bitrate={}{} ! \
                      {} \
                      rtph264pay name=pay0 pt=96 config-interval=1 )",
-                    gst_path, config.resolution.width, config.resolution.height, network_sim
+                    gst_path,
+                    config.resolution.width,
+                    config.resolution.height,
+                    PLAYBACK_ELEMENT,
+                    self.bitrate_kbps,
+                    encoder_options,
+                    network_sim
                 )
             }
             crate::config_types::VideoSourceType::Rtsp { .. } => {
@@ -139,26 +309,42 @@ impl MediaFactoryBuilder {
             }
         };
 
-        Ok(launch)
+        Ok(self.with_dvr_branch(launch))
+    }
+
+    /// Add the recording branch inside the media's bin, before its
+    /// closing parenthesis
+    fn with_dvr_branch(&self, launch: String) -> String {
+        let Some((dvr, dir)) = &self.dvr else {
+            return launch;
+        };
+        match launch.trim_end().strip_suffix(')') {
+            Some(body) => format!("{} {} )", body, dvr::branch(dvr, dir)),
+            None => format!("{} {}", launch, dvr::branch(dvr, dir)),
+        }
     }
 }
 
 pub fn create_test_pattern_factory(pattern: &str) -> Result<rtsp_server::RTSPMediaFactory> {
-    let _pattern = TestPattern::from_str(pattern)?; // Validate pattern exists
+    let (test_pattern, marker) = markers::parse_pattern(pattern)?;
 
     let launch = format!(
         "( videotestsrc pattern={} is-live=true ! \
          video/x-raw,width=1920,height=1080,framerate=30/1 ! \
-         videoconvert ! \
+         {}videoconvert ! \
          x264enc tune=zerolatency speed-preset=ultrafast ! \
          rtph264pay name=pay0 pt=96 config-interval=1 )",
-        pattern
+        test_pattern.to_gst_pattern(),
+        if marker.is_some() { MARKER_ELEMENT } else { "" }
     );
 
-    MediaFactoryBuilder::new()
+    let mut builder = MediaFactoryBuilder::new()
         .launch_string(launch)
-        .shared(true)
-        .build()
+        .shared(true);
+    if let Some(kind) = marker {
+        builder = builder.marker(kind, pattern);
+    }
+    builder.build()
 }
 
 pub fn create_file_source_factory(file_path: &str) -> Result<rtsp_server::RTSPMediaFactory> {
@@ -188,32 +374,30 @@ pub fn create_test_pattern_with_network(
     pattern: &str,
     profile: NetworkProfile,
 ) -> Result<rtsp_server::RTSPMediaFactory> {
-    let _pattern = TestPattern::from_str(pattern)?; // Validate pattern exists
-
-    let conditions = profile.into_conditions();
-    let network_sim = format!(
-        "queue max-size-buffers=1000 max-size-bytes=0 max-size-time=0 leaky=2 ! \
-         identity drop-probability={} sync=true ! \
-         valve drop={} ! ",
-        conditions.packet_loss / 100.0,
-        conditions.connection_dropped
-    );
+    let (test_pattern, marker) = markers::parse_pattern(pattern)?;
+
+    let network_sim = stream::launch_fragment();
 
     let launch = format!(
         "( videotestsrc pattern={} is-live=true ! \
          video/x-raw,width=1920,height=1080,framerate=30/1 ! \
-         videoconvert ! \
+         {}videoconvert ! \
          x264enc tune=zerolatency speed-preset=ultrafast ! \
          {} \
          rtph264pay name=pay0 pt=96 config-interval=1 )",
-        pattern, network_sim
+        test_pattern.to_gst_pattern(),
+        if marker.is_some() { MARKER_ELEMENT } else { "" },
+        network_sim
     );
 
-    MediaFactoryBuilder::new()
+    let mut builder = MediaFactoryBuilder::new()
         .launch_string(launch)
         .network_profile(profile)
-        .shared(true)
-        .build()
+        .shared(true);
+    if let Some(kind) = marker {
+        builder = builder.marker(kind, pattern);
+    }
+    builder.build()
 }
 
 #[cfg(test)]
@@ -247,4 +431,20 @@ mod tests {
         let factory = create_test_pattern_factory("invalid");
         assert!(factory.is_err());
     }
+
+    #[test]
+    fn test_dvr_branch_in_media() {
+        let config = VideoSourceConfig::test_pattern("dvr", "smpte");
+        let launch = MediaFactoryBuilder::new()
+            .from_config(&config)
+            .unwrap()
+            .dvr(DvrConfig::default(), "/tmp/dvr")
+            .create_launch_string(&config)
+            .unwrap();
+
+        assert!(launch.contains("key-int-max=60 ! tee name=dvr ! queue !"));
+        assert!(launch.contains("dvr. ! queue ! h264parse ! hlssink2"));
+        assert!(launch.trim_end().ends_with(')'));
+        assert_eq!(launch.matches(')').count(), 1);
+    }
 }
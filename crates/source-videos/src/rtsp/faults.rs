@@ -0,0 +1,96 @@
+//! Faults injected into running mounts
+//!
+//! Every media a mount builds gets a probe on its payloaders that discards
+//! buffers while the mount is marked dropped, so clients see the stream
+//! stall as on a dead link while their sessions stay open. EOS injection
+//! goes to the media of a mount's current sessions.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Mounts whose media is being discarded
+#[derive(Debug, Default)]
+pub struct MountFaults {
+    dropped: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl MountFaults {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn set_dropped(&self, mount_point: &str, dropped: bool) {
+        self.switch(mount_point).store(dropped, Ordering::Relaxed);
+    }
+
+    pub fn is_dropped(&self, mount_point: &str) -> bool {
+        self.switch(mount_point).load(Ordering::Relaxed)
+    }
+
+    fn switch(&self, mount_point: &str) -> Arc<AtomicBool> {
+        self.dropped
+            .lock()
+            .unwrap()
+            .entry(mount_point.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Let the mount's media be dropped through [`set_dropped`](Self::set_dropped)
+    pub(crate) fn install(&self, factory: &rtsp_server::RTSPMediaFactory, mount_point: &str) {
+        let dropped = self.switch(mount_point);
+        factory.connect_media_configure(move |_, media| {
+            let Ok(bin) = media.element().downcast::<gst::Bin>() else {
+                return;
+            };
+            for payloader in bin.iterate_elements().into_iter().flatten() {
+                if !payloader.name().starts_with("pay") {
+                    continue;
+                }
+                let Some(pad) = payloader.static_pad("src") else {
+                    continue;
+                };
+                let dropped = dropped.clone();
+                pad.add_probe(
+                    gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                    move |_, _| {
+                        if dropped.load(Ordering::Relaxed) {
+                            gst::PadProbeReturn::Drop
+                        } else {
+                            gst::PadProbeReturn::Ok
+                        }
+                    },
+                );
+            }
+        });
+    }
+}
+
+/// End every running media of `mount_point` with an EOS; returns how many
+/// got one
+pub(crate) fn send_eos(server: &rtsp_server::RTSPServer, mount_point: &str) -> usize {
+    let Some(pool) = server.session_pool() else {
+        return 0;
+    };
+    let mut ended: Vec<rtsp_server::RTSPMedia> = Vec::new();
+    for session in pool.filter(None) {
+        for session_media in session.filter(None) {
+            if session_media.matches(mount_point) != Some(mount_point.len() as i32) {
+                continue;
+            }
+            let media = session_media.media();
+            // Shared media is in several sessions but ends once
+            if ended.contains(&media) {
+                continue;
+            }
+            media.element().send_event(gst::event::Eos::new());
+            ended.push(media);
+        }
+    }
+    ended.len()
+}
@@ -1,24 +1,65 @@
+pub mod dvr;
 pub mod factory;
-
-use crate::config::{RtspServerConfig, VideoSourceConfig};
+pub mod faults;
+pub mod namespace;
+pub mod playback;
+pub mod shaping;
+pub mod sync;
+pub mod traffic;
+pub mod transport;
+
+use crate::config::{NamespaceConfig, RtspServerConfig, VideoSourceConfig};
 use crate::error::{Result, SourceVideoError};
-use crate::network::{NetworkConditions, NetworkProfile};
+use crate::network::{NetworkConditions, NetworkController, NetworkProfile, StreamImpairment};
+use crate::rotation::PatternController;
 use crate::watch::FileSystemEvent;
+use dvr::{DvrConfig, DvrRecorder};
 use factory::MediaFactoryBuilder;
+use faults::MountFaults;
 use gstreamer_rtsp_server as rtsp_server;
 use gstreamer_rtsp_server::prelude::*;
+use playback::{MountPlayback, PlaybackStatus};
+use shaping::{ClientShaper, ShapingRule};
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 use std::sync::{Arc, Mutex};
+use sync::MountSync;
+use traffic::TrafficLog;
+use transport::{TransportLog, TransportMode};
 
 pub struct RtspServer {
     server: rtsp_server::RTSPServer,
     mounts: rtsp_server::RTSPMountPoints,
     sources: Arc<Mutex<HashMap<String, VideoSourceConfig>>>,
-    port: u16,
+    factories: HashMap<String, rtsp_server::RTSPMediaFactory>,
+    port: AtomicU16,
+    port_retries: u16,
     address: String,
     global_network_profile: Option<NetworkProfile>,
     per_source_network: HashMap<String, NetworkProfile>,
+    /// Conditions of the global [`NetworkProfile::Custom`] profile
+    custom_network_conditions: Option<NetworkConditions>,
+    /// Network impairment of each mount's media
+    impairments: HashMap<String, Arc<StreamImpairment>>,
+    namespaces: HashMap<String, NamespaceConfig>,
+    /// Installed once the first namespace with credentials is added
+    auth: Option<rtsp_server::RTSPAuth>,
+    client_shaper: Option<Arc<ClientShaper>>,
+    transport: TransportMode,
+    mount_transports: HashMap<String, TransportMode>,
+    transports: Arc<TransportLog>,
+    traffic: Arc<TrafficLog>,
+    faults: Arc<MountFaults>,
+    playback: Arc<MountPlayback>,
+    sync: Arc<MountSync>,
+    dvr_configs: HashMap<String, DvrConfig>,
+    dvr_dir: PathBuf,
+    recorders: HashMap<String, Arc<DvrRecorder>>,
+    attached: AtomicBool,
+    source_id: Mutex<Option<(gstreamer::glib::MainContext, gstreamer::glib::SourceId)>>,
+    patterns: Arc<PatternController>,
 }
 
 impl RtspServer {
@@ -39,70 +80,602 @@ impl RtspServer {
             .mount_points()
             .ok_or_else(|| SourceVideoError::server("Failed to get mount points"))?;
 
-        Ok(Self {
+        let sources = Arc::new(Mutex::new(HashMap::new()));
+        let transports = TransportLog::new();
+        transport::track(&server, transports.clone(), sources.clone());
+
+        let mut rtsp = Self {
             server,
             mounts,
-            sources: Arc::new(Mutex::new(HashMap::new())),
-            port: config.port,
+            sources,
+            factories: HashMap::new(),
+            port: AtomicU16::new(config.port),
+            port_retries: config.port_retries,
             address: config.address,
             global_network_profile: None,
             per_source_network: HashMap::new(),
-        })
+            custom_network_conditions: None,
+            impairments: HashMap::new(),
+            namespaces: HashMap::new(),
+            auth: None,
+            client_shaper: None,
+            transport: config.transport,
+            mount_transports: config
+                .mount_transports
+                .into_iter()
+                .map(|(mount, mode)| (normalize_mount(&mount), mode))
+                .collect(),
+            transports,
+            traffic: TrafficLog::new(),
+            faults: MountFaults::new(),
+            playback: MountPlayback::new(),
+            sync: MountSync::new(),
+            dvr_configs: config
+                .dvr
+                .into_iter()
+                .map(|(mount, dvr)| (normalize_mount(&mount), dvr))
+                .collect(),
+            dvr_dir: config.dvr_dir.unwrap_or_else(|| {
+                std::env::temp_dir().join(format!("source-videos-dvr-{}", std::process::id()))
+            }),
+            recorders: HashMap::new(),
+            attached: AtomicBool::new(false),
+            source_id: Mutex::new(None),
+            patterns: PatternController::new(),
+        };
+        for namespace in config.namespaces {
+            rtsp.add_namespace(namespace)?;
+        }
+        Ok(rtsp)
     }
 
     pub fn add_source(&mut self, config: VideoSourceConfig) -> Result<String> {
-        let mount_point =
-            if let crate::config::VideoSourceType::Rtsp { mount_point, .. } = &config.source_type {
-                format!("/{}", mount_point)
-            } else {
-                format!("/{}", config.name)
-            };
+        let mount_point = default_mount(&config);
+        self.mount_source(mount_point, config)
+    }
+
+    /// Mount `config` inside `namespace`, at `/{namespace}/{name}`. The
+    /// source is renamed `{namespace}/{name}` so tenants can reuse names.
+    pub fn add_source_to(
+        &mut self,
+        namespace: &str,
+        mut config: VideoSourceConfig,
+    ) -> Result<String> {
+        if !self.namespaces.contains_key(namespace) {
+            return Err(SourceVideoError::config(format!(
+                "Unknown namespace '{}'",
+                namespace
+            )));
+        }
+        let mount_point = namespace::mount_point(namespace, &default_mount(&config));
+        config.name = format!("{}/{}", namespace, config.name);
+        self.mount_source(mount_point, config)
+    }
+
+    fn mount_source(&mut self, mount_point: String, config: VideoSourceConfig) -> Result<String> {
+        let namespace = self.namespace_of(&mount_point).cloned();
+        if let Some(namespace) = &namespace {
+            self.check_capacity(namespace, &mount_point)?;
+        }
 
         // Build factory with network profile if configured
         let mut factory_builder = MediaFactoryBuilder::new().from_config(&config)?;
+        if let Some(kbps) = namespace.as_ref().and_then(|n| n.bandwidth_kbps) {
+            factory_builder = factory_builder.max_bitrate(kbps);
+        }
+        if let Some(shaper) = &self.client_shaper {
+            factory_builder = factory_builder.client_shaping(shaper.clone());
+        }
+        factory_builder = factory_builder.transport(self.transport_for(&mount_point));
+
+        // Every mount is impairable, so its conditions can change live
+        let impairment = StreamImpairment::new(self.network_conditions_for(&config.name));
+        factory_builder = factory_builder.impairment(impairment.clone());
+
+        let dvr = self.dvr_configs.get(&mount_point).cloned().filter(|_| {
+            dvr::check_available()
+                .inspect_err(|e| log::warn!("No DVR buffer for {}: {}", mount_point, e))
+                .is_ok()
+        });
+        if let Some(dvr) = &dvr {
+            factory_builder = factory_builder.dvr(dvr.clone(), self.dvr_dir_of(&mount_point));
+        }
 
-        // Apply per-source network profile if exists, otherwise use global
-        if let Some(profile) = self.per_source_network.get(&config.name) {
-            factory_builder = factory_builder.network_profile(*profile);
-        } else if let Some(profile) = self.global_network_profile {
-            factory_builder = factory_builder.network_profile(profile);
+        if let crate::config::VideoSourceType::TestPattern { pattern } = &config.source_type
+            && !crate::avsync::is_avsync(pattern)
+        {
+            let (initial, _) = crate::markers::parse_pattern(pattern)?;
+            self.patterns.add_source(&config.name, initial);
+            factory_builder =
+                factory_builder.pattern_controller(self.patterns.clone(), &config.name, initial);
         }
 
         let factory = factory_builder.build()?;
+        namespace::grant(&factory, &namespace::factory_role(namespace.as_ref()));
+        self.traffic.count(&factory, &mount_point);
+        self.faults.install(&factory, &mount_point);
+        self.sync.install(&factory, &mount_point);
+        if let crate::config::VideoSourceType::File { .. } = &config.source_type {
+            self.playback.install(&factory, &mount_point);
+        }
 
-        self.mounts.add_factory(&mount_point, factory);
+        self.mounts.add_factory(&mount_point, factory.clone());
+        self.factories.insert(mount_point.clone(), factory);
+        self.impairments.insert(mount_point.clone(), impairment);
+
+        if let Some(dvr) = dvr
+            && let Err(e) = self.start_recorder(&mount_point, dvr)
+        {
+            log::warn!("No DVR buffer for {}: {}", mount_point, e);
+        }
 
         if let Ok(mut sources) = self.sources.lock() {
             sources.insert(mount_point.clone(), config);
         }
 
-        log::info!(
-            "Added RTSP source at: rtsp://{}:{}{}",
-            self.address,
-            self.port,
-            mount_point
-        );
+        log::info!("Added RTSP source at: {}", self.get_url(&mount_point));
 
         Ok(mount_point)
     }
 
+    /// Mount `config` at an explicit mount point, e.g. to put back a
+    /// source that was removed from it
+    pub fn add_source_at(
+        &mut self,
+        mount_point: &str,
+        config: VideoSourceConfig,
+    ) -> Result<String> {
+        self.mount_source(normalize_mount(mount_point), config)
+    }
+
+    /// Serve the source at `mount_point` under `alias` too. Both mounts use
+    /// the same factory, so a shared factory streams one pipeline to
+    /// clients of either.
+    /// An alias must stay in the namespace of its source, since the
+    /// factory carries that namespace's permissions.
+    pub fn add_alias(&mut self, alias: &str, mount_point: &str) -> Result<String> {
+        let target = normalize_mount(mount_point);
+        let alias = normalize_mount(alias);
+        let factory =
+            self.factories.get(&target).cloned().ok_or_else(|| {
+                SourceVideoError::server(format!("No source mounted at {}", target))
+            })?;
+
+        let namespace = self.namespace_of(&alias).cloned();
+        if namespace.as_ref().map(|n| &n.name) != self.namespace_of(&target).map(|n| &n.name) {
+            return Err(SourceVideoError::config(format!(
+                "Alias {} must be in the same namespace as {}",
+                alias, target
+            )));
+        }
+        if let Some(namespace) = &namespace {
+            self.check_capacity(namespace, &alias)?;
+        }
+
+        self.mounts.add_factory(&alias, factory.clone());
+        self.factories.insert(alias.clone(), factory);
+        log::info!("Added RTSP alias {} for {}", self.get_url(&alias), target);
+        Ok(alias)
+    }
+
     pub fn remove_source(&mut self, mount_point: &str) -> Result<()> {
-        let path = if mount_point.starts_with('/') {
-            mount_point.to_string()
-        } else {
-            format!("/{}", mount_point)
-        };
+        let path = normalize_mount(mount_point);
 
         self.mounts.remove_factory(&path);
-
-        if let Ok(mut sources) = self.sources.lock() {
-            sources.remove(&path);
+        self.factories.remove(&path);
+        self.impairments.remove(&path);
+        self.recorders.remove(&path);
+        self.playback.forget(&path);
+        self.sync.clear(&path);
+
+        if let Ok(mut sources) = self.sources.lock()
+            && let Some(config) = sources.remove(&path)
+        {
+            self.patterns.unregister(&config.name);
         }
 
         log::info!("Removed RTSP source: {}", path);
         Ok(())
     }
 
+    /// Register a tenant namespace and mount its configured sources
+    pub fn add_namespace(&mut self, mut config: NamespaceConfig) -> Result<()> {
+        namespace::validate_name(&config.name)?;
+        let name = config.name.clone();
+        let sources = std::mem::take(&mut config.sources);
+        let config = match self.namespaces.entry(name.clone()) {
+            Entry::Occupied(_) => {
+                return Err(SourceVideoError::config(format!(
+                    "Namespace '{}' already exists",
+                    name
+                )));
+            }
+            Entry::Vacant(entry) => entry.insert(config),
+        };
+
+        if config.authentication.is_some() {
+            let auth = self.auth.get_or_insert_with(|| {
+                let auth = namespace::new_auth();
+                self.server.set_auth(Some(&auth));
+                auth
+            });
+            namespace::add_user(auth, config);
+        }
+
+        log::info!("Added RTSP namespace: /{}/", name);
+        for source in sources {
+            self.add_source_to(&name, source)?;
+        }
+        Ok(())
+    }
+
+    /// Unmount everything in the namespace and drop its credentials
+    pub fn remove_namespace(&mut self, name: &str) -> Result<()> {
+        let config = self
+            .namespaces
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SourceVideoError::config(format!("Unknown namespace '{}'", name)))?;
+
+        for mount_point in self.namespace_mounts(name) {
+            self.remove_source(&mount_point)?;
+        }
+        if let Some(auth) = &self.auth {
+            namespace::remove_user(auth, &config);
+        }
+        self.namespaces.remove(name);
+        log::info!("Removed RTSP namespace: /{}/", name);
+        Ok(())
+    }
+
+    /// Transports accepted at `mount_point`; mounts added afterwards use it
+    /// too, while an existing mount keeps its factory's until re-added
+    pub fn set_mount_transport(&mut self, mount_point: &str, mode: TransportMode) {
+        self.mount_transports
+            .insert(normalize_mount(mount_point), mode);
+    }
+
+    pub fn transport_for(&self, mount_point: &str) -> TransportMode {
+        self.mount_transports
+            .get(&normalize_mount(mount_point))
+            .copied()
+            .unwrap_or(self.transport)
+    }
+
+    /// Transports negotiated by the server's sessions
+    pub fn transport_log(&self) -> Arc<TransportLog> {
+        self.transports.clone()
+    }
+
+    /// Configuration of the source served at `mount_point`
+    pub fn source_config(&self, mount_point: &str) -> Option<VideoSourceConfig> {
+        self.sources
+            .lock()
+            .ok()
+            .and_then(|sources| sources.get(&normalize_mount(mount_point)).cloned())
+    }
+
+    /// Discard the media of `mount_point` until undone, as if its link went
+    /// down; connected clients keep their sessions
+    pub fn set_mount_dropped(&self, mount_point: &str, dropped: bool) {
+        self.faults
+            .set_dropped(&normalize_mount(mount_point), dropped);
+    }
+
+    /// Run new media of the mounts tagged `tag` on `clock` with
+    /// `base_time`, ending their sessions so clients reconnect to media on
+    /// the shared base time; returns the mounts
+    pub fn sync_group(
+        &self,
+        tag: &str,
+        clock: &gstreamer::Clock,
+        base_time: gstreamer::ClockTime,
+    ) -> Vec<String> {
+        let mut mounts: Vec<String> = self
+            .sources
+            .lock()
+            .map(|sources| {
+                sources
+                    .iter()
+                    .filter(|(_, config)| config.has_tag(tag))
+                    .map(|(mount, _)| mount.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        mounts.sort();
+
+        for mount in &mounts {
+            self.sync.set(mount, clock, base_time);
+            let ended = sync::end_sessions(&self.server, mount);
+            log::info!(
+                "Synced {} to base time {}, ended {} sessions",
+                mount,
+                base_time,
+                ended
+            );
+        }
+        mounts
+    }
+
+    /// End the running media of `mount_point` with an EOS; returns how
+    /// many media got one
+    pub fn inject_eos(&self, mount_point: &str) -> usize {
+        faults::send_eos(&self.server, &normalize_mount(mount_point))
+    }
+
+    /// Give one mount its own network profile, or go back to the global one
+    /// with `None`. The running media of the mount are changed in place, so
+    /// connected clients keep their sessions; other mounts are unaffected.
+    pub fn set_mount_network(
+        &mut self,
+        mount_point: &str,
+        profile: Option<NetworkProfile>,
+    ) -> Result<()> {
+        let path = normalize_mount(mount_point);
+        let config = self.source_config(&path).ok_or_else(|| {
+            SourceVideoError::SourceNotFound(format!("No source mounted at {}", path))
+        })?;
+        match profile {
+            Some(profile) => self.per_source_network.insert(config.name.clone(), profile),
+            None => self.per_source_network.remove(&config.name),
+        };
+        if let Some(impairment) = self.impairments.get(&path) {
+            impairment.apply_conditions(self.network_conditions_for(&config.name));
+        }
+        Ok(())
+    }
+
+    /// Change the network conditions of `mount_point`'s running media in
+    /// place, e.g. to conditions no profile describes
+    pub fn set_mount_conditions(
+        &self,
+        mount_point: &str,
+        conditions: NetworkConditions,
+    ) -> Result<()> {
+        let path = normalize_mount(mount_point);
+        let impairment = self.impairments.get(&path).ok_or_else(|| {
+            SourceVideoError::SourceNotFound(format!("No source mounted at {}", path))
+        })?;
+        impairment.apply_conditions(conditions);
+        Ok(())
+    }
+
+    /// Current network conditions of each mount
+    pub fn mount_conditions(&self) -> HashMap<String, NetworkConditions> {
+        self.impairments
+            .iter()
+            .map(|(mount, impairment)| (mount.clone(), impairment.get_conditions()))
+            .collect()
+    }
+
+    /// Mount point of `selector`, given as a mount point or a source name
+    pub fn resolve_mount(&self, selector: &str) -> Option<String> {
+        let sources = self.sources.lock().ok()?;
+        let path = normalize_mount(selector);
+        if sources.contains_key(&path) {
+            return Some(path);
+        }
+        sources
+            .iter()
+            .find(|(_, config)| config.name == selector)
+            .map(|(mount, _)| mount.clone())
+    }
+
+    /// Network impairment of the media at `mount_point`
+    pub fn mount_impairment(&self, mount_point: &str) -> Option<Arc<StreamImpairment>> {
+        self.impairments.get(&normalize_mount(mount_point)).cloned()
+    }
+
+    /// Conditions a source starts with: its own profile, else the global one
+    fn network_conditions_for(&self, source_name: &str) -> NetworkConditions {
+        match self
+            .per_source_network
+            .get(source_name)
+            .or(self.global_network_profile.as_ref())
+        {
+            Some(NetworkProfile::Custom) => {
+                self.custom_network_conditions.clone().unwrap_or_default()
+            }
+            Some(profile) => profile.into_conditions(),
+            None => NetworkConditions::perfect(),
+        }
+    }
+
+    /// Hold the file mount at `mount_point` on its current frame; clients
+    /// keep their sessions
+    pub fn pause_mount(&self, mount_point: &str) -> Result<PlaybackStatus> {
+        let path = self.file_mount(mount_point)?;
+        self.playback.pause(&path);
+        Ok(self.playback.status(&path))
+    }
+
+    pub fn resume_mount(&self, mount_point: &str) -> Result<PlaybackStatus> {
+        let path = self.file_mount(mount_point)?;
+        self.playback.resume(&path);
+        Ok(self.playback.status(&path))
+    }
+
+    /// Send `frames` more frames of a file mount, pausing it first if it
+    /// is playing
+    pub fn step_mount(&self, mount_point: &str, frames: u64) -> Result<PlaybackStatus> {
+        let path = self.file_mount(mount_point)?;
+        self.playback.step(&path, frames);
+        Ok(self.playback.status(&path))
+    }
+
+    /// Playback state of every file mount
+    pub fn playback_status(&self) -> Vec<PlaybackStatus> {
+        let mut mounts: Vec<String> = self
+            .sources
+            .lock()
+            .map(|sources| {
+                sources
+                    .iter()
+                    .filter(|(_, config)| {
+                        matches!(
+                            config.source_type,
+                            crate::config::VideoSourceType::File { .. }
+                        )
+                    })
+                    .map(|(mount, _)| mount.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        mounts.sort();
+        mounts
+            .iter()
+            .map(|mount| self.playback.status(mount))
+            .collect()
+    }
+
+    /// Path of `mount_point`, if it serves a file
+    fn file_mount(&self, mount_point: &str) -> Result<String> {
+        let path = normalize_mount(mount_point);
+        match self.source_config(&path).map(|config| config.source_type) {
+            Some(crate::config::VideoSourceType::File { .. }) => Ok(path),
+            Some(_) => Err(SourceVideoError::config(format!(
+                "{} does not serve a file; only file mounts can be paused",
+                path
+            ))),
+            None => Err(SourceVideoError::SourceNotFound(format!(
+                "No source mounted at {}",
+                path
+            ))),
+        }
+    }
+
+    /// Keep a rolling time-shift buffer of `mount_point`, replacing any
+    /// buffer it had; the mount keeps it when it is re-created. The mount
+    /// is re-created to record its media, ending its sessions.
+    pub fn enable_dvr(&mut self, mount_point: &str, config: DvrConfig) -> Result<()> {
+        let path = normalize_mount(mount_point);
+        config.validate()?;
+        dvr::check_available()?;
+        let previous = self.dvr_configs.insert(path.clone(), config);
+        if let Err(e) = self.remount(&path) {
+            match previous {
+                Some(previous) => self.dvr_configs.insert(path, previous),
+                None => self.dvr_configs.remove(&path),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stop buffering `mount_point`; returns whether it had a buffer
+    pub fn disable_dvr(&mut self, mount_point: &str) -> bool {
+        let path = normalize_mount(mount_point);
+        if self.dvr_configs.remove(&path).is_none() {
+            return false;
+        }
+        if let Err(e) = self.remount(&path) {
+            log::warn!("Cannot re-create {} without DVR: {}", path, e);
+        }
+        true
+    }
+
+    /// Time-shift buffer of `mount_point`
+    pub fn dvr(&self, mount_point: &str) -> Option<Arc<DvrRecorder>> {
+        self.recorders.get(&normalize_mount(mount_point)).cloned()
+    }
+
+    pub fn dvr_status(&self) -> Vec<dvr::DvrStatus> {
+        let mut status: Vec<_> = self.recorders.values().map(|r| r.status()).collect();
+        status.sort_by(|a, b| a.mount.cmp(&b.mount));
+        status
+    }
+
+    fn dvr_dir_of(&self, mount_point: &str) -> PathBuf {
+        self.dvr_dir
+            .join(mount_point.trim_start_matches('/').replace('/', "_"))
+    }
+
+    fn start_recorder(&mut self, mount_point: &str, config: DvrConfig) -> Result<()> {
+        let factory = self.factories.get(mount_point).cloned().ok_or_else(|| {
+            SourceVideoError::server(format!("No source mounted at {}", mount_point))
+        })?;
+        let dir = self.dvr_dir_of(mount_point);
+        let recorder = DvrRecorder::start(mount_point, &factory, config, dir)?;
+        self.recorders
+            .insert(mount_point.to_string(), Arc::new(recorder));
+        Ok(())
+    }
+
+    /// Mount the source at `mount_point` again with a new factory, moving
+    /// its aliases over
+    fn remount(&mut self, mount_point: &str) -> Result<()> {
+        let source = self.source_config(mount_point).ok_or_else(|| {
+            SourceVideoError::SourceNotFound(format!("No source mounted at {}", mount_point))
+        })?;
+        let old = self.factories.get(mount_point).cloned();
+        let aliases: Vec<String> = self
+            .factories
+            .iter()
+            .filter(|(path, factory)| {
+                path.as_str() != mount_point && Some(*factory) == old.as_ref()
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        self.remove_source(mount_point)?;
+        self.mount_source(mount_point.to_string(), source)?;
+        sync::end_sessions(&self.server, mount_point);
+        for alias in aliases {
+            self.mounts.remove_factory(&alias);
+            self.factories.remove(&alias);
+            self.add_alias(&alias, mount_point)?;
+        }
+        Ok(())
+    }
+
+    /// Bytes streamed per mount
+    pub fn traffic_log(&self) -> Arc<TrafficLog> {
+        self.traffic.clone()
+    }
+
+    /// Per-client shaping rules, when the server was built with them.
+    /// Rules can be changed while clients are connected.
+    pub fn client_shaper(&self) -> Option<Arc<ClientShaper>> {
+        self.client_shaper.clone()
+    }
+
+    pub fn list_namespaces(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.namespaces.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Mount points in namespace `name`, aliases included
+    pub fn namespace_mounts(&self, name: &str) -> Vec<String> {
+        let mut mounts: Vec<_> = self
+            .factories
+            .keys()
+            .filter(|mount| namespace::namespace_of(mount) == Some(name))
+            .cloned()
+            .collect();
+        mounts.sort();
+        mounts
+    }
+
+    fn namespace_of(&self, mount_point: &str) -> Option<&NamespaceConfig> {
+        namespace::namespace_of(mount_point).and_then(|name| self.namespaces.get(name))
+    }
+
+    fn check_capacity(&self, namespace: &NamespaceConfig, mount_point: &str) -> Result<()> {
+        let Some(max) = namespace.max_mounts else {
+            return Ok(());
+        };
+        if !self.factories.contains_key(mount_point)
+            && self.namespace_mounts(&namespace.name).len() >= max
+        {
+            return Err(SourceVideoError::resource(format!(
+                "Namespace '{}' is full ({} mounts)",
+                namespace.name, max
+            )));
+        }
+        Ok(())
+    }
+
     pub fn list_sources(&self) -> Vec<String> {
         self.sources
             .lock()
@@ -110,10 +683,132 @@ impl RtspServer {
             .unwrap_or_default()
     }
 
+    /// Live pattern control for the test sources served here
+    pub fn pattern_controller(&self) -> Arc<PatternController> {
+        self.patterns.clone()
+    }
+
     pub fn start(&self) -> Result<()> {
-        let _source_id = self.server.attach(None);
+        self.start_with_context(None)
+    }
+
+    /// Attach to the given main context, or the default one when `None`.
+    ///
+    /// Whoever owns the context must iterate it for clients to be served.
+    /// When the configured port is taken, the next `port_retries` ports are
+    /// tried; with port `0` the OS picks one. [`get_port`](Self::get_port)
+    /// reports the port actually bound once this returns.
+    pub fn start_with_context(&self, context: Option<&gstreamer::glib::MainContext>) -> Result<()> {
+        let requested = self.port.load(Ordering::SeqCst);
+        let mut last_error = None;
+        let mut in_use = 0;
+
+        for port in crate::ports::candidates(requested, self.port_retries) {
+            if crate::ports::is_in_use(&self.address, port) {
+                log::debug!("RTSP port {} already in use on {}", port, self.address);
+                in_use += 1;
+                continue;
+            }
+
+            self.server.set_service(&port.to_string());
+            match self.server.attach(context) {
+                Ok(source_id) => {
+                    let bound = match self.server.bound_port() {
+                        bound if bound > 0 => bound as u16,
+                        _ => port,
+                    };
+                    self.port.store(bound, Ordering::SeqCst);
+
+                    let context = context
+                        .cloned()
+                        .unwrap_or_else(gstreamer::glib::MainContext::default);
+                    if let Ok(mut id) = self.source_id.lock() {
+                        *id = Some((context, source_id));
+                    }
+                    self.attached.store(true, Ordering::SeqCst);
+
+                    if bound != requested {
+                        log::info!(
+                            "RTSP server bound to port {} (requested {})",
+                            bound,
+                            requested
+                        );
+                    }
+                    log::info!("RTSP server started on {}:{}", self.address, bound);
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::debug!("Failed to attach RTSP server on port {}: {}", port, e);
+                    last_error = Some(e.to_string());
+                }
+            }
+        }
 
-        log::info!("RTSP server started on {}:{}", self.address, self.port);
+        let range = crate::ports::describe_range(requested, self.port_retries);
+        let reason = match last_error {
+            Some(e) => e,
+            None if in_use > 0 => "address already in use".to_string(),
+            None => "no port available".to_string(),
+        };
+        Err(SourceVideoError::server(format!(
+            "Failed to attach RTSP server on {}:{}: {}",
+            self.address, range, reason
+        )))
+    }
+
+    /// Stop accepting clients and disconnect existing sessions.
+    ///
+    /// Removing the clients tears down their media; factories built with
+    /// `eos_shutdown` send EOS through the pipeline before it is stopped.
+    pub fn stop(&self) -> Result<()> {
+        let source_id = self
+            .source_id
+            .lock()
+            .map_err(|_| SourceVideoError::server("RTSP server state lock poisoned"))?
+            .take();
+
+        if let Some((context, source_id)) = source_id {
+            if let Some(source) = context.find_source_by_id(&source_id) {
+                source.destroy();
+            }
+        }
+
+        self.server
+            .client_filter(Some(&mut |_, _| rtsp_server::RTSPFilterResult::Remove));
+        self.attached.store(false, Ordering::SeqCst);
+
+        log::info!(
+            "RTSP server stopped on {}:{}",
+            self.address,
+            self.get_port()
+        );
+        Ok(())
+    }
+
+    /// Move the server to a new address and port, keeping its mounts.
+    ///
+    /// Connected clients are disconnected; a server that was attached is
+    /// attached again to the same main context.
+    pub fn rebind(&mut self, address: &str, port: u16) -> Result<()> {
+        let context = self
+            .source_id
+            .lock()
+            .map_err(|_| SourceVideoError::server("RTSP server state lock poisoned"))?
+            .as_ref()
+            .map(|(context, _)| context.clone());
+        let was_attached = self.is_attached();
+        if was_attached {
+            self.stop()?;
+        }
+
+        self.address = address.to_string();
+        self.server.set_address(address);
+        self.server.set_service(&port.to_string());
+        self.port.store(port, Ordering::SeqCst);
+
+        if was_attached {
+            self.start_with_context(context.as_ref())?;
+        }
         Ok(())
     }
 
@@ -128,26 +823,62 @@ impl RtspServer {
             "0.0.0.0" => "localhost",
             _ => &self.address,
         };
-        format!("rtsp://{}:{}{}", addr, self.port, path)
+        format!("rtsp://{}:{}{}", addr, self.get_port(), path)
+    }
+
+    /// Whether the server has been attached to a main context and is accepting clients.
+    pub fn is_attached(&self) -> bool {
+        self.attached.load(Ordering::SeqCst)
     }
 
+    /// Port the server listens on; the bound port once started.
     pub fn get_port(&self) -> u16 {
-        self.port
+        self.port.load(Ordering::SeqCst)
     }
 
     pub fn get_address(&self) -> &str {
         &self.address
     }
 
-    // File watching integration methods for RTSP server
+    /// Update the source at a mount point. A change of test pattern alone is
+    /// applied to connected clients' media; anything else rebuilds the
+    /// mount's factory from the new configuration and ends its sessions, so
+    /// no client keeps streaming the old pipeline.
     pub fn update_source(&mut self, mount_point: &str, config: VideoSourceConfig) -> Result<()> {
-        // Remove existing source if it exists
-        let _ = self.remove_source(mount_point);
+        let path = normalize_mount(mount_point);
+        let existing = self
+            .sources
+            .lock()
+            .ok()
+            .and_then(|sources| sources.get(&path).cloned());
+        if let Some(old) = existing
+            && let crate::config::VideoSourceType::TestPattern { pattern } = &config.source_type
+            && crate::source::SourceUpdate::between(&old, &config)
+                == crate::source::SourceUpdate::Live
+            && (&old.resolution, &old.framerate, &old.format)
+                == (&config.resolution, &config.framerate, &config.format)
+        {
+            let (base, _) = crate::markers::parse_pattern(pattern)?;
+            self.patterns.switch(&config.name, base)?;
+            if let Ok(mut sources) = self.sources.lock() {
+                sources.insert(path.clone(), config);
+            }
+            log::info!("Changed pattern of RTSP source at {} to {}", path, base);
+            return Ok(());
+        }
 
-        // Add the updated source
-        self.add_source(config)?;
+        if !self.factories.contains_key(&path) {
+            self.mount_source(path.clone(), config)?;
+        } else {
+            if let Ok(mut sources) = self.sources.lock()
+                && let Some(old) = sources.insert(path.clone(), config)
+            {
+                self.patterns.unregister(&old.name);
+            }
+            self.remount(&path)?;
+        }
 
-        log::info!("Updated RTSP source at mount point: {}", mount_point);
+        log::info!("Updated RTSP source at mount point: {}", path);
         Ok(())
     }
 
@@ -186,6 +917,7 @@ impl RtspServer {
                         duration: None,
                         num_buffers: None,
                         is_live: false,
+                        tags: Vec::new(),
                     };
 
                     self.add_source(config)?;
@@ -239,12 +971,29 @@ impl RtspServer {
     }
 }
 
+/// Where a source is mounted unless a namespace says otherwise
+fn default_mount(config: &VideoSourceConfig) -> String {
+    match &config.source_type {
+        crate::config::VideoSourceType::Rtsp { mount_point, .. } => format!("/{}", mount_point),
+        _ => format!("/{}", config.name),
+    }
+}
+
+fn normalize_mount(mount_point: &str) -> String {
+    if mount_point.starts_with('/') {
+        mount_point.to_string()
+    } else {
+        format!("/{}", mount_point)
+    }
+}
+
 pub struct RtspServerBuilder {
     config: RtspServerConfig,
     sources: Vec<VideoSourceConfig>,
     global_network_profile: Option<NetworkProfile>,
     per_source_network: HashMap<String, NetworkProfile>,
     custom_network_conditions: Option<NetworkConditions>,
+    client_shaper: Option<Arc<ClientShaper>>,
 }
 
 impl RtspServerBuilder {
@@ -255,6 +1004,7 @@ impl RtspServerBuilder {
             global_network_profile: None,
             per_source_network: HashMap::new(),
             custom_network_conditions: None,
+            client_shaper: None,
         }
     }
 
@@ -268,6 +1018,12 @@ impl RtspServerBuilder {
         self
     }
 
+    /// Try up to `retries` following ports if the configured one is taken.
+    pub fn port_retries(mut self, retries: u16) -> Self {
+        self.config.port_retries = retries;
+        self
+    }
+
     pub fn max_connections(mut self, max: u32) -> Self {
         self.config.max_connections = max;
         self
@@ -323,28 +1079,75 @@ impl RtspServerBuilder {
         self
     }
 
+    /// Transports every mount accepts unless set per mount
+    pub fn transport(mut self, mode: TransportMode) -> Self {
+        self.config.transport = mode;
+        self
+    }
+
+    pub fn mount_transport(mut self, mount_point: &str, mode: TransportMode) -> Self {
+        self.config
+            .mount_transports
+            .insert(mount_point.to_string(), mode);
+        self
+    }
+
+    /// Shape clients at `host` (or all others, for
+    /// [`DEFAULT_CLIENT`](shaping::DEFAULT_CLIENT)) to `kbps`. Any client
+    /// rule makes every mount give each client its own pipeline.
+    pub fn client_bandwidth(mut self, host: &str, kbps: u32) -> Self {
+        self.client_shaper
+            .get_or_insert_with(ClientShaper::new)
+            .set_client(host, ShapingRule::new(kbps));
+        self
+    }
+
+    /// Share `shaper` with the server, to change rules later
+    pub fn client_shaping(mut self, shaper: Arc<ClientShaper>) -> Self {
+        self.client_shaper = Some(shaper);
+        self
+    }
+
+    /// Add a tenant namespace; its sources are mounted by [`build`](Self::build)
+    pub fn namespace(mut self, namespace: NamespaceConfig) -> Self {
+        self.config.namespaces.push(namespace);
+        self
+    }
+
+    /// Keep a rolling time-shift buffer of `mount_point`
+    pub fn mount_dvr(mut self, mount_point: &str, config: DvrConfig) -> Self {
+        self.config.dvr.insert(mount_point.to_string(), config);
+        self
+    }
+
     pub fn per_source_network(mut self, source_name: &str, profile: NetworkProfile) -> Self {
         self.per_source_network
             .insert(source_name.to_string(), profile);
         self
     }
 
-    pub fn build(self) -> Result<RtspServer> {
+    pub fn build(mut self) -> Result<RtspServer> {
+        // Added after the network settings, which their sources use too
+        let namespaces = std::mem::take(&mut self.config.namespaces);
         let mut server = RtspServer::new(self.config)?;
 
         // Apply network configuration
         server.global_network_profile = self.global_network_profile;
         server.per_source_network = self.per_source_network.clone();
+        server.client_shaper = self.client_shaper;
 
-        // If custom conditions are set, convert to Custom profile
+        // Custom conditions are served as the Custom profile
         if let Some(conditions) = self.custom_network_conditions {
-            // We'll handle custom conditions by setting them directly when creating factories
+            server.custom_network_conditions = Some(conditions);
             server.global_network_profile = Some(NetworkProfile::Custom);
         }
 
         for source in self.sources {
             server.add_source(source)?;
         }
+        for namespace in namespaces {
+            server.add_namespace(namespace)?;
+        }
 
         Ok(server)
     }
@@ -392,4 +1195,179 @@ mod tests {
         assert_eq!(server.get_url("/test"), "rtsp://localhost:8554/test");
         assert_eq!(server.get_url("test"), "rtsp://localhost:8554/test");
     }
+
+    fn started_server(port: u16, context: &gstreamer::glib::MainContext) -> RtspServer {
+        let server = RtspServerBuilder::new()
+            .port(port)
+            .address("127.0.0.1")
+            .build()
+            .unwrap();
+        server.start_with_context(Some(context)).unwrap();
+        server
+    }
+
+    #[test]
+    fn test_ephemeral_port() {
+        gstreamer::init().unwrap();
+        let context = gstreamer::glib::MainContext::new();
+
+        let server = started_server(0, &context);
+        let port = server.get_port();
+        assert_ne!(port, 0);
+        assert_eq!(server.get_url("/a"), format!("rtsp://127.0.0.1:{}/a", port));
+        server.stop().unwrap();
+    }
+
+    #[test]
+    fn test_taken_port_fails() {
+        gstreamer::init().unwrap();
+        let context = gstreamer::glib::MainContext::new();
+        let server = started_server(0, &context);
+
+        let conflicting = RtspServerBuilder::new()
+            .port(server.get_port())
+            .address("127.0.0.1")
+            .build()
+            .unwrap();
+        let err = conflicting.start_with_context(Some(&context)).unwrap_err();
+        assert!(err.to_string().contains("already in use"));
+        server.stop().unwrap();
+    }
+
+    #[test]
+    fn test_port_retries() {
+        gstreamer::init().unwrap();
+        let context = gstreamer::glib::MainContext::new();
+        let server = started_server(0, &context);
+        let taken = server.get_port();
+
+        let retrying = RtspServerBuilder::new()
+            .port(taken)
+            .port_retries(10)
+            .address("127.0.0.1")
+            .build()
+            .unwrap();
+        retrying.start_with_context(Some(&context)).unwrap();
+        assert!(retrying.get_port() > taken);
+
+        server.stop().unwrap();
+        retrying.stop().unwrap();
+    }
+
+    #[test]
+    fn test_stop_releases_port() {
+        gstreamer::init().unwrap();
+        let context = gstreamer::glib::MainContext::new();
+
+        let server = started_server(0, &context);
+        assert!(server.is_attached());
+        let port = server.get_port();
+
+        server.stop().unwrap();
+        assert!(!server.is_attached());
+        // A second stop has nothing left to detach
+        server.stop().unwrap();
+
+        let rebound = started_server(port, &context);
+        assert_eq!(rebound.get_port(), port);
+        rebound.stop().unwrap();
+    }
+
+    #[test]
+    fn test_namespace_limits() {
+        gstreamer::init().unwrap();
+
+        let acme = NamespaceConfig::new("acme")
+            .with_max_mounts(2)
+            .with_bandwidth(500)
+            .with_auth("acme", "secret");
+        let mut server = RtspServerBuilder::new()
+            .address("127.0.0.1")
+            .add_test_pattern("lobby", "smpte")
+            .namespace(acme)
+            .namespace(NamespaceConfig::new("globex"))
+            .build()
+            .unwrap();
+
+        let lobby = VideoSourceConfig::test_pattern("lobby", "ball");
+        assert_eq!(
+            server.add_source_to("acme", lobby.clone()).unwrap(),
+            "/acme/lobby"
+        );
+        assert_eq!(
+            server.add_source_to("globex", lobby.clone()).unwrap(),
+            "/globex/lobby"
+        );
+        assert!(server.add_source_to("initech", lobby).is_err());
+
+        server.add_alias("/acme/entrance", "/acme/lobby").unwrap();
+        assert!(server.add_alias("/acme/extra", "/acme/lobby").is_err());
+        assert!(server.add_alias("/globex/entrance", "/acme/lobby").is_err());
+        assert_eq!(
+            server.namespace_mounts("acme"),
+            ["/acme/entrance", "/acme/lobby"]
+        );
+
+        assert!(server.add_namespace(NamespaceConfig::new("acme")).is_err());
+        server.remove_namespace("acme").unwrap();
+        assert_eq!(server.list_namespaces(), ["globex"]);
+        let mut sources = server.list_sources();
+        sources.sort();
+        assert_eq!(sources, ["/globex/lobby", "/lobby"]);
+    }
+
+    #[test]
+    fn test_update_rebuilds_factory() {
+        gstreamer::init().unwrap();
+
+        let mut server = RtspServerBuilder::new()
+            .port(0)
+            .add_test_pattern("cam", "smpte")
+            .build()
+            .unwrap();
+        server.add_alias("/door", "/cam").unwrap();
+        let old = server.factories["/cam"].clone();
+
+        let mut config = VideoSourceConfig::test_pattern("cam", "smpte");
+        config.resolution.width = 640;
+        server.update_source("cam", config).unwrap();
+
+        let new = server.factories["/cam"].clone();
+        assert_ne!(new, old);
+        assert_eq!(server.factories["/door"], new);
+        assert_eq!(server.source_config("/cam").unwrap().resolution.width, 640);
+    }
+
+    #[test]
+    fn test_mount_conditions_change_live() {
+        gstreamer::init().unwrap();
+
+        let mut server = RtspServerBuilder::new()
+            .port(0)
+            .add_test_pattern("calm", "smpte")
+            .add_test_pattern_with_network("rough", "ball", NetworkProfile::Poor)
+            .build()
+            .unwrap();
+
+        assert_eq!(server.resolve_mount("rough").as_deref(), Some("/rough"));
+        assert_eq!(server.resolve_mount("/calm").as_deref(), Some("/calm"));
+        assert!(server.resolve_mount("missing").is_none());
+
+        let conditions = server.mount_conditions();
+        assert_eq!(conditions["/calm"].packet_loss, 0.0);
+        assert!(conditions["/rough"].packet_loss > 0.0);
+
+        server
+            .set_mount_conditions("calm", NetworkConditions::custom(20.0, 100, 0, 10))
+            .unwrap();
+        server.set_mount_network("/rough", None).unwrap();
+        let conditions = server.mount_conditions();
+        assert_eq!(conditions["/calm"].packet_loss, 20.0);
+        assert_eq!(conditions["/rough"].packet_loss, 0.0);
+        assert!(
+            server
+                .set_mount_conditions("/missing", NetworkConditions::perfect())
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,132 @@
+//! Tenant namespaces
+//!
+//! A namespace groups mounts under `/{name}/` so one server can stand in
+//! for several independent camera estates. A namespace can cap how many
+//! mounts it holds and the bitrate of its streams, and can require its own
+//! basic-auth credentials.
+//!
+//! Authentication goes through the server's `RTSPAuth`: a namespace's user
+//! gets a token whose role is only granted on that namespace's factories,
+//! while clients without credentials get a default token whose role is
+//! granted on every public factory.
+
+use crate::config_types::NamespaceConfig;
+use crate::error::{Result, SourceVideoError};
+use gstreamer as gst;
+use gstreamer_rtsp_server as rtsp_server;
+
+/// Role of clients that sent no credentials
+const ANONYMOUS_ROLE: &str = "anonymous";
+
+// Token and permission fields from rtsp-auth.h
+const TOKEN_FACTORY_ROLE: &str = "media.factory.role";
+const PERM_FACTORY_ACCESS: &str = "media.factory.access";
+const PERM_FACTORY_CONSTRUCT: &str = "media.factory.construct";
+
+/// Check that `name` can be used as the first segment of a mount path
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(SourceVideoError::config(format!(
+            "Invalid namespace name '{}': use letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Mount point of `path` inside namespace `name`
+pub fn mount_point(name: &str, path: &str) -> String {
+    format!("/{}/{}", name, path.trim_start_matches('/'))
+}
+
+/// First segment of a nested mount point, the namespace it would belong to
+pub fn namespace_of(mount_point: &str) -> Option<&str> {
+    let (name, rest) = mount_point.trim_start_matches('/').split_once('/')?;
+    (!name.is_empty() && !rest.is_empty()).then_some(name)
+}
+
+/// Role a factory in `namespace` is granted to; public mounts use the
+/// anonymous role
+pub(crate) fn factory_role(namespace: Option<&NamespaceConfig>) -> String {
+    match namespace {
+        Some(namespace) if namespace.authentication.is_some() => role(&namespace.name),
+        _ => ANONYMOUS_ROLE.to_string(),
+    }
+}
+
+/// Let clients with `role` see and play the factory's media
+pub(crate) fn grant(factory: &rtsp_server::RTSPMediaFactory, role: &str) {
+    let permissions = gst::Structure::builder(role)
+        .field(PERM_FACTORY_ACCESS, true)
+        .field(PERM_FACTORY_CONSTRUCT, true)
+        .build();
+    factory.add_role_from_structure(&permissions);
+}
+
+/// Authentication for a server with private namespaces; clients without
+/// credentials still reach the public mounts
+pub(crate) fn new_auth() -> rtsp_server::RTSPAuth {
+    let auth = rtsp_server::RTSPAuth::new();
+    auth.set_default_token(Some(&token(ANONYMOUS_ROLE)));
+    auth
+}
+
+/// Accept the namespace's credentials, if it has any
+pub(crate) fn add_user(auth: &rtsp_server::RTSPAuth, namespace: &NamespaceConfig) {
+    if let Some(credentials) = &namespace.authentication {
+        let basic = rtsp_server::RTSPAuth::make_basic(&credentials.username, &credentials.password);
+        auth.add_basic(basic.as_str(), &token(&role(&namespace.name)));
+    }
+}
+
+pub(crate) fn remove_user(auth: &rtsp_server::RTSPAuth, namespace: &NamespaceConfig) {
+    if let Some(credentials) = &namespace.authentication {
+        let basic = rtsp_server::RTSPAuth::make_basic(&credentials.username, &credentials.password);
+        auth.remove_basic(basic.as_str());
+    }
+}
+
+fn role(name: &str) -> String {
+    // Prefixed so a namespace called "anonymous" gets a role of its own
+    format!("ns-{}", name)
+}
+
+fn token(role: &str) -> rtsp_server::RTSPToken {
+    rtsp_server::RTSPToken::builder()
+        .field(TOKEN_FACTORY_ROLE, role)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("acme-east_2").is_ok());
+        for bad in ["", "a/b", "-acme", "a b"] {
+            assert!(validate_name(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_namespace_paths() {
+        assert_eq!(mount_point("acme", "/lobby"), "/acme/lobby");
+        assert_eq!(namespace_of("/acme/lobby"), Some("acme"));
+        assert_eq!(namespace_of("/lobby"), None);
+        assert_eq!(namespace_of("/acme/"), None);
+    }
+
+    #[test]
+    fn test_factory_role() {
+        let public = NamespaceConfig::new("anonymous");
+        let private = NamespaceConfig::new("anonymous").with_auth("u", "p");
+        assert_eq!(factory_role(None), ANONYMOUS_ROLE);
+        assert_eq!(factory_role(Some(&public)), ANONYMOUS_ROLE);
+        assert_eq!(factory_role(Some(&private)), "ns-anonymous");
+    }
+}
@@ -0,0 +1,279 @@
+//! Pause, resume and frame stepping of file mounts
+//!
+//! File mounts pass their decoded frames through an `identity` named
+//! `playback` before encoding. Pausing a mount blocks that element's sink
+//! pad, so clients keep their sessions but get no new frames; stepping
+//! lets single frames through the block, to hold a specific frame on
+//! screen. Frames after a hold are moved forward in time by its length, so
+//! playback resumes at normal speed instead of racing to catch up.
+
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Launch fragment of the element file mounts are paused at
+pub const PLAYBACK_ELEMENT: &str = "identity name=playback ! ";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackStatus {
+    pub mount: String,
+    pub paused: bool,
+    /// Frames sent since the mount's newest media started
+    pub frame: u64,
+    /// Media of the mount currently running
+    pub media: usize,
+}
+
+/// Playback element of one media, with the block holding it while paused
+struct GatedMedia {
+    element: glib::WeakRef<gst::Element>,
+    frames: Arc<AtomicU64>,
+    block: Option<gst::PadProbeId>,
+}
+
+#[derive(Default)]
+struct MountGate {
+    paused: bool,
+    /// Frames the blocks may still let through
+    steps: Arc<AtomicU64>,
+    media: Vec<GatedMedia>,
+}
+
+impl MountGate {
+    fn prune(&mut self) {
+        self.media.retain(|media| media.element.upgrade().is_some());
+    }
+
+    /// Block `media`, replacing any block it has. A frame waiting at the
+    /// old block is offered to the new one, which is how steps reach it.
+    fn block(steps: &Arc<AtomicU64>, media: &mut GatedMedia) {
+        let Some(pad) = media.element.upgrade().and_then(|e| e.static_pad("sink")) else {
+            return;
+        };
+        let steps = steps.clone();
+        let block = pad.add_probe(
+            gst::PadProbeType::BLOCK | gst::PadProbeType::BUFFER,
+            move |_, _| {
+                if steps
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok()
+                {
+                    gst::PadProbeReturn::Pass
+                } else {
+                    gst::PadProbeReturn::Ok
+                }
+            },
+        );
+        if let Some(old) = std::mem::replace(&mut media.block, block) {
+            pad.remove_probe(old);
+        }
+    }
+
+    fn unblock(media: &mut GatedMedia) {
+        if let (Some(element), Some(block)) = (media.element.upgrade(), media.block.take())
+            && let Some(pad) = element.static_pad("sink")
+        {
+            pad.remove_probe(block);
+        }
+    }
+}
+
+/// Playback state of every file mount
+#[derive(Default)]
+pub struct MountPlayback {
+    mounts: Mutex<HashMap<String, MountGate>>,
+}
+
+impl MountPlayback {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Control the `playback` element of every media `factory` builds
+    pub(crate) fn install(
+        self: &Arc<Self>,
+        factory: &rtsp_server::RTSPMediaFactory,
+        mount_point: &str,
+    ) {
+        let playback = Arc::downgrade(self);
+        let mount_point = mount_point.to_string();
+        factory.connect_media_configure(move |_, media| {
+            let Some(playback) = playback.upgrade() else {
+                return;
+            };
+            let element = media
+                .element()
+                .downcast::<gst::Bin>()
+                .ok()
+                .and_then(|bin| bin.by_name("playback"));
+            match element {
+                Some(element) => playback.attach(&mount_point, &element),
+                None => log::warn!("No playback element in media for {}", mount_point),
+            }
+        });
+    }
+
+    fn attach(&self, mount_point: &str, element: &gst::Element) {
+        let frames = Arc::new(AtomicU64::new(0));
+        if let Some(src) = element.static_pad("src") {
+            let frames = frames.clone();
+            let mut shift = gst::ClockTime::ZERO;
+            src.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+                frames.fetch_add(1, Ordering::Relaxed);
+                let now = pad.parent_element().and_then(|e| e.current_running_time());
+                if let Some(buffer) = info.buffer_mut() {
+                    let buffer = buffer.make_mut();
+                    // A frame behind the clock was held; later ones follow it
+                    if let (Some(pts), Some(now)) = (buffer.pts(), now)
+                        && pts + shift < now
+                    {
+                        shift = now - pts;
+                    }
+                    buffer.set_pts(buffer.pts().map(|pts| pts + shift));
+                    buffer.set_dts(buffer.dts().map(|dts| dts + shift));
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
+
+        let mut mounts = self.mounts.lock().unwrap();
+        let gate = mounts.entry(mount_point.to_string()).or_default();
+        gate.prune();
+        let mut media = GatedMedia {
+            element: element.downgrade(),
+            frames,
+            block: None,
+        };
+        if gate.paused {
+            MountGate::block(&gate.steps, &mut media);
+        }
+        gate.media.push(media);
+    }
+
+    pub fn pause(&self, mount_point: &str) {
+        let mut mounts = self.mounts.lock().unwrap();
+        let gate = mounts.entry(mount_point.to_string()).or_default();
+        gate.prune();
+        if gate.paused {
+            return;
+        }
+        gate.paused = true;
+        gate.steps.store(0, Ordering::SeqCst);
+        for media in &mut gate.media {
+            MountGate::block(&gate.steps, media);
+        }
+    }
+
+    pub fn resume(&self, mount_point: &str) {
+        let mut mounts = self.mounts.lock().unwrap();
+        let Some(gate) = mounts.get_mut(mount_point) else {
+            return;
+        };
+        gate.paused = false;
+        for media in &mut gate.media {
+            MountGate::unblock(media);
+        }
+        gate.prune();
+    }
+
+    /// Let `frames` more frames through, pausing the mount first if it is
+    /// playing
+    pub fn step(&self, mount_point: &str, frames: u64) {
+        self.pause(mount_point);
+        let mut mounts = self.mounts.lock().unwrap();
+        let Some(gate) = mounts.get_mut(mount_point) else {
+            return;
+        };
+        gate.steps.fetch_add(frames, Ordering::SeqCst);
+        for media in &mut gate.media {
+            MountGate::block(&gate.steps, media);
+        }
+    }
+
+    pub fn status(&self, mount_point: &str) -> PlaybackStatus {
+        let mut mounts = self.mounts.lock().unwrap();
+        let gate = mounts.entry(mount_point.to_string()).or_default();
+        gate.prune();
+        PlaybackStatus {
+            mount: mount_point.to_string(),
+            paused: gate.paused,
+            frame: gate
+                .media
+                .last()
+                .map_or(0, |media| media.frames.load(Ordering::Relaxed)),
+            media: gate.media.len(),
+        }
+    }
+
+    /// Let go of a mount that is being removed
+    pub fn forget(&self, mount_point: &str) {
+        self.resume(mount_point);
+        self.mounts.lock().unwrap().remove(mount_point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    /// A playing clip mounted at `/clip`, paused before the first frame
+    fn paused_clip() -> (gst::Pipeline, MountPlayback) {
+        gst::init().unwrap();
+
+        let pipeline = gst::parse::launch(&format!(
+            "videotestsrc num-buffers=100 ! {}fakesink name=sink sync=false",
+            PLAYBACK_ELEMENT
+        ))
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        let playback = MountPlayback::new();
+        playback.attach("/clip", &pipeline.by_name("playback").unwrap());
+        playback.pause("/clip");
+
+        pipeline.set_state(gst::State::Playing).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        (pipeline, playback)
+    }
+
+    #[test]
+    fn test_pause_holds_frames() {
+        let (pipeline, playback) = paused_clip();
+        let status = playback.status("/clip");
+        assert!(status.paused);
+        assert_eq!(status.frame, 0);
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[test]
+    fn test_step_while_paused() {
+        let (pipeline, playback) = paused_clip();
+
+        playback.step("/clip", 2);
+        std::thread::sleep(Duration::from_millis(200));
+        let status = playback.status("/clip");
+        assert!(status.paused);
+        assert_eq!(status.frame, 2);
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[test]
+    fn test_resume() {
+        let (pipeline, playback) = paused_clip();
+
+        playback.resume("/clip");
+        std::thread::sleep(Duration::from_millis(500));
+        let status = playback.status("/clip");
+        assert!(!status.paused);
+        assert!(status.frame > 0);
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+}
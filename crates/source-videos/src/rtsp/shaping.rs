@@ -0,0 +1,321 @@
+//! Per-client bandwidth shaping
+//!
+//! Network profiles act on a source, so every client of a mount sees the
+//! same conditions. A [`ClientShaper`] instead limits each client on its
+//! own: mounts served with one get a pipeline per client, and a token
+//! bucket on that pipeline's `shaper` queue keeps the encoded stream
+//! within the client's rate. When the bucket runs dry the rest of the
+//! group of pictures is dropped and sending resumes on the next keyframe,
+//! so the client never gets frames it cannot decode. The queue holds at
+//! most a second of video for the client's connection to drain.
+//!
+//! Clients are told apart by address, learned from the UDP sink the RTSP
+//! server adds for them. Clients on one machine can still be shaped
+//! differently by connecting through different loopback addresses
+//! (`127.0.0.2`, `127.0.0.3`, ...). TCP-interleaved clients get the
+//! default rule.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Launch fragment limited by the shaper, placed after the encoder
+pub(crate) const SHAPER_ELEMENT: &str = "queue name=shaper max-size-buffers=0 \
+     max-size-bytes=0 max-size-time=1000000000 ! ";
+
+/// Host key matching clients without a rule of their own
+pub const DEFAULT_CLIENT: &str = "*";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShapingRule {
+    pub bandwidth_kbps: u32,
+    /// Bytes that may be sent at once after an idle period; a quarter of
+    /// a second's worth when not set
+    #[serde(default)]
+    pub burst_bytes: Option<u32>,
+}
+
+impl ShapingRule {
+    pub fn new(bandwidth_kbps: u32) -> Self {
+        Self {
+            bandwidth_kbps,
+            burst_bytes: None,
+        }
+    }
+}
+
+/// Token bucket in bytes, allowed to go into debt so that a large buffer
+/// is sent at once and paid for by the wait before the next one
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(rule: ShapingRule) -> Self {
+        let rate = rule.bandwidth_kbps.max(1) as f64 * 1000.0 / 8.0;
+        let burst = match rule.burst_bytes {
+            Some(bytes) => bytes as f64,
+            None => (rate / 4.0).max(1500.0),
+        };
+        Self {
+            rate,
+            burst,
+            tokens: burst,
+            last: None,
+        }
+    }
+
+    /// Spend `bytes` at `now`; returns how long to wait before sending
+    pub fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        }
+        self.last = Some(now);
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Shaping rules by client address, shared by the mounts of a server.
+/// Changes apply to connected clients from their next buffer.
+#[derive(Debug, Default)]
+pub struct ClientShaper {
+    rules: RwLock<HashMap<String, ShapingRule>>,
+}
+
+impl ClientShaper {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Shape clients at `host`, or every other client for [`DEFAULT_CLIENT`]
+    pub fn set_client(&self, host: &str, rule: ShapingRule) {
+        self.rules
+            .write()
+            .unwrap()
+            .insert(normalize_host(host), rule);
+    }
+
+    pub fn remove_client(&self, host: &str) {
+        self.rules.write().unwrap().remove(&normalize_host(host));
+    }
+
+    pub fn rules(&self) -> HashMap<String, ShapingRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// Rule for a client, falling back to the default one
+    pub fn rule_for(&self, host: Option<&str>) -> Option<ShapingRule> {
+        let rules = self.rules.read().unwrap();
+        host.and_then(|host| rules.get(&normalize_host(host)))
+            .or_else(|| rules.get(DEFAULT_CLIENT))
+            .copied()
+    }
+
+    /// Limit the media's `shaper` queue to the rate of the client it is
+    /// served to
+    pub(crate) fn attach(self: &Arc<Self>, media: &rtsp_server::RTSPMedia) {
+        let element = media.element();
+        let Some(pad) = element
+            .downcast_ref::<gst::Bin>()
+            .and_then(|bin| bin.by_name("shaper"))
+            .and_then(|queue| queue.static_pad("src"))
+        else {
+            log::warn!("No shaper element in media, client shaping disabled");
+            return;
+        };
+
+        let pacing = Arc::new(Mutex::new(ClientPacing::default()));
+
+        // The RTSP server adds a UDP sink per client to the media's
+        // pipeline; its client-added signal names the client
+        if let Some(pipeline) = element
+            .parent()
+            .and_then(|parent| parent.downcast::<gst::Bin>().ok())
+        {
+            let pacing = pacing.clone();
+            pipeline.connect_deep_element_added(move |_, _, added| {
+                if added
+                    .factory()
+                    .is_none_or(|factory| factory.name().as_str() != "multiudpsink")
+                {
+                    return;
+                }
+                let pacing = pacing.clone();
+                added.connect("client-added", false, move |values| {
+                    if let Some(Ok(host)) = values.get(1).map(|value| value.get::<String>()) {
+                        log::debug!("Shaping RTSP client {}", host);
+                        pacing.lock().unwrap().host = Some(host);
+                    }
+                    None
+                });
+            });
+        }
+
+        let shaper = self.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+            let admitted =
+                pacing
+                    .lock()
+                    .unwrap()
+                    .admit(&shaper, buffer.size(), keyframe, Instant::now());
+            if admitted {
+                gst::PadProbeReturn::Ok
+            } else {
+                gst::PadProbeReturn::Drop
+            }
+        });
+    }
+}
+
+/// Pacing state of one client's media
+#[derive(Default)]
+struct ClientPacing {
+    host: Option<String>,
+    bucket: Option<(ShapingRule, TokenBucket)>,
+    /// Dropping the rest of the current group of pictures
+    dropping: bool,
+}
+
+impl ClientPacing {
+    /// Whether a frame of `bytes` goes out at `now`. Frames are only sent
+    /// while the bucket is out of debt; once it is not, everything up to
+    /// the next keyframe is dropped.
+    fn admit(&mut self, shaper: &ClientShaper, bytes: usize, keyframe: bool, now: Instant) -> bool {
+        let Some(rule) = shaper.rule_for(self.host.as_deref()) else {
+            self.bucket = None;
+            self.dropping = false;
+            return true;
+        };
+        if self
+            .bucket
+            .as_ref()
+            .is_none_or(|(current, _)| *current != rule)
+        {
+            self.bucket = Some((rule, TokenBucket::new(rule)));
+        }
+        let (_, bucket) = self.bucket.as_mut().unwrap();
+
+        if self.dropping && !keyframe {
+            return false;
+        }
+        if !bucket.take(0, now).is_zero() {
+            self.dropping = true;
+            return false;
+        }
+        self.dropping = false;
+        bucket.take(bytes, now);
+        true
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    IpAddr::from_str(host)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|_| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8 kbps is 1000 bytes a second
+    fn bucket() -> TokenBucket {
+        TokenBucket::new(ShapingRule {
+            bandwidth_kbps: 8,
+            burst_bytes: Some(1000),
+        })
+    }
+
+    #[test]
+    fn test_token_bucket_delays_over_rate() {
+        let mut bucket = bucket();
+        let start = Instant::now();
+        assert_eq!(bucket.take(1000, start), Duration::ZERO);
+        assert_eq!(bucket.take(500, start), Duration::from_millis(500));
+        assert_eq!(
+            bucket.take(0, start + Duration::from_millis(500)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_token_bucket_refill_capped_at_burst() {
+        let mut bucket = bucket();
+        let start = Instant::now();
+        bucket.take(1000, start);
+
+        // Idle time refills no further than the burst
+        assert_eq!(
+            bucket.take(1500, start + Duration::from_secs(60)),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[test]
+    fn test_client_rules() {
+        let shaper = ClientShaper::new();
+        assert_eq!(shaper.rule_for(Some("127.0.0.2")), None);
+
+        shaper.set_client(DEFAULT_CLIENT, ShapingRule::new(4000));
+        shaper.set_client("::1", ShapingRule::new(256));
+        assert_eq!(
+            shaper.rule_for(Some("0:0:0:0:0:0:0:1")),
+            Some(ShapingRule::new(256))
+        );
+        assert_eq!(
+            shaper.rule_for(Some("127.0.0.2")),
+            Some(ShapingRule::new(4000))
+        );
+        assert_eq!(shaper.rule_for(None), Some(ShapingRule::new(4000)));
+    }
+
+    #[test]
+    fn test_drops_rest_of_gop_when_over_rate() {
+        let shaper = ClientShaper::new();
+        shaper.set_client(
+            DEFAULT_CLIENT,
+            ShapingRule {
+                bandwidth_kbps: 8,
+                burst_bytes: Some(1000),
+            },
+        );
+        let mut pacing = ClientPacing::default();
+        let start = Instant::now();
+
+        // The keyframe goes out and puts the bucket into debt
+        assert!(pacing.admit(&shaper, 1500, true, start));
+        assert!(!pacing.admit(&shaper, 100, false, start));
+        // Still within the dropped group after the debt is paid off
+        let later = start + Duration::from_secs(1);
+        assert!(!pacing.admit(&shaper, 100, false, later));
+        assert!(pacing.admit(&shaper, 100, true, later));
+        assert!(pacing.admit(&shaper, 100, false, later));
+    }
+
+    #[test]
+    fn test_unshaped_clients_pass() {
+        let shaper = ClientShaper::new();
+        let mut pacing = ClientPacing::default();
+        assert!(pacing.admit(&shaper, 1_000_000, false, Instant::now()));
+    }
+}
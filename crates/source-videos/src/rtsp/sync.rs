@@ -0,0 +1,151 @@
+//! Shared clock and base time for groups of mounts
+//!
+//! Media of a synced mount run on the group's clock with the group's base
+//! time, set when the media is configured, before it is prepared. Their
+//! running times, and the RTP timestamps clients see, then agree across
+//! the group. Media already running when a group is synced are ended, so
+//! their clients reconnect to media on the shared base time.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+struct SyncPoint {
+    clock: gst::Clock,
+    base_time: gst::ClockTime,
+}
+
+/// Mounts whose media start on a shared base time
+#[derive(Debug, Default)]
+pub struct MountSync {
+    points: Mutex<HashMap<String, SyncPoint>>,
+}
+
+impl MountSync {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Start the mount's media from now on with `clock` and `base_time`
+    pub fn set(&self, mount_point: &str, clock: &gst::Clock, base_time: gst::ClockTime) {
+        self.points.lock().unwrap().insert(
+            mount_point.to_string(),
+            SyncPoint {
+                clock: clock.clone(),
+                base_time,
+            },
+        );
+    }
+
+    pub fn clear(&self, mount_point: &str) {
+        self.points.lock().unwrap().remove(mount_point);
+    }
+
+    pub fn base_time(&self, mount_point: &str) -> Option<gst::ClockTime> {
+        self.point(mount_point).map(|point| point.base_time)
+    }
+
+    fn point(&self, mount_point: &str) -> Option<SyncPoint> {
+        self.points.lock().unwrap().get(mount_point).cloned()
+    }
+
+    /// Put the mount's media on its sync point, once it has one
+    pub(crate) fn install(
+        self: &Arc<Self>,
+        factory: &rtsp_server::RTSPMediaFactory,
+        mount_point: &str,
+    ) {
+        let sync = self.clone();
+        let mount_point = mount_point.to_string();
+        factory.connect_media_configure(move |_, media| {
+            let Some(point) = sync.point(&mount_point) else {
+                return;
+            };
+            media.set_clock(Some(&point.clock));
+            // The media's element sits in the pipeline the media runs
+            let pipeline = media
+                .element()
+                .parent()
+                .and_then(|parent| parent.downcast::<gst::Pipeline>().ok());
+            match pipeline {
+                Some(pipeline) => {
+                    pipeline.use_clock(Some(&point.clock));
+                    pipeline.set_start_time(gst::ClockTime::NONE);
+                    pipeline.set_base_time(point.base_time);
+                }
+                None => log::warn!("No pipeline to sync in media of {}", mount_point),
+            }
+        });
+    }
+}
+
+/// End the sessions on `mount_point`, so its running media are released
+/// and the next clients get new ones; returns how many were ended
+pub(crate) fn end_sessions(server: &rtsp_server::RTSPServer, mount_point: &str) -> usize {
+    let Some(pool) = server.session_pool() else {
+        return 0;
+    };
+    let mut ended = 0;
+    for session in pool.filter(None) {
+        let on_mount = session
+            .filter(None)
+            .iter()
+            .any(|media| media.matches(mount_point) == Some(mount_point.len() as i32));
+        if on_mount && pool.remove(&session).is_ok() {
+            ended += 1;
+        }
+    }
+    ended
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_point_per_mount() {
+        gst::init().unwrap();
+
+        let sync = MountSync::new();
+        let clock = gst::SystemClock::obtain();
+        sync.set("/left", &clock, gst::ClockTime::from_seconds(5));
+
+        assert_eq!(
+            sync.base_time("/left"),
+            Some(gst::ClockTime::from_seconds(5))
+        );
+        assert_eq!(sync.base_time("/right"), None);
+
+        sync.clear("/left");
+        assert_eq!(sync.base_time("/left"), None);
+    }
+
+    #[test]
+    fn test_configured_media_gets_base_time() {
+        gst::init().unwrap();
+
+        let sync = MountSync::new();
+        let factory = rtsp_server::RTSPMediaFactory::new();
+        factory.set_launch("( videotestsrc is-live=true ! rtpvrawpay name=pay0 )");
+        sync.install(&factory, "/left");
+        let clock = gst::SystemClock::obtain();
+        let base_time = clock.time() + gst::ClockTime::from_seconds(1);
+        sync.set("/left", &clock, base_time);
+
+        let url = gstreamer_rtsp::RTSPUrl::parse("rtsp://localhost/left")
+            .1
+            .unwrap();
+        let media = factory.construct(&url).unwrap();
+        let pipeline = media
+            .element()
+            .parent()
+            .and_then(|parent| parent.downcast::<gst::Pipeline>().ok())
+            .unwrap();
+        assert_eq!(pipeline.base_time(), Some(base_time));
+        assert_eq!(pipeline.start_time(), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! Bytes streamed per mount
+//!
+//! Every media a mount's factory builds gets a probe on its payloaders,
+//! adding the size of each RTP packet to the mount's counter. A shared
+//! media is counted once however many clients it is sent to; a mount that
+//! builds a pipeline per client counts each of them.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// RTP bytes sent per mount since the server started
+#[derive(Debug, Default)]
+pub struct TrafficLog {
+    mounts: Mutex<HashMap<String, Arc<AtomicU64>>>,
+}
+
+impl TrafficLog {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn bytes_sent(&self) -> HashMap<String, u64> {
+        self.mounts
+            .lock()
+            .map(|mounts| {
+                mounts
+                    .iter()
+                    .map(|(mount, bytes)| (mount.clone(), bytes.load(Ordering::Relaxed)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn counter(&self, mount_point: &str) -> Arc<AtomicU64> {
+        self.mounts
+            .lock()
+            .unwrap()
+            .entry(mount_point.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Count the payloaded bytes of every media `factory` builds
+    pub(crate) fn count(&self, factory: &rtsp_server::RTSPMediaFactory, mount_point: &str) {
+        let bytes = self.counter(mount_point);
+        factory.connect_media_configure(move |_, media| {
+            let Ok(bin) = media.element().downcast::<gst::Bin>() else {
+                return;
+            };
+            for payloader in bin.iterate_elements().into_iter().flatten() {
+                if !payloader.name().starts_with("pay") {
+                    continue;
+                }
+                let Some(pad) = payloader.static_pad("src") else {
+                    continue;
+                };
+                let bytes = bytes.clone();
+                pad.add_probe(
+                    gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                    move |_, info| {
+                        let size = match &info.data {
+                            Some(gst::PadProbeData::Buffer(buffer)) => buffer.size(),
+                            Some(gst::PadProbeData::BufferList(list)) => {
+                                list.iter().map(|buffer| buffer.size()).sum()
+                            }
+                            _ => 0,
+                        };
+                        bytes.fetch_add(size as u64, Ordering::Relaxed);
+                        gst::PadProbeReturn::Ok
+                    },
+                );
+            }
+        });
+    }
+}
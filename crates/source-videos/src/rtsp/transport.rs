@@ -0,0 +1,289 @@
+//! RTP transport forcing and the transports sessions negotiated
+//!
+//! A mount normally offers UDP, UDP multicast and RTP interleaved in the
+//! RTSP connection (TCP), and the client picks. Forcing one per mount
+//! lets client fallback logic be tested deterministically: a mount forced
+//! to TCP answers a UDP SETUP with `461 Unsupported Transport`. The
+//! [`TransportLog`] records what each session ended up with.
+
+use crate::config::VideoSourceConfig;
+use gstreamer_rtsp as gst_rtsp;
+use gstreamer_rtsp_server as rtsp_server;
+use gstreamer_rtsp_server::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Lower transports a mount accepts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportMode {
+    /// Whatever the client asks for
+    #[default]
+    Any,
+    /// RTP interleaved in the RTSP connection only
+    Tcp,
+    /// Unicast or multicast UDP only
+    Udp,
+}
+
+impl TransportMode {
+    pub(crate) fn protocols(&self) -> gst_rtsp::RTSPLowerTrans {
+        match self {
+            Self::Any => {
+                gst_rtsp::RTSPLowerTrans::UDP
+                    | gst_rtsp::RTSPLowerTrans::UDP_MCAST
+                    | gst_rtsp::RTSPLowerTrans::TCP
+            }
+            Self::Tcp => gst_rtsp::RTSPLowerTrans::TCP,
+            Self::Udp => gst_rtsp::RTSPLowerTrans::UDP | gst_rtsp::RTSPLowerTrans::UDP_MCAST,
+        }
+    }
+}
+
+impl FromStr for TransportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(Self::Any),
+            "tcp" | "interleaved" => Ok(Self::Tcp),
+            "udp" => Ok(Self::Udp),
+            _ => Err(format!(
+                "Unknown transport '{}', expected any, tcp or udp",
+                s
+            )),
+        }
+    }
+}
+
+/// Transport a session's stream was set up with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiatedTransport {
+    Udp,
+    UdpMulticast,
+    Tcp,
+}
+
+impl NegotiatedTransport {
+    fn from_lower(lower: gst_rtsp::RTSPLowerTrans) -> Option<Self> {
+        if lower.contains(gst_rtsp::RTSPLowerTrans::TCP) {
+            Some(Self::Tcp)
+        } else if lower.contains(gst_rtsp::RTSPLowerTrans::UDP_MCAST) {
+            Some(Self::UdpMulticast)
+        } else if lower.contains(gst_rtsp::RTSPLowerTrans::UDP) {
+            Some(Self::Udp)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SessionTransport {
+    pub session_id: String,
+    /// Mount the session plays, when it matches a known source
+    pub mount: Option<String>,
+    pub transport: NegotiatedTransport,
+}
+
+/// Sessions counted by the transport they negotiated since the server
+/// started
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TransportCounts {
+    pub udp: u64,
+    pub udp_multicast: u64,
+    pub tcp: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransportReport {
+    /// Sessions of clients still connected
+    pub sessions: Vec<SessionTransport>,
+    pub totals: TransportCounts,
+}
+
+/// Transports negotiated by the sessions of one server
+#[derive(Debug, Default)]
+pub struct TransportLog {
+    state: Mutex<(HashMap<String, SessionTransport>, TransportCounts)>,
+}
+
+impl TransportLog {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Note a session's transport; each session is counted once
+    pub fn record(&self, session: SessionTransport) {
+        let mut state = self.state.lock().unwrap();
+        let (sessions, totals) = &mut *state;
+        if sessions.contains_key(&session.session_id) {
+            return;
+        }
+        match session.transport {
+            NegotiatedTransport::Udp => totals.udp += 1,
+            NegotiatedTransport::UdpMulticast => totals.udp_multicast += 1,
+            NegotiatedTransport::Tcp => totals.tcp += 1,
+        }
+        log::info!(
+            "RTSP session {} on {} uses {:?}",
+            session.session_id,
+            session.mount.as_deref().unwrap_or("?"),
+            session.transport
+        );
+        sessions.insert(session.session_id.clone(), session);
+    }
+
+    /// Forget a session whose client went away; totals keep it
+    pub fn remove(&self, session_id: &str) {
+        self.state.lock().unwrap().0.remove(session_id);
+    }
+
+    pub fn session(&self, session_id: &str) -> Option<SessionTransport> {
+        self.state.lock().unwrap().0.get(session_id).cloned()
+    }
+
+    pub fn report(&self) -> TransportReport {
+        let state = self.state.lock().unwrap();
+        let mut sessions: Vec<_> = state.0.values().cloned().collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        TransportReport {
+            sessions,
+            totals: state.1,
+        }
+    }
+}
+
+/// Record the transport of every session `server` plays to `log`
+pub(crate) fn track(
+    server: &rtsp_server::RTSPServer,
+    log: Arc<TransportLog>,
+    mounts: Arc<Mutex<HashMap<String, VideoSourceConfig>>>,
+) {
+    server.connect_client_connected(move |_, client| {
+        let sessions: Arc<Mutex<Vec<rtsp_server::RTSPSession>>> = Arc::default();
+
+        let new_sessions = sessions.clone();
+        client.connect_new_session(move |_, session| {
+            new_sessions.lock().unwrap().push(session.clone());
+        });
+
+        // Transports are fixed by SETUP, so they are all known at PLAY
+        let (playing, log_playing, mounts) = (sessions.clone(), log.clone(), mounts.clone());
+        client.connect_play_request(move |_, _| {
+            for session in playing.lock().unwrap().iter() {
+                inspect(session, &log_playing, &mounts);
+            }
+        });
+
+        let log = log.clone();
+        client.connect_closed(move |_| {
+            for session in sessions.lock().unwrap().drain(..) {
+                if let Some(id) = session.sessionid() {
+                    log.remove(&id);
+                }
+            }
+        });
+    });
+}
+
+fn inspect(
+    session: &rtsp_server::RTSPSession,
+    log: &TransportLog,
+    mounts: &Mutex<HashMap<String, VideoSourceConfig>>,
+) {
+    let Some(session_id) = session.sessionid() else {
+        return;
+    };
+    let mounts: Vec<String> = mounts
+        .lock()
+        .map(|mounts| mounts.keys().cloned().collect())
+        .unwrap_or_default();
+
+    for media in session.filter(None) {
+        let mount = mounts
+            .iter()
+            .filter_map(|mount| media.matches(mount).map(|matched| (matched, mount)))
+            .max()
+            .map(|(_, mount)| mount.clone());
+        let transport = media.transports().iter().find_map(|transport| {
+            NegotiatedTransport::from_lower(transport.transport().lower_transport())
+        });
+        if let Some(transport) = transport {
+            log.record(SessionTransport {
+                session_id: session_id.to_string(),
+                mount,
+                transport,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, transport: NegotiatedTransport) -> SessionTransport {
+        SessionTransport {
+            session_id: id.to_string(),
+            mount: Some("/cam".to_string()),
+            transport,
+        }
+    }
+
+    #[test]
+    fn test_parse_transport_mode() {
+        assert_eq!("TCP".parse(), Ok(TransportMode::Tcp));
+        assert_eq!("interleaved".parse(), Ok(TransportMode::Tcp));
+        assert!("quic".parse::<TransportMode>().is_err());
+    }
+
+    #[test]
+    fn test_mode_protocols() {
+        assert!(
+            !TransportMode::Udp
+                .protocols()
+                .contains(gst_rtsp::RTSPLowerTrans::TCP)
+        );
+        assert_eq!(
+            NegotiatedTransport::from_lower(
+                gst_rtsp::RTSPLowerTrans::TCP | gst_rtsp::RTSPLowerTrans::UDP
+            ),
+            Some(NegotiatedTransport::Tcp)
+        );
+    }
+
+    #[test]
+    fn test_log_keeps_open_sessions() {
+        let log = TransportLog::new();
+        log.record(session("a", NegotiatedTransport::Tcp));
+        log.record(session("b", NegotiatedTransport::Udp));
+        log.remove("a");
+
+        assert_eq!(
+            log.report().sessions,
+            [session("b", NegotiatedTransport::Udp)]
+        );
+    }
+
+    #[test]
+    fn test_log_totals_count_sessions_once() {
+        let log = TransportLog::new();
+        log.record(session("a", NegotiatedTransport::Tcp));
+        log.record(session("a", NegotiatedTransport::Tcp));
+        log.record(session("b", NegotiatedTransport::Udp));
+        log.remove("a");
+
+        assert_eq!(
+            log.report().totals,
+            TransportCounts {
+                udp: 1,
+                udp_multicast: 0,
+                tcp: 1,
+            }
+        );
+    }
+}
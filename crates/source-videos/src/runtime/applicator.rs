@@ -1,22 +1,63 @@
 use super::differ::{ConfigChange, ConfigDiffer};
+use crate::config::loader::ConfigValidator;
+use crate::config::validator::DefaultConfigValidator;
+use crate::config_types::WatchConfig;
 use crate::error::{Result, SourceVideoError};
 use crate::manager::VideoSourceManager;
+use crate::network::{NetworkController, NetworkProfile};
+use crate::rtsp::RtspServer;
+#[cfg(feature = "watch")]
+use crate::watch::WatcherManager;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::sync::RwLock;
 
 pub struct ChangeApplicator {
     manager: Arc<VideoSourceManager>,
+    rtsp_server: Option<Arc<RwLock<RtspServer>>>,
+    network: Option<Arc<dyn NetworkController>>,
+    #[cfg(feature = "watch")]
+    watchers: Option<Arc<RwLock<WatcherManager>>>,
 }
 
 impl ChangeApplicator {
     pub fn new(manager: Arc<VideoSourceManager>) -> Self {
-        Self { manager }
+        Self {
+            manager,
+            rtsp_server: None,
+            network: None,
+            #[cfg(feature = "watch")]
+            watchers: None,
+        }
+    }
+
+    /// Server to rebind on port and address changes
+    pub fn with_rtsp_server(mut self, server: Arc<RwLock<RtspServer>>) -> Self {
+        self.rtsp_server = Some(server);
+        self
+    }
+
+    /// Controller that network profile changes are applied to
+    pub fn with_network(mut self, network: Arc<dyn NetworkController>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    #[cfg(feature = "watch")]
+    /// Watchers stopped when file watching is disabled
+    pub fn with_watchers(mut self, watchers: Arc<RwLock<WatcherManager>>) -> Self {
+        self.watchers = Some(watchers);
+        self
     }
 
+    /// Apply `changes` all or none: they are validated before any is
+    /// applied, and if one still fails the ones before it are undone
     pub async fn apply_changes(&self, changes: Vec<ConfigChange>) -> Result<()> {
         if changes.is_empty() {
             return Ok(());
         }
+        self.validate_changes(&changes)?;
 
         let start = Instant::now();
         log::info!("Applying {} configuration changes", changes.len());
@@ -25,28 +66,24 @@ impl ChangeApplicator {
         let differ = ConfigDiffer::new();
         let plan = differ.generate_change_plan(&changes);
 
-        // Track applied changes for potential rollback
-        let mut applied = Vec::new();
+        // Inverse of every applied change, for rollback
+        let mut undo = Vec::new();
 
         // Apply changes in order
         let ordered_changes = plan.get_ordered_changes();
         for change in ordered_changes {
-            match self.apply_change(change.clone()).await {
-                Ok(()) => {
-                    applied.push(change);
-                }
-                Err(e) => {
-                    log::error!("Failed to apply change: {:?}, error: {}", change, e);
-
-                    // Attempt to rollback applied changes
-                    if !applied.is_empty() {
-                        log::info!("Attempting to rollback {} applied changes", applied.len());
-                        self.rollback_changes(applied).await;
-                    }
+            let inverse = self.inverse(&change)?;
+            if let Err(e) = self.apply_change(change.clone()).await {
+                log::error!("Failed to apply change: {:?}, error: {}", change, e);
 
-                    return Err(e);
+                if !undo.is_empty() {
+                    log::info!("Rolling back {} applied changes", undo.len());
+                    self.rollback_changes(undo).await;
                 }
+
+                return Err(e);
             }
+            undo.push(inverse);
         }
 
         let elapsed = start.elapsed();
@@ -55,6 +92,93 @@ impl ChangeApplicator {
         Ok(())
     }
 
+    /// Reject `changes` if any of them cannot be applied, before touching
+    /// anything
+    pub fn validate_changes(&self, changes: &[ConfigChange]) -> Result<()> {
+        self.validate_dependencies(changes)?;
+        let validator = DefaultConfigValidator::new();
+        for change in changes {
+            match change {
+                ConfigChange::SourceAdded { config } => validator.validate_source(config)?,
+                ConfigChange::SourceRemoved { name } => {
+                    self.manager.get_source_config(name)?;
+                }
+                ConfigChange::SourceModified {
+                    name, new_config, ..
+                } => {
+                    self.manager.get_source_config(name)?;
+                    validator.validate_source(new_config)?;
+                }
+                ConfigChange::NetworkProfileChanged {
+                    new_profile: Some(profile),
+                    ..
+                } => {
+                    NetworkProfile::from_str(profile).map_err(SourceVideoError::config)?;
+                }
+                ConfigChange::LogLevelChanged { new_level, .. } => {
+                    parse_log_level(new_level)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The change that undoes `change`, taken before it is applied
+    fn inverse(&self, change: &ConfigChange) -> Result<ConfigChange> {
+        Ok(match change.clone() {
+            ConfigChange::SourceAdded { config } => {
+                ConfigChange::SourceRemoved { name: config.name }
+            }
+            ConfigChange::SourceRemoved { name } => ConfigChange::SourceAdded {
+                config: self.manager.get_source_config(&name)?,
+            },
+            ConfigChange::SourceModified {
+                name,
+                old_config,
+                new_config,
+            } => ConfigChange::SourceModified {
+                name,
+                old_config: new_config,
+                new_config: old_config,
+            },
+            ConfigChange::ServerPortChanged { old_port, new_port } => {
+                ConfigChange::ServerPortChanged {
+                    old_port: new_port,
+                    new_port: old_port,
+                }
+            }
+            ConfigChange::ServerAddressChanged {
+                old_address,
+                new_address,
+            } => ConfigChange::ServerAddressChanged {
+                old_address: new_address,
+                new_address: old_address,
+            },
+            ConfigChange::LogLevelChanged {
+                old_level,
+                new_level,
+            } => ConfigChange::LogLevelChanged {
+                old_level: new_level,
+                new_level: old_level,
+            },
+            ConfigChange::NetworkProfileChanged {
+                old_profile,
+                new_profile,
+            } => ConfigChange::NetworkProfileChanged {
+                old_profile: new_profile,
+                new_profile: old_profile,
+            },
+            ConfigChange::WatchConfigChanged {
+                old_config,
+                new_config,
+            } => ConfigChange::WatchConfigChanged {
+                old_config: new_config,
+                new_config: old_config,
+            },
+        })
+    }
+
     pub async fn apply_change(&self, change: ConfigChange) -> Result<()> {
         match change {
             ConfigChange::SourceAdded { config } => {
@@ -73,10 +197,7 @@ impl ChangeApplicator {
                 new_config,
             } => {
                 log::info!("Modifying source: {}", name);
-                // For now, we'll remove and re-add the source
-                // In the future, this could be optimized to update in-place
-                self.manager.remove_source(&name)?;
-                self.manager.add_source(new_config)?;
+                self.manager.update_source(&name, new_config)?;
             }
 
             ConfigChange::ServerPortChanged {
@@ -84,9 +205,7 @@ impl ChangeApplicator {
                 new_port,
             } => {
                 log::info!("Server port changed to: {}", new_port);
-                // This would require restarting the RTSP server
-                // For now, just log the change
-                log::warn!("Server port changes require application restart");
+                self.rebind_server(None, Some(new_port)).await?;
             }
 
             ConfigChange::ServerAddressChanged {
@@ -94,9 +213,25 @@ impl ChangeApplicator {
                 new_address,
             } => {
                 log::info!("Server address changed to: {}", new_address);
-                // This would require restarting the RTSP server
-                // For now, just log the change
-                log::warn!("Server address changes require application restart");
+                self.rebind_server(Some(&new_address), None).await?;
+            }
+
+            ConfigChange::NetworkProfileChanged {
+                old_profile: _,
+                new_profile,
+            } => {
+                log::info!(
+                    "Network profile changed to: {}",
+                    new_profile.as_deref().unwrap_or("perfect")
+                );
+                self.apply_network_profile(new_profile.as_deref())?;
+            }
+
+            ConfigChange::WatchConfigChanged {
+                old_config: _,
+                new_config,
+            } => {
+                self.apply_watch_config(new_config.as_ref()).await?;
             }
 
             ConfigChange::LogLevelChanged {
@@ -112,60 +247,59 @@ impl ChangeApplicator {
         Ok(())
     }
 
-    async fn rollback_changes(&self, changes: Vec<ConfigChange>) {
-        // Apply inverse operations in reverse order
-        for change in changes.into_iter().rev() {
-            let rollback_result = match change {
-                ConfigChange::SourceAdded { config } => {
-                    // Rollback: remove the added source
-                    self.manager.remove_source(&config.name)
-                }
+    /// Apply the inverses of applied changes, latest first
+    async fn rollback_changes(&self, undo: Vec<ConfigChange>) {
+        for change in undo.into_iter().rev() {
+            if let Err(e) = self.apply_change(change).await {
+                log::error!("Failed to rollback change: {}", e);
+            }
+        }
+    }
 
-                ConfigChange::SourceRemoved { name: _ } => {
-                    // Rollback: cannot re-add removed source without config
-                    // This would require storing the original config
-                    log::warn!("Cannot rollback source removal without original configuration");
-                    Ok(())
-                }
+    /// Managed restart of the RTSP server on a new address or port
+    async fn rebind_server(&self, address: Option<&str>, port: Option<u16>) -> Result<()> {
+        let Some(server) = &self.rtsp_server else {
+            log::warn!("No RTSP server to rebind; the change applies on next start");
+            return Ok(());
+        };
 
-                ConfigChange::SourceModified {
-                    name,
-                    old_config,
-                    new_config: _,
-                } => {
-                    // Rollback: restore the old configuration
-                    self.manager
-                        .remove_source(&name)
-                        .and_then(|_| self.manager.add_source(old_config))
-                        .map(|_| ())
-                }
+        let mut server = server.write().await;
+        let address = address.map_or_else(|| server.get_address().to_string(), str::to_string);
+        let port = port.unwrap_or_else(|| server.get_port());
+        server.rebind(&address, port)?;
+        log::info!("RTSP server now on {}:{}", address, server.get_port());
+        Ok(())
+    }
 
-                _ => Ok(()),
-            };
+    fn apply_network_profile(&self, profile: Option<&str>) -> Result<()> {
+        let profile = match profile {
+            Some(name) => NetworkProfile::from_str(name).map_err(SourceVideoError::config)?,
+            None => NetworkProfile::Perfect,
+        };
 
-            if let Err(e) = rollback_result {
-                log::error!("Failed to rollback change: {}", e);
-            }
+        match &self.network {
+            Some(network) => network.apply_profile(profile),
+            None => log::warn!("No network controller; profile {:?} not applied", profile),
         }
+        Ok(())
     }
 
-    fn update_log_level(&self, level: &str) -> Result<()> {
-        let log_level = match level.to_lowercase().as_str() {
-            "trace" => log::LevelFilter::Trace,
-            "debug" => log::LevelFilter::Debug,
-            "info" => log::LevelFilter::Info,
-            "warn" | "warning" => log::LevelFilter::Warn,
-            "error" => log::LevelFilter::Error,
-            "off" => log::LevelFilter::Off,
-            _ => {
-                return Err(SourceVideoError::config(format!(
-                    "Invalid log level: {}",
-                    level
-                )));
-            }
-        };
+    /// Stop all watchers when watching is turned off; other settings apply
+    /// to watchers added from now on
+    async fn apply_watch_config(&self, config: Option<&WatchConfig>) -> Result<()> {
+        let enabled = config.is_some_and(|config| config.enabled);
+        #[cfg(feature = "watch")]
+        if !enabled && let Some(watchers) = &self.watchers {
+            watchers.write().await.stop_all().await?;
+            log::info!("File watching disabled");
+            return Ok(());
+        }
+        log::info!("Watch settings updated");
+        Ok(())
+    }
 
-        log::set_max_level(log_level);
+    fn update_log_level(&self, level: &str) -> Result<()> {
+        log::set_max_level(parse_log_level(level)?);
         log::info!("Log level updated to: {}", level);
 
         Ok(())
@@ -204,6 +338,21 @@ impl ChangeApplicator {
     }
 }
 
+fn parse_log_level(level: &str) -> Result<log::LevelFilter> {
+    match level.to_lowercase().as_str() {
+        "trace" => Ok(log::LevelFilter::Trace),
+        "debug" => Ok(log::LevelFilter::Debug),
+        "info" => Ok(log::LevelFilter::Info),
+        "warn" | "warning" => Ok(log::LevelFilter::Warn),
+        "error" => Ok(log::LevelFilter::Error),
+        "off" => Ok(log::LevelFilter::Off),
+        _ => Err(SourceVideoError::config(format!(
+            "Invalid log level: {}",
+            level
+        ))),
+    }
+}
+
 pub struct PerformanceMonitor {
     changes: Vec<(ConfigChange, std::time::Duration)>,
 }
@@ -288,6 +437,53 @@ mod tests {
         assert!(applicator.validate_dependencies(&changes).is_err());
     }
 
+    #[tokio::test]
+    async fn test_invalid_change_applies_nothing() {
+        gstreamer::init().unwrap();
+
+        let manager = Arc::new(VideoSourceManager::new());
+        let applicator = ChangeApplicator::new(manager.clone());
+
+        let changes = vec![
+            ConfigChange::SourceAdded {
+                config: VideoSourceConfig::test_pattern("source1", "smpte"),
+            },
+            ConfigChange::LogLevelChanged {
+                old_level: "info".to_string(),
+                new_level: "loud".to_string(),
+            },
+        ];
+        assert!(applicator.apply_changes(changes).await.is_err());
+        assert!(manager.get_source_config("source1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_change_rolls_back_removal() {
+        gstreamer::init().unwrap();
+
+        let manager = Arc::new(VideoSourceManager::new());
+        manager
+            .add_source(VideoSourceConfig::test_pattern("kept", "smpte"))
+            .unwrap();
+        let applicator = ChangeApplicator::new(manager.clone());
+
+        // Valid up front, but adding `other` fails once it is running
+        let changes = vec![
+            ConfigChange::SourceRemoved {
+                name: "kept".to_string(),
+            },
+            ConfigChange::SourceAdded {
+                config: VideoSourceConfig::test_pattern("other", "ball"),
+            },
+        ];
+        manager
+            .add_source(VideoSourceConfig::test_pattern("other", "snow"))
+            .unwrap();
+
+        assert!(applicator.apply_changes(changes).await.is_err());
+        assert!(manager.get_source_config("kept").is_ok());
+    }
+
     #[test]
     fn test_performance_monitor() {
         let mut monitor = PerformanceMonitor::new();
@@ -1,5 +1,5 @@
 #![allow(unused)]
-use crate::config_types::{AppConfig, VideoSourceConfig};
+use crate::config_types::{AppConfig, VideoSourceConfig, WatchConfig};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,6 +27,24 @@ pub enum ConfigChange {
         old_level: String,
         new_level: String,
     },
+    NetworkProfileChanged {
+        old_profile: Option<String>,
+        new_profile: Option<String>,
+    },
+    WatchConfigChanged {
+        old_config: Option<WatchConfig>,
+        new_config: Option<WatchConfig>,
+    },
+}
+
+impl ConfigChange {
+    /// Changes that rebind the RTSP server and so disconnect its clients
+    pub fn requires_restart(&self) -> bool {
+        matches!(
+            self,
+            Self::ServerPortChanged { .. } | Self::ServerAddressChanged { .. }
+        )
+    }
 }
 
 pub struct ConfigDiffer;
@@ -62,6 +80,20 @@ impl ConfigDiffer {
             });
         }
 
+        if old.network_profile != new.network_profile {
+            changes.push(ConfigChange::NetworkProfileChanged {
+                old_profile: old.network_profile.clone(),
+                new_profile: new.network_profile.clone(),
+            });
+        }
+
+        if old.watch != new.watch {
+            changes.push(ConfigChange::WatchConfigChanged {
+                old_config: old.watch.clone(),
+                new_config: new.watch.clone(),
+            });
+        }
+
         // Check source changes
         let old_sources: HashMap<String, VideoSourceConfig> = old
             .sources
@@ -278,4 +310,50 @@ mod tests {
         assert!(matches!(ordered[1], ConfigChange::SourceModified { .. }));
         assert!(matches!(ordered[2], ConfigChange::SourceAdded { .. }));
     }
+
+    #[test]
+    fn test_server_change_requires_restart() {
+        let differ = ConfigDiffer::new();
+        let old_config = AppConfig::default();
+        let mut new_config = old_config.clone();
+        new_config.server.port = 9554;
+
+        let changes = differ.diff(&old_config, &new_config);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].requires_restart());
+    }
+
+    #[test]
+    fn test_network_profile_change() {
+        let differ = ConfigDiffer::new();
+        let old_config = AppConfig::default();
+        let mut new_config = old_config.clone();
+        new_config.network_profile = Some("3g".to_string());
+
+        let changes = differ.diff(&old_config, &new_config);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            &changes[0],
+            ConfigChange::NetworkProfileChanged { new_profile: Some(p), .. } if p == "3g"
+        ));
+        assert!(!changes[0].requires_restart());
+    }
+
+    #[test]
+    fn test_watch_config_change() {
+        let differ = ConfigDiffer::new();
+        let old_config = AppConfig::default();
+        let mut new_config = old_config.clone();
+        new_config.watch = Some(serde_json::from_str("{}").unwrap());
+
+        let changes = differ.diff(&old_config, &new_config);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            changes[0],
+            ConfigChange::WatchConfigChanged {
+                old_config: None,
+                ..
+            }
+        ));
+    }
 }
@@ -6,6 +6,10 @@ pub mod signal_handler;
 use crate::config_types::{AppConfig, VideoSourceConfig};
 use crate::error::{Result, SourceVideoError};
 use crate::manager::VideoSourceManager;
+use crate::network::NetworkController;
+use crate::rtsp::RtspServer;
+#[cfg(feature = "watch")]
+use crate::watch::WatcherManager;
 use applicator::ChangeApplicator;
 use differ::{ConfigChange, ConfigDiffer};
 use events::{ConfigurationEvent, EventBus};
@@ -19,6 +23,10 @@ pub struct RuntimeManager {
     current_config: Arc<RwLock<AppConfig>>,
     config_history: Arc<RwLock<VecDeque<AppConfig>>>,
     max_history: usize,
+    rtsp_server: Option<Arc<RwLock<RtspServer>>>,
+    network: Option<Arc<dyn NetworkController>>,
+    #[cfg(feature = "watch")]
+    watchers: Option<Arc<RwLock<WatcherManager>>>,
 }
 
 impl RuntimeManager {
@@ -29,6 +37,10 @@ impl RuntimeManager {
             current_config: Arc::new(RwLock::new(initial_config)),
             config_history: Arc::new(RwLock::new(VecDeque::new())),
             max_history: 10,
+            rtsp_server: None,
+            network: None,
+            #[cfg(feature = "watch")]
+            watchers: None,
         }
     }
 
@@ -37,6 +49,40 @@ impl RuntimeManager {
         self
     }
 
+    /// Rebind this server when the port or address changes
+    pub fn with_rtsp_server(mut self, server: Arc<RwLock<RtspServer>>) -> Self {
+        self.rtsp_server = Some(server);
+        self
+    }
+
+    /// Apply network profile changes through this controller
+    pub fn with_network(mut self, network: Arc<dyn NetworkController>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    #[cfg(feature = "watch")]
+    /// Stop these watchers when file watching is disabled
+    pub fn with_watchers(mut self, watchers: Arc<RwLock<WatcherManager>>) -> Self {
+        self.watchers = Some(watchers);
+        self
+    }
+
+    fn applicator(&self) -> ChangeApplicator {
+        let mut applicator = ChangeApplicator::new(self.manager.clone());
+        if let Some(server) = &self.rtsp_server {
+            applicator = applicator.with_rtsp_server(server.clone());
+        }
+        if let Some(network) = &self.network {
+            applicator = applicator.with_network(network.clone());
+        }
+        #[cfg(feature = "watch")]
+        if let Some(watchers) = &self.watchers {
+            applicator = applicator.with_watchers(watchers.clone());
+        }
+        applicator
+    }
+
     pub async fn apply_config(&self, new_config: AppConfig) -> Result<()> {
         let current = self.current_config.read().await;
 
@@ -56,7 +102,7 @@ impl RuntimeManager {
         drop(current);
 
         // Apply changes
-        let applicator = ChangeApplicator::new(self.manager.clone());
+        let applicator = self.applicator();
 
         match applicator.apply_changes(changes.clone()).await {
             Ok(()) => {
@@ -120,7 +166,7 @@ impl RuntimeManager {
         let changes = differ.diff(&*current, &config);
         drop(current);
 
-        let applicator = ChangeApplicator::new(self.manager.clone());
+        let applicator = self.applicator();
         applicator.apply_changes(changes).await?;
 
         let mut current = self.current_config.write().await;
@@ -155,17 +201,17 @@ impl RuntimeManager {
             })?;
 
         let old_config = current.sources[source_index].clone();
-        current.sources[source_index] = config.clone();
 
-        // Apply the change
+        // Apply the change; the running source is reconfigured in place
         let change = ConfigChange::SourceModified {
             name: source_name.to_string(),
             old_config,
-            new_config: config,
+            new_config: config.clone(),
         };
 
-        let applicator = ChangeApplicator::new(self.manager.clone());
+        let applicator = self.applicator();
         applicator.apply_change(change).await?;
+        current.sources[source_index] = config;
 
         self.event_bus
             .emit(ConfigurationEvent::SourceUpdated {
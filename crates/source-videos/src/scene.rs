@@ -0,0 +1,634 @@
+//! Synthetic scenes with known ground truth
+//!
+//! A [`SceneConfig`] describes solid rectangles moving along deterministic
+//! [`Trajectory`]s. [`SceneGenerator`] renders it frame by frame through
+//! appsrc and writes the exact bounding box of every object in every frame
+//! as COCO JSON and MOT text next to the video, so detection and tracking
+//! output can be scored against truth.
+//!
+//! Objects are drawn in list order, so later objects occlude earlier ones;
+//! occluded objects are still annotated.
+
+use crate::encoding::EncodingConfig;
+use crate::error::{Result, SourceVideoError};
+use crate::file_utils::detect_container_format;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// COCO category names used for random scenes
+const RANDOM_LABELS: &[&str] = &["person", "car", "bicycle", "dog", "truck"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trajectory {
+    /// Start at (x, y) and move at (vx, vy) pixels per second, bouncing off
+    /// the frame edges
+    Bounce { x: f64, y: f64, vx: f64, vy: f64 },
+    /// Move in a straight line over the whole scene; may leave the frame
+    Linear { from: (f64, f64), to: (f64, f64) },
+    /// Circle around (cx, cy) once per period; may leave the frame
+    Circle {
+        cx: f64,
+        cy: f64,
+        radius: f64,
+        period_secs: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneObject {
+    /// Track id in the annotations
+    pub id: u32,
+    pub label: String,
+    pub width: u32,
+    pub height: u32,
+    pub color: [u8; 3],
+    pub trajectory: Trajectory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    pub fn area(&self) -> u32 {
+        self.width * self.height
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Zero-based frame number
+    pub frame: u32,
+    pub object_id: u32,
+    pub label: String,
+    pub bbox: BoundingBox,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub duration_secs: f64,
+    pub background: [u8; 3],
+    pub objects: Vec<SceneObject>,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            fps: 30,
+            duration_secs: 10.0,
+            background: [32, 32, 32],
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl SceneConfig {
+    /// Load from a TOML or JSON file, chosen by extension
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        crate::file_utils::load_toml_or_json(path.as_ref(), "scene")
+    }
+
+    /// Add `count` bouncing objects with sizes, colors and velocities drawn
+    /// from `seed`, so the same seed always gives the same scene. Fails on
+    /// a scene that does not [`validate`](Self::validate).
+    pub fn with_random_objects(mut self, count: usize, seed: u64) -> Result<Self> {
+        self.validate()?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let first_id = self.objects.iter().map(|o| o.id).max().unwrap_or(0) + 1;
+        for i in 0..count {
+            let width = rng.gen_range(self.width / 16..=self.width / 6).max(1);
+            let height = rng.gen_range(self.height / 12..=self.height / 4).max(1);
+            let speed = self.width as f64 / 4.0;
+            self.objects.push(SceneObject {
+                id: first_id + i as u32,
+                label: RANDOM_LABELS[i % RANDOM_LABELS.len()].to_string(),
+                width,
+                height,
+                color: [
+                    rng.gen_range(64..=255),
+                    rng.gen_range(64..=255),
+                    rng.gen_range(64..=255),
+                ],
+                trajectory: Trajectory::Bounce {
+                    x: rng.gen_range(0.0..(self.width - width.min(self.width)) as f64 + 1.0),
+                    y: rng.gen_range(0.0..(self.height - height.min(self.height)) as f64 + 1.0),
+                    vx: rng.gen_range(-speed..speed),
+                    vy: rng.gen_range(-speed..speed),
+                },
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 || self.fps == 0 || self.duration_secs <= 0.0 {
+            return Err(SourceVideoError::config(
+                "Scene size, framerate and duration must be positive",
+            ));
+        }
+        let mut ids = HashSet::new();
+        for object in &self.objects {
+            if object.width == 0 || object.height == 0 {
+                return Err(SourceVideoError::config(format!(
+                    "Object {} has an empty size",
+                    object.id
+                )));
+            }
+            if !ids.insert(object.id) {
+                return Err(SourceVideoError::config(format!(
+                    "Duplicate object id {}",
+                    object.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        (self.duration_secs * self.fps as f64).round() as u32
+    }
+
+    /// Top-left corner of `object` at `time` seconds
+    fn position(&self, object: &SceneObject, time: f64) -> (f64, f64) {
+        let max_x = self.width.saturating_sub(object.width) as f64;
+        let max_y = self.height.saturating_sub(object.height) as f64;
+        match object.trajectory {
+            Trajectory::Bounce { x, y, vx, vy } => {
+                (reflect(x + vx * time, max_x), reflect(y + vy * time, max_y))
+            }
+            Trajectory::Linear { from, to } => {
+                let progress = (time / self.duration_secs).clamp(0.0, 1.0);
+                (
+                    from.0 + (to.0 - from.0) * progress,
+                    from.1 + (to.1 - from.1) * progress,
+                )
+            }
+            Trajectory::Circle {
+                cx,
+                cy,
+                radius,
+                period_secs,
+            } => {
+                let angle = std::f64::consts::TAU * time / period_secs.max(f64::EPSILON);
+                (
+                    cx + radius * angle.cos() - object.width as f64 / 2.0,
+                    cy + radius * angle.sin() - object.height as f64 / 2.0,
+                )
+            }
+        }
+    }
+
+    /// Visible part of `object` in `frame`, in whole pixels
+    fn visible_box(&self, object: &SceneObject, frame: u32) -> Option<BoundingBox> {
+        let (x, y) = self.position(object, frame as f64 / self.fps as f64);
+        let (x, y) = (x.round() as i64, y.round() as i64);
+        let left = x.clamp(0, self.width as i64);
+        let top = y.clamp(0, self.height as i64);
+        let right = (x + object.width as i64).clamp(0, self.width as i64);
+        let bottom = (y + object.height as i64).clamp(0, self.height as i64);
+        (right > left && bottom > top).then(|| BoundingBox {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    /// Ground truth for one frame: every object at least partly in view
+    pub fn annotations(&self, frame: u32) -> Vec<Annotation> {
+        self.objects
+            .iter()
+            .filter_map(|object| {
+                self.visible_box(object, frame).map(|bbox| Annotation {
+                    frame,
+                    object_id: object.id,
+                    label: object.label.clone(),
+                    bbox,
+                })
+            })
+            .collect()
+    }
+
+    /// RGBA pixels of one frame
+    pub fn render_frame(&self, frame: u32) -> Vec<u8> {
+        let stride = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(stride * self.height as usize);
+        for _ in 0..self.width as usize * self.height as usize {
+            pixels.extend_from_slice(&[
+                self.background[0],
+                self.background[1],
+                self.background[2],
+                255,
+            ]);
+        }
+
+        for object in &self.objects {
+            let Some(bbox) = self.visible_box(object, frame) else {
+                continue;
+            };
+            let [r, g, b] = object.color;
+            for row in bbox.y..bbox.y + bbox.height {
+                let start = row as usize * stride + bbox.x as usize * 4;
+                let end = start + bbox.width as usize * 4;
+                for pixel in pixels[start..end].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&[r, g, b, 255]);
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Category names in order of first appearance; COCO ids are 1-based
+    /// positions in this list
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = Vec::new();
+        for object in &self.objects {
+            if !categories.contains(&object.label) {
+                categories.push(object.label.clone());
+            }
+        }
+        categories
+    }
+
+    /// MOTChallenge ground truth: frame,id,left,top,width,height,conf,x,y,z
+    /// with 1-based frames
+    pub fn to_mot(&self) -> String {
+        let mut lines = String::new();
+        for frame in 0..self.frame_count() {
+            for annotation in self.annotations(frame) {
+                let bbox = annotation.bbox;
+                lines.push_str(&format!(
+                    "{},{},{},{},{},{},1,-1,-1,-1\n",
+                    frame + 1,
+                    annotation.object_id,
+                    bbox.x,
+                    bbox.y,
+                    bbox.width,
+                    bbox.height
+                ));
+            }
+        }
+        lines
+    }
+
+    /// COCO detection ground truth, one image per frame, with the track id
+    /// on every annotation
+    pub fn to_coco(&self, video_name: &str) -> serde_json::Value {
+        let categories = self.categories();
+        let category_id = |label: &str| {
+            categories
+                .iter()
+                .position(|category| category == label)
+                .map_or(0, |index| index + 1)
+        };
+
+        let mut images = Vec::new();
+        let mut annotations = Vec::new();
+        for frame in 0..self.frame_count() {
+            images.push(serde_json::json!({
+                "id": frame + 1,
+                "file_name": format!("{}#{:06}", video_name, frame),
+                "frame_index": frame,
+                "width": self.width,
+                "height": self.height,
+            }));
+            for annotation in self.annotations(frame) {
+                let bbox = annotation.bbox;
+                annotations.push(serde_json::json!({
+                    "id": annotations.len() + 1,
+                    "image_id": frame + 1,
+                    "category_id": category_id(&annotation.label),
+                    "track_id": annotation.object_id,
+                    "bbox": [bbox.x, bbox.y, bbox.width, bbox.height],
+                    "area": bbox.area(),
+                    "iscrowd": 0,
+                }));
+            }
+        }
+
+        serde_json::json!({
+            "images": images,
+            "annotations": annotations,
+            "categories": categories
+                .iter()
+                .enumerate()
+                .map(|(index, name)| serde_json::json!({ "id": index + 1, "name": name }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Write `<video>.coco.json` and `<video>.mot.txt` next to `video_path`
+    pub fn write_annotations(&self, video_path: &Path) -> Result<(PathBuf, PathBuf)> {
+        let coco_path = video_path.with_extension("coco.json");
+        let mot_path = video_path.with_extension("mot.txt");
+        let video_name = video_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let coco = serde_json::to_string_pretty(&self.to_coco(&video_name))
+            .map_err(|e| SourceVideoError::config(format!("Cannot encode COCO: {}", e)))?;
+        std::fs::write(&coco_path, coco)?;
+        std::fs::write(&mot_path, self.to_mot())?;
+        Ok((coco_path, mot_path))
+    }
+}
+
+/// Fold `value` into [0, max] as if bouncing between the bounds
+fn reflect(value: f64, max: f64) -> f64 {
+    if max <= 0.0 {
+        return 0.0;
+    }
+    let folded = value.rem_euclid(2.0 * max);
+    if folded > max {
+        2.0 * max - folded
+    } else {
+        folded
+    }
+}
+
+/// Renders a [`SceneConfig`] to a video file along with its ground truth
+pub struct SceneGenerator {
+    scene: SceneConfig,
+    output_path: PathBuf,
+    encoding: EncodingConfig,
+}
+
+impl SceneGenerator {
+    pub fn new(scene: SceneConfig, output_path: impl AsRef<Path>) -> Self {
+        let output_path = output_path.as_ref().to_path_buf();
+        let container =
+            detect_container_format(&output_path).unwrap_or(crate::config::FileContainer::Mp4);
+        Self {
+            scene,
+            output_path,
+            encoding: EncodingConfig::for_container(container),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: EncodingConfig) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn scene(&self) -> &SceneConfig {
+        &self.scene
+    }
+
+    /// Render the video and write the annotations; returns the COCO and MOT
+    /// paths
+    pub fn generate(&self) -> Result<(PathBuf, PathBuf)> {
+        self.scene.validate()?;
+        self.encoding.validate()?;
+
+        let pipeline = gst::Pipeline::builder().name("scene-gen").build();
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("format", "RGBA")
+            .field("width", self.scene.width as i32)
+            .field("height", self.scene.height as i32)
+            .field("framerate", gst::Fraction::new(self.scene.fps as i32, 1))
+            .build();
+        let frame_bytes = self.scene.width as u64 * self.scene.height as u64 * 4;
+        let appsrc = gst_app::AppSrc::builder()
+            .name("source")
+            .caps(&caps)
+            .format(gst::Format::Time)
+            .block(true)
+            .max_bytes(frame_bytes * 4)
+            .build();
+
+        let mut chain = vec![appsrc.clone().upcast::<gst::Element>()];
+        chain.push(
+            gst::ElementFactory::make("videoconvert")
+                .name("convert")
+                .build()
+                .map_err(|_| SourceVideoError::element("videoconvert"))?,
+        );
+        if let Some(format) = &self.encoding.pixel_format {
+            let caps = gst::Caps::builder("video/x-raw")
+                .field("format", format.as_str())
+                .build();
+            chain.push(
+                gst::ElementFactory::make("capsfilter")
+                    .name("encoder-format")
+                    .property("caps", &caps)
+                    .build()
+                    .map_err(|_| SourceVideoError::element("capsfilter"))?,
+            );
+        }
+        chain.push(self.encoding.create_encoder()?);
+        chain.extend(self.encoding.create_parser()?);
+        chain.push(self.encoding.create_muxer()?);
+        chain.push(
+            gst::ElementFactory::make("filesink")
+                .name("sink")
+                .property("location", self.output_path.to_string_lossy().to_string())
+                .build()
+                .map_err(|_| SourceVideoError::element("filesink"))?,
+        );
+
+        pipeline
+            .add_many(&chain)
+            .map_err(|_| SourceVideoError::pipeline("Failed to add scene elements"))?;
+        gst::Element::link_many(&chain)
+            .map_err(|_| SourceVideoError::pipeline("Failed to link scene elements"))?;
+
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|_| SourceVideoError::StateChange("Failed to start scene".to_string()))?;
+        let result = self
+            .push_frames(&appsrc)
+            .and_then(|()| wait_for_eos(&pipeline));
+        let _ = pipeline.set_state(gst::State::Null);
+        result?;
+
+        self.scene.write_annotations(&self.output_path)
+    }
+
+    fn push_frames(&self, appsrc: &gst_app::AppSrc) -> Result<()> {
+        let frame_duration = gst::ClockTime::SECOND / self.scene.fps as u64;
+        for frame in 0..self.scene.frame_count() {
+            let mut buffer = gst::Buffer::from_mut_slice(self.scene.render_frame(frame));
+            {
+                let buffer = buffer.get_mut().expect("new buffer is writable");
+                buffer.set_pts(frame_duration * frame as u64);
+                buffer.set_duration(frame_duration);
+            }
+            appsrc.push_buffer(buffer).map_err(|e| {
+                SourceVideoError::pipeline(format!("Scene frame {} rejected: {:?}", frame, e))
+            })?;
+        }
+        appsrc
+            .end_of_stream()
+            .map_err(|e| SourceVideoError::pipeline(format!("Scene EOS rejected: {:?}", e)))?;
+        Ok(())
+    }
+}
+
+fn wait_for_eos(pipeline: &gst::Pipeline) -> Result<()> {
+    let bus = pipeline.bus().expect("Pipeline should have a bus");
+    let message = bus.timed_pop_filtered(
+        gst::ClockTime::NONE,
+        &[gst::MessageType::Eos, gst::MessageType::Error],
+    );
+    match message.as_ref().map(|message| message.view()) {
+        Some(gst::MessageView::Error(err)) => Err(SourceVideoError::pipeline(format!(
+            "Scene generation error from {:?}: {}",
+            err.src().map(|s| s.path_string()),
+            err.error()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene() -> SceneConfig {
+        SceneConfig {
+            width: 100,
+            height: 50,
+            fps: 10,
+            duration_secs: 1.0,
+            background: [0, 0, 0],
+            objects: vec![
+                SceneObject {
+                    id: 1,
+                    label: "car".to_string(),
+                    width: 20,
+                    height: 10,
+                    color: [255, 0, 0],
+                    trajectory: Trajectory::Bounce {
+                        x: 70.0,
+                        y: 0.0,
+                        vx: 100.0,
+                        vy: 0.0,
+                    },
+                },
+                SceneObject {
+                    id: 2,
+                    label: "person".to_string(),
+                    width: 10,
+                    height: 10,
+                    color: [0, 255, 0],
+                    trajectory: Trajectory::Linear {
+                        from: (-10.0, 20.0),
+                        to: (90.0, 20.0),
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_frame_count() {
+        let scene = scene();
+        assert!(scene.validate().is_ok());
+        assert_eq!(scene.frame_count(), 10);
+    }
+
+    #[test]
+    fn test_bounce_reflects_at_edge() {
+        let scene = scene();
+        assert_eq!(scene.annotations(0)[0].bbox.x, 70);
+
+        // 70 + 100 * 0.2 = 90 bounces back from the right edge at 80
+        assert_eq!(scene.annotations(2)[0].bbox.x, 70);
+    }
+
+    #[test]
+    fn test_objects_outside_frame_are_clipped() {
+        let scene = scene();
+
+        // The linear object starts fully outside the frame
+        let first = scene.annotations(0);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].object_id, 1);
+
+        // -10 + 100 * 0.1 puts it just inside the left edge
+        let entering = scene.annotations(1);
+        assert_eq!(
+            entering[1].bbox,
+            BoundingBox {
+                x: 0,
+                y: 20,
+                width: 10,
+                height: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_matches_annotations() {
+        let scene = scene();
+        let pixels = scene.render_frame(1);
+        for annotation in scene.annotations(1) {
+            let bbox = annotation.bbox;
+            let at = |x: u32, y: u32| {
+                let offset = ((y * scene.width + x) * 4) as usize;
+                &pixels[offset..offset + 4]
+            };
+            let color = scene
+                .objects
+                .iter()
+                .find(|object| object.id == annotation.object_id)
+                .unwrap()
+                .color;
+            assert_eq!(at(bbox.x, bbox.y)[..3], color);
+            assert_eq!(
+                at(bbox.x + bbox.width - 1, bbox.y + bbox.height - 1)[..3],
+                color
+            );
+        }
+        assert_eq!(&pixels[..4], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_ground_truth_formats() {
+        let scene = scene();
+        let mot = scene.to_mot();
+        assert!(mot.starts_with("1,1,70,0,20,10,1,-1,-1,-1\n"));
+
+        let coco = scene.to_coco("scene.mp4");
+        assert_eq!(coco["images"].as_array().unwrap().len(), 10);
+        assert_eq!(coco["categories"][1]["name"], "person");
+        assert_eq!(
+            coco["annotations"].as_array().unwrap().len(),
+            mot.lines().count()
+        );
+
+        let random = SceneConfig::default().with_random_objects(3, 7).unwrap();
+        assert_eq!(
+            random,
+            SceneConfig::default().with_random_objects(3, 7).unwrap()
+        );
+        assert!(random.validate().is_ok());
+    }
+
+    #[test]
+    fn test_random_objects_in_empty_scene_fail() {
+        let empty = SceneConfig {
+            width: 0,
+            ..Default::default()
+        };
+        assert!(empty.with_random_objects(3, 7).is_err());
+    }
+}
@@ -0,0 +1,11 @@
+//! Integration with OS service managers: systemd notify on Linux and the
+//! Windows Service Control Manager.
+
+pub mod systemd;
+#[cfg(windows)]
+pub mod windows;
+
+pub use systemd::{SystemdNotifier, SystemdUnit};
+
+/// Default service name used when registering with the OS
+pub const DEFAULT_SERVICE_NAME: &str = "source-videos";
@@ -0,0 +1,259 @@
+use crate::error::{Result, SourceVideoError};
+use crate::shutdown::ShutdownToken;
+use std::path::PathBuf;
+use std::time::Duration;
+
+enum Notification<'a> {
+    Ready,
+    Reloading,
+    Stopping,
+    Watchdog,
+    Status(&'a str),
+}
+
+/// Sends `sd_notify` state changes when running as a `Type=notify` unit.
+///
+/// Every call is a no-op when `NOTIFY_SOCKET` is unset or the platform is
+/// not Unix, so the notifier can be used unconditionally.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemdNotifier {
+    enabled: bool,
+}
+
+impl SystemdNotifier {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: cfg!(unix) && std::env::var_os("NOTIFY_SOCKET").is_some(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn ready(&self) -> Result<()> {
+        self.send(Notification::Ready)
+    }
+
+    pub fn reloading(&self) -> Result<()> {
+        self.send(Notification::Reloading)
+    }
+
+    pub fn stopping(&self) -> Result<()> {
+        self.send(Notification::Stopping)
+    }
+
+    pub fn watchdog(&self) -> Result<()> {
+        self.send(Notification::Watchdog)
+    }
+
+    pub fn status(&self, message: &str) -> Result<()> {
+        self.send(Notification::Status(message))
+    }
+
+    /// Watchdog interval requested by the unit's `WatchdogSec=`, if any.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        if !self.enabled {
+            return None;
+        }
+
+        #[cfg(unix)]
+        {
+            let mut usec = 0;
+            if sd_notify::watchdog_enabled(false, &mut usec) {
+                return Some(Duration::from_micros(usec));
+            }
+        }
+
+        None
+    }
+
+    /// Ping the watchdog at half the requested interval until shutdown.
+    pub fn spawn_watchdog(&self, token: ShutdownToken) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = self.watchdog_interval()? / 2;
+        let notifier = *self;
+
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {
+                        if let Err(e) = notifier.watchdog() {
+                            log::warn!("Failed to ping systemd watchdog: {}", e);
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    fn send(&self, notification: Notification) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        #[cfg(unix)]
+        {
+            use sd_notify::NotifyState;
+
+            let state = match notification {
+                Notification::Ready => NotifyState::Ready,
+                Notification::Reloading => NotifyState::Reloading,
+                Notification::Stopping => NotifyState::Stopping,
+                Notification::Watchdog => NotifyState::Watchdog,
+                Notification::Status(message) => NotifyState::Status(message),
+            };
+
+            sd_notify::notify(false, &[state])
+                .map_err(|e| SourceVideoError::server(format!("sd_notify failed: {}", e)))?;
+        }
+
+        #[cfg(not(unix))]
+        let _ = notification;
+
+        Ok(())
+    }
+}
+
+/// Generates a systemd unit for running `source-videos serve`.
+#[derive(Debug, Clone)]
+pub struct SystemdUnit {
+    pub description: String,
+    /// Program and arguments, quoted for `ExecStart=` when rendered
+    pub exec_start: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    pub user: Option<String>,
+    /// Value for `Restart=` (e.g. `on-failure`, `always`, `no`)
+    pub restart: String,
+    pub restart_sec: u64,
+    pub watchdog_sec: Option<u64>,
+    pub stop_timeout_sec: u64,
+    pub environment: Vec<(String, String)>,
+}
+
+impl SystemdUnit {
+    pub fn new<I, S>(command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            description: "source-videos RTSP test source server".to_string(),
+            exec_start: command.into_iter().map(Into::into).collect(),
+            working_directory: None,
+            user: None,
+            restart: "on-failure".to_string(),
+            restart_sec: 5,
+            watchdog_sec: None,
+            stop_timeout_sec: 15,
+            environment: Vec::new(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut unit = format!(
+            "[Unit]\n\
+             Description={}\n\
+             After=network-online.target\n\
+             Wants=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=notify\n\
+             NotifyAccess=main\n\
+             ExecStart={}\n\
+             KillSignal=SIGTERM\n\
+             TimeoutStopSec={}\n\
+             Restart={}\n\
+             RestartSec={}\n",
+            self.description,
+            self.exec_start
+                .iter()
+                .map(|arg| quote_arg(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            self.stop_timeout_sec,
+            self.restart,
+            self.restart_sec
+        );
+
+        if let Some(watchdog) = self.watchdog_sec {
+            unit.push_str(&format!("WatchdogSec={}\n", watchdog));
+        }
+
+        if let Some(dir) = &self.working_directory {
+            unit.push_str(&format!("WorkingDirectory={}\n", dir.display()));
+        }
+
+        if let Some(user) = &self.user {
+            unit.push_str(&format!("User={}\n", user));
+        }
+
+        for (key, value) in &self.environment {
+            unit.push_str(&format!("Environment=\"{}={}\"\n", key, value));
+        }
+
+        unit.push_str("\n[Install]\nWantedBy=multi-user.target\n");
+        unit
+    }
+}
+
+/// Quote one `ExecStart=` word so spaces, quotes, `%` specifiers and `$`
+/// variables are passed through literally
+fn quote_arg(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    let needs_quotes = escaped.is_empty()
+        || escaped
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | '\'' | ';'));
+    if !needs_quotes {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_render() {
+        let mut unit = SystemdUnit::new(["/usr/bin/video-source", "serve", "-d", "/srv/videos"]);
+        unit.watchdog_sec = Some(30);
+        unit.user = Some("video".to_string());
+        unit.environment
+            .push(("RUST_LOG".to_string(), "info".to_string()));
+
+        let text = unit.render();
+        assert!(text.contains("Type=notify"));
+        assert!(text.contains("ExecStart=/usr/bin/video-source serve -d /srv/videos"));
+        assert!(text.contains("Restart=on-failure"));
+        assert!(text.contains("WatchdogSec=30"));
+        assert!(text.contains("User=video"));
+        assert!(text.contains("Environment=\"RUST_LOG=info\""));
+        assert!(text.ends_with("WantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn test_exec_start_quotes_paths() {
+        let unit = SystemdUnit::new([
+            "/opt/video tools/video-source",
+            "serve",
+            "-d",
+            "/srv/my \"videos\"",
+            "--name=50%",
+        ]);
+
+        let text = unit.render();
+        assert!(text.contains(
+            "ExecStart=\"/opt/video tools/video-source\" serve -d \"/srv/my \\\"videos\\\"\" --name=50%%\n"
+        ));
+    }
+
+    #[test]
+    fn test_disabled_notifier_is_noop() {
+        let notifier = SystemdNotifier::default();
+        assert!(!notifier.is_enabled());
+        assert!(notifier.ready().is_ok());
+        assert!(notifier.watchdog_interval().is_none());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::error::{Result, SourceVideoError};
+use crate::shutdown::ShutdownCoordinator;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows_service::service::{
+    ServiceAccess, ServiceAction, ServiceActionType, ServiceControl, ServiceControlAccept,
+    ServiceErrorControl, ServiceExitCode, ServiceFailureActions, ServiceFailureResetPeriod,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+type ServiceEntry = Box<dyn FnOnce(Arc<ShutdownCoordinator>) -> Result<()> + Send>;
+
+static SERVICE_NAME: Mutex<String> = Mutex::new(String::new());
+static SERVICE_ENTRY: Mutex<Option<ServiceEntry>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Options for registering the service with the SCM.
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub executable: PathBuf,
+    /// Arguments passed to the executable, normally `service run -- <serve args>`
+    pub arguments: Vec<OsString>,
+    /// Delay before the SCM restarts the service after a failure
+    pub restart_delay: Duration,
+}
+
+fn service_error(e: windows_service::Error) -> SourceVideoError {
+    SourceVideoError::server(format!("Windows service error: {}", e))
+}
+
+/// Register the service with automatic start and restart-on-failure.
+pub fn install(options: &InstallOptions) -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(service_error)?;
+
+    let info = ServiceInfo {
+        name: OsString::from(&options.name),
+        display_name: OsString::from(&options.display_name),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: options.executable.clone(),
+        launch_arguments: options.arguments.clone(),
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(service_error)?;
+
+    service
+        .set_description(&options.description)
+        .map_err(service_error)?;
+
+    let restart = ServiceAction {
+        action_type: ServiceActionType::Restart,
+        delay: options.restart_delay,
+    };
+    service
+        .update_failure_actions(ServiceFailureActions {
+            reset_period: ServiceFailureResetPeriod::After(Duration::from_secs(86400)),
+            reboot_msg: None,
+            command: None,
+            actions: Some(vec![restart.clone(), restart.clone(), restart]),
+        })
+        .map_err(service_error)?;
+    service
+        .set_failure_actions_on_non_crash_failures(true)
+        .map_err(service_error)?;
+
+    log::info!("Installed Windows service '{}'", options.name);
+    Ok(())
+}
+
+/// Stop the service if running and remove it from the SCM.
+pub fn uninstall(name: &str) -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(service_error)?;
+
+    let service = manager
+        .open_service(
+            name,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(service_error)?;
+
+    let status = service.query_status().map_err(service_error)?;
+    if status.current_state != ServiceState::Stopped {
+        service.stop().map_err(service_error)?;
+    }
+
+    service.delete().map_err(service_error)?;
+    log::info!("Uninstalled Windows service '{}'", name);
+    Ok(())
+}
+
+/// Run under the SCM, blocking until the service stops.
+///
+/// `entry` receives a shutdown coordinator that is cancelled when the SCM
+/// sends Stop or Shutdown, and should return once the server has stopped.
+pub fn run<F>(name: &str, entry: F) -> Result<()>
+where
+    F: FnOnce(Arc<ShutdownCoordinator>) -> Result<()> + Send + 'static,
+{
+    *SERVICE_NAME.lock().unwrap() = name.to_string();
+    *SERVICE_ENTRY.lock().unwrap() = Some(Box::new(entry));
+
+    service_dispatcher::start(name, ffi_service_main).map_err(service_error)
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        log::error!("Windows service failed: {}", e);
+    }
+}
+
+fn run_service() -> Result<()> {
+    let name = SERVICE_NAME.lock().unwrap().clone();
+    let entry = SERVICE_ENTRY
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| SourceVideoError::server("Service entry point already consumed"))?;
+
+    let coordinator = Arc::new(ShutdownCoordinator::default());
+    let stop_coordinator = coordinator.clone();
+
+    let status_handle = service_control_handler::register(&name, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            stop_coordinator.cancel();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })
+    .map_err(service_error)?;
+
+    let status = |state: ServiceState, exit_code: u32, wait_hint: Duration| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: if state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        },
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    };
+
+    status_handle
+        .set_service_status(status(ServiceState::Running, 0, Duration::default()))
+        .map_err(service_error)?;
+
+    let result = entry(coordinator.clone());
+
+    status_handle
+        .set_service_status(status(ServiceState::StopPending, 0, coordinator.timeout()))
+        .map_err(service_error)?;
+
+    let exit_code = if result.is_ok() { 0 } else { 1 };
+    status_handle
+        .set_service_status(status(
+            ServiceState::Stopped,
+            exit_code,
+            Duration::default(),
+        ))
+        .map_err(service_error)?;
+
+    result
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            name: super::DEFAULT_SERVICE_NAME.to_string(),
+            display_name: "Source Videos RTSP Server".to_string(),
+            description: "Serves test video sources over RTSP".to_string(),
+            executable: std::env::current_exe().unwrap_or_default(),
+            arguments: Vec::new(),
+            restart_delay: Duration::from_secs(5),
+        }
+    }
+}
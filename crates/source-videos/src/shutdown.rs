@@ -0,0 +1,275 @@
+use crate::error::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+type StopFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+type StopFn = Box<dyn FnOnce() -> StopFuture + Send>;
+
+/// Cloneable handle that subsystems use to observe a shutdown request.
+#[derive(Clone)]
+pub struct ShutdownToken {
+    rx: watch::Receiver<bool>,
+}
+
+impl ShutdownToken {
+    pub fn is_cancelled(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn cancelled(&self) {
+        let mut rx = self.rx.clone();
+        // An error means the coordinator was dropped, which is also a shutdown.
+        let _ = rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Outcome of a coordinated shutdown.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShutdownReport {
+    pub stopped: Vec<String>,
+    pub failed: Vec<(String, String)>,
+    pub elapsed_ms: u64,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+struct Component {
+    name: String,
+    stop: StopFn,
+}
+
+/// Propagates a single cancellation to every registered subsystem and waits
+/// for each to stop, bounded by a timeout.
+///
+/// Components are stopped in reverse registration order so that consumers
+/// (API, watchers) go down before the servers they depend on.
+pub struct ShutdownCoordinator {
+    tx: watch::Sender<bool>,
+    timeout: Duration,
+    components: Mutex<Vec<Component>>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(timeout: Duration) -> Self {
+        let (tx, _rx) = watch::channel(false);
+        Self {
+            tx,
+            timeout,
+            components: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn token(&self) -> ShutdownToken {
+        ShutdownToken {
+            rx: self.tx.subscribe(),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Register a subsystem with the async action that stops it.
+    pub fn register<F, Fut>(&self, name: impl Into<String>, stop: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        if let Ok(mut components) = self.components.lock() {
+            components.push(Component {
+                name: name.into(),
+                stop: Box::new(move || Box::pin(stop())),
+            });
+        }
+    }
+
+    /// Register a spawned task that exits on its own once the token is cancelled.
+    pub fn register_task(&self, name: impl Into<String>, handle: tokio::task::JoinHandle<()>) {
+        let name = name.into();
+        let task_name = name.clone();
+        self.register(name, move || async move {
+            handle.await.map_err(|e| {
+                crate::error::SourceVideoError::resource(format!(
+                    "Task '{}' failed: {}",
+                    task_name, e
+                ))
+            })
+        });
+    }
+
+    /// Signal cancellation without waiting for components.
+    pub fn cancel(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Cancel the token, stop every component and report those that failed
+    /// or did not finish within the timeout.
+    pub async fn shutdown(&self) -> ShutdownReport {
+        let started = Instant::now();
+        self.cancel();
+
+        let components = self
+            .components
+            .lock()
+            .map(|mut components| std::mem::take(&mut *components))
+            .unwrap_or_default();
+
+        let mut report = ShutdownReport::default();
+
+        for component in components.into_iter().rev() {
+            let remaining = self.timeout.saturating_sub(started.elapsed());
+            log::info!("Stopping {}", component.name);
+
+            match tokio::time::timeout(remaining, (component.stop)()).await {
+                Ok(Ok(())) => report.stopped.push(component.name),
+                Ok(Err(e)) => {
+                    log::error!("Failed to stop {}: {}", component.name, e);
+                    report.failed.push((component.name, e.to_string()));
+                }
+                Err(_) => {
+                    log::error!("Timed out stopping {}", component.name);
+                    report
+                        .failed
+                        .push((component.name, "timed out".to_string()));
+                }
+            }
+        }
+
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        report
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+/// Resolves on Ctrl+C, SIGTERM or when the token is cancelled elsewhere.
+pub async fn wait_for_signal(token: &ShutdownToken) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        // Embedded in another runtime the handler may not be available;
+        // Ctrl+C and the token still work then
+        let mut sigterm = signal(SignalKind::terminate())
+            .inspect_err(|e| log::warn!("Cannot listen for SIGTERM: {}", e))
+            .ok();
+        let terminated = async {
+            match &mut sigterm {
+                Some(sigterm) => {
+                    sigterm.recv().await;
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => log::info!("Received Ctrl+C - initiating shutdown"),
+            _ = terminated => log::info!("Received SIGTERM - initiating shutdown"),
+            _ = token.cancelled() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => log::info!("Received Ctrl+C - initiating shutdown"),
+            _ = token.cancelled() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SourceVideoError;
+
+    #[tokio::test]
+    async fn test_token_cancellation() {
+        let coordinator = ShutdownCoordinator::default();
+        let token = coordinator.token();
+        assert!(!token.is_cancelled());
+
+        coordinator.cancel();
+        assert!(token.is_cancelled());
+        tokio::time::timeout(Duration::from_millis(100), token.cancelled())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_reports_failures() {
+        let coordinator = ShutdownCoordinator::default();
+        coordinator.register("ok", || async { Ok(()) });
+        coordinator.register("broken", || async {
+            Err(SourceVideoError::server("still bound"))
+        });
+
+        let report = coordinator.shutdown().await;
+
+        assert!(!report.is_clean());
+        assert_eq!(report.stopped, vec!["ok".to_string()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "broken");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_times_out_stuck_components() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_millis(200));
+        coordinator.register("stuck", || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        });
+
+        let report = coordinator.shutdown().await;
+
+        assert_eq!(
+            report.failed,
+            vec![("stuck".to_string(), "timed out".to_string())]
+        );
+        assert!(report.elapsed_ms < 5000);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_tasks() {
+        let coordinator = ShutdownCoordinator::default();
+        let token = coordinator.token();
+        let task = tokio::spawn(async move { token.cancelled().await });
+        coordinator.register_task("task", task);
+
+        let report = coordinator.shutdown().await;
+
+        assert!(report.is_clean());
+        assert_eq!(report.stopped, vec!["task".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_in_reverse_order() {
+        let coordinator = ShutdownCoordinator::default();
+        coordinator.register("server", || async { Ok(()) });
+        coordinator.register("api", || async { Ok(()) });
+
+        let report = coordinator.shutdown().await;
+
+        assert_eq!(
+            report.stopped,
+            vec!["api".to_string(), "server".to_string()]
+        );
+    }
+}
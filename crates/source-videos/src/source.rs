@@ -1,5 +1,6 @@
 use crate::config_types::{VideoSourceConfig, VideoSourceType};
 use crate::error::{Result, SourceVideoError};
+use crate::markers;
 use crate::pipeline::{self, PipelineFactory};
 use gstreamer as gst;
 use gstreamer::prelude::*;
@@ -27,6 +28,71 @@ impl std::fmt::Display for SourceState {
     }
 }
 
+/// How a running source takes a new configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceUpdate {
+    Unchanged,
+    /// New caps or pattern set on the running pipeline
+    Live,
+    /// Pipeline rebuilt in place, keeping the source ID and state
+    Restart,
+}
+
+impl SourceUpdate {
+    /// Resolution, framerate, format and the base pattern of a test source
+    /// can change on a running pipeline; anything else needs a rebuild
+    pub fn between(old: &VideoSourceConfig, new: &VideoSourceConfig) -> Self {
+        // Tags only group sources; the pipeline does not use them
+        let untagged = |config: &VideoSourceConfig| VideoSourceConfig {
+            tags: Vec::new(),
+            ..config.clone()
+        };
+        if untagged(old) == untagged(new) {
+            return Self::Unchanged;
+        }
+        let live_type = match (&old.source_type, &new.source_type) {
+            (
+                VideoSourceType::TestPattern { pattern: old },
+                VideoSourceType::TestPattern { pattern: new },
+            ) => match (markers::parse_pattern(old), markers::parse_pattern(new)) {
+                (Ok((_, old_marker)), Ok((_, new_marker))) => old_marker == new_marker,
+                _ => false,
+            },
+            _ => false,
+        };
+        let same_timing = old.name == new.name
+            && old.is_live == new.is_live
+            && old.num_buffers == new.num_buffers
+            && old.duration == new.duration;
+        if live_type && same_timing {
+            Self::Live
+        } else {
+            Self::Restart
+        }
+    }
+}
+
+/// Restart `pipeline` from zero on `clock` with `base_time`. Pipelines
+/// started with the same pair run in step: their running times, and with
+/// them the timestamps of live sources, are the same at any moment.
+pub fn play_synced(
+    pipeline: &gst::Pipeline,
+    clock: &gst::Clock,
+    base_time: gst::ClockTime,
+) -> Result<()> {
+    pipeline
+        .set_state(gst::State::Null)
+        .map_err(|_| SourceVideoError::StateChange("Failed to set null state".to_string()))?;
+    // Keep the given base time instead of one chosen on the way to playing
+    pipeline.use_clock(Some(clock));
+    pipeline.set_start_time(gst::ClockTime::NONE);
+    pipeline.set_base_time(base_time);
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| SourceVideoError::StateChange("Failed to set playing state".to_string()))?;
+    Ok(())
+}
+
 pub trait VideoSource: Send + Sync {
     fn get_id(&self) -> &str;
     fn get_name(&self) -> &str;
@@ -37,6 +103,31 @@ pub trait VideoSource: Send + Sync {
     fn pause(&mut self) -> Result<()>;
     fn resume(&mut self) -> Result<()>;
     fn get_pipeline(&self) -> Option<&gst::Pipeline>;
+
+    /// The pipeline to start in step with other sources through
+    /// [`play_synced`], created if needed. The state change is left to the
+    /// caller so it can happen without holding a lock on the source.
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        Err(SourceVideoError::config(format!(
+            "Source '{}' cannot start synchronized",
+            self.get_name()
+        )))
+    }
+
+    /// Record that the pipeline from [`Self::synced_pipeline`] is playing
+    fn mark_playing(&mut self) {}
+
+    fn get_config(&self) -> Option<&VideoSourceConfig> {
+        None
+    }
+
+    /// Apply a new configuration without replacing the source
+    fn reconfigure(&mut self, _config: VideoSourceConfig) -> Result<SourceUpdate> {
+        Err(SourceVideoError::config(format!(
+            "Source '{}' cannot be reconfigured in place",
+            self.get_name()
+        )))
+    }
 }
 
 pub struct BaseVideoSource {
@@ -69,6 +160,9 @@ impl BaseVideoSource {
         }
 
         let pipeline = self.factory.create_pipeline(&self.config)?;
+        if let Err(e) = crate::preview::install_tap(&pipeline) {
+            log::warn!("No preview for source '{}': {}", self.name, e);
+        }
         self.pipeline = Some(pipeline);
         Ok(())
     }
@@ -78,6 +172,49 @@ impl BaseVideoSource {
             *s = state;
         }
     }
+
+    fn apply_live(&self, pipeline: &gst::Pipeline) -> Result<()> {
+        if let VideoSourceType::TestPattern { pattern } = &self.config.source_type
+            && let Some(src) = pipeline.by_name("source")
+        {
+            let (pattern, _) = markers::parse_pattern(pattern)?;
+            src.set_property_from_str("pattern", &pattern.to_gst_pattern().to_string());
+        }
+        let filter = pipeline
+            .by_name("filter")
+            .ok_or_else(|| SourceVideoError::pipeline("No capsfilter to renegotiate"))?;
+        // New caps on the filter send a reconfigure event upstream
+        filter.set_property("caps", pipeline::video_caps(&self.config));
+        Ok(())
+    }
+
+    fn reconfigure(&mut self, config: VideoSourceConfig) -> Result<SourceUpdate> {
+        let update = SourceUpdate::between(&self.config, &config);
+        let previous = std::mem::replace(&mut self.config, config);
+        match (update, self.pipeline.clone()) {
+            (SourceUpdate::Unchanged, _) | (_, None) => {}
+            (SourceUpdate::Live, Some(pipeline)) => {
+                if let Err(e) = self.apply_live(&pipeline) {
+                    self.config = previous;
+                    return Err(e);
+                }
+            }
+            (SourceUpdate::Restart, Some(_)) => {
+                let state = self.get_state();
+                VideoSource::stop(self)?;
+                self.name = self.config.name.clone();
+                match state {
+                    SourceState::Playing => VideoSource::start(self)?,
+                    SourceState::Paused => {
+                        VideoSource::start(self)?;
+                        VideoSource::pause(self)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(update)
+    }
 }
 
 impl VideoSource for BaseVideoSource {
@@ -157,6 +294,25 @@ impl VideoSource for BaseVideoSource {
     fn get_pipeline(&self) -> Option<&gst::Pipeline> {
         self.pipeline.as_ref()
     }
+
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        self.create_pipeline()?;
+        self.pipeline
+            .clone()
+            .ok_or_else(|| SourceVideoError::pipeline("Pipeline not created"))
+    }
+
+    fn mark_playing(&mut self) {
+        self.set_state(SourceState::Playing);
+    }
+
+    fn get_config(&self) -> Option<&VideoSourceConfig> {
+        Some(&self.config)
+    }
+
+    fn reconfigure(&mut self, config: VideoSourceConfig) -> Result<SourceUpdate> {
+        BaseVideoSource::reconfigure(self, config)
+    }
 }
 
 pub struct TestPatternSource {
@@ -208,6 +364,22 @@ impl VideoSource for TestPatternSource {
     fn get_pipeline(&self) -> Option<&gst::Pipeline> {
         self.base.get_pipeline()
     }
+
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        self.base.synced_pipeline()
+    }
+
+    fn mark_playing(&mut self) {
+        self.base.mark_playing()
+    }
+
+    fn get_config(&self) -> Option<&VideoSourceConfig> {
+        self.base.get_config()
+    }
+
+    fn reconfigure(&mut self, config: VideoSourceConfig) -> Result<SourceUpdate> {
+        self.base.reconfigure(config)
+    }
 }
 
 pub struct FileSource {
@@ -259,6 +431,22 @@ impl VideoSource for FileSource {
     fn get_pipeline(&self) -> Option<&gst::Pipeline> {
         self.base.get_pipeline()
     }
+
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        self.base.synced_pipeline()
+    }
+
+    fn mark_playing(&mut self) {
+        self.base.mark_playing()
+    }
+
+    fn get_config(&self) -> Option<&VideoSourceConfig> {
+        self.base.get_config()
+    }
+
+    fn reconfigure(&mut self, config: VideoSourceConfig) -> Result<SourceUpdate> {
+        self.base.reconfigure(config)
+    }
 }
 
 pub struct RtspSource {
@@ -310,6 +498,22 @@ impl VideoSource for RtspSource {
     fn get_pipeline(&self) -> Option<&gst::Pipeline> {
         self.base.get_pipeline()
     }
+
+    fn synced_pipeline(&mut self) -> Result<gst::Pipeline> {
+        self.base.synced_pipeline()
+    }
+
+    fn mark_playing(&mut self) {
+        self.base.mark_playing()
+    }
+
+    fn get_config(&self) -> Option<&VideoSourceConfig> {
+        self.base.get_config()
+    }
+
+    fn reconfigure(&mut self, config: VideoSourceConfig) -> Result<SourceUpdate> {
+        self.base.reconfigure(config)
+    }
 }
 
 /// A source that always returns errors, used for unexpanded directory/file list sources
@@ -433,4 +637,22 @@ mod tests {
         source.stop().unwrap();
         assert_eq!(source.get_state(), SourceState::Stopped);
     }
+
+    #[test]
+    fn test_source_update_kind() {
+        let old = VideoSourceConfig::test_pattern("cam", "smpte");
+        assert_eq!(SourceUpdate::between(&old, &old), SourceUpdate::Unchanged);
+
+        let mut new = VideoSourceConfig::test_pattern("cam", "ball");
+        new.resolution.width = 640;
+        new.framerate.numerator = 15;
+        assert_eq!(SourceUpdate::between(&old, &new), SourceUpdate::Live);
+
+        let marked = VideoSourceConfig::test_pattern("cam", "ball+qr");
+        assert_eq!(SourceUpdate::between(&old, &marked), SourceUpdate::Restart);
+
+        let mut finite = old.clone();
+        finite.num_buffers = Some(100);
+        assert_eq!(SourceUpdate::between(&old, &finite), SourceUpdate::Restart);
+    }
 }
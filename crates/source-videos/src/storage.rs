@@ -0,0 +1,490 @@
+//! Disk space management for generated files
+//!
+//! A [`StorageManager`] owns an output directory. Before each file is
+//! generated it checks the directory's quota and the free space on its
+//! disk, and either refuses to generate or deletes the oldest files it
+//! generated itself, depending on the [`CleanupPolicy`]. Files it generated
+//! are listed in a manifest in the directory, so other files there are
+//! never deleted. What it does is broadcast as [`StorageEvent`]s.
+
+use crate::error::{Result, SourceVideoError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use sysinfo::Disks;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupPolicy {
+    /// Delete the oldest generated files until the new one fits
+    DeleteOldest,
+    /// Keep existing files and fail generation
+    #[default]
+    StopGeneration,
+}
+
+/// Names of the files a [`StorageManager`] generated, one per line
+const MANIFEST: &str = ".source-videos-generated";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Upper bound for the total size of video files in the directory
+    pub quota_bytes: Option<u64>,
+
+    /// Free space to leave on the disk
+    pub min_free_bytes: u64,
+
+    pub policy: CleanupPolicy,
+
+    /// Extensions of the files that count towards the quota; only the
+    /// ones generated here may be deleted
+    pub extensions: Vec<String>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            quota_bytes: None,
+            min_free_bytes: 512 * 1024 * 1024,
+            policy: CleanupPolicy::default(),
+            extensions: ["mp4", "mkv", "webm", "avi"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StorageEvent {
+    LowSpace {
+        directory: PathBuf,
+        available_bytes: u64,
+        required_bytes: u64,
+    },
+    QuotaExceeded {
+        directory: PathBuf,
+        used_bytes: u64,
+        quota_bytes: u64,
+    },
+    FilesDeleted {
+        paths: Vec<PathBuf>,
+        freed_bytes: u64,
+    },
+    GenerationStopped {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageStatus {
+    pub directory: PathBuf,
+    pub used_bytes: u64,
+    pub file_count: usize,
+    /// Free space on the disk, when it could be determined
+    pub available_bytes: Option<u64>,
+    pub quota_bytes: Option<u64>,
+}
+
+struct ManagedFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Free space on the disk holding `path`
+pub fn available_space(path: &Path) -> Option<u64> {
+    let path = path.canonicalize().ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+pub struct StorageManager {
+    directory: PathBuf,
+    config: StorageConfig,
+    events: broadcast::Sender<StorageEvent>,
+    /// Serializes reads and writes of the manifest
+    manifest: Mutex<()>,
+}
+
+impl StorageManager {
+    pub fn new(directory: impl Into<PathBuf>, config: StorageConfig) -> Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        let (events, _) = broadcast::channel(64);
+        Ok(Self {
+            directory,
+            config,
+            events,
+            manifest: Mutex::new(()),
+        })
+    }
+
+    /// Manager for the directory a file at `path` is written to
+    pub fn for_output(path: &Path, config: StorageConfig) -> Result<Self> {
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        Self::new(directory, config)
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: StorageEvent) {
+        log::debug!("Storage event: {:?}", event);
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    fn is_managed(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                self.config
+                    .extensions
+                    .iter()
+                    .any(|managed| managed.eq_ignore_ascii_case(ext))
+            })
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.directory.join(MANIFEST)
+    }
+
+    /// Files listed in the manifest
+    fn generated(&self) -> HashSet<PathBuf> {
+        std::fs::read_to_string(self.manifest_path())
+            .map(|names| {
+                names
+                    .lines()
+                    .filter(|name| !name.is_empty())
+                    .map(|name| self.directory.join(name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Check for room like [`ensure_space`](Self::ensure_space), then list
+    /// `path` as generated here so the cleanup policy may delete it later
+    pub fn reserve(&self, path: &Path, needed_bytes: u64) -> Result<()> {
+        self.ensure_space(needed_bytes)?;
+        self.record(path)
+    }
+
+    /// List `path` as generated here; files outside the directory are not
+    /// managed
+    pub fn record(&self, path: &Path) -> Result<()> {
+        let parent = match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let Some(name) = path.file_name() else {
+            return Ok(());
+        };
+        if !same_directory(parent, &self.directory) {
+            return Ok(());
+        }
+        let _guard = self.manifest.lock().unwrap_or_else(|e| e.into_inner());
+        if self.generated().contains(&self.directory.join(name)) {
+            return Ok(());
+        }
+        let mut manifest = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.manifest_path())?;
+        writeln!(manifest, "{}", name.to_string_lossy())?;
+        Ok(())
+    }
+
+    /// Drop deleted files from the manifest
+    fn forget(&self, deleted: &[PathBuf]) -> Result<()> {
+        let _guard = self.manifest.lock().unwrap_or_else(|e| e.into_inner());
+        let mut names: Vec<String> = self
+            .generated()
+            .into_iter()
+            .filter(|path| !deleted.contains(path))
+            .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        let mut contents = names.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        std::fs::write(self.manifest_path(), contents)?;
+        Ok(())
+    }
+
+    /// Managed files, oldest first
+    fn managed_files(&self) -> Result<Vec<ManagedFile>> {
+        let mut files: Vec<ManagedFile> = std::fs::read_dir(&self.directory)?
+            .flatten()
+            .filter(|entry| self.is_managed(&entry.path()))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                metadata.is_file().then(|| ManagedFile {
+                    path: entry.path(),
+                    size: metadata.len(),
+                    modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                })
+            })
+            .collect();
+        files.sort_by_key(|file| file.modified);
+        Ok(files)
+    }
+
+    pub fn status(&self) -> Result<StorageStatus> {
+        let files = self.managed_files()?;
+        Ok(StorageStatus {
+            directory: self.directory.clone(),
+            used_bytes: files.iter().map(|file| file.size).sum(),
+            file_count: files.len(),
+            available_bytes: available_space(&self.directory),
+            quota_bytes: self.config.quota_bytes,
+        })
+    }
+
+    /// Make room for a file of about `needed_bytes`, cleaning up according
+    /// to the policy. Fails, after emitting
+    /// [`StorageEvent::GenerationStopped`], when there is not enough room.
+    pub fn ensure_space(&self, needed_bytes: u64) -> Result<()> {
+        self.ensure_space_with(needed_bytes, available_space(&self.directory))
+    }
+
+    fn ensure_space_with(&self, needed_bytes: u64, available: Option<u64>) -> Result<()> {
+        let managed = self.managed_files()?;
+        let mut used: u64 = managed.iter().map(|file| file.size).sum();
+        // Everything counts towards the quota, but only generated files
+        // are deleted
+        let generated = self.generated();
+        let mut files = managed
+            .into_iter()
+            .filter(|file| generated.contains(&file.path));
+        let mut available = available;
+
+        let over_quota = |used: u64| {
+            self.config
+                .quota_bytes
+                .is_some_and(|quota| used.saturating_add(needed_bytes) > quota)
+        };
+        let low_space = |available: Option<u64>| {
+            available.is_some_and(|available| {
+                available < needed_bytes.saturating_add(self.config.min_free_bytes)
+            })
+        };
+
+        if over_quota(used) {
+            self.emit(StorageEvent::QuotaExceeded {
+                directory: self.directory.clone(),
+                used_bytes: used,
+                quota_bytes: self.config.quota_bytes.unwrap_or_default(),
+            });
+        }
+        if let Some(available_bytes) = available
+            && low_space(available)
+        {
+            self.emit(StorageEvent::LowSpace {
+                directory: self.directory.clone(),
+                available_bytes,
+                required_bytes: needed_bytes.saturating_add(self.config.min_free_bytes),
+            });
+        }
+
+        if self.config.policy == CleanupPolicy::DeleteOldest {
+            let mut deleted = Vec::new();
+            let mut freed = 0;
+            while over_quota(used) || low_space(available) {
+                let Some(file) = files.next() else {
+                    break;
+                };
+                match std::fs::remove_file(&file.path) {
+                    Ok(()) => {
+                        log::info!("Deleted {} to free disk space", file.path.display());
+                        used = used.saturating_sub(file.size);
+                        available = available.map(|available| available + file.size);
+                        freed += file.size;
+                        deleted.push(file.path);
+                    }
+                    Err(e) => log::warn!("Cannot delete {}: {}", file.path.display(), e),
+                }
+            }
+            if !deleted.is_empty() {
+                if let Err(e) = self.forget(&deleted) {
+                    log::warn!("Cannot update {}: {}", self.manifest_path().display(), e);
+                }
+                self.emit(StorageEvent::FilesDeleted {
+                    paths: deleted,
+                    freed_bytes: freed,
+                });
+            }
+        }
+
+        if over_quota(used) || low_space(available) {
+            let reason = format!(
+                "Not enough space in {} for {} bytes (used {}, quota {:?}, available {:?})",
+                self.directory.display(),
+                needed_bytes,
+                used,
+                self.config.quota_bytes,
+                available
+            );
+            self.emit(StorageEvent::GenerationStopped {
+                reason: reason.clone(),
+            });
+            return Err(SourceVideoError::resource(reason));
+        }
+        Ok(())
+    }
+}
+
+fn same_directory(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_file(dir: &Path, name: &str, size: usize, age_secs: u64) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0u8; size]).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_quota_deletes_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let foreign = write_file(dir.path(), "theirs.mp4", 400, 400);
+        let oldest = write_file(dir.path(), "a.mp4", 400, 300);
+        let middle = write_file(dir.path(), "b.mp4", 400, 200);
+        let newest = write_file(dir.path(), "c.mp4", 400, 100);
+        let other = write_file(dir.path(), "notes.txt", 4000, 400);
+
+        let manager = StorageManager::new(
+            dir.path(),
+            StorageConfig {
+                quota_bytes: Some(1400),
+                policy: CleanupPolicy::DeleteOldest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for path in [&oldest, &middle, &newest] {
+            manager.record(path).unwrap();
+        }
+        let mut events = manager.subscribe();
+
+        manager.ensure_space_with(200, None).unwrap();
+        assert!(!oldest.exists());
+        assert!(foreign.exists() && middle.exists() && newest.exists() && other.exists());
+        assert!(!manager.generated().contains(&oldest));
+
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            StorageEvent::QuotaExceeded {
+                used_bytes: 1600,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            StorageEvent::FilesDeleted {
+                freed_bytes: 400,
+                ..
+            }
+        ));
+        assert_eq!(manager.status().unwrap().used_bytes, 1200);
+    }
+
+    #[test]
+    fn test_only_generated_files_are_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let foreign = write_file(dir.path(), "theirs.mp4", 400, 300);
+
+        let manager = StorageManager::new(
+            dir.path(),
+            StorageConfig {
+                quota_bytes: Some(500),
+                policy: CleanupPolicy::DeleteOldest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(manager.ensure_space_with(200, None).is_err());
+        assert!(foreign.exists());
+    }
+
+    #[test]
+    fn test_default_policy_keeps_files() {
+        assert_eq!(
+            StorageConfig::default().policy,
+            CleanupPolicy::StopGeneration
+        );
+    }
+
+    #[test]
+    fn test_stop_generation_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = write_file(dir.path(), "a.mp4", 100, 10);
+
+        let manager = StorageManager::new(
+            dir.path(),
+            StorageConfig {
+                min_free_bytes: 1000,
+                policy: CleanupPolicy::StopGeneration,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut events = manager.subscribe();
+
+        assert!(manager.ensure_space_with(100, Some(500)).is_err());
+        assert!(existing.exists());
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            StorageEvent::LowSpace {
+                available_bytes: 500,
+                required_bytes: 1100,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            StorageEvent::GenerationStopped { .. }
+        ));
+
+        assert!(manager.ensure_space_with(100, Some(5000)).is_ok());
+    }
+}
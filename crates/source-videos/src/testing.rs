@@ -0,0 +1,278 @@
+//! Ephemeral RTSP streams for integration tests.
+//!
+//! ```no_run
+//! use source_videos::network::NetworkProfile;
+//! use source_videos::testing::TestRtspStream;
+//!
+//! let stream = TestRtspStream::smpte()
+//!     .with_profile(NetworkProfile::Poor)
+//!     .start()
+//!     .unwrap();
+//!
+//! // Feed stream.url() to the pipeline under test; the server is torn
+//! // down when `stream` is dropped.
+//! println!("{}", stream.url());
+//! ```
+
+use crate::config_types::{Framerate, Resolution, VideoSourceConfig};
+use crate::error::{Result, SourceVideoError};
+use crate::network::NetworkProfile;
+use crate::ports::free_port;
+use crate::rtsp::{RtspServer, RtspServerBuilder};
+use gstreamer::glib;
+use std::thread::JoinHandle;
+
+const PORT_ATTEMPTS: usize = 5;
+
+/// Builder for a single-mount RTSP stream served on a private thread.
+#[derive(Debug, Clone)]
+pub struct TestRtspStream {
+    name: String,
+    pattern: String,
+    address: String,
+    port: Option<u16>,
+    profile: Option<NetworkProfile>,
+    resolution: Option<Resolution>,
+    framerate: Option<i32>,
+}
+
+impl TestRtspStream {
+    pub fn pattern(pattern: impl Into<String>) -> Self {
+        Self {
+            name: "test".to_string(),
+            pattern: pattern.into(),
+            address: "127.0.0.1".to_string(),
+            port: None,
+            profile: None,
+            resolution: None,
+            framerate: None,
+        }
+    }
+
+    pub fn smpte() -> Self {
+        Self::pattern("smpte")
+    }
+
+    pub fn ball() -> Self {
+        Self::pattern("ball")
+    }
+
+    pub fn snow() -> Self {
+        Self::pattern("snow")
+    }
+
+    /// Mount name; the stream is served at `/<name>`
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_profile(mut self, profile: NetworkProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Use a fixed port instead of picking a free one
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
+        self.resolution = Some(Resolution { width, height });
+        self
+    }
+
+    pub fn with_framerate(mut self, fps: i32) -> Self {
+        self.framerate = Some(fps);
+        self
+    }
+
+    /// Start serving and return a guard that stops the server on drop.
+    ///
+    /// Without an explicit port a free one is chosen; if another process
+    /// grabs it before the server binds, a new port is tried.
+    pub fn start(self) -> Result<RtspStreamGuard> {
+        crate::init()?;
+
+        let attempts = if self.port.is_some() {
+            1
+        } else {
+            PORT_ATTEMPTS
+        };
+        let mut last_error = None;
+
+        for _ in 0..attempts {
+            let port = match self.port {
+                Some(port) => port,
+                None => free_port(&self.address)?,
+            };
+
+            match self.start_on(port) {
+                Ok(guard) => return Ok(guard),
+                Err(e) => {
+                    log::debug!("Test RTSP stream failed to start on port {}: {}", port, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| SourceVideoError::server("Failed to start test RTSP stream")))
+    }
+
+    fn source_config(&self) -> VideoSourceConfig {
+        let mut config = VideoSourceConfig::test_pattern(&self.name, &self.pattern);
+        if let Some(resolution) = &self.resolution {
+            config.resolution = resolution.clone();
+        }
+        if let Some(fps) = self.framerate {
+            config.framerate = Framerate {
+                numerator: fps,
+                denominator: 1,
+            };
+        }
+        config
+    }
+
+    fn start_on(&self, port: u16) -> Result<RtspStreamGuard> {
+        let mut builder = RtspServerBuilder::new()
+            .port(port)
+            .address(self.address.clone())
+            .add_source(self.source_config());
+
+        if let Some(profile) = self.profile {
+            builder = builder.network_profile(profile);
+        }
+
+        let server = builder.build()?;
+        let context = glib::MainContext::new();
+        server.start_with_context(Some(&context))?;
+
+        let main_loop = glib::MainLoop::new(Some(&context), false);
+        let loop_clone = main_loop.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("test-rtsp-{}", port))
+            .spawn(move || {
+                let _ = context.with_thread_default(|| loop_clone.run());
+            })?;
+
+        let mount_point = format!("/{}", self.name);
+
+        Ok(RtspStreamGuard {
+            url: server.get_url(&mount_point),
+            port,
+            mount_point,
+            server,
+            main_loop,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// A running test stream. Dropping it stops the server and its thread.
+pub struct RtspStreamGuard {
+    url: String,
+    port: u16,
+    mount_point: String,
+    server: RtspServer,
+    main_loop: glib::MainLoop,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RtspStreamGuard {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn mount_point(&self) -> &str {
+        &self.mount_point
+    }
+
+    /// Access the server, e.g. to add further mounts.
+    pub fn server_mut(&mut self) -> &mut RtspServer {
+        &mut self.server
+    }
+}
+
+impl Drop for RtspStreamGuard {
+    fn drop(&mut self) {
+        let _ = self.server.stop();
+        self.main_loop.quit();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run `body` with the URL of a freshly started stream.
+pub fn with_rtsp_stream<T>(stream: TestRtspStream, body: impl FnOnce(&str) -> T) -> Result<T> {
+    let guard = stream.start()?;
+    Ok(body(guard.url()))
+}
+
+/// Declare a `#[test]` that receives the URL of an ephemeral RTSP stream.
+///
+/// ```ignore
+/// rtsp_test!(fn plays_smpte(url) {
+///     assert!(url.starts_with("rtsp://"));
+/// });
+///
+/// rtsp_test!(fn survives_poor_network(url) with
+///     TestRtspStream::ball().with_profile(NetworkProfile::Poor) => {
+///     // ...
+/// });
+/// ```
+#[macro_export]
+macro_rules! rtsp_test {
+    ($(#[$meta:meta])* fn $name:ident($url:ident) with $stream:expr => $body:block) => {
+        $(#[$meta])*
+        #[test]
+        fn $name() {
+            let guard = $stream
+                .start()
+                .expect("Failed to start test RTSP stream");
+            let $url: &str = guard.url();
+            $body
+        }
+    };
+    ($(#[$meta:meta])* fn $name:ident($url:ident) $body:block) => {
+        $crate::rtsp_test!(
+            $(#[$meta])* fn $name($url) with $crate::testing::TestRtspStream::smpte() => $body
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_gets_free_port() {
+        let first = TestRtspStream::smpte().start().unwrap();
+        let second = TestRtspStream::ball().with_name("cam").start().unwrap();
+
+        assert_ne!(first.port(), 0);
+        assert_ne!(first.port(), second.port());
+        assert_eq!(
+            first.url(),
+            format!("rtsp://127.0.0.1:{}/test", first.port())
+        );
+        assert_eq!(second.mount_point(), "/cam");
+    }
+
+    crate::rtsp_test!(fn test_rtsp_test_macro(url) with
+        TestRtspStream::snow().with_profile(NetworkProfile::Poor).with_resolution(320, 240) => {
+        assert!(url.starts_with("rtsp://127.0.0.1:"));
+        assert!(url.ends_with("/test"));
+    });
+}
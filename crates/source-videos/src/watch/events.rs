@@ -6,11 +6,37 @@ use tokio::sync::broadcast;
 use tokio::time::sleep;
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct FileEventMetadata {
     pub path: PathBuf,
     pub size: Option<u64>,
     pub modified: Option<SystemTime>,
     pub watcher_id: String,
+    /// Reported for a file that existed when its watcher started, rather
+    /// than for a change
+    pub initial: bool,
+}
+
+impl FileEventMetadata {
+    /// Metadata for `path` as it is on disk now, without size or
+    /// modification time once it is gone
+    pub fn new(path: impl Into<PathBuf>, watcher_id: impl Into<String>) -> Self {
+        let path = path.into();
+        let metadata = std::fs::metadata(&path).ok();
+        Self {
+            size: metadata.as_ref().map(|m| m.len()),
+            modified: metadata.and_then(|m| m.modified().ok()),
+            path,
+            watcher_id: watcher_id.into(),
+            initial: false,
+        }
+    }
+
+    /// Mark the file as present when its watcher started
+    pub fn initial(mut self) -> Self {
+        self.initial = true;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +56,11 @@ pub enum FileSystemEvent {
     },
 }
 
+/// Every [`FileSystemEvent::event_type`]
+pub const EVENT_TYPES: [&str; 6] = [
+    "created", "modified", "deleted", "accessed", "renamed", "error",
+];
+
 impl FileSystemEvent {
     pub fn path(&self) -> &PathBuf {
         match self {
@@ -53,6 +84,12 @@ impl FileSystemEvent {
         }
     }
 
+    /// Whether this is a synthetic [`FileSystemEvent::Created`] for a file
+    /// present when the watcher started
+    pub fn is_initial(&self) -> bool {
+        matches!(self, FileSystemEvent::Created(meta) if meta.initial)
+    }
+
     pub fn is_actionable(&self) -> bool {
         matches!(
             self,
@@ -120,21 +157,19 @@ impl EventRouter {
     }
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct EventFilter {
     include_patterns: Vec<String>,
     exclude_patterns: Vec<String>,
     include_extensions: Vec<String>,
     exclude_extensions: Vec<String>,
+    /// [`FileSystemEvent::event_type`] names to pass; all when empty
+    event_types: Vec<String>,
 }
 
 impl EventFilter {
     pub fn new() -> Self {
-        Self {
-            include_patterns: Vec::new(),
-            exclude_patterns: Vec::new(),
-            include_extensions: Vec::new(),
-            exclude_extensions: Vec::new(),
-        }
+        Self::default()
     }
 
     pub fn with_include_pattern(mut self, pattern: String) -> Self {
@@ -157,6 +192,25 @@ impl EventFilter {
         self
     }
 
+    /// Pass only events of this type, such as `"created"`; call again to
+    /// pass more types
+    pub fn with_event_type(mut self, event_type: String) -> Self {
+        self.event_types.push(event_type.to_lowercase());
+        self
+    }
+
+    /// Whether `event` has a passing type and path. Errors always pass.
+    pub fn matches(&self, event: &FileSystemEvent) -> bool {
+        if matches!(event, FileSystemEvent::Error { .. }) {
+            return true;
+        }
+        if !self.event_types.is_empty() && !self.event_types.iter().any(|t| t == event.event_type())
+        {
+            return false;
+        }
+        self.should_process(event.path())
+    }
+
     pub fn should_process(&self, path: &PathBuf) -> bool {
         let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
@@ -419,12 +473,7 @@ mod tests {
 
     #[test]
     fn test_file_system_event_methods() {
-        let metadata = FileEventMetadata {
-            path: PathBuf::from("/test/video.mp4"),
-            size: Some(1024),
-            modified: None,
-            watcher_id: "test-id".to_string(),
-        };
+        let metadata = FileEventMetadata::new("/test/video.mp4", "test-id");
 
         let event = FileSystemEvent::Created(metadata.clone());
 
@@ -458,16 +507,28 @@ mod tests {
         assert!(!filter.should_process(&PathBuf::from("video.tmp")));
     }
 
+    #[test]
+    fn test_event_filter_event_types() {
+        let filter = EventFilter::new()
+            .with_event_type("created".to_string())
+            .with_include_extension("mp4".to_string());
+        let metadata = |path: &str| FileEventMetadata::new(path, "test-id");
+
+        assert!(filter.matches(&FileSystemEvent::Created(metadata("new.mp4"))));
+        assert!(!filter.matches(&FileSystemEvent::Created(metadata("new.mkv"))));
+        assert!(!filter.matches(&FileSystemEvent::Deleted(metadata("old.mp4"))));
+        assert!(filter.matches(&FileSystemEvent::Error {
+            path: PathBuf::from("/videos"),
+            error: "gone".to_string(),
+            watcher_id: "test-id".to_string(),
+        }));
+    }
+
     #[test]
     fn test_event_batch() {
         let mut batch = EventBatch::new();
 
-        let metadata = FileEventMetadata {
-            path: PathBuf::from("/test/video.mp4"),
-            size: Some(1024),
-            modified: None,
-            watcher_id: "test-id".to_string(),
-        };
+        let metadata = FileEventMetadata::new("/test/video.mp4", "test-id");
 
         batch.add_event(FileSystemEvent::Created(metadata.clone()));
         batch.add_event(FileSystemEvent::Modified(metadata));
@@ -482,12 +543,7 @@ mod tests {
     fn test_event_stats() {
         let mut stats = EventStats::new();
 
-        let metadata = FileEventMetadata {
-            path: PathBuf::from("/test/video.mp4"),
-            size: Some(1024),
-            modified: None,
-            watcher_id: "test-id".to_string(),
-        };
+        let metadata = FileEventMetadata::new("/test/video.mp4", "test-id");
 
         stats.record_event(&FileSystemEvent::Created(metadata.clone()));
         stats.record_event(&FileSystemEvent::Modified(metadata.clone()));
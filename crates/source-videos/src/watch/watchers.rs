@@ -0,0 +1,1161 @@
+//! File and directory watchers backed by `notify`
+
+use super::events::{EventFilter, FileEventMetadata, FileSystemEvent};
+use crate::error::{Result, SourceVideoError};
+use crate::file_utils::is_video_file;
+use async_trait::async_trait;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{Notify, mpsc};
+use uuid::Uuid;
+
+/// A source of file system events. Object safe, so a [`WatcherManager`]
+/// can hold any implementation next to the built-in watchers.
+#[async_trait]
+pub trait FileWatcher: Send + Sync {
+    async fn start(&mut self) -> Result<()>;
+    async fn stop(&mut self) -> Result<()>;
+    async fn recv(&mut self) -> Option<FileSystemEvent>;
+    fn is_watching(&self) -> bool;
+    fn id(&self) -> &str;
+}
+
+pub struct DirectoryWatcher {
+    id: String,
+    path: PathBuf,
+    recursive: bool,
+    tx: mpsc::Sender<FileSystemEvent>,
+    rx: Option<mpsc::Receiver<FileSystemEvent>>,
+    watcher: Option<RecommendedWatcher>,
+    debounce_duration: Duration,
+    last_events: HashMap<PathBuf, SystemTime>,
+    initial_sync: bool,
+}
+
+impl DirectoryWatcher {
+    pub fn new<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (tx, rx) = mpsc::channel(1000);
+
+        if !path.exists() {
+            return Err(SourceVideoError::config(format!(
+                "Watch path does not exist: {}",
+                path.display()
+            )));
+        }
+
+        if !path.is_dir() {
+            return Err(SourceVideoError::config(format!(
+                "Watch path is not a directory: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            path,
+            recursive,
+            tx,
+            rx: Some(rx),
+            watcher: None,
+            debounce_duration: Duration::from_millis(500),
+            last_events: HashMap::new(),
+            initial_sync: false,
+        })
+    }
+
+    pub fn new_with_sender<P: AsRef<Path>>(
+        path: P,
+        recursive: bool,
+        tx: mpsc::Sender<FileSystemEvent>,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if !path.exists() {
+            return Err(SourceVideoError::config(format!(
+                "Watch path does not exist: {}",
+                path.display()
+            )));
+        }
+
+        if !path.is_dir() {
+            return Err(SourceVideoError::config(format!(
+                "Watch path is not a directory: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            path,
+            recursive,
+            tx,
+            rx: None,
+            watcher: None,
+            debounce_duration: Duration::from_millis(500),
+            last_events: HashMap::new(),
+            initial_sync: false,
+        })
+    }
+
+    pub fn with_debounce(mut self, duration: Duration) -> Self {
+        self.debounce_duration = duration;
+        self
+    }
+
+    /// On start, report a [`FileSystemEvent::Created`] marked `initial` for
+    /// every video file already in the directory, before any change. The
+    /// directory is watched before it is listed, so a file created in
+    /// between may be reported twice but is never missed.
+    pub fn with_initial_sync(mut self, enabled: bool) -> Self {
+        self.initial_sync = enabled;
+        self
+    }
+
+    /// Created events for the video files in the directory now
+    fn initial_events(&self) -> Vec<FileSystemEvent> {
+        let max_depth = if self.recursive { usize::MAX } else { 1 };
+        walkdir::WalkDir::new(&self.path)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file() && is_video_file(entry.path()))
+            .map(|entry| {
+                FileSystemEvent::Created(
+                    FileEventMetadata::new(entry.path(), self.id.as_str()).initial(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    fn should_process_event(&mut self, path: &Path, event_kind: &EventKind) -> bool {
+        let now = SystemTime::now();
+
+        // Check debouncing
+        if let Some(last_time) = self.last_events.get(path) {
+            if let Ok(duration) = now.duration_since(*last_time) {
+                if duration < self.debounce_duration {
+                    return false;
+                }
+            }
+        }
+
+        // Update last event time
+        self.last_events.insert(path.to_path_buf(), now);
+
+        // Only process video files for create/modify/delete
+        match event_kind {
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                is_video_file(path)
+            }
+            _ => false,
+        }
+    }
+
+    fn create_file_event(&self, path: PathBuf, kind: EventKind) -> Option<FileSystemEvent> {
+        let metadata = FileEventMetadata::new(path.clone(), self.id.as_str());
+
+        match kind {
+            EventKind::Create(_) => Some(FileSystemEvent::Created(metadata)),
+            EventKind::Modify(_) => Some(FileSystemEvent::Modified(metadata)),
+            EventKind::Remove(_) => Some(FileSystemEvent::Deleted(metadata)),
+            EventKind::Access(_) => Some(FileSystemEvent::Accessed(metadata)),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl FileWatcher for DirectoryWatcher {
+    async fn start(&mut self) -> Result<()> {
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
+        let path = self.path.clone();
+        let tx = self.tx.clone();
+        let recursive = self.recursive;
+        let watcher_id = self.id.clone();
+
+        // Create async watcher with channel
+        let (notify_tx, mut notify_rx) = mpsc::channel(1000);
+
+        let mut watcher = RecommendedWatcher::new(
+            move |res: std::result::Result<Event, notify::Error>| match res {
+                Ok(event) => {
+                    if let Err(e) = notify_tx.blocking_send(event) {
+                        log::error!("Failed to send notify event: {}", e);
+                    }
+                }
+                Err(e) => {
+                    log::error!("File watcher error: {}", e);
+                }
+            },
+            Config::default(),
+        )
+        .map_err(|e| {
+            SourceVideoError::config(format!("Failed to create directory watcher: {}", e))
+        })?;
+
+        let recursive_mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher
+            .watch(&path, recursive_mode)
+            .map_err(|e| SourceVideoError::config(format!("Failed to watch directory: {}", e)))?;
+
+        self.watcher = Some(watcher);
+
+        // Listed after the watch is set up so no file falls in between
+        let initial = if self.initial_sync {
+            self.initial_events()
+        } else {
+            Vec::new()
+        };
+        if !initial.is_empty() {
+            log::info!(
+                "Reporting {} existing video files in {}",
+                initial.len(),
+                path.display()
+            );
+        }
+
+        // Spawn async task to handle events
+        let tx_clone = tx.clone();
+        let path_clone = path.clone();
+        tokio::spawn(async move {
+            for event in initial {
+                if tx_clone.send(event).await.is_err() {
+                    return;
+                }
+            }
+
+            let mut last_events: HashMap<PathBuf, SystemTime> = HashMap::new();
+            let debounce = Duration::from_millis(500);
+
+            while let Some(event) = notify_rx.recv().await {
+                for event_path in event.paths {
+                    // Skip if not a video file
+                    if !is_video_file(&event_path) {
+                        continue;
+                    }
+
+                    // Debouncing check
+                    let now = SystemTime::now();
+                    if let Some(last_time) = last_events.get(&event_path) {
+                        if let Ok(duration) = now.duration_since(*last_time) {
+                            if duration < debounce {
+                                continue;
+                            }
+                        }
+                    }
+                    last_events.insert(event_path.clone(), now);
+
+                    // Create file event
+                    let metadata = FileEventMetadata::new(event_path.clone(), watcher_id.as_str());
+
+                    let fs_event = match event.kind {
+                        EventKind::Create(_) => {
+                            log::info!("Video file created: {}", event_path.display());
+                            FileSystemEvent::Created(metadata)
+                        }
+                        EventKind::Modify(_) => {
+                            log::info!("Video file modified: {}", event_path.display());
+                            FileSystemEvent::Modified(metadata)
+                        }
+                        EventKind::Remove(_) => {
+                            log::info!("Video file deleted: {}", event_path.display());
+                            FileSystemEvent::Deleted(metadata)
+                        }
+                        EventKind::Access(_) => FileSystemEvent::Accessed(metadata),
+                        _ => continue,
+                    };
+
+                    if let Err(e) = tx_clone.send(fs_event).await {
+                        log::error!("Failed to send file system event: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            log::info!("Directory watcher task ended for: {}", path_clone.display());
+        });
+
+        log::info!(
+            "Started watching directory: {} (recursive: {})",
+            self.path.display(),
+            self.recursive
+        );
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.watcher = None;
+        self.rx = None;
+        self.last_events.clear();
+
+        log::info!("Stopped watching directory: {}", self.path.display());
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Option<FileSystemEvent> {
+        if let Some(ref mut rx) = self.rx {
+            rx.recv().await
+        } else {
+            None
+        }
+    }
+
+    fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+pub struct FileWatcherInstance {
+    path: PathBuf,
+    tx: mpsc::Sender<FileSystemEvent>,
+    rx: Option<mpsc::Receiver<FileSystemEvent>>,
+    watcher: Option<RecommendedWatcher>,
+    id: String,
+}
+
+impl FileWatcherInstance {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (tx, rx) = mpsc::channel(100);
+
+        if !path.exists() {
+            return Err(SourceVideoError::config(format!(
+                "File does not exist: {}",
+                path.display()
+            )));
+        }
+
+        if !path.is_file() {
+            return Err(SourceVideoError::config(format!(
+                "Path is not a file: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            path,
+            tx,
+            rx: Some(rx),
+            watcher: None,
+            id: Uuid::new_v4().to_string(),
+        })
+    }
+
+    pub fn new_with_sender<P: AsRef<Path>>(
+        path: P,
+        tx: mpsc::Sender<FileSystemEvent>,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if !path.exists() {
+            return Err(SourceVideoError::config(format!(
+                "File does not exist: {}",
+                path.display()
+            )));
+        }
+
+        if !path.is_file() {
+            return Err(SourceVideoError::config(format!(
+                "Path is not a file: {}",
+                path.display()
+            )));
+        }
+
+        Ok(Self {
+            path,
+            tx,
+            rx: None,
+            watcher: None,
+            id: Uuid::new_v4().to_string(),
+        })
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl FileWatcher for FileWatcherInstance {
+    async fn start(&mut self) -> Result<()> {
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
+        let path = self.path.clone();
+        let tx = self.tx.clone();
+        let watcher_id = self.id.clone();
+
+        let (notify_tx, mut notify_rx) = mpsc::channel(100);
+
+        let mut watcher = RecommendedWatcher::new(
+            move |res: std::result::Result<Event, notify::Error>| {
+                if let Ok(event) = res {
+                    let _ = notify_tx.blocking_send(event);
+                }
+            },
+            Config::default(),
+        )
+        .map_err(|e| SourceVideoError::config(format!("Failed to create file watcher: {}", e)))?;
+
+        watcher
+            .watch(&path, RecursiveMode::NonRecursive)
+            .map_err(|e| SourceVideoError::config(format!("Failed to watch file: {}", e)))?;
+
+        self.watcher = Some(watcher);
+
+        tokio::spawn(async move {
+            while let Some(event) = notify_rx.recv().await {
+                for event_path in event.paths {
+                    if event_path != path {
+                        continue;
+                    }
+
+                    let metadata = FileEventMetadata::new(event_path.clone(), watcher_id.as_str());
+
+                    let fs_event = match event.kind {
+                        EventKind::Modify(_) => FileSystemEvent::Modified(metadata),
+                        EventKind::Remove(_) => FileSystemEvent::Deleted(metadata),
+                        EventKind::Access(_) => FileSystemEvent::Accessed(metadata),
+                        _ => continue,
+                    };
+
+                    if let Err(e) = tx.send(fs_event).await {
+                        log::error!("Failed to send file event: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        log::info!("Started watching file: {}", self.path.display());
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.watcher = None;
+        self.rx = None;
+        log::info!("Stopped watching file: {}", self.path.display());
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Option<FileSystemEvent> {
+        if let Some(ref mut rx) = self.rx {
+            rx.recv().await
+        } else {
+            None
+        }
+    }
+
+    fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// One of the built-in watchers
+#[deprecated(note = "hold watchers as `Box<dyn FileWatcher>`")]
+pub enum WatcherType {
+    Directory(DirectoryWatcher),
+    File(FileWatcherInstance),
+}
+
+#[allow(deprecated)]
+impl WatcherType {
+    fn inner(&mut self) -> &mut dyn FileWatcher {
+        match self {
+            WatcherType::Directory(w) => w,
+            WatcherType::File(w) => w,
+        }
+    }
+
+    pub fn get_id(&self) -> &str {
+        match self {
+            WatcherType::Directory(w) => w.get_id(),
+            WatcherType::File(w) => w.get_id(),
+        }
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl FileWatcher for WatcherType {
+    async fn start(&mut self) -> Result<()> {
+        self.inner().start().await
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.inner().stop().await
+    }
+
+    async fn recv(&mut self) -> Option<FileSystemEvent> {
+        self.inner().recv().await
+    }
+
+    fn is_watching(&self) -> bool {
+        match self {
+            WatcherType::Directory(w) => w.is_watching(),
+            WatcherType::File(w) => w.is_watching(),
+        }
+    }
+
+    fn id(&self) -> &str {
+        self.get_id()
+    }
+}
+
+/// Events a paused watcher holds at most; the oldest go first
+const MAX_BACKLOG: usize = 1000;
+
+/// Pause and filter state of one watcher
+#[derive(Default)]
+struct Gate {
+    paused: bool,
+    filter: Option<EventFilter>,
+    /// Events received while paused, delivered on resume
+    backlog: VecDeque<FileSystemEvent>,
+}
+
+impl Gate {
+    fn hold(&mut self, id: &str, event: FileSystemEvent) {
+        if self.backlog.len() >= MAX_BACKLOG {
+            self.backlog.pop_front();
+            log::warn!(
+                "Backlog of paused watcher {} is full, dropping its oldest event",
+                id
+            );
+        }
+        self.backlog.push_back(event);
+    }
+}
+
+#[derive(Default)]
+struct GateState {
+    gates: HashMap<String, Gate>,
+    /// Held events of resumed watchers, delivered before new ones
+    ready: VecDeque<FileSystemEvent>,
+    closed: bool,
+}
+
+/// Gates shared by a [`WatcherManager`] and its [`WatchEventReceiver`].
+/// Only held briefly, never across an await, so controlling the watchers
+/// does not wait for the next event.
+#[derive(Default)]
+struct Gates {
+    state: Mutex<GateState>,
+    /// Wakes the receiver when held events become ready or on close
+    wake: Notify,
+}
+
+fn gate_mut<'a>(state: &'a mut GateState, id: &str) -> Result<&'a mut Gate> {
+    state
+        .gates
+        .get_mut(id)
+        .ok_or_else(|| SourceVideoError::config(format!("No watcher with ID {}", id)))
+}
+
+impl Gates {
+    fn lock(&self) -> std::sync::MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The event if its watcher passes it on now. Filtered events are
+    /// dropped and a paused watcher's are held.
+    fn admit(&self, event: FileSystemEvent) -> Option<FileSystemEvent> {
+        let mut state = self.lock();
+        let id = event.watcher_id().to_string();
+        let Some(gate) = state.gates.get_mut(&id) else {
+            return Some(event);
+        };
+        if gate.filter.as_ref().is_some_and(|f| !f.matches(&event)) {
+            return None;
+        }
+        if gate.paused {
+            gate.hold(&id, event);
+            return None;
+        }
+        Some(event)
+    }
+}
+
+/// Receives the events of a [`WatcherManager`]'s watchers, paused ones
+/// held back and filtered ones dropped.
+///
+/// Taken from a shared manager with [`WatcherManager::take_receiver`] so
+/// events are awaited without holding the manager's lock.
+pub struct WatchEventReceiver {
+    rx: mpsc::Receiver<FileSystemEvent>,
+    gates: Arc<Gates>,
+    event_counts: Arc<WatchEventCounts>,
+}
+
+impl WatchEventReceiver {
+    /// The next event, or `None` once the manager is closed
+    pub async fn recv(&mut self) -> Option<FileSystemEvent> {
+        loop {
+            let ready = {
+                let mut state = self.gates.lock();
+                if state.closed {
+                    return None;
+                }
+                state.ready.pop_front()
+            };
+            let event = match ready {
+                Some(event) => event,
+                None => {
+                    let event = tokio::select! {
+                        event = self.rx.recv() => event?,
+                        _ = self.gates.wake.notified() => continue,
+                    };
+                    match self.gates.admit(event) {
+                        Some(event) => event,
+                        None => continue,
+                    }
+                }
+            };
+            self.event_counts.record(&event);
+            return Some(event);
+        }
+    }
+}
+
+/// State of one managed watcher
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatcherStatus {
+    pub id: String,
+    pub watching: bool,
+    pub paused: bool,
+    /// Whether an [`EventFilter`] is set
+    pub filtered: bool,
+    /// Events held while paused
+    pub backlog: usize,
+}
+
+pub struct WatcherManager {
+    watchers: HashMap<String, Box<dyn FileWatcher>>,
+    gates: Arc<Gates>,
+    tx: mpsc::Sender<FileSystemEvent>,
+    /// Until taken with [`Self::take_receiver`]
+    receiver: Option<WatchEventReceiver>,
+    event_counts: Arc<WatchEventCounts>,
+}
+
+/// Events delivered from all watchers, by event type. Shared so they can
+/// be read without locking the manager.
+#[derive(Debug, Default)]
+pub struct WatchEventCounts {
+    counts: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl WatchEventCounts {
+    fn record(&self, event: &FileSystemEvent) {
+        if let Ok(mut counts) = self.counts.lock() {
+            *counts.entry(event.event_type()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<&'static str, u64> {
+        self.counts
+            .lock()
+            .map(|counts| counts.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl WatcherManager {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel(1000);
+        let gates = Arc::new(Gates::default());
+        let event_counts = Arc::new(WatchEventCounts::default());
+
+        Self {
+            watchers: HashMap::new(),
+            receiver: Some(WatchEventReceiver {
+                rx,
+                gates: gates.clone(),
+                event_counts: event_counts.clone(),
+            }),
+            gates,
+            tx,
+            event_counts,
+        }
+    }
+
+    pub fn event_counts(&self) -> Arc<WatchEventCounts> {
+        self.event_counts.clone()
+    }
+
+    /// Channel the managed watchers send their events to; give it to a
+    /// custom watcher before adding it with [`Self::add_watcher`]
+    pub fn sender(&self) -> mpsc::Sender<FileSystemEvent> {
+        self.tx.clone()
+    }
+
+    /// Take the event receiver, to read events outside the manager's lock
+    /// when the manager is shared. [`Self::recv`] returns `None` after.
+    pub fn take_receiver(&mut self) -> Option<WatchEventReceiver> {
+        self.receiver.take()
+    }
+
+    pub async fn add_directory_watcher<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        recursive: bool,
+    ) -> Result<String> {
+        let watcher = DirectoryWatcher::new_with_sender(path, recursive, self.tx.clone())?;
+        let id = self.add_watcher(Box::new(watcher)).await?;
+        log::info!("Added directory watcher: {}", id);
+        Ok(id)
+    }
+
+    /// Like [`Self::add_directory_watcher`], first reporting the video files
+    /// already in the directory as initial Created events
+    pub async fn add_synced_directory_watcher<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        recursive: bool,
+    ) -> Result<String> {
+        let watcher = DirectoryWatcher::new_with_sender(path, recursive, self.tx.clone())?
+            .with_initial_sync(true);
+        let id = self.add_watcher(Box::new(watcher)).await?;
+        log::info!("Added synced directory watcher: {}", id);
+        Ok(id)
+    }
+
+    pub async fn add_file_watcher<P: AsRef<Path>>(&mut self, path: P) -> Result<String> {
+        let watcher = FileWatcherInstance::new_with_sender(path, self.tx.clone())?;
+        let id = self.add_watcher(Box::new(watcher)).await?;
+        log::info!("Added file watcher: {}", id);
+        Ok(id)
+    }
+
+    /// Start `watcher` and manage it under its ID, stopping and replacing
+    /// any watcher already managed under that ID
+    pub async fn add_watcher(&mut self, mut watcher: Box<dyn FileWatcher>) -> Result<String> {
+        let id = watcher.id().to_string();
+        if self.watchers.contains_key(&id) {
+            log::info!("Replacing watcher: {}", id);
+            self.remove_watcher(&id).await?;
+        }
+        watcher.start().await?;
+        self.gates.lock().gates.insert(id.clone(), Gate::default());
+        self.watchers.insert(id.clone(), watcher);
+        Ok(id)
+    }
+
+    pub async fn remove_watcher(&mut self, id: &str) -> Result<()> {
+        if let Some(mut watcher) = self.watchers.remove(id) {
+            self.gates.lock().gates.remove(id);
+            watcher.stop().await?;
+            log::info!("Removed watcher: {}", id);
+        }
+
+        Ok(())
+    }
+
+    pub async fn stop_all(&mut self) -> Result<()> {
+        for (id, mut watcher) in self.watchers.drain() {
+            if let Err(e) = watcher.stop().await {
+                log::error!("Error stopping watcher {}: {}", id, e);
+            }
+        }
+
+        {
+            let mut state = self.gates.lock();
+            state.gates.clear();
+            state.ready.clear();
+        }
+        log::info!("Stopped all watchers");
+        Ok(())
+    }
+
+    /// End event delivery; the receiver returns `None` from then on
+    pub fn close(&mut self) {
+        self.gates.lock().closed = true;
+        self.gates.wake.notify_one();
+    }
+
+    pub fn list_watchers(&self) -> Vec<&str> {
+        self.watchers.keys().map(|s| s.as_str()).collect()
+    }
+
+    pub fn is_watching(&self, id: &str) -> bool {
+        self.watchers
+            .get(id)
+            .map(|w| w.is_watching())
+            .unwrap_or(false)
+    }
+
+    /// Hold the watcher's events until it is resumed
+    pub fn pause_watcher(&mut self, id: &str) -> Result<()> {
+        gate_mut(&mut self.gates.lock(), id)?.paused = true;
+        log::info!("Paused watcher: {}", id);
+        Ok(())
+    }
+
+    /// Deliver the events held while paused, then new ones
+    pub fn resume_watcher(&mut self, id: &str) -> Result<()> {
+        let held = {
+            let mut state = self.gates.lock();
+            let gate = gate_mut(&mut state, id)?;
+            gate.paused = false;
+            let backlog = std::mem::take(&mut gate.backlog);
+            let held = backlog.len();
+            state.ready.extend(backlog);
+            held
+        };
+        self.gates.wake.notify_one();
+        log::info!("Resumed watcher: {} ({} held events)", id, held);
+        Ok(())
+    }
+
+    pub fn is_paused(&self, id: &str) -> bool {
+        self.gates.lock().gates.get(id).is_some_and(|g| g.paused)
+    }
+
+    /// Drop the watcher's events that do not match `filter`, including
+    /// those already held; `None` passes all events
+    pub fn set_filter(&mut self, id: &str, filter: Option<EventFilter>) -> Result<()> {
+        {
+            let mut state = self.gates.lock();
+            let gate = gate_mut(&mut state, id)?;
+            if let Some(filter) = &filter {
+                gate.backlog.retain(|event| filter.matches(event));
+            }
+            gate.filter = filter;
+        }
+        log::info!("Set event filter of watcher: {}", id);
+        Ok(())
+    }
+
+    /// Events the watcher holds while paused, as far as they have been
+    /// received; `None` for an unknown watcher
+    pub fn backlog_len(&self, id: &str) -> Option<usize> {
+        self.gates.lock().gates.get(id).map(|g| g.backlog.len())
+    }
+
+    /// Every watcher's state, by ID
+    pub fn statuses(&self) -> Vec<WatcherStatus> {
+        let state = self.gates.lock();
+        let mut statuses: Vec<_> = self
+            .watchers
+            .iter()
+            .map(|(id, watcher)| {
+                let gate = state.gates.get(id);
+                WatcherStatus {
+                    id: id.clone(),
+                    watching: watcher.is_watching(),
+                    paused: gate.is_some_and(|g| g.paused),
+                    filtered: gate.is_some_and(|g| g.filter.is_some()),
+                    backlog: gate.map_or(0, |g| g.backlog.len()),
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    /// The next event, while the manager still has its receiver. A shared
+    /// manager should hand out [`Self::take_receiver`] instead, since this
+    /// holds the manager borrowed until an event arrives.
+    pub async fn recv(&mut self) -> Option<FileSystemEvent> {
+        match self.receiver {
+            Some(ref mut receiver) => receiver.recv().await,
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+    use tokio::time::{sleep, timeout};
+
+    #[tokio::test]
+    async fn test_directory_watcher_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let watcher = DirectoryWatcher::new(temp_dir.path(), false);
+        assert!(watcher.is_ok());
+
+        let watcher = watcher.unwrap();
+        assert_eq!(watcher.get_path(), temp_dir.path());
+        assert!(!watcher.is_recursive());
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.mp4");
+        fs::write(&file_path, b"dummy content").unwrap();
+
+        let watcher = FileWatcherInstance::new(&file_path);
+        assert!(watcher.is_ok());
+
+        let watcher = watcher.unwrap();
+        assert_eq!(watcher.get_path(), &file_path);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_manager() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = WatcherManager::new();
+
+        let id = manager.add_directory_watcher(temp_dir.path(), false).await;
+        assert!(id.is_ok());
+
+        let id = id.unwrap();
+        assert!(manager.is_watching(&id));
+
+        let watchers = manager.list_watchers();
+        assert_eq!(watchers.len(), 1);
+        assert!(watchers.contains(&id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_initial_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("existing.mp4"), b"dummy content").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), b"not a video").unwrap();
+        let mut manager = WatcherManager::new();
+
+        manager
+            .add_synced_directory_watcher(temp_dir.path(), false)
+            .await
+            .unwrap();
+
+        let event = timeout(Duration::from_secs(1), manager.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(event.is_initial());
+        assert_eq!(event.path(), &temp_dir.path().join("existing.mp4"));
+
+        // Nothing else existed
+        assert!(
+            timeout(Duration::from_millis(100), manager.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    struct StubWatcher {
+        tx: mpsc::Sender<FileSystemEvent>,
+        watching: bool,
+        stops: Arc<AtomicUsize>,
+    }
+
+    impl StubWatcher {
+        fn new(tx: mpsc::Sender<FileSystemEvent>) -> Self {
+            Self {
+                tx,
+                watching: false,
+                stops: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl FileWatcher for StubWatcher {
+        async fn start(&mut self) -> Result<()> {
+            self.watching = true;
+            let metadata = FileEventMetadata::new("/videos/new.mp4", "stub");
+            let _ = self.tx.send(FileSystemEvent::Created(metadata)).await;
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<()> {
+            self.watching = false;
+            self.stops.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn recv(&mut self) -> Option<FileSystemEvent> {
+            None
+        }
+
+        fn is_watching(&self) -> bool {
+            self.watching
+        }
+
+        fn id(&self) -> &str {
+            "stub"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_watcher() {
+        let mut manager = WatcherManager::new();
+        let watcher = StubWatcher::new(manager.sender());
+
+        let id = manager.add_watcher(Box::new(watcher)).await.unwrap();
+        assert_eq!(id, "stub");
+        assert!(manager.is_watching("stub"));
+
+        let event = timeout(Duration::from_secs(1), manager.recv())
+            .await
+            .unwrap();
+        assert!(matches!(event, Some(FileSystemEvent::Created(_))));
+
+        manager.remove_watcher("stub").await.unwrap();
+        assert!(!manager.is_watching("stub"));
+    }
+
+    #[tokio::test]
+    async fn test_add_watcher_replaces_same_id() {
+        let mut manager = WatcherManager::new();
+        let first = StubWatcher::new(manager.sender());
+        let stops = first.stops.clone();
+        manager.add_watcher(Box::new(first)).await.unwrap();
+
+        manager
+            .add_watcher(Box::new(StubWatcher::new(manager.sender())))
+            .await
+            .unwrap();
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+        assert_eq!(manager.statuses().len(), 1);
+        assert!(manager.is_watching("stub"));
+    }
+
+    fn modified(path: &str) -> FileSystemEvent {
+        FileSystemEvent::Modified(FileEventMetadata::new(path, "stub"))
+    }
+
+    async fn stub_manager() -> WatcherManager {
+        let mut manager = WatcherManager::new();
+        let watcher = StubWatcher::new(manager.sender());
+        manager.add_watcher(Box::new(watcher)).await.unwrap();
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_paused_watcher_holds_events() {
+        let mut manager = stub_manager().await;
+        let tx = manager.sender();
+
+        manager.pause_watcher("stub").unwrap();
+        tx.send(modified("/videos/a.mp4")).await.unwrap();
+        tx.send(modified("/videos/b.mp4")).await.unwrap();
+        assert!(
+            timeout(Duration::from_millis(100), manager.recv())
+                .await
+                .is_err()
+        );
+        assert_eq!(manager.backlog_len("stub"), Some(3));
+        assert!(manager.statuses()[0].paused);
+    }
+
+    #[tokio::test]
+    async fn test_filter_applies_to_held_events() {
+        let mut manager = stub_manager().await;
+        let tx = manager.sender();
+
+        manager.pause_watcher("stub").unwrap();
+        tx.send(modified("/videos/a.mp4")).await.unwrap();
+        let _ = timeout(Duration::from_millis(100), manager.recv()).await;
+
+        // Only modified events pass from now on, held ones included
+        let filter = EventFilter::new().with_event_type("modified".to_string());
+        manager.set_filter("stub", Some(filter)).unwrap();
+        assert_eq!(manager.backlog_len("stub"), Some(1));
+        assert!(manager.statuses()[0].filtered);
+    }
+
+    #[tokio::test]
+    async fn test_resume_delivers_backlog_first() {
+        let mut manager = stub_manager().await;
+        let tx = manager.sender();
+
+        manager.pause_watcher("stub").unwrap();
+        tx.send(modified("/videos/a.mp4")).await.unwrap();
+        let _ = timeout(Duration::from_millis(100), manager.recv()).await;
+
+        manager.resume_watcher("stub").unwrap();
+        tx.send(modified("/videos/b.mp4")).await.unwrap();
+        let first = manager.recv().await.unwrap();
+        assert!(matches!(first, FileSystemEvent::Created(_)));
+        let second = manager.recv().await.unwrap();
+        assert_eq!(second.path(), &PathBuf::from("/videos/a.mp4"));
+        assert_eq!(manager.backlog_len("stub"), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_watcher_is_an_error() {
+        let mut manager = WatcherManager::new();
+        assert!(manager.pause_watcher("missing").is_err());
+        assert!(manager.resume_watcher("missing").is_err());
+        assert!(manager.set_filter("missing", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shared_manager_is_controlled_while_receiving() {
+        let manager = Arc::new(tokio::sync::RwLock::new(stub_manager().await));
+        let mut events = manager.write().await.take_receiver().unwrap();
+        let tx = manager.read().await.sender();
+
+        // Waiting for events does not lock the manager
+        let receiving = tokio::spawn(async move {
+            let mut paths = Vec::new();
+            while let Some(event) = events.recv().await {
+                paths.push(event.path().clone());
+            }
+            paths
+        });
+        sleep(Duration::from_millis(50)).await;
+        manager.write().await.pause_watcher("stub").unwrap();
+        tx.send(modified("/videos/a.mp4")).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(manager.read().await.backlog_len("stub"), Some(1));
+
+        manager.write().await.resume_watcher("stub").unwrap();
+        sleep(Duration::from_millis(50)).await;
+        manager.write().await.stop_all().await.unwrap();
+        manager.write().await.close();
+
+        let paths = timeout(Duration::from_secs(1), receiving)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(paths.last(), Some(&PathBuf::from("/videos/a.mp4")));
+        assert!(manager.write().await.recv().await.is_none());
+    }
+}
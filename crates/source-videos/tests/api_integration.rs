@@ -75,6 +75,45 @@ async fn test_network_profiles() {
     assert!(!json.is_empty());
 }
 
+#[tokio::test]
+async fn test_mount_conditions_need_rtsp_server() {
+    let server = setup_test_api().await;
+
+    let response = server
+        .put("/api/v1/network/conditions")
+        .json(&serde_json::json!({ "packet_loss": 5.0, "sources": ["test"] }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = server
+        .put("/api/v1/network/conditions")
+        .json(&serde_json::json!({ "packet_loss": 5.0 }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_dvr_needs_rtsp_server() {
+    let server = setup_test_api().await;
+
+    let response = server.get("/api/v1/dvr").await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = server.get("/api/v1/dvr/test/playlist.m3u8").await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_playback_needs_rtsp_server() {
+    let server = setup_test_api().await;
+
+    let response = server
+        .post("/api/v1/playback/step")
+        .json(&serde_json::json!({ "mount": "/clip", "frames": 5 }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
 #[tokio::test]
 async fn test_metrics_endpoint() {
     let server = setup_test_api().await;
@@ -86,3 +125,163 @@ async fn test_metrics_endpoint() {
     let json: serde_json::Value = response.json();
     assert!(json["source_count"].is_number());
 }
+
+#[tokio::test]
+async fn test_prometheus_metrics() {
+    let server = setup_test_api().await;
+
+    server.get("/api/v1/sources").await;
+    let response = server.get("/metrics").await;
+
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let text = response.text();
+    assert!(text.contains("# TYPE source_videos_sources gauge"));
+    assert!(text.contains("source_videos_network_simulation_active 0"));
+    assert!(text.contains(
+        "source_videos_api_request_duration_seconds_count{method=\"GET\",route=\"/api/v1/sources\",status=\"200\"} 1"
+    ));
+}
+
+#[tokio::test]
+async fn test_chaos_needs_rtsp_server() {
+    let server = setup_test_api().await;
+
+    let response = server.get("/api/v1/chaos").await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = server
+        .post("/api/v1/chaos/start")
+        .json(&serde_json::json!({ "seed": 7, "drop": 0.5 }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_readiness_reports_components() {
+    let server = setup_test_api().await;
+
+    let response = server.get("/api/v1/health/ready").await;
+
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["ready"], true);
+    assert_eq!(json["status"], "healthy");
+    assert!(json["components"]["rtsp_server"]["status"].is_string());
+    assert!(json["components"]["source_manager"]["details"].is_object());
+}
+
+#[tokio::test]
+async fn test_background_scan_operation() {
+    let server = setup_test_api().await;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("clip.mp4"), b"not really a video").unwrap();
+
+    let response = server
+        .post("/api/v1/operations/scan")
+        .json(&serde_json::json!({ "path": dir.path().to_string_lossy() }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let id = response.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let mut status = serde_json::Value::Null;
+    for _ in 0..100 {
+        status = server
+            .get(&format!("/api/v1/operations/{}", id))
+            .await
+            .json();
+        if status["status"] != "pending" && status["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(status["status"], "completed");
+    assert_eq!(status["result"]["found_count"], 1);
+
+    let response = server.delete(&format!("/api/v1/operations/{}", id)).await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    let response = server.get("/api/v1/operations/op-999").await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_source_preview() {
+    let server = setup_test_api().await;
+
+    let response = server.get("/api/v1/sources/missing/preview").await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+    server
+        .post("/api/v1/sources")
+        .json(&serde_json::json!({
+            "name": "preview_source",
+            "type": "test_pattern",
+            "pattern": "smpte"
+        }))
+        .await;
+
+    let response = server
+        .get("/api/v1/sources/preview_source/preview")
+        .add_query_param("width", 160)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("content-type"), "image/jpeg");
+    // JPEG start of image marker
+    assert_eq!(&response.as_bytes()[..2], &[0xff, 0xd8]);
+}
+
+#[tokio::test]
+async fn test_recent_events_by_topic() {
+    let server = setup_test_api().await;
+
+    server
+        .post("/api/v1/sources")
+        .json(&serde_json::json!({
+            "name": "evented",
+            "type": "test_pattern",
+            "pattern": "smpte"
+        }))
+        .await;
+    server.post("/api/v1/network/reset").await;
+
+    let response = server.get("/api/v1/events/recent?topics=sources").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let events: Vec<serde_json::Value> = response.json();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["topic"], "sources");
+    assert_eq!(events[0]["event"]["type"], "config");
+    assert_eq!(
+        events[0]["event"]["data"]["SourceAdded"]["source"],
+        "evented"
+    );
+
+    let response = server.get("/api/v1/events/recent").await;
+    let events: Vec<serde_json::Value> = response.json();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1]["topic"], "network");
+    assert_eq!(events[1]["event"]["type"], "network_reset");
+
+    let since = events[0]["id"].as_u64().unwrap();
+    let response = server
+        .get(&format!("/api/v1/events/recent?since={}", since))
+        .await;
+    let events: Vec<serde_json::Value> = response.json();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["topic"], "network");
+}
+
+#[tokio::test]
+async fn test_events_reject_unknown_topic() {
+    let server = setup_test_api().await;
+
+    let response = server
+        .get("/api/v1/events/recent?topics=sources,bogus")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    let response = server.get("/api/v1/events?topics=bogus").await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
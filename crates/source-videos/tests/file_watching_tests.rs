@@ -330,6 +330,7 @@ async fn test_auto_repeat_integration() {
         duration: Some(5),
         num_buffers: None,
         is_live: false,
+        tags: Vec::new(),
     };
 
     let file_source = FileVideoSource::from_config(&video_config).unwrap();
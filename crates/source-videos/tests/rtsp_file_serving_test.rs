@@ -40,6 +40,7 @@ fn test_rtsp_server_with_file_source() {
         duration: None,
         num_buffers: None,
         is_live: false,
+        tags: Vec::new(),
     };
 
     let server = RtspServerBuilder::new()
@@ -107,12 +108,7 @@ fn test_rtsp_server_file_watching_integration() {
     use source_videos::FileEventMetadata;
     use source_videos::FileSystemEvent;
 
-    let event = FileSystemEvent::Created(FileEventMetadata {
-        path: test_file.clone(),
-        size: Some(13),
-        modified: Some(std::time::SystemTime::now()),
-        watcher_id: "test".to_string(),
-    });
+    let event = FileSystemEvent::Created(FileEventMetadata::new(&test_file, "test"));
 
     // Handle the file creation event
     assert!(server.handle_file_event(&event).is_ok());
@@ -122,12 +118,7 @@ fn test_rtsp_server_file_watching_integration() {
     assert!(sources.iter().any(|s| s.contains("new_video")));
 
     // Test file deletion
-    let event = FileSystemEvent::Deleted(FileEventMetadata {
-        path: test_file.clone(),
-        size: Some(13),
-        modified: Some(std::time::SystemTime::now()),
-        watcher_id: "test".to_string(),
-    });
+    let event = FileSystemEvent::Deleted(FileEventMetadata::new(&test_file, "test"));
 
     assert!(server.handle_file_event(&event).is_ok());
 
@@ -166,6 +157,7 @@ fn test_rtsp_server_multiple_file_sources() {
             duration: None,
             num_buffers: None,
             is_live: false,
+            tags: Vec::new(),
         };
 
         configs.push(config);
@@ -213,6 +205,7 @@ fn test_rtsp_server_windows_path_handling() {
         duration: None,
         num_buffers: None,
         is_live: false,
+        tags: Vec::new(),
     };
 
     let server = RtspServerBuilder::new()
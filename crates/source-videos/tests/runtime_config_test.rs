@@ -19,6 +19,7 @@ async fn test_runtime_manager_basic() {
     assert!(!current.sources.is_empty());
 }
 
+#[cfg(feature = "watch")]
 #[tokio::test]
 async fn test_config_file_monitoring() {
     gstreamer::init().unwrap();